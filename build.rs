@@ -1,8 +1,110 @@
+use std::path::{Path, PathBuf};
+
+// Only the compatibility check is used here
+#[allow(dead_code)]
+#[path = "src/schema.rs"]
+mod schema;
+
+// The config types and the hub's own validator, to check the shipped configs with. Paths
+// inside `mod config` resolve from the config/ directory.
+#[allow(dead_code)]
+mod config {
+    #[path = "../src/config/bus_config.rs"]
+    pub mod bus_config;
+    #[path = "../src/config/diff.rs"]
+    pub mod diff;
+    #[path = "../src/config/sensor_config.rs"]
+    pub mod sensor_config;
+}
+#[allow(dead_code)]
+#[path = "src/kinds.rs"]
+mod kinds;
+mod sensors {
+    pub type DriverParams = std::collections::BTreeMap<String, toml::Value>;
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+
+    // The descriptor set is served by reflection and embedded in MCAP recordings
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
         .protoc_arg("--experimental_allow_proto3_optional")
+        .file_descriptor_set_path(out_dir.join("sensorhub_descriptor.bin"))
         .compile_protos(&["proto/sensorhub.proto"], &["proto"])?;
+
+    // Build provenance for GetBuildInfo; Docker builds have no .git and report "unknown"
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let git_hash = match git(&["rev-parse", "--short=12", "HEAD"]) {
+        Some(hash) if git(&["status", "--porcelain"]).is_some_and(|s| !s.is_empty()) => {
+            format!("{}-dirty", hash)
+        }
+        Some(hash) => hash,
+        None => "unknown".to_string(),
+    };
+    println!("cargo:rustc-env=SENSORHUB_GIT_HASH={}", git_hash);
+    println!(
+        "cargo:rustc-env=SENSORHUB_TARGET={}",
+        std::env::var("TARGET")?
+    );
+    println!(
+        "cargo:rustc-env=SENSORHUB_PROFILE={}",
+        std::env::var("PROFILE")?
+    );
+    for path in [".git/HEAD", ".git/refs/heads", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    // Refuse changes that would break clients built against a released schema, unless
+    // a breaking release is being cut on purpose
+    println!("cargo:rerun-if-changed=proto/released");
+    println!("cargo:rerun-if-changed=src/schema.rs");
+    println!("cargo:rerun-if-env-changed=SENSORHUB_ALLOW_BREAKING");
+    let current = schema::decode(&std::fs::read(out_dir.join("sensorhub_descriptor.bin"))?)?;
+    for (version, released) in schema::RELEASES {
+        let changes = schema::breaking_changes(&schema::decode(released)?, &current);
+        for change in changes.iter() {
+            println!("cargo:warning=Breaks schema {}: {}", version, change);
+        }
+        if !changes.is_empty() && std::env::var_os("SENSORHUB_ALLOW_BREAKING").is_none() {
+            return Err(format!(
+                "sensorhub.proto breaks compatibility with release {} \
+                 (set SENSORHUB_ALLOW_BREAKING=1 to allow)",
+                version
+            )
+            .into());
+        }
+    }
+
+    // The shipped configs are baked into the image as examples: fail here rather than
+    // at startup if an entry no longer matches the config types
+    println!("cargo:rerun-if-changed=config");
+    println!("cargo:rerun-if-changed=src/config");
+    check_configs(Path::new("config"))?;
+    Ok(())
+}
+
+/// Check `sensors.toml` and `buses.toml` in `dir` with the validator `ApplyConfig` uses
+fn check_configs(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let read = |path: PathBuf| {
+        std::fs::read_to_string(&path)
+            .map(|text| (text, path.display().to_string()))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    let (buses, path) = read(dir.join("buses.toml"))?;
+    let buses: config::bus_config::BusConfig =
+        toml::from_str(&buses).map_err(|e| format!("{}: {}", path, e))?;
+    let (sensors, path) = read(dir.join("sensors.toml"))?;
+    config::diff::validate(&sensors, &buses).map_err(|e| format!("{}: {}", path, e))?;
     Ok(())
 }
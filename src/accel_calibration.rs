@@ -0,0 +1,277 @@
+use crate::messages::SensorMessage;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::info;
+
+type Vector = [f64; 3];
+
+/// Standard gravity (m/s²), the magnitude every pose should measure
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+/// Fraction of the measured vector that must lie on one axis (within ~25°)
+const MIN_ALIGNMENT: f64 = 0.9;
+/// Accepted magnitude range, as a fraction of standard gravity
+const MAGNITUDE_RANGE: (f64, f64) = (0.8, 1.2);
+/// Per-axis standard deviation (m/s²) above which the vehicle was not held still
+const MAX_NOISE: f64 = 0.3;
+const MIN_SAMPLES: u64 = 10;
+
+/// Per-axis correction solved from the six poses, `(raw - bias) * scale`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccelFit {
+    pub bias: Vector,
+    pub scale: Vector,
+    /// RMS of the corrected poses' relative deviation from standard gravity
+    pub residual: f64,
+    /// Raw samples averaged into the poses
+    pub samples: u64,
+}
+
+/// One of the six poses: a sensor axis pointing straight down or up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pose {
+    axis: usize,
+    down: bool,
+}
+
+impl Pose {
+    const ALL: [Pose; 6] = [
+        Pose {
+            axis: 2,
+            down: true,
+        },
+        Pose {
+            axis: 2,
+            down: false,
+        },
+        Pose {
+            axis: 0,
+            down: true,
+        },
+        Pose {
+            axis: 0,
+            down: false,
+        },
+        Pose {
+            axis: 1,
+            down: true,
+        },
+        Pose {
+            axis: 1,
+            down: false,
+        },
+    ];
+
+    /// e.g. "z_down" (the sensor's z axis points down, as when level for most mountings)
+    pub fn name(&self) -> String {
+        format!(
+            "{}_{}",
+            ["x", "y", "z"][self.axis],
+            if self.down { "down" } else { "up" }
+        )
+    }
+}
+
+#[derive(Default)]
+struct Capture {
+    sum: Vector,
+    sum_sq: Vector,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Session {
+    /// Mean raw reading of each pose captured so far
+    poses: BTreeMap<Pose, Vector>,
+    /// Samples averaged into each pose
+    counts: BTreeMap<Pose, u64>,
+    capture: Option<Capture>,
+}
+
+/// Six-position accelerometer calibration sessions, one per IMU
+#[derive(Default)]
+pub struct AccelCalibrator {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl AccelCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start averaging raw samples of `sensor_id` for one pose
+    pub fn start_capture(&self, sensor_id: &str) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .entry(sensor_id.to_string())
+            .or_default()
+            .capture = Some(Capture::default());
+    }
+
+    /// Record an uncalibrated IMU sample if its sensor is capturing a pose
+    pub fn observe(&self, message: &SensorMessage) {
+        let SensorMessage::Imu(imu) = message else {
+            return;
+        };
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(capture) = sessions
+            .get_mut(&imu.h.sensor_id)
+            .and_then(|s| s.capture.as_mut())
+        else {
+            return;
+        };
+        let accel = match imu.precise {
+            Some(p) => [p[0], p[1], p[2]],
+            None => [imu.ax, imu.ay, imu.az].map(f64::from),
+        };
+        for (i, a) in accel.into_iter().enumerate() {
+            capture.sum[i] += a;
+            capture.sum_sq[i] += a * a;
+        }
+        capture.count += 1;
+    }
+
+    /// End the capture and record its pose; returns the pose and all poses captured so far
+    pub fn finish_capture(&self, sensor_id: &str) -> Result<(Pose, Vec<Pose>), String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(sensor_id)
+            .ok_or("no capture in progress")?;
+        let capture = session.capture.take().ok_or("no capture in progress")?;
+        let (pose, mean) = classify(&capture)?;
+        session.counts.insert(pose, capture.count);
+        if session.poses.insert(pose, mean).is_some() {
+            // Recapturing a pose replaces it, e.g. after a bump
+            info!("[accel_cal] {}: {} captured again", sensor_id, pose.name());
+        }
+        Ok((pose, session.poses.keys().copied().collect()))
+    }
+
+    /// Poses not captured yet
+    pub fn remaining(&self, sensor_id: &str) -> Vec<Pose> {
+        let sessions = self.sessions.lock().unwrap();
+        let captured = sessions.get(sensor_id).map(|s| &s.poses);
+        Pose::ALL
+            .into_iter()
+            .filter(|p| captured.is_none_or(|c| !c.contains_key(p)))
+            .collect()
+    }
+
+    /// Solve once all six poses are in, ending the session
+    pub fn take_solution(&self, sensor_id: &str) -> Option<AccelFit> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(sensor_id)?;
+        let (bias, scale) = solve(&session.poses)?;
+        let residual = (session
+            .poses
+            .values()
+            .map(|mean| {
+                let corrected = [0, 1, 2].map(|i| (mean[i] - bias[i]) * scale[i]);
+                let magnitude = corrected.iter().map(|v| v * v).sum::<f64>().sqrt();
+                (magnitude / STANDARD_GRAVITY - 1.0).powi(2)
+            })
+            .sum::<f64>()
+            / session.poses.len() as f64)
+            .sqrt();
+        let samples = session.counts.values().sum();
+        sessions.remove(sensor_id);
+        Some(AccelFit {
+            bias,
+            scale,
+            residual,
+            samples,
+        })
+    }
+
+    /// Discard a session's captures
+    pub fn cancel(&self, sensor_id: &str) {
+        self.sessions.lock().unwrap().remove(sensor_id);
+    }
+}
+
+/// Which pose a still capture shows, with its mean reading
+fn classify(capture: &Capture) -> Result<(Pose, Vector), String> {
+    if capture.count < MIN_SAMPLES {
+        return Err(format!("only {} samples received", capture.count));
+    }
+    let n = capture.count as f64;
+    let mean = capture.sum.map(|s| s / n);
+    let noisy =
+        (0..3).any(|i| (capture.sum_sq[i] / n - mean[i] * mean[i]).max(0.0).sqrt() > MAX_NOISE);
+    if noisy {
+        return Err("the vehicle moved during the capture - hold it still".to_string());
+    }
+    let magnitude = mean.iter().map(|v| v * v).sum::<f64>().sqrt();
+    if magnitude < MAGNITUDE_RANGE.0 * STANDARD_GRAVITY
+        || magnitude > MAGNITUDE_RANGE.1 * STANDARD_GRAVITY
+    {
+        return Err(format!(
+            "measured {:.2} m/s², expected about {:.2}",
+            magnitude, STANDARD_GRAVITY
+        ));
+    }
+    let axis = (0..3)
+        .max_by(|&a, &b| mean[a].abs().total_cmp(&mean[b].abs()))
+        .unwrap_or(2);
+    if mean[axis].abs() < MIN_ALIGNMENT * magnitude {
+        return Err("no sensor axis is vertical - align one face with the ground".to_string());
+    }
+    // At rest the accelerometer measures the reaction to gravity: a down axis reads negative
+    let pose = Pose {
+        axis,
+        down: mean[axis] < 0.0,
+    };
+    Ok((pose, mean))
+}
+
+/// Per-axis bias and scale for `(raw - bias) * scale` from the six pose means
+fn solve(poses: &BTreeMap<Pose, Vector>) -> Option<(Vector, Vector)> {
+    let mut bias = [0.0; 3];
+    let mut scale = [1.0; 3];
+    for axis in 0..3 {
+        let up = poses.get(&Pose { axis, down: false })?[axis];
+        let down = poses.get(&Pose { axis, down: true })?[axis];
+        bias[axis] = (up + down) / 2.0;
+        scale[axis] = 2.0 * STANDARD_GRAVITY / (up - down);
+    }
+    Some((bias, scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_six_poses_solve_bias_and_scale() {
+        let bias = [0.2, -0.15, 0.4];
+        let gain = [1.02, 0.97, 1.01];
+        let mut poses = BTreeMap::new();
+        for pose in Pose::ALL {
+            // Reading of a still sensor: gain * specific force + bias
+            let mut reading = bias;
+            let force = if pose.down {
+                -STANDARD_GRAVITY
+            } else {
+                STANDARD_GRAVITY
+            };
+            reading[pose.axis] += gain[pose.axis] * force;
+            let capture = Capture {
+                sum: reading.map(|v| v * 100.0),
+                sum_sq: reading.map(|v| v * v * 100.0),
+                count: 100,
+            };
+            let (classified, mean) = classify(&capture).unwrap();
+            assert_eq!(classified, pose);
+            poses.insert(classified, mean);
+            if poses.len() < 6 {
+                assert!(solve(&poses).is_none());
+            }
+        }
+        let (solved_bias, solved_scale) = solve(&poses).unwrap();
+        for i in 0..3 {
+            assert!((solved_bias[i] - bias[i]).abs() < 1e-9);
+            assert!((solved_scale[i] * gain[i] - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(Pose::ALL[0].name(), "z_down");
+    }
+}
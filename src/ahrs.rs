@@ -0,0 +1,365 @@
+use crate::config::sensor_config::{AhrsAlgorithm, AhrsEntry};
+use crate::grpc_service::SensorHubService;
+use crate::messages::{AttitudeMessage, Header, ImuMessage, SensorMessage};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+type Vector = [f64; 3];
+/// w, x, y, z; rotates body (FRD) vectors into the local NED frame
+type Quaternion = [f64; 4];
+
+/// Bus id reported for fused attitude sensors
+const AHRS_BUS_ID: &str = "ahrs";
+/// Magnetometer samples older than this are not used for heading
+const MAG_MAX_AGE_NS: u64 = 500_000_000;
+/// A longer gap between IMU samples restarts the filter from the next sample
+const MAX_STEP_S: f64 = 0.5;
+
+/// Attitude filter for one IMU
+struct Filter {
+    algorithm: AhrsAlgorithm,
+    beta: f64,
+    kp: f64,
+    ki: f64,
+    q: Option<Quaternion>,
+    /// Mahony integral term (rad/s)
+    integral: Vector,
+    last_ns: u64,
+}
+
+impl Filter {
+    fn new(entry: &AhrsEntry) -> Self {
+        Self {
+            algorithm: entry.algorithm,
+            beta: entry.beta,
+            kp: entry.kp,
+            ki: entry.ki,
+            q: None,
+            integral: [0.0; 3],
+            last_ns: 0,
+        }
+    }
+
+    /// Advance to `t_ns` with body-frame gyro (rad/s), accel (m/s²) and optional mag
+    fn update(
+        &mut self,
+        t_ns: u64,
+        gyro: Vector,
+        accel: Vector,
+        mag: Option<Vector>,
+    ) -> Quaternion {
+        let dt = t_ns.saturating_sub(self.last_ns) as f64 / 1e9;
+        self.last_ns = t_ns;
+        // At rest the accelerometer measures the reaction to gravity: down is -accel
+        let down = normalize(accel.map(|a| -a));
+        let mag = mag.and_then(normalize);
+        let q = match self.q {
+            Some(q) if dt > 0.0 && dt <= MAX_STEP_S => q,
+            _ => {
+                let q = down.map_or([1.0, 0.0, 0.0, 0.0], |down| initial(down, mag));
+                self.q = Some(q);
+                self.integral = [0.0; 3];
+                return q;
+            }
+        };
+
+        // Body-frame error rotating the estimated references onto the measured ones
+        let mut error = [0.0; 3];
+        if let Some(down) = down {
+            error = add(error, cross(down, rotate_inverse(q, [0.0, 0.0, 1.0])));
+            if let Some(mag) = mag {
+                // Reference field: the measured one in NED, with its horizontal part on north
+                let h = rotate(q, mag);
+                let reference = [(h[0] * h[0] + h[1] * h[1]).sqrt(), 0.0, h[2]];
+                error = add(error, cross(mag, rotate_inverse(q, reference)));
+            }
+        }
+
+        let rate = match self.algorithm {
+            AhrsAlgorithm::Madgwick => match normalize(error) {
+                Some(direction) => add(gyro, direction.map(|e| 2.0 * self.beta * e)),
+                None => gyro,
+            },
+            AhrsAlgorithm::Mahony => {
+                if self.ki > 0.0 {
+                    self.integral = add(self.integral, error.map(|e| self.ki * e * dt));
+                }
+                add(add(gyro, error.map(|e| self.kp * e)), self.integral)
+            }
+        };
+        let dq = multiply(q, [0.0, rate[0], rate[1], rate[2]]);
+        let q = normalize_quaternion([0, 1, 2, 3].map(|i| q[i] + 0.5 * dq[i] * dt));
+        self.q = Some(q);
+        q
+    }
+}
+
+/// Attitude straight from one accelerometer (and magnetometer) reading
+fn initial(down: Vector, mag: Option<Vector>) -> Quaternion {
+    let roll = down[1].atan2(down[2]);
+    let pitch = (-down[0]).clamp(-1.0, 1.0).asin();
+    let yaw = mag.map_or(0.0, |m| {
+        // Level the field, then take its direction in the horizontal plane
+        let (sr, cr, sp, cp) = (roll.sin(), roll.cos(), pitch.sin(), pitch.cos());
+        let north = m[0] * cp + m[1] * sr * sp + m[2] * cr * sp;
+        let east = m[1] * cr - m[2] * sr;
+        (-east).atan2(north)
+    });
+    from_euler(roll, pitch, yaw)
+}
+
+fn from_euler(roll: f64, pitch: f64, yaw: f64) -> Quaternion {
+    let (cr, sr) = ((roll / 2.0).cos(), (roll / 2.0).sin());
+    let (cp, sp) = ((pitch / 2.0).cos(), (pitch / 2.0).sin());
+    let (cy, sy) = ((yaw / 2.0).cos(), (yaw / 2.0).sin());
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+/// Roll, pitch, yaw (rad, ZYX)
+fn euler(q: Quaternion) -> Vector {
+    let [w, x, y, z] = q;
+    [
+        (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)),
+        (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin(),
+        (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)),
+    ]
+}
+
+fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: Vector) -> Option<Vector> {
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    (norm > 1e-9).then(|| v.map(|x| x / norm))
+}
+
+fn normalize_quaternion(q: Quaternion) -> Quaternion {
+    let norm = q.iter().map(|x| x * x).sum::<f64>().sqrt();
+    q.map(|x| x / norm)
+}
+
+fn multiply(a: Quaternion, b: Quaternion) -> Quaternion {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+/// Body to NED
+fn rotate(q: Quaternion, v: Vector) -> Vector {
+    let p = multiply(multiply(q, [0.0, v[0], v[1], v[2]]), conjugate(q));
+    [p[1], p[2], p[3]]
+}
+
+/// NED to body
+fn rotate_inverse(q: Quaternion, v: Vector) -> Vector {
+    rotate(conjugate(q), v)
+}
+
+fn conjugate(q: Quaternion) -> Quaternion {
+    [q[0], -q[1], -q[2], -q[3]]
+}
+
+/// One configured fusion: its filter and where it publishes
+struct Fusion {
+    imu: String,
+    mag: Option<String>,
+    sensor_id: String,
+    filter: Filter,
+    seq: u64,
+}
+
+impl Fusion {
+    fn attitude(&mut self, imu: &ImuMessage, mag: Option<Vector>) -> AttitudeMessage {
+        let (accel, gyro) = match imu.precise {
+            Some(p) => ([p[0], p[1], p[2]], [p[3], p[4], p[5]]),
+            None => (
+                [imu.ax, imu.ay, imu.az].map(f64::from),
+                [imu.gx, imu.gy, imu.gz].map(f64::from),
+            ),
+        };
+        let q = self.filter.update(imu.h.t_utc_ns, gyro, accel, mag);
+        let [roll, pitch, yaw] = euler(q);
+        self.seq += 1;
+        let h = Header::at(
+            imu.h.device_id.clone(),
+            self.sensor_id.clone(),
+            imu.h.frame_id.clone(),
+            self.seq,
+            imu.h.stamp(),
+        );
+        AttitudeMessage {
+            h,
+            qw: q[0] as f32,
+            qx: q[1] as f32,
+            qy: q[2] as f32,
+            qz: q[3] as f32,
+            roll: roll as f32,
+            pitch: pitch as f32,
+            yaw: yaw as f32,
+            rollspeed: gyro[0] as f32,
+            pitchspeed: gyro[1] as f32,
+            yawspeed: gyro[2] as f32,
+            gnss_yaw: None,
+            gnss_yaw_accuracy: None,
+        }
+    }
+}
+
+/// Fuse local IMU (and magnetometer) data into attitude for boards without a flight
+/// controller, publishing one attitude sensor per `[[ahrs]]` entry
+pub fn spawn_fusion(entries: &[AhrsEntry], grpc_service: Arc<SensorHubService>) {
+    if entries.is_empty() {
+        return;
+    }
+    let mut fusions: Vec<Fusion> = entries
+        .iter()
+        .map(|entry| Fusion {
+            imu: entry.imu.clone(),
+            mag: entry.mag.clone(),
+            sensor_id: entry
+                .sensor_id
+                .clone()
+                .unwrap_or_else(|| format!("{}_ahrs", entry.imu)),
+            filter: Filter::new(entry),
+            seq: 0,
+        })
+        .collect();
+    for (fusion, entry) in fusions.iter().zip(entries) {
+        info!(
+            "[ahrs] {} from {}{} ({:?})",
+            fusion.sensor_id,
+            fusion.imu,
+            fusion
+                .mag
+                .as_deref()
+                .map(|m| format!(" + {}", m))
+                .unwrap_or_default(),
+            entry.algorithm
+        );
+    }
+    let mut rx = grpc_service.subscribe_messages();
+
+    tokio::spawn(async move {
+        for fusion in fusions.iter() {
+            let driver = match fusion.filter.algorithm {
+                AhrsAlgorithm::Madgwick => "ahrs_madgwick",
+                AhrsAlgorithm::Mahony => "ahrs_mahony",
+            };
+            grpc_service
+                .register_sensor(&fusion.sensor_id, driver, AHRS_BUS_ID, "attitude")
+                .await;
+        }
+        // Latest field of each magnetometer in use, with its time
+        let mut mags: HashMap<String, (u64, Vector)> = HashMap::new();
+        loop {
+            let message = match rx.recv().await {
+                Ok(message) => message,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("[ahrs] Fell behind, {} message(s) skipped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let imu = match &message {
+                SensorMessage::Imu(imu) => imu,
+                SensorMessage::Magnetometer(mag) => {
+                    if fusions
+                        .iter()
+                        .any(|f| f.mag.as_ref() == Some(&mag.h.sensor_id))
+                    {
+                        let field = [mag.mx, mag.my, mag.mz].map(f64::from);
+                        mags.insert(mag.h.sensor_id.clone(), (mag.h.t_utc_ns, field));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            for fusion in fusions.iter_mut().filter(|f| f.imu == imu.h.sensor_id) {
+                let mag = fusion
+                    .mag
+                    .as_ref()
+                    .and_then(|id| mags.get(id))
+                    .filter(|(t_ns, _)| imu.h.t_utc_ns.abs_diff(*t_ns) <= MAG_MAX_AGE_NS)
+                    .map(|(_, field)| *field);
+                let attitude = fusion.attitude(imu, mag);
+                if let Err(e) = grpc_service
+                    .publish(SensorMessage::Attitude(attitude))
+                    .await
+                {
+                    error!("[ahrs] Failed to publish {}: {}", fusion.sensor_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(algorithm: AhrsAlgorithm) -> AhrsEntry {
+        AhrsEntry {
+            imu: "imu0".to_string(),
+            mag: Some("mag0".to_string()),
+            algorithm,
+            sensor_id: None,
+            beta: 0.1,
+            kp: 1.0,
+            ki: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_converges_to_tilted_attitude() {
+        // Held still, rolled 20°, pitched -10° and heading 60°, with a small gyro bias
+        let angles = [20f64, -10.0, 60.0].map(f64::to_radians);
+        let truth = from_euler(angles[0], angles[1], angles[2]);
+        let accel = rotate_inverse(truth, [0.0, 0.0, -9.81]);
+        // Inclined field: 20 μT north, 45 μT down
+        let mag = rotate_inverse(truth, [20.0, 0.0, 45.0]);
+        let direct = euler(initial(
+            normalize(accel.map(|a| -a)).unwrap(),
+            normalize(mag),
+        ));
+        assert!((0..3).all(|i| (direct[i] - angles[i]).abs() < 1e-9));
+
+        for algorithm in [AhrsAlgorithm::Madgwick, AhrsAlgorithm::Mahony] {
+            let mut filter = Filter::new(&entry(algorithm));
+            // Start from a level, north-facing estimate
+            filter.update(0, [0.0; 3], [0.0, 0.0, -9.81], Some([20.0, 0.0, 45.0]));
+            let mut q = [1.0, 0.0, 0.0, 0.0];
+            // 60 s at 200 Hz; Mahony turns slowly onto a large heading error
+            for i in 1..=12000 {
+                q = filter.update(i * 5_000_000, [0.002, -0.001, 0.0], accel, Some(mag));
+            }
+            let estimated = euler(q);
+            for (e, t) in estimated.iter().zip(angles) {
+                assert!(
+                    (e - t).abs() < 1f64.to_radians(),
+                    "{:?}: {:?}",
+                    algorithm,
+                    estimated
+                );
+            }
+        }
+    }
+}
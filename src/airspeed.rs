@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use tracing::info;
+
+/// Sea-level air density used for indicated airspeed (kg/m³)
+const RHO_SEA_LEVEL: f32 = 1.225;
+
+/// Indicated airspeed (m/s) from differential pressure (Pa)
+/// Negative pressure (reversed flow, residual offset) gives a negative speed
+pub fn indicated_airspeed(differential_pressure: f32) -> f32 {
+    let speed = (2.0 * differential_pressure.abs() / RHO_SEA_LEVEL).sqrt();
+    speed.copysign(differential_pressure)
+}
+
+/// Result of a completed zeroing run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZeroResult {
+    /// Offset now subtracted from every reading (Pa)
+    pub offset: f32,
+    /// Number of samples averaged
+    pub samples: u32,
+}
+
+struct Zeroing {
+    sum: f64,
+    count: u32,
+    target: u32,
+    done: oneshot::Sender<ZeroResult>,
+}
+
+#[derive(Default)]
+struct PitotState {
+    offset: f32,
+    zeroing: Option<Zeroing>,
+}
+
+/// Per-sensor pitot zero offsets
+///
+/// Offsets are measured by averaging raw differential pressure while the pitot
+/// is covered, then subtracted from every subsequent reading. They are stored in
+/// the sensor's calibration file (`[airspeed]`) and restored from it at startup.
+#[derive(Default)]
+pub struct AirspeedCalibration {
+    sensors: Mutex<HashMap<String, PitotState>>,
+}
+
+impl AirspeedCalibration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a raw differential pressure reading, returning the zero-corrected value
+    pub fn apply(&self, sensor_id: &str, raw: f32) -> f32 {
+        let mut sensors = self.sensors.lock().unwrap();
+        let state = sensors.entry(sensor_id.to_string()).or_default();
+
+        if let Some(zeroing) = state.zeroing.as_mut() {
+            zeroing.sum += raw as f64;
+            zeroing.count += 1;
+            if zeroing.count >= zeroing.target {
+                let zeroing = state.zeroing.take().unwrap();
+                state.offset = (zeroing.sum / zeroing.count as f64) as f32;
+                info!(
+                    "[{}] Pitot zeroed: offset {:.2} Pa from {} samples",
+                    sensor_id, state.offset, zeroing.count
+                );
+                let _ = zeroing.done.send(ZeroResult {
+                    offset: state.offset,
+                    samples: zeroing.count,
+                });
+            }
+        }
+
+        raw - state.offset
+    }
+
+    /// Restore a previously measured offset (e.g. from the calibration file)
+    pub fn set_offset(&self, sensor_id: &str, offset: f32) {
+        let mut sensors = self.sensors.lock().unwrap();
+        sensors.entry(sensor_id.to_string()).or_default().offset = offset;
+    }
+
+    /// Start averaging the next `samples` readings into a new offset
+    /// A zeroing run already in progress for the sensor is replaced
+    pub fn start_zeroing(&self, sensor_id: &str, samples: u32) -> oneshot::Receiver<ZeroResult> {
+        let (done, rx) = oneshot::channel();
+        let mut sensors = self.sensors.lock().unwrap();
+        sensors.entry(sensor_id.to_string()).or_default().zeroing = Some(Zeroing {
+            sum: 0.0,
+            count: 0,
+            target: samples.max(1),
+            done,
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zeroing_applies_offset() {
+        let cal = AirspeedCalibration::new();
+        assert_eq!(cal.apply("pitot0", 12.0), 12.0);
+
+        let mut rx = cal.start_zeroing("pitot0", 2);
+        cal.apply("pitot0", 10.0);
+        cal.apply("pitot0", 14.0);
+        let result = rx.try_recv().unwrap();
+        assert_eq!(result.offset, 12.0);
+        assert_eq!(result.samples, 2);
+
+        assert_eq!(cal.apply("pitot0", 12.0), 0.0);
+        assert_eq!(cal.apply("other", 12.0), 12.0);
+        assert!((indicated_airspeed(61.25) - 10.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::accel_calibration::STANDARD_GRAVITY;
+use crate::config::sensor_config::AltitudeEntry;
+use crate::grpc_service::SensorHubService;
+use crate::messages::{AltitudeMessage, Header, ImuMessage, SensorMessage};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+type Vector = [f64; 3];
+/// w, x, y, z; rotates body (FRD) vectors into the local NED frame
+type Quaternion = [f64; 4];
+
+/// Bus id reported for fused altitude sensors
+const ALTITUDE_BUS_ID: &str = "altitude";
+/// Barometer and attitude samples older than this are not used
+const MAX_AGE_NS: u64 = 500_000_000;
+/// A longer gap between IMU samples restarts the filter from the barometer
+const MAX_STEP_S: f64 = 0.5;
+/// Low-pass time constant of the gravity direction used without an attitude source (s)
+const GRAVITY_TIME_CONSTANT_S: f64 = 1.0;
+
+/// Third-order complementary filter: the accelerometer drives altitude and climb rate,
+/// the barometer corrects them and estimates the accelerometer's vertical bias
+///
+/// The gains place all three error poles at `-1/τ`.
+struct Filter {
+    k1: f64,
+    k2: f64,
+    k3: f64,
+    /// Altitude (m), climb rate (m/s), vertical accelerometer bias (m/s²)
+    state: Option<[f64; 3]>,
+}
+
+impl Filter {
+    fn new(time_constant_s: f64) -> Self {
+        let tau = time_constant_s.max(0.05);
+        Self {
+            k1: 3.0 / tau,
+            k2: 3.0 / (tau * tau),
+            k3: 1.0 / (tau * tau * tau),
+            state: None,
+        }
+    }
+
+    /// Start again from the barometer, keeping the learned bias
+    fn reset(&mut self, baro: Option<f64>) {
+        let bias = self.state.map(|s| s[2]).unwrap_or(0.0);
+        self.state = baro.map(|h| [h, 0.0, bias]);
+    }
+
+    /// Advance by `dt` with the vertical acceleration (m/s², up, gravity removed) and
+    /// the latest barometric altitude, returning altitude, climb rate and corrected
+    /// acceleration
+    fn step(&mut self, dt: f64, a_up: f64, baro: Option<f64>) -> Option<[f64; 3]> {
+        let Some([h, v, bias]) = self.state else {
+            self.reset(baro);
+            return self.state.map(|[h, v, _]| [h, v, 0.0]);
+        };
+        let e = baro.map(|b| b - h).unwrap_or(0.0);
+        let a = a_up - bias;
+        let state = [
+            h + (v + self.k1 * e) * dt,
+            v + (a + self.k2 * e) * dt,
+            bias - self.k3 * e * dt,
+        ];
+        self.state = Some(state);
+        Some([state[0], state[1], a])
+    }
+}
+
+/// One configured estimator: its filter, inputs and where it publishes
+struct Estimator {
+    baro: String,
+    imu: String,
+    attitude: Option<String>,
+    sensor_id: String,
+    filter: Filter,
+    min_interval_ns: u64,
+    /// Latest barometric altitude and attitude, with their times
+    baro_alt: Option<(u64, f64)>,
+    orientation: Option<(u64, Quaternion)>,
+    /// Low-passed specific force, pointing up, when there is no attitude
+    gravity: Option<Vector>,
+    last_imu_ns: Option<u64>,
+    last_publish_ns: Option<u64>,
+    seq: u64,
+}
+
+impl Estimator {
+    fn new(entry: &AltitudeEntry) -> Self {
+        Self {
+            baro: entry.baro.clone(),
+            imu: entry.imu.clone(),
+            attitude: entry.attitude.clone(),
+            sensor_id: entry
+                .sensor_id
+                .clone()
+                .unwrap_or_else(|| format!("{}_alt", entry.baro)),
+            filter: Filter::new(entry.time_constant_s),
+            min_interval_ns: if entry.rate_hz > 0.0 {
+                (1e9 / entry.rate_hz) as u64
+            } else {
+                0
+            },
+            baro_alt: None,
+            orientation: None,
+            gravity: None,
+            last_imu_ns: None,
+            last_publish_ns: None,
+            seq: 0,
+        }
+    }
+
+    /// Vertical acceleration (m/s², up) from one accelerometer reading (body FRD)
+    fn vertical_accel(&mut self, t_ns: u64, accel: Vector, dt: Option<f64>) -> f64 {
+        if let Some((_, [w, x, y, z])) = self
+            .orientation
+            .filter(|(t, _)| t_ns.abs_diff(*t) <= MAX_AGE_NS)
+        {
+            // Third row of the body-to-NED rotation: the "down" component
+            let down = [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ];
+            return -dot(down, accel) - STANDARD_GRAVITY;
+        }
+        let gravity = match (self.gravity, dt) {
+            (Some(g), Some(dt)) if dt <= MAX_STEP_S => {
+                let alpha = dt / (GRAVITY_TIME_CONSTANT_S + dt);
+                [0, 1, 2].map(|i| g[i] + alpha * (accel[i] - g[i]))
+            }
+            _ => accel,
+        };
+        self.gravity = Some(gravity);
+        let norm = dot(gravity, gravity).sqrt();
+        if norm < 1e-6 {
+            return 0.0;
+        }
+        dot(accel, gravity.map(|g| g / norm)) - STANDARD_GRAVITY
+    }
+
+    fn imu(&mut self, imu: &ImuMessage) -> Option<AltitudeMessage> {
+        let t_ns = imu.h.t_utc_ns;
+        let accel = match imu.precise {
+            Some(p) => [p[0], p[1], p[2]],
+            None => [imu.ax, imu.ay, imu.az].map(f64::from),
+        };
+        let dt = self
+            .last_imu_ns
+            .map(|last| t_ns.saturating_sub(last) as f64 / 1e9);
+        self.last_imu_ns = Some(t_ns);
+        let a_up = self.vertical_accel(t_ns, accel, dt);
+        let baro = self
+            .baro_alt
+            .filter(|(t, _)| t_ns.abs_diff(*t) <= MAX_AGE_NS)
+            .map(|(_, h)| h);
+        let [altitude, climb_rate, vertical_accel] = match dt {
+            Some(dt) if dt > 0.0 && dt <= MAX_STEP_S => self.filter.step(dt, a_up, baro)?,
+            Some(dt) if dt <= 0.0 => return None,
+            _ => {
+                self.filter.reset(baro);
+                return None;
+            }
+        };
+
+        if self
+            .last_publish_ns
+            .is_some_and(|last| t_ns < last + self.min_interval_ns)
+        {
+            return None;
+        }
+        self.last_publish_ns = Some(t_ns);
+        self.seq += 1;
+        let h = Header::at(
+            imu.h.device_id.clone(),
+            self.sensor_id.clone(),
+            imu.h.frame_id.clone(),
+            self.seq,
+            imu.h.stamp(),
+        );
+        Some(AltitudeMessage {
+            h,
+            altitude: altitude as f32,
+            climb_rate: climb_rate as f32,
+            vertical_accel: vertical_accel as f32,
+        })
+    }
+}
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Fuse barometric altitude with IMU vertical acceleration, publishing one altitude
+/// sensor per `[[altitude]]` entry
+pub fn spawn_estimator(entries: &[AltitudeEntry], grpc_service: Arc<SensorHubService>) {
+    if entries.is_empty() {
+        return;
+    }
+    let mut estimators: Vec<Estimator> = entries.iter().map(Estimator::new).collect();
+    for estimator in estimators.iter() {
+        info!(
+            "[altitude] {} from {} + {}{}",
+            estimator.sensor_id,
+            estimator.baro,
+            estimator.imu,
+            estimator
+                .attitude
+                .as_deref()
+                .map(|a| format!(" ({} for vertical)", a))
+                .unwrap_or_default()
+        );
+    }
+    let mut rx = grpc_service.subscribe_messages();
+
+    tokio::spawn(async move {
+        for estimator in estimators.iter() {
+            grpc_service
+                .register_sensor(
+                    &estimator.sensor_id,
+                    "altitude_fusion",
+                    ALTITUDE_BUS_ID,
+                    "altitude",
+                )
+                .await;
+        }
+        loop {
+            let message = match rx.recv().await {
+                Ok(message) => message,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("[altitude] Fell behind, {} message(s) skipped", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            match &message {
+                SensorMessage::Barometer(baro) => {
+                    for estimator in estimators.iter_mut().filter(|e| e.baro == baro.h.sensor_id) {
+                        estimator.baro_alt = Some((baro.h.t_utc_ns, f64::from(baro.altitude)));
+                    }
+                }
+                SensorMessage::Attitude(att) => {
+                    let q = [att.qw, att.qx, att.qy, att.qz].map(f64::from);
+                    for estimator in estimators
+                        .iter_mut()
+                        .filter(|e| e.attitude.as_ref() == Some(&att.h.sensor_id))
+                    {
+                        estimator.orientation = Some((att.h.t_utc_ns, q));
+                    }
+                }
+                SensorMessage::Imu(imu) => {
+                    for estimator in estimators.iter_mut().filter(|e| e.imu == imu.h.sensor_id) {
+                        let Some(altitude) = estimator.imu(imu) else {
+                            continue;
+                        };
+                        if let Err(e) = grpc_service
+                            .publish(SensorMessage::Altitude(altitude))
+                            .await
+                        {
+                            error!(
+                                "[altitude] Failed to publish {}: {}",
+                                estimator.sensor_id, e
+                            );
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_climb_through_baro_noise() {
+        // 200 Hz accelerometer with a 0.3 m/s² bias, 25 Hz baro with ±1 m of noise
+        let mut filter = Filter::new(2.0);
+        let dt = 0.005;
+        let mut seed: u32 = 1;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f64 / (1u32 << 24) as f64 * 2.0 - 1.0
+        };
+        let (mut h, mut v) = (100.0, 0.0);
+        let mut baro = None;
+        let mut estimate = [0.0; 3];
+        // 20 s at rest, 2 s accelerating at 1 m/s², then 20 s climbing at 2 m/s
+        for i in 0..8400 {
+            let t = i as f64 * dt;
+            let a = if (20.0..22.0).contains(&t) { 1.0 } else { 0.0 };
+            v += a * dt;
+            h += v * dt;
+            if i % 8 == 0 {
+                baro = Some(h + noise());
+            }
+            estimate = filter.step(dt, a + 0.3, baro).unwrap();
+        }
+        assert!((estimate[1] - 2.0).abs() < 0.2, "{:?}", estimate);
+        assert!((estimate[0] - h).abs() < 0.5, "{:?} vs {}", estimate, h);
+        assert!(estimate[2].abs() < 0.1, "{:?}", estimate);
+    }
+}
@@ -0,0 +1,676 @@
+//! Soak test: runs the hub binary for hours against a simulated flight controller with
+//! randomized fault injection, and reports memory growth, sensor restarts and end-to-end
+//! latency percentiles. Exits non-zero if the hub crashed, wedged or regressed.
+//!
+//! ```bash
+//! cargo build --release
+//! SOAK_DURATION_S=14400 ./target/release/soak
+//! ```
+
+use mavlink::common::{
+    GpsFixType, MavAutopilot, MavMessage, MavModeFlag, MavState, MavType, ATTITUDE_DATA,
+    GPS_RAW_INT_DATA, HEARTBEAT_DATA, SCALED_IMU_DATA, SCALED_PRESSURE_DATA,
+};
+use mavlink::MavHeader;
+use sensorhub::sensor_data::Data;
+use sensorhub::sensor_event::Event;
+use sensorhub::sensor_hub_client::SensorHubClient;
+use sensorhub::{Header, SensorRequest};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+#[allow(dead_code, clippy::enum_variant_names, clippy::large_enum_variant)]
+mod sensorhub {
+    tonic::include_proto!("sensorhub");
+}
+
+/// Latency histogram resolution and range (100 µs buckets up to 10 s)
+const BUCKET_US: u64 = 100;
+const BUCKETS: usize = 100_000;
+/// Simulated flight controller base tick (IMU rate)
+const FC_TICK: Duration = Duration::from_millis(5);
+/// Mean seconds between link faults and between hub-side scenario faults
+const LINK_FAULT_INTERVAL_S: u64 = 90;
+const SCENARIO_FAULT_INTERVAL_S: f64 = 300.0;
+const RSS_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Soak parameters, from SOAK_* environment variables
+struct Settings {
+    duration: Duration,
+    warmup: Duration,
+    report_interval: Duration,
+    seed: u64,
+    hub: PathBuf,
+    max_rss_growth_kb: u64,
+    max_p99_ms: f64,
+}
+
+impl Settings {
+    fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+        let duration_s: u64 = var("SOAK_DURATION_S", 3600);
+        let seed = var(
+            "SOAK_SEED",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64,
+        );
+        // The hub binary is built next to this one
+        let hub = std::env::var("SOAK_HUB")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                std::env::current_exe()
+                    .ok()
+                    .and_then(|exe| exe.parent().map(|dir| dir.join("navigate_sensorhub")))
+                    .unwrap_or_else(|| PathBuf::from("navigate_sensorhub"))
+            });
+        Self {
+            duration: Duration::from_secs(duration_s),
+            warmup: Duration::from_secs(var("SOAK_WARMUP_S", (duration_s / 10).clamp(10, 600))),
+            report_interval: Duration::from_secs(var("SOAK_REPORT_S", 60).max(1)),
+            seed,
+            hub,
+            max_rss_growth_kb: var("SOAK_MAX_RSS_GROWTH_MB", 16) * 1024,
+            max_p99_ms: var("SOAK_MAX_P99_MS", 50.0),
+        }
+    }
+}
+
+/// xorshift64*: reproducible fault schedules from SOAK_SEED
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [lo, hi)
+    fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * (hi - lo)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+/// Fixed-bucket latency histogram
+struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            max_us: 0,
+        }
+    }
+
+    fn record(&mut self, us: u64) {
+        let bucket = ((us / BUCKET_US) as usize).min(BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// Upper edge of the bucket holding quantile `q`, in milliseconds
+    fn percentile_ms(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                // The last bucket also holds everything beyond the range
+                let edge = if bucket == BUCKETS - 1 {
+                    self.max_us
+                } else {
+                    ((bucket as u64 + 1) * BUCKET_US).min(self.max_us)
+                };
+                return edge as f64 / 1000.0;
+            }
+        }
+        self.max_us as f64 / 1000.0
+    }
+
+    fn reset(&mut self) {
+        self.buckets.iter_mut().for_each(|b| *b = 0);
+        self.count = 0;
+        self.max_us = 0;
+    }
+}
+
+/// Everything observed during the run, shared by the soak tasks
+struct Stats {
+    interval: Histogram,
+    total: Histogram,
+    /// Link faults injected by the simulated flight controller, by kind
+    faults: BTreeMap<&'static str, u64>,
+    /// Sensors announced at least once; a repeated announcement is a restart
+    announced: HashSet<String>,
+    restarts: u64,
+    removals: u64,
+    anomalies: u64,
+    stream_reconnects: u64,
+}
+
+type Shared = Arc<Mutex<Stats>>;
+
+/// Link-level faults of the simulated flight controller
+#[derive(Clone, Copy)]
+enum LinkFault {
+    /// Send nothing (longer than the hub's 3 s sensor timeout: sensors are removed)
+    Stall,
+    /// Close the TCP connection; the hub reconnects
+    Disconnect,
+    /// Random bytes between frames
+    Garbage,
+    /// Every IMU frame sent several times
+    Burst,
+    /// GPS goes quiet while the rest keeps streaming
+    GpsLoss,
+}
+
+impl LinkFault {
+    const ALL: [LinkFault; 5] = [
+        LinkFault::Stall,
+        LinkFault::Disconnect,
+        LinkFault::Garbage,
+        LinkFault::Burst,
+        LinkFault::GpsLoss,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            LinkFault::Stall => "stall",
+            LinkFault::Disconnect => "disconnect",
+            LinkFault::Garbage => "garbage",
+            LinkFault::Burst => "burst",
+            LinkFault::GpsLoss => "gps_loss",
+        }
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Hub-side faults for the `[scenario]` section, spread over the run
+fn scenario_toml(rng: &mut Rng, duration: Duration) -> String {
+    let targets = [
+        ("fc_imu0", "gz", 0.05),
+        ("fc_baro0", "pressure", 2.0),
+        ("fc_attitude_euler", "yaw", 0.1),
+        ("fc_gps0", "altitude", 5.0),
+    ];
+    let count = (duration.as_secs_f64() / SCENARIO_FAULT_INTERVAL_S).ceil() as u64;
+    let mut out = String::from("[scenario]\nenabled = true\n");
+    for _ in 0..count {
+        let (sensor, field, offset) = targets[rng.below(targets.len() as u64) as usize];
+        let at_s = rng.range(0.0, duration.as_secs_f64()).round();
+        let duration_s = rng.range(5.0, 60.0).round();
+        let _ = write!(
+            out,
+            "\n[[scenario.fault]]\nsensor = \"{}\"\nat_s = {}\nduration_s = {}\n",
+            sensor, at_s, duration_s
+        );
+        if rng.below(2) == 0 {
+            out.push_str("kind = \"dropout\"\n");
+        } else {
+            let _ = writeln!(
+                out,
+                "kind = \"bias\"\noffsets = {{ {} = {} }}",
+                field, offset
+            );
+        }
+    }
+    out
+}
+
+fn write_config(dir: &Path, fc_port: u16, scenario: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join("buses.toml"),
+        format!(
+            "[[bus]]\nid = \"fc\"\ntype = \"tcp\"\npath = \"tcpout://127.0.0.1:{}\"\n",
+            fc_port
+        ),
+    )?;
+    std::fs::write(dir.join("sensors.toml"), scenario)
+}
+
+fn spawn_hub(settings: &Settings, config_dir: &Path, grpc_port: u16) -> std::io::Result<Child> {
+    let log = std::fs::File::create(config_dir.join("hub.log"))?;
+    Command::new(&settings.hub)
+        .env("CONFIG_PATH", config_dir)
+        .env("GRPC_PORT", grpc_port.to_string())
+        .env(
+            "RUST_LOG",
+            std::env::var("RUST_LOG").unwrap_or("warn".into()),
+        )
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .stdin(Stdio::null())
+        .spawn()
+}
+
+/// Resident set size of a process, in kB
+fn rss_kb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+fn frame(sequence: u8, msg: &MavMessage) -> Vec<u8> {
+    let header = MavHeader {
+        system_id: 1,
+        component_id: 1,
+        sequence,
+    };
+    let mut buf = Vec::new();
+    let _ = mavlink::write_v2_msg(&mut buf, header, msg);
+    buf
+}
+
+/// Frames due at `tick` (IMU every tick, attitude 50 Hz, baro 20 Hz, GPS 5 Hz, heartbeat 1 Hz)
+fn telemetry(tick: u64, gps: bool) -> Vec<MavMessage> {
+    let time_boot_ms = (tick * FC_TICK.as_millis() as u64) as u32;
+    let phase = tick as f32 * 0.01;
+    let mut out = vec![MavMessage::SCALED_IMU(SCALED_IMU_DATA {
+        time_boot_ms,
+        xacc: (phase.sin() * 20.0) as i16,
+        zacc: -1000,
+        zgyro: (phase.cos() * 30.0) as i16,
+        ..Default::default()
+    })];
+    if tick.is_multiple_of(4) {
+        out.push(MavMessage::ATTITUDE(ATTITUDE_DATA {
+            time_boot_ms,
+            yaw: phase.sin(),
+            ..Default::default()
+        }));
+    }
+    if tick.is_multiple_of(10) {
+        out.push(MavMessage::SCALED_PRESSURE(SCALED_PRESSURE_DATA {
+            time_boot_ms,
+            press_abs: 1013.25 + phase.sin() * 0.1,
+            temperature: 2500,
+            ..Default::default()
+        }));
+    }
+    if gps && tick.is_multiple_of(40) {
+        out.push(MavMessage::GPS_RAW_INT(GPS_RAW_INT_DATA {
+            time_usec: time_boot_ms as u64 * 1000,
+            lat: 473_977_420,
+            lon: 85_455_940,
+            alt: 488_000,
+            fix_type: GpsFixType::GPS_FIX_TYPE_3D_FIX,
+            satellites_visible: 12,
+            ..Default::default()
+        }));
+    }
+    if tick.is_multiple_of(200) {
+        out.push(MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            mavtype: MavType::MAV_TYPE_QUADROTOR,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            base_mode: MavModeFlag::MAV_MODE_FLAG_CUSTOM_MODE_ENABLED,
+            system_status: MavState::MAV_STATE_STANDBY,
+            mavlink_version: 3,
+            ..Default::default()
+        }));
+    }
+    out
+}
+
+/// Stream telemetry to the hub over one connection, injecting link faults at random
+/// Returns when the connection drops or a disconnect fault closes it
+async fn serve_fc(stream: &mut TcpStream, rng: &mut Rng, stats: &Shared) {
+    let mut ticker = tokio::time::interval(FC_TICK);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let ticks_per_s = 1000 / FC_TICK.as_millis() as u64;
+    let mut sequence: u8 = 0;
+    let mut active: Option<(LinkFault, u64)> = None;
+
+    for tick in 0u64.. {
+        ticker.tick().await;
+        if tick.is_multiple_of(ticks_per_s) {
+            if active.is_some_and(|(_, until)| tick >= until) {
+                active = None;
+            }
+            if active.is_none() && rng.below(LINK_FAULT_INTERVAL_S) == 0 {
+                let fault = LinkFault::ALL[rng.below(LinkFault::ALL.len() as u64) as usize];
+                let seconds = match fault {
+                    LinkFault::Stall => rng.range(1.0, 10.0),
+                    LinkFault::Burst => rng.range(2.0, 5.0),
+                    LinkFault::GpsLoss => rng.range(5.0, 20.0),
+                    LinkFault::Disconnect | LinkFault::Garbage => 0.0,
+                };
+                *stats
+                    .lock()
+                    .unwrap()
+                    .faults
+                    .entry(fault.name())
+                    .or_default() += 1;
+                match fault {
+                    LinkFault::Disconnect => return,
+                    LinkFault::Garbage => {
+                        let garbage: Vec<u8> = (0..rng.range(64.0, 512.0) as usize)
+                            .map(|_| rng.next_u64() as u8)
+                            .collect();
+                        if stream.write_all(&garbage).await.is_err() {
+                            return;
+                        }
+                    }
+                    _ => active = Some((fault, tick + (seconds * ticks_per_s as f64) as u64)),
+                }
+            }
+        }
+
+        let fault = active.map(|(fault, _)| fault);
+        if matches!(fault, Some(LinkFault::Stall)) {
+            continue;
+        }
+        let mut buf = Vec::new();
+        for msg in telemetry(tick, !matches!(fault, Some(LinkFault::GpsLoss))) {
+            let copies = match (&msg, fault) {
+                (MavMessage::SCALED_IMU(_), Some(LinkFault::Burst)) => 5,
+                _ => 1,
+            };
+            for _ in 0..copies {
+                buf.extend(frame(sequence, &msg));
+                sequence = sequence.wrapping_add(1);
+            }
+        }
+        if stream.write_all(&buf).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Simulated flight controller: accepts the hub's connection, reconnects forever
+async fn run_fc(listener: TcpListener, seed: u64, stats: Shared) {
+    let mut rng = Rng::new(seed);
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let _ = stream.set_nodelay(true);
+        serve_fc(&mut stream, &mut rng, &stats).await;
+    }
+}
+
+fn header(data: &Data) -> Option<&Header> {
+    match data {
+        Data::Imu(m) => m.header.as_ref(),
+        Data::Barometer(m) => m.header.as_ref(),
+        Data::Gps(m) => m.header.as_ref(),
+        Data::Attitude(m) => m.header.as_ref(),
+        _ => None,
+    }
+}
+
+/// Latency from the hub's header stamp to arrival on a StreamAll client
+async fn watch_data(endpoint: String, stats: Shared) {
+    loop {
+        if let Ok(mut client) = SensorHubClient::connect(endpoint.clone()).await {
+            if let Ok(response) = client.stream_all(SensorRequest::default()).await {
+                let mut stream = response.into_inner();
+                while let Ok(Some(message)) = stream.message().await {
+                    let received = now_ns();
+                    let Some(h) = message.data.as_ref().and_then(header) else {
+                        continue;
+                    };
+                    let us = received.saturating_sub(h.t_utc_ns) / 1000;
+                    let mut stats = stats.lock().unwrap();
+                    stats.interval.record(us);
+                    stats.total.record(us);
+                }
+                stats.lock().unwrap().stream_reconnects += 1;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Sensor removals, re-announcements (task restarts) and rate anomalies
+async fn watch_events(endpoint: String, stats: Shared) {
+    loop {
+        if let Ok(mut client) = SensorHubClient::connect(endpoint.clone()).await {
+            if let Ok(response) = client.stream_events(SensorRequest::default()).await {
+                let mut stream = response.into_inner();
+                while let Ok(Some(event)) = stream.message().await {
+                    let mut stats = stats.lock().unwrap();
+                    match event.event {
+                        Some(Event::SensorAdded(info))
+                            if !stats.announced.insert(info.sensor_id.clone()) =>
+                        {
+                            stats.restarts += 1
+                        }
+                        Some(Event::SensorRemoved(_)) => stats.removals += 1,
+                        Some(Event::RateAnomaly(a)) if a.degraded => stats.anomalies += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn free_port() -> std::io::Result<u16> {
+    Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+#[tokio::main]
+async fn main() {
+    let settings = Settings::from_env();
+    if let Err(e) = run(&settings).await {
+        eprintln!("[soak] FAILED: {}", e);
+        std::process::exit(1);
+    }
+    println!("[soak] PASSED");
+}
+
+async fn run(settings: &Settings) -> Result<(), String> {
+    let mut rng = Rng::new(settings.seed);
+    let config_dir = std::env::temp_dir().join(format!("sensorhub-soak-{}", std::process::id()));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|e| e.to_string())?;
+    let fc_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let grpc_port = free_port().map_err(|e| e.to_string())?;
+    write_config(
+        &config_dir,
+        fc_port,
+        &scenario_toml(&mut rng, settings.duration),
+    )
+    .map_err(|e| format!("Cannot write config to {}: {}", config_dir.display(), e))?;
+
+    println!(
+        "[soak] {} for {}s, seed {}, config and hub log in {}",
+        settings.hub.display(),
+        settings.duration.as_secs(),
+        settings.seed,
+        config_dir.display()
+    );
+    let mut hub = spawn_hub(settings, &config_dir, grpc_port)
+        .map_err(|e| format!("Cannot start {}: {}", settings.hub.display(), e))?;
+    let result = soak(settings, &mut hub, listener, grpc_port, rng.next_u64()).await;
+    let _ = hub.kill();
+    let _ = hub.wait();
+    result
+}
+
+async fn soak(
+    settings: &Settings,
+    hub: &mut Child,
+    listener: TcpListener,
+    grpc_port: u16,
+    fc_seed: u64,
+) -> Result<(), String> {
+    let stats: Shared = Arc::new(Mutex::new(Stats {
+        interval: Histogram::new(),
+        total: Histogram::new(),
+        faults: BTreeMap::new(),
+        announced: HashSet::new(),
+        restarts: 0,
+        removals: 0,
+        anomalies: 0,
+        stream_reconnects: 0,
+    }));
+    let endpoint = format!("http://127.0.0.1:{}", grpc_port);
+    tokio::spawn(run_fc(listener, fc_seed, stats.clone()));
+    tokio::spawn(watch_data(endpoint.clone(), stats.clone()));
+    tokio::spawn(watch_events(endpoint, stats.clone()));
+
+    let start = Instant::now();
+    let mut sampler = tokio::time::interval(RSS_SAMPLE_INTERVAL);
+    let mut next_report = start + settings.report_interval;
+    let mut baseline_kb: Option<u64> = None;
+    let mut peak_kb = 0;
+    // Lowest RSS since the last report, so transient spikes don't count as growth
+    let mut floor_kb = u64::MAX;
+    let mut last_floor_kb = 0;
+    let mut worst_p99_ms: f64 = 0.0;
+
+    while start.elapsed() < settings.duration {
+        sampler.tick().await;
+        if let Ok(Some(status)) = hub.try_wait() {
+            return Err(format!(
+                "hub exited with {} after {}s (see hub.log)",
+                status,
+                start.elapsed().as_secs()
+            ));
+        }
+        let rss = rss_kb(hub.id()).unwrap_or(0);
+        peak_kb = peak_kb.max(rss);
+        floor_kb = floor_kb.min(rss);
+        if baseline_kb.is_none() && start.elapsed() >= settings.warmup {
+            baseline_kb = Some(rss);
+        }
+
+        if Instant::now() < next_report {
+            continue;
+        }
+        next_report += settings.report_interval;
+        let mut stats = stats.lock().unwrap();
+        let (p50, p99, count) = (
+            stats.interval.percentile_ms(0.5),
+            stats.interval.percentile_ms(0.99),
+            stats.interval.count,
+        );
+        println!(
+            "[soak] t+{}s rss {} kB, {} msgs, latency p50 {:.1} ms p99 {:.1} ms max {:.1} ms, \
+             restarts {}, faults {:?}",
+            start.elapsed().as_secs(),
+            rss,
+            count,
+            p50,
+            p99,
+            stats.interval.max_us as f64 / 1000.0,
+            stats.restarts,
+            stats.faults
+        );
+        if count == 0 {
+            return Err(format!(
+                "no data for {}s - hub wedged",
+                settings.report_interval.as_secs()
+            ));
+        }
+        if start.elapsed() >= settings.warmup {
+            worst_p99_ms = worst_p99_ms.max(p99);
+        }
+        stats.interval.reset();
+        last_floor_kb = floor_kb;
+        floor_kb = u64::MAX;
+    }
+
+    let stats = stats.lock().unwrap();
+    let growth_kb = last_floor_kb.saturating_sub(baseline_kb.unwrap_or(last_floor_kb));
+    println!(
+        "[soak] done: {} msgs, latency p50 {:.1} ms p99 {:.1} ms p99.9 {:.1} ms max {:.1} ms",
+        stats.total.count,
+        stats.total.percentile_ms(0.5),
+        stats.total.percentile_ms(0.99),
+        stats.total.percentile_ms(0.999),
+        stats.total.max_us as f64 / 1000.0
+    );
+    println!(
+        "[soak] rss baseline {} kB, final {} kB (+{} kB), peak {} kB",
+        baseline_kb.unwrap_or(0),
+        last_floor_kb,
+        growth_kb,
+        peak_kb
+    );
+    println!(
+        "[soak] {} sensor restarts, {} removals, {} rate anomalies, {} stream reconnects, \
+         link faults {:?}",
+        stats.restarts, stats.removals, stats.anomalies, stats.stream_reconnects, stats.faults
+    );
+
+    if growth_kb > settings.max_rss_growth_kb {
+        return Err(format!(
+            "RSS grew {} kB after warm-up (limit {} kB)",
+            growth_kb, settings.max_rss_growth_kb
+        ));
+    }
+    if worst_p99_ms > settings.max_p99_ms {
+        return Err(format!(
+            "p99 latency reached {:.1} ms (limit {} ms)",
+            worst_p99_ms, settings.max_p99_ms
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = Histogram::new();
+        for us in 1..=1000 {
+            histogram.record(us * 10);
+        }
+        // Bucket upper edges: within 100 µs of the exact value, capped at the maximum
+        assert_eq!(histogram.percentile_ms(0.5), 5.1);
+        assert_eq!(histogram.percentile_ms(0.99), 10.0);
+        assert_eq!(histogram.percentile_ms(1.0), 10.0);
+        histogram.record(60_000_000);
+        assert_eq!(histogram.max_us, 60_000_000);
+        assert_eq!(histogram.percentile_ms(1.0), 60_000.0);
+    }
+}
@@ -0,0 +1,59 @@
+//! What this hub binary was built from and with, for GetBuildInfo and the startup log
+
+/// Crate version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the binary was built from ("unknown" outside a git checkout), with "-dirty" for
+/// uncommitted changes
+pub const GIT_HASH: &str = env!("SENSORHUB_GIT_HASH");
+/// Target triple, e.g. "aarch64-unknown-linux-gnu"
+pub const TARGET: &str = env!("SENSORHUB_TARGET");
+/// Cargo profile, "debug" or "release"
+pub const PROFILE: &str = env!("SENSORHUB_PROFILE");
+
+/// Every Cargo feature of the crate and whether it is compiled in
+const FEATURES: [(&str, bool); 15] = [
+    ("lsm6dsl", cfg!(feature = "lsm6dsl")),
+    ("lis3mdl", cfg!(feature = "lis3mdl")),
+    ("bmp388", cfg!(feature = "bmp388")),
+    ("icm42688p", cfg!(feature = "icm42688p")),
+    ("icp10111", cfg!(feature = "icp10111")),
+    ("icp20100", cfg!(feature = "icp20100")),
+    ("mavlink_sensors", cfg!(feature = "mavlink_sensors")),
+    ("lightware", cfg!(feature = "lightware")),
+    ("iio", cfg!(feature = "iio")),
+    ("modbus", cfg!(feature = "modbus")),
+    ("drdy", cfg!(feature = "drdy")),
+    ("gpio", cfg!(feature = "gpio")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("webrtc", cfg!(feature = "webrtc")),
+    ("test-util", cfg!(feature = "test-util")),
+];
+
+/// Cargo features compiled in: sensor drivers, bus and output backends, test helpers
+pub fn features() -> Vec<&'static str> {
+    FEATURES
+        .into_iter()
+        .filter_map(|(feature, enabled)| enabled.then_some(feature))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_cargo_feature_listed() {
+        let manifest: toml::Table = include_str!("../Cargo.toml").parse().unwrap();
+        let mut declared: Vec<&str> = manifest["features"]
+            .as_table()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .filter(|feature| *feature != "default")
+            .collect();
+        let mut listed: Vec<&str> = FEATURES.iter().map(|(feature, _)| *feature).collect();
+        declared.sort_unstable();
+        listed.sort_unstable();
+        assert_eq!(listed, declared);
+    }
+}
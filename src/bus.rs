@@ -1,21 +1,41 @@
-#[cfg(feature = "lsm6dsl")]
 pub mod i2c;
+#[cfg(all(test, target_os = "linux"))]
+pub mod i2c_mock;
 
+pub mod gpio;
 pub mod mavlink;
 pub mod serial;
+pub mod tcp;
+pub mod udp;
 
 /// Bus type enum for different communication interfaces
 #[derive(Debug, Clone)]
 pub enum BusType {
     I2C,
     Serial,
+    /// MAVLink over TCP (`tcpout://host:port` or `tcpin://:port`)
+    Tcp,
+    /// MAVLink over UDP (`udpin://:port` or `udpout://host:port`), e.g. a mavlink-router
+    /// or mavp2p endpoint
+    Udp,
+    /// Dedicated UART for a single non-MAVLink sensor (rangefinders, etc.)
+    Uart,
+    /// Linux Industrial I/O device handled by a kernel driver
+    Iio,
+    /// Recorded log (MCAP file or CSV log directory) played back as virtual sensors
+    Replay,
 }
 
 impl BusType {
-    pub fn from_str(s: &str) -> Option<Self> {
+    pub fn parse(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "i2c" => Some(BusType::I2C),
             "serial" => Some(BusType::Serial),
+            "tcp" => Some(BusType::Tcp),
+            "udp" => Some(BusType::Udp),
+            "uart" => Some(BusType::Uart),
+            "iio" => Some(BusType::Iio),
+            "replay" => Some(BusType::Replay),
             _ => None,
         }
     }
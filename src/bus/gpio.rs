@@ -0,0 +1,169 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Data-ready interrupt line of a sensor, a GPIO character device line watched for rising
+/// edges
+///
+/// Edges are timestamped by the kernel on CLOCK_REALTIME when the interrupt fires, so the
+/// time of a sample does not depend on how quickly the hub gets to it.
+#[cfg(all(feature = "drdy", target_os = "linux"))]
+pub struct DataReady {
+    request: gpiocdev::tokio::AsyncRequest,
+    /// e.g. "gpiochip0:17", for logging
+    pub name: String,
+}
+
+#[cfg(all(feature = "drdy", target_os = "linux"))]
+impl DataReady {
+    /// Request `line` of `chip` (a name such as "gpiochip0" or a /dev path) as an input
+    pub fn open(chip: &str, line: u32, consumer: &str) -> io::Result<Self> {
+        use gpiocdev::line::{EdgeDetection, EventClock};
+
+        let request = gpiocdev::Request::builder()
+            .on_chip(chip_path(chip))
+            .with_consumer(consumer)
+            .with_line(line)
+            .as_input()
+            .with_edge_detection(EdgeDetection::RisingEdge)
+            .with_event_clock(EventClock::Realtime)
+            .request()
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            request: gpiocdev::tokio::AsyncRequest::new(request),
+            name: line_name(chip, line),
+        })
+    }
+
+    /// Wait for the next rising edge and return its time (UTC ns)
+    pub async fn next_edge(&self) -> io::Result<u64> {
+        let event = self
+            .request
+            .read_edge_event()
+            .await
+            .map_err(io::Error::other)?;
+        Ok(event.timestamp_ns)
+    }
+}
+
+#[cfg(not(all(feature = "drdy", target_os = "linux")))]
+pub struct DataReady {
+    pub name: String,
+}
+
+#[cfg(not(all(feature = "drdy", target_os = "linux")))]
+impl DataReady {
+    pub fn open(_chip: &str, _line: u32, _consumer: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "data-ready GPIOs need Linux and the drdy feature",
+        ))
+    }
+
+    pub async fn next_edge(&self) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "data-ready GPIOs need Linux and the drdy feature",
+        ))
+    }
+}
+
+/// Output line the hub drives itself (an RS-485 DE pin), put back to its idle level when
+/// dropped, even after an error or a cancelled write
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+pub struct OutputLine {
+    request: gpiocdev::Request,
+    line: u32,
+    idle: bool,
+    /// e.g. "gpiochip0:17", for logging
+    pub name: String,
+}
+
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+impl OutputLine {
+    /// Request `line` of `chip` as an output at `idle`
+    pub fn open(chip: &str, line: u32, consumer: &str, idle: bool) -> io::Result<Self> {
+        let request = gpiocdev::Request::builder()
+            .on_chip(chip_path(chip))
+            .with_consumer(consumer)
+            .with_line(line)
+            .as_output(level(idle))
+            .request()
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            request,
+            line,
+            idle,
+            name: line_name(chip, line),
+        })
+    }
+
+    pub fn set(&self, high: bool) -> io::Result<()> {
+        self.request
+            .set_value(self.line, level(high))
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+impl Drop for OutputLine {
+    fn drop(&mut self) {
+        // The kernel keeps a released line as it was last driven
+        let _ = self.set(self.idle);
+    }
+}
+
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+fn level(high: bool) -> gpiocdev::line::Value {
+    if high {
+        gpiocdev::line::Value::Active
+    } else {
+        gpiocdev::line::Value::Inactive
+    }
+}
+
+#[cfg(not(all(feature = "gpio", target_os = "linux")))]
+pub struct OutputLine {
+    pub name: String,
+}
+
+#[cfg(not(all(feature = "gpio", target_os = "linux")))]
+impl OutputLine {
+    pub fn open(_chip: &str, _line: u32, _consumer: &str, _idle: bool) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "GPIO outputs need Linux and the gpio feature",
+        ))
+    }
+
+    pub fn set(&self, _high: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "GPIO outputs need Linux and the gpio feature",
+        ))
+    }
+}
+
+/// Character device of `chip`, a name such as "gpiochip0" or a /dev path
+#[cfg_attr(not(all(feature = "gpio", target_os = "linux")), allow(dead_code))]
+fn chip_path(chip: &str) -> String {
+    if chip.starts_with('/') {
+        chip.to_string()
+    } else {
+        format!("/dev/{}", chip)
+    }
+}
+
+/// e.g. "gpiochip0:17"
+#[cfg_attr(not(all(feature = "gpio", target_os = "linux")), allow(dead_code))]
+fn line_name(chip: &str, line: u32) -> String {
+    format!("{}:{}", chip.trim_start_matches("/dev/"), line)
+}
+
+/// Time since the edge at `edge_utc_ns`, zero if the clock reads earlier
+pub fn edge_age(edge_utc_ns: u64) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    Duration::from_nanos(now.saturating_sub(edge_utc_ns))
+}
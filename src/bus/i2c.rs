@@ -2,6 +2,33 @@
 use i2cdev::core::I2CDevice;
 #[cfg(target_os = "linux")]
 use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+#[cfg(target_os = "linux")]
+use tracing::{info, warn};
+
+/// Highest 7-bit address; anything above is sent as a 10-bit address
+pub const MAX_7BIT_ADDRESS: u16 = 0x7F;
+/// Highest 10-bit address
+pub const MAX_10BIT_ADDRESS: u16 = 0x3FF;
+/// Addresses probed by a scan by default (0x00-0x07 and 0x78-0x7F are reserved)
+pub const SCAN_RANGE: RangeInclusive<u16> = 0x08..=0x77;
+
+/// Wait before recovering a bus again if it is still stuck, doubling up to the maximum
+#[cfg(target_os = "linux")]
+const RECOVERY_BACKOFF: Duration = Duration::from_millis(100);
+#[cfg(target_os = "linux")]
+const RECOVERY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+#[cfg(target_os = "linux")]
+mod ioctl {
+    // I2C_TENBIT from linux/i2c-dev.h, not wrapped by i2cdev
+    nix::ioctl_write_int_bad!(set_ten_bit, 0x0704);
+}
 
 /// I2C bus error type - platform specific
 #[cfg(target_os = "linux")]
@@ -21,10 +48,231 @@ impl std::fmt::Display for I2CError {
 #[cfg(not(target_os = "linux"))]
 impl std::error::Error for I2CError {}
 
+/// One recorded bus transaction
+#[derive(Debug, Clone)]
+pub struct I2cTransaction {
+    pub t_utc_ns: u64,
+    pub bus_id: String,
+    /// 7-bit, or 10-bit above 0x7F
+    pub address: u16,
+    /// Register for register reads/writes, None for plain transfers
+    pub reg: Option<u8>,
+    pub write: bool,
+    /// Bytes written, or bytes read back on success
+    pub data: Vec<u8>,
+    pub duration_us: u32,
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for I2cTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.t_utc_ns,
+            self.bus_id,
+            Address(self.address),
+            if self.write { "W" } else { "R" }
+        )?;
+        if let Some(reg) = self.reg {
+            write!(f, " reg=0x{:02X}", reg)?;
+        }
+        write!(f, " {:02X?} {}us", self.data, self.duration_us)?;
+        match &self.error {
+            Some(e) => write!(f, " ERR {}", e),
+            None => write!(f, " ok"),
+        }
+    }
+}
+
+/// Displays a bus address as 0x68, or 0x3A5 for 10-bit addresses
+pub struct Address(pub u16);
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 > MAX_7BIT_ADDRESS {
+            write!(f, "0x{:03X}", self.0)
+        } else {
+            write!(f, "0x{:02X}", self.0)
+        }
+    }
+}
+
+/// Addresses a scan should probe: `requested`, or the 7-bit range when empty, minus the
+/// bus's do-not-probe list, which is returned separately
+pub fn scan_plan(requested: &[u16], no_probe: &[u16]) -> (Vec<u16>, Vec<u16>) {
+    let candidates: Vec<u16> = if requested.is_empty() {
+        SCAN_RANGE.collect()
+    } else {
+        let mut requested = requested.to_vec();
+        requested.sort_unstable();
+        requested.dedup();
+        requested
+    };
+    candidates
+        .into_iter()
+        .partition(|address| !no_probe.contains(address))
+}
+
+/// Recovery of a stuck bus (a wedged adapter), set per bus in `buses.toml`
+///
+/// A slave holding SDA low is left to the kernel: adapters with bus recovery clock SCL
+/// themselves when a transfer finds the bus stuck. Driving SCL as a GPIO from here would
+/// take the pin off its I2C function until the next boot.
+#[derive(Debug, Clone)]
+pub struct BusRecovery {
+    /// Failed transactions in a row, with no success in between, after which the bus is
+    /// considered stuck and re-opened (0 disables recovery)
+    pub error_threshold: u32,
+}
+
+impl Default for BusRecovery {
+    fn default() -> Self {
+        Self {
+            error_threshold: 50,
+        }
+    }
+}
+
+/// Software bus analyzer: ring buffer of recent I2C transactions on all buses
+pub struct I2cTrace {
+    enabled: AtomicBool,
+    capacity: Mutex<usize>,
+    entries: Mutex<VecDeque<I2cTransaction>>,
+}
+
+impl I2cTrace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            capacity: Mutex::new(capacity),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        *self.capacity.lock().unwrap() = capacity.max(1);
+    }
+
+    pub fn record(&self, transaction: I2cTransaction) {
+        let capacity = *self.capacity.lock().unwrap();
+        let mut entries = self.entries.lock().unwrap();
+        while entries.len() >= capacity {
+            entries.pop_front();
+        }
+        entries.push_back(transaction);
+    }
+
+    /// Recorded transactions, oldest first
+    pub fn snapshot(&self) -> Vec<I2cTransaction> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// The adapter behind an I2CBus
+#[cfg(target_os = "linux")]
+enum Device {
+    Linux(LinuxI2CDevice),
+    #[cfg(test)]
+    Mock(super::i2c_mock::MockI2c),
+}
+
+#[cfg(target_os = "linux")]
+impl Device {
+    fn io(&mut self) -> &mut dyn I2CDevice<Error = LinuxI2CError> {
+        match self {
+            Device::Linux(device) => device,
+            #[cfg(test)]
+            Device::Mock(mock) => mock,
+        }
+    }
+
+    /// Close the adapter and open `path` again
+    fn reopen(&mut self, path: &str) -> Result<(), I2CError> {
+        match self {
+            Device::Linux(device) => {
+                *device = LinuxI2CDevice::new(path, 0)?;
+                Ok(())
+            }
+            #[cfg(test)]
+            Device::Mock(_) => Ok(()),
+        }
+    }
+}
+
+/// Failures counted towards the next recovery of a bus
+#[cfg(target_os = "linux")]
+struct RecoveryState {
+    config: BusRecovery,
+    consecutive_errors: u32,
+    backoff: Duration,
+    /// Earliest time of the next recovery
+    retry_at: Instant,
+    /// Recoveries, exported on /metrics
+    count: Option<Arc<AtomicU64>>,
+}
+
+#[cfg(target_os = "linux")]
+impl RecoveryState {
+    fn new(config: BusRecovery) -> Self {
+        Self {
+            config,
+            consecutive_errors: 0,
+            backoff: RECOVERY_BACKOFF,
+            retry_at: Instant::now(),
+            count: None,
+        }
+    }
+
+    fn ok(&mut self) {
+        self.consecutive_errors = 0;
+        self.backoff = RECOVERY_BACKOFF;
+    }
+
+    /// Count a failure; true when the bus is due for recovery
+    fn failed(&mut self, now: Instant) -> bool {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        self.config.error_threshold > 0
+            && self.consecutive_errors >= self.config.error_threshold
+            && now >= self.retry_at
+    }
+
+    /// After a recovery: count failures afresh, and wait out the backoff if they go on
+    fn recovered(&mut self, now: Instant) {
+        self.consecutive_errors = 0;
+        self.retry_at = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(RECOVERY_MAX_BACKOFF);
+        if let Some(count) = &self.count {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
 /// I2C bus implementation
 #[cfg(target_os = "linux")]
 pub struct I2CBus {
-    device: LinuxI2CDevice,
+    device: Device,
+    /// i2c-dev path, for re-opening during recovery
+    path: String,
+    /// Bus id and analyzer, when attached
+    trace: Option<(String, Arc<I2cTrace>)>,
+    /// Failed transactions, exported on /metrics
+    errors: Option<Arc<AtomicU64>>,
+    /// Whether the adapter is currently in 10-bit address mode
+    ten_bit: bool,
+    recovery: RecoveryState,
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -36,25 +284,162 @@ pub struct I2CBus {
 impl I2CBus {
     pub fn new(path: &str) -> Result<Self, I2CError> {
         let device = LinuxI2CDevice::new(path, 0)?;
-        Ok(Self { device })
+        Ok(Self {
+            device: Device::Linux(device),
+            path: path.to_string(),
+            trace: None,
+            errors: None,
+            ten_bit: false,
+            recovery: RecoveryState::new(BusRecovery::default()),
+        })
+    }
+
+    /// A bus of simulated chips
+    #[cfg(test)]
+    pub fn mock(mock: super::i2c_mock::MockI2c) -> Self {
+        Self {
+            device: Device::Mock(mock),
+            path: "mock".to_string(),
+            trace: None,
+            errors: None,
+            ten_bit: false,
+            recovery: RecoveryState::new(BusRecovery::default()),
+        }
+    }
+
+    /// Address `address`, switching the adapter between 7- and 10-bit mode as needed
+    fn select(&mut self, address: u16) -> Result<(), I2CError> {
+        match &mut self.device {
+            Device::Linux(device) => {
+                let ten_bit = address > MAX_7BIT_ADDRESS;
+                if ten_bit != self.ten_bit {
+                    use std::os::unix::io::AsRawFd;
+                    // SAFETY: the descriptor is the open i2c-dev file owned by `device`
+                    unsafe { ioctl::set_ten_bit(device.as_raw_fd(), ten_bit as i32) }
+                        .map_err(|e| LinuxI2CError::Errno(e as i32))?;
+                    self.ten_bit = ten_bit;
+                }
+                device.set_slave_address(address)
+            }
+            #[cfg(test)]
+            Device::Mock(mock) => {
+                mock.select(address);
+                Ok(())
+            }
+        }
+    }
+
+    /// Record this bus's transactions into `trace` while it is enabled
+    pub fn set_trace(&mut self, bus_id: &str, trace: Arc<I2cTrace>) {
+        self.trace = Some((bus_id.to_string(), trace));
+    }
+
+    /// Count failed transactions in `errors`
+    pub fn set_error_counter(&mut self, errors: Arc<AtomicU64>) {
+        self.errors = Some(errors);
+    }
+
+    /// Recover the bus as `config` says when it gets stuck, counting recoveries in `count`
+    pub fn set_recovery(&mut self, config: BusRecovery, count: Arc<AtomicU64>) {
+        self.recovery = RecoveryState::new(config);
+        self.recovery.count = Some(count);
+    }
+
+    /// Count a transaction's outcome towards recovery, recovering the bus when it is due
+    ///
+    /// Sensor tasks keep polling throughout; chips that lost their configuration are then
+    /// re-initialized by the scheduler's per-sensor recovery.
+    fn track<T>(&mut self, result: Result<T, I2CError>) -> Result<T, I2CError> {
+        if result.is_ok() {
+            self.recovery.ok();
+            return result;
+        }
+        if !self.recovery.failed(Instant::now()) {
+            return result;
+        }
+        warn!(
+            "[I2C {}] {} failed transactions in a row, recovering the bus",
+            self.path, self.recovery.consecutive_errors
+        );
+        match self.device.reopen(&self.path) {
+            Ok(()) => {
+                // A fresh descriptor starts in 7-bit mode
+                self.ten_bit = false;
+                info!("[I2C {}] Re-opened", self.path);
+            }
+            Err(e) => warn!("[I2C {}] Failed to re-open: {}", self.path, e),
+        }
+        self.recovery.recovered(Instant::now());
+        result
+    }
+
+    fn record(
+        &self,
+        address: u16,
+        reg: Option<u8>,
+        write: bool,
+        data: &[u8],
+        start: std::time::Instant,
+        result: &Result<(), I2CError>,
+    ) {
+        if let (Err(_), Some(errors)) = (result, self.errors.as_ref()) {
+            errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let Some((bus_id, trace)) = self.trace.as_ref() else {
+            return;
+        };
+        if !trace.is_enabled() {
+            return;
+        }
+        let t_utc_ns = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        trace.record(I2cTransaction {
+            t_utc_ns,
+            bus_id: bus_id.clone(),
+            address,
+            reg,
+            write,
+            data: if result.is_ok() || write {
+                data.to_vec()
+            } else {
+                Vec::new()
+            },
+            duration_us: start.elapsed().as_micros() as u32,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
     }
 
     pub async fn read_bytes(
         &mut self,
-        address: u8,
+        address: u16,
+        reg: u8,
+        buf: &mut [u8],
+    ) -> Result<(), I2CError> {
+        let start = std::time::Instant::now();
+        let result = self.read_bytes_untraced(address, reg, buf);
+        self.record(address, Some(reg), false, buf, start, &result);
+        self.track(result)
+    }
+
+    fn read_bytes_untraced(
+        &mut self,
+        address: u16,
         reg: u8,
         buf: &mut [u8],
     ) -> Result<(), I2CError> {
-        self.device.set_slave_address(address as u16)?;
+        self.select(address)?;
 
         if buf.len() == 1 {
             // Use SMBus read byte data for single byte reads
-            let byte = self.device.smbus_read_byte_data(reg)?;
+            let byte = self.device.io().smbus_read_byte_data(reg)?;
             buf[0] = byte;
         } else {
             // Use SMBus block read for multi-byte reads
             let temp_buf = self
                 .device
+                .io()
                 .smbus_read_i2c_block_data(reg, buf.len() as u8)?;
             buf.copy_from_slice(&temp_buf);
         }
@@ -62,9 +447,48 @@ impl I2CBus {
         Ok(())
     }
 
-    pub async fn write_byte(&mut self, address: u8, reg: u8, byte: u8) -> Result<(), I2CError> {
-        self.device.set_slave_address(address as u16)?;
-        self.device.smbus_write_byte_data(reg, byte)
+    pub async fn write_byte(&mut self, address: u16, reg: u8, byte: u8) -> Result<(), I2CError> {
+        let start = std::time::Instant::now();
+        let result = self
+            .select(address)
+            .and_then(|_| self.device.io().smbus_write_byte_data(reg, byte));
+        self.record(address, Some(reg), true, &[byte], start, &result);
+        self.track(result)
+    }
+
+    /// Plain I2C write without a register byte (for command-based devices)
+    pub async fn write(&mut self, address: u16, data: &[u8]) -> Result<(), I2CError> {
+        let start = std::time::Instant::now();
+        let result = self
+            .select(address)
+            .and_then(|_| self.device.io().write(data));
+        self.record(address, None, true, data, start, &result);
+        self.track(result)
+    }
+
+    /// Plain I2C read without a register byte (for command-based devices)
+    pub async fn read(&mut self, address: u16, buf: &mut [u8]) -> Result<(), I2CError> {
+        let result = self.read_untracked(address, buf);
+        self.track(result)
+    }
+
+    fn read_untracked(&mut self, address: u16, buf: &mut [u8]) -> Result<(), I2CError> {
+        let start = std::time::Instant::now();
+        let result = self
+            .select(address)
+            .and_then(|_| self.device.io().read(buf));
+        self.record(address, None, false, buf, start, &result);
+        result
+    }
+
+    /// Whether a device acknowledges a one-byte read at `address`
+    ///
+    /// Reads rather than zero-length writes, like `i2cdetect -r`, since a quick write
+    /// can corrupt some EEPROMs. Scans NAK on most addresses, so probes don't count
+    /// towards bus recovery.
+    pub async fn probe(&mut self, address: u16) -> bool {
+        let mut buf = [0u8; 1];
+        self.read_untracked(address, &mut buf).is_ok()
     }
 }
 
@@ -77,16 +501,147 @@ impl I2CBus {
         ))
     }
 
+    pub fn set_trace(&mut self, _bus_id: &str, _trace: Arc<I2cTrace>) {}
+
+    pub fn set_error_counter(&mut self, _errors: Arc<AtomicU64>) {}
+
+    pub fn set_recovery(&mut self, _config: BusRecovery, _count: Arc<AtomicU64>) {}
+
     pub async fn read_bytes(
         &mut self,
-        _address: u8,
+        _address: u16,
         _reg: u8,
         _buf: &mut [u8],
     ) -> Result<(), I2CError> {
         Err(I2CError("I2C is only supported on Linux".to_string()))
     }
 
-    pub async fn write_byte(&mut self, _address: u8, _reg: u8, _byte: u8) -> Result<(), I2CError> {
+    pub async fn write_byte(&mut self, _address: u16, _reg: u8, _byte: u8) -> Result<(), I2CError> {
+        Err(I2CError("I2C is only supported on Linux".to_string()))
+    }
+
+    pub async fn write(&mut self, _address: u16, _data: &[u8]) -> Result<(), I2CError> {
+        Err(I2CError("I2C is only supported on Linux".to_string()))
+    }
+
+    pub async fn read(&mut self, _address: u16, _buf: &mut [u8]) -> Result<(), I2CError> {
         Err(I2CError("I2C is only supported on Linux".to_string()))
     }
+
+    pub async fn probe(&mut self, _address: u16) -> bool {
+        false
+    }
+}
+
+impl I2CBus {
+    /// Poll `reg` until all bits of `mask` are set, for at most `timeout`
+    ///
+    /// Returns false on timeout. Read errors count as "not ready", since some devices
+    /// NACK while they reset.
+    pub async fn wait_for_bits(
+        &mut self,
+        address: u16,
+        reg: u8,
+        mask: u8,
+        timeout: std::time::Duration,
+    ) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut buf = [0u8; 1];
+        loop {
+            if self.read_bytes(address, reg, &mut buf).await.is_ok() && buf[0] & mask == mask {
+                return true;
+            }
+            if std::time::Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_ring_keeps_latest() {
+        let trace = I2cTrace::new(2);
+        for reg in 0..3u8 {
+            trace.record(I2cTransaction {
+                t_utc_ns: reg as u64,
+                bus_id: "i2c1".to_string(),
+                address: 0x68,
+                reg: Some(reg),
+                write: false,
+                data: vec![0xAB],
+                duration_us: 120,
+                error: None,
+            });
+        }
+        let recorded = trace.snapshot();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].reg, Some(1));
+        assert_eq!(
+            recorded[1].to_string(),
+            "2 i2c1 0x68 R reg=0x02 [AB] 120us ok"
+        );
+        assert_eq!(Address(0x3A5).to_string(), "0x3A5");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_stuck_bus_recovery() {
+        let mock = crate::bus::i2c_mock::MockI2c::new();
+        mock.set_registers(0x68, 0x00, &[0x47]);
+        let mut bus = I2CBus::mock(mock.clone());
+        let count = Arc::new(AtomicU64::new(0));
+        let recovery = BusRecovery { error_threshold: 3 };
+        bus.set_recovery(recovery, count.clone());
+        let mut buf = [0u8; 1];
+
+        // Failures interrupted by a success don't add up
+        mock.set_failing(true);
+        for _ in 0..2 {
+            assert!(bus.read_bytes(0x68, 0x00, &mut buf).await.is_err());
+        }
+        mock.set_failing(false);
+        bus.read_bytes(0x68, 0x00, &mut buf).await.unwrap();
+        mock.set_failing(true);
+        for _ in 0..2 {
+            assert!(bus.read_bytes(0x68, 0x00, &mut buf).await.is_err());
+        }
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+
+        // Probes of empty addresses don't count either
+        for address in 0x08..0x10 {
+            assert!(!bus.probe(address).await);
+        }
+        assert!(bus.read_bytes(0x68, 0x00, &mut buf).await.is_err());
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+
+        // Still stuck: the next recovery waits out the backoff
+        for _ in 0..3 {
+            assert!(bus.read_bytes(0x68, 0x00, &mut buf).await.is_err());
+        }
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        tokio::time::sleep(RECOVERY_BACKOFF).await;
+        assert!(bus.read_bytes(0x68, 0x00, &mut buf).await.is_err());
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+
+        mock.set_failing(false);
+        bus.read_bytes(0x68, 0x00, &mut buf).await.unwrap();
+        assert_eq!(buf[0], 0x47);
+    }
+
+    #[test]
+    fn test_scan_plan_skips_no_probe() {
+        let (probe, skipped) = scan_plan(&[], &[0x50, 0x51]);
+        assert_eq!(probe.len(), SCAN_RANGE.count() - 2);
+        assert!(!probe.contains(&0x50));
+        assert_eq!(skipped, [0x50, 0x51]);
+
+        let (probe, skipped) = scan_plan(&[0x3A5, 0x50, 0x3A5], &[0x50]);
+        assert_eq!(probe, [0x3A5]);
+        assert_eq!(skipped, [0x50]);
+    }
 }
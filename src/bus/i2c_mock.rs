@@ -0,0 +1,172 @@
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CError;
+use nix::errno::Errno;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct State {
+    /// Address selected by the bus
+    address: u16,
+    /// Register file of each register-based chip
+    registers: HashMap<u16, [u8; 256]>,
+    /// Replies to plain reads following a command (plain write), by chip and command,
+    /// taken in turn and starting over after the last
+    replies: HashMap<(u16, Vec<u8>), VecDeque<Vec<u8>>>,
+    last_command: HashMap<u16, Vec<u8>>,
+    failing: bool,
+}
+
+impl State {
+    fn present(&self) -> bool {
+        self.registers.contains_key(&self.address)
+            || self
+                .replies
+                .keys()
+                .any(|(address, _)| *address == self.address)
+    }
+
+    /// Error of a transaction, if it fails: the bus is failing or nothing acknowledges
+    fn check(&self) -> Result<(), LinuxI2CError> {
+        if self.failing {
+            return Err(LinuxI2CError::Errno(Errno::EIO as i32));
+        }
+        if !self.present() {
+            return Err(LinuxI2CError::Errno(Errno::ENXIO as i32));
+        }
+        Ok(())
+    }
+
+    fn registers(&mut self) -> &mut [u8; 256] {
+        self.registers.entry(self.address).or_insert([0; 256])
+    }
+}
+
+/// Simulated chips behind an I2CBus, for driver tests: register-based chips have a
+/// register file with auto-incrementing reads, command-based chips answer plain reads
+/// with replies scripted per command
+#[derive(Clone, Default)]
+pub struct MockI2c {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockI2c {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fill registers of the chip at `address` from `start`, adding the chip if needed
+    pub fn set_registers(&self, address: u16, start: u8, values: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        let registers = state.registers.entry(address).or_insert([0; 256]);
+        for (i, value) in values.iter().enumerate() {
+            registers[(start as usize + i) % 256] = *value;
+        }
+    }
+
+    /// Replies to plain reads after `command` is written to the chip at `address`, in turn
+    /// and starting over after the last
+    pub fn set_replies(&self, address: u16, command: &[u8], replies: &[&[u8]]) {
+        self.state.lock().unwrap().replies.insert(
+            (address, command.to_vec()),
+            replies.iter().map(|r| r.to_vec()).collect(),
+        );
+    }
+
+    /// Make every transaction fail, as after a brown-out or a loose connector
+    pub fn set_failing(&self, failing: bool) {
+        self.state.lock().unwrap().failing = failing;
+    }
+
+    pub(crate) fn select(&self, address: u16) {
+        self.state.lock().unwrap().address = address;
+    }
+}
+
+impl I2CDevice for MockI2c {
+    type Error = LinuxI2CError;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        let address = state.address;
+        let command = state
+            .last_command
+            .get(&address)
+            .cloned()
+            .unwrap_or_default();
+        let replies = state
+            .replies
+            .get_mut(&(address, command))
+            .ok_or(LinuxI2CError::Errno(Errno::EIO as i32))?;
+        let reply = replies
+            .pop_front()
+            .ok_or(LinuxI2CError::Errno(Errno::EIO as i32))?;
+        replies.push_back(reply.clone());
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = reply.get(i).copied().unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        let address = state.address;
+        state.last_command.insert(address, data.to_vec());
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, _bit: bool) -> Result<(), Self::Error> {
+        self.state.lock().unwrap().check()
+    }
+
+    fn smbus_read_byte_data(&mut self, register: u8) -> Result<u8, Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        Ok(state.registers()[register as usize])
+    }
+
+    fn smbus_write_byte_data(&mut self, register: u8, value: u8) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        state.registers()[register as usize] = value;
+        Ok(())
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.smbus_read_i2c_block_data(register, 32)
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        let registers = state.registers();
+        Ok((0..len as usize)
+            .map(|i| registers[(register as usize + i) % 256])
+            .collect())
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.smbus_write_i2c_block_data(register, values)
+    }
+
+    fn smbus_write_i2c_block_data(
+        &mut self,
+        register: u8,
+        values: &[u8],
+    ) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        state.check()?;
+        let registers = state.registers();
+        for (i, value) in values.iter().enumerate() {
+            registers[(register as usize + i) % 256] = *value;
+        }
+        Ok(())
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.smbus_write_i2c_block_data(register, values)?;
+        self.smbus_read_i2c_block_data(register, values.len() as u8)
+    }
+}
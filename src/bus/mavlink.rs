@@ -1,9 +1,21 @@
 use super::serial::SerialBus;
+use super::tcp::TcpEndpoint;
+use super::udp::UdpEndpoint;
+use crate::mavlink_log::{CaptureSlot, RawRecorder};
 use mavlink;
-use std::collections::HashSet;
+use mavlink::async_peek_reader::AsyncPeekReader;
+use mavlink::common::{MavAutopilot, MavMessage};
+use mavlink::error::MessageReadError;
+use mavlink::{MavHeader, MavlinkVersion, Message};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf, WriteHalf};
 use tokio::sync::broadcast;
 use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
 use tracing::{debug, info, trace, warn};
 
 /// Detected sensor types from MAVLink stream
@@ -19,20 +31,16 @@ use tracing::{debug, info, trace, warn};
 ///   - Add MavlinkSensorType variants for new sensor types
 ///   - Add message conversion functions (convert_*_to_frame)
 ///   - Add match arms in message loop (line 89+) to handle messages
-///   - Add gRPC message types in messages.rs if needed (e.g., Attitude)
+///   - Add gRPC message types in messages.rs if needed
 ///
 /// Priority message types to add:
-/// - ATTITUDE: Basic euler angles (roll/pitch/yaw) + rates - simpler than quaternions, widely used
 /// - RAW_IMU: Raw sensor readings (if FC sends them separately from SCALED/HIGHRES)
 /// - ALTITUDE: Altitude data with different sources (barometric, GPS, etc.)
-/// - VFR_HUD: Airspeed, groundspeed, heading, climb rate, throttle
-/// - LOCAL_POSITION_NED: Local position in NED frame (for navigation)
-/// - VIBRATION: IMU vibration levels (useful for diagnosing mechanical issues)
-/// - GPS_RAW_INT: Raw GPS data (position, satellites, fix type)
-/// - GLOBAL_POSITION_INT: Fused global position estimate
 ///
 /// Currently supported:
-/// ✅ SCALED_IMU/2/3, HIGHRES_IMU, SCALED_PRESSURE, ATTITUDE_QUATERNION
+/// ✅ SCALED_IMU/2/3, HIGHRES_IMU, SCALED_PRESSURE, ATTITUDE_QUATERNION, ATTITUDE,
+///    GPS_RAW_INT, GPS2_RAW, GLOBAL_POSITION_INT, LOCAL_POSITION_NED, VFR_HUD, VIBRATION,
+///    DISTANCE_SENSOR, RC_CHANNELS, ESC_STATUS (+ ESC_INFO), GIMBAL_DEVICE_ATTITUDE_STATUS
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DetectedSensor {
     ScaledImu,
@@ -41,43 +49,251 @@ pub enum DetectedSensor {
     HighresImu,
     ScaledPressure,
     AttitudeQuaternion,
+    Attitude,
+    GpsRawInt,
+    Gps2Raw,
+    GlobalPositionInt,
+    LocalPositionNed,
+    VfrHud,
+    Vibration,
+    /// DISTANCE_SENSOR, one per rangefinder `id`
+    DistanceSensor {
+        id: u8,
+    },
+    RcChannels,
+    EscStatus,
+    /// GIMBAL_DEVICE_ATTITUDE_STATUS, one per `gimbal_device_id`
+    GimbalDeviceAttitude {
+        id: u8,
+    },
+}
+
+/// MAVLink system id used for messages the hub sends to the flight controller
+const HUB_SYSTEM_ID: u8 = 255;
+/// MAV_COMP_ID_ONBOARD_COMPUTER
+const HUB_COMPONENT_ID: u8 = 191;
+
+/// Largest payload of a single GPS_RTCM_DATA message
+const RTCM_FRAGMENT_LEN: usize = 180;
+/// GPS_RTCM_DATA can carry one RTCM frame in at most 4 fragments
+const RTCM_MAX_FRAGMENTS: usize = 4;
+
+/// A link counts as lost when the flight controller's heartbeat (1 Hz) is missing this long
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval of the hub's own heartbeat on links whose router only learns of it by traffic
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Byte stream a MAVLink connection talks over (serial port, TCP or UDP socket)
+trait LinkIo: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> LinkIo for T {}
+
+type LinkStream = Box<dyn LinkIo>;
+
+/// Write side of the current link, replaced on every reconnect
+type LinkWriter = Arc<Mutex<Option<WriteHalf<LinkStream>>>>;
+
+/// Recorders the receive loop feeds
+#[derive(Clone, Default)]
+struct Taps {
+    /// Every parsed message (None while not recording)
+    frames: Arc<std::sync::RwLock<Option<RawRecorder>>>,
+    /// Bytes as received, before parsing
+    bytes: CaptureSlot,
+}
+
+/// Read half of a link that copies everything read into the raw byte capture
+struct CaptureReader<R> {
+    inner: R,
+    capture: CaptureSlot,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CaptureReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if buf.filled().len() > before {
+            self.capture.feed(&buf.filled()[before..]);
+        }
+        result
+    }
+}
+
+/// Link event counters, exported on /metrics
+#[derive(Clone, Default)]
+pub struct LinkCounters {
+    /// Frames that failed to parse
+    pub parse_errors: Arc<AtomicU64>,
+    /// Times the link was lost and re-opened
+    pub reconnects: Arc<AtomicU64>,
+}
+
+/// Physical link behind a MAVLink connection, used to (re)open the byte stream
+enum Link {
+    Serial {
+        /// Last known working port path
+        port_path: Arc<Mutex<String>>,
+        /// Whether to re-discover the flight controller on reconnection
+        auto_detect: bool,
+    },
+    Tcp(TcpEndpoint),
+    Udp(UdpEndpoint),
+}
+
+impl Link {
+    /// Try to re-open the link after a disconnect. Returns None if it is not available yet.
+    async fn reopen(&self) -> Option<LinkStream> {
+        match self {
+            Link::Serial {
+                port_path,
+                auto_detect,
+            } => {
+                let path = if *auto_detect {
+                    // Auto-detect mode: scan for flight controller (handles port changes)
+                    info!("[MAVLink] Auto-detecting flight controller...");
+                    match SerialBus::detect_flight_controller().await {
+                        Ok(path) => {
+                            let mut p = port_path.lock().await;
+                            if *p != path {
+                                info!(
+                                    "[MAVLink] Flight controller port changed: {} -> {}",
+                                    *p, path
+                                );
+                            }
+                            *p = path.clone();
+                            path
+                        }
+                        Err(e) => {
+                            warn!("[MAVLink] Auto-detection failed: {}", e);
+                            return None;
+                        }
+                    }
+                } else {
+                    // Manual mode: try to reconnect to same port
+                    let p = port_path.lock().await;
+                    info!("[MAVLink] Attempting to reconnect to {}...", *p);
+                    p.clone()
+                };
+
+                match SerialBus::new(&path) {
+                    Ok(serial) => {
+                        info!("[MAVLink] Reconnected successfully to {}", path);
+                        Some(Box::new(serial.into_stream()))
+                    }
+                    Err(e) => {
+                        warn!("[MAVLink] Reconnection failed: {}", e);
+                        None
+                    }
+                }
+            }
+            Link::Tcp(endpoint) => match endpoint.connect().await {
+                Ok(stream) => {
+                    info!("[MAVLink] Connected to {}", endpoint);
+                    Some(Box::new(stream))
+                }
+                Err(e) => {
+                    warn!("[MAVLink] Connection to {} failed: {}", endpoint, e);
+                    None
+                }
+            },
+            Link::Udp(endpoint) => match endpoint.connect().await {
+                Ok(link) => {
+                    info!("[MAVLink] Opened {}", endpoint);
+                    Some(Box::new(link))
+                }
+                Err(e) => {
+                    warn!("[MAVLink] Opening {} failed: {}", endpoint, e);
+                    None
+                }
+            },
+        }
+    }
 }
 
 /// MAVLink connection wrapper that handles message streaming
 pub struct MavlinkConnection {
     /// Broadcast sender for MAVLink messages (can be cloned for multiple subscribers)
     tx: broadcast::Sender<mavlink::common::MavMessage>,
-    /// Set of detected sensors
-    detected_sensors: Arc<Mutex<HashSet<DetectedSensor>>>,
-    /// Last known working port path (used internally by reconnection logic in receive_loop)
-    #[allow(dead_code)]
-    port_path: Arc<Mutex<String>>,
-    /// Whether this connection was auto-detected (used internally by reconnection logic in receive_loop)
-    #[allow(dead_code)]
-    auto_detect: bool,
+    /// Detected sensors and when each was last heard from
+    detected_sensors: Arc<Mutex<HashMap<DetectedSensor, Instant>>>,
+    /// Write side of the link (None until the first connection is up)
+    writer: LinkWriter,
+    /// Sequence number for messages sent by the hub
+    tx_sequence: Arc<AtomicU8>,
+    /// Sequence id for fragmented GPS_RTCM_DATA (5 bits)
+    rtcm_sequence: AtomicU8,
+    /// Recorders for every received message and byte
+    taps: Taps,
 }
 
 impl MavlinkConnection {
     /// Create a new MAVLink connection from a serial bus
     /// Takes ownership of the SerialBus and starts the message loop
     /// auto_detect: if true, will attempt to re-discover the flight controller on reconnection
-    pub fn new(serial: SerialBus, auto_detect: bool) -> Self {
+    pub fn new(serial: SerialBus, auto_detect: bool, counters: LinkCounters) -> Self {
+        let link = Link::Serial {
+            port_path: Arc::new(Mutex::new(serial.path().to_string())),
+            auto_detect,
+        };
+        Self::spawn(Some(Box::new(serial.into_stream())), link, counters)
+    }
+
+    /// Create a new MAVLink connection over TCP
+    /// The connection is established in the background and re-established on disconnect
+    pub fn new_tcp(endpoint: TcpEndpoint, counters: LinkCounters) -> Self {
+        Self::spawn(None, Link::Tcp(endpoint), counters)
+    }
+
+    /// Create a new MAVLink connection over UDP, e.g. to a mavlink-router or mavp2p endpoint
+    /// In udpout mode the hub sends a heartbeat every second so the router keeps it as a
+    /// client
+    pub fn new_udp(endpoint: UdpEndpoint, counters: LinkCounters) -> Self {
+        let keepalive = endpoint.needs_keepalive();
+        let conn = Self::spawn(None, Link::Udp(endpoint), counters);
+        if keepalive {
+            let writer = conn.writer.clone();
+            let sequence = conn.tx_sequence.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(KEEPALIVE_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    // Not connected until the receive loop has opened the socket
+                    let _ = write_message(&writer, &sequence, &hub_heartbeat()).await;
+                }
+            });
+        }
+        conn
+    }
+
+    /// Start the receive loop on `stream`, or on the first successful `link.reopen()` if None
+    fn spawn(stream: Option<LinkStream>, link: Link, counters: LinkCounters) -> Self {
         // Create a broadcast channel with a reasonable buffer (1000 messages)
         let (tx, _rx) = broadcast::channel(1000);
-        let detected_sensors = Arc::new(Mutex::new(HashSet::new()));
-        let port_path = Arc::new(Mutex::new(serial.path().to_string()));
+        let detected_sensors = Arc::new(Mutex::new(HashMap::new()));
+        let writer: LinkWriter = Arc::new(Mutex::new(None));
+        let taps = Taps::default();
 
         // Spawn the receive loop
         let tx_clone = tx.clone();
         let detected_clone = detected_sensors.clone();
-        let port_path_clone = port_path.clone();
+        let writer_clone = writer.clone();
+        let taps_clone = taps.clone();
         tokio::spawn(async move {
+            let stream = match stream {
+                Some(stream) => stream,
+                None => Self::open_with_backoff(&link).await,
+            };
             Self::receive_loop(
-                serial,
+                stream,
+                link,
                 tx_clone,
                 detected_clone,
-                port_path_clone,
-                auto_detect,
+                writer_clone,
+                counters,
+                taps_clone,
             )
             .await;
         });
@@ -85,36 +301,89 @@ impl MavlinkConnection {
         Self {
             tx,
             detected_sensors,
-            port_path,
-            auto_detect,
+            writer,
+            tx_sequence: Arc::new(AtomicU8::new(0)),
+            rtcm_sequence: AtomicU8::new(0),
+            taps,
+        }
+    }
+
+    /// Record every message received from now on
+    pub fn record_raw(&self, recorder: RawRecorder) {
+        *self.taps.frames.write().unwrap() = Some(recorder);
+    }
+
+    /// Slot through which raw byte captures of this link are started (CaptureMavlink)
+    pub fn capture_slot(&self) -> CaptureSlot {
+        self.taps.bytes.clone()
+    }
+
+    /// Keep trying to open the link with exponential backoff until it succeeds
+    async fn open_with_backoff(link: &Link) -> LinkStream {
+        let mut backoff_ms = 100u64;
+        const MAX_BACKOFF_MS: u64 = 2000;
+        loop {
+            if let Some(stream) = link.reopen().await {
+                return stream;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
+            backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
         }
     }
 
     /// Main receive loop with automatic reconnection support
+    ///
+    /// On an I/O error, or when the flight controller's heartbeat stops, the link is closed
+    /// and re-opened (re-detecting the flight controller in auto-detect mode). The broadcast
+    /// channel outlives the link, so subscribers just see a gap.
     async fn receive_loop(
-        mut serial: SerialBus,
+        mut stream: LinkStream,
+        link: Link,
         tx: broadcast::Sender<mavlink::common::MavMessage>,
-        detected_sensors: Arc<Mutex<HashSet<DetectedSensor>>>,
-        port_path: Arc<Mutex<String>>,
-        auto_detect: bool,
+        detected_sensors: Arc<Mutex<HashMap<DetectedSensor, Instant>>>,
+        writer: LinkWriter,
+        counters: LinkCounters,
+        taps: Taps,
     ) {
         info!("[MAVLink] Starting receive loop...");
-        let mut backoff_ms = 100u64; // Start with 100ms backoff
-        const MAX_BACKOFF_MS: u64 = 2000; // Max 2 second backoff
 
         loop {
-            // Take ownership of the stream and wrap in AsyncPeekReader
-            let stream = serial.into_stream();
-            let mut peek_reader = mavlink::async_peek_reader::AsyncPeekReader::new(stream);
+            // Hand the write side to senders, read from the other half
+            let (reader, write_half) = tokio::io::split(stream);
+            *writer.lock().await = Some(write_half);
 
-            loop {
+            // Wrap the read side in AsyncPeekReader, capturing raw bytes on the way
+            let reader = CaptureReader {
+                inner: reader,
+                capture: taps.bytes.clone(),
+            };
+            let mut peek_reader = mavlink::async_peek_reader::AsyncPeekReader::new(reader);
+            // Armed by the first autopilot heartbeat, so links that carry none never time out
+            let mut last_heartbeat: Option<Instant> = None;
+
+            let lost = loop {
                 // Auto-detect MAVLink v1 (0xFE) or v2 (0xFD) protocol version
-                match mavlink::read_versioned_msg_async::<mavlink::common::MavMessage, _>(
-                    &mut peek_reader,
-                    mavlink::ReadVersion::Any,
-                )
-                .await
-                {
+                let read = read_frame(&mut peek_reader);
+                let result = match last_heartbeat {
+                    Some(at) => match tokio::time::timeout_at(at + HEARTBEAT_TIMEOUT, read).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            break format!(
+                                "Flight controller heartbeat lost (none for {}s)",
+                                HEARTBEAT_TIMEOUT.as_secs()
+                            )
+                        }
+                    },
+                    None => read.await,
+                };
+                // Recorded before parsing, so frames whose payload fails to decode are kept too
+                let result = result.and_then(|frame| {
+                    if let Some(recorder) = taps.frames.read().unwrap().as_ref() {
+                        recorder.record(frame.msg_id, &frame.bytes);
+                    }
+                    frame.parse()
+                });
+                match result {
                     Ok((header, msg)) => {
                         // Successfully parsed a MAVLink message (auto-detected version)
                         trace!(
@@ -158,6 +427,18 @@ impl MavlinkConnection {
                                        att.rollspeed, att.pitchspeed, att.yawspeed);
                                 Some(DetectedSensor::AttitudeQuaternion)
                             }
+                            mavlink::common::MavMessage::ATTITUDE(att) => {
+                                debug!(
+                                    "[MAVLink] ATTITUDE: rpy=({},{},{}), rates=({},{},{})",
+                                    att.roll,
+                                    att.pitch,
+                                    att.yaw,
+                                    att.rollspeed,
+                                    att.pitchspeed,
+                                    att.yawspeed
+                                );
+                                Some(DetectedSensor::Attitude)
+                            }
                             mavlink::common::MavMessage::HIGHRES_IMU(imu) => {
                                 debug!(
                                     "[MAVLink] HIGHRES_IMU: acc=({},{},{}), gyro=({},{},{})",
@@ -165,8 +446,89 @@ impl MavlinkConnection {
                                 );
                                 Some(DetectedSensor::HighresImu)
                             }
-                            mavlink::common::MavMessage::HEARTBEAT(_) => {
+                            mavlink::common::MavMessage::GPS_RAW_INT(gps) => {
+                                debug!(
+                                    "[MAVLink] GPS_RAW_INT: fix={:?}, sats={}, lat={}, lon={}, alt={}",
+                                    gps.fix_type, gps.satellites_visible, gps.lat, gps.lon, gps.alt
+                                );
+                                Some(DetectedSensor::GpsRawInt)
+                            }
+                            mavlink::common::MavMessage::GPS2_RAW(gps) => {
+                                debug!(
+                                    "[MAVLink] GPS2_RAW: fix={:?}, sats={}, yaw={}",
+                                    gps.fix_type, gps.satellites_visible, gps.yaw
+                                );
+                                Some(DetectedSensor::Gps2Raw)
+                            }
+                            mavlink::common::MavMessage::GLOBAL_POSITION_INT(pos) => {
+                                debug!(
+                                    "[MAVLink] GLOBAL_POSITION_INT: lat={}, lon={}, alt={}, rel_alt={}, hdg={}",
+                                    pos.lat, pos.lon, pos.alt, pos.relative_alt, pos.hdg
+                                );
+                                Some(DetectedSensor::GlobalPositionInt)
+                            }
+                            mavlink::common::MavMessage::LOCAL_POSITION_NED(pos) => {
+                                debug!(
+                                    "[MAVLink] LOCAL_POSITION_NED: pos=({},{},{}), vel=({},{},{})",
+                                    pos.x, pos.y, pos.z, pos.vx, pos.vy, pos.vz
+                                );
+                                Some(DetectedSensor::LocalPositionNed)
+                            }
+                            mavlink::common::MavMessage::VFR_HUD(hud) => {
+                                debug!(
+                                    "[MAVLink] VFR_HUD: airspeed={}, groundspeed={}, heading={}, climb={}, throttle={}",
+                                    hud.airspeed, hud.groundspeed, hud.heading, hud.climb, hud.throttle
+                                );
+                                Some(DetectedSensor::VfrHud)
+                            }
+                            mavlink::common::MavMessage::VIBRATION(vib) => {
+                                debug!(
+                                    "[MAVLink] VIBRATION: x={}, y={}, z={}, clip=[{}, {}, {}]",
+                                    vib.vibration_x,
+                                    vib.vibration_y,
+                                    vib.vibration_z,
+                                    vib.clipping_0,
+                                    vib.clipping_1,
+                                    vib.clipping_2
+                                );
+                                Some(DetectedSensor::Vibration)
+                            }
+                            mavlink::common::MavMessage::DISTANCE_SENSOR(dist) => {
+                                debug!(
+                                    "[MAVLink] DISTANCE_SENSOR: id={}, distance={}cm, orientation={:?}",
+                                    dist.id, dist.current_distance, dist.orientation
+                                );
+                                Some(DetectedSensor::DistanceSensor { id: dist.id })
+                            }
+                            mavlink::common::MavMessage::RC_CHANNELS(rc) => {
+                                debug!(
+                                    "[MAVLink] RC_CHANNELS: count={}, rssi={}, ch1-4=[{}, {}, {}, {}]",
+                                    rc.chancount, rc.rssi, rc.chan1_raw, rc.chan2_raw, rc.chan3_raw, rc.chan4_raw
+                                );
+                                Some(DetectedSensor::RcChannels)
+                            }
+                            mavlink::common::MavMessage::ESC_STATUS(esc) => {
+                                debug!(
+                                    "[MAVLink] ESC_STATUS: index={}, rpm={:?}, voltage={:?}, current={:?}",
+                                    esc.index, esc.rpm, esc.voltage, esc.current
+                                );
+                                Some(DetectedSensor::EscStatus)
+                            }
+                            mavlink::common::MavMessage::GIMBAL_DEVICE_ATTITUDE_STATUS(gimbal) => {
+                                debug!(
+                                    "[MAVLink] GIMBAL_DEVICE_ATTITUDE_STATUS: id={}, q={:?}, flags={:?}",
+                                    gimbal.gimbal_device_id, gimbal.q, gimbal.flags
+                                );
+                                Some(DetectedSensor::GimbalDeviceAttitude {
+                                    id: gimbal.gimbal_device_id,
+                                })
+                            }
+                            mavlink::common::MavMessage::HEARTBEAT(heartbeat) => {
                                 trace!("[MAVLink] Heartbeat received");
+                                // GCS and companion heartbeats say nothing about the FC
+                                if heartbeat.autopilot != MavAutopilot::MAV_AUTOPILOT_INVALID {
+                                    last_heartbeat = Some(Instant::now());
+                                }
                                 None
                             }
                             other => {
@@ -176,10 +538,10 @@ impl MavlinkConnection {
                             }
                         };
 
-                        // Track newly detected sensors
+                        // Track detected sensors and refresh their last-seen time
                         if let Some(sensor) = sensor_type {
                             let mut detected = detected_sensors.lock().await;
-                            if detected.insert(sensor) {
+                            if detected.insert(sensor, Instant::now()).is_none() {
                                 info!("[MAVLink] Auto-detected new sensor: {:?}", sensor);
                             }
                         }
@@ -190,90 +552,40 @@ impl MavlinkConnection {
                             Err(_) => trace!("[MAVLink] No active receivers"),
                         }
                     }
-                    Err(e) => {
-                        match e {
-                            mavlink::error::MessageReadError::Io(io_err) => {
-                                // Provide user-friendly error messages for common cases
-                                let error_msg = match io_err.raw_os_error() {
-                                    Some(6) => "Flight controller disconnected (device not configured)",
-                                    Some(5) => "Flight controller disconnected (I/O error)",
-                                    _ => {
-                                        if io_err.kind() == std::io::ErrorKind::BrokenPipe {
-                                            "Flight controller disconnected (broken pipe)"
-                                        } else {
-                                            "Flight controller connection lost"
-                                        }
-                                    }
-                                };
-                                warn!("[MAVLink] {}", error_msg);
-
-                                // Connection lost - attempt to reconnect
-                                info!(
-                                    "[MAVLink] Attempting reconnection in {}ms...",
-                                    backoff_ms
-                                );
-                                tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms))
-                                    .await;
-
-                                // Try to reconnect
-                                let new_port_path = if auto_detect {
-                                    // Auto-detect mode: scan for flight controller (handles port changes)
-                                    info!("[MAVLink] Auto-detecting flight controller...");
-                                    match SerialBus::detect_flight_controller().await {
-                                        Ok(path) => {
-                                            let mut p = port_path.lock().await;
-                                            if *p != path {
-                                                info!(
-                                                    "[MAVLink] Flight controller port changed: {} -> {}",
-                                                    *p, path
-                                                );
-                                            }
-                                            *p = path.clone();
-                                            Some(path)
-                                        }
-                                        Err(e) => {
-                                            warn!("[MAVLink] Auto-detection failed: {}", e);
-                                            None
-                                        }
-                                    }
-                                } else {
-                                    // Manual mode: try to reconnect to same port
-                                    let p = port_path.lock().await;
-                                    info!("[MAVLink] Attempting to reconnect to {}...", *p);
-                                    Some(p.clone())
-                                };
-
-                                if let Some(path) = new_port_path {
-                                    match SerialBus::new(&path) {
-                                        Ok(new_serial) => {
-                                            info!("[MAVLink] Reconnected successfully to {}", path);
-                                            serial = new_serial;
-                                            backoff_ms = 100; // Reset backoff on success
-                                            break; // Break inner loop to recreate peek_reader
-                                        }
-                                        Err(e) => {
-                                            warn!("[MAVLink] Reconnection failed: {}", e);
-                                            // Increase backoff exponentially
-                                            backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
-                                        }
-                                    }
+                    Err(mavlink::error::MessageReadError::Io(io_err)) => {
+                        // Provide user-friendly error messages for common cases
+                        let error_msg = match io_err.raw_os_error() {
+                            Some(6) => "Flight controller disconnected (device not configured)",
+                            Some(5) => "Flight controller disconnected (I/O error)",
+                            _ => {
+                                if io_err.kind() == std::io::ErrorKind::BrokenPipe {
+                                    "Flight controller disconnected (broken pipe)"
                                 } else {
-                                    // No port found, increase backoff
-                                    backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
+                                    "Flight controller connection lost"
                                 }
-
-                                continue;
-                            }
-                            mavlink::error::MessageReadError::Parse(parse_err) => {
-                                warn!("[MAVLink] Parse error (skipping): {:?}", parse_err);
                             }
-                        }
+                        };
+                        break error_msg.to_string();
+                    }
+                    Err(mavlink::error::MessageReadError::Parse(parse_err)) => {
+                        counters.parse_errors.fetch_add(1, Ordering::Relaxed);
+                        warn!("[MAVLink] Parse error (skipping): {:?}", parse_err);
                     }
                 }
 
                 // Small yield to prevent tight loop
                 tokio::task::yield_now().await;
-            }
+            };
+            warn!("[MAVLink] {}", lost);
+
+            // Close the port before reopening it: a USB flight controller that is replugged
+            // while its old device is still open comes back under another name
+            drop(peek_reader);
+            writer.lock().await.take();
+
+            stream = Self::open_with_backoff(&link).await;
+            counters.reconnects.fetch_add(1, Ordering::Relaxed);
+            info!("[MAVLink] Link restored, resuming broadcast");
         }
     }
 
@@ -282,9 +594,255 @@ impl MavlinkConnection {
         self.tx.subscribe()
     }
 
+    /// Send a message to the flight controller
+    pub async fn send(&self, msg: &mavlink::common::MavMessage) -> std::io::Result<()> {
+        write_message(&self.writer, &self.tx_sequence, msg).await
+    }
+
+    /// Forward one RTCM frame to the flight controller as GPS_RTCM_DATA
+    /// Frames longer than 180 bytes are split into up to 4 fragments
+    pub async fn inject_rtcm(&self, frame: &[u8]) -> std::io::Result<()> {
+        let fragments = frame.len().div_ceil(RTCM_FRAGMENT_LEN);
+        if fragments > RTCM_MAX_FRAGMENTS {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "RTCM frame of {} bytes exceeds {} bytes",
+                    frame.len(),
+                    RTCM_FRAGMENT_LEN * RTCM_MAX_FRAGMENTS
+                ),
+            ));
+        }
+
+        let sequence = self.rtcm_sequence.fetch_add(1, Ordering::Relaxed) & 0x1F;
+        for (index, chunk) in frame.chunks(RTCM_FRAGMENT_LEN).enumerate() {
+            // bit 0: fragmented, bits 1-2: fragment id, bits 3-7: sequence id
+            let mut flags = (sequence << 3) | ((index as u8) << 1);
+            if fragments > 1 {
+                flags |= 1;
+            }
+            let mut data = [0u8; RTCM_FRAGMENT_LEN];
+            data[..chunk.len()].copy_from_slice(chunk);
+            let msg =
+                mavlink::common::MavMessage::GPS_RTCM_DATA(mavlink::common::GPS_RTCM_DATA_DATA {
+                    flags,
+                    len: chunk.len() as u8,
+                    data,
+                });
+            self.send(&msg).await?;
+        }
+        Ok(())
+    }
+
     /// Get the list of detected sensors
     pub async fn get_detected_sensors(&self) -> Vec<DetectedSensor> {
         let detected = self.detected_sensors.lock().await;
-        detected.iter().copied().collect()
+        detected.keys().copied().collect()
+    }
+
+    /// Get the detected sensors that have sent data within `max_age`
+    pub async fn get_active_sensors(&self, max_age: Duration) -> Vec<DetectedSensor> {
+        let detected = self.detected_sensors.lock().await;
+        detected
+            .iter()
+            .filter(|(_, last_seen)| last_seen.elapsed() <= max_age)
+            .map(|(sensor, _)| *sensor)
+            .collect()
+    }
+}
+
+/// A frame with a valid checksum, as received and not yet parsed
+struct RawFrame {
+    version: MavlinkVersion,
+    header: MavHeader,
+    msg_id: u32,
+    payload: Vec<u8>,
+    bytes: Vec<u8>,
+}
+
+impl RawFrame {
+    fn parse(self) -> Result<(MavHeader, MavMessage), MessageReadError> {
+        let msg = MavMessage::parse(self.version, self.msg_id, &self.payload)?;
+        Ok((self.header, msg))
+    }
+}
+
+/// Next valid frame of either MAVLink version
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut AsyncPeekReader<R>,
+) -> Result<RawFrame, MessageReadError> {
+    loop {
+        match reader.peek_exact(1).await?[0] {
+            mavlink::MAV_STX => {
+                let raw = mavlink::read_v1_raw_message_async::<MavMessage, _>(reader).await?;
+                return Ok(RawFrame {
+                    version: MavlinkVersion::V1,
+                    header: MavHeader {
+                        system_id: raw.system_id(),
+                        component_id: raw.component_id(),
+                        sequence: raw.sequence(),
+                    },
+                    msg_id: raw.message_id() as u32,
+                    payload: raw.payload().to_vec(),
+                    bytes: raw.raw_bytes().to_vec(),
+                });
+            }
+            mavlink::MAV_STX_V2 => {
+                let raw = mavlink::read_v2_raw_message_async::<MavMessage, _>(reader).await?;
+                return Ok(RawFrame {
+                    version: MavlinkVersion::V2,
+                    header: MavHeader {
+                        system_id: raw.system_id(),
+                        component_id: raw.component_id(),
+                        sequence: raw.sequence(),
+                    },
+                    msg_id: raw.message_id(),
+                    payload: raw.payload().to_vec(),
+                    bytes: raw.raw_bytes().to_vec(),
+                });
+            }
+            _ => {
+                reader.consume(1);
+            }
+        }
+    }
+}
+
+/// Frame `msg` as sent by the hub and write it to the current link
+async fn write_message(
+    writer: &LinkWriter,
+    sequence: &AtomicU8,
+    msg: &mavlink::common::MavMessage,
+) -> std::io::Result<()> {
+    let header = mavlink::MavHeader {
+        system_id: HUB_SYSTEM_ID,
+        component_id: HUB_COMPONENT_ID,
+        sequence: sequence.fetch_add(1, Ordering::Relaxed),
+    };
+    let mut buf = Vec::new();
+    mavlink::write_v2_msg(&mut buf, header, msg)
+        .map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+
+    let mut writer = writer.lock().await;
+    let writer = writer.as_mut().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotConnected, "MAVLink link not open")
+    })?;
+    writer.write_all(&buf).await?;
+    writer.flush().await
+}
+
+/// HEARTBEAT announcing the hub as an onboard computer (not an autopilot)
+fn hub_heartbeat() -> mavlink::common::MavMessage {
+    mavlink::common::MavMessage::HEARTBEAT(mavlink::common::HEARTBEAT_DATA {
+        custom_mode: 0,
+        mavtype: mavlink::common::MavType::MAV_TYPE_ONBOARD_CONTROLLER,
+        autopilot: MavAutopilot::MAV_AUTOPILOT_INVALID,
+        base_mode: mavlink::common::MavModeFlag::empty(),
+        system_status: mavlink::common::MavState::MAV_STATE_ACTIVE,
+        mavlink_version: 3,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::common::{MavMessage, HEARTBEAT_DATA, VFR_HUD_DATA};
+    use tokio::net::TcpListener;
+
+    fn frame(msg: &MavMessage) -> Vec<u8> {
+        let mut buf = Vec::new();
+        mavlink::write_v2_msg(&mut buf, mavlink::MavHeader::default(), msg).unwrap();
+        buf
+    }
+
+    async fn next_vfr_hud(rx: &mut broadcast::Receiver<MavMessage>) -> VFR_HUD_DATA {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+                Ok(Ok(MavMessage::VFR_HUD(hud))) => return hud,
+                Ok(Ok(_)) => continue,
+                other => panic!("no VFR_HUD received: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_survive_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = TcpEndpoint::Client(listener.local_addr().unwrap().to_string());
+        let counters = LinkCounters::default();
+        let conn = MavlinkConnection::new_tcp(endpoint, counters.clone());
+        let mut rx = conn.subscribe();
+
+        // The peer goes away after one message
+        let (mut peer, _) = listener.accept().await.unwrap();
+        let hud = MavMessage::VFR_HUD(VFR_HUD_DATA {
+            airspeed: 12.0,
+            ..Default::default()
+        });
+        peer.write_all(&frame(&hud)).await.unwrap();
+        assert_eq!(next_vfr_hud(&mut rx).await.airspeed, 12.0);
+        drop(peer);
+
+        // The hub dials again and the same subscription carries on
+        let (mut peer, _) = listener.accept().await.unwrap();
+        let hud = MavMessage::VFR_HUD(VFR_HUD_DATA {
+            airspeed: 13.0,
+            ..Default::default()
+        });
+        peer.write_all(&frame(&hud)).await.unwrap();
+        assert_eq!(next_vfr_hud(&mut rx).await.airspeed, 13.0);
+        assert_eq!(counters.reconnects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_loss_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = TcpEndpoint::Client(listener.local_addr().unwrap().to_string());
+        let counters = LinkCounters::default();
+        let conn = MavlinkConnection::new_tcp(endpoint, counters.clone());
+        let mut rx = conn.subscribe();
+
+        // A flight controller that goes silent after one heartbeat, keeping the socket open
+        let (mut silent, _) = listener.accept().await.unwrap();
+        let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            autopilot: MavAutopilot::MAV_AUTOPILOT_PX4,
+            ..Default::default()
+        });
+        silent.write_all(&frame(&heartbeat)).await.unwrap();
+
+        let (mut peer, _) = listener.accept().await.unwrap();
+        let hud = MavMessage::VFR_HUD(VFR_HUD_DATA::default());
+        peer.write_all(&frame(&hud)).await.unwrap();
+        next_vfr_hud(&mut rx).await;
+        assert_eq!(counters.reconnects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_udp_router_server_endpoint() {
+        // A router in server mode only sends to clients it has heard from
+        let router = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = UdpEndpoint::Send(router.local_addr().unwrap().to_string());
+        let conn = MavlinkConnection::new_udp(endpoint, LinkCounters::default());
+        let mut rx = conn.subscribe();
+
+        let mut buf = [0u8; 512];
+        let (n, hub) = tokio::time::timeout(Duration::from_secs(5), router.recv_from(&mut buf))
+            .await
+            .expect("no keepalive heartbeat from the hub")
+            .unwrap();
+        let mut reader = mavlink::peek_reader::PeekReader::new(&buf[..n]);
+        let (header, msg) =
+            mavlink::read_v2_msg::<MavMessage, _>(&mut reader).expect("not a MAVLink frame");
+        assert_eq!(header.system_id, HUB_SYSTEM_ID);
+        assert!(
+            matches!(msg, MavMessage::HEARTBEAT(h) if h.autopilot == MavAutopilot::MAV_AUTOPILOT_INVALID)
+        );
+
+        let hud = MavMessage::VFR_HUD(VFR_HUD_DATA {
+            airspeed: 14.0,
+            ..Default::default()
+        });
+        router.send_to(&frame(&hud), hub).await.unwrap();
+        assert_eq!(next_vfr_hud(&mut rx).await.airspeed, 14.0);
     }
 }
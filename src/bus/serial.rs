@@ -1,14 +1,106 @@
+use super::gpio::OutputLine;
 use mavlink::common::MavAutopilot;
 use std::io;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_serial::{SerialPortBuilderExt, SerialStream};
 use tracing::{debug, info, warn};
 
+#[cfg(target_os = "linux")]
+mod ioctl {
+    /// struct serial_rs485 from linux/serial.h
+    #[repr(C)]
+    pub struct SerialRs485 {
+        pub flags: u32,
+        pub delay_rts_before_send: u32,
+        pub delay_rts_after_send: u32,
+        pub padding: [u32; 5],
+    }
+
+    pub const SER_RS485_ENABLED: u32 = 1 << 0;
+    pub const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+
+    // TIOCSRS485 from asm-generic/ioctls.h (x86, ARM, RISC-V)
+    nix::ioctl_write_ptr_bad!(set_rs485, 0x542F, SerialRs485);
+}
+
+/// Half-duplex RS-485 on a UART bus, set per bus in `buses.toml`
+///
+/// The transceiver's driver is enabled only while the hub transmits, either by the kernel
+/// (its rs485 mode raises RTS around each write) or by the hub through a GPIO line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rs485Config {
+    /// GPIO line wired to the transceiver's DE (and RE) pin; None uses the kernel's rs485
+    /// mode on RTS
+    pub de_gpio: Option<u32>,
+    /// GPIO chip of `de_gpio` (default "gpiochip0")
+    pub de_chip: Option<String>,
+    /// Driver enabled this long before the first bit is sent
+    pub delay_before: Duration,
+    /// Driver kept enabled this long after the last bit, before the bus is released
+    pub delay_after: Duration,
+}
+
+/// Time the UART needs to shift out `len` bytes (8N1: ten bits per byte)
+fn transmit_time(len: usize, baud: u32) -> Duration {
+    Duration::from_micros(len as u64 * 10_000_000 / u64::from(baud.max(1)))
+}
+
+/// Kernel rs485 delays are whole milliseconds; round up so the turnaround is never shorter
+fn kernel_delay_ms(delay: Duration) -> u32 {
+    delay.as_micros().div_ceil(1000) as u32
+}
+
+/// Block the calling thread until `deadline`, to within microseconds
+///
+/// tokio timers have millisecond granularity, longer than the gap before a sensor's reply
+/// at common baud rates, so the DE turnaround is timed on a blocking thread instead.
+fn wait_until(deadline: Instant) {
+    // Thread sleeps overshoot as well: sleep through most of the wait, spin the rest
+    const SPIN: Duration = Duration::from_micros(200);
+    let sleep = deadline
+        .checked_duration_since(Instant::now())
+        .and_then(|left| left.checked_sub(SPIN));
+    if let Some(sleep) = sleep {
+        std::thread::sleep(sleep);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// RS-485 driver enabled for as long as it is held, disabled again even if the write
+/// future is dropped halfway, or every other node on the bus is shouted down
+struct Transmitting(Arc<OutputLine>);
+
+impl Transmitting {
+    fn start(de: &Arc<OutputLine>) -> io::Result<Self> {
+        de.set(true)?;
+        Ok(Self(de.clone()))
+    }
+}
+
+impl Drop for Transmitting {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.set(false) {
+            warn!(
+                "[serial] Failed to release RS-485 DE {}: {}",
+                self.0.name, e
+            );
+        }
+    }
+}
+
 /// Serial port wrapper for async communication
 pub struct SerialBus {
     port: SerialStream,
     /// Port path - useful for logging, error messages, and reconnection logic
     path: String,
+    baud_rate: u32,
+    rs485: Option<Rs485Config>,
+    /// DE pin when the hub drives it, held low (receive) while idle
+    de: Option<Arc<OutputLine>>,
 }
 
 impl SerialBus {
@@ -25,9 +117,56 @@ impl SerialBus {
         Ok(Self {
             port,
             path: path.to_string(),
+            baud_rate,
+            rs485: None,
+            de: None,
         })
     }
 
+    /// Open a half-duplex RS-485 port, leaving the transceiver in receive mode
+    pub fn new_rs485(path: &str, baud_rate: u32, config: Rs485Config) -> io::Result<Self> {
+        let mut bus = Self::new_with_baud(path, baud_rate)?;
+        match config.de_gpio {
+            Some(line) => {
+                let chip = config.de_chip.as_deref().unwrap_or("gpiochip0");
+                let de = OutputLine::open(chip, line, "sensorhub-rs485", false)?;
+                bus.de = Some(Arc::new(de));
+            }
+            None => bus.enable_kernel_rs485(&config)?,
+        }
+        bus.rs485 = Some(config);
+        Ok(bus)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn enable_kernel_rs485(&self, config: &Rs485Config) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        let rs485 = ioctl::SerialRs485 {
+            flags: ioctl::SER_RS485_ENABLED | ioctl::SER_RS485_RTS_ON_SEND,
+            delay_rts_before_send: kernel_delay_ms(config.delay_before),
+            delay_rts_after_send: kernel_delay_ms(config.delay_after),
+            padding: [0; 5],
+        };
+        // SAFETY: the fd is an open tty and `rs485` outlives the call
+        unsafe { ioctl::set_rs485(self.port.as_raw_fd(), &rs485) }
+            .map(|_| ())
+            .map_err(|e| {
+                io::Error::other(format!(
+                    "{}: kernel rs485 mode not supported ({}), set rs485_de_gpio instead",
+                    self.path, e
+                ))
+            })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn enable_kernel_rs485(&self, _config: &Rs485Config) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "kernel rs485 mode is Linux-only, set rs485_de_gpio instead",
+        ))
+    }
+
     /// Get the port path (useful for logging, debugging, and multi-machine testing)
     pub fn path(&self) -> &str {
         &self.path
@@ -38,6 +177,35 @@ impl SerialBus {
         self.port
     }
 
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf).await
+    }
+
+    /// Send `bytes`, enabling the RS-485 driver around them when the hub drives DE itself
+    pub async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let (Some(de), Some(config)) = (&self.de, &self.rs485) else {
+            return self.port.write_all(bytes).await;
+        };
+        let transmitting = Transmitting::start(de)?;
+        if !config.delay_before.is_zero() {
+            let send_at = Instant::now() + config.delay_before;
+            tokio::task::spawn_blocking(move || wait_until(send_at))
+                .await
+                .map_err(io::Error::other)?;
+        }
+        self.port.write_all(bytes).await?;
+        // write_all returns once the bytes are queued: release DE right after the last stop
+        // bit, from the blocking thread, which does so even if this future is dropped
+        let release_at =
+            Instant::now() + transmit_time(bytes.len(), self.baud_rate) + config.delay_after;
+        tokio::task::spawn_blocking(move || {
+            wait_until(release_at);
+            drop(transmitting);
+        })
+        .await
+        .map_err(io::Error::other)
+    }
+
     /// Auto-detect flight controller(s) by probing serial ports in parallel for MAVLink HEARTBEAT messages
     /// Returns the path of the first device that responds with a valid flight controller heartbeat
     ///
@@ -222,3 +390,26 @@ impl SerialBus {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rs485_timing() {
+        // 8 bytes of a Modbus request at 9600 baud: 80 bits
+        assert_eq!(transmit_time(8, 9600), Duration::from_micros(8333));
+        assert_eq!(transmit_time(1, 115200), Duration::from_micros(86));
+        assert_eq!(kernel_delay_ms(Duration::ZERO), 0);
+        assert_eq!(kernel_delay_ms(Duration::from_micros(1)), 1);
+        assert_eq!(kernel_delay_ms(Duration::from_micros(2000)), 2);
+
+        // Never early, and well under a tokio timer tick late on an idle machine
+        let start = Instant::now();
+        wait_until(start + Duration::from_micros(300));
+        let waited = start.elapsed();
+        assert!(waited >= Duration::from_micros(300), "{:?}", waited);
+        assert!(waited < Duration::from_millis(20), "{:?}", waited);
+        wait_until(start);
+    }
+}
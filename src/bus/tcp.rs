@@ -0,0 +1,153 @@
+use std::io;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::info;
+
+/// MAVLink-over-TCP endpoint
+///
+/// Parsed from bus paths of the form:
+///   - `tcpout://host:port` - connect out to a remote listener (SITL, mavlink-router)
+///   - `tcpin://:port` or `tcpin://addr:port` - listen locally and accept one peer
+///
+/// IPv6 hosts go in brackets, e.g. `tcpout://[fd00::2]:5760`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TcpEndpoint {
+    /// Client mode: connect to `host:port`
+    Client(String),
+    /// Server mode: bind to `addr:port` and accept a single connection
+    Server(String),
+}
+
+impl TcpEndpoint {
+    /// Parse a `tcpout://` or `tcpin://` URL
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let url = url.trim();
+        if let Some(addr) = url.strip_prefix("tcpout://") {
+            if addr.is_empty() || addr.starts_with(':') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("tcpout requires a host: {}", url),
+                ));
+            }
+            check_host_port(url, addr)?;
+            Ok(TcpEndpoint::Client(addr.to_string()))
+        } else if let Some(addr) = url.strip_prefix("tcpin://") {
+            check_host_port(url, addr)?;
+            // Empty host means listen on all interfaces
+            let addr = if addr.starts_with(':') {
+                format!("0.0.0.0{}", addr)
+            } else {
+                addr.to_string()
+            };
+            Ok(TcpEndpoint::Server(addr))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected tcpout://host:port or tcpin://:port, got '{}'",
+                    url
+                ),
+            ))
+        }
+    }
+
+    /// Open the connection: dial out in client mode, wait for a peer in server mode
+    pub async fn connect(&self) -> io::Result<TcpStream> {
+        let stream = match self {
+            TcpEndpoint::Client(addr) => TcpStream::connect(addr).await?,
+            TcpEndpoint::Server(addr) => {
+                let listener = TcpListener::bind(addr).await?;
+                info!("[TcpBus] Listening for MAVLink peer on {}", addr);
+                let (stream, peer) = listener.accept().await?;
+                info!("[TcpBus] Accepted MAVLink peer {}", peer);
+                stream
+            }
+        };
+        // MAVLink frames are small - don't let Nagle batch them
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+/// `addr` must end in a port, with an IPv6 host in brackets so the port can be told apart
+fn check_host_port(url: &str, addr: &str) -> io::Result<()> {
+    let valid = match addr.rsplit_once(':') {
+        Some((host, port)) => {
+            let bracketed = host.starts_with('[') && host.ends_with(']');
+            port.parse::<u16>().is_ok() && (bracketed || !host.contains(':'))
+        }
+        None => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Expected host:port ([addr]:port for IPv6) in '{}'", url),
+        ))
+    }
+}
+
+impl std::fmt::Display for TcpEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TcpEndpoint::Client(addr) => write!(f, "tcpout://{}", addr),
+            TcpEndpoint::Server(addr) => write!(f, "tcpin://{}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            TcpEndpoint::parse("tcpout://127.0.0.1:5760").unwrap(),
+            TcpEndpoint::Client("127.0.0.1:5760".to_string())
+        );
+        assert_eq!(
+            TcpEndpoint::parse("tcpout://sitl.local:5760").unwrap(),
+            TcpEndpoint::Client("sitl.local:5760".to_string())
+        );
+        assert_eq!(
+            TcpEndpoint::parse("tcpin://:5760").unwrap(),
+            TcpEndpoint::Server("0.0.0.0:5760".to_string())
+        );
+        assert_eq!(
+            TcpEndpoint::parse("tcpin://127.0.0.1:5760").unwrap(),
+            TcpEndpoint::Server("127.0.0.1:5760".to_string())
+        );
+        assert!(TcpEndpoint::parse("tcpout://:5760").is_err());
+        assert!(TcpEndpoint::parse("udpout://127.0.0.1:5760").is_err());
+    }
+
+    #[test]
+    fn test_parse_missing_port() {
+        assert!(TcpEndpoint::parse("tcpout://127.0.0.1").is_err());
+        assert!(TcpEndpoint::parse("tcpout://sitl.local:").is_err());
+        assert!(TcpEndpoint::parse("tcpin://").is_err());
+        assert!(TcpEndpoint::parse("tcpout://[fd00::2]").is_err());
+        assert!(TcpEndpoint::parse("tcpout://127.0.0.1:70000").is_err());
+    }
+
+    #[test]
+    fn test_parse_ipv6() {
+        assert_eq!(
+            TcpEndpoint::parse("tcpout://[fd00::2]:5760").unwrap(),
+            TcpEndpoint::Client("[fd00::2]:5760".to_string())
+        );
+        assert_eq!(
+            TcpEndpoint::parse("tcpin://[::]:5760").unwrap(),
+            TcpEndpoint::Server("[::]:5760".to_string())
+        );
+        // Without brackets the port cannot be told from the address
+        assert!(TcpEndpoint::parse("tcpout://fd00::2:5760").is_err());
+        assert_eq!(
+            TcpEndpoint::parse("tcpout://[::1]:5760")
+                .unwrap()
+                .to_string(),
+            "tcpout://[::1]:5760"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UdpSocket;
+use tracing::info;
+
+/// Largest datagram accepted (MAVLink routers send one or a few frames per datagram)
+const MAX_DATAGRAM: usize = 2048;
+
+/// MAVLink-over-UDP endpoint, for joining a mavlink-router or mavp2p deployment
+///
+/// Parsed from bus paths of the form:
+///   - `udpin://:port` or `udpin://addr:port` - listen; the router sends to this port
+///     (mavlink-router `Mode = Normal`, mavp2p `udpc:`), replies go to the latest sender
+///   - `udpout://host:port` - send to a router listening there (mavlink-router
+///     `Mode = Server`, mavp2p `udps:`), which learns the hub from its heartbeats
+#[derive(Debug, Clone, PartialEq)]
+pub enum UdpEndpoint {
+    /// Bind to `addr:port` and talk to whoever sends
+    Listen(String),
+    /// Send to `host:port` from an ephemeral port
+    Send(String),
+}
+
+impl UdpEndpoint {
+    /// Parse a `udpin://` or `udpout://` URL
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let url = url.trim();
+        if let Some(addr) = url.strip_prefix("udpout://") {
+            if addr.is_empty() || addr.starts_with(':') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("udpout requires a host: {}", url),
+                ));
+            }
+            Ok(UdpEndpoint::Send(addr.to_string()))
+        } else if let Some(addr) = url.strip_prefix("udpin://") {
+            // Empty host means listen on all interfaces
+            let addr = if addr.starts_with(':') {
+                format!("0.0.0.0{}", addr)
+            } else {
+                addr.to_string()
+            };
+            Ok(UdpEndpoint::Listen(addr))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected udpin://:port or udpout://host:port, got '{}'",
+                    url
+                ),
+            ))
+        }
+    }
+
+    /// Whether the peer only learns of the hub from what it sends (udpout)
+    pub fn needs_keepalive(&self) -> bool {
+        matches!(self, UdpEndpoint::Send(_))
+    }
+
+    /// Bind the socket; in send mode resolve the router's address
+    pub async fn connect(&self) -> io::Result<UdpLink> {
+        match self {
+            UdpEndpoint::Listen(addr) => {
+                let socket = UdpSocket::bind(addr).await?;
+                info!("[UdpBus] Listening for MAVLink datagrams on {}", addr);
+                Ok(UdpLink::new(socket, None))
+            }
+            UdpEndpoint::Send(addr) => {
+                let peer = tokio::net::lookup_host(addr).await?.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve", addr))
+                })?;
+                let bind = if peer.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(bind).await?;
+                Ok(UdpLink::new(socket, Some(peer)))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for UdpEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UdpEndpoint::Listen(addr) => write!(f, "udpin://{}", addr),
+            UdpEndpoint::Send(addr) => write!(f, "udpout://{}", addr),
+        }
+    }
+}
+
+/// A UDP socket as a byte stream: datagrams are read back to back, each write is sent as
+/// one datagram
+pub struct UdpLink {
+    socket: UdpSocket,
+    /// Where writes go: fixed for udpout, the latest sender for udpin (None until one)
+    peer: Option<SocketAddr>,
+    follow_sender: bool,
+    /// Received datagram and how much of it has been read
+    datagram: Vec<u8>,
+    read: usize,
+}
+
+impl UdpLink {
+    fn new(socket: UdpSocket, peer: Option<SocketAddr>) -> Self {
+        Self {
+            socket,
+            follow_sender: peer.is_none(),
+            peer,
+            datagram: Vec::new(),
+            read: 0,
+        }
+    }
+}
+
+impl AsyncRead for UdpLink {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        // An empty datagram is skipped, reading nothing would signal end of stream
+        while this.read == this.datagram.len() {
+            let mut storage = [0u8; MAX_DATAGRAM];
+            let mut datagram = ReadBuf::new(&mut storage);
+            let sender = match this.socket.poll_recv_from(cx, &mut datagram) {
+                Poll::Ready(Ok(sender)) => sender,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if this.follow_sender && this.peer != Some(sender) {
+                info!("[UdpBus] MAVLink peer is {}", sender);
+                this.peer = Some(sender);
+            }
+            this.datagram = datagram.filled().to_vec();
+            this.read = 0;
+        }
+        let n = buf.remaining().min(this.datagram.len() - this.read);
+        buf.put_slice(&this.datagram[this.read..this.read + n]);
+        this.read += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for UdpLink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.peer {
+            Some(peer) => self.socket.poll_send_to(cx, buf, peer),
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "no MAVLink datagram received yet",
+            ))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            UdpEndpoint::parse("udpin://:14550").unwrap(),
+            UdpEndpoint::Listen("0.0.0.0:14550".to_string())
+        );
+        assert_eq!(
+            UdpEndpoint::parse("udpout://127.0.0.1:14551").unwrap(),
+            UdpEndpoint::Send("127.0.0.1:14551".to_string())
+        );
+        assert!(UdpEndpoint::parse("udpout://:14551").is_err());
+        assert!(UdpEndpoint::parse("tcpout://127.0.0.1:5760").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_listen_replies_to_sender() {
+        let mut link = UdpEndpoint::Listen("127.0.0.1:0".to_string())
+            .connect()
+            .await
+            .unwrap();
+        // Nothing to reply to before the router has sent anything
+        assert!(link.write_all(b"early").await.is_err());
+
+        let router = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let hub = link.socket.local_addr().unwrap();
+        router.send_to(b"abcdef", hub).await.unwrap();
+        // A reader with a small buffer gets the datagram in pieces
+        let mut head = [0u8; 4];
+        link.read_exact(&mut head).await.unwrap();
+        assert_eq!(&head, b"abcd");
+        let mut tail = [0u8; 2];
+        link.read_exact(&mut tail).await.unwrap();
+        assert_eq!(&tail, b"ef");
+
+        link.write_all(b"reply").await.unwrap();
+        let mut buf = [0u8; 16];
+        let (n, from) = router.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"reply");
+        assert_eq!(from, hub);
+    }
+}
@@ -0,0 +1,663 @@
+use crate::config::sensor_config::CalibrationConfig;
+use crate::grpc_service::sensorhub::CalibrationQuality;
+use crate::grpc_service::SensorHubService;
+use crate::messages::SensorMessage;
+use crate::orientation::SensorOrientation;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+type Vector = [f64; 3];
+
+/// Weight of each magnetometer sample in the running mean of its corrected field strength
+/// (about 10 s at 100 Hz), slow enough to ride out motor currents and manoeuvres
+const DRIFT_SMOOTHING: f64 = 0.001;
+/// Samples before the running mean is trusted
+const MIN_DRIFT_SAMPLES: u64 = 1000;
+
+/// How a section was fitted, written alongside it by the hub's own calibrations
+/// (`[mag.quality]`); hand-written files usually have none
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Quality {
+    /// Unix time the section was stored
+    pub created_utc_s: u64,
+    pub samples: u64,
+    pub residual: f64,
+    pub coverage: f64,
+    /// Fitted field strength (μT) of a mag section, the reference for live drift
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_ut: Option<f64>,
+}
+
+/// Per-axis accelerometer correction: `(raw - bias) * scale`
+#[derive(Debug, Clone, Deserialize)]
+struct AccelCalibration {
+    /// m/s²
+    #[serde(default)]
+    bias: Vector,
+    #[serde(default = "unit_scale")]
+    scale: Vector,
+    quality: Option<Quality>,
+}
+
+/// Gyroscope turn-on bias (rad/s), subtracted
+#[derive(Debug, Clone, Deserialize)]
+struct GyroCalibration {
+    bias: Vector,
+    quality: Option<Quality>,
+}
+
+/// Magnetometer correction: `soft_iron * (raw - hard_iron)`
+#[derive(Debug, Clone, Deserialize)]
+struct MagCalibration {
+    /// μT
+    #[serde(default)]
+    hard_iron: Vector,
+    #[serde(default = "identity")]
+    soft_iron: [Vector; 3],
+    quality: Option<Quality>,
+}
+
+fn unit_scale() -> Vector {
+    [1.0; 3]
+}
+
+fn identity() -> [Vector; 3] {
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+}
+
+/// Pitot zero offset (Pa), subtracted from differential pressure
+#[derive(Debug, Clone, Deserialize)]
+struct AirspeedCalibration {
+    offset: f32,
+    quality: Option<Quality>,
+}
+
+/// Mounting rotation found by DetectOrientation, applied after the corrections above
+#[derive(Debug, Clone, Deserialize)]
+struct OrientationCalibration {
+    #[serde(flatten)]
+    rotation: SensorOrientation,
+    quality: Option<Quality>,
+}
+
+/// Contents of one `<sensor_id>.toml` calibration file
+#[derive(Debug, Clone, Deserialize)]
+pub struct SensorCalibration {
+    /// Identifies the calibration run, stamped on corrected messages
+    id: String,
+    accel: Option<AccelCalibration>,
+    gyro: Option<GyroCalibration>,
+    mag: Option<MagCalibration>,
+    airspeed: Option<AirspeedCalibration>,
+    orientation: Option<OrientationCalibration>,
+}
+
+impl SensorCalibration {
+    /// Sections present, with their quality if recorded
+    fn sections(&self) -> Vec<(&'static str, Option<&Quality>)> {
+        let mut sections = Vec::new();
+        if let Some(accel) = &self.accel {
+            sections.push(("accel", accel.quality.as_ref()));
+        }
+        if let Some(gyro) = &self.gyro {
+            sections.push(("gyro", gyro.quality.as_ref()));
+        }
+        if let Some(mag) = &self.mag {
+            sections.push(("mag", mag.quality.as_ref()));
+        }
+        if let Some(airspeed) = &self.airspeed {
+            sections.push(("airspeed", airspeed.quality.as_ref()));
+        }
+        if let Some(orientation) = &self.orientation {
+            sections.push(("orientation", orientation.quality.as_ref()));
+        }
+        sections
+    }
+
+    fn correct_accel(&self, v: Vector) -> Vector {
+        match &self.accel {
+            Some(c) => [0, 1, 2].map(|i| (v[i] - c.bias[i]) * c.scale[i]),
+            None => v,
+        }
+    }
+
+    fn correct_gyro(&self, v: Vector) -> Vector {
+        match &self.gyro {
+            Some(c) => [0, 1, 2].map(|i| v[i] - c.bias[i]),
+            None => v,
+        }
+    }
+
+    fn correct_mag(&self, v: Vector) -> Vector {
+        match &self.mag {
+            Some(c) => {
+                let d = [0, 1, 2].map(|i| v[i] - c.hard_iron[i]);
+                c.soft_iron
+                    .map(|row| row[0] * d[0] + row[1] * d[1] + row[2] * d[2])
+            }
+            None => v,
+        }
+    }
+}
+
+/// Running mean of a magnetometer's corrected field strength over its fitted one
+#[derive(Debug, Clone, Copy)]
+struct Drift {
+    ratio: f64,
+    samples: u64,
+}
+
+/// Calibrations loaded at startup, by sensor id
+#[derive(Default)]
+pub struct Calibrations {
+    sensors: RwLock<HashMap<String, SensorCalibration>>,
+    /// Directory the files were loaded from, where new calibrations are written
+    dir: RwLock<Option<PathBuf>>,
+    /// Expiry age (0 = never) and tolerated mag drift (0 = unchecked)
+    limits: RwLock<(u64, f64)>,
+    drift: Mutex<HashMap<String, Drift>>,
+    /// "<sensor>/<section>/<calibration id>/<reason>" already announced
+    announced: Mutex<HashSet<String>>,
+}
+
+impl Calibrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the expiry and drift limits of `[calibration]`
+    pub fn configure(&self, config: &CalibrationConfig) {
+        let max_age_s = (config.max_age_days.max(0.0) * 86_400.0) as u64;
+        *self.limits.write().unwrap() = (max_age_s, config.max_mag_drift.max(0.0));
+    }
+
+    /// Load every `<sensor_id>.toml` in `dir`; unreadable files are skipped with a warning
+    pub fn load_dir(&self, dir: &Path) {
+        *self.dir.write().unwrap() = Some(dir.to_path_buf());
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                info!(
+                    "[calibration] No calibrations loaded from {}: {}",
+                    dir.display(),
+                    e
+                );
+                return;
+            }
+        };
+        let mut sensors = self.sensors.write().unwrap();
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            let Some(sensor_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| {
+                    toml::from_str::<SensorCalibration>(&text).map_err(|e| e.to_string())
+                });
+            match parsed {
+                Ok(calibration) => {
+                    info!(
+                        "[calibration] {}: {} (accel: {}, gyro: {}, mag: {}, airspeed: {}, \
+                         orientation: {})",
+                        sensor_id,
+                        calibration.id,
+                        calibration.accel.is_some(),
+                        calibration.gyro.is_some(),
+                        calibration.mag.is_some(),
+                        calibration.airspeed.is_some(),
+                        calibration.orientation.is_some()
+                    );
+                    sensors.insert(sensor_id.to_string(), calibration);
+                }
+                Err(e) => warn!("[calibration] Ignoring {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Where calibration files are read and written (None when disabled)
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir.read().unwrap().clone()
+    }
+
+    /// Store a fitted magnetometer correction; returns the new calibration id and the file
+    pub fn store_mag(
+        &self,
+        sensor_id: &str,
+        hard_iron: Vector,
+        soft_iron: [Vector; 3],
+        quality: Quality,
+    ) -> Result<(String, PathBuf), String> {
+        let mut mag = toml::Table::new();
+        mag.insert(
+            "hard_iron".into(),
+            toml::Value::try_from(hard_iron).unwrap(),
+        );
+        mag.insert(
+            "soft_iron".into(),
+            toml::Value::try_from(soft_iron).unwrap(),
+        );
+        self.store_section(sensor_id, "mag", mag, quality)
+    }
+
+    /// Store a six-position accelerometer correction; returns the new calibration id and the file
+    pub fn store_accel(
+        &self,
+        sensor_id: &str,
+        bias: Vector,
+        scale: Vector,
+        quality: Quality,
+    ) -> Result<(String, PathBuf), String> {
+        let mut accel = toml::Table::new();
+        accel.insert("bias".into(), toml::Value::try_from(bias).unwrap());
+        accel.insert("scale".into(), toml::Value::try_from(scale).unwrap());
+        self.store_section(sensor_id, "accel", accel, quality)
+    }
+
+    /// Store a pitot zero offset (Pa) from ZeroAirspeed; returns the new calibration id and
+    /// the file
+    pub fn store_airspeed(
+        &self,
+        sensor_id: &str,
+        offset: f32,
+        quality: Quality,
+    ) -> Result<(String, PathBuf), String> {
+        let mut airspeed = toml::Table::new();
+        airspeed.insert("offset".into(), toml::Value::from(f64::from(offset)));
+        self.store_section(sensor_id, "airspeed", airspeed, quality)
+    }
+
+    /// Pitot zero offsets (Pa) of the loaded calibrations, by sensor id
+    pub fn airspeed_offsets(&self) -> Vec<(String, f32)> {
+        self.sensors
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(sensor_id, c)| Some((sensor_id.clone(), c.airspeed.as_ref()?.offset)))
+            .collect()
+    }
+
+    /// Store a mounting rotation from DetectOrientation; returns the new calibration id and
+    /// the file
+    pub fn store_orientation(
+        &self,
+        sensor_id: &str,
+        rotation: SensorOrientation,
+        quality: Quality,
+    ) -> Result<(String, PathBuf), String> {
+        let orientation = toml::Value::try_from(rotation).map_err(|e| e.to_string())?;
+        let toml::Value::Table(orientation) = orientation else {
+            unreachable!("a struct serializes to a table");
+        };
+        self.store_section(sensor_id, "orientation", orientation, quality)
+    }
+
+    /// Mounting rotations of the loaded calibrations, by sensor id
+    pub fn orientations(&self) -> Vec<(String, SensorOrientation)> {
+        self.sensors
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(sensor_id, c)| {
+                Some((sensor_id.clone(), c.orientation.as_ref()?.rotation))
+            })
+            .collect()
+    }
+
+    /// Replace one section of `<sensor_id>.toml`, keeping the others, and apply the file from
+    /// the next sample
+    fn store_section(
+        &self,
+        sensor_id: &str,
+        section: &str,
+        mut values: toml::Table,
+        mut quality: Quality,
+    ) -> Result<(String, PathBuf), String> {
+        let dir = self
+            .dir()
+            .ok_or("calibration files are disabled ([calibration] enabled = false)")?;
+        let path = dir.join(format!("{}.toml", sensor_id));
+        let mut table = match std::fs::read_to_string(&path) {
+            Ok(text) => text
+                .parse::<toml::Table>()
+                .map_err(|e| format!("cannot update {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+
+        let t_s = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let id = format!("{}-{}", section, t_s);
+        quality.created_utc_s = t_s;
+        values.insert(
+            "quality".into(),
+            toml::Value::try_from(quality).map_err(|e| e.to_string())?,
+        );
+        table.insert("id".into(), id.clone().into());
+        table.insert(section.into(), values.into());
+        let content = toml::to_string(&table).map_err(|e| e.to_string())?;
+        let calibration: SensorCalibration = toml::from_str(&content)
+            .map_err(|e| format!("cannot update {}: {}", path.display(), e))?;
+
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(&dir)?;
+            let tmp = path.with_extension("toml.tmp");
+            std::fs::write(&tmp, &content)?;
+            std::fs::rename(&tmp, &path)
+        };
+        write().map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        info!(
+            "[calibration] {}: stored {} in {}",
+            sensor_id,
+            id,
+            path.display()
+        );
+        self.sensors
+            .write()
+            .unwrap()
+            .insert(sensor_id.to_string(), calibration);
+        if section == "mag" {
+            self.drift.lock().unwrap().remove(sensor_id);
+        }
+        Ok((id, path))
+    }
+
+    /// Quality of each section of `sensor_id`'s calibration, at `now_s` (Unix seconds)
+    pub fn quality(&self, sensor_id: &str, now_s: u64) -> Vec<CalibrationQuality> {
+        let sensors = self.sensors.read().unwrap();
+        let Some(c) = sensors.get(sensor_id) else {
+            return Vec::new();
+        };
+        let (max_age_s, max_drift) = *self.limits.read().unwrap();
+        let drift = self.drift.lock().unwrap().get(sensor_id).copied();
+        c.sections()
+            .into_iter()
+            .map(|(section, quality)| {
+                let quality = quality.cloned().unwrap_or_default();
+                let age_s = match quality.created_utc_s {
+                    0 => 0,
+                    created => now_s.saturating_sub(created),
+                };
+                let drift = match drift {
+                    Some(d) if section == "mag" && d.samples >= MIN_DRIFT_SAMPLES => {
+                        (d.ratio - 1.0).abs()
+                    }
+                    _ => 0.0,
+                };
+                CalibrationQuality {
+                    section: section.to_string(),
+                    calibration_id: c.id.clone(),
+                    created_utc_s: quality.created_utc_s,
+                    age_s,
+                    samples: quality.samples.min(u64::from(u32::MAX)) as u32,
+                    residual: quality.residual as f32,
+                    coverage: quality.coverage as f32,
+                    drift: drift as f32,
+                    expired: max_age_s > 0 && quality.created_utc_s > 0 && age_s > max_age_s,
+                    stale: max_drift > 0.0 && drift > max_drift,
+                }
+            })
+            .collect()
+    }
+
+    /// Sections that expired or drifted since the last check, each reported once per
+    /// calibration: (sensor id, quality, reason)
+    pub fn check(&self, now_s: u64) -> Vec<(String, CalibrationQuality, String)> {
+        let sensor_ids: Vec<String> = self.sensors.read().unwrap().keys().cloned().collect();
+        let max_age_s = self.limits.read().unwrap().0;
+        let mut announced = self.announced.lock().unwrap();
+        let mut tripped = Vec::new();
+        for sensor_id in sensor_ids {
+            for quality in self.quality(&sensor_id, now_s) {
+                let mut reasons = Vec::new();
+                if quality.expired {
+                    reasons.push((
+                        "expired",
+                        format!(
+                            "{} days old, limit {}",
+                            quality.age_s / 86_400,
+                            max_age_s / 86_400
+                        ),
+                    ));
+                }
+                if quality.stale {
+                    reasons.push((
+                        "drift",
+                        format!(
+                            "live field strength {:.1} % off the fit",
+                            quality.drift * 100.0
+                        ),
+                    ));
+                }
+                for (kind, reason) in reasons {
+                    let key = format!(
+                        "{}/{}/{}/{}",
+                        sensor_id, quality.section, quality.calibration_id, kind
+                    );
+                    if announced.insert(key) {
+                        tripped.push((sensor_id.clone(), quality.clone(), reason));
+                    }
+                }
+            }
+        }
+        tripped
+    }
+
+    /// Correct IMU and magnetometer data in the sensor frame, stamping the calibration id
+    pub fn apply(&self, message: &mut SensorMessage) {
+        let sensors = self.sensors.read().unwrap();
+        let Some(c) = sensors.get(&message.header().sensor_id) else {
+            return;
+        };
+        match message {
+            SensorMessage::Imu(imu) if c.accel.is_some() || c.gyro.is_some() => {
+                let accel = c.correct_accel([imu.ax, imu.ay, imu.az].map(f64::from));
+                let gyro = c.correct_gyro([imu.gx, imu.gy, imu.gz].map(f64::from));
+                [imu.ax, imu.ay, imu.az] = accel.map(|x| x as f32);
+                [imu.gx, imu.gy, imu.gz] = gyro.map(|x| x as f32);
+                if let Some(p) = imu.precise.as_mut() {
+                    let accel = c.correct_accel([p[0], p[1], p[2]]);
+                    let gyro = c.correct_gyro([p[3], p[4], p[5]]);
+                    *p = [accel[0], accel[1], accel[2], gyro[0], gyro[1], gyro[2]];
+                }
+                imu.h.calibration_id.clone_from(&c.id);
+            }
+            SensorMessage::Magnetometer(mag) if c.mag.is_some() => {
+                let corrected = c.correct_mag([mag.mx, mag.my, mag.mz].map(f64::from));
+                [mag.mx, mag.my, mag.mz] = corrected.map(|x| x as f32);
+                let field_ut = c.mag.as_ref().and_then(|m| m.quality.as_ref()?.field_ut);
+                if let Some(field_ut) = field_ut.filter(|&f| f > 0.0) {
+                    let ratio = corrected.iter().map(|v| v * v).sum::<f64>().sqrt() / field_ut;
+                    let mut drift = self.drift.lock().unwrap();
+                    let d = drift
+                        .entry(mag.h.sensor_id.clone())
+                        .or_insert(Drift { ratio, samples: 0 });
+                    d.ratio += DRIFT_SMOOTHING * (ratio - d.ratio);
+                    d.samples += 1;
+                }
+                mag.h.calibration_id.clone_from(&c.id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Periodically announce calibrations that expired or no longer fit the live data
+pub fn spawn_quality_check(config: &CalibrationConfig, grpc_service: Arc<SensorHubService>) {
+    if !config.enabled || (config.max_age_days <= 0.0 && config.max_mag_drift <= 0.0) {
+        return;
+    }
+    let interval = Duration::from_secs(config.check_interval_s.max(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            grpc_service.check_calibrations();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{Header, MagnetometerMessage};
+
+    #[test]
+    fn test_hard_and_soft_iron() {
+        let calibration: SensorCalibration = toml::from_str(
+            r#"
+            id = "bench-2026-10-01"
+            [mag]
+            hard_iron = [10.0, -5.0, 2.0]
+            soft_iron = [[2.0, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 1.0]]
+            "#,
+        )
+        .unwrap();
+        let calibrations = Calibrations::new();
+        calibrations
+            .sensors
+            .write()
+            .unwrap()
+            .insert("mag0".to_string(), calibration);
+
+        let mut message = SensorMessage::Magnetometer(MagnetometerMessage {
+            h: Header::new("hub".into(), "mag0".into(), "mag0".into(), 1),
+            mx: 30.0,
+            my: 15.0,
+            mz: -40.0,
+        });
+        calibrations.apply(&mut message);
+        let SensorMessage::Magnetometer(mag) = message else {
+            unreachable!()
+        };
+        assert_eq!([mag.mx, mag.my, mag.mz], [40.0, 10.0, -42.0]);
+        assert_eq!(mag.h.calibration_id, "bench-2026-10-01");
+    }
+
+    #[test]
+    fn test_expiry_and_drift() {
+        let calibration: SensorCalibration = toml::from_str(
+            r#"
+            id = "mag-1000"
+            [mag]
+            hard_iron = [0.0, 0.0, 0.0]
+            [mag.quality]
+            created_utc_s = 1000
+            samples = 800
+            residual = 0.01
+            coverage = 0.9
+            field_ut = 50.0
+            "#,
+        )
+        .unwrap();
+        let calibrations = Calibrations::new();
+        calibrations.configure(&CalibrationConfig {
+            max_age_days: 1.0,
+            ..Default::default()
+        });
+        calibrations
+            .sensors
+            .write()
+            .unwrap()
+            .insert("mag0".to_string(), calibration);
+
+        // A day old, and the field now reads 20 % stronger than fitted
+        let now_s = 1000 + 86_400;
+        assert!(calibrations.check(now_s).is_empty());
+        for seq in 0..MIN_DRIFT_SAMPLES {
+            calibrations.apply(&mut SensorMessage::Magnetometer(MagnetometerMessage {
+                h: Header::new("hub".into(), "mag0".into(), "mag0".into(), seq),
+                mx: 60.0,
+                my: 0.0,
+                mz: 0.0,
+            }));
+        }
+        let quality = &calibrations.quality("mag0", now_s + 1)[0];
+        assert_eq!((quality.samples, quality.age_s), (800, 86_401));
+        assert!((quality.drift - 0.2).abs() < 1e-6);
+        assert!(quality.expired && quality.stale);
+
+        // Each reason is announced once
+        let reasons: Vec<String> = calibrations
+            .check(now_s + 1)
+            .into_iter()
+            .map(|(_, _, reason)| reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "1 days old, limit 1",
+                "live field strength 20.0 % off the fit"
+            ]
+        );
+        assert!(calibrations.check(now_s + 2).is_empty());
+    }
+
+    #[test]
+    fn test_airspeed_offset_survives_restart() {
+        let dir =
+            std::env::temp_dir().join(format!("sensorhub-calibration-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let calibrations = Calibrations::new();
+        calibrations.load_dir(&dir);
+        let quality = Quality {
+            samples: 100,
+            ..Default::default()
+        };
+        let (id, path) = calibrations.store_airspeed("pitot0", 3.5, quality).unwrap();
+        assert!(id.starts_with("airspeed-"));
+        assert_eq!(path, dir.join("pitot0.toml"));
+
+        let restarted = Calibrations::new();
+        restarted.load_dir(&dir);
+        assert_eq!(restarted.airspeed_offsets(), [("pitot0".to_string(), 3.5)]);
+        assert_eq!(restarted.quality("pitot0", 0)[0].section, "airspeed");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_orientation_stored_with_calibration() {
+        let dir =
+            std::env::temp_dir().join(format!("sensorhub-orientation-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("imu0.toml"),
+            "id = \"factory\"\n[gyro]\nbias = [0.01, 0.0, 0.0]\n",
+        )
+        .unwrap();
+
+        let calibrations = Calibrations::new();
+        calibrations.load_dir(&dir);
+        let mounting = SensorOrientation {
+            roll_deg: 180.0,
+            pitch_deg: 0.0,
+            yaw_deg: 90.0,
+        };
+        calibrations
+            .store_orientation("imu0", mounting, Quality::default())
+            .unwrap();
+
+        let restarted = Calibrations::new();
+        restarted.load_dir(&dir);
+        assert_eq!(restarted.orientations(), [("imu0".to_string(), mounting)]);
+        let sections: Vec<_> = restarted
+            .quality("imu0", 0)
+            .into_iter()
+            .map(|q| q.section)
+            .collect();
+        assert_eq!(sections, ["gyro", "orientation"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Time of a sample on both of the hub's clocks, as published in every Header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stamp {
+    /// UTC (ns), on the PPS time scale when locked to one
+    pub utc_ns: u64,
+    /// CLOCK_MONOTONIC_RAW (ns)
+    pub mono_ns: u64,
+}
+
+impl Stamp {
+    /// Both clocks read now, back to back
+    pub fn now() -> Self {
+        let mono_ns = mono_ns();
+        Self {
+            utc_ns: crate::pps::now_utc_ns(),
+            mono_ns,
+        }
+    }
+
+    /// A UTC time that was not read now (a sync tick, a kernel-stamped edge) with the
+    /// monotonic time it corresponds to
+    pub fn at_utc(utc_ns: u64) -> Self {
+        let now = Self::now();
+        let offset_ns = utc_ns as i64 - now.utc_ns as i64;
+        Self {
+            utc_ns,
+            mono_ns: (now.mono_ns as i64 + offset_ns).max(0) as u64,
+        }
+    }
+}
+
+/// CLOCK_MONOTONIC_RAW (ns): counts from boot, is never stepped or slewed by NTP, and is the
+/// same clock in every process on the host
+#[cfg(target_os = "linux")]
+pub fn mono_ns() -> u64 {
+    use nix::libc;
+
+    // SAFETY: all-zero is a valid timespec, and the call only writes to it
+    let (result, ts) = unsafe {
+        let mut ts: libc::timespec = std::mem::zeroed();
+        (libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut ts), ts)
+    };
+    if result != 0 {
+        return process_mono_ns();
+    }
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+/// Without CLOCK_MONOTONIC_RAW, time since the process's first timestamp
+#[cfg(not(target_os = "linux"))]
+pub fn mono_ns() -> u64 {
+    process_mono_ns()
+}
+
+fn process_mono_ns() -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_mono_advances() {
+        let first = Stamp::now();
+        std::thread::sleep(Duration::from_millis(5));
+        let second = Stamp::now();
+        let elapsed = second.mono_ns - first.mono_ns;
+        assert!((5_000_000..1_000_000_000).contains(&elapsed));
+
+        // A tick 2 ms ago keeps its distance on the monotonic clock
+        let tick = Stamp::at_utc(second.utc_ns - 2_000_000);
+        let behind = second.mono_ns as i64 - tick.mono_ns as i64;
+        assert!((behind - 2_000_000).abs() < 1_000_000);
+    }
+}
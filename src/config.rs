@@ -1,4 +1,5 @@
 pub mod bus_config;
+pub mod diff;
 pub mod sensor_config;
 
 pub use bus_config::load_bus_config;
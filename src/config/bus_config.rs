@@ -1,8 +1,12 @@
+//! Types of `buses.toml`. Shared with build.rs, which checks the shipped config against
+//! them, so it depends on serde and toml alone.
+
 use serde::Deserialize;
 use std::fs;
 
 /// Root structure for loading `[[bus]]` style TOML config
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BusConfig {
     #[serde(rename = "bus")]
     pub buses: Vec<BusEntry>,
@@ -10,11 +14,34 @@ pub struct BusConfig {
 
 /// One bus entry (e.g., I2C, SPI, etc.)
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BusEntry {
     pub id: String,
     #[serde(rename = "type")]
     pub r#type: String, // 'type' is a reserved word in Rust, use raw identifier
     pub path: String,
+    /// Baud rate for UART buses (defaults to the sensor protocol's rate)
+    pub baud: Option<u32>,
+    /// Playback rate for replay buses (1.0 = original speed)
+    pub speed: Option<f64>,
+    /// I2C addresses ScanI2c must never probe (EEPROMs, cameras, ...)
+    #[serde(default)]
+    pub no_probe: Vec<u16>,
+    /// Failed I2C transactions in a row before the bus is re-opened (0 disables recovery)
+    pub recovery_errors: Option<u32>,
+    /// Half-duplex RS-485 on a UART bus
+    #[serde(default)]
+    pub rs485: bool,
+    /// GPIO line driving the RS-485 transceiver's DE pin (default: the kernel's rs485 mode on RTS)
+    pub rs485_de_gpio: Option<u32>,
+    /// GPIO chip of `rs485_de_gpio` (default "gpiochip0")
+    pub rs485_de_chip: Option<String>,
+    /// Microseconds the RS-485 driver is enabled before the first bit is sent
+    #[serde(default)]
+    pub rs485_delay_before_us: u32,
+    /// Microseconds the RS-485 driver stays enabled after the last bit
+    #[serde(default)]
+    pub rs485_delay_after_us: u32,
 }
 
 /// Load bus config file
@@ -0,0 +1,299 @@
+use super::bus_config::BusConfig;
+use super::sensor_config::SensorConfig;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Polling rate used for sensors without a `frequency` (see the scheduler)
+const DEFAULT_FREQUENCY: &str = "100";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    SensorAdded,
+    SensorRemoved,
+    /// Only `frequency` changed
+    SensorRateChanged,
+    /// Driver, bus, address or other settings changed
+    SensorChanged,
+    /// A top-level section (`[mqtt]`, `[[queue]]`, ...) was added, removed or edited
+    SectionChanged,
+}
+
+/// One difference between the running and the proposed `sensors.toml`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub kind: ChangeKind,
+    /// Sensor id or section name
+    pub target: String,
+    /// Old and new values, empty when absent
+    pub before: String,
+    pub after: String,
+}
+
+impl ConfigChange {
+    fn new(kind: ChangeKind, target: &str, before: String, after: String) -> Self {
+        Self {
+            kind,
+            target: target.to_string(),
+            before,
+            after,
+        }
+    }
+}
+
+/// Parse a proposed `sensors.toml`, rejecting anything the hub would fail to start with
+/// alongside `buses`
+pub fn validate(text: &str, buses: &BusConfig) -> Result<toml::Table, String> {
+    let config: SensorConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+    config.check()?;
+    let bus_ids: HashSet<&str> = buses.buses.iter().map(|b| b.id.as_str()).collect();
+    let mut ids = HashSet::new();
+    for sensor in config.sensors.iter() {
+        if !ids.insert(&sensor.id) {
+            return Err(format!("sensor id '{}' is used twice", sensor.id));
+        }
+        // MAVLink sensors are fed by the autopilot link, not opened on a bus of their own
+        if !sensor.driver.starts_with("mavlink_") && !bus_ids.contains(sensor.bus.as_str()) {
+            return Err(format!(
+                "sensor '{}' is on bus '{}', which buses.toml does not define",
+                sensor.id, sensor.bus
+            ));
+        }
+    }
+    text.parse().map_err(|e: toml::de::Error| e.to_string())
+}
+
+/// Changes from `current` to `proposed`: sensors by id, then the other sections by name
+pub fn diff(current: &toml::Table, proposed: &toml::Table) -> Vec<ConfigChange> {
+    let (old, new) = (sensors(current), sensors(proposed));
+    let mut changes = Vec::new();
+    for (id, entry) in old.iter() {
+        if !new.contains_key(id) {
+            changes.push(ConfigChange::new(
+                ChangeKind::SensorRemoved,
+                id,
+                summary(entry),
+                String::new(),
+            ));
+        }
+    }
+    for (id, entry) in new.iter() {
+        let Some(before) = old.get(id) else {
+            changes.push(ConfigChange::new(
+                ChangeKind::SensorAdded,
+                id,
+                String::new(),
+                summary(entry),
+            ));
+            continue;
+        };
+        let edited: Vec<&str> = before
+            .keys()
+            .chain(entry.keys())
+            .filter(|key| *key != "frequency" && before.get(*key) != entry.get(*key))
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if !edited.is_empty() {
+            changes.push(ConfigChange::new(
+                ChangeKind::SensorChanged,
+                id,
+                fields(before, &edited),
+                fields(entry, &edited),
+            ));
+        }
+        if before.get("frequency") != entry.get("frequency") {
+            changes.push(ConfigChange::new(
+                ChangeKind::SensorRateChanged,
+                id,
+                frequency(before),
+                frequency(entry),
+            ));
+        }
+    }
+
+    let sections: BTreeSet<&String> = current
+        .keys()
+        .chain(proposed.keys())
+        .filter(|key| *key != "sensor")
+        .collect();
+    for section in sections {
+        let (before, after) = (current.get(section), proposed.get(section));
+        if before != after {
+            let show = |v: Option<&toml::Value>| v.map(|v| v.to_string()).unwrap_or_default();
+            changes.push(ConfigChange::new(
+                ChangeKind::SectionChanged,
+                section,
+                show(before),
+                show(after),
+            ));
+        }
+    }
+    changes
+}
+
+/// `[[sensor]]` tables by id
+fn sensors(config: &toml::Table) -> BTreeMap<String, &toml::Table> {
+    config
+        .get("sensor")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_table())
+        .filter_map(|t| Some((t.get("id")?.as_str()?.to_string(), t)))
+        .collect()
+}
+
+/// e.g. "icm42688p on i2c-1 @ 0x68, 200 Hz"
+fn summary(entry: &toml::Table) -> String {
+    let field = |key| entry.get(key).and_then(|v| v.as_str()).unwrap_or("?");
+    let address = entry
+        .get("address")
+        .and_then(|v| v.as_integer())
+        .map(|a| format!("{:#04x}", a))
+        .unwrap_or_else(|| "?".to_string());
+    format!(
+        "{} on {} @ {}, {} Hz",
+        field("driver"),
+        field("bus"),
+        address,
+        frequency(entry)
+    )
+}
+
+fn frequency(entry: &toml::Table) -> String {
+    entry
+        .get("frequency")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| DEFAULT_FREQUENCY.to_string())
+}
+
+/// e.g. "bus = \"i2c-1\", address = 104"
+fn fields(entry: &toml::Table, keys: &[&str]) -> String {
+    keys.iter()
+        .filter_map(|key| Some(format!("{} = {}", key, entry.get(*key)?)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buses() -> BusConfig {
+        toml::from_str(
+            r#"
+            [[bus]]
+            id = "i2c-1"
+            type = "i2c"
+            path = "/dev/i2c-1"
+
+            [[bus]]
+            id = "i2c-2"
+            type = "i2c"
+            path = "/dev/i2c-2"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff_sensors_and_sections() {
+        let buses = buses();
+        let current = validate(
+            r#"
+            [[sensor]]
+            id = "imu0"
+            driver = "icm42688p"
+            bus = "i2c-1"
+            address = 0x68
+            frequency = 200
+
+            [[sensor]]
+            id = "baro0"
+            driver = "bmp388"
+            bus = "i2c-1"
+            address = 0x76
+
+            [mqtt]
+            enabled = false
+            "#,
+            &buses,
+        )
+        .unwrap();
+        let proposed = validate(
+            r#"
+            [[sensor]]
+            id = "imu0"
+            driver = "icm42688p"
+            bus = "i2c-2"
+            address = 0x68
+            frequency = 400
+
+            [[sensor]]
+            id = "mag0"
+            driver = "lis3mdl"
+            bus = "i2c-1"
+            address = 0x1c
+            "#,
+            &buses,
+        )
+        .unwrap();
+
+        let changes = diff(&current, &proposed);
+        let summary: Vec<(ChangeKind, &str)> = changes
+            .iter()
+            .map(|c| (c.kind, c.target.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ChangeKind::SensorRemoved, "baro0"),
+                (ChangeKind::SensorChanged, "imu0"),
+                (ChangeKind::SensorRateChanged, "imu0"),
+                (ChangeKind::SensorAdded, "mag0"),
+                (ChangeKind::SectionChanged, "mqtt"),
+            ]
+        );
+        assert_eq!(changes[1].before, "bus = \"i2c-1\"");
+        assert_eq!(
+            (changes[2].before.as_str(), changes[2].after.as_str()),
+            ("200", "400")
+        );
+        assert_eq!(changes[3].after, "lis3mdl on i2c-1 @ 0x1c, 100 Hz");
+        assert!(diff(&current, &current).is_empty());
+
+        let duplicate = "[[sensor]]\nid = \"a\"\ndriver = \"x\"\nbus = \"i2c-1\"\naddress = 1\n";
+        assert!(validate(duplicate, &buses).is_ok());
+        assert!(validate(&duplicate.repeat(2), &buses).is_err());
+    }
+
+    #[test]
+    fn test_unknown_bus_rejected() {
+        let buses = buses();
+        let sensor = |driver: &str| {
+            format!("[[sensor]]\nid = \"a\"\ndriver = \"{driver}\"\nbus = \"spi-0\"\naddress = 1\n")
+        };
+        let error = validate(&sensor("icm42688p"), &buses).unwrap_err();
+        assert!(error.contains("'spi-0'"), "{}", error);
+        assert!(validate(&sensor("mavlink_imu"), &buses).is_ok());
+    }
+
+    #[test]
+    fn test_misspelled_key_rejected() {
+        let buses = buses();
+        let sensor = "[[sensor]]\nid = \"a\"\ndriver = \"x\"\nbus = \"i2c-1\"\naddress = 1\n";
+        assert!(validate(&format!("{sensor}frequncy = 200\n"), &buses).is_err());
+        assert!(validate(&format!("{sensor}[mqtt]\nenable = true\n"), &buses).is_err());
+    }
+
+    #[test]
+    fn test_unknown_group_kind_rejected() {
+        let buses = buses();
+        let group = |kind: &str| {
+            format!("[[group]]\nname = \"nav\"\n[[group.member]]\nkind = \"{kind}\"\n")
+        };
+        assert!(validate(&group("gps"), &buses).is_ok());
+        let error = validate(&group("gnss"), &buses).unwrap_err();
+        assert!(error.contains("'gnss'"), "{}", error);
+    }
+}
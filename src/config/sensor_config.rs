@@ -1,26 +1,868 @@
+//! Types of `sensors.toml`. Shared with build.rs, which checks the shipped config against
+//! them, so it depends on serde, toml, the DriverParams alias and the kind names alone.
+
+use crate::kinds;
+use crate::sensors::DriverParams;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
 
 /// Root configuration struct expecting `[[sensor]]` TOML array format
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SensorConfig {
     #[serde(rename = "sensor", default)]
     pub sensors: Vec<SensorEntry>,
+    #[serde(rename = "queue", default)]
+    pub queues: Vec<QueueEntry>,
+    #[serde(rename = "group", default)]
+    pub groups: Vec<GroupEntry>,
+    #[serde(rename = "mounting", default)]
+    pub mountings: Vec<MountingEntry>,
+    #[serde(rename = "filter", default)]
+    pub filters: Vec<FilterEntry>,
+    #[serde(rename = "decimate", default)]
+    pub decimations: Vec<DecimateEntry>,
+    #[serde(rename = "ahrs", default)]
+    pub ahrs: Vec<AhrsEntry>,
+    #[serde(rename = "altitude", default)]
+    pub altitude: Vec<AltitudeEntry>,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    pub downlink: DownlinkConfig,
+    #[serde(default)]
+    pub overrides: OverridesConfig,
+    #[serde(default)]
+    pub i2c_trace: I2cTraceConfig,
+    #[serde(default)]
+    pub scenario: ScenarioConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub mavlink_log: MavlinkLogConfig,
+    #[serde(default)]
+    pub rosbridge: RosbridgeConfig,
+    #[serde(default)]
+    pub webrtc: WebRtcConfig,
+    #[serde(default)]
+    pub environment: EnvironmentConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub csv_log: CsvLogConfig,
+    #[serde(default)]
+    pub system: SystemConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    pub rate_monitor: RateMonitorConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub recovery: RecoveryConfig,
+    #[serde(default)]
+    pub calibration: CalibrationConfig,
+    #[serde(default)]
+    pub gyro_bias: GyroBiasConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub pps: PpsConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
 }
 
 /// One sensor entry, matching each `[[sensor]]` section
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SensorEntry {
     pub id: String,
     pub driver: String,
     pub bus: String,
-    pub address: u8,
+    pub address: u16,
     pub frequency: Option<u32>,
+    /// Logical role reported to clients (e.g. "primary_imu", "pitot")
+    pub role: Option<String>,
+    /// IMU output precision (default f32)
+    pub precision: Option<ImuPrecision>,
+    /// Driver settings such as ranges, output data rate and filters (driver defaults if unset)
+    #[serde(default)]
+    pub params: DriverParams,
+    /// GPIO line wired to the chip's data-ready interrupt; sampled on its edges instead of
+    /// on a timer (needs the `drdy` feature)
+    pub drdy_gpio: Option<u32>,
+    /// GPIO chip of `drdy_gpio` (default "gpiochip0")
+    pub drdy_chip: Option<String>,
+}
+
+/// Numeric precision of published IMU samples
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImuPrecision {
+    F32,
+    /// Also fill IMUData.precise with doubles (HIGHRES/tactical-grade IMUs)
+    F64,
+}
+
+/// Per-sensor publish queue policy, matching each `[[queue]]` section
+/// Applies to auto-discovered MAVLink sensors too (e.g. `sensor = "fc_attitude"`)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueEntry {
+    pub sensor: String,
+    #[serde(default)]
+    pub policy: OverflowPolicy,
+    /// Backlog limit for `lossless` (default 1000)
+    pub depth: Option<usize>,
+    /// Backlog lane (default: `critical` for IMU and attitude data, `bulk` otherwise)
+    pub tier: Option<QueueTier>,
+}
+
+/// Physical mounting of a sensor, matching each `[[mounting]]` section
+/// Applies to auto-discovered MAVLink sensors too (e.g. `sensor = "fc_imu0"`)
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MountingEntry {
+    pub sensor: String,
+    /// Lever arm from the vehicle reference point, body frame FRD (m)
+    pub position: [f32; 3],
+    /// `frame_id` stamped on the sensor's data (default: the sensor id)
+    pub frame_id: Option<String>,
+}
+
+/// Digital filter chain on some of a sensor's fields, matching each `[[filter]]` section
+/// Applies to auto-discovered MAVLink sensors too (e.g. `sensor = "fc_imu0"`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterEntry {
+    pub sensor: String,
+    /// Measurement fields to filter, e.g. `["gx", "gy", "gz"]`
+    pub fields: Vec<String>,
+    /// Applied in order
+    pub stages: Vec<FilterStage>,
+}
+
+/// One stage of a `[[filter]]` chain
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum FilterStage {
+    /// Second-order Butterworth low-pass
+    Lowpass { cutoff_hz: f64 },
+    /// Second-order notch, e.g. at the propeller frequency
+    Notch { center_hz: f64, bandwidth_hz: f64 },
+    /// Subtract the mean tracked over `time_constant_s` (first-order high-pass)
+    Bias { time_constant_s: f64 },
+    /// Mean of the last `window` samples
+    Average { window: usize },
+    /// Exponential smoothing with `time_constant_s` (first-order low-pass)
+    Exponential { time_constant_s: f64 },
+}
+
+/// Reduced gRPC output rate for one sensor, matching each `[[decimate]]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DecimateEntry {
+    pub sensor: String,
+    /// Publish one message for every `factor` samples
+    pub factor: u32,
+    /// Publish the mean of each block of samples instead of its last one
+    #[serde(default)]
+    pub average: bool,
+}
+
+/// On-board attitude estimation for one IMU, matching each `[[ahrs]]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AhrsEntry {
+    pub imu: String,
+    /// Magnetometer for heading (without one, yaw is integrated gyro and drifts)
+    pub mag: Option<String>,
+    #[serde(default)]
+    pub algorithm: AhrsAlgorithm,
+    /// Sensor id of the published attitude (default: `<imu>_ahrs`)
+    pub sensor_id: Option<String>,
+    /// Madgwick gradient step (rad/s)
+    #[serde(default = "default_ahrs_beta")]
+    pub beta: f64,
+    /// Mahony proportional and integral gains
+    #[serde(default = "default_ahrs_kp")]
+    pub kp: f64,
+    #[serde(default)]
+    pub ki: f64,
+}
+
+fn default_ahrs_beta() -> f64 {
+    0.1
+}
+
+fn default_ahrs_kp() -> f64 {
+    1.0
+}
+
+/// Barometer + accelerometer altitude estimation, matching each `[[altitude]]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AltitudeEntry {
+    pub baro: String,
+    pub imu: String,
+    /// Attitude sensor (`[[ahrs]]` or flight controller) used to find "up"; without
+    /// one, the low-passed accelerometer direction is taken as vertical
+    pub attitude: Option<String>,
+    /// Sensor id of the published altitude (default: `<baro>_alt`)
+    pub sensor_id: Option<String>,
+    /// How long the estimate trusts the accelerometer before following the
+    /// barometer (s); larger is smoother, smaller follows the baro more closely
+    #[serde(default = "default_altitude_time_constant")]
+    pub time_constant_s: f64,
+    /// Maximum publish rate (Hz); the filter itself runs at the IMU rate
+    #[serde(default = "default_altitude_rate")]
+    pub rate_hz: f64,
+}
+
+fn default_altitude_time_constant() -> f64 {
+    2.0
+}
+
+fn default_altitude_rate() -> f64 {
+    50.0
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AhrsAlgorithm {
+    #[default]
+    Madgwick,
+    Mahony,
+}
+
+/// Named stream composition for StreamGroup, matching each `[[group]]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupEntry {
+    pub name: String,
+    /// Rate cap for members without their own (unset = full rate)
+    pub max_frequency_hz: Option<u32>,
+    /// What the group contains (everything if empty)
+    #[serde(default, rename = "member")]
+    pub members: Vec<GroupMember>,
+}
+
+/// One selection in a group: a sensor id, a data kind (e.g. "imu", "gps"), or both
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupMember {
+    pub sensor: Option<String>,
+    pub kind: Option<String>,
+    pub max_frequency_hz: Option<u32>,
+}
+
+/// What to do when a client falls behind a sensor
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Only the newest message is kept
+    LatestWins,
+    /// Every message is kept up to `depth`
+    #[default]
+    Lossless,
+}
+
+/// Priority of a sensor's messages in a slow client's backlog
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueTier {
+    /// Sent ahead of bulk messages
+    Critical,
+    /// Sent when nothing critical is pending, shed first under load
+    Bulk,
+}
+
+/// Watchdog diagnostic bundles, matching the optional `[diagnostics]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    pub enabled: bool,
+    /// Seconds a bus must stay silent before a bundle is written
+    pub failure_timeout_s: u64,
+    /// Directory bundles are written to and served from
+    pub bundle_dir: String,
+    /// Older bundles beyond this count are deleted
+    pub keep_bundles: usize,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_timeout_s: 10,
+            bundle_dir: "diagnostics".to_string(),
+            keep_bundles: 5,
+        }
+    }
+}
+
+/// Egress budget for client streams, matching the optional `[downlink]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownlinkConfig {
+    /// Total egress across all client streams (kbit/s, 0 = unlimited)
+    #[serde(default)]
+    pub budget_kbps: u32,
+    #[serde(rename = "stream", default)]
+    pub streams: Vec<DownlinkStream>,
+}
+
+/// Sensor priority under the downlink budget, matching each `[[downlink.stream]]`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownlinkStream {
+    pub sensor: String,
+    /// Higher priorities keep their full rate longest (default 0)
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Persistence of runtime changes, matching the optional `[overrides]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverridesConfig {
+    pub enabled: bool,
+    /// Overrides file, relative to the config directory
+    pub path: String,
+}
+
+impl Default for OverridesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "overrides.toml".to_string(),
+        }
+    }
+}
+
+/// Per-sensor calibration files, matching the optional `[calibration]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalibrationConfig {
+    pub enabled: bool,
+    /// Directory of `<sensor_id>.toml` files, relative to the config directory
+    pub dir: String,
+    /// Age at which a calibration stored by the hub is reported expired (0 = never)
+    pub max_age_days: f64,
+    /// Tolerated drift of the corrected mag field strength from the fitted one (0 = unchecked)
+    pub max_mag_drift: f64,
+    pub check_interval_s: u64,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "calibration".to_string(),
+            max_age_days: 180.0,
+            max_mag_drift: 0.15,
+            check_interval_s: 10,
+        }
+    }
+}
+
+/// Startup gyro bias estimation, matching the optional `[gyro_bias]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GyroBiasConfig {
+    pub enabled: bool,
+    /// Seconds of stationary data averaged per IMU
+    pub duration_s: f64,
+    /// Deviation from the running mean (rad/s) treated as motion, restarting the average
+    pub motion_threshold: f64,
+    /// Give up (and publish uncorrected) if not stationary for `duration_s` within this time
+    pub timeout_s: f64,
+    /// IMUs to estimate (empty = all)
+    pub sensors: Vec<String>,
+}
+
+impl Default for GyroBiasConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration_s: 5.0,
+            motion_threshold: 0.05,
+            timeout_s: 60.0,
+            sensors: Vec::new(),
+        }
+    }
+}
+
+/// Time-triggered sampling aligned across hubs, matching the optional `[sync]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Poll on whole multiples of each sensor's period in TAI instead of free-running
+    pub enabled: bool,
+    /// TAI minus the system (UTC) clock, i.e. the current leap second count
+    pub tai_offset_s: i64,
+    /// Shift of this hub's ticks after the epoch boundaries (µs)
+    pub phase_us: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tai_offset_s: 37,
+            phase_us: 0,
+        }
+    }
+}
+
+/// Pulse-per-second input disciplining published timestamps, matching the optional `[pps]`
+/// section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PpsConfig {
+    /// Kernel PPS device, e.g. "/dev/pps0"; unset leaves timestamps on the system clock
+    pub device: Option<String>,
+}
+
+/// In-memory history served by GetHistory, matching the optional `[history]` section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Seconds kept per stream: a data kind (e.g. "imu", "gps") or "events"
+    pub retention_s: BTreeMap<String, f64>,
+    /// Seconds kept for streams not listed (0 = not kept)
+    pub default_s: f64,
+}
+
+/// I2C bus analyzer, matching the optional `[i2c_trace]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct I2cTraceConfig {
+    /// Record from startup (GetI2cTrace can also switch it on later)
+    pub enabled: bool,
+    /// Transactions kept across all I2C buses
+    pub capacity: usize,
+}
+
+impl Default for I2cTraceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: 4096,
+        }
+    }
+}
+
+/// Simulated failures for SITL/replay runs, matching the optional `[scenario]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScenarioConfig {
+    pub enabled: bool,
+    #[serde(rename = "fault")]
+    pub faults: Vec<FaultEntry>,
+}
+
+/// One scheduled degradation, matching each `[[scenario.fault]]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaultEntry {
+    pub sensor: String,
+    pub kind: FaultKind,
+    /// Seconds after startup
+    pub at_s: f64,
+    /// How long the fault lasts (forever if unset)
+    pub duration_s: Option<f64>,
+    /// Added to the named fields for `bias` (e.g. `{ gz = 0.02 }`)
+    #[serde(default)]
+    pub offsets: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    /// The sensor publishes nothing
+    Dropout,
+    /// Constant offsets on measurement fields
+    Bias,
+}
+
+/// MQTT output, matching the optional `[mqtt]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    /// Broker address as host[:port], IPv6 literals in brackets (`[::1]:1883`)
+    pub broker: String,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Seconds; 0 disables keep-alive pings
+    pub keep_alive_s: u16,
+    pub format: MqttFormat,
+    /// Topic template, `{kind}` and `{sensor_id}` are substituted
+    pub topic: String,
+    /// Per-kind templates overriding `topic` (e.g. `imu = "vehicle/imu/{sensor_id}"`)
+    pub topics: BTreeMap<String, String>,
+    /// Ask the broker to keep the last message of each topic for new subscribers
+    pub retain: bool,
+    /// MQTT QoS level (0 or 1); 0 suits high-rate sensor data, 1 waits for broker acks
+    pub qos: u8,
+    /// Connect over TLS (default port 8883)
+    pub tls: bool,
+    /// CA certificate (PEM) to verify the broker with, instead of the system roots
+    pub ca_file: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: "127.0.0.1:1883".to_string(),
+            client_id: "navigate-sensorhub".to_string(),
+            username: None,
+            password: None,
+            keep_alive_s: 30,
+            format: MqttFormat::Json,
+            topic: "sensorhub/{kind}/{sensor_id}".to_string(),
+            topics: BTreeMap::new(),
+            retain: false,
+            qos: 0,
+            tls: false,
+            ca_file: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MqttFormat {
+    /// The hub's `SensorMessage` as JSON
+    Json,
+    /// `SensorData` as encoded protobuf, as on StreamAll
+    Protobuf,
+}
+
+/// Arming interlock for control RPCs, matching the optional `[safety]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// Refuse disruptive control RPCs while the vehicle is armed
+    pub enabled: bool,
+    /// Also refuse them while no autopilot HEARTBEAT is being received
+    pub block_when_unknown: bool,
+    /// HEARTBEAT age after which the arming state is unknown
+    pub heartbeat_timeout_ms: u64,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            block_when_unknown: false,
+            heartbeat_timeout_ms: 3000,
+        }
+    }
+}
+
+/// Raw MAVLink recording, matching the optional `[mavlink_log]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MavlinkLogConfig {
+    pub enabled: bool,
+    /// Directory segments and their indexes are written to
+    pub dir: String,
+    /// Start a new segment after this many seconds
+    pub segment_s: u64,
+    /// Time covered by one compressed, seekable block
+    pub block_ms: u64,
+    /// zstd level (1-22, higher is smaller and slower)
+    pub level: i32,
+    /// Also capture the raw byte stream of every link for this long after startup (0 = off)
+    pub capture_s: u64,
+}
+
+impl Default for MavlinkLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "mavlink_logs".to_string(),
+            segment_s: 3600,
+            block_ms: 1000,
+            level: 3,
+            capture_s: 0,
+        }
+    }
+}
+
+/// rosbridge client publishing to ROS 2, matching the optional `[rosbridge]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RosbridgeConfig {
+    pub enabled: bool,
+    /// rosbridge_server WebSocket address as host:port
+    pub address: String,
+    /// Topics are `<topic_prefix>/<sensor_id>/<imu|mag|pressure|fix>`
+    pub topic_prefix: String,
+    /// Publish the `[[mounting]]` frames on /tf_static
+    pub tf_static: bool,
+}
+
+impl Default for RosbridgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9090".to_string(),
+            topic_prefix: "/sensorhub".to_string(),
+            tf_static: true,
+        }
+    }
+}
+
+/// WebRTC data-channel output for remote viewers, matching the optional `[webrtc]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebRtcConfig {
+    pub enabled: bool,
+    /// Address of the HTTP endpoint browsers POST their SDP offer to, as host:port
+    pub signaling_addr: String,
+    /// STUN/TURN server URLs used to get through NAT
+    pub ice_servers: Vec<String>,
+    /// Highest rate each sensor is sent at (Hz), the feed is for viewing
+    pub max_rate_hz: u32,
+    /// Viewers connected at once
+    pub max_peers: usize,
+}
+
+impl Default for WebRtcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signaling_addr: "0.0.0.0:9003".to_string(),
+            ice_servers: vec!["stun:stun.l.google.com:19302".to_string()],
+            max_rate_hz: 10,
+            max_peers: 4,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+/// Aggregated temperature stream, matching the optional `[environment]` section
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnvironmentConfig {
+    /// Unit used when a StreamEnvironment client does not ask for one
+    pub unit: TemperatureUnit,
+    /// Default snapshot period of StreamEnvironment
+    pub interval_ms: u64,
+    /// Readings older than this are left out of snapshots
+    pub max_age_s: u64,
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            unit: TemperatureUnit::Celsius,
+            interval_ms: 1000,
+            max_age_s: 5,
+        }
+    }
+}
+
+/// MCAP recording of all sensor data, matching the optional `[recording]` section
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingConfig {
+    /// Record from startup (SetRecording can also start and stop it)
+    pub enabled: bool,
+    /// Directory recordings are written to
+    pub dir: String,
+    /// Start a new file after this many seconds
+    pub segment_s: u64,
+    /// Start a new file once the current one reaches this size
+    pub max_file_mb: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "recordings".to_string(),
+            segment_s: 600,
+            max_file_mb: 1024,
+        }
+    }
+}
+
+/// Per-sensor CSV files, matching the optional `[csv_log]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvLogConfig {
+    pub enabled: bool,
+    /// Each run writes its files to a new subdirectory of this one
+    pub dir: String,
+    /// How often buffered rows are written to disk
+    pub flush_interval_ms: u64,
+}
+
+impl Default for CsvLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "csv_logs".to_string(),
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+/// Built-in host health sensor, matching the optional `[system]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemConfig {
+    pub enabled: bool,
+    pub interval_ms: u64,
+    /// Disk usage is reported for the filesystem holding this path
+    pub disk_path: String,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_ms: 1000,
+            disk_path: "/".to_string(),
+        }
+    }
+}
+
+/// Publish rate anomaly detection, matching the optional `[rate_monitor]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateMonitorConfig {
+    pub enabled: bool,
+    /// Fraction below the expected rate that counts as degraded (recovery needs half of it)
+    pub tolerance: f64,
+    /// Seconds the rate must stay low before the sensor is flagged
+    pub trip_s: u32,
+    /// Seconds the rate must stay back up before the flag is cleared
+    pub clear_s: u32,
+    /// Expected messages per second by sensor id; other sensors learn theirs
+    pub expected: BTreeMap<String, u32>,
+}
+
+impl Default for RateMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tolerance: 0.25,
+            trip_s: 3,
+            clear_s: 5,
+            expected: BTreeMap::new(),
+        }
+    }
+}
+
+/// Staleness detection, matching the optional `[watchdog]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// Missed sample periods, at the sensor's measured rate, before it is marked unhealthy
+    pub stale_periods: u32,
+    /// Never flag a sensor silent for less than this, however fast it publishes
+    pub min_timeout_ms: u64,
+    pub check_interval_ms: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stale_periods: 5,
+            min_timeout_ms: 250,
+            check_interval_ms: 100,
+        }
+    }
+}
+
+/// Re-initialization of failing polled sensors, matching the optional `[recovery]` section
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecoveryConfig {
+    pub enabled: bool,
+    /// Consecutive failed reads before the driver's init() is re-run
+    pub error_threshold: u32,
+    /// Wait after a failed re-init, doubling per attempt up to `max_backoff_ms`
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            error_threshold: 10,
+            backoff_ms: 100,
+            max_backoff_ms: 5000,
+        }
+    }
+}
+
+/// Overload limits of the gRPC server, matching the optional `[grpc]` section
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcConfig {
+    /// Concurrent client streams across all streaming RPCs
+    pub max_streams: usize,
+    /// Retry pushback sent with RESOURCE_EXHAUSTED when no stream slot is free
+    pub retry_after_ms: u64,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            max_streams: 64,
+            retry_after_ms: 1000,
+        }
+    }
+}
+
+impl SensorConfig {
+    /// Reject what the types alone cannot: `[[group]]` members selecting an unknown kind
+    pub fn check(&self) -> Result<(), String> {
+        for group in self.groups.iter() {
+            let unknown = group
+                .members
+                .iter()
+                .filter_map(|m| m.kind.as_deref())
+                .find(|kind| !kinds::ALL.contains(kind));
+            if let Some(kind) = unknown {
+                return Err(format!(
+                    "group '{}' selects unknown kind '{}' (expected one of {})",
+                    group.name,
+                    kind,
+                    kinds::ALL.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Loads config from TOML file
 pub fn load_sensor_config(path: &str) -> Result<SensorConfig, std::io::Error> {
     let content = fs::read_to_string(path)?;
     let parsed: SensorConfig = toml::from_str(&content).map_err(std::io::Error::other)?;
+    parsed.check().map_err(std::io::Error::other)?;
     Ok(parsed)
 }
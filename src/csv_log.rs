@@ -0,0 +1,263 @@
+use crate::config::sensor_config::CsvLogConfig;
+use crate::grpc_service::SensorHubService;
+use crate::messages::SensorMessage;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+/// Flatten a message body into `(column, value)` pairs
+///
+/// Header fields come first and keep their own names (`seq`, `t_utc_ns`, ...), nested
+/// fields are joined with dots (`precise.0`, `escs.1.rpm`).
+fn flatten(prefix: &str, value: &Value, out: &mut Vec<(String, Value)>) {
+    let name = |key: &str| match prefix {
+        "" | "h" => key.to_string(),
+        _ => format!("{}.{}", prefix, key),
+    };
+    match value {
+        Value::Object(fields) => {
+            let header = fields.iter().filter(|(key, _)| *key == "h");
+            let rest = fields.iter().filter(|(key, _)| *key != "h");
+            for (key, field) in header.chain(rest) {
+                flatten(&name(key), field, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(&name(&i.to_string()), item, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// One CSV cell; f32 fields are printed at f32 precision (serde widens them to f64)
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Number(n) => match (n.as_f64(), n.is_f64()) {
+            (Some(f), true) if (f as f32) as f64 == f => (f as f32).to_string(),
+            _ => n.to_string(),
+        },
+        Value::String(s) if s.contains([',', '"', '\n', '\r']) => {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        }
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// An open CSV file; its columns are fixed by the first message written to it
+///
+/// A message with columns the header lacks (an optional field set for the first time, a
+/// longer ESC list) continues the log in a new part, `<sensor_id>_<kind>.<part>.csv`, whose
+/// header adds them.
+struct CsvFile {
+    file: BufWriter<File>,
+    columns: Vec<String>,
+    /// `<dir>/<sensor_id>_<kind>`, without part number and extension
+    base: PathBuf,
+    part: u32,
+}
+
+impl CsvFile {
+    fn create(base: PathBuf, part: u32, columns: Vec<String>) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(Self::path(&base, part))?);
+        writeln!(file, "{}", columns.join(","))?;
+        Ok(Self {
+            file,
+            columns,
+            base,
+            part,
+        })
+    }
+
+    fn path(base: &Path, part: u32) -> PathBuf {
+        let mut name = base.as_os_str().to_owned();
+        if part > 0 {
+            name.push(format!(".{}", part));
+        }
+        name.push(".csv");
+        PathBuf::from(name)
+    }
+
+    /// Write a row; columns missing from it are left empty
+    fn write(&mut self, row: &[(String, Value)]) -> std::io::Result<()> {
+        let values: HashMap<&str, &Value> = row.iter().map(|(c, v)| (c.as_str(), v)).collect();
+        let known = self
+            .columns
+            .iter()
+            .filter(|column| values.contains_key(column.as_str()))
+            .count();
+        if known < values.len() {
+            let mut columns = self.columns.clone();
+            for (column, _) in row {
+                if !self.columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+            self.file.flush()?;
+            *self = Self::create(self.base.clone(), self.part + 1, columns)?;
+            info!(
+                "[csv_log] New columns, continuing in {}",
+                Self::path(&self.base, self.part).display()
+            );
+        }
+        let line: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                values
+                    .get(column.as_str())
+                    .map(|v| cell(v))
+                    .unwrap_or_default()
+            })
+            .collect();
+        writeln!(self.file, "{}", line.join(","))
+    }
+}
+
+/// Write every published message to `<dir>/<run>/<sensor_id>_<kind>.csv`, if enabled
+pub fn spawn_logger(config: CsvLogConfig, grpc_service: &SensorHubService) {
+    if !config.enabled {
+        return;
+    }
+    let run = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let dir = PathBuf::from(&config.dir).join(run.to_string());
+    if let Err(e) = fs::create_dir_all(&dir) {
+        error!("[csv_log] Cannot create {}: {}", dir.display(), e);
+        return;
+    }
+    info!("[csv_log] Logging sensor data to {}", dir.display());
+
+    let mut rx = grpc_service.subscribe_messages();
+    let flush_interval = Duration::from_millis(config.flush_interval_ms);
+    // Files are written from a dedicated thread so disk stalls never block the runtime
+    let spawned = std::thread::Builder::new()
+        .name("csv-log".to_string())
+        .spawn(move || {
+            let mut files: HashMap<(String, &'static str), CsvFile> = HashMap::new();
+            let mut flushed = Instant::now();
+            loop {
+                let message = match rx.blocking_recv() {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("[csv_log] Fell behind, {} message(s) not logged", n);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Err(e) = log(&dir, &mut files, &message) {
+                    error!("[csv_log] Write failed, logging stopped: {}", e);
+                    break;
+                }
+                if flushed.elapsed() >= flush_interval {
+                    flushed = Instant::now();
+                    for file in files.values_mut() {
+                        let _ = file.file.flush();
+                    }
+                }
+            }
+            for file in files.values_mut() {
+                let _ = file.file.flush();
+            }
+        });
+    if let Err(e) = spawned {
+        error!("[csv_log] Cannot start the logger thread: {}", e);
+    }
+}
+
+fn log(
+    dir: &Path,
+    files: &mut HashMap<(String, &'static str), CsvFile>,
+    message: &SensorMessage,
+) -> std::io::Result<()> {
+    // Externally tagged: {"Imu": {"h": {...}, "ax": ...}}
+    let value = serde_json::to_value(message)?;
+    let Some(body) = value.as_object().and_then(|o| o.values().next()) else {
+        return Ok(());
+    };
+    let mut row = Vec::new();
+    flatten("", body, &mut row);
+
+    let key = (message.header().sensor_id.clone(), message.kind());
+    match files.get_mut(&key) {
+        Some(file) => file.write(&row),
+        None => {
+            let base = dir.join(format!("{}_{}", key.0, key.1));
+            let columns = row.iter().map(|(column, _)| column.clone()).collect();
+            let mut file = CsvFile::create(base, 0, columns)?;
+            file.write(&row)?;
+            files.insert(key, file);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten_row() {
+        let body = json!({
+            "h": { "sensor_id": "baro0", "seq": 7 },
+            "pressure": 101325.0f32,
+            "temperature": 21.3f32,
+            "precise": [1.5, 2.0],
+            "label": "a,\"b\"",
+            "missing": null,
+        });
+        let mut row = Vec::new();
+        flatten("", &body, &mut row);
+        let line: Vec<(String, String)> = row.iter().map(|(c, v)| (c.clone(), cell(v))).collect();
+        let expected = [
+            ("sensor_id", "baro0"),
+            ("seq", "7"),
+            ("label", "\"a,\"\"b\"\"\""),
+            ("missing", ""),
+            ("precise.0", "1.5"),
+            ("precise.1", "2"),
+            ("pressure", "101325"),
+            ("temperature", "21.3"),
+        ];
+        assert_eq!(line, expected.map(|(c, v)| (c.to_string(), v.to_string())));
+    }
+
+    #[test]
+    fn test_new_columns_continue_in_a_new_part() {
+        let dir = std::env::temp_dir().join(format!("sensorhub-csv-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let row = |fields: &[(&str, f64)]| -> Vec<(String, Value)> {
+            fields
+                .iter()
+                .map(|(c, v)| (c.to_string(), Value::from(*v)))
+                .collect()
+        };
+        let first = row(&[("seq", 1.0), ("ax", 0.5)]);
+        let columns = first.iter().map(|(c, _)| c.clone()).collect();
+        let mut file = CsvFile::create(dir.join("imu0_imu"), 0, columns).unwrap();
+        file.write(&first).unwrap();
+        file.write(&row(&[("seq", 2.0), ("ax", 0.25), ("precise.0", 1.5)]))
+            .unwrap();
+        file.write(&row(&[("seq", 3.0)])).unwrap();
+        file.file.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("imu0_imu.csv"), "seq,ax\n1,0.5\n");
+        assert_eq!(
+            read("imu0_imu.1.csv"),
+            "seq,ax,precise.0\n2,0.25,1.5\n3,,\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
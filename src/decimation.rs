@@ -0,0 +1,184 @@
+use crate::config::sensor_config::DecimateEntry;
+use crate::messages::{ImuMessage, SensorMessage};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Running block of one sensor's samples
+struct Block {
+    factor: u32,
+    average: bool,
+    count: u32,
+    /// Per field of the message type, and for double-precision IMU output
+    sums: Vec<f64>,
+    precise_sums: [f64; 6],
+    /// Sample times relative to the block's first one, so their sum cannot overflow
+    first_t_ns: u64,
+    t_offset_sum: u64,
+}
+
+impl Block {
+    fn new(entry: &DecimateEntry) -> Self {
+        Self {
+            factor: entry.factor,
+            average: entry.average,
+            count: 0,
+            sums: Vec::new(),
+            precise_sums: [0.0; 6],
+            first_t_ns: 0,
+            t_offset_sum: 0,
+        }
+    }
+
+    fn push(&mut self, mut message: SensorMessage) -> Option<SensorMessage> {
+        // Angles wrap and go with a quaternion, so attitude is only ever decimated
+        let average = self.average && !matches!(message, SensorMessage::Attitude(_));
+        if average {
+            self.accumulate(&mut message);
+        }
+        self.count += 1;
+        if self.count < self.factor {
+            return None;
+        }
+        if average {
+            let n = f64::from(self.count);
+            for (field, sum) in message.fields().iter().zip(self.sums.iter()) {
+                if let Some(value) = message.field_mut(field) {
+                    *value = (sum / n) as f32;
+                }
+            }
+            if let SensorMessage::Imu(ImuMessage {
+                precise: Some(precise),
+                ..
+            }) = &mut message
+            {
+                *precise = self.precise_sums.map(|sum| sum / n);
+            }
+            // The mean stands for the middle of the block, on both clocks
+            let mean_ns = self.first_t_ns + self.t_offset_sum / self.count as u64;
+            let header = message.header_mut();
+            header.t_mono_ns = header
+                .t_mono_ns
+                .saturating_sub(header.t_utc_ns.saturating_sub(mean_ns));
+            header.t_utc_ns = mean_ns;
+        }
+        self.count = 0;
+        Some(message)
+    }
+
+    fn accumulate(&mut self, message: &mut SensorMessage) {
+        let t_ns = message.header().t_utc_ns;
+        if self.count == 0 {
+            self.sums = vec![0.0; message.fields().len()];
+            self.precise_sums = [0.0; 6];
+            self.first_t_ns = t_ns;
+            self.t_offset_sum = 0;
+        }
+        self.t_offset_sum += t_ns.saturating_sub(self.first_t_ns);
+        for (field, sum) in message.fields().iter().zip(self.sums.iter_mut()) {
+            if let Some(value) = message.field_mut(field) {
+                *sum += f64::from(*value);
+            }
+        }
+        if let SensorMessage::Imu(ImuMessage {
+            precise: Some(precise),
+            ..
+        }) = message
+        {
+            for (sum, value) in self.precise_sums.iter_mut().zip(precise.iter()) {
+                *sum += value;
+            }
+        }
+    }
+}
+
+/// Per-sensor output decimation: sensors sample and feed on-board consumers at full rate,
+/// while gRPC clients get every Nth sample or the mean of each N
+pub struct Decimator {
+    blocks: Mutex<HashMap<String, Block>>,
+}
+
+impl Decimator {
+    pub fn new() -> Self {
+        Self {
+            blocks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Apply the `[[decimate]]` entries, restarting any running blocks
+    pub fn configure(&self, entries: &[DecimateEntry]) {
+        let mut blocks = HashMap::new();
+        for entry in entries {
+            if entry.factor < 2 {
+                warn!(
+                    "[decimate] {}: factor {} does not reduce the rate, ignored",
+                    entry.sensor, entry.factor
+                );
+                continue;
+            }
+            info!(
+                "[decimate] {}: 1 in {}{}",
+                entry.sensor,
+                entry.factor,
+                if entry.average { ", averaged" } else { "" }
+            );
+            blocks.insert(entry.sensor.clone(), Block::new(entry));
+        }
+        *self.blocks.lock().unwrap() = blocks;
+    }
+
+    /// The message to publish, or None while its sensor's block is still filling
+    pub fn apply(&self, message: SensorMessage) -> Option<SensorMessage> {
+        let mut blocks = self.blocks.lock().unwrap();
+        match blocks.get_mut(&message.header().sensor_id) {
+            Some(block) => block.push(message),
+            None => Some(message),
+        }
+    }
+}
+
+impl Default for Decimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{BarometerMessage, Header};
+
+    #[test]
+    fn test_averages_blocks() {
+        let decimator = Decimator::new();
+        decimator.configure(&[DecimateEntry {
+            sensor: "baro0".into(),
+            factor: 4,
+            average: true,
+        }]);
+
+        let published: Vec<BarometerMessage> = (0..10u64)
+            .filter_map(|i| {
+                let mut h = Header::new("hub".into(), "baro0".into(), "baro".into(), i);
+                h.t_utc_ns = 1_000_000_000 + i * 1_000_000;
+                decimator.apply(SensorMessage::Barometer(BarometerMessage {
+                    h,
+                    pressure: 1000.0 + i as f32,
+                    temperature: 20.0,
+                    altitude: 100.0,
+                }))
+            })
+            .map(|message| match message {
+                SensorMessage::Barometer(baro) => baro,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        // Two full blocks; the last two samples wait for the next
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[0].pressure, 1001.5);
+        assert_eq!(published[1].pressure, 1005.5);
+        assert_eq!(published[1].h.seq, 7);
+        assert_eq!(published[1].h.t_utc_ns, 1_005_500_000);
+    }
+}
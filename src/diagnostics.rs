@@ -0,0 +1,344 @@
+use crate::config::load_bus_config;
+use crate::config::sensor_config::DiagnosticsConfig;
+use crate::grpc_service::sensorhub::{DiagnosticBundle, SensorData};
+use crate::grpc_service::SensorHubService;
+use prost::Message;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Bytes of formatted log output kept for diagnostic bundles
+pub const LOG_RING_BYTES: usize = 256 * 1024;
+/// Most recent StreamAll messages kept in the black box
+const BLACKBOX_MESSAGES: usize = 2000;
+/// How often buses are checked for silence
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+/// Bundle file name prefix (also what retrieval and rotation look for)
+pub const BUNDLE_PREFIX: &str = "diag-";
+
+/// In-memory tail of the hub log, installed as a tracing writer
+#[derive(Clone)]
+pub struct LogRing {
+    buf: Arc<Mutex<VecDeque<u8>>>,
+    capacity: usize,
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Copy of the retained log, starting at the first complete line
+    pub fn snapshot(&self) -> Vec<u8> {
+        let buf = self.buf.lock().unwrap();
+        let mut bytes: Vec<u8> = buf.iter().copied().collect();
+        if buf.len() == self.capacity {
+            let start = bytes.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+            bytes.drain(..start);
+        }
+        bytes
+    }
+}
+
+impl std::io::Write for LogRing {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let mut buf = self.buf.lock().unwrap();
+        let keep = data.len().min(self.capacity);
+        let overflow = (buf.len() + keep).saturating_sub(self.capacity);
+        buf.drain(..overflow);
+        buf.extend(&data[data.len() - keep..]);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogRing {
+    type Writer = LogRing;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Longest name a ustar header holds itself
+const TAR_NAME_LEN: usize = 100;
+
+/// Build an uncompressed ustar archive from (path, contents) pairs
+///
+/// Paths longer than a ustar header holds are carried in a PAX extended header.
+pub fn write_tar(entries: &[(String, Vec<u8>)], mtime: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut append = |name: &[u8], typeflag: u8, data: &[u8]| {
+        out.extend_from_slice(&tar_header(name, typeflag, data.len(), mtime));
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(512), 0);
+    };
+    for (path, data) in entries {
+        let name = path.as_bytes();
+        if name.len() > TAR_NAME_LEN {
+            append(b"././@PaxHeader", b'x', &pax_record("path", path));
+        }
+        append(&name[..name.len().min(TAR_NAME_LEN)], b'0', data);
+    }
+    // End-of-archive marker
+    out.resize(out.len() + 1024, 0);
+    out
+}
+
+fn tar_header(name: &[u8], typeflag: u8, size: usize, mtime: u64) -> [u8; 512] {
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name);
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    header[136..148].copy_from_slice(format!("{:011o}\0", mtime).as_bytes());
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // Checksum is computed with its own field set to spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    header
+}
+
+/// One PAX record, `<length> <key>=<value>\n`, where the length counts its own digits
+fn pax_record(key: &str, value: &str) -> Vec<u8> {
+    let body = key.len() + value.len() + 3; // space, '=' and newline
+    let mut len = body + 1;
+    while len != body + len.to_string().len() {
+        len = body + len.to_string().len();
+    }
+    format!("{} {}={}\n", len, key, value).into_bytes()
+}
+
+/// Watch every bus for sustained silence and write a diagnostic bundle when one goes quiet
+///
+/// Bus liveness comes from the StreamAll feed (the `source` attached to each message), which
+/// also fills the black-box ring dumped into the bundle. Every bus in `buses.toml` counts as
+/// last seen at startup, so one that never delivers is reported too. A bus is reported once
+/// per outage.
+pub fn spawn_watchdog(
+    config: &DiagnosticsConfig,
+    config_dir: PathBuf,
+    log_ring: LogRing,
+    grpc_service: Arc<SensorHubService>,
+) {
+    if !config.enabled {
+        info!("[diagnostics] Watchdog disabled");
+        return;
+    }
+    let bundle_dir = PathBuf::from(&config.bundle_dir);
+    let failure_timeout = Duration::from_secs(config.failure_timeout_s);
+    let keep_bundles = config.keep_bundles.max(1);
+    grpc_service.set_diagnostics_dir(bundle_dir.clone());
+    let mut rx = grpc_service.subscribe_all();
+    let started = Instant::now();
+    let mut last_seen: HashMap<String, Instant> = HashMap::new();
+    match load_bus_config(&config_dir.join("buses.toml").to_string_lossy()) {
+        Ok(bus_config) => {
+            for bus in bus_config.buses {
+                last_seen.insert(bus.id, started);
+            }
+        }
+        Err(e) => warn!(
+            "[diagnostics] Could not read buses.toml, watching buses once they deliver: {}",
+            e
+        ),
+    }
+
+    tokio::spawn(async move {
+        info!(
+            "[diagnostics] Watchdog started: bundles after {}s of bus silence, in {}",
+            failure_timeout.as_secs(),
+            bundle_dir.display()
+        );
+        let mut blackbox: VecDeque<SensorData> = VecDeque::with_capacity(BLACKBOX_MESSAGES);
+        let mut failed: HashSet<String> = HashSet::new();
+        let mut ticker = tokio::time::interval(WATCHDOG_INTERVAL);
+
+        loop {
+            tokio::select! {
+                result = rx.recv() => match result {
+                    Ok(data) => {
+                        if let Some(bus_id) = data.source.as_ref().map(|s| s.bus_id.clone()) {
+                            if failed.remove(&bus_id) {
+                                info!("[diagnostics] Bus {} is delivering data again", bus_id);
+                            }
+                            last_seen.insert(bus_id, Instant::now());
+                        }
+                        if blackbox.len() == BLACKBOX_MESSAGES {
+                            blackbox.pop_front();
+                        }
+                        blackbox.push_back(data);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = ticker.tick() => {
+                    let silent: Vec<String> = last_seen
+                        .iter()
+                        .filter(|(bus_id, seen)| {
+                            seen.elapsed() > failure_timeout && !failed.contains(*bus_id)
+                        })
+                        .map(|(bus_id, _)| bus_id.clone())
+                        .collect();
+                    for bus_id in silent {
+                        failed.insert(bus_id.clone());
+                        let reason = format!(
+                            "bus {}: no data for {}s",
+                            bus_id,
+                            failure_timeout.as_secs()
+                        );
+                        warn!("[diagnostics] Sustained failure, {}", reason);
+
+                        let bundle = BundleContents {
+                            reason,
+                            logs: log_ring.snapshot(),
+                            blackbox: blackbox.iter().fold(Vec::new(), |mut buf, data| {
+                                data.encode_length_delimited(&mut buf).ok();
+                                buf
+                            }),
+                        };
+                        match write_bundle(&bundle, &bus_id, &bundle_dir, &config_dir, &grpc_service).await {
+                            Ok(announced) => {
+                                info!("[diagnostics] Wrote bundle {} ({} bytes)", announced.name, announced.size_bytes);
+                                prune_bundles(&bundle_dir, keep_bundles).await;
+                                grpc_service.announce_diagnostic_bundle(announced);
+                            }
+                            Err(e) => error!("[diagnostics] Failed to write bundle: {}", e),
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+struct BundleContents {
+    reason: String,
+    logs: Vec<u8>,
+    /// Length-delimited SensorData protobufs, oldest first
+    blackbox: Vec<u8>,
+}
+
+async fn write_bundle(
+    contents: &BundleContents,
+    bus_id: &str,
+    bundle_dir: &Path,
+    config_dir: &Path,
+    grpc_service: &SensorHubService,
+) -> std::io::Result<DiagnosticBundle> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!("{}{}-{}.tar", BUNDLE_PREFIX, now.as_secs(), bus_id);
+
+    let sensors = grpc_service.sensor_list().await;
+    let statuses = grpc_service.sensor_statuses().await;
+    let status = format!(
+        "reason: {}\nt_utc_ns: {}\n\nsensors:\n{:#?}\n\nstatus:\n{:#?}\n",
+        contents.reason,
+        now.as_nanos(),
+        sensors,
+        statuses
+    );
+
+    let mut entries = vec![
+        ("status.txt".to_string(), status.into_bytes()),
+        ("hub.log".to_string(), contents.logs.clone()),
+        ("blackbox.pb".to_string(), contents.blackbox.clone()),
+    ];
+    let i2c_trace = grpc_service.i2c_trace().snapshot();
+    if !i2c_trace.is_empty() {
+        let lines: String = i2c_trace.iter().map(|t| format!("{}\n", t)).collect();
+        entries.push(("i2c_trace.txt".to_string(), lines.into_bytes()));
+    }
+    for file in ["sensors.toml", "buses.toml"] {
+        if let Ok(data) = tokio::fs::read(config_dir.join(file)).await {
+            entries.push((format!("config/{}", file), data));
+        }
+    }
+    let archive = write_tar(&entries, now.as_secs());
+
+    tokio::fs::create_dir_all(bundle_dir).await?;
+    tokio::fs::write(bundle_dir.join(&name), &archive).await?;
+
+    Ok(DiagnosticBundle {
+        name,
+        reason: contents.reason.clone(),
+        size_bytes: archive.len() as u64,
+    })
+}
+
+/// Delete the oldest bundles so at most `keep` remain
+async fn prune_bundles(bundle_dir: &Path, keep: usize) {
+    let Ok(mut dir) = tokio::fs::read_dir(bundle_dir).await else {
+        return;
+    };
+    let mut names = Vec::new();
+    while let Ok(Some(entry)) = dir.next_entry().await {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(BUNDLE_PREFIX) && name.ends_with(".tar") {
+            names.push(name);
+        }
+    }
+    // Names embed the UTC second, so lexical order is age order
+    names.sort();
+    for name in names.iter().take(names.len().saturating_sub(keep)) {
+        if let Err(e) = tokio::fs::remove_file(bundle_dir.join(name)).await {
+            warn!("[diagnostics] Failed to remove old bundle {}: {}", name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_tar_layout_and_log_ring() {
+        let archive = write_tar(&[("a.txt".to_string(), b"hello".to_vec())], 0);
+        assert_eq!(archive.len(), 512 + 512 + 1024);
+        assert_eq!(&archive[..5], b"a.txt");
+        assert_eq!(&archive[124..135], b"00000000005");
+        assert_eq!(&archive[512..517], b"hello");
+
+        let stored = u32::from_str_radix(std::str::from_utf8(&archive[148..154]).unwrap(), 8);
+        let mut header = archive[..512].to_vec();
+        header[148..156].copy_from_slice(b"        ");
+        assert_eq!(
+            stored.unwrap(),
+            header.iter().map(|&b| b as u32).sum::<u32>()
+        );
+
+        // A path too long for the header goes first in a PAX record
+        let long = format!("logs/{}.log", "x".repeat(120));
+        let archive = write_tar(&[(long.clone(), b"hi".to_vec())], 0);
+        assert_eq!(archive.len(), 4 * 512 + 1024);
+        assert_eq!(archive[156], b'x');
+        let record = format!("139 path={}\n", long);
+        assert_eq!(record.len(), 139);
+        assert_eq!(&archive[512..512 + record.len()], record.as_bytes());
+        assert_eq!(archive[1024 + 156], b'0');
+        assert_eq!(&archive[1024..1124], &long.as_bytes()[..100]);
+        assert_eq!(&archive[1536..1538], b"hi");
+
+        let mut ring = LogRing::new(16);
+        ring.write_all(b"first line\nsecond\nthird\n").unwrap();
+        assert_eq!(ring.snapshot(), b"second\nthird\n");
+    }
+}
@@ -0,0 +1,389 @@
+use crate::config::sensor_config::DownlinkConfig;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// How often measured demand is turned into new allocations
+const ALLOCATION_WINDOW: Duration = Duration::from_secs(1);
+
+/// Bandwidth share of one sensor stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Allocation {
+    pub priority: i32,
+    /// Measured publish rate for one client (bit/s)
+    pub demand_bps: u64,
+    /// Rate this sensor may use per client (bit/s)
+    pub allocated_bps: u64,
+    /// Fraction of messages delivered (1.0 = no decimation)
+    pub keep_ratio: f32,
+}
+
+/// Split a per-client budget across sensors, highest priority first
+///
+/// Within a priority tier cheaper streams are served first so a single heavy
+/// stream cannot starve several light ones.
+pub fn allocate(budget_bps: u64, demands: &[(String, i32, u64)]) -> HashMap<String, Allocation> {
+    let mut order: Vec<&(String, i32, u64)> = demands.iter().collect();
+    order.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)));
+
+    let mut remaining = budget_bps;
+    order
+        .into_iter()
+        .map(|(sensor_id, priority, demand_bps)| {
+            let allocated_bps = (*demand_bps).min(remaining);
+            remaining -= allocated_bps;
+            let keep_ratio = if *demand_bps == 0 {
+                1.0
+            } else {
+                allocated_bps as f32 / *demand_bps as f32
+            };
+            (
+                sensor_id.clone(),
+                Allocation {
+                    priority: *priority,
+                    demand_bps: *demand_bps,
+                    allocated_bps,
+                    keep_ratio,
+                },
+            )
+        })
+        .collect()
+}
+
+/// A connected client stream and what its queue has done so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientStream {
+    pub stream: String,
+    pub peer: String,
+    /// Requested sensors, rate cap and tag, as logged on subscribe
+    pub filters: String,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub shed: u64,
+    pub lagged: u64,
+}
+
+/// Running totals of a client stream, updated by its forwarder without taking a lock
+#[derive(Default)]
+struct ClientCounters {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    shed: AtomicU64,
+    lagged: AtomicU64,
+}
+
+/// A connected client stream as registered, with live counters
+#[derive(Default)]
+struct ClientEntry {
+    stream: String,
+    peer: String,
+    filters: String,
+    counters: Arc<ClientCounters>,
+}
+
+struct BudgetState {
+    /// Total egress budget across all clients (bit/s, 0 = unlimited)
+    budget_bps: u64,
+    priorities: HashMap<String, i32>,
+    window_start: Instant,
+    window_bytes: HashMap<String, u64>,
+    allocations: HashMap<String, Allocation>,
+}
+
+/// Egress budget for client streams, enforced by per-sensor decimation
+///
+/// Demand is measured from what the hub publishes (the size of each message on
+/// StreamAll). The budget is shared evenly by connected client streams, and each
+/// client stream drops messages of a sensor down to that sensor's keep ratio.
+pub struct DownlinkBudget {
+    state: Mutex<BudgetState>,
+    /// Whether a budget is set, checked before anything is measured
+    enabled: AtomicBool,
+    /// Current keep ratio of each sensor (f32 bits), shared with the client streams
+    keep_ratios: Mutex<HashMap<String, Arc<AtomicU32>>>,
+    /// Connected client streams by subscription id
+    clients: Mutex<BTreeMap<u64, ClientEntry>>,
+}
+
+impl DownlinkBudget {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(BudgetState {
+                budget_bps: 0,
+                priorities: HashMap::new(),
+                window_start: Instant::now(),
+                window_bytes: HashMap::new(),
+                allocations: HashMap::new(),
+            }),
+            enabled: AtomicBool::new(false),
+            keep_ratios: Mutex::new(HashMap::new()),
+            clients: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Apply the `[downlink]` settings
+    pub fn configure(&self, config: &DownlinkConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.budget_bps = config.budget_kbps as u64 * 1000;
+        state.priorities = config
+            .streams
+            .iter()
+            .map(|s| (s.sensor.clone(), s.priority))
+            .collect();
+        state.allocations.clear();
+        self.publish_keep_ratios(&state.allocations);
+        self.enabled.store(state.budget_bps > 0, Ordering::Relaxed);
+        if state.budget_bps > 0 {
+            info!(
+                "[downlink] Egress budget {} kbit/s, {} prioritized stream(s)",
+                config.budget_kbps,
+                state.priorities.len()
+            );
+        }
+    }
+
+    /// Account for a published message of `bytes()` bytes and refresh allocations once per
+    /// window; nothing is measured while no budget is set
+    pub fn record(&self, sensor_id: &str, bytes: impl FnOnce() -> usize) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let bits = bytes() as u64 * 8;
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if let Some(window) = state.window_bytes.get_mut(sensor_id) {
+            *window += bits;
+        } else {
+            state.window_bytes.insert(sensor_id.to_string(), bits);
+        }
+
+        let elapsed = state.window_start.elapsed();
+        if elapsed < ALLOCATION_WINDOW {
+            return;
+        }
+        let demands: Vec<(String, i32, u64)> = state
+            .window_bytes
+            .drain()
+            .map(|(id, bits)| {
+                let priority = state.priorities.get(&id).copied().unwrap_or(0);
+                (id, priority, (bits as f64 / elapsed.as_secs_f64()) as u64)
+            })
+            .collect();
+        let clients = self.clients.lock().unwrap().len().max(1) as u64;
+        state.allocations = allocate(state.budget_bps / clients, &demands);
+        state.window_start = Instant::now();
+        self.publish_keep_ratios(&state.allocations);
+        debug!("[downlink] Allocations: {:?}", state.allocations);
+    }
+
+    /// Shared slot holding the fraction of a sensor's messages each client should receive
+    fn keep_ratio_slot(&self, sensor_id: &str) -> Arc<AtomicU32> {
+        self.keep_ratios
+            .lock()
+            .unwrap()
+            .entry(sensor_id.to_string())
+            .or_insert_with(|| Arc::new(AtomicU32::new(1.0f32.to_bits())))
+            .clone()
+    }
+
+    /// Hand new allocations to the client streams
+    fn publish_keep_ratios(&self, allocations: &HashMap<String, Allocation>) {
+        let mut slots = self.keep_ratios.lock().unwrap();
+        for sensor_id in allocations.keys() {
+            if !slots.contains_key(sensor_id) {
+                slots.insert(sensor_id.clone(), Arc::default());
+            }
+        }
+        for (sensor_id, slot) in slots.iter() {
+            let keep_ratio = allocations.get(sensor_id).map_or(1.0, |a| a.keep_ratio);
+            slot.store(keep_ratio.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Total budget (bit/s, 0 = unlimited), connected clients and current allocations
+    pub fn snapshot(
+        &self,
+    ) -> (
+        u64,
+        BTreeMap<u64, ClientStream>,
+        HashMap<String, Allocation>,
+    ) {
+        let state = self.state.lock().unwrap();
+        let clients = self
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, c)| {
+                let stream = ClientStream {
+                    stream: c.stream.clone(),
+                    peer: c.peer.clone(),
+                    filters: c.filters.clone(),
+                    delivered: c.counters.delivered.load(Ordering::Relaxed),
+                    dropped: c.counters.dropped.load(Ordering::Relaxed),
+                    shed: c.counters.shed.load(Ordering::Relaxed),
+                    lagged: c.counters.lagged.load(Ordering::Relaxed),
+                };
+                (id, stream)
+            })
+            .collect();
+        (state.budget_bps, clients, state.allocations.clone())
+    }
+
+    /// Name the stream, peer and filters a subscription was opened with
+    pub fn describe_client(&self, subscription_id: u64, stream: &str, peer: &str, filters: &str) {
+        let mut clients = self.clients.lock().unwrap();
+        let client = clients.entry(subscription_id).or_default();
+        client.stream = stream.to_string();
+        client.peer = peer.to_string();
+        client.filters = filters.to_string();
+    }
+
+    /// Count a client stream against the budget until the guard is dropped
+    pub fn client_connected(self: &Arc<Self>, subscription_id: u64) -> ClientGuard {
+        let counters = self
+            .clients
+            .lock()
+            .unwrap()
+            .entry(subscription_id)
+            .or_default()
+            .counters
+            .clone();
+        ClientGuard {
+            budget: self.clone(),
+            subscription_id,
+            counters,
+            keep_ratios: HashMap::new(),
+        }
+    }
+}
+
+impl Default for DownlinkBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ClientGuard {
+    budget: Arc<DownlinkBudget>,
+    subscription_id: u64,
+    counters: Arc<ClientCounters>,
+    /// Keep ratio slots of the sensors this stream has seen
+    keep_ratios: HashMap<String, Arc<AtomicU32>>,
+}
+
+impl ClientGuard {
+    /// Fraction of a sensor's messages this client should receive
+    pub fn keep_ratio(&mut self, sensor_id: &str) -> f32 {
+        if !self.budget.enabled.load(Ordering::Relaxed) {
+            return 1.0;
+        }
+        if !self.keep_ratios.contains_key(sensor_id) {
+            let slot = self.budget.keep_ratio_slot(sensor_id);
+            self.keep_ratios.insert(sensor_id.to_string(), slot);
+        }
+        f32::from_bits(self.keep_ratios[sensor_id].load(Ordering::Relaxed))
+    }
+
+    /// Publish the stream's running totals
+    pub fn report(&self, delivered: u64, dropped: u64, shed: u64, lagged: u64) {
+        let counters = &self.counters;
+        counters.delivered.store(delivered, Ordering::Relaxed);
+        counters.dropped.store(dropped, Ordering::Relaxed);
+        counters.shed.store(shed, Ordering::Relaxed);
+        counters.lagged.store(lagged, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.budget
+            .clients
+            .lock()
+            .unwrap()
+            .remove(&self.subscription_id);
+    }
+}
+
+/// Per-client decimator: keeps a sensor's messages at its keep ratio
+#[derive(Default)]
+pub struct Decimator {
+    credit: HashMap<String, f32>,
+}
+
+impl Decimator {
+    /// Whether the next message of `sensor_id` should be delivered
+    pub fn admit(&mut self, sensor_id: &str, keep_ratio: f32) -> bool {
+        if keep_ratio >= 1.0 {
+            self.credit.remove(sensor_id);
+            return true;
+        }
+        let credit = self.credit.entry(sensor_id.to_string()).or_insert(0.0);
+        *credit += keep_ratio;
+        if *credit >= 1.0 {
+            *credit -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_by_priority_and_decimate() {
+        let demands = vec![
+            ("imu".to_string(), 0, 40_000),
+            ("attitude".to_string(), 10, 5_000),
+            ("gps".to_string(), 0, 1_000),
+        ];
+        let alloc = allocate(26_000, &demands);
+        assert_eq!(alloc["attitude"].allocated_bps, 5_000);
+        assert_eq!(alloc["gps"].allocated_bps, 1_000);
+        assert_eq!(alloc["imu"].allocated_bps, 20_000);
+        assert_eq!(alloc["imu"].keep_ratio, 0.5);
+
+        let mut decimator = Decimator::default();
+        let kept = (0..10).filter(|_| decimator.admit("imu", 0.5)).count();
+        assert_eq!(kept, 5);
+        assert!(decimator.admit("gps", 1.0));
+    }
+
+    #[test]
+    fn test_clients_listed_by_subscription_id() {
+        let budget = Arc::new(DownlinkBudget::new());
+        budget.describe_client(7, "imu", "10.0.0.2:5000", "(max 10 Hz)");
+        let client = budget.client_connected(7);
+        client.report(3, 1, 0, 2);
+
+        let (_, clients, _) = budget.snapshot();
+        let entry = &clients[&7];
+        assert_eq!(entry.stream, "imu");
+        assert_eq!(entry.filters, "(max 10 Hz)");
+        assert_eq!((entry.delivered, entry.dropped, entry.lagged), (3, 1, 2));
+
+        drop(client);
+        assert!(budget.snapshot().1.is_empty());
+    }
+
+    #[test]
+    fn test_no_budget_measures_nothing() {
+        let budget = Arc::new(DownlinkBudget::new());
+        budget.record("imu", || unreachable!("sized while no budget is set"));
+        let mut client = budget.client_connected(1);
+        assert_eq!(client.keep_ratio("imu"), 1.0);
+
+        budget.configure(&DownlinkConfig {
+            budget_kbps: 10,
+            ..Default::default()
+        });
+        budget.record("imu", || 100);
+        assert_eq!(client.keep_ratio("imu"), 1.0);
+        assert_eq!(budget.snapshot().0, 10_000);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::sensor_config::{EnvironmentConfig, TemperatureUnit};
+use crate::messages::{Header, SensorMessage};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::RwLock;
+
+impl TemperatureUnit {
+    /// Express a Celsius reading in this unit
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+}
+
+/// Latest temperature of one source
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureSample {
+    /// Source label: the sensor id, `<sensor_id>/esc<n>` for ESCs or `system/<zone>` for
+    /// thermal zones of the host
+    pub source: String,
+    pub sensor_id: String,
+    /// Message kind the reading came from (imu, barometer, airspeed, esc, system)
+    pub kind: &'static str,
+    pub celsius: f32,
+    pub t_utc_ns: u64,
+}
+
+/// Temperatures gathered from every published message that carries one
+pub struct Environment {
+    config: RwLock<EnvironmentConfig>,
+    samples: RwLock<BTreeMap<String, TemperatureSample>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(EnvironmentConfig::default()),
+            samples: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn configure(&self, config: &EnvironmentConfig) {
+        *self.config.write().unwrap() = config.clone();
+    }
+
+    pub fn config(&self) -> EnvironmentConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn observe(&self, message: &SensorMessage) {
+        let h = message.header();
+        let kind = message.kind();
+        let sensor_id = h.sensor_id.as_str();
+        match message {
+            SensorMessage::Imu(m) => {
+                if let Some(celsius) = m.temperature {
+                    self.record(sensor_id, h, kind, celsius);
+                }
+            }
+            SensorMessage::Barometer(m) => self.record(sensor_id, h, kind, m.temperature),
+            SensorMessage::Airspeed(m) => {
+                if let Some(celsius) = m.temperature {
+                    self.record(sensor_id, h, kind, celsius);
+                }
+            }
+            SensorMessage::EscTelemetry(m) => {
+                let mut source = String::new();
+                for esc in m.escs.iter() {
+                    if let Some(celsius) = esc.temperature {
+                        source.clear();
+                        let _ = write!(source, "{}/esc{}", sensor_id, esc.index);
+                        self.record(&source, h, kind, celsius);
+                    }
+                }
+            }
+            SensorMessage::System(m) => {
+                let mut source = String::new();
+                for zone in m.thermal_zones.iter() {
+                    source.clear();
+                    let _ = write!(source, "{}/{}", sensor_id, zone.name);
+                    self.record(&source, h, kind, zone.temperature);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Update the latest reading of `source` in place; only a new source allocates
+    fn record(&self, source: &str, h: &Header, kind: &'static str, celsius: f32) {
+        let mut samples = self.samples.write().unwrap();
+        if let Some(sample) = samples.get_mut(source) {
+            sample.celsius = celsius;
+            sample.t_utc_ns = h.t_utc_ns;
+            return;
+        }
+        samples.insert(
+            source.to_string(),
+            TemperatureSample {
+                source: source.to_string(),
+                sensor_id: h.sensor_id.clone(),
+                kind,
+                celsius,
+                t_utc_ns: h.t_utc_ns,
+            },
+        );
+    }
+
+    /// Readings of `sensor_ids` (all if empty) no older than the configured max age
+    pub fn snapshot(&self, sensor_ids: &[String], now_ns: u64) -> Vec<TemperatureSample> {
+        let max_age_ns = self.config.read().unwrap().max_age_s * 1_000_000_000;
+        self.samples
+            .read()
+            .unwrap()
+            .values()
+            .filter(|s| sensor_ids.is_empty() || sensor_ids.contains(&s.sensor_id))
+            .filter(|s| now_ns.saturating_sub(s.t_utc_ns) <= max_age_ns)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{BarometerMessage, EscReadingMessage, EscTelemetryMessage, Header};
+
+    fn header(sensor_id: &str, t_s: u64) -> Header {
+        let mut h = Header::new("hub".into(), sensor_id.into(), "base".into(), 0);
+        h.t_utc_ns = t_s * 1_000_000_000;
+        h
+    }
+
+    #[test]
+    fn test_aggregates_labelled_sources() {
+        let env = Environment::new();
+        env.observe(&SensorMessage::Barometer(BarometerMessage {
+            h: header("baro0", 100),
+            pressure: 101325.0,
+            temperature: 25.0,
+            altitude: 0.0,
+        }));
+        let esc = |index, temperature| EscReadingMessage {
+            index,
+            rpm: 0,
+            voltage: 0.0,
+            current: 0.0,
+            temperature,
+            error_count: None,
+            failure_flags: 0,
+        };
+        env.observe(&SensorMessage::EscTelemetry(EscTelemetryMessage {
+            h: header("fc_esc", 90),
+            escs: vec![esc(0, Some(40.0)), esc(1, None)],
+        }));
+
+        let all = env.snapshot(&[], 100 * 1_000_000_000);
+        let sources: Vec<_> = all.iter().map(|s| (s.source.as_str(), s.kind)).collect();
+        assert_eq!(sources, [("baro0", "barometer")]);
+        let all = env.snapshot(&[], 92 * 1_000_000_000);
+        assert_eq!(all[1].source, "fc_esc/esc0");
+        assert_eq!(
+            env.snapshot(&["fc_esc".to_string()], 92 * 1_000_000_000)
+                .len(),
+            1
+        );
+
+        // A newer reading replaces the source's sample
+        env.observe(&SensorMessage::Barometer(BarometerMessage {
+            h: header("baro0", 101),
+            pressure: 101325.0,
+            temperature: 26.5,
+            altitude: 0.0,
+        }));
+        let baro = env.snapshot(&["baro0".to_string()], 101 * 1_000_000_000);
+        assert_eq!(baro.len(), 1);
+        assert_eq!(
+            (baro[0].celsius, baro[0].t_utc_ns),
+            (26.5, 101 * 1_000_000_000)
+        );
+        assert_eq!(TemperatureUnit::Fahrenheit.convert(25.0), 77.0);
+        assert_eq!(TemperatureUnit::Kelvin.convert(-273.15), 0.0);
+    }
+}
@@ -1,5 +1,11 @@
 use crate::bus::i2c::I2CError;
+use std::collections::HashMap;
 use thiserror::Error;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+/// Domain of the ErrorInfo details attached to gRPC errors
+pub const ERROR_DOMAIN: &str = "sensorhub.navigate";
 
 /// Comprehensive error types for the Navigate SensorHub
 #[derive(Error, Debug)]
@@ -85,6 +91,12 @@ pub enum ServiceError {
 
     #[error("No active subscribers for sensor data")]
     NoSubscribers,
+
+    #[error("Unknown sensor '{sensor_id}'")]
+    SensorNotFound { sensor_id: String },
+
+    #[error("No data from sensor '{sensor_id}' within {timeout_ms} ms")]
+    SensorTimeout { sensor_id: String, timeout_ms: u64 },
 }
 
 /// Registry and initialization errors
@@ -128,6 +140,150 @@ impl From<RegistryError> for String {
     }
 }
 
+/// What a client needs to tell errors apart without parsing messages
+struct ErrorKind<'a> {
+    code: Code,
+    /// UPPER_SNAKE_CASE reason, also reported lower-case as the `kind` metadata
+    reason: &'static str,
+    sensor_id: Option<&'a str>,
+    bus: Option<&'a str>,
+}
+
+impl<'a> ErrorKind<'a> {
+    fn new(code: Code, reason: &'static str) -> Self {
+        Self {
+            code,
+            reason,
+            sensor_id: None,
+            bus: None,
+        }
+    }
+
+    fn sensor(mut self, sensor_id: &'a str) -> Self {
+        self.sensor_id = Some(sensor_id);
+        self
+    }
+
+    fn bus(mut self, bus: &'a str) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
+    /// Status with an ErrorInfo detail carrying `sensor_id`, `bus` and `kind`
+    fn into_status(self, message: String) -> Status {
+        let mut metadata = HashMap::new();
+        metadata.insert("kind".to_string(), self.reason.to_lowercase());
+        if let Some(sensor_id) = self.sensor_id {
+            metadata.insert("sensor_id".to_string(), sensor_id.to_string());
+        }
+        if let Some(bus) = self.bus {
+            metadata.insert("bus".to_string(), bus.to_string());
+        }
+        let details = ErrorDetails::with_error_info(self.reason, ERROR_DOMAIN, metadata);
+        Status::with_error_details(self.code, message, details)
+    }
+}
+
+impl SensorError {
+    fn kind(&self) -> ErrorKind<'_> {
+        match self {
+            SensorError::I2cError(_) => ErrorKind::new(Code::Unavailable, "I2C_ERROR"),
+            SensorError::SerialError(_) => ErrorKind::new(Code::Unavailable, "SERIAL_ERROR"),
+            SensorError::MavlinkError(_) => ErrorKind::new(Code::Unavailable, "MAVLINK_ERROR"),
+            SensorError::InitError { sensor, .. } => {
+                ErrorKind::new(Code::FailedPrecondition, "SENSOR_INIT_FAILED").sensor(sensor)
+            }
+            SensorError::ReadError { sensor, .. } => {
+                ErrorKind::new(Code::Unavailable, "SENSOR_READ_FAILED").sensor(sensor)
+            }
+            SensorError::ConfigError { sensor, .. } => {
+                ErrorKind::new(Code::FailedPrecondition, "SENSOR_MISCONFIGURED").sensor(sensor)
+            }
+            SensorError::DataError { sensor, .. } => {
+                ErrorKind::new(Code::DataLoss, "SENSOR_DATA_INVALID").sensor(sensor)
+            }
+            SensorError::CalibrationError { sensor, .. } => {
+                ErrorKind::new(Code::Aborted, "CALIBRATION_FAILED").sensor(sensor)
+            }
+            SensorError::UnsupportedDriver { .. } => {
+                ErrorKind::new(Code::Unimplemented, "UNSUPPORTED_DRIVER")
+            }
+            SensorError::BusNotFound { bus } => {
+                ErrorKind::new(Code::NotFound, "BUS_NOT_FOUND").bus(bus)
+            }
+            SensorError::BusTimeout { bus, .. } => {
+                ErrorKind::new(Code::DeadlineExceeded, "BUS_TIMEOUT").bus(bus)
+            }
+            SensorError::WrongChipId { sensor, .. } => {
+                ErrorKind::new(Code::FailedPrecondition, "WRONG_CHIP_ID").sensor(sensor)
+            }
+        }
+    }
+}
+
+impl ServiceError {
+    fn kind(&self) -> ErrorKind<'_> {
+        match self {
+            ServiceError::ServerStartError(_) => {
+                ErrorKind::new(Code::Internal, "SERVER_START_FAILED")
+            }
+            ServiceError::PublishError { .. } => ErrorKind::new(Code::Internal, "PUBLISH_FAILED"),
+            ServiceError::InvalidRequest { .. } => {
+                ErrorKind::new(Code::InvalidArgument, "INVALID_REQUEST")
+            }
+            ServiceError::ConversionError(_) => ErrorKind::new(Code::Internal, "CONVERSION_FAILED"),
+            ServiceError::NoSubscribers => ErrorKind::new(Code::Unavailable, "NO_SUBSCRIBERS"),
+            ServiceError::SensorNotFound { sensor_id } => {
+                ErrorKind::new(Code::NotFound, "SENSOR_NOT_FOUND").sensor(sensor_id)
+            }
+            ServiceError::SensorTimeout { sensor_id, .. } => {
+                ErrorKind::new(Code::DeadlineExceeded, "SENSOR_TIMEOUT").sensor(sensor_id)
+            }
+        }
+    }
+}
+
+impl From<SensorError> for Status {
+    fn from(error: SensorError) -> Self {
+        error.kind().into_status(error.to_string())
+    }
+}
+
+impl From<ServiceError> for Status {
+    fn from(error: ServiceError) -> Self {
+        error.kind().into_status(error.to_string())
+    }
+}
+
 /// Result type aliases for convenience
 pub type SensorResult<T> = Result<T, SensorError>;
 pub type RegistryResult<T> = Result<T, RegistryError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_error_info() {
+        let status = Status::from(SensorError::BusTimeout {
+            bus: "i2c1".to_string(),
+            timeout_ms: 50,
+        });
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        let details = status.check_error_details().unwrap();
+        let info = details.error_info().unwrap();
+        assert_eq!(info.reason, "BUS_TIMEOUT");
+        assert_eq!(info.domain, ERROR_DOMAIN);
+        assert_eq!(info.metadata["bus"], "i2c1");
+        assert_eq!(info.metadata["kind"], "bus_timeout");
+        assert!(!info.metadata.contains_key("sensor_id"));
+
+        let status = Status::from(ServiceError::SensorNotFound {
+            sensor_id: "imu9".to_string(),
+        });
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "Unknown sensor 'imu9'");
+        let info = status.get_error_details().error_info().cloned().unwrap();
+        assert_eq!(info.metadata["sensor_id"], "imu9");
+    }
+}
@@ -0,0 +1,382 @@
+use crate::config::sensor_config::{FilterEntry, FilterStage};
+use crate::messages::{ImuMessage, SensorMessage};
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::PI;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// A longer gap between samples restarts the chain from the next sample
+const MAX_GAP_S: f64 = 0.5;
+/// Redesign the stages when the measured sample rate moves by more than this fraction
+const RATE_TOLERANCE: f64 = 0.05;
+/// Weight of each new interval in the sample rate estimate
+const RATE_SMOOTHING: f64 = 0.1;
+
+/// Second-order section, transposed direct form II
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    /// Coefficients from the Audio EQ Cookbook, or None when `f0` is not below Nyquist
+    fn design(stage: FilterStage, rate_hz: f64) -> Option<([f64; 3], [f64; 2])> {
+        let (f0, q) = match stage {
+            FilterStage::Lowpass { cutoff_hz } => (cutoff_hz, std::f64::consts::FRAC_1_SQRT_2),
+            FilterStage::Notch {
+                center_hz,
+                bandwidth_hz,
+            } => (center_hz, center_hz / bandwidth_hz.max(f64::EPSILON)),
+            FilterStage::Bias { .. }
+            | FilterStage::Average { .. }
+            | FilterStage::Exponential { .. } => return None,
+        };
+        if f0 <= 0.0 || f0 >= rate_hz / 2.0 {
+            return None;
+        }
+        let w0 = 2.0 * PI * f0 / rate_hz;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let b = match stage {
+            FilterStage::Lowpass { .. } => [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            _ => [1.0, -2.0 * cos, 1.0],
+        };
+        let a0 = 1.0 + alpha;
+        Some((b.map(|b| b / a0), [-2.0 * cos / a0, (1.0 - alpha) / a0]))
+    }
+
+    /// Settle on a constant input, so the output starts without a transient
+    fn prime(&mut self, x: f64) {
+        let [b0, b1, b2] = self.b;
+        let [a1, a2] = self.a;
+        let y = x * (b0 + b1 + b2) / (1.0 + a1 + a2);
+        self.z[1] = b2 * x - a2 * y;
+        self.z[0] = b1 * x - a1 * y + self.z[1];
+    }
+
+    fn step(&mut self, x: f64) -> f64 {
+        let [b0, b1, b2] = self.b;
+        let [a1, a2] = self.a;
+        let y = b0 * x + self.z[0];
+        self.z[0] = b1 * x - a1 * y + self.z[1];
+        self.z[1] = b2 * x - a2 * y;
+        y
+    }
+}
+
+/// Filter state of one stage on one field
+#[derive(Debug, Clone)]
+enum Stage {
+    Biquad(Biquad),
+    Bias {
+        alpha: f64,
+        mean: f64,
+    },
+    Average {
+        window: usize,
+        samples: VecDeque<f64>,
+    },
+    Exponential {
+        alpha: f64,
+        value: f64,
+    },
+    /// Not realisable at the current sample rate
+    Bypass,
+}
+
+impl Stage {
+    fn design(&mut self, stage: FilterStage, rate_hz: f64) {
+        let dt = 1.0 / rate_hz;
+        // A redesign keeps the running state, so it doesn't jolt the output
+        *self = match (stage, std::mem::replace(self, Stage::Bypass)) {
+            (FilterStage::Bias { time_constant_s }, previous) => Stage::Bias {
+                alpha: dt / (time_constant_s.max(0.0) + dt),
+                mean: match previous {
+                    Stage::Bias { mean, .. } => mean,
+                    _ => f64::NAN,
+                },
+            },
+            (FilterStage::Average { window }, previous) => Stage::Average {
+                window: window.max(1),
+                samples: match previous {
+                    Stage::Average { samples, .. } => samples,
+                    _ => VecDeque::new(),
+                },
+            },
+            (FilterStage::Exponential { time_constant_s }, previous) => Stage::Exponential {
+                alpha: dt / (time_constant_s.max(0.0) + dt),
+                value: match previous {
+                    Stage::Exponential { value, .. } => value,
+                    _ => f64::NAN,
+                },
+            },
+            (_, previous) => match Biquad::design(stage, rate_hz) {
+                Some((b, a)) => Stage::Biquad(Biquad {
+                    b,
+                    a,
+                    z: match previous {
+                        Stage::Biquad(q) => q.z,
+                        _ => [f64::NAN; 2],
+                    },
+                }),
+                None => Stage::Bypass,
+            },
+        };
+    }
+
+    fn step(&mut self, x: f64) -> f64 {
+        match self {
+            Stage::Biquad(q) => {
+                if q.z[0].is_nan() {
+                    q.prime(x);
+                }
+                q.step(x)
+            }
+            Stage::Bias { alpha, mean } => {
+                // Starts from the first sample, taken to be all bias
+                if mean.is_nan() {
+                    *mean = x;
+                }
+                *mean += *alpha * (x - *mean);
+                x - *mean
+            }
+            Stage::Average { window, samples } => {
+                samples.push_back(x);
+                if samples.len() > *window {
+                    samples.pop_front();
+                }
+                samples.iter().sum::<f64>() / samples.len() as f64
+            }
+            Stage::Exponential { alpha, value } => {
+                if value.is_nan() {
+                    *value = x;
+                }
+                *value += *alpha * (x - *value);
+                *value
+            }
+            Stage::Bypass => x,
+        }
+    }
+}
+
+/// One `[[filter]]` entry: its stages and their state per field
+struct Chain {
+    fields: Vec<String>,
+    stages: Vec<FilterStage>,
+    /// Per field, per stage
+    state: Vec<Vec<Stage>>,
+    last_t_ns: Option<u64>,
+    /// Smoothed sample interval (s), and the rate the stages were designed for
+    interval: Option<f64>,
+    designed_hz: Option<f64>,
+    warned: bool,
+}
+
+impl Chain {
+    fn new(entry: &FilterEntry) -> Self {
+        Self {
+            fields: entry.fields.clone(),
+            stages: entry.stages.clone(),
+            state: Vec::new(),
+            last_t_ns: None,
+            interval: None,
+            designed_hz: None,
+            warned: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state.clear();
+        self.interval = None;
+        self.designed_hz = None;
+    }
+
+    /// Track the sample rate; false while it is not known yet
+    fn clock(&mut self, sensor_id: &str, t_ns: u64) -> bool {
+        let last = self.last_t_ns.replace(t_ns);
+        let Some(dt) = last.map(|last| t_ns.saturating_sub(last) as f64 / 1e9) else {
+            return false;
+        };
+        if dt > MAX_GAP_S {
+            self.reset();
+            return false;
+        }
+        if dt > 0.0 {
+            self.interval = Some(match self.interval {
+                Some(interval) => interval + RATE_SMOOTHING * (dt - interval),
+                None => dt,
+            });
+        }
+        let Some(rate_hz) = self.interval.map(|interval| 1.0 / interval) else {
+            return false;
+        };
+        let redesign = self
+            .designed_hz
+            .is_none_or(|designed| (rate_hz - designed).abs() > RATE_TOLERANCE * designed);
+        if redesign {
+            if self.state.is_empty() {
+                self.state = vec![vec![Stage::Bypass; self.stages.len()]; self.fields.len()];
+            }
+            for stages in self.state.iter_mut() {
+                for (stage, config) in stages.iter_mut().zip(self.stages.iter()) {
+                    stage.design(*config, rate_hz);
+                }
+            }
+            let bypassed = self.state[0]
+                .iter()
+                .filter(|stage| matches!(stage, Stage::Bypass))
+                .count();
+            if bypassed > 0 && !self.warned {
+                warn!(
+                    "[filter] {}: {} stage(s) at or above Nyquist for {:.0} Hz data, passed through",
+                    sensor_id, bypassed, rate_hz
+                );
+                self.warned = true;
+            }
+            self.designed_hz = Some(rate_hz);
+        }
+        true
+    }
+
+    fn apply(&mut self, message: &mut SensorMessage) {
+        let (sensor_id, t_ns) = (
+            message.header().sensor_id.clone(),
+            message.header().t_utc_ns,
+        );
+        if !self.clock(&sensor_id, t_ns) {
+            return;
+        }
+        for (field, stages) in self.fields.iter().zip(self.state.iter_mut()) {
+            // Double-precision IMU output is filtered in full and the f32 field follows it
+            let precise = match message {
+                SensorMessage::Imu(ImuMessage {
+                    precise: Some(precise),
+                    ..
+                }) => ["ax", "ay", "az", "gx", "gy", "gz"]
+                    .iter()
+                    .position(|&a| a == field)
+                    .map(|axis| &mut precise[axis]),
+                _ => None,
+            };
+            let filtered = match precise {
+                Some(value) => {
+                    *value = stages.iter_mut().fold(*value, |x, stage| stage.step(x));
+                    *value
+                }
+                None => {
+                    let Some(value) = message.field_mut(field) else {
+                        continue;
+                    };
+                    stages
+                        .iter_mut()
+                        .fold(f64::from(*value), |x, stage| stage.step(x))
+                }
+            };
+            if let Some(value) = message.field_mut(field) {
+                *value = filtered as f32;
+            }
+        }
+    }
+}
+
+/// Per-sensor digital filter chains (low-pass, notch, bias removal) run before publishing
+pub struct FilterPipeline {
+    chains: Mutex<HashMap<String, Vec<Chain>>>,
+}
+
+impl FilterPipeline {
+    pub fn new() -> Self {
+        Self {
+            chains: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Apply the `[[filter]]` entries, replacing any running chains
+    pub fn configure(&self, entries: &[FilterEntry]) {
+        let mut chains: HashMap<String, Vec<Chain>> = HashMap::new();
+        for entry in entries {
+            if entry.fields.is_empty() || entry.stages.is_empty() {
+                warn!("[filter] {}: no fields or stages, ignored", entry.sensor);
+                continue;
+            }
+            info!(
+                "[filter] {} {:?}: {:?}",
+                entry.sensor, entry.fields, entry.stages
+            );
+            chains
+                .entry(entry.sensor.clone())
+                .or_default()
+                .push(Chain::new(entry));
+        }
+        *self.chains.lock().unwrap() = chains;
+    }
+
+    /// Run a message through its sensor's chains, in configuration order
+    pub fn apply(&self, message: &mut SensorMessage) {
+        let mut chains = self.chains.lock().unwrap();
+        if let Some(chains) = chains.get_mut(&message.header().sensor_id) {
+            for chain in chains.iter_mut() {
+                chain.apply(message);
+            }
+        }
+    }
+}
+
+impl Default for FilterPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Header;
+
+    #[test]
+    fn test_notch_removes_motor_vibration() {
+        let entry: FilterEntry = toml::from_str(
+            r#"
+            sensor = "imu0"
+            fields = ["gz"]
+            stages = [
+                { type = "notch", center_hz = 120, bandwidth_hz = 30 },
+                { type = "lowpass", cutoff_hz = 80 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let pipeline = FilterPipeline::new();
+        pipeline.configure(&[entry]);
+
+        // 400 Hz gyro: a slow 0.5 rad/s turn under 2 rad/s of 120 Hz vibration
+        let mut worst: f64 = 0.0;
+        for i in 0..2000u64 {
+            let t = i as f64 / 400.0;
+            let vibration = 2.0 * (2.0 * PI * 120.0 * t).sin();
+            let mut h = Header::new("hub".into(), "imu0".into(), "imu".into(), i);
+            h.t_utc_ns = i * 2_500_000;
+            let mut message = SensorMessage::Imu(ImuMessage {
+                h,
+                ax: 0.0,
+                ay: 0.0,
+                az: -9.81,
+                gx: 0.0,
+                gy: 0.0,
+                gz: (0.5 + vibration) as f32,
+                precise: None,
+                temperature: None,
+            });
+            pipeline.apply(&mut message);
+            let SensorMessage::Imu(imu) = message else {
+                unreachable!()
+            };
+            if i >= 400 {
+                worst = worst.max((f64::from(imu.gz) - 0.5).abs());
+            }
+        }
+        assert!(worst < 0.05, "residual vibration {}", worst);
+    }
+}
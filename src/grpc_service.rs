@@ -1,27 +1,112 @@
+use crate::accel_calibration::AccelCalibrator;
+use crate::airspeed::{indicated_airspeed, AirspeedCalibration};
+use crate::build_info;
+use crate::bus::i2c::{self, I2CBus, I2cTrace};
+use crate::calibration::{self, Calibrations};
+use crate::config::bus_config::load_bus_config;
+use crate::config::diff::{self, ChangeKind};
+use crate::config::sensor_config::{
+    CalibrationConfig, DecimateEntry, DownlinkConfig, EnvironmentConfig, FilterEntry, GroupEntry,
+    GrpcConfig, GyroBiasConfig, HistoryConfig, I2cTraceConfig, ImuPrecision, RateMonitorConfig,
+    RecordingConfig, SafetyConfig, ScenarioConfig, TemperatureUnit, WatchdogConfig,
+};
+use crate::decimation::Decimator;
+use crate::diagnostics::BUNDLE_PREFIX;
+use crate::downlink::DownlinkBudget;
+use crate::environment::Environment;
+use crate::errors::ServiceError;
+use crate::filters::FilterPipeline;
+use crate::gyro_bias::GyroBiasEstimator;
+use crate::health::SensorHealth;
+use crate::history::{self, History};
+use crate::kinds;
+use crate::mag_calibration::{self, MagCalibrator};
+use crate::mavlink_log::MavlinkCaptures;
+use crate::mcap::{self, McapRecorder};
 use crate::messages::SensorMessage;
-use std::collections::HashMap;
+use crate::metrics::{Metrics, CLIENT_DISCONNECTS};
+use crate::orientation::{self, Orientations, Reference, VectorKind};
+use crate::overrides::{OverrideStore, Overrides};
+use crate::publish_queue::{
+    next_subscription_id, policy_stream, DisconnectHook, QueuePolicies, SensorQueue, SensorTopic,
+    StreamFilter,
+};
+use crate::rate_monitor::RateMonitor;
+use crate::rpc_guard::StreamLimit;
+use crate::safety::Interlock;
+use crate::scenario::ScenarioEngine;
+use crate::scheduler::{SensorTaskManager, TaskCommand};
+use crate::schema;
+use crate::timing::TimingStats;
+use crate::watchdog;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tokio_stream::wrappers::BroadcastStream;
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use tokio_stream::wrappers::{BroadcastStream, ReceiverStream};
 use tokio_stream::Stream;
 use tokio_stream::StreamExt;
 use tonic::{Request, Response, Result, Status};
-use tracing::info;
+use tonic_reflection::server::v1alpha::{ServerReflection, ServerReflectionServer};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 // Include the generated protobuf code
+// HistoryRecord holds either a full SensorData or a small event
+#[allow(clippy::large_enum_variant)]
 pub mod sensorhub {
     tonic::include_proto!("sensorhub");
+
+    /// Descriptor set of sensorhub.proto, also embedded as the schema of MCAP recordings
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("sensorhub_descriptor");
 }
 
 use sensorhub::{
     sensor_hub_server::{SensorHub, SensorHubServer},
-    BarometerData, Header, ImuData, MagnetometerData, SensorData, SensorRequest, SensorStatus,
-    SensorStatusResponse,
+    AccelCalibrationAction, AccelCalibrationRequest, AccelCalibrationResponse, AirspeedData,
+    AltitudeData, ApplyConfigRequest, ApplyConfigResponse, AttitudeData, BarometerData, BuildInfo,
+    ClearOverridesResponse, ClientDisconnected, ClientStream, ConfigChange, ConfigChangeKind,
+    DetectOrientationRequest, DetectOrientationResponse, DiagnosticBundle, DiagnosticBundleChunk,
+    DiagnosticBundleRequest, DownlinkBudgetResponse, EnvironmentData, EnvironmentRequest,
+    EscReading, EscTelemetryData, FrameTransform, GimbalAttitudeData, GlobalPositionData, GpsData,
+    GyroBias, Header, HistoryRecord, HistoryRequest, I2cScanRequest, I2cScanResponse,
+    I2cTraceRequest, I2cTraceResponse, I2cTransaction, ImuData, ImuDoubleData,
+    InjectGpsCorrectionsResponse, LatestSampleResponse, LocalPositionData, MagCalibrationRequest,
+    MagCalibrationResult, MagnetometerData, MavlinkCapture, MavlinkCaptureRequest,
+    MavlinkCaptureResponse, MeasurementsData, NamedValue, OrientationResult, OrientationStep,
+    RangeData, RcChannelsData, RtcmFrame, SamplingTiming, SensorAction, SensorControlRequest,
+    SensorControlResponse, SensorData, SensorEvent, SensorInfo, SensorInfoRequest,
+    SensorListResponse, SensorMounting, SensorRemoved, SensorRequest, SensorStatus,
+    SensorStatusResponse, SetLogLevelRequest, SetLogLevelResponse, SetRecordingRequest,
+    SetRecordingResponse, StartMagCalibrationResponse, StreamAllocation, StreamGroupRequest,
+    SystemData, TemperatureReading, ThermalZone, TimeSyncRequest, TimeSyncResponse,
+    TransformsResponse, VfrHudData, VibrationData, Watermark, ZeroAirspeedRequest,
+    ZeroAirspeedResponse,
 };
 
+/// Parent of the sensor frames in GetTransforms: the body frame, forward-right-down
+const BODY_FRAME_ID: &str = "base_link_frd";
+
+/// Default number of readings averaged by ZeroAirspeed
+const DEFAULT_ZERO_SAMPLES: u32 = 50;
+/// Default time allowed to collect the zeroing samples
+const DEFAULT_ZERO_TIMEOUT_MS: u32 = 10_000;
+/// Default averaging window of each DetectOrientation step
+const DEFAULT_ORIENTATION_DURATION_MS: u32 = 2000;
+/// Slice size for GetDiagnosticBundle
+const BUNDLE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Handle to the process-wide tracing filter installed in main
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
 pub type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
 
+/// An I2C bus with the addresses ScanI2c must not probe
+type ScanTarget = (Arc<Mutex<I2CBus>>, Vec<u16>);
+
 /// gRPC service implementation for sensor data streaming
 #[derive(Clone)]
 pub struct SensorHubService {
@@ -29,31 +114,158 @@ pub struct SensorHubService {
     imu_tx: broadcast::Sender<ImuData>,
     mag_tx: broadcast::Sender<MagnetometerData>,
     baro_tx: broadcast::Sender<BarometerData>,
+    gps_tx: broadcast::Sender<GpsData>,
+    attitude_tx: broadcast::Sender<AttitudeData>,
+    range_tx: broadcast::Sender<RangeData>,
+    vibration_tx: broadcast::Sender<VibrationData>,
+    esc_tx: broadcast::Sender<EscTelemetryData>,
+    gimbal_tx: broadcast::Sender<GimbalAttitudeData>,
+    measurements_tx: broadcast::Sender<MeasurementsData>,
     all_tx: broadcast::Sender<SensorData>,
+    // Published messages before protobuf conversion, for output backends (MQTT JSON)
+    messages_tx: broadcast::Sender<SensorMessage>,
+    events_tx: broadcast::Sender<SensorEvent>,
+    // RTCM corrections from InjectGpsCorrections, consumed by GPS / flight controller links
+    corrections_tx: broadcast::Sender<Arc<Vec<u8>>>,
+
+    // Per-sensor overflow policy for client streams
+    queue_policies: QueuePolicies,
+
+    // Egress budget enforced on client streams (unlimited unless configured)
+    downlink: Arc<DownlinkBudget>,
+
+    // Sensors currently known to the hub (ListSensors)
+    sensors: Arc<RwLock<HashMap<String, SensorInfo>>>,
 
     // Sensor status tracking
     sensor_stats: Arc<RwLock<HashMap<String, SensorStats>>>,
+
+    // Pitot zero offsets, applied to airspeed messages before publishing
+    airspeed_cal: Arc<AirspeedCalibration>,
+
+    // IMU/magnetometer mounting rotations (DetectOrientation), applied before publishing
+    orientations: Arc<Orientations>,
+
+    // Latest temperature per source, for StreamEnvironment
+    environment: Arc<Environment>,
+
+    // MCAP recording of the unified stream (SetRecording)
+    recorder: Arc<McapRecorder>,
+
+    // Recent samples and events per stream, for GetHistory backfill
+    history: Arc<History>,
+
+    // Where the diagnostics watchdog writes bundles (None while it is disabled)
+    diagnostics_dir: Arc<std::sync::RwLock<Option<PathBuf>>>,
+
+    // sensors.toml the hub started from, replaced by ApplyConfig
+    config_file: Arc<std::sync::RwLock<Option<PathBuf>>>,
+
+    // Runtime-adjustable log filter (SetLogLevel)
+    log_filter: Arc<std::sync::RwLock<Option<LogFilterHandle>>>,
+
+    // Arming state from the flight controller, gating disruptive control RPCs
+    interlock: Arc<Interlock>,
+
+    // Calibration files applied to IMU and magnetometer data
+    calibrations: Arc<Calibrations>,
+
+    // Raw magnetometer samples collected for StartMagCalibration/FinishMagCalibration
+    mag_calibrator: Arc<MagCalibrator>,
+
+    // Poses captured so far by CalibrateAccel
+    accel_calibrator: Arc<AccelCalibrator>,
+
+    // Startup gyro bias, estimated while stationary and then subtracted
+    gyro_bias: Arc<GyroBiasEstimator>,
+
+    // Per-sensor low-pass/notch/bias chains, run before publishing
+    filters: Arc<FilterPipeline>,
+
+    // Reduced gRPC output rates; on-board consumers still get every sample
+    decimator: Arc<Decimator>,
+
+    // Publish rates compared against expected ones
+    rate_monitor: Arc<RateMonitor>,
+
+    // Jitter and latency of the scheduler's reads
+    timing: Arc<TimingStats>,
+
+    // Runtime changes persisted across restarts (when enabled)
+    overrides: Arc<OverrideStore>,
+
+    // I2C bus analyzer shared by all I2C buses (GetI2cTrace)
+    i2c_trace: Arc<I2cTrace>,
+
+    // Sampling tasks of the polled sensors (ControlSensor), set once they are spawned
+    sensor_tasks: Arc<std::sync::RwLock<Option<Arc<SensorTaskManager>>>>,
+
+    // Raw byte captures of the MAVLink links (CaptureMavlink)
+    mavlink_captures: Arc<MavlinkCaptures>,
+
+    // I2C buses and their do-not-probe addresses (ScanI2c)
+    i2c_buses: Arc<std::sync::RwLock<HashMap<String, ScanTarget>>>,
+
+    // Last value published per sensor (GetLatestSample), kept by a task of its own
+    latest: Arc<std::sync::RwLock<HashMap<String, SensorData>>>,
+    latest_tracker: Arc<std::sync::Once>,
+
+    // Simulated failures injected before publishing (`[scenario]`)
+    scenario: Arc<ScenarioEngine>,
+
+    // Sensors configured with `precision = "f64"`
+    imu_f64: Arc<std::sync::RwLock<HashSet<String>>>,
+
+    // Named consumer groups served by StreamGroup
+    groups: Arc<std::sync::RwLock<HashMap<String, GroupEntry>>>,
+
+    // Mounting positions from `[[mounting]]`, reported in SensorInfo
+    mountings: Arc<std::sync::RwLock<HashMap<String, SensorMounting>>>,
+    // frame_id stamped on the data of mounted sensors
+    frame_ids: Arc<std::sync::RwLock<HashMap<String, String>>>,
+
+    // Error and lag counters exported on /metrics
+    metrics: Arc<Metrics>,
+
+    // Concurrent client stream cap (`[grpc]`)
+    stream_limit: Arc<StreamLimit>,
 }
 
 #[derive(Clone, Debug)]
 struct SensorStats {
+    /// Bus of the sensor, kept after it is removed so its bus still reports unhealthy
+    bus_id: String,
     is_active: bool,
     is_healthy: bool,
     frequency_hz: u32,
     messages_sent: u64,
     last_message_time_ns: u64,
     error_message: Option<String>,
+    init_duration_ms: Option<u32>,
+    /// Publish rate below the expected rate
+    degraded: bool,
+    /// Watchdog timeout (ns) while flagged for publishing nothing
+    stale: Option<u64>,
+    /// Start of the current rate measurement window and messages counted in it
+    window_start_ns: u64,
+    window_messages: u64,
 }
 
 impl Default for SensorStats {
     fn default() -> Self {
         Self {
+            bus_id: String::new(),
             is_active: false,
             is_healthy: true,
             frequency_hz: 0,
             messages_sent: 0,
             last_message_time_ns: 0,
             error_message: None,
+            init_duration_ms: None,
+            degraded: false,
+            stale: None,
+            window_start_ns: 0,
+            window_messages: 0,
         }
     }
 }
@@ -64,20 +276,131 @@ impl SensorHubService {
         let (imu_tx, _) = broadcast::channel(1000);
         let (mag_tx, _) = broadcast::channel(800);
         let (baro_tx, _) = broadcast::channel(800);
+        let (gps_tx, _) = broadcast::channel(200);
+        let (attitude_tx, _) = broadcast::channel(800);
+        let (range_tx, _) = broadcast::channel(800);
+        let (vibration_tx, _) = broadcast::channel(100);
+        let (esc_tx, _) = broadcast::channel(200);
+        let (gimbal_tx, _) = broadcast::channel(400);
+        let (measurements_tx, _) = broadcast::channel(200);
         let (all_tx, _) = broadcast::channel(2000);
+        let (messages_tx, _) = broadcast::channel(2000);
+        let (events_tx, _) = broadcast::channel(100);
+        let (corrections_tx, _) = broadcast::channel(100);
 
         Self {
             imu_tx,
             mag_tx,
             baro_tx,
+            gps_tx,
+            attitude_tx,
+            range_tx,
+            vibration_tx,
+            esc_tx,
+            gimbal_tx,
+            measurements_tx,
             all_tx,
+            messages_tx,
+            events_tx,
+            corrections_tx,
+            queue_policies: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            downlink: Arc::new(DownlinkBudget::new()),
+            sensors: Arc::new(RwLock::new(HashMap::new())),
             sensor_stats: Arc::new(RwLock::new(HashMap::new())),
+            airspeed_cal: Arc::new(AirspeedCalibration::new()),
+            orientations: Arc::new(Orientations::new()),
+            environment: Arc::new(Environment::new()),
+            recorder: Arc::new(McapRecorder::new()),
+            history: Arc::new(History::new()),
+            diagnostics_dir: Arc::new(std::sync::RwLock::new(None)),
+            config_file: Arc::new(std::sync::RwLock::new(None)),
+            log_filter: Arc::new(std::sync::RwLock::new(None)),
+            interlock: Arc::new(Interlock::new()),
+            rate_monitor: Arc::new(RateMonitor::new()),
+            timing: Arc::new(TimingStats::new()),
+            calibrations: Arc::new(Calibrations::new()),
+            mag_calibrator: Arc::new(MagCalibrator::new()),
+            accel_calibrator: Arc::new(AccelCalibrator::new()),
+            gyro_bias: Arc::new(GyroBiasEstimator::new()),
+            filters: Arc::new(FilterPipeline::new()),
+            decimator: Arc::new(Decimator::new()),
+            overrides: Arc::new(OverrideStore::new()),
+            i2c_trace: Arc::new(I2cTrace::new(I2cTraceConfig::default().capacity)),
+            sensor_tasks: Arc::new(std::sync::RwLock::new(None)),
+            mavlink_captures: Arc::new(MavlinkCaptures::new()),
+            i2c_buses: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            latest: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            latest_tracker: Arc::new(std::sync::Once::new()),
+            scenario: Arc::new(ScenarioEngine::new()),
+            imu_f64: Arc::new(std::sync::RwLock::new(HashSet::new())),
+            groups: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            mountings: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            frame_ids: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            metrics: Arc::new(Metrics::new()),
+            stream_limit: Arc::new(StreamLimit::new()),
         }
     }
 
     /// Publish sensor data to appropriate streams
     pub async fn publish(&self, message: SensorMessage) -> Result<(), String> {
+        let Some(mut message) = self.scenario.apply(message) else {
+            return Ok(());
+        };
+        if let SensorMessage::Airspeed(airspeed) = &mut message {
+            // Remove the stored zero offset before computing airspeed
+            airspeed.differential_pressure = self
+                .airspeed_cal
+                .apply(&airspeed.h.sensor_id, airspeed.differential_pressure);
+            airspeed.indicated_airspeed = indicated_airspeed(airspeed.differential_pressure);
+        }
+        if let Some(frame_id) = self
+            .frame_ids
+            .read()
+            .unwrap()
+            .get(&message.header().sensor_id)
+        {
+            message.header_mut().frame_id.clone_from(frame_id);
+        }
+        self.mag_calibrator.observe(&message);
+        self.accel_calibrator.observe(&message);
+        self.calibrations.apply(&mut message);
+        self.gyro_bias.apply(&mut message);
+        self.filters.apply(&mut message);
+        self.rotate_to_body(&mut message);
+        self.environment.observe(&message);
+        if self.messages_tx.receiver_count() > 0 {
+            let _ = self.messages_tx.send(message.clone());
+        }
+        let Some(message) = self.decimator.apply(message) else {
+            return Ok(());
+        };
+        self.send_to_streams(message).await
+    }
+
+    /// Publish a message that already went through the processing stages (a replayed
+    /// recording), so offsets, calibration, filters, rotation and decimation are not
+    /// applied a second time
+    pub async fn publish_processed(&self, message: SensorMessage) -> Result<(), String> {
+        let Some(message) = self.scenario.apply(message) else {
+            return Ok(());
+        };
+        self.environment.observe(&message);
+        if self.messages_tx.receiver_count() > 0 {
+            let _ = self.messages_tx.send(message.clone());
+        }
+        self.send_to_streams(message).await
+    }
+
+    /// Convert a processed message and send it to its streams
+    async fn send_to_streams(&self, message: SensorMessage) -> Result<(), String> {
         let header = convert_header(message.header());
+        // Registered metadata lets StreamAll clients demultiplex without ListSensors
+        let source = self
+            .sensors
+            .read()
+            .await
+            .get(&message.header().sensor_id)
+            .cloned();
 
         match message {
             SensorMessage::Imu(imu) => {
@@ -89,6 +412,20 @@ impl SensorHubService {
                     gx: imu.gx,
                     gy: imu.gy,
                     gz: imu.gz,
+                    temperature: imu.temperature,
+                    precise: source.as_ref().filter(|s| s.precision == "f64").map(|_| {
+                        let p = imu.precise.unwrap_or(
+                            [imu.ax, imu.ay, imu.az, imu.gx, imu.gy, imu.gz].map(f64::from),
+                        );
+                        ImuDoubleData {
+                            ax: p[0],
+                            ay: p[1],
+                            az: p[2],
+                            gx: p[3],
+                            gy: p[4],
+                            gz: p[5],
+                        }
+                    }),
                 };
 
                 // Send to IMU-specific stream
@@ -99,10 +436,9 @@ impl SensorHubService {
                 // Send to unified stream
                 let sensor_data = SensorData {
                     data: Some(sensorhub::sensor_data::Data::Imu(imu_data)),
+                    source,
                 };
-                if self.all_tx.send(sensor_data).is_err() {
-                    // No active subscribers - this is fine
-                }
+                self.send_all(sensor_data);
 
                 self.update_sensor_stats(&imu.h.sensor_id, 1).await;
             }
@@ -121,10 +457,9 @@ impl SensorHubService {
 
                 let sensor_data = SensorData {
                     data: Some(sensorhub::sensor_data::Data::Magnetometer(mag_data)),
+                    source,
                 };
-                if self.all_tx.send(sensor_data).is_err() {
-                    // No active subscribers - this is fine
-                }
+                self.send_all(sensor_data);
 
                 self.update_sensor_stats(&mag.h.sensor_id, 1).await;
             }
@@ -143,97 +478,731 @@ impl SensorHubService {
 
                 let sensor_data = SensorData {
                     data: Some(sensorhub::sensor_data::Data::Barometer(baro_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&baro.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Gps(gps) => {
+                let gps_data = GpsData {
+                    header: Some(header.clone()),
+                    fix_type: gps.fix_type,
+                    satellites_visible: gps.satellites_visible,
+                    latitude: gps.latitude,
+                    longitude: gps.longitude,
+                    altitude: gps.altitude,
+                    hdop: gps.hdop,
+                    vdop: gps.vdop,
+                    ground_speed: gps.ground_speed,
+                    course: gps.course,
+                    yaw: gps.yaw,
+                    yaw_accuracy: gps.yaw_accuracy,
                 };
-                if self.all_tx.send(sensor_data).is_err() {
+
+                if self.gps_tx.send(gps_data.clone()).is_err() {
                     // No active subscribers - this is fine
                 }
 
-                self.update_sensor_stats(&baro.h.sensor_id, 1).await;
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Gps(gps_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&gps.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Attitude(att) => {
+                let att_data = AttitudeData {
+                    header: Some(header.clone()),
+                    qw: att.qw,
+                    qx: att.qx,
+                    qy: att.qy,
+                    qz: att.qz,
+                    roll: att.roll,
+                    pitch: att.pitch,
+                    yaw: att.yaw,
+                    rollspeed: att.rollspeed,
+                    pitchspeed: att.pitchspeed,
+                    yawspeed: att.yawspeed,
+                    gnss_yaw: att.gnss_yaw,
+                    gnss_yaw_accuracy: att.gnss_yaw_accuracy,
+                };
+
+                if self.attitude_tx.send(att_data.clone()).is_err() {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Attitude(att_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&att.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::GlobalPosition(pos) => {
+                let pos_data = GlobalPositionData {
+                    header: Some(header.clone()),
+                    latitude: pos.latitude,
+                    longitude: pos.longitude,
+                    altitude: pos.altitude,
+                    relative_altitude: pos.relative_altitude,
+                    vn: pos.vn,
+                    ve: pos.ve,
+                    vd: pos.vd,
+                    heading: pos.heading,
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::GlobalPosition(pos_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&pos.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::LocalPosition(pos) => {
+                let pos_data = LocalPositionData {
+                    header: Some(header.clone()),
+                    x: pos.x,
+                    y: pos.y,
+                    z: pos.z,
+                    vx: pos.vx,
+                    vy: pos.vy,
+                    vz: pos.vz,
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::LocalPosition(pos_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&pos.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Rangefinder(range) => {
+                let range_data = RangeData {
+                    header: Some(header.clone()),
+                    distance: range.distance,
+                    min_distance: range.min_distance,
+                    max_distance: range.max_distance,
+                    orientation: range.orientation,
+                    covariance: range.covariance,
+                };
+
+                if self.range_tx.send(range_data.clone()).is_err() {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Range(range_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&range.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Airspeed(airspeed) => {
+                let airspeed_data = AirspeedData {
+                    header: Some(header.clone()),
+                    differential_pressure: airspeed.differential_pressure,
+                    indicated_airspeed: airspeed.indicated_airspeed,
+                    temperature: airspeed.temperature,
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Airspeed(airspeed_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&airspeed.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::VfrHud(hud) => {
+                let hud_data = VfrHudData {
+                    header: Some(header.clone()),
+                    airspeed: hud.airspeed,
+                    groundspeed: hud.groundspeed,
+                    heading: hud.heading,
+                    climb_rate: hud.climb_rate,
+                    throttle: hud.throttle,
+                    altitude: hud.altitude,
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::VfrHud(hud_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&hud.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Vibration(vib) => {
+                let vibration_data = VibrationData {
+                    header: Some(header.clone()),
+                    vibration_x: vib.vibration_x,
+                    vibration_y: vib.vibration_y,
+                    vibration_z: vib.vibration_z,
+                    clipping_0: vib.clipping[0],
+                    clipping_1: vib.clipping[1],
+                    clipping_2: vib.clipping[2],
+                };
+
+                if self.vibration_tx.send(vibration_data.clone()).is_err() {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Vibration(vibration_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&vib.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::RcChannels(rc) => {
+                let rc_data = RcChannelsData {
+                    header: Some(header.clone()),
+                    channels: rc.channels.iter().map(|&c| c as u32).collect(),
+                    rssi: rc.rssi.map(u32::from),
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::RcChannels(rc_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&rc.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::EscTelemetry(esc) => {
+                let esc_data = EscTelemetryData {
+                    header: Some(header.clone()),
+                    escs: esc
+                        .escs
+                        .iter()
+                        .map(|e| EscReading {
+                            index: e.index as u32,
+                            rpm: e.rpm,
+                            voltage: e.voltage,
+                            current: e.current,
+                            temperature: e.temperature,
+                            error_count: e.error_count,
+                            failure_flags: e.failure_flags as u32,
+                        })
+                        .collect(),
+                };
+
+                if self.esc_tx.send(esc_data.clone()).is_err() {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Esc(esc_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&esc.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::System(system) => {
+                let system_data = SystemData {
+                    header: Some(header.clone()),
+                    cpu_load: system.cpu_load,
+                    load_avg_1m: system.load_avg_1m,
+                    mem_total_bytes: system.mem_total_bytes,
+                    mem_available_bytes: system.mem_available_bytes,
+                    disk_total_bytes: system.disk_total_bytes,
+                    disk_available_bytes: system.disk_available_bytes,
+                    history_bytes: system.history_bytes,
+                    thermal_zones: system
+                        .thermal_zones
+                        .iter()
+                        .map(|z| ThermalZone {
+                            name: z.name.clone(),
+                            temperature: z.temperature,
+                        })
+                        .collect(),
+                };
+
+                // Only on the unified stream (and the environment stream, for its zones)
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::System(system_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&system.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::GimbalAttitude(gimbal) => {
+                let gimbal_data = GimbalAttitudeData {
+                    header: Some(header.clone()),
+                    gimbal_device_id: gimbal.gimbal_device_id as u32,
+                    qw: gimbal.qw,
+                    qx: gimbal.qx,
+                    qy: gimbal.qy,
+                    qz: gimbal.qz,
+                    roll: gimbal.roll,
+                    pitch: gimbal.pitch,
+                    yaw: gimbal.yaw,
+                    angular_velocity_x: gimbal.angular_velocity_x,
+                    angular_velocity_y: gimbal.angular_velocity_y,
+                    angular_velocity_z: gimbal.angular_velocity_z,
+                    yaw_in_earth_frame: gimbal.yaw_in_earth_frame,
+                    delta_yaw: gimbal.delta_yaw,
+                    device_flags: gimbal.device_flags as u32,
+                    failure_flags: gimbal.failure_flags,
+                };
+
+                if self.gimbal_tx.send(gimbal_data.clone()).is_err() {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::GimbalAttitude(gimbal_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&gimbal.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Measurements(measurements) => {
+                let measurements_data = MeasurementsData {
+                    header: Some(header.clone()),
+                    values: measurements
+                        .values
+                        .iter()
+                        .map(|v| NamedValue {
+                            name: v.name.clone(),
+                            value: v.value,
+                            unit: v.unit.clone(),
+                        })
+                        .collect(),
+                };
+
+                if self
+                    .measurements_tx
+                    .send(measurements_data.clone())
+                    .is_err()
+                {
+                    // No active subscribers - this is fine
+                }
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Measurements(
+                        measurements_data,
+                    )),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&measurements.h.sensor_id, 1).await;
+            }
+
+            SensorMessage::Altitude(alt) => {
+                let altitude_data = AltitudeData {
+                    header: Some(header.clone()),
+                    altitude: alt.altitude,
+                    climb_rate: alt.climb_rate,
+                    vertical_accel: alt.vertical_accel,
+                };
+
+                let sensor_data = SensorData {
+                    data: Some(sensorhub::sensor_data::Data::Altitude(altitude_data)),
+                    source,
+                };
+                self.send_all(sensor_data);
+
+                self.update_sensor_stats(&alt.h.sensor_id, 1).await;
             }
         }
 
         Ok(())
     }
 
-    async fn update_sensor_stats(&self, sensor_id: &str, message_count: u64) {
-        let mut stats = self.sensor_stats.write().await;
-        let entry = stats.entry(sensor_id.to_string()).or_default();
+    /// Send to StreamAll, accounting the message against the downlink budget
+    fn send_all(&self, sensor_data: SensorData) {
+        self.latest_tracker
+            .call_once(|| self.spawn_latest_tracker());
+        self.downlink.record(sensor_data.sensor_id(), || {
+            prost::Message::encoded_len(&sensor_data)
+        });
+        if self.all_tx.send(sensor_data).is_err() {
+            // No active subscribers - this is fine
+        }
+    }
 
-        entry.is_active = true;
-        entry.messages_sent += message_count;
-        entry.last_message_time_ns = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos() as u64;
+    /// Keep the last value of each sensor for GetLatestSample from StreamAll, so publishing
+    /// neither copies the message nor takes the map's write lock
+    fn spawn_latest_tracker(&self) {
+        let latest = self.latest.clone();
+        let mut rx = self.all_tx.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(data) => {
+                        let mut latest = latest.write().unwrap();
+                        if let Some(slot) = latest.get_mut(data.sensor_id()) {
+                            *slot = data;
+                        } else {
+                            latest.insert(data.sensor_id().to_string(), data);
+                        }
+                    }
+                    // Only the newest value of each sensor is kept anyway
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
     }
-}
 
-#[tonic::async_trait]
-impl SensorHub for SensorHubService {
-    type StreamIMUStream = ResponseStream<ImuData>;
-    type StreamMagnetometerStream = ResponseStream<MagnetometerData>;
-    type StreamBarometerStream = ResponseStream<BarometerData>;
-    type StreamAllStream = ResponseStream<SensorData>;
+    /// Rotate IMU and magnetometer vectors from the sensor frame into the body frame
+    fn rotate_to_body(&self, message: &mut SensorMessage) {
+        match message {
+            SensorMessage::Imu(imu) => {
+                let id = &imu.h.sensor_id;
+                self.orientations
+                    .observe(id, VectorKind::Accel, [imu.ax, imu.ay, imu.az]);
+                let Some(rotation) = self.orientations.rotation(id) else {
+                    return;
+                };
+                [imu.ax, imu.ay, imu.az] = rotation.rotate_f32([imu.ax, imu.ay, imu.az]);
+                [imu.gx, imu.gy, imu.gz] = rotation.rotate_f32([imu.gx, imu.gy, imu.gz]);
+                if let Some(p) = imu.precise.as_mut() {
+                    let accel = rotation.rotate([p[0], p[1], p[2]]);
+                    let gyro = rotation.rotate([p[3], p[4], p[5]]);
+                    *p = [accel[0], accel[1], accel[2], gyro[0], gyro[1], gyro[2]];
+                }
+            }
+            SensorMessage::Magnetometer(mag) => {
+                let id = &mag.h.sensor_id;
+                self.orientations
+                    .observe(id, VectorKind::Mag, [mag.mx, mag.my, mag.mz]);
+                if let Some(rotation) = self.orientations.rotation(id) {
+                    [mag.mx, mag.my, mag.mz] = rotation.rotate_f32([mag.mx, mag.my, mag.mz]);
+                }
+            }
+            _ => {}
+        }
+    }
 
-    async fn stream_imu(
-        &self,
-        _request: Request<SensorRequest>,
-    ) -> Result<Response<Self::StreamIMUStream>> {
-        info!("[gRPC] New IMU stream client connected");
+    /// File ApplyConfig compares against and replaces
+    pub fn set_config_file(&self, path: PathBuf) {
+        *self.config_file.write().unwrap() = Some(path);
+    }
 
-        let rx = self.imu_tx.subscribe();
-        let stream = BroadcastStream::new(rx)
-            .map(|item| item.map_err(|e| Status::internal(format!("Broadcast error: {}", e))));
+    /// Let ControlSensor reach the polled sensors' tasks
+    pub fn set_sensor_tasks(&self, tasks: Arc<SensorTaskManager>) {
+        *self.sensor_tasks.write().unwrap() = Some(tasks);
+    }
 
-        Ok(Response::new(Box::pin(stream)))
+    /// Let SetLogLevel replace the tracing filter
+    pub fn set_log_filter_handle(&self, handle: LogFilterHandle) {
+        *self.log_filter.write().unwrap() = Some(handle);
     }
 
-    async fn stream_magnetometer(
-        &self,
-        _request: Request<SensorRequest>,
-    ) -> Result<Response<Self::StreamMagnetometerStream>> {
-        info!("[gRPC] New magnetometer stream client connected");
+    /// Persist control RPC changes to `path` and restore the ones saved there
+    /// Per-sensor entries are returned for the caller to merge into the sensor config
+    pub fn open_overrides(&self, path: PathBuf) -> Overrides {
+        let overrides = self.overrides.open(path);
+        if let Some(filter) = overrides.log_filter.as_deref() {
+            let handle = self.log_filter.read().unwrap().clone();
+            let applied = match (handle, EnvFilter::try_new(filter)) {
+                (Some(handle), Ok(new_filter)) => handle.reload(new_filter).is_ok(),
+                _ => false,
+            };
+            if applied {
+                info!("[overrides] Log filter '{}'", filter);
+            } else {
+                warn!("[overrides] Could not apply log filter '{}'", filter);
+            }
+        }
+        overrides
+    }
 
-        let rx = self.mag_tx.subscribe();
-        let stream = BroadcastStream::new(rx)
-            .map(|item| item.map_err(|e| Status::internal(format!("Broadcast error: {}", e))));
+    /// Apply the `[safety]` settings to the arming interlock
+    pub fn configure_safety(&self, config: &SafetyConfig) {
+        self.interlock.configure(config);
+        if !config.enabled {
+            warn!("[safety] Arming interlock disabled, control RPCs are allowed while armed");
+        }
+    }
 
-        Ok(Response::new(Box::pin(stream)))
+    /// Arming interlock, fed with HEARTBEATs by the MAVLink buses
+    pub fn interlock(&self) -> Arc<Interlock> {
+        self.interlock.clone()
     }
 
-    async fn stream_barometer(
-        &self,
-        _request: Request<SensorRequest>,
-    ) -> Result<Response<Self::StreamBarometerStream>> {
-        info!("[gRPC] New barometer stream client connected");
+    /// Apply the `[i2c_trace]` settings to the bus analyzer
+    pub fn configure_i2c_trace(&self, config: &I2cTraceConfig) {
+        self.i2c_trace.set_capacity(config.capacity);
+        self.i2c_trace.set_enabled(config.enabled);
+        if config.enabled {
+            info!(
+                "[i2c_trace] Recording I2C transactions (last {})",
+                config.capacity
+            );
+        }
+    }
 
-        let rx = self.baro_tx.subscribe();
-        let stream = BroadcastStream::new(rx)
-            .map(|item| item.map_err(|e| Status::internal(format!("Broadcast error: {}", e))));
+    /// Bus analyzer to attach to each I2C bus
+    pub fn i2c_trace(&self) -> Arc<I2cTrace> {
+        self.i2c_trace.clone()
+    }
 
-        Ok(Response::new(Box::pin(stream)))
+    /// Raw byte captures, to register each MAVLink link with
+    pub fn mavlink_captures(&self) -> Arc<MavlinkCaptures> {
+        self.mavlink_captures.clone()
     }
 
-    async fn stream_all(
-        &self,
-        _request: Request<SensorRequest>,
-    ) -> Result<Response<Self::StreamAllStream>> {
-        info!("[gRPC] New unified stream client connected");
+    /// Make an I2C bus available to ScanI2c, which never probes `no_probe`
+    pub fn register_i2c_bus(&self, bus_id: &str, bus: Arc<Mutex<I2CBus>>, no_probe: Vec<u16>) {
+        self.i2c_buses
+            .write()
+            .unwrap()
+            .insert(bus_id.to_string(), (bus, no_probe));
+    }
 
-        let rx = self.all_tx.subscribe();
-        let stream = BroadcastStream::new(rx)
-            .map(|item| item.map_err(|e| Status::internal(format!("Broadcast error: {}", e))));
+    /// Counters exported on /metrics, shared with the buses and sensor tasks
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
 
-        Ok(Response::new(Box::pin(stream)))
+    /// Sampling jitter and latency, recorded by the scheduler
+    pub fn timing(&self) -> &TimingStats {
+        &self.timing
     }
 
-    async fn get_sensor_status(
-        &self,
-        _request: Request<SensorRequest>,
-    ) -> Result<Response<SensorStatusResponse>> {
-        let stats = self.sensor_stats.read().await;
-        let sensor_statuses: Vec<SensorStatus> = stats
-            .iter()
+    /// Load the `[scenario]` fault schedule (simulation runs only)
+    pub fn configure_scenario(&self, config: &ScenarioConfig) {
+        self.scenario.configure(config);
+    }
+
+    /// Apply the `[environment]` defaults of StreamEnvironment
+    pub fn configure_environment(&self, config: &EnvironmentConfig) {
+        self.environment.configure(config);
+    }
+
+    /// Start the MCAP writer with the `[recording]` settings, recording right away if enabled
+    pub fn configure_recording(&self, config: &RecordingConfig) {
+        self.recorder.configure(config);
+        mcap::spawn_writer(self.recorder.clone(), self.all_tx.subscribe());
+        if config.enabled {
+            if let Err(e) = self.recorder.start() {
+                error!("[mcap] Cannot start recording in {}: {}", config.dir, e);
+            }
+        }
+    }
+
+    /// Apply the `[history]` retention times and start keeping published data
+    pub fn configure_history(&self, config: &HistoryConfig) {
+        if !config.enabled {
+            return;
+        }
+        self.history.configure(config);
+        history::spawn_recorder(
+            self.history.clone(),
+            self.all_tx.subscribe(),
+            self.events_tx.subscribe(),
+        );
+    }
+
+    /// Memory held by the in-memory history, in bytes
+    pub fn history_bytes(&self) -> u64 {
+        self.history.bytes()
+    }
+
+    /// Apply the `[grpc]` overload limits
+    pub fn configure_grpc(&self, config: &GrpcConfig) {
+        self.stream_limit.configure(config);
+    }
+
+    /// Load the calibration files in `dir`, applied to every later sample
+    pub fn load_calibrations(&self, dir: &Path, config: &CalibrationConfig) {
+        self.calibrations.configure(config);
+        self.calibrations.load_dir(dir);
+        for (sensor_id, offset) in self.calibrations.airspeed_offsets() {
+            info!("[airspeed] Pitot {} offset {:.2} Pa", sensor_id, offset);
+            self.airspeed_cal.set_offset(&sensor_id, offset);
+        }
+        for (sensor_id, orientation) in self.calibrations.orientations() {
+            info!(
+                "[calibration] Sensor {} mounted roll {}° pitch {}° yaw {}°",
+                sensor_id, orientation.roll_deg, orientation.pitch_deg, orientation.yaw_deg
+            );
+            self.orientations.set(&sensor_id, orientation);
+        }
+    }
+
+    /// Announce calibrations that expired or no longer fit the live data
+    pub fn check_calibrations(&self) {
+        for (sensor_id, quality, reason) in self.calibrations.check(now_ns() / 1_000_000_000) {
+            warn!(
+                "[calibration] {} {} ({}) stale: {}",
+                sensor_id, quality.section, quality.calibration_id, reason
+            );
+            let event = SensorEvent {
+                t_utc_ns: now_ns(),
+                event: Some(sensorhub::sensor_event::Event::CalibrationStale(
+                    sensorhub::CalibrationStale {
+                        sensor_id,
+                        quality: Some(quality),
+                        reason,
+                    },
+                )),
+            };
+            if self.events_tx.send(event).is_err() {
+                // No active subscribers - this is fine
+            }
+        }
+    }
+
+    /// Apply the `[gyro_bias]` settings; IMUs start averaging with their next sample
+    pub fn configure_gyro_bias(&self, config: &GyroBiasConfig) {
+        self.gyro_bias.configure(config);
+    }
+
+    /// Set up the `[[filter]]` chains run on published data
+    pub fn configure_filters(&self, entries: &[FilterEntry]) {
+        self.filters.configure(entries);
+    }
+
+    /// Set up the `[[decimate]]` output rates
+    pub fn configure_decimation(&self, entries: &[DecimateEntry]) {
+        self.decimator.configure(entries);
+    }
+
+    /// Apply the `[rate_monitor]` thresholds and expected rates
+    pub fn configure_rate_monitor(&self, config: &RateMonitorConfig) {
+        self.rate_monitor.configure(config);
+    }
+
+    /// A sensor's sampling rate was changed at runtime; its old rate is no longer expected
+    pub fn sensor_rate_changed(&self, sensor_id: &str) {
+        self.rate_monitor.relearn(sensor_id);
+    }
+
+    /// Apply the `[downlink]` egress budget to client streams
+    pub fn configure_downlink(&self, config: &DownlinkConfig) {
+        self.downlink.configure(config);
+    }
+
+    /// Receive RTCM correction frames sent to InjectGpsCorrections
+    pub fn subscribe_corrections(&self) -> broadcast::Receiver<Arc<Vec<u8>>> {
+        self.corrections_tx.subscribe()
+    }
+
+    /// Give a new client stream its id, log who subscribed to what, and build the hook that
+    /// logs the unsubscribe and announces the stream's totals on the event stream
+    fn stream_client(
+        &self,
+        stream: String,
+        peer: Option<SocketAddr>,
+        filter: &StreamFilter,
+    ) -> (u64, DisconnectHook) {
+        let subscription_id = next_subscription_id();
+        let peer = peer.map(|p| p.to_string()).unwrap_or_default();
+        let from = if peer.is_empty() {
+            String::new()
+        } else {
+            format!(" from {}", peer)
+        };
+        let filters = filter_label(filter);
+        info!(
+            "[gRPC] Client #{} subscribed to {} stream{}{}",
+            subscription_id, stream, from, filters
+        );
+        self.downlink
+            .describe_client(subscription_id, &stream, &peer, filters.trim_start());
+        let events_tx = self.events_tx.clone();
+        let metrics = self.metrics.clone();
+        let hook: DisconnectHook = Box::new(move |totals| {
+            info!(
+                "[gRPC] Client #{} unsubscribed from {} stream{} after {:.1}s: {} delivered, {} dropped, {} shed, {} lagged",
+                subscription_id,
+                stream,
+                from,
+                totals.duration.as_secs_f64(),
+                totals.delivered,
+                totals.dropped,
+                totals.shed,
+                totals.lagged
+            );
+            metrics.add(CLIENT_DISCONNECTS, &stream, 1);
+            let event = SensorEvent {
+                t_utc_ns: now_ns(),
+                event: Some(sensorhub::sensor_event::Event::ClientDisconnected(
+                    ClientDisconnected {
+                        stream,
+                        peer,
+                        delivered: totals.delivered,
+                        dropped: totals.dropped,
+                        lagged: totals.lagged,
+                        shed: totals.shed,
+                        duration_ms: totals.duration.as_millis() as u64,
+                        subscription_id,
+                    },
+                )),
+            };
+            if events_tx.send(event).is_err() {
+                // No active subscribers - this is fine
+            }
+        });
+        (subscription_id, hook)
+    }
+
+    /// Receive everything published on StreamAll
+    pub fn subscribe_all(&self) -> broadcast::Receiver<SensorData> {
+        self.all_tx.subscribe()
+    }
+
+    /// Receive every published message as the hub's own `SensorMessage`
+    pub fn subscribe_messages(&self) -> broadcast::Receiver<SensorMessage> {
+        self.messages_tx.subscribe()
+    }
+
+    /// Directory GetDiagnosticBundle serves bundles from
+    pub fn set_diagnostics_dir(&self, dir: PathBuf) {
+        *self.diagnostics_dir.write().unwrap() = Some(dir);
+    }
+
+    /// Announce a newly written diagnostic bundle on the event stream
+    pub fn announce_diagnostic_bundle(&self, bundle: DiagnosticBundle) {
+        let event = SensorEvent {
+            t_utc_ns: now_ns(),
+            event: Some(sensorhub::sensor_event::Event::BundleReady(bundle)),
+        };
+        if self.events_tx.send(event).is_err() {
+            // No active subscribers - this is fine
+        }
+    }
+
+    /// Sensors currently known to the hub, sorted by id
+    pub async fn sensor_list(&self) -> Vec<SensorInfo> {
+        let mut sensors: Vec<SensorInfo> = self.sensors.read().await.values().cloned().collect();
+        sensors.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+        sensors
+    }
+
+    /// Statistics for every sensor that has published data
+    pub async fn sensor_statuses(&self) -> Vec<SensorStatus> {
+        let stats = self.sensor_stats.read().await;
+        stats
+            .iter()
             .map(|(sensor_id, stats)| SensorStatus {
                 sensor_id: sensor_id.clone(),
                 is_active: stats.is_active,
@@ -242,11 +1211,1800 @@ impl SensorHub for SensorHubService {
                 messages_sent: stats.messages_sent,
                 last_message_time_ns: stats.last_message_time_ns,
                 error_message: stats.error_message.clone(),
+                init_duration_ms: stats.init_duration_ms,
+                degraded: stats.degraded,
+                gyro_bias: self.gyro_bias.bias(sensor_id).map(|b| GyroBias {
+                    x: b[0] as f32,
+                    y: b[1] as f32,
+                    z: b[2] as f32,
+                }),
+                timing: self.timing.summary(sensor_id).map(|t| SamplingTiming {
+                    jitter_p50_us: t.jitter_p50_us,
+                    jitter_p95_us: t.jitter_p95_us,
+                    jitter_max_us: t.jitter_max_us,
+                    latency_p50_us: t.latency_p50_us,
+                    latency_p95_us: t.latency_p95_us,
+                    latency_max_us: t.latency_max_us,
+                    reads: t.reads,
+                    misses: t.misses,
+                    overrun_p50_us: t.overrun_p50_us,
+                    overrun_p95_us: t.overrun_p95_us,
+                    overrun_max_us: t.overrun_max_us,
+                }),
+            })
+            .collect()
+    }
+
+    /// Select the IMU precision of a configured sensor (before it registers)
+    pub fn set_imu_precision(&self, sensor_id: &str, precision: ImuPrecision) {
+        let mut imu_f64 = self.imu_f64.write().unwrap();
+        if precision == ImuPrecision::F64 {
+            info!("[gRPC] {} publishes double-precision IMU data", sensor_id);
+            imu_f64.insert(sensor_id.to_string());
+        } else {
+            imu_f64.remove(sensor_id);
+        }
+    }
+
+    /// Record where a sensor is mounted and the frame its data is stamped with (before it registers)
+    pub fn set_mounting(&self, sensor_id: &str, position: [f32; 3], frame_id: &str) {
+        info!(
+            "[gRPC] {} mounted at ({}, {}, {}) m, frame {}",
+            sensor_id, position[0], position[1], position[2], frame_id
+        );
+        self.frame_ids
+            .write()
+            .unwrap()
+            .insert(sensor_id.to_string(), frame_id.to_string());
+        self.mountings.write().unwrap().insert(
+            sensor_id.to_string(),
+            SensorMounting {
+                x_m: position[0],
+                y_m: position[1],
+                z_m: position[2],
+            },
+        );
+    }
+
+    /// Static transforms of the mounted sensors, sorted by sensor id
+    pub fn transforms(&self) -> Vec<FrameTransform> {
+        let frame_ids = self.frame_ids.read().unwrap();
+        let mut transforms: Vec<FrameTransform> = self
+            .mountings
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(sensor_id, mounting)| FrameTransform {
+                sensor_id: sensor_id.clone(),
+                parent_frame_id: BODY_FRAME_ID.to_string(),
+                child_frame_id: frame_ids.get(sensor_id).cloned().unwrap_or_default(),
+                translation: Some(*mounting),
+                mounting_rotation: self.orientations.get(sensor_id).map(|o| {
+                    sensorhub::SensorOrientation {
+                        roll_deg: o.roll_deg,
+                        pitch_deg: o.pitch_deg,
+                        yaw_deg: o.yaw_deg,
+                    }
+                }),
             })
             .collect();
+        transforms.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+        transforms
+    }
+
+    /// Define a consumer group for StreamGroup
+    pub fn add_consumer_group(&self, group: &GroupEntry) {
+        info!(
+            "[gRPC] Consumer group '{}' with {} member(s)",
+            group.name,
+            group.members.len()
+        );
+        self.groups
+            .write()
+            .unwrap()
+            .insert(group.name.clone(), group.clone());
+    }
+
+    /// Per-sensor state for the health service, including removed sensors
+    pub async fn sensor_health(&self) -> Vec<SensorHealth> {
+        let stats = self.sensor_stats.read().await;
+        stats
+            .iter()
+            .map(|(sensor_id, stats)| SensorHealth {
+                sensor_id: sensor_id.clone(),
+                bus_id: stats.bus_id.clone(),
+                is_active: stats.is_active,
+                is_healthy: stats.is_healthy,
+                last_message_time_ns: stats.last_message_time_ns,
+            })
+            .collect()
+    }
+
+    /// Set the overflow policy and tier used by client streams for a sensor's messages
+    pub fn set_queue_policy(&self, sensor_id: &str, queue: SensorQueue) {
+        info!("[gRPC] Queue policy for {}: {:?}", sensor_id, queue);
+        self.queue_policies
+            .write()
+            .unwrap()
+            .insert(sensor_id.to_string(), queue);
+    }
+
+    /// Add a sensor to ListSensors and announce it on the event stream
+    pub async fn register_sensor(&self, sensor_id: &str, driver: &str, bus_id: &str, role: &str) {
+        let info = SensorInfo {
+            sensor_id: sensor_id.to_string(),
+            driver: driver.to_string(),
+            bus_id: bus_id.to_string(),
+            role: role.to_string(),
+            precision: if self.imu_f64.read().unwrap().contains(sensor_id) {
+                "f64".to_string()
+            } else {
+                String::new()
+            },
+            mounting: self.mountings.read().unwrap().get(sensor_id).cloned(),
+            calibrations: Vec::new(),
+        };
+        self.sensor_stats
+            .write()
+            .await
+            .entry(sensor_id.to_string())
+            .or_default()
+            .bus_id = bus_id.to_string();
+        self.sensors
+            .write()
+            .await
+            .insert(sensor_id.to_string(), info.clone());
+        info!(
+            "[gRPC] Sensor added: {} ({} on {})",
+            sensor_id, driver, bus_id
+        );
+
+        let event = SensorEvent {
+            t_utc_ns: now_ns(),
+            event: Some(sensorhub::sensor_event::Event::SensorAdded(info)),
+        };
+        if self.events_tx.send(event).is_err() {
+            // No active subscribers - this is fine
+        }
+    }
+
+    /// Record how long a sensor took to come up, reported in GetSensorStatus
+    pub async fn set_init_duration(&self, sensor_id: &str, duration: std::time::Duration) {
+        self.sensor_stats
+            .write()
+            .await
+            .entry(sensor_id.to_string())
+            .or_default()
+            .init_duration_ms = Some(duration.as_millis() as u32);
+    }
+
+    /// Remove a sensor from ListSensors and announce it on the event stream
+    /// Returns the removed entry so the caller can re-register it later
+    pub async fn remove_sensor(&self, sensor_id: &str, reason: &str) -> Option<SensorInfo> {
+        let removed = self.sensors.write().await.remove(sensor_id)?;
+        self.latest.write().unwrap().remove(sensor_id);
+        if let Some(stats) = self.sensor_stats.write().await.get_mut(sensor_id) {
+            stats.is_active = false;
+        }
+        info!("[gRPC] Sensor removed: {} ({})", sensor_id, reason);
+
+        let event = SensorEvent {
+            t_utc_ns: now_ns(),
+            event: Some(sensorhub::sensor_event::Event::SensorRemoved(
+                SensorRemoved {
+                    sensor_id: sensor_id.to_string(),
+                    reason: reason.to_string(),
+                },
+            )),
+        };
+        if self.events_tx.send(event).is_err() {
+            // No active subscribers - this is fine
+        }
+        Some(removed)
+    }
+
+    /// Flag active sensors that published nothing for longer than their watchdog timeout
+    pub async fn check_stale(&self, config: &WatchdogConfig) {
+        let now = now_ns();
+        let mut stats = self.sensor_stats.write().await;
+        for (sensor_id, entry) in stats.iter_mut() {
+            if entry.stale.is_some() || !entry.is_active {
+                continue;
+            }
+            let Some(timeout_ns) = watchdog::timeout_ns(entry.frequency_hz, config) else {
+                continue;
+            };
+            let silent_ns = now.saturating_sub(entry.last_message_time_ns);
+            if silent_ns <= timeout_ns {
+                continue;
+            }
+            let reason = format!(
+                "no data for {} ms (timeout {} ms at {} Hz)",
+                silent_ns / 1_000_000,
+                timeout_ns / 1_000_000,
+                entry.frequency_hz
+            );
+            warn!("[watchdog] {} stale: {}", sensor_id, reason);
+            entry.stale = Some(timeout_ns);
+            entry.is_healthy = false;
+            entry.error_message = Some(reason);
+            self.announce_stale(sensor_id, silent_ns, timeout_ns, true);
+        }
+    }
+
+    fn announce_stale(&self, sensor_id: &str, silent_ns: u64, timeout_ns: u64, stale: bool) {
+        let event = SensorEvent {
+            t_utc_ns: now_ns(),
+            event: Some(sensorhub::sensor_event::Event::SensorStale(
+                sensorhub::SensorStale {
+                    sensor_id: sensor_id.to_string(),
+                    silent_ms: (silent_ns / 1_000_000).min(u64::from(u32::MAX)) as u32,
+                    timeout_ms: (timeout_ns / 1_000_000) as u32,
+                    stale,
+                },
+            )),
+        };
+        if self.events_tx.send(event).is_err() {
+            // No active subscribers - this is fine
+        }
+    }
+
+    async fn update_sensor_stats(&self, sensor_id: &str, message_count: u64) {
+        let mut stats = self.sensor_stats.write().await;
+        let entry = stats.entry(sensor_id.to_string()).or_default();
+
+        let now = now_ns();
+        entry.is_active = true;
+        entry.messages_sent += message_count;
+        let silent_ns = now.saturating_sub(entry.last_message_time_ns);
+        entry.last_message_time_ns = now;
+        if let Some(timeout_ns) = entry.stale.take() {
+            info!(
+                "[watchdog] {} resumed after {} ms",
+                sensor_id,
+                silent_ns / 1_000_000
+            );
+            entry.is_healthy = true;
+            entry.error_message = None;
+            self.announce_stale(sensor_id, silent_ns, timeout_ns, false);
+        }
+
+        // Measured rate, refreshed once per second
+        if entry.window_start_ns == 0 {
+            entry.window_start_ns = now;
+        }
+        entry.window_messages += message_count;
+        let elapsed_ns = now.saturating_sub(entry.window_start_ns);
+        if elapsed_ns < 1_000_000_000 {
+            return;
+        }
+        let measured_hz = entry.window_messages as f64 * 1e9 / elapsed_ns as f64;
+        entry.frequency_hz = measured_hz.round() as u32;
+        entry.window_start_ns = now;
+        entry.window_messages = 0;
+
+        let Some(anomaly) = self.rate_monitor.observe(sensor_id, measured_hz) else {
+            return;
+        };
+        entry.degraded = anomaly.degraded;
+        if anomaly.degraded {
+            let reason = format!(
+                "rate {:.1} Hz, expected {:.1} Hz",
+                anomaly.measured_hz, anomaly.expected_hz
+            );
+            warn!("[gRPC] {} degraded: {}", sensor_id, reason);
+            entry.error_message = Some(reason);
+        } else {
+            info!(
+                "[gRPC] {} rate recovered ({:.1} Hz)",
+                sensor_id, anomaly.measured_hz
+            );
+            entry.error_message = None;
+        }
+        drop(stats);
+
+        let event = SensorEvent {
+            t_utc_ns: now,
+            event: Some(sensorhub::sensor_event::Event::RateAnomaly(
+                sensorhub::RateAnomaly {
+                    sensor_id: sensor_id.to_string(),
+                    expected_hz: anomaly.expected_hz as f32,
+                    measured_hz: anomaly.measured_hz as f32,
+                    degraded: anomaly.degraded,
+                },
+            )),
+        };
+        if self.events_tx.send(event).is_err() {
+            // No active subscribers - this is fine
+        }
+    }
+}
+
+impl Default for SensorHubService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sensor id from an optional protobuf header
+fn header_sensor_id(header: &Option<Header>) -> &str {
+    header.as_ref().map(|h| h.sensor_id.as_str()).unwrap_or("")
+}
+
+macro_rules! impl_sensor_topic {
+    ($($ty:ty => $kind:expr),*) => {
+        $(impl SensorTopic for $ty {
+            fn sensor_id(&self) -> &str {
+                header_sensor_id(&self.header)
+            }
+
+            fn kind(&self) -> &'static str {
+                $kind
+            }
+
+            fn set_watermark(&mut self, watermark: Watermark) {
+                if let Some(header) = self.header.as_mut() {
+                    header.watermark = Some(watermark);
+                }
+            }
+        })*
+    };
+}
+
+impl_sensor_topic!(
+    ImuData => kinds::IMU,
+    MagnetometerData => kinds::MAGNETOMETER,
+    BarometerData => kinds::BAROMETER,
+    GpsData => kinds::GPS,
+    AttitudeData => kinds::ATTITUDE,
+    RangeData => kinds::RANGE,
+    VibrationData => kinds::VIBRATION,
+    EscTelemetryData => kinds::ESC,
+    GimbalAttitudeData => kinds::GIMBAL_ATTITUDE,
+    MeasurementsData => kinds::MEASUREMENTS
+);
+
+impl SensorTopic for SensorData {
+    fn sensor_id(&self) -> &str {
+        use sensorhub::sensor_data::Data;
+        match &self.data {
+            Some(Data::Imu(d)) => header_sensor_id(&d.header),
+            Some(Data::Magnetometer(d)) => header_sensor_id(&d.header),
+            Some(Data::Barometer(d)) => header_sensor_id(&d.header),
+            Some(Data::Gps(d)) => header_sensor_id(&d.header),
+            Some(Data::Range(d)) => header_sensor_id(&d.header),
+            Some(Data::GlobalPosition(d)) => header_sensor_id(&d.header),
+            Some(Data::LocalPosition(d)) => header_sensor_id(&d.header),
+            Some(Data::Attitude(d)) => header_sensor_id(&d.header),
+            Some(Data::Airspeed(d)) => header_sensor_id(&d.header),
+            Some(Data::VfrHud(d)) => header_sensor_id(&d.header),
+            Some(Data::Vibration(d)) => header_sensor_id(&d.header),
+            Some(Data::RcChannels(d)) => header_sensor_id(&d.header),
+            Some(Data::Esc(d)) => header_sensor_id(&d.header),
+            Some(Data::System(d)) => header_sensor_id(&d.header),
+            Some(Data::Altitude(d)) => header_sensor_id(&d.header),
+            Some(Data::GimbalAttitude(d)) => header_sensor_id(&d.header),
+            Some(Data::Measurements(d)) => header_sensor_id(&d.header),
+            None => "",
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        use sensorhub::sensor_data::Data;
+        match &self.data {
+            Some(Data::Imu(_)) => kinds::IMU,
+            Some(Data::Magnetometer(_)) => kinds::MAGNETOMETER,
+            Some(Data::Barometer(_)) => kinds::BAROMETER,
+            Some(Data::Gps(_)) => kinds::GPS,
+            Some(Data::Range(_)) => kinds::RANGE,
+            Some(Data::GlobalPosition(_)) => kinds::GLOBAL_POSITION,
+            Some(Data::LocalPosition(_)) => kinds::LOCAL_POSITION,
+            Some(Data::Attitude(_)) => kinds::ATTITUDE,
+            Some(Data::Airspeed(_)) => kinds::AIRSPEED,
+            Some(Data::VfrHud(_)) => kinds::VFR_HUD,
+            Some(Data::Vibration(_)) => kinds::VIBRATION,
+            Some(Data::RcChannels(_)) => kinds::RC_CHANNELS,
+            Some(Data::Esc(_)) => kinds::ESC,
+            Some(Data::System(_)) => kinds::SYSTEM,
+            Some(Data::Altitude(_)) => kinds::ALTITUDE,
+            Some(Data::GimbalAttitude(_)) => kinds::GIMBAL_ATTITUDE,
+            Some(Data::Measurements(_)) => kinds::MEASUREMENTS,
+            None => "",
+        }
+    }
+
+    fn set_watermark(&mut self, watermark: Watermark) {
+        use sensorhub::sensor_data::Data;
+        let header = match &mut self.data {
+            Some(Data::Imu(d)) => &mut d.header,
+            Some(Data::Magnetometer(d)) => &mut d.header,
+            Some(Data::Barometer(d)) => &mut d.header,
+            Some(Data::Gps(d)) => &mut d.header,
+            Some(Data::Range(d)) => &mut d.header,
+            Some(Data::GlobalPosition(d)) => &mut d.header,
+            Some(Data::LocalPosition(d)) => &mut d.header,
+            Some(Data::Attitude(d)) => &mut d.header,
+            Some(Data::Airspeed(d)) => &mut d.header,
+            Some(Data::VfrHud(d)) => &mut d.header,
+            Some(Data::Vibration(d)) => &mut d.header,
+            Some(Data::RcChannels(d)) => &mut d.header,
+            Some(Data::Esc(d)) => &mut d.header,
+            Some(Data::System(d)) => &mut d.header,
+            Some(Data::Altitude(d)) => &mut d.header,
+            Some(Data::GimbalAttitude(d)) => &mut d.header,
+            Some(Data::Measurements(d)) => &mut d.header,
+            None => return,
+        };
+        if let Some(header) = header.as_mut() {
+            header.watermark = Some(watermark);
+        }
+    }
+}
+
+impl From<SensorRequest> for StreamFilter {
+    fn from(request: SensorRequest) -> Self {
+        Self {
+            sensor_ids: request.sensor_ids,
+            max_frequency_hz: request.max_frequency_hz.filter(|&hz| hz > 0),
+            members: Vec::new(),
+            kinds: None,
+            tag: request.tag.filter(|tag| !tag.is_empty()),
+        }
+    }
+}
+
+/// SensorData kinds a client built against an older schema release understands, from its
+/// request metadata (None = all)
+fn client_schema_kinds<T>(request: &Request<T>) -> Option<Vec<String>> {
+    let version = request
+        .metadata()
+        .get(schema::SCHEMA_METADATA_KEY)?
+        .to_str()
+        .ok()?;
+    let kinds = schema::released_kinds(version);
+    match &kinds {
+        Some(kinds) => info!(
+            "[gRPC] Client built against schema {}, sending only {}",
+            version,
+            kinds.join(", ")
+        ),
+        None => debug!("[gRPC] Client schema {} is not a known release", version),
+    }
+    kinds
+}
+
+/// Log suffix describing a stream's sensor filter
+fn filter_label(filter: &StreamFilter) -> String {
+    let mut label = sensors_label(&filter.sensor_ids);
+    if let Some(hz) = filter.max_frequency_hz {
+        label.push_str(&format!(" (max {} Hz)", hz));
+    }
+    if let Some(tag) = &filter.tag {
+        label.push_str(&format!(" (tag: {})", tag));
+    }
+    label
+}
+
+/// Log suffix listing the requested sensors
+fn sensors_label(sensor_ids: &[String]) -> String {
+    if sensor_ids.is_empty() {
+        String::new()
+    } else {
+        format!(" (sensors: {})", sensor_ids.join(", "))
+    }
+}
+
+/// Current UTC time in nanoseconds
+fn now_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+#[tonic::async_trait]
+impl SensorHub for SensorHubService {
+    type StreamIMUStream = ResponseStream<ImuData>;
+    type StreamMagnetometerStream = ResponseStream<MagnetometerData>;
+    type StreamBarometerStream = ResponseStream<BarometerData>;
+    type StreamGPSStream = ResponseStream<GpsData>;
+    type StreamAttitudeStream = ResponseStream<AttitudeData>;
+    type StreamRangeStream = ResponseStream<RangeData>;
+    type StreamVibrationStream = ResponseStream<VibrationData>;
+    type StreamEscStream = ResponseStream<EscTelemetryData>;
+    type StreamGimbalAttitudeStream = ResponseStream<GimbalAttitudeData>;
+    type StreamMeasurementsStream = ResponseStream<MeasurementsData>;
+    type StreamAllStream = ResponseStream<SensorData>;
+    type StreamGroupStream = ResponseStream<SensorData>;
+    type StreamEventsStream = ResponseStream<SensorEvent>;
+    type StreamEnvironmentStream = ResponseStream<EnvironmentData>;
+    type GetDiagnosticBundleStream = ResponseStream<DiagnosticBundleChunk>;
+    type GetHistoryStream = ResponseStream<HistoryRecord>;
+
+    async fn stream_imu(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamIMUStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::IMU.to_string(), peer, &filter);
+        let rx = self.imu_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_magnetometer(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamMagnetometerStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::MAGNETOMETER.to_string(), peer, &filter);
+        let rx = self.mag_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_barometer(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamBarometerStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::BAROMETER.to_string(), peer, &filter);
+        let rx = self.baro_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_gps(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamGPSStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::GPS.to_string(), peer, &filter);
+        let rx = self.gps_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_attitude(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamAttitudeStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::ATTITUDE.to_string(), peer, &filter);
+        let rx = self.attitude_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_range(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamRangeStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::RANGE.to_string(), peer, &filter);
+        let rx = self.range_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_vibration(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamVibrationStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::VIBRATION.to_string(), peer, &filter);
+        let rx = self.vibration_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_esc(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamEscStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::ESC.to_string(), peer, &filter);
+        let rx = self.esc_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_gimbal_attitude(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamGimbalAttitudeStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::GIMBAL_ATTITUDE.to_string(), peer, &filter);
+        let rx = self.gimbal_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_measurements(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamMeasurementsStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let filter = StreamFilter::from(request.into_inner());
+        let (subscription_id, on_disconnect) =
+            self.stream_client(kinds::MEASUREMENTS.to_string(), peer, &filter);
+        let rx = self.measurements_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn stream_all(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamAllStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let kinds = client_schema_kinds(&request);
+        let filter = StreamFilter {
+            kinds,
+            ..StreamFilter::from(request.into_inner())
+        };
+        let (subscription_id, on_disconnect) = self.stream_client("all".to_string(), peer, &filter);
+        let rx = self.all_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn get_sensor_info(
+        &self,
+        request: Request<SensorInfoRequest>,
+    ) -> Result<Response<SensorInfo>> {
+        let sensor_id = request.into_inner().sensor_id;
+        let mut info = self
+            .sensors
+            .read()
+            .await
+            .get(&sensor_id)
+            .cloned()
+            .ok_or_else(|| Status::from(ServiceError::SensorNotFound { sensor_id }))?;
+        info.calibrations = self
+            .calibrations
+            .quality(&info.sensor_id, now_ns() / 1_000_000_000);
+        Ok(Response::new(info))
+    }
+
+    async fn stream_group(
+        &self,
+        request: Request<StreamGroupRequest>,
+    ) -> Result<Response<Self::StreamGroupStream>> {
+        let permit = self.stream_limit.admit()?;
+        let peer = request.remote_addr();
+        let kinds = client_schema_kinds(&request);
+        let StreamGroupRequest { name, tag } = request.into_inner();
+        let group = self
+            .groups
+            .read()
+            .unwrap()
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("No consumer group '{}'", name)))?;
+        let filter = StreamFilter {
+            sensor_ids: Vec::new(),
+            max_frequency_hz: group.max_frequency_hz,
+            members: group.members,
+            kinds,
+            tag: tag.filter(|tag| !tag.is_empty()),
+        };
+        let (subscription_id, on_disconnect) =
+            self.stream_client(format!("group:{}", name), peer, &filter);
+        let rx = self.all_tx.subscribe();
+        let stream = policy_stream(
+            rx,
+            filter,
+            self.queue_policies.clone(),
+            self.downlink.clone(),
+            self.metrics.clone(),
+            subscription_id,
+            on_disconnect,
+        );
 
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn get_sensor_status(
+        &self,
+        _request: Request<SensorRequest>,
+    ) -> Result<Response<SensorStatusResponse>> {
         Ok(Response::new(SensorStatusResponse {
-            sensors: sensor_statuses,
+            sensors: self.sensor_statuses().await,
+        }))
+    }
+
+    async fn list_sensors(
+        &self,
+        _request: Request<SensorRequest>,
+    ) -> Result<Response<SensorListResponse>> {
+        Ok(Response::new(SensorListResponse {
+            sensors: self.sensor_list().await,
+        }))
+    }
+
+    async fn get_latest_sample(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<LatestSampleResponse>> {
+        let kinds = client_schema_kinds(&request);
+        let sensor_ids = request.into_inner().sensor_ids;
+        let latest = self.latest.read().unwrap();
+        let mut samples: Vec<SensorData> = latest
+            .iter()
+            .filter(|(id, _)| sensor_ids.is_empty() || sensor_ids.contains(id))
+            .filter(|(_, data)| {
+                kinds
+                    .as_ref()
+                    .is_none_or(|kinds| kinds.iter().any(|k| k == data.kind()))
+            })
+            .map(|(_, data)| data.clone())
+            .collect();
+        samples.sort_by(|a, b| a.sensor_id().cmp(b.sensor_id()));
+
+        Ok(Response::new(LatestSampleResponse { samples }))
+    }
+
+    async fn get_downlink_budget(
+        &self,
+        _request: Request<SensorRequest>,
+    ) -> Result<Response<DownlinkBudgetResponse>> {
+        let (budget_bps, clients, allocations) = self.downlink.snapshot();
+        let client_streams = clients
+            .iter()
+            .map(|(&subscription_id, c)| ClientStream {
+                subscription_id,
+                stream: c.stream.clone(),
+                peer: c.peer.clone(),
+                filters: c.filters.clone(),
+                delivered: c.delivered,
+                dropped: c.dropped,
+                shed: c.shed,
+                lagged: c.lagged,
+            })
+            .collect();
+        let mut allocations: Vec<StreamAllocation> = allocations
+            .into_iter()
+            .map(|(sensor_id, a)| StreamAllocation {
+                sensor_id,
+                priority: a.priority,
+                demand_bps: a.demand_bps,
+                allocated_bps: a.allocated_bps,
+                keep_ratio: a.keep_ratio,
+            })
+            .collect();
+        allocations.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+
+        Ok(Response::new(DownlinkBudgetResponse {
+            budget_bps,
+            clients: clients.len() as u32,
+            allocations,
+            client_streams,
+        }))
+    }
+
+    async fn stream_events(
+        &self,
+        _request: Request<SensorRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>> {
+        let permit = self.stream_limit.admit()?;
+        info!("[gRPC] New event stream client connected");
+
+        let rx = self.events_tx.subscribe();
+        let stream = BroadcastStream::new(rx)
+            .map(|item| item.map_err(|e| Status::internal(format!("Broadcast error: {}", e))));
+
+        Ok(Response::new(Box::pin(permit.hold(stream))))
+    }
+
+    async fn inject_gps_corrections(
+        &self,
+        request: Request<tonic::Streaming<RtcmFrame>>,
+    ) -> Result<Response<InjectGpsCorrectionsResponse>> {
+        if self.corrections_tx.receiver_count() == 0 {
+            return Err(Status::failed_precondition(
+                "No GPS correction consumer (no MAVLink bus configured)",
+            ));
+        }
+        info!("[gRPC] GPS correction source connected");
+
+        let mut stream = request.into_inner();
+        let mut frames_received = 0u64;
+        let mut bytes_received = 0u64;
+        while let Some(frame) = stream.message().await? {
+            if frame.data.is_empty() {
+                continue;
+            }
+            frames_received += 1;
+            bytes_received += frame.data.len() as u64;
+            if self.corrections_tx.send(Arc::new(frame.data)).is_err() {
+                return Err(Status::unavailable("GPS correction consumer went away"));
+            }
+        }
+
+        info!(
+            "[gRPC] GPS correction source finished: {} frame(s), {} byte(s)",
+            frames_received, bytes_received
+        );
+        Ok(Response::new(InjectGpsCorrectionsResponse {
+            frames_received,
+            bytes_received,
+        }))
+    }
+
+    async fn sync_time(
+        &self,
+        request: Request<TimeSyncRequest>,
+    ) -> Result<Response<TimeSyncResponse>> {
+        let t2_ns = now_ns();
+        let t1_ns = request.into_inner().t1_ns;
+
+        Ok(Response::new(TimeSyncResponse {
+            t1_ns,
+            t2_ns,
+            t3_ns: now_ns(),
+        }))
+    }
+
+    async fn get_diagnostic_bundle(
+        &self,
+        request: Request<DiagnosticBundleRequest>,
+    ) -> Result<Response<Self::GetDiagnosticBundleStream>> {
+        let name = request.into_inner().name;
+        let dir = self
+            .diagnostics_dir
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| Status::failed_precondition("Diagnostics watchdog is disabled"))?;
+        // Only bare bundle names - never a path outside the bundle directory
+        if !name.starts_with(BUNDLE_PREFIX) || name.contains(['/', '\\']) {
+            return Err(Status::invalid_argument(format!(
+                "Not a diagnostic bundle name: {}",
+                name
+            )));
+        }
+
+        let data = match tokio::fs::read(dir.join(&name)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Status::not_found(format!("No bundle named {}", name)))
+            }
+            Err(e) => return Err(Status::internal(format!("Failed to read {}: {}", name, e))),
+        };
+        info!(
+            "[gRPC] Sending diagnostic bundle {} ({} bytes)",
+            name,
+            data.len()
+        );
+
+        let chunks: Vec<Result<DiagnosticBundleChunk, Status>> = data
+            .chunks(BUNDLE_CHUNK_BYTES)
+            .map(|chunk| {
+                Ok(DiagnosticBundleChunk {
+                    data: chunk.to_vec(),
+                })
+            })
+            .collect();
+
+        Ok(Response::new(Box::pin(tokio_stream::iter(chunks))))
+    }
+
+    async fn get_history(
+        &self,
+        request: Request<HistoryRequest>,
+    ) -> Result<Response<Self::GetHistoryStream>> {
+        let req = request.into_inner();
+        let records: Vec<Result<HistoryRecord, Status>> = self
+            .history
+            .query(&req.streams, &req.sensor_ids, req.since_utc_ns)
+            .into_iter()
+            .map(Ok)
+            .collect();
+        info!("[gRPC] Sending {} history record(s)", records.len());
+        Ok(Response::new(Box::pin(tokio_stream::iter(records))))
+    }
+
+    async fn set_recording(
+        &self,
+        request: Request<SetRecordingRequest>,
+    ) -> Result<Response<SetRecordingResponse>> {
+        match request.into_inner().enable {
+            Some(true) => {
+                self.recorder
+                    .start()
+                    .map_err(|e| Status::internal(format!("Cannot start recording: {}", e)))?;
+                info!("[gRPC] MCAP recording started");
+            }
+            Some(false) => {
+                self.recorder.stop();
+                info!("[gRPC] MCAP recording stopped");
+            }
+            None => {}
+        }
+
+        let status = self.recorder.status();
+        Ok(Response::new(SetRecordingResponse {
+            recording: status.is_some(),
+            path: status
+                .as_ref()
+                .map(|s| s.path.display().to_string())
+                .unwrap_or_default(),
+            messages: status.as_ref().map_or(0, |s| s.messages),
+            bytes: status.as_ref().map_or(0, |s| s.bytes),
+        }))
+    }
+
+    async fn get_build_info(
+        &self,
+        _request: Request<SensorRequest>,
+    ) -> Result<Response<BuildInfo>> {
+        Ok(Response::new(BuildInfo {
+            version: build_info::VERSION.to_string(),
+            git_hash: build_info::GIT_HASH.to_string(),
+            features: build_info::features()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            target: build_info::TARGET.to_string(),
+            profile: build_info::PROFILE.to_string(),
+        }))
+    }
+
+    async fn get_transforms(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<TransformsResponse>> {
+        let sensor_ids = request.into_inner().sensor_ids;
+        let transforms = self
+            .transforms()
+            .into_iter()
+            .filter(|t| sensor_ids.is_empty() || sensor_ids.contains(&t.sensor_id))
+            .collect();
+        Ok(Response::new(TransformsResponse { transforms }))
+    }
+
+    async fn start_mag_calibration(
+        &self,
+        request: Request<MagCalibrationRequest>,
+    ) -> Result<Response<StartMagCalibrationResponse>> {
+        let sensor_id = request.into_inner().sensor_id;
+        if sensor_id.is_empty() {
+            return Err(Status::invalid_argument("sensor_id is required"));
+        }
+        self.interlock
+            .check("StartMagCalibration")
+            .map_err(Status::failed_precondition)?;
+        if self.calibrations.dir().is_none() {
+            return Err(Status::failed_precondition(
+                "Calibration files are disabled ([calibration] enabled = false)",
+            ));
+        }
+        if !self.sensors.read().await.contains_key(&sensor_id) {
+            return Err(ServiceError::SensorNotFound { sensor_id }.into());
+        }
+        info!("[gRPC] Magnetometer calibration of {} started", sensor_id);
+        self.mag_calibrator.start(&sensor_id);
+        Ok(Response::new(StartMagCalibrationResponse {
+            sensor_id,
+            instruction: "Rotate the vehicle slowly through all orientations (each face down \
+                          in turn, away from metal), then call FinishMagCalibration"
+                .to_string(),
+        }))
+    }
+
+    async fn finish_mag_calibration(
+        &self,
+        request: Request<MagCalibrationRequest>,
+    ) -> Result<Response<MagCalibrationResult>> {
+        let sensor_id = request.into_inner().sensor_id;
+        let samples = self.mag_calibrator.finish(&sensor_id).ok_or_else(|| {
+            Status::failed_precondition(format!(
+                "No magnetometer calibration of '{}' in progress - call StartMagCalibration first",
+                sensor_id
+            ))
+        })?;
+        let fit = mag_calibration::fit(&samples).map_err(|e| {
+            warn!(
+                "[gRPC] Magnetometer calibration of {} failed: {}",
+                sensor_id, e
+            );
+            Status::failed_precondition(format!("Calibration of '{}' failed: {}", sensor_id, e))
+        })?;
+        let (calibration_id, path) = self
+            .calibrations
+            .store_mag(
+                &sensor_id,
+                fit.hard_iron,
+                fit.soft_iron,
+                calibration::Quality {
+                    samples: samples.len() as u64,
+                    residual: fit.residual,
+                    coverage: fit.coverage,
+                    field_ut: Some(fit.field_ut),
+                    ..Default::default()
+                },
+            )
+            .map_err(Status::internal)?;
+        info!(
+            "[gRPC] Magnetometer {} calibrated from {} samples: field {:.1} μT, residual {:.2} %",
+            sensor_id,
+            samples.len(),
+            fit.field_ut,
+            fit.residual * 100.0
+        );
+        Ok(Response::new(MagCalibrationResult {
+            sensor_id,
+            samples: samples.len() as u32,
+            hard_iron: fit.hard_iron.iter().map(|&v| v as f32).collect(),
+            soft_iron: fit.soft_iron.iter().flatten().map(|&v| v as f32).collect(),
+            field_ut: fit.field_ut as f32,
+            residual: fit.residual as f32,
+            calibration_id,
+            path: path.display().to_string(),
+            coverage: fit.coverage as f32,
+        }))
+    }
+
+    async fn calibrate_accel(
+        &self,
+        request: Request<AccelCalibrationRequest>,
+    ) -> Result<Response<AccelCalibrationResponse>> {
+        let req = request.into_inner();
+        let action = req.action();
+        let sensor_id = req.sensor_id;
+        if sensor_id.is_empty() {
+            return Err(Status::invalid_argument("sensor_id is required"));
+        }
+        if action == AccelCalibrationAction::AccelCalCancel {
+            info!(
+                "[gRPC] Accelerometer calibration of {} cancelled",
+                sensor_id
+            );
+            self.accel_calibrator.cancel(&sensor_id);
+            return Ok(Response::new(AccelCalibrationResponse {
+                sensor_id,
+                instruction: "Calibration cancelled".to_string(),
+                ..Default::default()
+            }));
+        }
+        self.interlock
+            .check("CalibrateAccel")
+            .map_err(Status::failed_precondition)?;
+        if self.calibrations.dir().is_none() {
+            return Err(Status::failed_precondition(
+                "Calibration files are disabled ([calibration] enabled = false)",
+            ));
+        }
+        if !self.sensors.read().await.contains_key(&sensor_id) {
+            return Err(ServiceError::SensorNotFound { sensor_id }.into());
+        }
+        let duration_ms = req.duration_ms.unwrap_or(DEFAULT_ORIENTATION_DURATION_MS);
+
+        self.accel_calibrator.start_capture(&sensor_id);
+        tokio::time::sleep(std::time::Duration::from_millis(duration_ms as u64)).await;
+        let (pose, captured) = self
+            .accel_calibrator
+            .finish_capture(&sensor_id)
+            .map_err(|e| {
+                Status::failed_precondition(format!("Capture of '{}' rejected: {}", sensor_id, e))
+            })?;
+        info!(
+            "[gRPC] Accelerometer calibration of {}: captured {} ({}/6)",
+            sensor_id,
+            pose.name(),
+            captured.len()
+        );
+        let captured = captured.iter().map(|p| p.name()).collect();
+
+        let Some(fit) = self.accel_calibrator.take_solution(&sensor_id) else {
+            let remaining: Vec<String> = self
+                .accel_calibrator
+                .remaining(&sensor_id)
+                .iter()
+                .map(|p| p.name())
+                .collect();
+            return Ok(Response::new(AccelCalibrationResponse {
+                sensor_id,
+                captured,
+                instruction: format!(
+                    "Hold the vehicle still with one of these axes pointing down, then call \
+                     CalibrateAccel again: {}",
+                    remaining.join(", ")
+                ),
+                ..Default::default()
+            }));
+        };
+        let (bias, scale) = (fit.bias, fit.scale);
+        let (calibration_id, path) = self
+            .calibrations
+            .store_accel(
+                &sensor_id,
+                bias,
+                scale,
+                calibration::Quality {
+                    samples: fit.samples,
+                    residual: fit.residual,
+                    coverage: 1.0,
+                    ..Default::default()
+                },
+            )
+            .map_err(Status::internal)?;
+        info!(
+            "[gRPC] Accelerometer {} calibrated: bias [{:.3}, {:.3}, {:.3}] m/s², scale [{:.4}, {:.4}, {:.4}]",
+            sensor_id, bias[0], bias[1], bias[2], scale[0], scale[1], scale[2]
+        );
+        Ok(Response::new(AccelCalibrationResponse {
+            sensor_id,
+            captured,
+            instruction: "Calibration complete".to_string(),
+            complete: true,
+            bias: bias.iter().map(|&v| v as f32).collect(),
+            scale: scale.iter().map(|&v| v as f32).collect(),
+            calibration_id,
+            path: path.display().to_string(),
+            residual: fit.residual as f32,
+        }))
+    }
+
+    async fn apply_config(
+        &self,
+        request: Request<ApplyConfigRequest>,
+    ) -> Result<Response<ApplyConfigResponse>> {
+        let req = request.into_inner();
+        let path = self
+            .config_file
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| Status::unavailable("The hub was not started from a config file"))?;
+        // Sensors must stay on buses the hub knows; buses.toml sits next to sensors.toml
+        let bus_path = path.with_file_name("buses.toml");
+        let buses = load_bus_config(&bus_path.to_string_lossy())
+            .map_err(|e| Status::internal(format!("Cannot read {}: {}", bus_path.display(), e)))?;
+        let proposed = diff::validate(&req.sensors_toml, &buses)
+            .map_err(|e| Status::invalid_argument(format!("Invalid sensors.toml: {}", e)))?;
+        let current = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| text.parse::<toml::Table>().map_err(|e| e.to_string()))
+            .map_err(|e| Status::internal(format!("Cannot read {}: {}", path.display(), e)))?;
+        let changes = diff::diff(&current, &proposed);
+
+        let applied = !req.dry_run && !changes.is_empty();
+        if applied {
+            self.interlock
+                .check("ApplyConfig")
+                .map_err(Status::failed_precondition)?;
+            // Keep the previous file to roll back by hand
+            let write = || -> std::io::Result<()> {
+                std::fs::copy(&path, path.with_extension("toml.bak"))?;
+                let tmp = path.with_extension("toml.tmp");
+                std::fs::write(&tmp, &req.sensors_toml)?;
+                std::fs::rename(&tmp, &path)
+            };
+            write()
+                .map_err(|e| Status::internal(format!("Cannot write {}: {}", path.display(), e)))?;
+        }
+        info!(
+            "[gRPC] ApplyConfig{}: {} change(s) to {}{}",
+            if req.dry_run { " (dry run)" } else { "" },
+            changes.len(),
+            path.display(),
+            if applied { ", applied on restart" } else { "" }
+        );
+
+        let changes = changes
+            .into_iter()
+            .map(|c| ConfigChange {
+                kind: match c.kind {
+                    ChangeKind::SensorAdded => ConfigChangeKind::ConfigSensorAdded,
+                    ChangeKind::SensorRemoved => ConfigChangeKind::ConfigSensorRemoved,
+                    ChangeKind::SensorRateChanged => ConfigChangeKind::ConfigSensorRateChanged,
+                    ChangeKind::SensorChanged => ConfigChangeKind::ConfigSensorChanged,
+                    ChangeKind::SectionChanged => ConfigChangeKind::ConfigSectionChanged,
+                } as i32,
+                target: c.target,
+                before: c.before,
+                after: c.after,
+            })
+            .collect();
+        Ok(Response::new(ApplyConfigResponse {
+            changes,
+            applied,
+            path: path.display().to_string(),
+        }))
+    }
+
+    async fn capture_mavlink(
+        &self,
+        request: Request<MavlinkCaptureRequest>,
+    ) -> Result<Response<MavlinkCaptureResponse>> {
+        let req = request.into_inner();
+        let io_status = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::NotFound => Status::not_found(e.to_string()),
+            _ => Status::internal(format!("Cannot capture MAVLink bytes: {}", e)),
+        };
+        match req.duration_s {
+            Some(0) => {
+                self.mavlink_captures.stop(&req.bus_id).map_err(io_status)?;
+                info!("[gRPC] MAVLink byte capture stopped");
+            }
+            Some(duration_s) => {
+                self.mavlink_captures
+                    .start(
+                        &req.bus_id,
+                        std::time::Duration::from_secs(duration_s as u64),
+                    )
+                    .map_err(io_status)?;
+            }
+            None => {}
+        }
+
+        let captures = self
+            .mavlink_captures
+            .status(&req.bus_id)
+            .map_err(io_status)?
+            .into_iter()
+            .map(|s| MavlinkCapture {
+                bus_id: s.bus_id,
+                capturing: s.path.is_some(),
+                path: s.path.map(|p| p.display().to_string()).unwrap_or_default(),
+                remaining_s: s.remaining.as_secs_f64().ceil() as u32,
+                bytes: s.bytes,
+            })
+            .collect();
+        Ok(Response::new(MavlinkCaptureResponse { captures }))
+    }
+
+    async fn control_sensor(
+        &self,
+        request: Request<SensorControlRequest>,
+    ) -> Result<Response<SensorControlResponse>> {
+        let req = request.into_inner();
+        let command = match req.action() {
+            SensorAction::SensorRestart => TaskCommand::Restart,
+            SensorAction::SensorStop => TaskCommand::Stop,
+            SensorAction::SensorStart => TaskCommand::Start,
+            SensorAction::SensorSetFrequency => TaskCommand::SetFrequency(req.frequency_hz),
+        };
+        let tasks = self
+            .sensor_tasks
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| Status::unavailable("Sensor tasks are not running yet"))?;
+        if !tasks.contains(&req.sensor_id) {
+            return Err(ServiceError::SensorNotFound {
+                sensor_id: req.sensor_id,
+            }
+            .into());
+        }
+        // Stopping or re-initializing a sensor mid-flight would starve the estimator
+        self.interlock
+            .check("ControlSensor")
+            .map_err(Status::failed_precondition)?;
+        info!("[gRPC] ControlSensor {}: {:?}", req.sensor_id, command);
+        tasks.command(&req.sensor_id, command).await?;
+        Ok(Response::new(SensorControlResponse {
+            sensor_id: req.sensor_id,
+        }))
+    }
+
+    async fn stream_environment(
+        &self,
+        request: Request<EnvironmentRequest>,
+    ) -> Result<Response<Self::StreamEnvironmentStream>> {
+        let permit = self.stream_limit.admit()?;
+        let request = request.into_inner();
+        let defaults = self.environment.config();
+        let unit = match request.unit.map(sensorhub::TemperatureUnit::try_from) {
+            None => defaults.unit,
+            Some(Ok(sensorhub::TemperatureUnit::Celsius)) => TemperatureUnit::Celsius,
+            Some(Ok(sensorhub::TemperatureUnit::Fahrenheit)) => TemperatureUnit::Fahrenheit,
+            Some(Ok(sensorhub::TemperatureUnit::Kelvin)) => TemperatureUnit::Kelvin,
+            Some(Err(_)) => return Err(Status::invalid_argument("Unknown temperature unit")),
+        };
+        let interval_ms = request
+            .interval_ms
+            .map(u64::from)
+            .unwrap_or(defaults.interval_ms)
+            .max(1);
+        info!(
+            "[gRPC] New environment stream client connected ({:?}, every {} ms)",
+            unit, interval_ms
+        );
+
+        let (tx, rx) = mpsc::channel(4);
+        let environment = self.environment.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
+            loop {
+                ticker.tick().await;
+                let t_utc_ns = now_ns();
+                let temperatures = environment
+                    .snapshot(&request.sensor_ids, t_utc_ns)
+                    .into_iter()
+                    .map(|s| TemperatureReading {
+                        source: s.source,
+                        sensor_id: s.sensor_id,
+                        kind: s.kind.to_string(),
+                        temperature: unit.convert(s.celsius),
+                        t_utc_ns: s.t_utc_ns,
+                    })
+                    .collect();
+                let data = EnvironmentData {
+                    t_utc_ns,
+                    unit: match unit {
+                        TemperatureUnit::Celsius => sensorhub::TemperatureUnit::Celsius,
+                        TemperatureUnit::Fahrenheit => sensorhub::TemperatureUnit::Fahrenheit,
+                        TemperatureUnit::Kelvin => sensorhub::TemperatureUnit::Kelvin,
+                    } as i32,
+                    temperatures,
+                };
+                if tx.send(Ok(data)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(
+            permit.hold(ReceiverStream::new(rx)),
+        )))
+    }
+
+    async fn set_log_level(
+        &self,
+        request: Request<SetLogLevelRequest>,
+    ) -> Result<Response<SetLogLevelResponse>> {
+        let filter = request.into_inner().filter;
+        let handle = self
+            .log_filter
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| Status::unavailable("Log filter is not reloadable"))?;
+        let previous = handle
+            .with_current(|current| current.to_string())
+            .map_err(|e| Status::internal(format!("Log filter unavailable: {}", e)))?;
+
+        // An empty filter only reports the current one
+        if filter.trim().is_empty() {
+            return Ok(Response::new(SetLogLevelResponse {
+                current: previous.clone(),
+                previous,
+            }));
+        }
+
+        let new_filter = EnvFilter::try_new(filter.trim()).map_err(|e| {
+            Status::invalid_argument(format!("Invalid log filter '{}': {}", filter, e))
+        })?;
+        handle
+            .reload(new_filter)
+            .map_err(|e| Status::internal(format!("Failed to apply log filter: {}", e)))?;
+        let current = handle
+            .with_current(|current| current.to_string())
+            .unwrap_or_default();
+        info!("[gRPC] Log filter changed: '{}' -> '{}'", previous, current);
+        self.overrides
+            .update(|o| o.log_filter = Some(current.clone()));
+
+        Ok(Response::new(SetLogLevelResponse { previous, current }))
+    }
+
+    async fn get_i2c_trace(
+        &self,
+        request: Request<I2cTraceRequest>,
+    ) -> Result<Response<I2cTraceResponse>> {
+        let req = request.into_inner();
+        let transactions = self
+            .i2c_trace
+            .snapshot()
+            .into_iter()
+            .map(|t| I2cTransaction {
+                t_utc_ns: t.t_utc_ns,
+                bus_id: t.bus_id,
+                address: t.address as u32,
+                reg: t.reg.map(|r| r as u32),
+                write: t.write,
+                data: t.data,
+                duration_us: t.duration_us,
+                error: t.error,
+            })
+            .collect();
+        if req.clear {
+            self.i2c_trace.clear();
+        }
+        if let Some(enable) = req.enable {
+            info!(
+                "[gRPC] I2C trace {}",
+                if enable { "enabled" } else { "disabled" }
+            );
+            self.i2c_trace.set_enabled(enable);
+        }
+
+        Ok(Response::new(I2cTraceResponse {
+            enabled: self.i2c_trace.is_enabled(),
+            transactions,
+        }))
+    }
+
+    async fn scan_i2c(
+        &self,
+        request: Request<I2cScanRequest>,
+    ) -> Result<Response<I2cScanResponse>> {
+        let req = request.into_inner();
+        // Probing can upset a device mid-transaction
+        self.interlock
+            .check("ScanI2c")
+            .map_err(Status::failed_precondition)?;
+        let Some((bus, no_probe)) = self.i2c_buses.read().unwrap().get(&req.bus_id).cloned() else {
+            return Err(Status::not_found(format!(
+                "Unknown I2C bus '{}'",
+                req.bus_id
+            )));
+        };
+        let mut requested = Vec::with_capacity(req.addresses.len());
+        for address in req.addresses {
+            match u16::try_from(address) {
+                Ok(a) if a <= i2c::MAX_10BIT_ADDRESS => requested.push(a),
+                _ => {
+                    return Err(Status::invalid_argument(format!(
+                        "Address {:#x} is not a 7- or 10-bit I2C address",
+                        address
+                    )))
+                }
+            }
+        }
+
+        let (candidates, skipped) = i2c::scan_plan(&requested, &no_probe);
+        let mut found = Vec::new();
+        for address in candidates {
+            // One probe per lock, so sensor polling on the bus carries on between them
+            if bus.lock().await.probe(address).await {
+                found.push(address as u32);
+            }
+        }
+        info!(
+            "[gRPC] I2C scan of {}: {} device(s), {} address(es) skipped",
+            req.bus_id,
+            found.len(),
+            skipped.len()
+        );
+        Ok(Response::new(I2cScanResponse {
+            bus_id: req.bus_id,
+            found,
+            skipped: skipped.into_iter().map(u32::from).collect(),
+        }))
+    }
+
+    async fn clear_overrides(
+        &self,
+        request: Request<SensorRequest>,
+    ) -> Result<Response<ClearOverridesResponse>> {
+        let sensor_ids = request.into_inner().sensor_ids;
+        let entries_removed = self
+            .overrides
+            .update(|o| o.clear(&sensor_ids))
+            .ok_or_else(|| Status::failed_precondition("Overrides are not persisted"))?;
+        info!(
+            "[gRPC] Cleared {} override(s){}",
+            entries_removed,
+            sensors_label(&sensor_ids)
+        );
+
+        Ok(Response::new(ClearOverridesResponse {
+            entries_removed: entries_removed as u32,
+        }))
+    }
+
+    async fn zero_airspeed(
+        &self,
+        request: Request<ZeroAirspeedRequest>,
+    ) -> Result<Response<ZeroAirspeedResponse>> {
+        let req = request.into_inner();
+        if req.sensor_id.is_empty() {
+            return Err(Status::invalid_argument("sensor_id is required"));
+        }
+        self.interlock
+            .check("ZeroAirspeed")
+            .map_err(Status::failed_precondition)?;
+        if !self.sensors.read().await.contains_key(&req.sensor_id) {
+            return Err(ServiceError::SensorNotFound {
+                sensor_id: req.sensor_id,
+            }
+            .into());
+        }
+        let samples = req.samples.unwrap_or(DEFAULT_ZERO_SAMPLES);
+        let timeout_ms = req.timeout_ms.unwrap_or(DEFAULT_ZERO_TIMEOUT_MS);
+        info!(
+            "[gRPC] Zeroing pitot {} over {} samples",
+            req.sensor_id, samples
+        );
+
+        let rx = self.airspeed_cal.start_zeroing(&req.sensor_id, samples);
+        let result =
+            match tokio::time::timeout(std::time::Duration::from_millis(timeout_ms as u64), rx)
+                .await
+            {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) => {
+                    return Err(Status::aborted(format!(
+                        "Zeroing of {} was superseded by a new request",
+                        req.sensor_id
+                    )))
+                }
+                Err(_) => {
+                    return Err(ServiceError::SensorTimeout {
+                        sensor_id: req.sensor_id,
+                        timeout_ms: timeout_ms as u64,
+                    }
+                    .into())
+                }
+            };
+
+        // Kept in memory even when it cannot be stored, until the next start
+        let quality = calibration::Quality {
+            samples: result.samples.into(),
+            ..Default::default()
+        };
+        if let Err(e) = self
+            .calibrations
+            .store_airspeed(&req.sensor_id, result.offset, quality)
+        {
+            warn!("[airspeed] Pitot {} offset not saved: {}", req.sensor_id, e);
+        }
+
+        Ok(Response::new(ZeroAirspeedResponse {
+            sensor_id: req.sensor_id,
+            offset: result.offset,
+            samples: result.samples,
+        }))
+    }
+
+    async fn detect_orientation(
+        &self,
+        request: Request<DetectOrientationRequest>,
+    ) -> Result<Response<DetectOrientationResponse>> {
+        let req = request.into_inner();
+        let step = req.step();
+        self.interlock
+            .check("DetectOrientation")
+            .map_err(Status::failed_precondition)?;
+        let duration_ms = req.duration_ms.unwrap_or(DEFAULT_ORIENTATION_DURATION_MS);
+        if step == OrientationStep::OrientationNoseDown && !self.orientations.has_level() {
+            return Err(Status::failed_precondition(
+                "Run the ORIENTATION_LEVEL step first",
+            ));
+        }
+        info!(
+            "[gRPC] Orientation detection: averaging {:?} pose for {} ms{}",
+            step,
+            duration_ms,
+            sensors_label(&req.sensor_ids)
+        );
+
+        self.orientations.start_capture(req.sensor_ids.clone());
+        tokio::time::sleep(std::time::Duration::from_millis(duration_ms as u64)).await;
+        let means = self.orientations.finish_capture();
+        if means.is_empty() {
+            return Err(Status::unavailable(
+                "No IMU or magnetometer data received during the capture",
+            ));
+        }
+
+        if step == OrientationStep::OrientationLevel {
+            self.orientations.store_level(means);
+            return Ok(Response::new(DetectOrientationResponse {
+                step: step as i32,
+                instruction: "Pitch the vehicle 90° nose-down, hold it still, then call \
+                              DetectOrientation with ORIENTATION_NOSE_DOWN"
+                    .to_string(),
+                results: Vec::new(),
+            }));
+        }
+
+        let mut level: Vec<_> = self.orientations.take_level().into_iter().collect();
+        level.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+        let mut results = Vec::new();
+        for ((sensor_id, kind), level_mean) in level {
+            let detected = match (means.get(&(sensor_id.clone(), kind)), kind) {
+                (None, _) => Err("no data during the nose-down step".to_string()),
+                (Some(_), VectorKind::Mag) if req.magnetic_inclination_deg.is_none() => {
+                    Err("magnetic_inclination_deg is required for magnetometers".to_string())
+                }
+                (Some(nose_down), VectorKind::Mag) => orientation::infer(
+                    level_mean,
+                    *nose_down,
+                    Reference::MagneticField {
+                        inclination_deg: req.magnetic_inclination_deg.unwrap_or_default() as f64,
+                    },
+                ),
+                (Some(nose_down), VectorKind::Accel) => {
+                    orientation::infer(level_mean, *nose_down, Reference::Gravity)
+                }
+            };
+            let result = match detected {
+                Ok((orientation, error_deg)) => {
+                    info!(
+                        "[gRPC] Sensor {} mounted roll {}° pitch {}° yaw {}° ({:.1}° error)",
+                        sensor_id,
+                        orientation.roll_deg,
+                        orientation.pitch_deg,
+                        orientation.yaw_deg,
+                        error_deg
+                    );
+                    self.orientations.set(&sensor_id, orientation);
+                    let quality = calibration::Quality {
+                        residual: error_deg.into(),
+                        ..Default::default()
+                    };
+                    let stored =
+                        match self
+                            .calibrations
+                            .store_orientation(&sensor_id, orientation, quality)
+                        {
+                            Ok(_) => true,
+                            Err(e) => {
+                                warn!("[gRPC] Orientation of {} not stored: {}", sensor_id, e);
+                                false
+                            }
+                        };
+                    OrientationResult {
+                        sensor_id,
+                        orientation: Some(sensorhub::SensorOrientation {
+                            roll_deg: orientation.roll_deg,
+                            pitch_deg: orientation.pitch_deg,
+                            yaw_deg: orientation.yaw_deg,
+                        }),
+                        error_deg,
+                        error: None,
+                        stored,
+                    }
+                }
+                Err(e) => {
+                    warn!("[gRPC] Orientation of {} not detected: {}", sensor_id, e);
+                    OrientationResult {
+                        sensor_id,
+                        error: Some(e),
+                        ..Default::default()
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        Ok(Response::new(DetectOrientationResponse {
+            step: step as i32,
+            instruction: "Done, the detected orientations are applied".to_string(),
+            results,
         }))
     }
 }
@@ -265,6 +3023,8 @@ fn convert_header(header: &crate::messages::Header) -> Header {
         clock_err_ppb: header.clock_err_ppb,
         sigma_t_ns: header.sigma_t_ns,
         schema_v: header.schema_v as u32,
+        calibration_id: header.calibration_id.clone(),
+        watermark: None,
     }
 }
 
@@ -274,3 +3034,12 @@ pub fn create_grpc_server(service: SensorHubService) -> SensorHubServer<SensorHu
         .max_encoding_message_size(1024 * 1024) // 1MB max message size
         .max_decoding_message_size(1024 * 1024)
 }
+
+/// gRPC server reflection, so grpcurl and other dynamic clients can list and call the API
+pub fn create_reflection_server() -> ServerReflectionServer<impl ServerReflection> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(sensorhub::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1alpha()
+        .expect("build.rs emits valid descriptor sets")
+}
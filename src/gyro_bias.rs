@@ -0,0 +1,185 @@
+use crate::config::sensor_config::GyroBiasConfig;
+use crate::messages::SensorMessage;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// Samples averaged before a deviation counts as motion
+const MIN_SAMPLES: u64 = 10;
+
+#[derive(Debug)]
+enum Estimate {
+    Averaging {
+        /// First sample of the estimation (for the timeout) and of the current window
+        started_ns: u64,
+        window_start_ns: u64,
+        sum: [f64; 3],
+        count: u64,
+    },
+    Done([f64; 3]),
+    /// Never stationary within the timeout: published uncorrected
+    GaveUp,
+}
+
+impl Estimate {
+    fn start(t_ns: u64) -> Self {
+        Estimate::Averaging {
+            started_ns: t_ns,
+            window_start_ns: t_ns,
+            sum: [0.0; 3],
+            count: 0,
+        }
+    }
+
+    /// Feed one sample (rad/s); true when the estimate just finished or gave up
+    fn feed(
+        &mut self,
+        sensor_id: &str,
+        gyro: [f64; 3],
+        t_ns: u64,
+        config: &GyroBiasConfig,
+    ) -> bool {
+        let Estimate::Averaging {
+            started_ns,
+            window_start_ns,
+            sum,
+            count,
+        } = self
+        else {
+            return false;
+        };
+        let elapsed_s = |since: u64| t_ns.saturating_sub(since) as f64 / 1e9;
+
+        if *count >= MIN_SAMPLES {
+            let moving =
+                (0..3).any(|i| (gyro[i] - sum[i] / *count as f64).abs() > config.motion_threshold);
+            if moving {
+                if elapsed_s(*started_ns) >= config.timeout_s {
+                    warn!(
+                        "[gyro_bias] {} not stationary within {}s, publishing uncorrected",
+                        sensor_id, config.timeout_s
+                    );
+                    *self = Estimate::GaveUp;
+                    return true;
+                }
+                // Restart the window after this sample
+                *window_start_ns = t_ns;
+                *sum = [0.0; 3];
+                *count = 0;
+                return false;
+            }
+        }
+        (0..3).for_each(|i| sum[i] += gyro[i]);
+        *count += 1;
+
+        if elapsed_s(*window_start_ns) >= config.duration_s && *count >= MIN_SAMPLES {
+            let bias = sum.map(|s| s / *count as f64);
+            info!(
+                "[gyro_bias] {}: bias [{:.4}, {:.4}, {:.4}] rad/s from {} samples",
+                sensor_id, bias[0], bias[1], bias[2], count
+            );
+            *self = Estimate::Done(bias);
+            return true;
+        }
+        false
+    }
+}
+
+/// Estimates each IMU's turn-on gyro bias while stationary at startup, then subtracts it
+pub struct GyroBiasEstimator {
+    config: RwLock<GyroBiasConfig>,
+    imus: Mutex<HashMap<String, Estimate>>,
+}
+
+impl GyroBiasEstimator {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(GyroBiasConfig::default()),
+            imus: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn configure(&self, config: &GyroBiasConfig) {
+        *self.config.write().unwrap() = config.clone();
+    }
+
+    /// Feed an IMU sample to its estimate, or subtract the finished estimate from it
+    pub fn apply(&self, message: &mut SensorMessage) {
+        let SensorMessage::Imu(imu) = message else {
+            return;
+        };
+        let config = self.config.read().unwrap();
+        if !config.enabled
+            || (!config.sensors.is_empty() && !config.sensors.contains(&imu.h.sensor_id))
+        {
+            return;
+        }
+        let mut imus = self.imus.lock().unwrap();
+        let estimate = imus
+            .entry(imu.h.sensor_id.clone())
+            .or_insert_with(|| Estimate::start(imu.h.t_utc_ns));
+        match estimate {
+            Estimate::Averaging { .. } => {
+                let gyro = match imu.precise {
+                    Some(p) => [p[3], p[4], p[5]],
+                    None => [imu.gx, imu.gy, imu.gz].map(f64::from),
+                };
+                estimate.feed(&imu.h.sensor_id, gyro, imu.h.t_utc_ns, &config);
+            }
+            Estimate::Done(bias) => {
+                imu.gx -= bias[0] as f32;
+                imu.gy -= bias[1] as f32;
+                imu.gz -= bias[2] as f32;
+                if let Some(p) = imu.precise.as_mut() {
+                    (0..3).for_each(|i| p[3 + i] -= bias[i]);
+                }
+            }
+            Estimate::GaveUp => {}
+        }
+    }
+
+    /// Bias subtracted from an IMU (rad/s, sensor axes), once estimated
+    pub fn bias(&self, sensor_id: &str) -> Option<[f64; 3]> {
+        match self.imus.lock().unwrap().get(sensor_id) {
+            Some(Estimate::Done(bias)) => Some(*bias),
+            _ => None,
+        }
+    }
+}
+
+impl Default for GyroBiasEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motion_restarts_window() {
+        let config = GyroBiasConfig {
+            enabled: true,
+            duration_s: 1.0,
+            ..GyroBiasConfig::default()
+        };
+        let bias = [0.05, -0.03, 0.01];
+        let mut estimate = Estimate::start(0);
+        let ms = 1_000_000;
+        // Stationary for 0.5 s, then a bump: the window restarts at t = 0.5 s
+        for t in 0..500 {
+            assert!(!estimate.feed("imu0", bias, t * ms, &config));
+        }
+        assert!(!estimate.feed("imu0", [0.5, 0.0, 0.0], 500 * ms, &config));
+        let mut t = 501;
+        while !estimate.feed("imu0", bias, t * ms, &config) {
+            t += 1;
+        }
+        assert!(t >= 1500);
+        let Estimate::Done(estimated) = estimate else {
+            panic!("expected a finished estimate, got {:?}", estimate);
+        };
+        assert!((0..3).all(|i| (estimated[i] - bias[i]).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::grpc_service::sensorhub::sensor_hub_server::SensorHubServer;
+use crate::grpc_service::SensorHubService;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic_health::pb::health_server::{Health, HealthServer};
+use tonic_health::server::{health_reporter, HealthReporter};
+use tonic_health::ServingStatus;
+use tracing::debug;
+
+/// A sensor counts as healthy if it published within this window
+const STALE_AFTER: Duration = Duration::from_secs(3);
+/// How often the reported health is re-evaluated
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+/// Service name of the overall status, besides "sensorhub.SensorHub"
+const OVERALL: &str = "";
+
+/// Health-relevant state of one sensor
+pub struct SensorHealth {
+    pub sensor_id: String,
+    pub bus_id: String,
+    pub is_active: bool,
+    pub is_healthy: bool,
+    pub last_message_time_ns: u64,
+}
+
+impl SensorHealth {
+    fn ok(&self, now_ns: u64) -> bool {
+        self.is_active
+            && self.is_healthy
+            && now_ns.saturating_sub(self.last_message_time_ns) < STALE_AFTER.as_nanos() as u64
+    }
+}
+
+/// Whether each health service name is serving
+///
+/// "" is healthy when every bus has a sensor delivering data, "bus/<id>" when that bus
+/// does, and "sensor/<id>" when that sensor does.
+pub fn evaluate(sensors: &[SensorHealth], now_ns: u64) -> BTreeMap<String, bool> {
+    let mut statuses = BTreeMap::new();
+    let mut buses: BTreeMap<&str, bool> = BTreeMap::new();
+    for sensor in sensors {
+        let ok = sensor.ok(now_ns);
+        statuses.insert(format!("sensor/{}", sensor.sensor_id), ok);
+        *buses.entry(&sensor.bus_id).or_default() |= ok;
+    }
+    statuses.insert(
+        OVERALL.to_string(),
+        !buses.is_empty() && buses.values().all(|&ok| ok),
+    );
+    for (bus_id, ok) in buses {
+        statuses.insert(format!("bus/{}", bus_id), ok);
+    }
+    statuses
+}
+
+/// Push changed statuses to the reporter
+async fn report(
+    reporter: &mut HealthReporter,
+    last: &mut HashMap<String, bool>,
+    statuses: BTreeMap<String, bool>,
+) {
+    for (service, ok) in statuses {
+        if last.insert(service.clone(), ok) == Some(ok) {
+            continue;
+        }
+        debug!("[health] '{}' serving: {}", service, ok);
+        let status = if ok {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        if service == OVERALL {
+            if ok {
+                reporter
+                    .set_serving::<SensorHubServer<SensorHubService>>()
+                    .await;
+            } else {
+                reporter
+                    .set_not_serving::<SensorHubServer<SensorHubService>>()
+                    .await;
+            }
+        }
+        reporter.set_service_status(&service, status).await;
+    }
+}
+
+/// Standard `grpc.health.v1.Health` service, kept up to date from the sensor statistics
+pub fn create_health_server(grpc_service: Arc<SensorHubService>) -> HealthServer<impl Health> {
+    let (mut reporter, server) = health_reporter();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(UPDATE_INTERVAL);
+        let mut last = HashMap::new();
+        loop {
+            ticker.tick().await;
+            let sensors = grpc_service.sensor_health().await;
+            let now_ns = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64;
+            report(&mut reporter, &mut last, evaluate(&sensors, now_ns)).await;
+        }
+    });
+    server
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(sensor_id: &str, bus_id: &str, last_message_s: u64) -> SensorHealth {
+        SensorHealth {
+            sensor_id: sensor_id.to_string(),
+            bus_id: bus_id.to_string(),
+            is_active: true,
+            is_healthy: true,
+            last_message_time_ns: last_message_s * 1_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_health_by_bus_and_sensor() {
+        let now_ns = 100 * 1_000_000_000;
+        let mut sensors = vec![
+            sensor("fc_imu0", "serial0", 100),
+            sensor("fc_gps0", "serial0", 50),
+            sensor("baro0", "i2c0", 99),
+        ];
+        let statuses = evaluate(&sensors, now_ns);
+        assert!(statuses[""]);
+        assert!(!statuses["sensor/fc_gps0"]);
+        assert!(!statuses.contains_key("bus/uart0"));
+
+        sensors[2].is_active = false;
+        let statuses = evaluate(&sensors, now_ns);
+        assert!(!statuses["bus/i2c0"]);
+        assert!(statuses["bus/serial0"]);
+        assert!(!statuses[""]);
+        assert!(!evaluate(&[], now_ns)[""]);
+    }
+}
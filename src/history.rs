@@ -0,0 +1,255 @@
+use crate::config::sensor_config::HistoryConfig;
+use crate::grpc_service::sensorhub::{
+    history_record::Record, HistoryRecord, SensorData, SensorEvent,
+};
+use crate::publish_queue::SensorTopic;
+use prost::Message;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// Stream name of the hub's events
+pub const EVENTS_STREAM: &str = "events";
+
+/// Retained records of one stream, oldest first
+#[derive(Default)]
+struct Stream {
+    retention_ns: u64,
+    records: VecDeque<(HistoryRecord, usize)>,
+    bytes: usize,
+}
+
+impl Stream {
+    fn push(&mut self, record: HistoryRecord) {
+        // Held as decoded messages; the encoded length is a close enough estimate of their
+        // heap use
+        let size = std::mem::size_of::<HistoryRecord>() + record.encoded_len();
+        let cutoff = record.t_utc_ns.saturating_sub(self.retention_ns);
+        self.records.push_back((record, size));
+        self.bytes += size;
+        while let Some((oldest, size)) = self.records.front() {
+            if oldest.t_utc_ns >= cutoff {
+                break;
+            }
+            self.bytes -= size;
+            self.records.pop_front();
+        }
+    }
+}
+
+struct Inner {
+    retention_ns: HashMap<String, u64>,
+    default_ns: u64,
+    streams: HashMap<String, Stream>,
+}
+
+impl Inner {
+    fn record(&mut self, stream: &str, record: impl FnOnce() -> HistoryRecord) {
+        if !self.streams.contains_key(stream) {
+            let retention_ns = self
+                .retention_ns
+                .get(stream)
+                .copied()
+                .unwrap_or(self.default_ns);
+            if retention_ns == 0 {
+                return;
+            }
+            self.streams.insert(
+                stream.to_string(),
+                Stream {
+                    retention_ns,
+                    ..Default::default()
+                },
+            );
+        }
+        self.streams.get_mut(stream).unwrap().push(record());
+    }
+}
+
+/// Recent samples and events kept in memory, per stream, so clients that connect late or
+/// reconnect can backfill
+pub struct History {
+    inner: Mutex<Inner>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                retention_ns: HashMap::new(),
+                default_ns: 0,
+                streams: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Apply the `[history]` retention times, dropping everything held so far
+    pub fn configure(&self, config: &HistoryConfig) {
+        let seconds = |s: f64| (s.max(0.0) * 1e9) as u64;
+        let mut inner = self.inner.lock().unwrap();
+        inner.retention_ns = config
+            .retention_s
+            .iter()
+            .map(|(stream, s)| (stream.clone(), seconds(*s)))
+            .collect();
+        inner.default_ns = seconds(config.default_s);
+        inner.streams.clear();
+        for (stream, s) in config.retention_s.iter() {
+            info!("[history] Keeping {} for {} s", stream, s);
+        }
+        if config.default_s > 0.0 {
+            info!("[history] Keeping other streams for {} s", config.default_s);
+        }
+    }
+
+    /// Keep a published sample, if its stream is retained
+    pub fn record_sample(&self, data: &SensorData, t_utc_ns: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .record(data.kind(), || HistoryRecord {
+                t_utc_ns,
+                record: Some(Record::Sample(data.clone())),
+            });
+    }
+
+    /// Keep an event, if events are retained
+    pub fn record_event(&self, event: &SensorEvent, t_utc_ns: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .record(EVENTS_STREAM, || HistoryRecord {
+                t_utc_ns,
+                record: Some(Record::Event(event.clone())),
+            });
+    }
+
+    /// Estimated memory held, in bytes
+    pub fn bytes(&self) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner.streams.values().map(|s| s.bytes as u64).sum()
+    }
+
+    /// Retained records of `streams` (all if empty) from `sensor_ids` (all if empty)
+    /// published after `since_utc_ns`, oldest first
+    pub fn query(
+        &self,
+        streams: &[String],
+        sensor_ids: &[String],
+        since_utc_ns: u64,
+    ) -> Vec<HistoryRecord> {
+        let inner = self.inner.lock().unwrap();
+        let mut records: Vec<HistoryRecord> = inner
+            .streams
+            .iter()
+            .filter(|(name, _)| streams.is_empty() || streams.contains(name))
+            .flat_map(|(_, stream)| stream.records.iter().map(|(record, _)| record))
+            .filter(|record| record.t_utc_ns > since_utc_ns)
+            .filter(|record| match &record.record {
+                Some(Record::Sample(data)) => {
+                    sensor_ids.is_empty() || sensor_ids.iter().any(|id| id == data.sensor_id())
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| record.t_utc_ns);
+        records
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Feed the published unified stream and events into `history`
+pub fn spawn_recorder(
+    history: Arc<History>,
+    mut samples: broadcast::Receiver<SensorData>,
+    mut events: broadcast::Receiver<SensorEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                sample = samples.recv() => match sample {
+                    Ok(data) => history.record_sample(&data, now_ns()),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("[history] Fell behind, {} sample(s) not kept", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                event = events.recv() => match event {
+                    Ok(event) => history.record_event(&event, now_ns()),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("[history] Fell behind, {} event(s) not kept", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_service::sensorhub::{sensor_data::Data, GpsData, Header, ImuData};
+
+    #[test]
+    fn test_retention_per_stream() {
+        let history = History::new();
+        history.configure(&HistoryConfig {
+            enabled: true,
+            retention_s: [("imu".to_string(), 10.0), ("gps".to_string(), 300.0)].into(),
+            default_s: 0.0,
+        });
+        let header = Some(Header {
+            sensor_id: "s".to_string(),
+            ..Default::default()
+        });
+        let imu = SensorData {
+            data: Some(Data::Imu(ImuData {
+                header: header.clone(),
+                ..Default::default()
+            })),
+            source: None,
+        };
+        let gps = SensorData {
+            data: Some(Data::Gps(GpsData {
+                header,
+                ..Default::default()
+            })),
+            source: None,
+        };
+        let s = 1_000_000_000;
+        for t in 1..=60 {
+            history.record_sample(&imu, t * s);
+            history.record_sample(&gps, t * s);
+        }
+        history.record_event(&SensorEvent::default(), 60 * s);
+
+        // IMU back to 50 s, all 60 GPS fixes, no events
+        let records = history.query(&[], &[], 0);
+        assert_eq!(records.len(), 11 + 60);
+        assert_eq!(
+            history.query(&["imu".to_string()], &[], 0)[0].t_utc_ns,
+            50 * s
+        );
+        assert!(history
+            .query(&[EVENTS_STREAM.to_string()], &[], 0)
+            .is_empty());
+        assert_eq!(history.query(&[], &[], 55 * s).len(), 10);
+        assert!(history.bytes() > 0);
+    }
+}
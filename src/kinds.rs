@@ -0,0 +1,41 @@
+//! Data kind names, as the `SensorData` oneof fields. Streams, `[[group]]` members, MQTT
+//! topics and CSV file names all use these. Shared with build.rs, so it has no dependencies.
+
+pub const IMU: &str = "imu";
+pub const MAGNETOMETER: &str = "magnetometer";
+pub const BAROMETER: &str = "barometer";
+pub const GPS: &str = "gps";
+pub const RANGE: &str = "range";
+pub const GLOBAL_POSITION: &str = "global_position";
+pub const ATTITUDE: &str = "attitude";
+pub const AIRSPEED: &str = "airspeed";
+pub const VFR_HUD: &str = "vfr_hud";
+pub const VIBRATION: &str = "vibration";
+pub const RC_CHANNELS: &str = "rc_channels";
+pub const LOCAL_POSITION: &str = "local_position";
+pub const ESC: &str = "esc";
+pub const SYSTEM: &str = "system";
+pub const ALTITUDE: &str = "altitude";
+pub const GIMBAL_ATTITUDE: &str = "gimbal_attitude";
+pub const MEASUREMENTS: &str = "measurements";
+
+/// Every kind, in `SensorData` oneof order
+pub const ALL: [&str; 17] = [
+    IMU,
+    MAGNETOMETER,
+    BAROMETER,
+    GPS,
+    RANGE,
+    GLOBAL_POSITION,
+    ATTITUDE,
+    AIRSPEED,
+    VFR_HUD,
+    VIBRATION,
+    RC_CHANNELS,
+    LOCAL_POSITION,
+    ESC,
+    SYSTEM,
+    ALTITUDE,
+    GIMBAL_ATTITUDE,
+    MEASUREMENTS,
+];
@@ -0,0 +1,55 @@
+//! Navigate SensorHub: sensor drivers, buses and the gRPC service, shared by the hub binary
+//! and the `test-util` helpers for client tests.
+
+pub mod accel_calibration;
+pub mod ahrs;
+pub mod airspeed;
+pub mod altitude;
+pub mod build_info;
+pub mod bus;
+pub mod calibration;
+pub mod clock;
+pub mod config;
+pub mod csv_log;
+pub mod decimation;
+pub mod diagnostics;
+pub mod downlink;
+pub mod environment;
+pub mod errors;
+pub mod filters;
+pub mod grpc_service;
+pub mod gyro_bias;
+pub mod health;
+pub mod history;
+pub mod kinds;
+pub mod mag_calibration;
+pub mod mavlink_log;
+pub mod mcap;
+pub mod messages;
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod ntp;
+pub mod orientation;
+pub mod overrides;
+pub mod pps;
+pub mod publish_queue;
+pub mod rate_monitor;
+pub mod registry;
+pub mod replay;
+pub mod rosbridge;
+pub mod rpc_guard;
+pub mod safety;
+pub mod scenario;
+pub mod scheduler;
+pub mod schema;
+pub mod sensors;
+pub mod system_monitor;
+pub mod timing;
+pub mod watchdog;
+#[cfg(feature = "webrtc")]
+pub mod webrtc_sink;
+pub mod websocket;
+
+#[cfg(feature = "test-util")]
+pub mod test_hub;
@@ -0,0 +1,297 @@
+use crate::messages::SensorMessage;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+type Vector = [f64; 3];
+type Matrix = [Vector; 3];
+
+/// Samples kept per session (a few minutes at 100 Hz)
+const MAX_SAMPLES: usize = 50_000;
+/// Fewer samples cannot constrain the 9 ellipsoid parameters reliably
+pub const MIN_SAMPLES: usize = 200;
+/// Each body axis must point within ~60° of both directions of the field at some point
+const MIN_AXIS_COVERAGE: f64 = 0.5;
+
+/// Hard/soft-iron correction fitted to a rotation, in the calibration file convention:
+/// `soft_iron * (raw - hard_iron)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagFit {
+    pub hard_iron: Vector,
+    pub soft_iron: Matrix,
+    /// Radius of the corrected sphere (μT)
+    pub field_ut: f64,
+    /// RMS of the corrected magnitude's relative deviation from `field_ut`
+    pub residual: f64,
+    /// Worst axis: how closely it pointed both ways along the field (1 = exactly)
+    pub coverage: f64,
+}
+
+/// Raw magnetometer samples collected between StartMagCalibration and FinishMagCalibration
+#[derive(Default)]
+pub struct MagCalibrator {
+    sessions: Mutex<HashMap<String, Vec<Vector>>>,
+}
+
+impl MagCalibrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start (or restart) collecting samples of `sensor_id`
+    pub fn start(&self, sensor_id: &str) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(sensor_id.to_string(), Vec::new());
+    }
+
+    /// Record an uncalibrated magnetometer sample if its sensor is being calibrated
+    pub fn observe(&self, message: &SensorMessage) {
+        let SensorMessage::Magnetometer(mag) = message else {
+            return;
+        };
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(samples) = sessions.get_mut(&mag.h.sensor_id) {
+            if samples.len() < MAX_SAMPLES {
+                samples.push([mag.mx, mag.my, mag.mz].map(f64::from));
+            }
+        }
+    }
+
+    /// End a session, returning its samples (None if it was never started)
+    pub fn finish(&self, sensor_id: &str) -> Option<Vec<Vector>> {
+        self.sessions.lock().unwrap().remove(sensor_id)
+    }
+}
+
+/// Least-squares ellipsoid fit: hard-iron offset is the centre, soft-iron maps the
+/// ellipsoid onto a sphere of the same volume
+pub fn fit(samples: &[Vector]) -> Result<MagFit, String> {
+    if samples.len() < MIN_SAMPLES {
+        return Err(format!(
+            "{} samples collected, at least {} needed",
+            samples.len(),
+            MIN_SAMPLES
+        ));
+    }
+    // Normalise to unit scale for a well-conditioned system
+    let scale = samples.iter().map(|p| norm(*p)).sum::<f64>() / samples.len() as f64;
+    if scale <= 0.0 {
+        return Err("all samples are zero".to_string());
+    }
+
+    // a x² + b y² + c z² + 2d xy + 2e xz + 2f yz + 2g x + 2h y + 2i z = 1
+    let mut ata = [[0.0; 9]; 9];
+    let mut atb = [0.0; 9];
+    for p in samples {
+        let [x, y, z] = p.map(|v| v / scale);
+        let row = [
+            x * x,
+            y * y,
+            z * z,
+            2.0 * x * y,
+            2.0 * x * z,
+            2.0 * y * z,
+            2.0 * x,
+            2.0 * y,
+            2.0 * z,
+        ];
+        for i in 0..9 {
+            for j in 0..9 {
+                ata[i][j] += row[i] * row[j];
+            }
+            atb[i] += row[i];
+        }
+    }
+    let [a, b, c, d, e, f, g, h, i] = solve(ata, atb)
+        .ok_or("samples do not constrain an ellipsoid - rotate through more orientations")?;
+
+    let m = [[a, d, e], [d, b, f], [e, f, c]];
+    let m_inv = invert(m).ok_or("degenerate fit - rotate through more orientations")?;
+    let centre = mat_vec(m_inv, [g, h, i]).map(|v| -v);
+    // (p - centre)ᵀ M (p - centre) = 1 + centreᵀ M centre
+    let k = 1.0 + dot(centre, mat_vec(m, centre));
+    let m = m.map(|row| row.map(|v| v / k));
+    let (values, vectors) = eigen_symmetric(m);
+    if values.iter().any(|&v| v <= 0.0) {
+        return Err(
+            "samples do not lie on an ellipsoid - rotate through more orientations".to_string(),
+        );
+    }
+
+    // M^½ maps the ellipsoid onto the unit sphere; rescale to keep the volume
+    let radius = values.iter().product::<f64>().powf(-1.0 / 6.0);
+    let mut soft_iron = [[0.0; 3]; 3];
+    for (value, vector) in values.iter().zip(vectors.iter()) {
+        let weight = value.sqrt() * radius;
+        for r in 0..3 {
+            for c in 0..3 {
+                soft_iron[r][c] += weight * vector[r] * vector[c];
+            }
+        }
+    }
+
+    let hard_iron = centre.map(|v| v * scale);
+    let corrected: Vec<Vector> = samples
+        .iter()
+        .map(|p| mat_vec(soft_iron, sub(*p, hard_iron)))
+        .collect();
+    let field_ut = radius * scale;
+    let residual = (corrected
+        .iter()
+        .map(|v| (norm(*v) / field_ut - 1.0).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64)
+        .sqrt();
+
+    // Without both directions along every axis the fit extrapolates
+    let mut coverage = 1.0f64;
+    for axis in 0..3 {
+        let (low, high) = corrected.iter().fold((0.0f64, 0.0f64), |(low, high), v| {
+            let u = v[axis] / norm(*v);
+            (low.min(u), high.max(u))
+        });
+        coverage = coverage.min(high).min(-low);
+        if high < MIN_AXIS_COVERAGE || -low < MIN_AXIS_COVERAGE {
+            return Err(format!(
+                "poor coverage of the {} axis - rotate through more orientations",
+                ["x", "y", "z"][axis]
+            ));
+        }
+    }
+
+    Ok(MagFit {
+        hard_iron,
+        soft_iron,
+        field_ut,
+        residual,
+        coverage,
+    })
+}
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(v: Vector) -> f64 {
+    dot(v, v).sqrt()
+}
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn mat_vec(m: Matrix, v: Vector) -> Vector {
+    m.map(|row| dot(row, v))
+}
+
+fn invert(m: Matrix) -> Option<Matrix> {
+    let cofactor = |r: usize, c: usize| {
+        let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+        let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+        m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+    };
+    let det = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum::<f64>();
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    // Inverse is the transposed cofactor matrix over the determinant
+    Some([0, 1, 2].map(|r| [0, 1, 2].map(|c| cofactor(c, r) / det)))
+}
+
+/// Gaussian elimination with partial pivoting
+fn solve<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..N {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col];
+            for (x, p) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let tail: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Eigenvalues and unit eigenvectors of a symmetric 3×3 matrix (cyclic Jacobi)
+fn eigen_symmetric(mut a: Matrix) -> (Vector, Matrix) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off = a[0][1].powi(2) + a[0][2].powi(2) + a[1][2].powi(2);
+        if off < 1e-24 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q].abs() < 1e-30 {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let (cos, sin) = (1.0 / (t * t + 1.0).sqrt(), t / (t * t + 1.0).sqrt());
+            // A' = Jᵀ A J, V' = V J
+            for row in a.iter_mut().chain(v.iter_mut()) {
+                let (rp, rq) = (row[p], row[q]);
+                row[p] = cos * rp - sin * rq;
+                row[q] = sin * rp + cos * rq;
+            }
+            let (row_p, row_q) = (a[p], a[q]);
+            a[p] = [0, 1, 2].map(|k| cos * row_p[k] - sin * row_q[k]);
+            a[q] = [0, 1, 2].map(|k| sin * row_p[k] + cos * row_q[k]);
+        }
+    }
+    // Columns of V are the eigenvectors
+    let vectors = [0, 1, 2].map(|c| [v[0][c], v[1][c], v[2][c]]);
+    ([a[0][0], a[1][1], a[2][2]], vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_recovers_distortion() {
+        // Unit sphere directions, distorted by a sheared soft-iron matrix and an offset
+        let distortion = [[1.2, 0.1, 0.0], [0.1, 0.8, 0.05], [0.0, 0.05, 1.0]];
+        let offset = [12.0, -3.5, 40.2];
+        let mut samples = Vec::new();
+        for i in 0..40 {
+            for j in 0..20 {
+                let (lon, lat) = (i as f64 * 0.157, (j as f64 / 19.0 - 0.5) * 3.1);
+                let dir = [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()];
+                let raw = mat_vec(distortion, dir.map(|v| v * 48.0));
+                samples.push([raw[0] + offset[0], raw[1] + offset[1], raw[2] + offset[2]]);
+            }
+        }
+
+        let fit = fit(&samples).unwrap();
+        assert!((0..3).all(|i| (fit.hard_iron[i] - offset[i]).abs() < 1e-6));
+        assert!(fit.residual < 1e-9);
+        assert!(fit.coverage > 0.99);
+        // The corrected field is a sphere
+        for p in samples.iter().step_by(37) {
+            let corrected = norm(mat_vec(fit.soft_iron, sub(*p, fit.hard_iron)));
+            assert!((corrected / fit.field_ut - 1.0).abs() < 1e-9);
+        }
+
+        // Turning only about the vertical axis cannot be fitted
+        let flat: Vec<Vector> = (0..400)
+            .map(|i| {
+                let yaw = i as f64 * 0.0157;
+                [30.0 * yaw.cos(), 30.0 * yaw.sin(), 40.0]
+            })
+            .collect();
+        assert!(super::fit(&flat).is_err());
+    }
+}
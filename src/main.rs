@@ -1,40 +1,106 @@
-mod bus;
-mod config;
-mod errors;
-mod grpc_service;
-mod messages;
-mod registry;
-mod scheduler;
-mod sensors;
-
-use crate::config::load_sensor_config;
-use crate::grpc_service::{create_grpc_server, SensorHubService};
-use crate::registry::init_all;
-use crate::scheduler::spawn_sensor_tasks;
+use navigate_sensorhub::config::load_sensor_config;
+use navigate_sensorhub::diagnostics::{LogRing, LOG_RING_BYTES};
+use navigate_sensorhub::grpc_service::{
+    create_grpc_server, create_reflection_server, SensorHubService,
+};
+use navigate_sensorhub::health::create_health_server;
+use navigate_sensorhub::publish_queue::SensorQueue;
+use navigate_sensorhub::registry::init_all;
+use navigate_sensorhub::rpc_guard::RpcLayer;
+use navigate_sensorhub::scheduler::spawn_sensor_tasks;
+use navigate_sensorhub::{
+    ahrs, altitude, build_info, calibration, csv_log, diagnostics, metrics, pps, rosbridge,
+    system_monitor, watchdog, websocket,
+};
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
 use std::sync::Arc;
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::server::Router;
 use tonic::transport::Server;
+use tower_layer::{Identity, Stack};
 use tracing::{error, info};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter};
 
 #[tokio::main]
 async fn main() {
     // Initialize tracing with RUST_LOG environment variable support
     // RUST_LOG=debug for verbose, RUST_LOG=info for normal, RUST_LOG=warn for production
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
+    // The log is also kept in memory for diagnostic bundles
+    // The filter can be replaced at runtime through SetLogLevel
+    let log_ring = LogRing::new(LOG_RING_BYTES);
+    let (log_filter, log_filter_handle) = reload::Layer::new(
+        EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()),
+    );
+    tracing_subscriber::registry()
+        .with(log_filter)
+        .with(fmt::layer())
+        .with(fmt::layer().with_ansi(false).with_writer(log_ring.clone()))
         .init();
 
-    info!("[NavigateSensorHub] starting up...");
+    info!(
+        "[NavigateSensorHub] starting up... (v{} {}, {} {}, features: {})",
+        build_info::VERSION,
+        build_info::GIT_HASH,
+        build_info::TARGET,
+        build_info::PROFILE,
+        build_info::features().join(", ")
+    );
 
     // Load configuration from CONFIG_PATH or default
     let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config".to_string());
     let sensor_config_path = format!("{}/sensors.toml", config_path);
-    let sensor_config =
+    let mut sensor_config =
         load_sensor_config(&sensor_config_path).expect("Failed to load sensor config");
     info!("[config] loaded {} sensor(s)", sensor_config.sensors.len());
 
     // Create gRPC service BEFORE initializing sensors (MAVLink sensors need it)
     let grpc_service = Arc::new(SensorHubService::new());
+    grpc_service.set_log_filter_handle(log_filter_handle);
+    grpc_service.set_config_file(sensor_config_path.clone().into());
+    if sensor_config.overrides.enabled {
+        let overrides_path = Path::new(&config_path).join(&sensor_config.overrides.path);
+        grpc_service
+            .open_overrides(overrides_path)
+            .apply_to_sensors(&mut sensor_config);
+    }
+    if sensor_config.calibration.enabled {
+        grpc_service.load_calibrations(
+            &Path::new(&config_path).join(&sensor_config.calibration.dir),
+            &sensor_config.calibration,
+        );
+    }
+    grpc_service.configure_gyro_bias(&sensor_config.gyro_bias);
+    grpc_service.configure_filters(&sensor_config.filters);
+    grpc_service.configure_decimation(&sensor_config.decimations);
+    for entry in sensor_config.sensors.iter() {
+        if let Some(precision) = entry.precision {
+            grpc_service.set_imu_precision(&entry.id, precision);
+        }
+    }
+    for entry in sensor_config.mountings.iter() {
+        let frame_id = entry.frame_id.as_deref().unwrap_or(&entry.sensor);
+        grpc_service.set_mounting(&entry.sensor, entry.position, frame_id);
+    }
+    for group in sensor_config.groups.iter() {
+        grpc_service.add_consumer_group(group);
+    }
+    for entry in sensor_config.queues.iter() {
+        grpc_service.set_queue_policy(&entry.sensor, SensorQueue::from_entry(entry));
+    }
+    grpc_service.configure_environment(&sensor_config.environment);
+    grpc_service.configure_downlink(&sensor_config.downlink);
+    grpc_service.configure_i2c_trace(&sensor_config.i2c_trace);
+    grpc_service.configure_scenario(&sensor_config.scenario);
+    grpc_service.configure_safety(&sensor_config.safety);
+    grpc_service.configure_recording(&sensor_config.recording);
+    grpc_service.configure_history(&sensor_config.history);
+    grpc_service.configure_grpc(&sensor_config.grpc);
+    grpc_service.configure_rate_monitor(&sensor_config.rate_monitor);
+    pps::spawn_discipline(&sensor_config.pps);
     info!("[gRPC] Service initialized");
 
     // Initialize sensors and buses (pass gRPC service for MAVLink sensor injection)
@@ -43,22 +109,150 @@ async fn main() {
         .expect("Initialization failed");
     info!("[registry] sensors and buses initialized");
 
-    // Spawn sensor tasks with gRPC service
+    // Spawn sensor tasks with gRPC service, controllable through ControlSensor
     let grpc_service_for_sensors = grpc_service.clone();
-    spawn_sensor_tasks(sensors, buses, grpc_service_for_sensors, &sensor_config).await;
+    let sensor_tasks = Arc::new(
+        spawn_sensor_tasks(sensors, buses, grpc_service_for_sensors, &sensor_config).await,
+    );
+    grpc_service.set_sensor_tasks(sensor_tasks.clone());
     info!("[main] sensor tasks launched");
 
-    // Start gRPC server
+    #[cfg(feature = "mqtt")]
+    navigate_sensorhub::mqtt::spawn_publisher(sensor_config.mqtt, &grpc_service);
+    #[cfg(not(feature = "mqtt"))]
+    if sensor_config.mqtt.enabled {
+        error!(
+            "[mqtt] Not publishing to {}: built without the mqtt feature",
+            sensor_config.mqtt.broker
+        );
+    }
+    rosbridge::spawn_client(sensor_config.rosbridge, &grpc_service);
+    csv_log::spawn_logger(sensor_config.csv_log, &grpc_service);
+    system_monitor::spawn_monitor(&sensor_config.system, grpc_service.clone());
+    watchdog::spawn_watchdog(&sensor_config.watchdog, grpc_service.clone());
+    calibration::spawn_quality_check(&sensor_config.calibration, grpc_service.clone());
+    ahrs::spawn_fusion(&sensor_config.ahrs, grpc_service.clone());
+    altitude::spawn_estimator(&sensor_config.altitude, grpc_service.clone());
+
+    // Collect a diagnostic bundle when a bus stays silent
+    diagnostics::spawn_watchdog(
+        &sensor_config.diagnostics,
+        config_path.into(),
+        log_ring,
+        grpc_service.clone(),
+    );
+
+    // Start gRPC server (TCP unless GRPC_PORT=off, plus a Unix socket if GRPC_UNIX_SOCKET is set)
     let host = std::env::var("GRPC_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
     let port = std::env::var("GRPC_PORT").unwrap_or_else(|_| "50051".to_string());
-    let addr = format!("{}:{}", host, port).parse().unwrap();
+    let tcp_addr: Option<SocketAddr> =
+        (port != "off").then(|| format!("{}:{}", host, port).parse().unwrap());
+    let unix_socket = std::env::var("GRPC_UNIX_SOCKET")
+        .ok()
+        .filter(|path| !path.is_empty());
+    if tcp_addr.is_none() && unix_socket.is_none() {
+        error!("[gRPC] GRPC_PORT=off needs GRPC_UNIX_SOCKET, nothing to serve on");
+        return;
+    }
     let server = create_grpc_server(grpc_service.as_ref().clone());
+    let reflection = create_reflection_server();
+    let health = create_health_server(grpc_service.clone());
+    let rpc_layer = RpcLayer::new(grpc_service.metrics());
+    let routes = || {
+        Server::builder()
+            .layer(rpc_layer.clone())
+            .add_service(server.clone())
+            .add_service(reflection.clone())
+            .add_service(health.clone())
+    };
 
-    info!("[gRPC] Server starting on {}", addr);
+    let tcp = async {
+        match tcp_addr {
+            Some(addr) => {
+                info!("[gRPC] Server starting on {}", addr);
+                routes().serve(addr).await.map_err(|e| e.to_string())
+            }
+            None => std::future::pending().await,
+        }
+    };
+    let unix = async {
+        match unix_socket.as_deref() {
+            Some(path) => serve_unix(path, routes()).await,
+            None => std::future::pending().await,
+        }
+    };
+    // Prometheus metrics on a separate plain-HTTP port, if METRICS_ADDR is set
+    if let Ok(metrics_addr) = std::env::var("METRICS_ADDR") {
+        match metrics_addr.parse::<SocketAddr>() {
+            Ok(addr) => {
+                let service = grpc_service.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = metrics::serve(addr, service).await {
+                        error!("[metrics] Server failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => error!("[metrics] Invalid METRICS_ADDR '{}': {}", metrics_addr, e),
+        }
+    }
+    // JSON feed for browser dashboards, if WS_ADDR is set
+    if let Ok(ws_addr) = std::env::var("WS_ADDR") {
+        match ws_addr.parse::<SocketAddr>() {
+            Ok(addr) => {
+                let service = grpc_service.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = websocket::serve(addr, service).await {
+                        error!("[websocket] Server failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => error!("[websocket] Invalid WS_ADDR '{}': {}", ws_addr, e),
+        }
+    }
+    // Low-rate feed for remote viewers behind NAT, if [webrtc] is enabled
+    #[cfg(feature = "webrtc")]
+    if sensor_config.webrtc.enabled {
+        let service = grpc_service.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                navigate_sensorhub::webrtc_sink::serve(sensor_config.webrtc, service).await
+            {
+                error!("[webrtc] Signaling server failed: {}", e);
+            }
+        });
+    }
+    #[cfg(not(feature = "webrtc"))]
+    if sensor_config.webrtc.enabled {
+        error!("[webrtc] Not serving viewers: built without the webrtc feature");
+    }
     info!("[main] Ready to serve sensor data");
 
-    // Run the gRPC server
-    if let Err(e) = Server::builder().add_service(server).serve(addr).await {
-        error!("[gRPC] Server failed: {}", e);
+    // Run the gRPC server until either listener fails
+    tokio::select! {
+        Err(e) = tcp => error!("[gRPC] Server failed: {}", e),
+        Err(e) = unix => error!("[gRPC] Unix socket server failed: {}", e),
+        else => {}
     }
+    // Let in-flight bus transactions finish before exiting
+    sensor_tasks.shutdown().await;
+}
+
+/// Serve the API on a Unix domain socket for on-board clients (no network port involved)
+async fn serve_unix(path: &str, routes: Router<Stack<RpcLayer, Identity>>) -> Result<(), String> {
+    // A socket file left over from an earlier run would make bind fail; anything else at
+    // the path is left alone
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(|e| format!("Cannot replace {}: {}", path, e))?
+        }
+        Ok(_) => return Err(format!("{} exists and is not a socket", path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Cannot check {}: {}", path, e)),
+    }
+    let listener = UnixListener::bind(path).map_err(|e| format!("Cannot bind {}: {}", path, e))?;
+    info!("[gRPC] Server starting on unix:{}", path);
+    routes
+        .serve_with_incoming(UnixListenerStream::new(listener))
+        .await
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,503 @@
+use crate::config::sensor_config::MavlinkLogConfig;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+/// First bytes of every log segment
+const MAGIC: &[u8] = b"MAVLOG1\n";
+/// Frames queued for the writer thread before new ones are dropped
+const QUEUE_DEPTH: usize = 4096;
+/// Longest raw byte capture; captures are for debugging, not continuous logging
+pub const MAX_CAPTURE: Duration = Duration::from_secs(3600);
+
+/// One index line per block, written next to the segment as `<segment>.idx` (JSON lines)
+#[derive(Debug, Serialize)]
+struct IndexEntry {
+    /// Byte offset of the block's length prefix in the segment
+    offset: u64,
+    /// Compressed length (after the 4-byte prefix)
+    length: u32,
+    t_start_us: u64,
+    t_end_us: u64,
+    messages: u32,
+    /// MAVLink message ids present in the block
+    msg_ids: BTreeSet<u32>,
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Records of one block before compression
+///
+/// Each record is `varint dt_us, varint len, frame`, with `dt_us` relative to the previous
+/// record (the first to the block start) and the frame as received. Blocks are decodable on
+/// their own, so a reader can seek straight to any block listed in the index.
+struct Block {
+    t_start_us: u64,
+    t_last_us: u64,
+    data: Vec<u8>,
+    messages: u32,
+    msg_ids: BTreeSet<u32>,
+}
+
+impl Block {
+    fn new(t_us: u64) -> Self {
+        Self {
+            t_start_us: t_us,
+            t_last_us: t_us,
+            data: Vec::new(),
+            messages: 0,
+            msg_ids: BTreeSet::new(),
+        }
+    }
+
+    fn push(&mut self, t_us: u64, msg_id: u32, frame: &[u8]) {
+        put_varint(&mut self.data, t_us.saturating_sub(self.t_last_us));
+        self.t_last_us = self.t_last_us.max(t_us);
+        put_varint(&mut self.data, frame.len() as u64);
+        self.data.extend_from_slice(frame);
+        self.messages += 1;
+        self.msg_ids.insert(msg_id);
+    }
+}
+
+/// A segment file plus its index
+struct Segment {
+    file: BufWriter<File>,
+    index: BufWriter<File>,
+    offset: u64,
+    started: SystemTime,
+}
+
+impl Segment {
+    fn create(dir: &Path, bus_id: &str) -> std::io::Result<Self> {
+        let started = SystemTime::now();
+        let secs = started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("mavlink-{}-{}.mavlog", bus_id, secs));
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(MAGIC)?;
+        let index = BufWriter::new(File::create(path.with_extension("mavlog.idx"))?);
+        info!("[mavlink_log] Recording to {}", path.display());
+        Ok(Self {
+            file,
+            index,
+            offset: MAGIC.len() as u64,
+            started,
+        })
+    }
+
+    fn write_block(&mut self, block: &Block, level: i32) -> std::io::Result<()> {
+        let compressed = zstd::bulk::compress(&block.data, level)?;
+        self.file
+            .write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.file.write_all(&compressed)?;
+        self.file.flush()?;
+        let entry = IndexEntry {
+            offset: self.offset,
+            length: compressed.len() as u32,
+            t_start_us: block.t_start_us,
+            t_end_us: block.t_last_us,
+            messages: block.messages,
+            msg_ids: block.msg_ids.clone(),
+        };
+        self.offset += 4 + compressed.len() as u64;
+        serde_json::to_writer(&mut self.index, &entry)?;
+        self.index.write_all(b"\n")?;
+        self.index.flush()
+    }
+}
+
+/// Handle the receive loop uses to hand frames to the writer thread
+pub struct RawRecorder {
+    tx: SyncSender<(u64, u32, Vec<u8>)>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl RawRecorder {
+    /// Start a writer thread recording frames of `bus_id` under `config.dir`
+    pub fn start(config: &MavlinkLogConfig, bus_id: &str) -> std::io::Result<Self> {
+        let dir = PathBuf::from(&config.dir);
+        fs::create_dir_all(&dir)?;
+        let segment = Segment::create(&dir, bus_id)?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let writer = Writer {
+            dir,
+            bus_id: bus_id.to_string(),
+            segment_len: Duration::from_secs(config.segment_s),
+            block_len: Duration::from_millis(config.block_ms),
+            level: config.level,
+        };
+        std::thread::Builder::new()
+            .name(format!("mavlink-log-{}", bus_id))
+            .spawn(move || writer.run(segment, rx))?;
+        Ok(Self {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Queue a received frame, bytes as they arrived; never blocks
+    pub fn record(&self, msg_id: u32, frame: &[u8]) {
+        let t_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        match self.tx.try_send((t_us, msg_id, frame.to_vec())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                if self
+                    .dropped
+                    .fetch_add(1, Ordering::Relaxed)
+                    .is_multiple_of(1000)
+                {
+                    warn!("[mavlink_log] Writer is behind, dropping frames");
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+struct Writer {
+    dir: PathBuf,
+    bus_id: String,
+    segment_len: Duration,
+    block_len: Duration,
+    level: i32,
+}
+
+impl Writer {
+    fn run(self, mut segment: Segment, rx: Receiver<(u64, u32, Vec<u8>)>) {
+        let mut block: Option<Block> = None;
+        loop {
+            let received = rx.recv_timeout(self.block_len);
+            if let Ok((t_us, msg_id, frame)) = &received {
+                block
+                    .get_or_insert_with(|| Block::new(*t_us))
+                    .push(*t_us, *msg_id, frame);
+            }
+            let now_us = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_micros() as u64;
+            let block_done = block.as_ref().is_some_and(|b| {
+                now_us.saturating_sub(b.t_start_us) >= self.block_len.as_micros() as u64
+            });
+            let closing = matches!(received, Err(mpsc::RecvTimeoutError::Disconnected));
+            if block_done || closing {
+                if let Some(b) = block.take() {
+                    if let Err(e) = segment.write_block(&b, self.level) {
+                        error!("[mavlink_log] Write failed, recording stopped: {}", e);
+                        return;
+                    }
+                }
+            }
+            if closing {
+                return;
+            }
+            if block.is_none() && segment.started.elapsed().unwrap_or_default() >= self.segment_len
+            {
+                match Segment::create(&self.dir, &self.bus_id) {
+                    Ok(next) => segment = next,
+                    Err(e) => {
+                        error!("[mavlink_log] Cannot start a new segment: {}", e);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Raw byte capture of one link: everything received, before any MAVLink parsing
+///
+/// Written verbatim to `mavlink-<bus>-<unix time>.raw`, so dialect and framing problems
+/// can be reproduced by feeding the file to a parser. The writer thread ends the capture
+/// when its duration is up, even if the link has gone quiet.
+pub struct RawCapture {
+    tx: SyncSender<Vec<u8>>,
+    path: PathBuf,
+    until: Instant,
+    bytes: Arc<AtomicU64>,
+}
+
+impl RawCapture {
+    /// Start capturing `bus_id` to a new file under `dir` for `duration` (capped at
+    /// `MAX_CAPTURE`)
+    pub fn start(dir: &Path, bus_id: &str, duration: Duration) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("mavlink-{}-{}.raw", bus_id, secs));
+        let file = BufWriter::new(File::create(&path)?);
+        let until = Instant::now() + duration.min(MAX_CAPTURE);
+        let bytes = Arc::new(AtomicU64::new(0));
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let written = bytes.clone();
+        let thread_path = path.clone();
+        std::thread::Builder::new()
+            .name(format!("mavlink-capture-{}", bus_id))
+            .spawn(move || capture_run(file, &thread_path, until, rx, &written))?;
+        info!(
+            "[mavlink_log] Capturing raw bytes of bus {} to {} for {}s",
+            bus_id,
+            path.display(),
+            duration.min(MAX_CAPTURE).as_secs()
+        );
+        Ok(Self {
+            tx,
+            path,
+            until,
+            bytes,
+        })
+    }
+
+    /// Queue received bytes; false once the capture has ended
+    fn write(&self, data: &[u8]) -> bool {
+        match self.tx.try_send(data.to_vec()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("[mavlink_log] Capture writer is behind, bytes lost");
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+fn capture_run(
+    mut file: BufWriter<File>,
+    path: &Path,
+    until: Instant,
+    rx: Receiver<Vec<u8>>,
+    bytes: &AtomicU64,
+) {
+    loop {
+        let remaining = until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match rx.recv_timeout(remaining) {
+            Ok(chunk) => {
+                if let Err(e) = file.write_all(&chunk) {
+                    error!("[mavlink_log] Capture write failed, stopped: {}", e);
+                    return;
+                }
+                bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            }
+            Err(_) => break,
+        }
+    }
+    if let Err(e) = file.flush() {
+        error!("[mavlink_log] Capture write failed: {}", e);
+    }
+    info!(
+        "[mavlink_log] Capture {} finished: {} byte(s)",
+        path.display(),
+        bytes.load(Ordering::Relaxed)
+    );
+}
+
+/// A link's current raw capture, shared by its receive loop and `MavlinkCaptures`
+#[derive(Clone, Default)]
+pub struct CaptureSlot(Arc<Mutex<Option<RawCapture>>>);
+
+impl CaptureSlot {
+    /// Hand bytes just read from the link to the running capture, if any
+    pub fn feed(&self, data: &[u8]) {
+        let mut slot = self.0.lock().unwrap();
+        if slot.as_ref().is_some_and(|capture| !capture.write(data)) {
+            *slot = None;
+        }
+    }
+}
+
+/// State of one link's raw capture
+#[derive(Debug, Clone)]
+pub struct CaptureStatus {
+    pub bus_id: String,
+    /// File being written (None while not capturing)
+    pub path: Option<PathBuf>,
+    pub remaining: Duration,
+    pub bytes: u64,
+}
+
+/// Raw captures of every MAVLink link, started and stopped at runtime (CaptureMavlink)
+pub struct MavlinkCaptures {
+    dir: RwLock<PathBuf>,
+    links: RwLock<BTreeMap<String, CaptureSlot>>,
+}
+
+impl Default for MavlinkCaptures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MavlinkCaptures {
+    pub fn new() -> Self {
+        Self {
+            dir: RwLock::new(PathBuf::from(MavlinkLogConfig::default().dir)),
+            links: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Write captures to the `[mavlink_log]` directory
+    pub fn configure(&self, config: &MavlinkLogConfig) {
+        *self.dir.write().unwrap() = PathBuf::from(&config.dir);
+    }
+
+    pub fn add_link(&self, bus_id: &str, slot: CaptureSlot) {
+        self.links.write().unwrap().insert(bus_id.to_string(), slot);
+    }
+
+    /// Slots of `bus_id`, or of every link if it is empty
+    fn selected(&self, bus_id: &str) -> std::io::Result<Vec<(String, CaptureSlot)>> {
+        let links = self.links.read().unwrap();
+        let selected: Vec<_> = links
+            .iter()
+            .filter(|(id, _)| bus_id.is_empty() || id.as_str() == bus_id)
+            .map(|(id, slot)| (id.clone(), slot.clone()))
+            .collect();
+        if selected.is_empty() {
+            let what = if bus_id.is_empty() {
+                "No MAVLink bus configured".to_string()
+            } else {
+                format!("No MAVLink bus '{}'", bus_id)
+            };
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, what));
+        }
+        Ok(selected)
+    }
+
+    /// Capture `bus_id` (empty = all links) for `duration`, replacing running captures
+    pub fn start(&self, bus_id: &str, duration: Duration) -> std::io::Result<()> {
+        let dir = self.dir.read().unwrap().clone();
+        for (id, slot) in self.selected(bus_id)? {
+            let capture = RawCapture::start(&dir, &id, duration)?;
+            *slot.0.lock().unwrap() = Some(capture);
+        }
+        Ok(())
+    }
+
+    /// End the captures of `bus_id` (empty = all links)
+    pub fn stop(&self, bus_id: &str) -> std::io::Result<()> {
+        for (_, slot) in self.selected(bus_id)? {
+            slot.0.lock().unwrap().take();
+        }
+        Ok(())
+    }
+
+    pub fn status(&self, bus_id: &str) -> std::io::Result<Vec<CaptureStatus>> {
+        let now = Instant::now();
+        Ok(self
+            .selected(bus_id)?
+            .into_iter()
+            .map(|(bus_id, slot)| {
+                let slot = slot.0.lock().unwrap();
+                let running = slot.as_ref().filter(|c| c.until > now);
+                CaptureStatus {
+                    bus_id,
+                    path: running.map(|c| c.path.clone()),
+                    remaining: running.map_or(Duration::ZERO, |c| c.until - now),
+                    bytes: slot.as_ref().map_or(0, |c| c.bytes.load(Ordering::Relaxed)),
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_varint(data: &[u8], pos: &mut usize) -> u64 {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = data[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let frames: [(u64, u32, &[u8]); 3] = [
+            (1_000, 30, &[0xFD, 1, 2, 3, 4]),
+            (1_250, 0, &[0xFE, 9, 9]),
+            (1_500, 30, &[0xFD, 1, 2, 7, 4]),
+        ];
+        let mut block = Block::new(1_000);
+        for (t_us, msg_id, frame) in frames {
+            block.push(t_us, msg_id, frame);
+        }
+        assert_eq!(block.msg_ids, BTreeSet::from([0, 30]));
+
+        let compressed = zstd::bulk::compress(&block.data, 3).unwrap();
+        let data = zstd::decode_all(compressed.as_slice()).unwrap();
+        let (mut pos, mut t_us) = (0, block.t_start_us);
+        for (expected_t, _, expected) in frames {
+            t_us += get_varint(&data, &mut pos);
+            let len = get_varint(&data, &mut pos) as usize;
+            let frame = &data[pos..pos + len];
+            pos += len;
+            assert_eq!((t_us, frame), (expected_t, expected));
+        }
+        assert_eq!(pos, data.len());
+    }
+
+    #[test]
+    fn test_raw_capture() {
+        let dir = std::env::temp_dir().join(format!("sensorhub-capture-{}", std::process::id()));
+        let captures = MavlinkCaptures::new();
+        captures.configure(&MavlinkLogConfig {
+            dir: dir.display().to_string(),
+            ..Default::default()
+        });
+        let slot = CaptureSlot::default();
+        captures.add_link("fc", slot.clone());
+        assert!(captures.start("gcs", Duration::from_secs(60)).is_err());
+
+        slot.feed(b"before");
+        captures.start("", Duration::from_secs(60)).unwrap();
+        slot.feed(&[0xFD, 0x09, 0x00]);
+        slot.feed(b"garbage");
+        let path = captures.status("fc").unwrap()[0].path.clone().unwrap();
+        captures.stop("fc").unwrap();
+        assert!(captures.status("fc").unwrap()[0].path.is_none());
+
+        // The writer thread finishes the file once the capture is dropped
+        let expected = b"\xFD\x09\x00garbage".to_vec();
+        let started = Instant::now();
+        while fs::read(&path).unwrap_or_default() != expected {
+            assert!(
+                started.elapsed() < Duration::from_secs(2),
+                "capture not written"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
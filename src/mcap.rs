@@ -0,0 +1,494 @@
+use crate::config::sensor_config::RecordingConfig;
+use crate::grpc_service::sensorhub::{sensor_data::Data, Header, SensorData, FILE_DESCRIPTOR_SET};
+use crate::publish_queue::SensorTopic;
+use prost::Message;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+/// First and last bytes of every MCAP file
+const MAGIC: &[u8] = b"\x89MCAP0\r\n";
+
+/// MCAP record opcodes
+const OP_HEADER: u8 = 0x01;
+const OP_FOOTER: u8 = 0x02;
+const OP_SCHEMA: u8 = 0x03;
+const OP_CHANNEL: u8 = 0x04;
+const OP_MESSAGE: u8 = 0x05;
+const OP_STATISTICS: u8 = 0x0B;
+const OP_DATA_END: u8 = 0x0F;
+
+/// Buffered data is written out at least this often
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Protobuf type, header and encoded body of the payload of a SensorData
+fn payload(data: &SensorData) -> Option<(&'static str, Option<&Header>, Vec<u8>)> {
+    Some(match data.data.as_ref()? {
+        Data::Imu(d) => ("sensorhub.IMUData", d.header.as_ref(), d.encode_to_vec()),
+        Data::Magnetometer(d) => (
+            "sensorhub.MagnetometerData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Barometer(d) => (
+            "sensorhub.BarometerData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Gps(d) => ("sensorhub.GPSData", d.header.as_ref(), d.encode_to_vec()),
+        Data::Range(d) => ("sensorhub.RangeData", d.header.as_ref(), d.encode_to_vec()),
+        Data::GlobalPosition(d) => (
+            "sensorhub.GlobalPositionData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::LocalPosition(d) => (
+            "sensorhub.LocalPositionData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Attitude(d) => (
+            "sensorhub.AttitudeData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Airspeed(d) => (
+            "sensorhub.AirspeedData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::VfrHud(d) => ("sensorhub.VfrHudData", d.header.as_ref(), d.encode_to_vec()),
+        Data::Vibration(d) => (
+            "sensorhub.VibrationData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::RcChannels(d) => (
+            "sensorhub.RcChannelsData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Esc(d) => (
+            "sensorhub.EscTelemetryData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::System(d) => ("sensorhub.SystemData", d.header.as_ref(), d.encode_to_vec()),
+        Data::GimbalAttitude(d) => (
+            "sensorhub.GimbalAttitudeData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Measurements(d) => (
+            "sensorhub.MeasurementsData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+        Data::Altitude(d) => (
+            "sensorhub.AltitudeData",
+            d.header.as_ref(),
+            d.encode_to_vec(),
+        ),
+    })
+}
+
+/// Unchunked MCAP writer with a summary section (schemas, channels, statistics)
+///
+/// Every sensor/kind pair gets its own channel, `/<sensor_id>/<kind>`, carrying the protobuf
+/// message of that kind. All schemas share the descriptor set of sensorhub.proto.
+struct McapWriter<W: Write> {
+    out: W,
+    offset: u64,
+    /// Schema ids are index + 1 (0 means "no schema" in MCAP)
+    schemas: Vec<&'static str>,
+    /// Channel id, topic and schema id, in id order
+    channels: Vec<(String, u16)>,
+    channel_ids: HashMap<String, u16>,
+    channel_counts: BTreeMap<u16, u64>,
+    messages: u64,
+    t_start_ns: u64,
+    t_end_ns: u64,
+}
+
+impl<W: Write> McapWriter<W> {
+    fn new(out: W) -> std::io::Result<Self> {
+        let mut writer = Self {
+            out,
+            offset: 0,
+            schemas: Vec::new(),
+            channels: Vec::new(),
+            channel_ids: HashMap::new(),
+            channel_counts: BTreeMap::new(),
+            messages: 0,
+            t_start_ns: 0,
+            t_end_ns: 0,
+        };
+        writer.out.write_all(MAGIC)?;
+        writer.offset = MAGIC.len() as u64;
+        let mut header = Vec::new();
+        put_str(&mut header, ""); // profile
+        put_str(
+            &mut header,
+            concat!("navigate-sensorhub ", env!("CARGO_PKG_VERSION")),
+        );
+        writer.record(OP_HEADER, &header)?;
+        Ok(writer)
+    }
+
+    fn record(&mut self, opcode: u8, content: &[u8]) -> std::io::Result<()> {
+        self.out.write_all(&[opcode])?;
+        self.out.write_all(&(content.len() as u64).to_le_bytes())?;
+        self.out.write_all(content)?;
+        self.offset += 9 + content.len() as u64;
+        Ok(())
+    }
+
+    fn schema(id: u16, name: &str) -> Vec<u8> {
+        let mut content = Vec::with_capacity(FILE_DESCRIPTOR_SET.len() + 64);
+        content.extend_from_slice(&id.to_le_bytes());
+        put_str(&mut content, name);
+        put_str(&mut content, "protobuf");
+        content.extend_from_slice(&(FILE_DESCRIPTOR_SET.len() as u32).to_le_bytes());
+        content.extend_from_slice(FILE_DESCRIPTOR_SET);
+        content
+    }
+
+    fn channel(id: u16, topic: &str, schema_id: u16) -> Vec<u8> {
+        let mut content = Vec::new();
+        content.extend_from_slice(&id.to_le_bytes());
+        content.extend_from_slice(&schema_id.to_le_bytes());
+        put_str(&mut content, topic);
+        put_str(&mut content, "protobuf");
+        content.extend_from_slice(&0u32.to_le_bytes()); // no metadata
+        content
+    }
+
+    /// Channel of `topic`, writing its schema and channel records the first time it is seen
+    fn channel_id(&mut self, topic: &str, schema: &'static str) -> std::io::Result<u16> {
+        if let Some(&id) = self.channel_ids.get(topic) {
+            return Ok(id);
+        }
+        let schema_id = match self.schemas.iter().position(|s| *s == schema) {
+            Some(index) => index as u16 + 1,
+            None => {
+                self.schemas.push(schema);
+                let id = self.schemas.len() as u16;
+                self.record(OP_SCHEMA, &Self::schema(id, schema))?;
+                id
+            }
+        };
+        let id = self.channels.len() as u16;
+        self.record(OP_CHANNEL, &Self::channel(id, topic, schema_id))?;
+        self.channels.push((topic.to_string(), schema_id));
+        self.channel_ids.insert(topic.to_string(), id);
+        Ok(id)
+    }
+
+    fn write(&mut self, data: &SensorData, log_time_ns: u64) -> std::io::Result<()> {
+        let Some((schema, header, body)) = payload(data) else {
+            return Ok(());
+        };
+        let topic = format!("/{}/{}", data.sensor_id(), data.kind());
+        let channel_id = self.channel_id(&topic, schema)?;
+        let count = self.channel_counts.entry(channel_id).or_default();
+        let sequence = *count as u32;
+        *count += 1;
+
+        let mut content = Vec::with_capacity(body.len() + 22);
+        content.extend_from_slice(&channel_id.to_le_bytes());
+        content.extend_from_slice(&sequence.to_le_bytes());
+        content.extend_from_slice(&log_time_ns.to_le_bytes());
+        // Publish time is the sample timestamp from the header
+        let publish_time_ns = header.map_or(log_time_ns, |h| h.t_utc_ns);
+        content.extend_from_slice(&publish_time_ns.to_le_bytes());
+        content.extend_from_slice(&body);
+        self.record(OP_MESSAGE, &content)?;
+
+        if self.messages == 0 {
+            self.t_start_ns = log_time_ns;
+        }
+        self.t_end_ns = self.t_end_ns.max(log_time_ns);
+        self.messages += 1;
+        Ok(())
+    }
+
+    /// Write the data end, summary and footer, completing the file
+    fn finish(mut self) -> std::io::Result<W> {
+        self.record(OP_DATA_END, &0u32.to_le_bytes())?; // CRC not computed
+        let summary_start = self.offset;
+        for (index, name) in self.schemas.clone().iter().enumerate() {
+            self.record(OP_SCHEMA, &Self::schema(index as u16 + 1, name))?;
+        }
+        for (id, (topic, schema_id)) in self.channels.clone().iter().enumerate() {
+            self.record(OP_CHANNEL, &Self::channel(id as u16, topic, *schema_id))?;
+        }
+
+        let mut stats = Vec::new();
+        stats.extend_from_slice(&self.messages.to_le_bytes());
+        stats.extend_from_slice(&(self.schemas.len() as u16).to_le_bytes());
+        stats.extend_from_slice(&(self.channels.len() as u32).to_le_bytes());
+        stats.extend_from_slice(&[0u8; 12]); // attachment, metadata and chunk counts
+        stats.extend_from_slice(&self.t_start_ns.to_le_bytes());
+        stats.extend_from_slice(&self.t_end_ns.to_le_bytes());
+        stats.extend_from_slice(&(self.channel_counts.len() as u32 * 10).to_le_bytes());
+        for (id, count) in &self.channel_counts {
+            stats.extend_from_slice(&id.to_le_bytes());
+            stats.extend_from_slice(&count.to_le_bytes());
+        }
+        self.record(OP_STATISTICS, &stats)?;
+
+        let mut footer = Vec::with_capacity(20);
+        footer.extend_from_slice(&summary_start.to_le_bytes());
+        footer.extend_from_slice(&0u64.to_le_bytes()); // no summary offset section
+        footer.extend_from_slice(&0u32.to_le_bytes()); // CRC not computed
+        self.record(OP_FOOTER, &footer)?;
+        self.out.write_all(MAGIC)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// The file being recorded
+struct Recording {
+    writer: McapWriter<BufWriter<File>>,
+    path: PathBuf,
+    opened: Instant,
+    flushed: Instant,
+}
+
+impl Recording {
+    fn create(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("sensorhub-{}.mcap", secs));
+        let writer = McapWriter::new(BufWriter::new(File::create(&path)?))?;
+        info!("[mcap] Recording to {}", path.display());
+        Ok(Self {
+            writer,
+            path,
+            opened: Instant::now(),
+            flushed: Instant::now(),
+        })
+    }
+
+    fn write(&mut self, data: &SensorData) -> std::io::Result<()> {
+        self.writer.write(data, now_ns())?;
+        if self.flushed.elapsed() >= FLUSH_INTERVAL {
+            self.flushed = Instant::now();
+            self.writer.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) {
+        let (path, messages) = (self.path, self.writer.messages);
+        match self.writer.finish() {
+            Ok(_) => info!("[mcap] Closed {} ({} messages)", path.display(), messages),
+            Err(e) => error!("[mcap] Cannot finish {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Current recording, as reported by SetRecording
+pub struct RecordingStatus {
+    pub path: PathBuf,
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+/// Records everything published on the unified stream to MCAP files
+pub struct McapRecorder {
+    config: RwLock<RecordingConfig>,
+    current: Mutex<Option<Recording>>,
+}
+
+impl McapRecorder {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(RecordingConfig::default()),
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn configure(&self, config: &RecordingConfig) {
+        *self.config.write().unwrap() = config.clone();
+    }
+
+    /// Start recording to a new file (no-op if already recording)
+    pub fn start(&self) -> std::io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        if current.is_none() {
+            let dir = PathBuf::from(&self.config.read().unwrap().dir);
+            *current = Some(Recording::create(&dir)?);
+        }
+        Ok(())
+    }
+
+    /// Stop recording, completing the current file
+    pub fn stop(&self) {
+        if let Some(recording) = self.current.lock().unwrap().take() {
+            recording.finish();
+        }
+    }
+
+    pub fn status(&self) -> Option<RecordingStatus> {
+        self.current
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|r| RecordingStatus {
+                path: r.path.clone(),
+                messages: r.writer.messages,
+                bytes: r.writer.offset,
+            })
+    }
+
+    fn record(&self, data: &SensorData) {
+        let mut current = self.current.lock().unwrap();
+        let Some(recording) = current.as_mut() else {
+            return;
+        };
+        if let Err(e) = recording.write(data) {
+            error!(
+                "[mcap] Write to {} failed, recording stopped: {}",
+                recording.path.display(),
+                e
+            );
+            *current = None;
+            return;
+        }
+
+        let config = self.config.read().unwrap();
+        let rotate = recording.opened.elapsed() >= Duration::from_secs(config.segment_s)
+            || recording.writer.offset >= config.max_file_mb * 1024 * 1024;
+        if rotate {
+            let dir = PathBuf::from(&config.dir);
+            if let Some(done) = current.take() {
+                done.finish();
+            }
+            match Recording::create(&dir) {
+                Ok(next) => *current = Some(next),
+                Err(e) => error!("[mcap] Cannot start a new file, recording stopped: {}", e),
+            }
+        }
+    }
+}
+
+impl Default for McapRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feed the unified stream to `recorder` from a dedicated thread, so file I/O never blocks
+/// the runtime; messages are only written while a recording is open
+pub fn spawn_writer(recorder: Arc<McapRecorder>, mut rx: broadcast::Receiver<SensorData>) {
+    let spawned = std::thread::Builder::new()
+        .name("mcap-writer".to_string())
+        .spawn(move || loop {
+            match rx.blocking_recv() {
+                Ok(data) => recorder.record(&data),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("[mcap] Writer fell behind, {} message(s) not recorded", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    recorder.stop();
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        error!("[mcap] Cannot start the writer thread: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_service::sensorhub::ImuData;
+
+    #[test]
+    fn test_mcap_layout() {
+        let imu = |sensor_id: &str| SensorData {
+            data: Some(Data::Imu(ImuData {
+                header: Some(Header {
+                    sensor_id: sensor_id.to_string(),
+                    t_utc_ns: 42,
+                    ..Default::default()
+                }),
+                az: -9.81,
+                ..Default::default()
+            })),
+            source: None,
+        };
+        let mut writer = McapWriter::new(Vec::new()).unwrap();
+        for (t, sensor_id) in [(100, "imu0"), (200, "imu1"), (300, "imu0")] {
+            writer.write(&imu(sensor_id), t).unwrap();
+        }
+        let out = writer.finish().unwrap();
+        assert!(out.starts_with(MAGIC) && out.ends_with(MAGIC));
+
+        // Walk the records between the magic bytes
+        let mut records = Vec::new();
+        let mut pos = MAGIC.len();
+        while pos < out.len() - MAGIC.len() {
+            let len = u64::from_le_bytes(out[pos + 1..pos + 9].try_into().unwrap()) as usize;
+            records.push((out[pos], &out[pos + 9..pos + 9 + len]));
+            pos += 9 + len;
+        }
+        assert_eq!(pos, out.len() - MAGIC.len());
+        let opcodes: Vec<u8> = records.iter().map(|(op, _)| *op).collect();
+        assert_eq!(
+            opcodes,
+            [
+                OP_HEADER,
+                OP_SCHEMA,
+                OP_CHANNEL,
+                OP_MESSAGE,
+                OP_CHANNEL,
+                OP_MESSAGE,
+                OP_MESSAGE,
+                OP_DATA_END,
+                OP_SCHEMA,
+                OP_CHANNEL,
+                OP_CHANNEL,
+                OP_STATISTICS,
+                OP_FOOTER
+            ]
+        );
+
+        // Third message: channel 0, its second message, logged at 300, sampled at 42
+        let message = records[6].1;
+        assert_eq!(message[..2], 0u16.to_le_bytes());
+        assert_eq!(message[2..6], 1u32.to_le_bytes());
+        assert_eq!(message[6..14], 300u64.to_le_bytes());
+        assert_eq!(message[14..22], 42u64.to_le_bytes());
+        let decoded = ImuData::decode(&message[22..]).unwrap();
+        assert_eq!(decoded.az, -9.81);
+
+        // Footer points at the first summary record
+        let footer = records[12].1;
+        let summary_start = u64::from_le_bytes(footer[..8].try_into().unwrap()) as usize;
+        assert_eq!(out[summary_start], OP_SCHEMA);
+    }
+}
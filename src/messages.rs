@@ -1,3 +1,5 @@
+use crate::clock::Stamp;
+use crate::kinds;
 use serde::{Deserialize, Serialize};
 
 /// Header metadata common to all sensor messages
@@ -21,38 +23,51 @@ pub struct Header {
     pub ptp_locked: bool,
     /// Clock frequency error in parts per billion
     pub clock_err_ppb: i32,
-    /// Timing uncertainty in nanoseconds
+    /// Timing uncertainty in nanoseconds, u32::MAX when unknown
     pub sigma_t_ns: u32,
     /// Message schema version for evolution
     pub schema_v: u16,
+    /// Calibration applied to the values (empty if uncalibrated)
+    #[serde(default)]
+    pub calibration_id: String,
 }
 
 impl Header {
     /// Create a new header with current timestamps
     pub fn new(device_id: String, sensor_id: String, frame_id: String, seq: u64) -> Self {
-        use std::time::{SystemTime, UNIX_EPOCH};
-
-        let now_utc = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos() as u64;
-
-        // Get monotonic time using tokio's Instant
-        let mono_start = std::time::Instant::now();
-        let t_mono_ns = mono_start.elapsed().as_nanos() as u64;
+        Self::at(device_id, sensor_id, frame_id, seq, Stamp::now())
+    }
 
+    /// Create a header for a sample taken at `stamp`
+    pub fn at(
+        device_id: String,
+        sensor_id: String,
+        frame_id: String,
+        seq: u64,
+        stamp: Stamp,
+    ) -> Self {
+        let clock = crate::pps::quality();
         Self {
             device_id,
             sensor_id,
             frame_id,
             seq,
-            t_utc_ns: now_utc,
-            t_mono_ns,
-            pps_locked: false, // TODO: Implement PPS detection
+            t_utc_ns: stamp.utc_ns,
+            t_mono_ns: stamp.mono_ns,
+            pps_locked: clock.pps_locked,
             ptp_locked: false, // TODO: Implement PTP detection
-            clock_err_ppb: 0,  // TODO: Implement clock error measurement
-            sigma_t_ns: 1000,  // Default 1μs uncertainty
+            clock_err_ppb: clock.clock_err_ppb,
+            sigma_t_ns: clock.sigma_t_ns,
             schema_v: 1,
+            calibration_id: String::new(),
+        }
+    }
+
+    /// When the sample was taken, for messages derived from it
+    pub fn stamp(&self) -> Stamp {
+        Stamp {
+            utc_ns: self.t_utc_ns,
+            mono_ns: self.t_mono_ns,
         }
     }
 }
@@ -73,6 +88,10 @@ pub struct ImuMessage {
     pub gy: f32,
     /// Angular velocity Z-axis (rad/s)
     pub gz: f32,
+    /// Full-precision ax, ay, az, gx, gy, gz from drivers that compute in f64
+    pub precise: Option<[f64; 6]>,
+    /// Die temperature (°C), if the driver reports it
+    pub temperature: Option<f32>,
 }
 
 /// Magnetometer sensor data
@@ -99,12 +118,310 @@ pub struct BarometerMessage {
     pub altitude: f32,
 }
 
+/// Pitot airspeed (differential pressure after zero-offset correction)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AirspeedMessage {
+    pub h: Header,
+    /// Differential pressure with the zero offset removed (Pa)
+    pub differential_pressure: f32,
+    /// Indicated airspeed (m/s)
+    pub indicated_airspeed: f32,
+    /// Sensor temperature (°C)
+    pub temperature: Option<f32>,
+}
+
+/// Vehicle attitude (from the flight controller estimator)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AttitudeMessage {
+    pub h: Header,
+    /// Attitude quaternion W component
+    pub qw: f32,
+    /// Attitude quaternion X component
+    pub qx: f32,
+    /// Attitude quaternion Y component
+    pub qy: f32,
+    /// Attitude quaternion Z component
+    pub qz: f32,
+    /// Roll angle (rad)
+    pub roll: f32,
+    /// Pitch angle (rad)
+    pub pitch: f32,
+    /// Yaw angle (rad)
+    pub yaw: f32,
+    /// Roll rate (rad/s)
+    pub rollspeed: f32,
+    /// Pitch rate (rad/s)
+    pub pitchspeed: f32,
+    /// Yaw rate (rad/s)
+    pub yawspeed: f32,
+    /// Latest dual-antenna GNSS heading (degrees from true north, None if unavailable)
+    pub gnss_yaw: Option<f32>,
+    /// GNSS heading uncertainty (degrees)
+    pub gnss_yaw_accuracy: Option<f32>,
+}
+
+/// GPS receiver data (raw fix, not the fused position estimate)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GpsMessage {
+    pub h: Header,
+    /// Fix type (0=no GPS, 1=no fix, 2=2D, 3=3D, 4=DGPS, 5=RTK float, 6=RTK fixed)
+    pub fix_type: u32,
+    /// Number of satellites used in the fix (None if unknown)
+    pub satellites_visible: Option<u32>,
+    /// Latitude (degrees, WGS84)
+    pub latitude: f64,
+    /// Longitude (degrees, WGS84)
+    pub longitude: f64,
+    /// Altitude above mean sea level (m)
+    pub altitude: f32,
+    /// Horizontal dilution of precision
+    pub hdop: Option<f32>,
+    /// Vertical dilution of precision
+    pub vdop: Option<f32>,
+    /// Ground speed (m/s)
+    pub ground_speed: Option<f32>,
+    /// Course over ground (degrees, 0..360)
+    pub course: Option<f32>,
+    /// GNSS heading from a dual-antenna setup (degrees from true north, 0..360)
+    pub yaw: Option<f32>,
+    /// Heading uncertainty (degrees)
+    pub yaw_accuracy: Option<f32>,
+}
+
+/// Fused global position estimate from the flight controller
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GlobalPositionMessage {
+    pub h: Header,
+    /// Latitude (degrees, WGS84)
+    pub latitude: f64,
+    /// Longitude (degrees, WGS84)
+    pub longitude: f64,
+    /// Altitude above mean sea level (m)
+    pub altitude: f32,
+    /// Altitude above home (m)
+    pub relative_altitude: f32,
+    /// Velocity north (m/s)
+    pub vn: f32,
+    /// Velocity east (m/s)
+    pub ve: f32,
+    /// Velocity down (m/s)
+    pub vd: f32,
+    /// Heading (degrees, 0..360)
+    pub heading: Option<f32>,
+}
+
+/// Local position and velocity in the NED frame, relative to the EKF origin
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LocalPositionMessage {
+    pub h: Header,
+    /// Position north (m)
+    pub x: f32,
+    /// Position east (m)
+    pub y: f32,
+    /// Position down (m)
+    pub z: f32,
+    /// Velocity north (m/s)
+    pub vx: f32,
+    /// Velocity east (m/s)
+    pub vy: f32,
+    /// Velocity down (m/s)
+    pub vz: f32,
+}
+
+/// HUD flight instrument summary from the flight controller (VFR_HUD)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VfrHudMessage {
+    pub h: Header,
+    /// Indicated airspeed (m/s)
+    pub airspeed: f32,
+    /// Ground speed (m/s)
+    pub groundspeed: f32,
+    /// Heading (degrees, 0..360)
+    pub heading: f32,
+    /// Climb rate (m/s, positive up)
+    pub climb_rate: f32,
+    /// Throttle setting (percent, 0..100)
+    pub throttle: f32,
+    /// Altitude above mean sea level (m)
+    pub altitude: f32,
+}
+
+/// IMU vibration levels and accelerometer clipping counters
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VibrationMessage {
+    pub h: Header,
+    /// Vibration level on X axis (m/s²)
+    pub vibration_x: f32,
+    /// Vibration level on Y axis (m/s²)
+    pub vibration_y: f32,
+    /// Vibration level on Z axis (m/s²)
+    pub vibration_z: f32,
+    /// Accelerometer clipping counts for IMU 0, 1, 2
+    pub clipping: [u32; 3],
+}
+
+/// RC receiver inputs (pilot sticks and switches)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RcChannelsMessage {
+    pub h: Header,
+    /// Channel pulse widths (µs), channel 1 first
+    #[serde(default)]
+    pub channels: Vec<u16>,
+    /// Receiver signal strength (0..254)
+    pub rssi: Option<u8>,
+}
+
+/// Telemetry from one ESC
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscReadingMessage {
+    /// ESC / motor index (0-based)
+    pub index: u8,
+    /// Motor speed (rpm)
+    pub rpm: i32,
+    /// Supply voltage (V)
+    pub voltage: f32,
+    /// Current draw (A)
+    pub current: f32,
+    /// Temperature (°C)
+    pub temperature: Option<f32>,
+    /// Errors reported since boot
+    pub error_count: Option<u32>,
+    /// ESC_FAILURE_FLAGS bitmask (0 = no failure)
+    pub failure_flags: u16,
+}
+
+/// ESC telemetry for a group of motors (one ESC_STATUS report)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscTelemetryMessage {
+    pub h: Header,
+    #[serde(default)]
+    pub escs: Vec<EscReadingMessage>,
+}
+
+/// Temperature of one kernel thermal zone
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ThermalZoneMessage {
+    /// Zone type (e.g. "cpu-thermal", "x86_pkg_temp")
+    pub name: String,
+    /// Temperature (°C)
+    pub temperature: f32,
+}
+
+/// Load and resources of the computer running the hub (built-in "system" sensor)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SystemMessage {
+    pub h: Header,
+    /// Busy fraction of all CPU cores since the previous reading (0..1)
+    pub cpu_load: f32,
+    /// 1-minute load average
+    pub load_avg_1m: f32,
+    pub mem_total_bytes: u64,
+    pub mem_available_bytes: u64,
+    /// Filesystem holding the configured disk path
+    pub disk_total_bytes: u64,
+    pub disk_available_bytes: u64,
+    // Empty lists leave no columns in CSV logs
+    #[serde(default)]
+    pub thermal_zones: Vec<ThermalZoneMessage>,
+    /// Memory held by the in-memory history
+    #[serde(default)]
+    pub history_bytes: u64,
+}
+
+/// Rangefinder distance measurement (laser altimeter, sonar, etc.)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RangefinderMessage {
+    pub h: Header,
+    /// Measured distance (m)
+    pub distance: f32,
+    /// Minimum measurable distance (m)
+    pub min_distance: f32,
+    /// Maximum measurable distance (m)
+    pub max_distance: f32,
+    /// Mounting orientation (MAV_SENSOR_ORIENTATION, 25 = downward)
+    pub orientation: u32,
+    /// Measurement variance (m²)
+    pub covariance: Option<f32>,
+}
+
+/// Orientation of a camera gimbal (GIMBAL_DEVICE_ATTITUDE_STATUS)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GimbalAttitudeMessage {
+    pub h: Header,
+    /// Gimbal device id (1-6 for a non-MAVLink gimbal behind an autopilot; 0 = the sender)
+    pub gimbal_device_id: u8,
+    /// Quaternion W, X, Y, Z, relative to the vehicle or to north (see `yaw_in_earth_frame`)
+    pub qw: f32,
+    pub qx: f32,
+    pub qy: f32,
+    pub qz: f32,
+    /// Euler angles of the quaternion (rad)
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    /// Angular velocity (rad/s), NaN when unknown
+    pub angular_velocity_x: f32,
+    pub angular_velocity_y: f32,
+    pub angular_velocity_z: f32,
+    /// Yaw is relative to north rather than to the vehicle's heading
+    pub yaw_in_earth_frame: bool,
+    /// Yaw relative to the vehicle (rad), when the gimbal reports it
+    pub delta_yaw: Option<f32>,
+    /// GIMBAL_DEVICE_FLAGS bitmask
+    pub device_flags: u16,
+    /// GIMBAL_DEVICE_ERROR_FLAGS bitmask (0 = healthy)
+    pub failure_flags: u32,
+}
+
+/// One named value of a config-driven sensor (Modbus register map)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NamedValueMessage {
+    pub name: String,
+    /// Scaled value
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+/// Values of a generic sensor, named in its configuration rather than by the hub
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeasurementsMessage {
+    pub h: Header,
+    #[serde(default)]
+    pub values: Vec<NamedValueMessage>,
+}
+
+/// Fused barometric altitude and climb rate (built-in altitude estimator)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AltitudeMessage {
+    pub h: Header,
+    /// Altitude above the barometer datum (m, standard atmosphere)
+    pub altitude: f32,
+    /// Vertical speed (m/s, positive up)
+    pub climb_rate: f32,
+    /// Bias-corrected vertical acceleration (m/s², positive up, gravity removed)
+    pub vertical_accel: f32,
+}
+
 /// Unified sensor message enum for different sensor types
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum SensorMessage {
     Imu(ImuMessage),
     Magnetometer(MagnetometerMessage),
     Barometer(BarometerMessage),
+    Gps(GpsMessage),
+    Rangefinder(RangefinderMessage),
+    GlobalPosition(GlobalPositionMessage),
+    LocalPosition(LocalPositionMessage),
+    Attitude(AttitudeMessage),
+    Airspeed(AirspeedMessage),
+    VfrHud(VfrHudMessage),
+    Vibration(VibrationMessage),
+    RcChannels(RcChannelsMessage),
+    EscTelemetry(EscTelemetryMessage),
+    System(SystemMessage),
+    Altitude(AltitudeMessage),
+    GimbalAttitude(GimbalAttitudeMessage),
+    Measurements(MeasurementsMessage),
 }
 
 impl SensorMessage {
@@ -114,9 +431,119 @@ impl SensorMessage {
             SensorMessage::Imu(msg) => &msg.h,
             SensorMessage::Magnetometer(msg) => &msg.h,
             SensorMessage::Barometer(msg) => &msg.h,
+            SensorMessage::Gps(msg) => &msg.h,
+            SensorMessage::Rangefinder(msg) => &msg.h,
+            SensorMessage::GlobalPosition(msg) => &msg.h,
+            SensorMessage::LocalPosition(msg) => &msg.h,
+            SensorMessage::Attitude(msg) => &msg.h,
+            SensorMessage::Airspeed(msg) => &msg.h,
+            SensorMessage::VfrHud(msg) => &msg.h,
+            SensorMessage::Vibration(msg) => &msg.h,
+            SensorMessage::RcChannels(msg) => &msg.h,
+            SensorMessage::EscTelemetry(msg) => &msg.h,
+            SensorMessage::System(msg) => &msg.h,
+            SensorMessage::Altitude(msg) => &msg.h,
+            SensorMessage::GimbalAttitude(msg) => &msg.h,
+            SensorMessage::Measurements(msg) => &msg.h,
         }
     }
 
+    pub fn header_mut(&mut self) -> &mut Header {
+        match self {
+            SensorMessage::Imu(msg) => &mut msg.h,
+            SensorMessage::Magnetometer(msg) => &mut msg.h,
+            SensorMessage::Barometer(msg) => &mut msg.h,
+            SensorMessage::Gps(msg) => &mut msg.h,
+            SensorMessage::Rangefinder(msg) => &mut msg.h,
+            SensorMessage::GlobalPosition(msg) => &mut msg.h,
+            SensorMessage::LocalPosition(msg) => &mut msg.h,
+            SensorMessage::Attitude(msg) => &mut msg.h,
+            SensorMessage::Airspeed(msg) => &mut msg.h,
+            SensorMessage::VfrHud(msg) => &mut msg.h,
+            SensorMessage::Vibration(msg) => &mut msg.h,
+            SensorMessage::RcChannels(msg) => &mut msg.h,
+            SensorMessage::EscTelemetry(msg) => &mut msg.h,
+            SensorMessage::System(msg) => &mut msg.h,
+            SensorMessage::Altitude(msg) => &mut msg.h,
+            SensorMessage::GimbalAttitude(msg) => &mut msg.h,
+            SensorMessage::Measurements(msg) => &mut msg.h,
+        }
+    }
+
+    /// Data type name, matching the `SensorData` oneof field it is published as
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SensorMessage::Imu(_) => kinds::IMU,
+            SensorMessage::Magnetometer(_) => kinds::MAGNETOMETER,
+            SensorMessage::Barometer(_) => kinds::BAROMETER,
+            SensorMessage::Gps(_) => kinds::GPS,
+            SensorMessage::Rangefinder(_) => kinds::RANGE,
+            SensorMessage::GlobalPosition(_) => kinds::GLOBAL_POSITION,
+            SensorMessage::LocalPosition(_) => kinds::LOCAL_POSITION,
+            SensorMessage::Attitude(_) => kinds::ATTITUDE,
+            SensorMessage::Airspeed(_) => kinds::AIRSPEED,
+            SensorMessage::VfrHud(_) => kinds::VFR_HUD,
+            SensorMessage::Vibration(_) => kinds::VIBRATION,
+            SensorMessage::RcChannels(_) => kinds::RC_CHANNELS,
+            SensorMessage::EscTelemetry(_) => kinds::ESC,
+            SensorMessage::System(_) => kinds::SYSTEM,
+            SensorMessage::Altitude(_) => kinds::ALTITUDE,
+            SensorMessage::GimbalAttitude(_) => kinds::GIMBAL_ATTITUDE,
+            SensorMessage::Measurements(_) => kinds::MEASUREMENTS,
+        }
+    }
+
+    /// Names of the measurement fields `field_mut` knows for this message's type
+    pub fn fields(&self) -> &'static [&'static str] {
+        match self {
+            SensorMessage::Imu(_) => &["ax", "ay", "az", "gx", "gy", "gz"],
+            SensorMessage::Magnetometer(_) => &["mx", "my", "mz"],
+            SensorMessage::Barometer(_) => &["pressure", "temperature", "altitude"],
+            SensorMessage::Gps(_) => &["altitude"],
+            SensorMessage::GlobalPosition(_) => &["altitude", "relative_altitude"],
+            SensorMessage::LocalPosition(_) => &["x", "y", "z"],
+            SensorMessage::Attitude(_) => &["roll", "pitch", "yaw"],
+            SensorMessage::Airspeed(_) => &["differential_pressure", "indicated_airspeed"],
+            SensorMessage::Rangefinder(_) => &["distance"],
+            SensorMessage::Altitude(_) => &["altitude", "climb_rate"],
+            _ => &[],
+        }
+    }
+
+    /// A named single-precision measurement field (e.g. "gz", "pressure"), if the message
+    /// has one
+    pub fn field_mut(&mut self, field: &str) -> Option<&mut f32> {
+        Some(match (self, field) {
+            (SensorMessage::Imu(m), "ax") => &mut m.ax,
+            (SensorMessage::Imu(m), "ay") => &mut m.ay,
+            (SensorMessage::Imu(m), "az") => &mut m.az,
+            (SensorMessage::Imu(m), "gx") => &mut m.gx,
+            (SensorMessage::Imu(m), "gy") => &mut m.gy,
+            (SensorMessage::Imu(m), "gz") => &mut m.gz,
+            (SensorMessage::Magnetometer(m), "mx") => &mut m.mx,
+            (SensorMessage::Magnetometer(m), "my") => &mut m.my,
+            (SensorMessage::Magnetometer(m), "mz") => &mut m.mz,
+            (SensorMessage::Barometer(m), "pressure") => &mut m.pressure,
+            (SensorMessage::Barometer(m), "temperature") => &mut m.temperature,
+            (SensorMessage::Barometer(m), "altitude") => &mut m.altitude,
+            (SensorMessage::Gps(m), "altitude") => &mut m.altitude,
+            (SensorMessage::GlobalPosition(m), "altitude") => &mut m.altitude,
+            (SensorMessage::GlobalPosition(m), "relative_altitude") => &mut m.relative_altitude,
+            (SensorMessage::LocalPosition(m), "x") => &mut m.x,
+            (SensorMessage::LocalPosition(m), "y") => &mut m.y,
+            (SensorMessage::LocalPosition(m), "z") => &mut m.z,
+            (SensorMessage::Attitude(m), "roll") => &mut m.roll,
+            (SensorMessage::Attitude(m), "pitch") => &mut m.pitch,
+            (SensorMessage::Attitude(m), "yaw") => &mut m.yaw,
+            (SensorMessage::Airspeed(m), "differential_pressure") => &mut m.differential_pressure,
+            (SensorMessage::Airspeed(m), "indicated_airspeed") => &mut m.indicated_airspeed,
+            (SensorMessage::Rangefinder(m), "distance") => &mut m.distance,
+            (SensorMessage::Altitude(m), "altitude") => &mut m.altitude,
+            (SensorMessage::Altitude(m), "climb_rate") => &mut m.climb_rate,
+            _ => return None,
+        })
+    }
+
     /// Serialize to JSON for debugging
     #[cfg(test)]
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
@@ -161,6 +588,8 @@ mod tests {
             gx: 0.1,
             gy: 0.2,
             gz: 0.3,
+            precise: None,
+            temperature: None,
         };
 
         let sensor_msg = SensorMessage::Imu(imu_msg.clone());
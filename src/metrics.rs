@@ -0,0 +1,340 @@
+use crate::grpc_service::SensorHubService;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+
+/// A labelled counter family exported on /metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Family {
+    pub name: &'static str,
+    pub help: &'static str,
+    /// Label distinguishing the counters of the family ("" for a single counter)
+    pub label: &'static str,
+}
+
+pub const I2C_ERRORS: Family = Family {
+    name: "sensorhub_i2c_errors_total",
+    help: "Failed I2C transactions",
+    label: "bus",
+};
+pub const I2C_RECOVERIES: Family = Family {
+    name: "sensorhub_i2c_recoveries_total",
+    help: "Recoveries of stuck I2C buses",
+    label: "bus",
+};
+pub const MAVLINK_PARSE_ERRORS: Family = Family {
+    name: "sensorhub_mavlink_parse_errors_total",
+    help: "MAVLink frames that failed to parse",
+    label: "bus",
+};
+pub const MAVLINK_RECONNECTS: Family = Family {
+    name: "sensorhub_mavlink_reconnects_total",
+    help: "MAVLink links re-opened after a disconnect or heartbeat loss",
+    label: "bus",
+};
+pub const SENSOR_READ_ERRORS: Family = Family {
+    name: "sensorhub_sensor_read_errors_total",
+    help: "Failed reads of polled sensors",
+    label: "sensor",
+};
+pub const SENSOR_REINITS: Family = Family {
+    name: "sensorhub_sensor_reinits_total",
+    help: "Re-initializations of polled sensors after persistent read failures",
+    label: "sensor",
+};
+pub const STREAM_LAGGED: Family = Family {
+    name: "sensorhub_stream_lagged_messages_total",
+    help: "Messages lost because a client stream fell behind the broadcast channel",
+    label: "",
+};
+pub const CLIENT_DROPPED: Family = Family {
+    name: "sensorhub_client_dropped_messages_total",
+    help: "Messages dropped by the queue policy of slow clients",
+    label: "sensor",
+};
+pub const CLIENT_SHED: Family = Family {
+    name: "sensorhub_client_shed_messages_total",
+    help: "Bulk-tier messages shed from slow clients' backlogs so critical streams kept flowing",
+    label: "sensor",
+};
+
+pub const CLIENT_DISCONNECTS: Family = Family {
+    name: "sensorhub_client_disconnects_total",
+    help: "Client data streams that ended, by stream",
+    label: "stream",
+};
+pub const RPC_REQUESTS: Family = Family {
+    name: "sensorhub_rpc_requests_total",
+    help: "gRPC calls handled",
+    label: "method",
+};
+pub const RPC_ERRORS: Family = Family {
+    name: "sensorhub_rpc_errors_total",
+    help: "gRPC calls answered with a non-OK status before any response message",
+    label: "method",
+};
+pub const RPC_LATENCY: Family = Family {
+    name: "sensorhub_rpc_latency_microseconds_total",
+    help: "Time from request to response start, summed over calls",
+    label: "method",
+};
+
+/// Event counters shared by the buses, sensors and client streams
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<(Family, String), Arc<AtomicU64>>>,
+}
+
+/// Counters of one family held by a single task, each looked up in the registry once
+pub struct FamilyCounters {
+    metrics: Arc<Metrics>,
+    family: Family,
+    resolved: HashMap<String, Arc<AtomicU64>>,
+}
+
+impl FamilyCounters {
+    pub fn add(&mut self, label: &str, n: u64) {
+        if !self.resolved.contains_key(label) {
+            let counter = self.metrics.counter(self.family, label);
+            self.resolved.insert(label.to_string(), counter);
+        }
+        self.resolved[label].fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter of `family` for `label` (created at zero on first use)
+    pub fn counter(&self, family: Family, label: &str) -> Arc<AtomicU64> {
+        self.counters
+            .lock()
+            .unwrap()
+            .entry((family, label.to_string()))
+            .or_default()
+            .clone()
+    }
+
+    pub fn add(&self, family: Family, label: &str, n: u64) {
+        self.counter(family, label).fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counters of `family` for a task to bump on its own, without the registry lock
+    pub fn family(self: &Arc<Self>, family: Family) -> FamilyCounters {
+        FamilyCounters {
+            metrics: self.clone(),
+            family,
+            resolved: HashMap::new(),
+        }
+    }
+
+    fn render_counters(&self, out: &mut String) {
+        let counters = self.counters.lock().unwrap();
+        let mut current = None;
+        for ((family, label), value) in counters.iter() {
+            if current != Some(family.name) {
+                current = Some(family.name);
+                header(out, family.name, family.help, "counter");
+            }
+            let labels = match family.label {
+                "" => String::new(),
+                name => format!("{{{}=\"{}\"}}", name, escape(label)),
+            };
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                family.name,
+                labels,
+                value.load(Ordering::Relaxed)
+            );
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value for the text exposition format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Current metrics in the Prometheus text exposition format
+pub async fn render(service: &SensorHubService) -> String {
+    let mut out = String::new();
+    let mut statuses = service.sensor_statuses().await;
+    statuses.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+
+    type Field = fn(&crate::grpc_service::sensorhub::SensorStatus) -> String;
+    let per_sensor: [(&str, &str, &str, Field); 3] = [
+        (
+            "sensorhub_sensor_messages_total",
+            "Messages published per sensor",
+            "counter",
+            |s| s.messages_sent.to_string(),
+        ),
+        (
+            "sensorhub_sensor_rate_hz",
+            "Measured publish rate per sensor",
+            "gauge",
+            |s| s.frequency_hz.to_string(),
+        ),
+        (
+            "sensorhub_sensor_active",
+            "Whether the sensor is currently delivering data",
+            "gauge",
+            |s| (s.is_active as u8).to_string(),
+        ),
+    ];
+    for (name, help, kind, field) in per_sensor {
+        header(&mut out, name, help, kind);
+        for status in statuses.iter() {
+            let _ = writeln!(
+                out,
+                "{}{{sensor=\"{}\"}} {}",
+                name,
+                escape(&status.sensor_id),
+                field(status)
+            );
+        }
+    }
+
+    // Read timing of polled sensors; quantile "1" is the maximum since startup
+    type Quantiles = fn(&crate::grpc_service::sensorhub::SamplingTiming) -> [u32; 3];
+    let timing: [(&str, &str, Quantiles); 3] = [
+        (
+            "sensorhub_sensor_jitter_microseconds",
+            "Delay of polled reads past their scheduled time",
+            |t| [t.jitter_p50_us, t.jitter_p95_us, t.jitter_max_us],
+        ),
+        (
+            "sensorhub_sensor_read_latency_microseconds",
+            "Time to take the bus and read a polled sensor",
+            |t| [t.latency_p50_us, t.latency_p95_us, t.latency_max_us],
+        ),
+        (
+            "sensorhub_sensor_overrun_microseconds",
+            "How far polled reads that missed their deadline ran into the next period",
+            |t| [t.overrun_p50_us, t.overrun_p95_us, t.overrun_max_us],
+        ),
+    ];
+    for (name, help, quantiles) in timing {
+        header(&mut out, name, help, "gauge");
+        for status in statuses.iter() {
+            let Some(t) = &status.timing else {
+                continue;
+            };
+            for (quantile, value) in ["0.5", "0.95", "1"].into_iter().zip(quantiles(t)) {
+                let _ = writeln!(
+                    out,
+                    "{}{{sensor=\"{}\",quantile=\"{}\"}} {}",
+                    name,
+                    escape(&status.sensor_id),
+                    quantile,
+                    value
+                );
+            }
+        }
+    }
+    header(
+        &mut out,
+        "sensorhub_sensor_deadline_misses_total",
+        "Polled reads that finished after the next one was due",
+        "counter",
+    );
+    for status in statuses.iter() {
+        if let Some(t) = &status.timing {
+            let _ = writeln!(
+                out,
+                "sensorhub_sensor_deadline_misses_total{{sensor=\"{}\"}} {}",
+                escape(&status.sensor_id),
+                t.misses
+            );
+        }
+    }
+    service.metrics().render_counters(&mut out);
+    out
+}
+
+/// Serve GET /metrics over plain HTTP until the listener fails
+pub async fn serve(addr: SocketAddr, service: Arc<SensorHubService>) -> Result<(), String> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Cannot bind {}: {}", addr, e))?;
+    info!(
+        "[metrics] Serving Prometheus metrics on http://{}/metrics",
+        addr
+    );
+    loop {
+        let (stream, peer) = listener.accept().await.map_err(|e| e.to_string())?;
+        let service = service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &service).await {
+                debug!("[metrics] Request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Answer one request and close the connection
+async fn respond(mut stream: TcpStream, service: &SensorHubService) -> std::io::Result<()> {
+    // Only the request line matters; scrapers send small requests
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(service).await),
+        (Some("GET"), _) => ("404 Not Found", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_grouped_by_family() {
+        let metrics = Metrics::new();
+        metrics.add(I2C_ERRORS, "i2c1", 2);
+        metrics.add(STREAM_LAGGED, "", 5);
+        metrics.add(I2C_ERRORS, "i2c0", 1);
+        metrics
+            .counter(I2C_ERRORS, "i2c1")
+            .fetch_add(1, Ordering::Relaxed);
+
+        let mut out = String::new();
+        metrics.render_counters(&mut out);
+        assert_eq!(
+            out,
+            "# HELP sensorhub_i2c_errors_total Failed I2C transactions\n\
+             # TYPE sensorhub_i2c_errors_total counter\n\
+             sensorhub_i2c_errors_total{bus=\"i2c0\"} 1\n\
+             sensorhub_i2c_errors_total{bus=\"i2c1\"} 3\n\
+             # HELP sensorhub_stream_lagged_messages_total Messages lost because a client stream fell behind the broadcast channel\n\
+             # TYPE sensorhub_stream_lagged_messages_total counter\n\
+             sensorhub_stream_lagged_messages_total 5\n"
+        );
+    }
+}
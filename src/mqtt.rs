@@ -0,0 +1,278 @@
+use crate::config::sensor_config::{MqttConfig, MqttFormat};
+use crate::grpc_service::sensorhub::SensorData;
+use crate::grpc_service::SensorHubService;
+use crate::messages::SensorMessage;
+use crate::publish_queue::SensorTopic;
+use prost::Message;
+use rumqttc::{
+    AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, TlsConfiguration, Transport,
+};
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+use tracing::{debug, error, info, warn};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Publishes waiting for the connection; newer messages are dropped while it is full
+const QUEUE_DEPTH: usize = 64;
+/// Largest message sent, e.g. protobuf-encoded vibration spectra
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+/// Topic for a message: the per-kind template if configured, else the default one
+fn topic(config: &MqttConfig, kind: &str, sensor_id: &str) -> String {
+    config
+        .topics
+        .get(kind)
+        .unwrap_or(&config.topic)
+        .replace("{kind}", kind)
+        .replace("{sensor_id}", sensor_id)
+}
+
+/// Host and port of `broker`, defaulting to the standard port for the transport
+fn broker_address(config: &MqttConfig) -> std::io::Result<(String, u16)> {
+    let broker = config.broker.as_str();
+    let default_port = if config.tls { 8883 } else { 1883 };
+    // IP literals, with a port (`10.0.0.2:1883`, `[::1]:1883`) or without (`::1`, `[::1]`)
+    if let Ok(addr) = broker.parse::<SocketAddr>() {
+        return Ok((addr.ip().to_string(), addr.port()));
+    }
+    let bare = broker.strip_prefix('[').and_then(|b| b.strip_suffix(']'));
+    if let Ok(ip) = bare.unwrap_or(broker).parse::<IpAddr>() {
+        return Ok((ip.to_string(), default_port));
+    }
+    // Host names cannot contain ':', so anything after one is the port
+    match broker.split_once(':') {
+        Some((host, port)) if !host.is_empty() => match port.parse() {
+            Ok(port) => Ok((host.to_string(), port)),
+            Err(_) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid port in broker address '{}'", broker),
+            )),
+        },
+        Some(_) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid broker address '{}'", broker),
+        )),
+        None => Ok((broker.to_string(), default_port)),
+    }
+}
+
+/// QoS for every publish; QoS 2 would hold each sample for a four-way handshake
+fn qos(config: &MqttConfig) -> std::io::Result<QoS> {
+    match config.qos {
+        0 => Ok(QoS::AtMostOnce),
+        1 => Ok(QoS::AtLeastOnce),
+        n => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("unsupported qos {} (0 or 1)", n),
+        )),
+    }
+}
+
+/// A message ready to go out: kind, sensor id and encoded payload
+type Outgoing = (&'static str, String, Vec<u8>);
+
+/// Publish sensor data to an MQTT broker, if `[mqtt]` is enabled
+pub fn spawn_publisher(config: MqttConfig, grpc_service: &SensorHubService) {
+    if !config.enabled {
+        return;
+    }
+    let (options, qos) = match options(&config).and_then(|o| Ok((o, qos(&config)?))) {
+        Ok(options) => options,
+        Err(e) => {
+            error!("[mqtt] Not publishing: {}", e);
+            return;
+        }
+    };
+    info!(
+        "[mqtt] Publishing {:?} to {}{} (topic {}, QoS {})",
+        config.format,
+        config.broker,
+        if config.tls { " over TLS" } else { "" },
+        config.topic,
+        config.qos
+    );
+    let (client, eventloop) = AsyncClient::new(options, QUEUE_DEPTH);
+    tokio::spawn(drive(config.broker.clone(), eventloop));
+    let config = Arc::new(config);
+    match config.format {
+        MqttFormat::Json => {
+            let rx = grpc_service.subscribe_messages();
+            tokio::spawn(forward(
+                config,
+                client,
+                qos,
+                rx,
+                |message: &SensorMessage| {
+                    let payload = serde_json::to_vec(message).ok()?;
+                    Some((message.kind(), message.header().sensor_id.clone(), payload))
+                },
+            ));
+        }
+        MqttFormat::Protobuf => {
+            let rx = grpc_service.subscribe_all();
+            tokio::spawn(forward(config, client, qos, rx, |data: &SensorData| {
+                Some((
+                    data.kind(),
+                    data.sensor_id().to_string(),
+                    data.encode_to_vec(),
+                ))
+            }));
+        }
+    }
+}
+
+fn options(config: &MqttConfig) -> std::io::Result<MqttOptions> {
+    let (host, port) = broker_address(config)?;
+    let mut options = MqttOptions::new(&config.client_id, host, port);
+    options
+        .set_keep_alive(Duration::from_secs(config.keep_alive_s as u64))
+        .set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
+    if let Some(username) = &config.username {
+        options.set_credentials(username, config.password.clone().unwrap_or_default());
+    }
+    if config.tls {
+        let transport = match &config.ca_file {
+            Some(path) => Transport::tls_with_config(TlsConfiguration::Simple {
+                ca: std::fs::read(path)?,
+                alpn: None,
+                client_auth: None,
+            }),
+            None => Transport::tls_with_default_config(),
+        };
+        options.set_transport(transport);
+    }
+    Ok(options)
+}
+
+/// Run the connection, reconnecting with backoff if the broker goes away
+async fn drive(broker: String, mut eventloop: EventLoop) {
+    let mut backoff = Duration::from_secs(1);
+    let mut connected = false;
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("[mqtt] Connected to {}", broker);
+                connected = true;
+                backoff = Duration::from_secs(1);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                if connected {
+                    warn!("[mqtt] Connection to {} lost: {}", broker, e);
+                } else {
+                    warn!(
+                        "[mqtt] Cannot connect to {} ({}), retrying in {}s",
+                        broker,
+                        e,
+                        backoff.as_secs()
+                    );
+                }
+                connected = false;
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Publish every message; data is dropped while the connection is backed up
+async fn forward<T: Clone>(
+    config: Arc<MqttConfig>,
+    client: AsyncClient,
+    qos: QoS,
+    mut rx: broadcast::Receiver<T>,
+    encode: fn(&T) -> Option<Outgoing>,
+) {
+    let mut dropped: u64 = 0;
+    loop {
+        match rx.recv().await {
+            Ok(message) => {
+                let Some((kind, sensor_id, payload)) = encode(&message) else {
+                    continue;
+                };
+                let topic = topic(&config, kind, &sensor_id);
+                if client
+                    .try_publish(topic, qos, config.retain, payload)
+                    .is_err()
+                {
+                    dropped += 1;
+                    if dropped.is_power_of_two() {
+                        debug!(
+                            "[mqtt] Broker not keeping up, {} message(s) dropped",
+                            dropped
+                        );
+                    }
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                debug!("[mqtt] Fell behind, {} message(s) skipped", n);
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                let _ = client.try_disconnect();
+                error!("[mqtt] Sensor data channel closed");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics_and_broker_address() {
+        let config: MqttConfig = toml::from_str(
+            r#"
+            enabled = true
+            broker = "broker.local"
+            tls = true
+            [topics]
+            imu = "vehicle/{sensor_id}/imu"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(topic(&config, "imu", "fc_imu0"), "vehicle/fc_imu0/imu");
+        assert_eq!(topic(&config, "gps", "fc_gps0"), "sensorhub/gps/fc_gps0");
+
+        let address = |broker: &str, tls| {
+            let config = MqttConfig {
+                broker: broker.to_string(),
+                tls,
+                ..Default::default()
+            };
+            broker_address(&config).map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            address("broker.local", true),
+            Ok(("broker.local".into(), 8883))
+        );
+        assert_eq!(
+            address("broker.local:1884", true),
+            Ok(("broker.local".into(), 1884))
+        );
+        assert_eq!(
+            address("127.0.0.1:1883", false),
+            Ok(("127.0.0.1".into(), 1883))
+        );
+        assert_eq!(address("[::1]:1884", false), Ok(("::1".into(), 1884)));
+        assert_eq!(address("[fe80::2]", false), Ok(("fe80::2".into(), 1883)));
+        assert_eq!(address("::1", true), Ok(("::1".into(), 8883)));
+        assert!(address("broker.local:mqtt", false).is_err());
+        assert!(address(":1883", false).is_err());
+    }
+
+    #[test]
+    fn test_qos() {
+        let config = |qos| MqttConfig {
+            qos,
+            ..Default::default()
+        };
+        assert_eq!(qos(&MqttConfig::default()).unwrap(), QoS::AtMostOnce);
+        assert_eq!(qos(&config(1)).unwrap(), QoS::AtLeastOnce);
+        assert!(qos(&config(2)).is_err());
+    }
+}
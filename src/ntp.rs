@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The kernel's estimate only changes when the NTP daemon polls, seconds to minutes apart,
+/// so it is read at most this often rather than for every message
+const REFRESH: Duration = Duration::from_secs(1);
+
+/// `SIGMA_NS` while the clock is not synchronized
+const UNSYNCHRONIZED: u64 = u64::MAX;
+
+/// Latest uncertainty read from the kernel
+static SIGMA_NS: AtomicU64 = AtomicU64::new(UNSYNCHRONIZED);
+/// When it was read, in ms since `START` plus one (0 = never)
+static READ_AT_MS: AtomicU64 = AtomicU64::new(0);
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Uncertainty of the system clock (ns) as estimated by the NTP daemon disciplining it
+/// (chronyd, ntpd, systemd-timesyncd), None while it is not synchronized
+pub fn sigma_t_ns() -> Option<u32> {
+    let now_ms = START.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1;
+    let read_at = READ_AT_MS.load(Ordering::Relaxed);
+    let due = read_at == 0 || now_ms.saturating_sub(read_at) >= REFRESH.as_millis() as u64;
+    // Whoever claims the refresh reads the kernel; everyone else uses the value already there
+    if due
+        && READ_AT_MS
+            .compare_exchange(read_at, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    {
+        let sigma = read_kernel();
+        SIGMA_NS.store(sigma.map_or(UNSYNCHRONIZED, u64::from), Ordering::Relaxed);
+        return sigma;
+    }
+    match SIGMA_NS.load(Ordering::Relaxed) {
+        UNSYNCHRONIZED => None,
+        sigma => Some(sigma as u32),
+    }
+}
+
+/// Query (not adjust) the kernel clock discipline with adjtimex(2)
+#[cfg(target_os = "linux")]
+fn read_kernel() -> Option<u32> {
+    use nix::libc;
+
+    // SAFETY: all-zero is a valid timex, and modes = 0 only reads the kernel's state into it
+    let (state, timex) = unsafe {
+        let mut timex: libc::timex = std::mem::zeroed();
+        (libc::adjtimex(&mut timex), timex)
+    };
+    if state < 0 {
+        return None;
+    }
+    sigma_from_timex(
+        state,
+        timex.status,
+        i64::from(timex.esterror),
+        i64::from(timex.maxerror),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_kernel() -> Option<u32> {
+    None
+}
+
+/// Uncertainty (ns) from adjtimex's clock state, status bits and error estimates (µs)
+///
+/// The estimated error is the daemon's one-sigma figure. Daemons that leave it at zero
+/// only bound the error, so the maximum error stands in for it; the kernel grows that bound
+/// by 500 ppm per second while the daemon is silent, and gives up on synchronization past
+/// 16 s.
+fn sigma_from_timex(state: i32, status: i32, esterror_us: i64, maxerror_us: i64) -> Option<u32> {
+    const TIME_ERROR: i32 = 5;
+    const STA_UNSYNC: i32 = 0x0040;
+
+    if state == TIME_ERROR || status & STA_UNSYNC != 0 {
+        return None;
+    }
+    let error_us = if esterror_us > 0 {
+        esterror_us
+    } else {
+        maxerror_us
+    };
+    if error_us <= 0 {
+        return None;
+    }
+    // u32::MAX itself means unknown
+    Some(error_us.saturating_mul(1000).min(u32::MAX as i64 - 1) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigma_from_timex() {
+        // chronyd synchronized to within 120 µs, the bound is much looser
+        assert_eq!(sigma_from_timex(0, 0x2001, 120, 35_000), Some(120_000));
+        // No estimate, only the bound
+        assert_eq!(sigma_from_timex(0, 0x0001, 0, 4_000), Some(4_000_000));
+        // Unsynchronized: nothing is claimed
+        assert_eq!(sigma_from_timex(5, 0x0040, 16, 16_000_000), None);
+        assert_eq!(sigma_from_timex(0, 0x0040, 16, 500), None);
+        // Absurd estimates stay below the "unknown" value
+        assert_eq!(sigma_from_timex(0, 0, 10_000_000, 0), Some(u32::MAX - 1));
+    }
+}
@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+
+type Matrix = [[f64; 3]; 3];
+type Vector = [f64; 3];
+
+/// Largest disagreement between a detected rotation and the measurements (degrees)
+const MAX_ERROR_DEG: f64 = 25.0;
+/// Smallest angle between the two poses for a usable measurement (degrees)
+const MIN_POSE_CHANGE_DEG: f64 = 45.0;
+
+/// Mounting rotation of a sensor relative to the body frame (FRD)
+///
+/// Applied as yaw, then pitch, then roll (like MAV_SENSOR_ROTATION), taking sensor-frame
+/// vectors into the body frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SensorOrientation {
+    pub roll_deg: f32,
+    pub pitch_deg: f32,
+    pub yaw_deg: f32,
+}
+
+impl SensorOrientation {
+    fn matrix(&self) -> Matrix {
+        let (sr, cr) = (self.roll_deg as f64).to_radians().sin_cos();
+        let (sp, cp) = (self.pitch_deg as f64).to_radians().sin_cos();
+        let (sy, cy) = (self.yaw_deg as f64).to_radians().sin_cos();
+        [
+            [cy * cp, cy * sp * sr - sy * cr, cy * sp * cr + sy * sr],
+            [sy * cp, sy * sp * sr + cy * cr, sy * sp * cr - cy * sr],
+            [-sp, cp * sr, cp * cr],
+        ]
+    }
+
+    fn from_matrix(m: &Matrix) -> Self {
+        let angle = |a: f64| {
+            // Mounting rotations are multiples of 90°, so tidy up -0 and 180/-180
+            let deg = a.to_degrees().round();
+            if deg == -180.0 || deg == 0.0 {
+                deg.abs()
+            } else {
+                deg
+            }
+        };
+        let pitch = (-m[2][0]).clamp(-1.0, 1.0).asin();
+        let (roll, yaw) = if m[2][0].abs() > 0.999 {
+            // Gimbal lock: fold roll into yaw
+            (0.0, (-m[0][1]).atan2(m[1][1]))
+        } else {
+            (m[2][1].atan2(m[2][2]), m[1][0].atan2(m[0][0]))
+        };
+        Self {
+            roll_deg: angle(roll) as f32,
+            pitch_deg: angle(pitch) as f32,
+            yaw_deg: angle(yaw) as f32,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.roll_deg == 0.0 && self.pitch_deg == 0.0 && self.yaw_deg == 0.0
+    }
+}
+
+fn mul(m: &Matrix, v: &Vector) -> Vector {
+    [0, 1, 2].map(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+}
+
+fn normalize(v: Vector) -> Option<Vector> {
+    let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    (norm > 1e-9).then(|| v.map(|x| x / norm))
+}
+
+/// Angle between two unit vectors (degrees)
+fn angle_deg(a: &Vector, b: &Vector) -> f64 {
+    let dot = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    dot.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// The 24 axis-aligned rotations (signed permutation matrices with determinant +1)
+fn axis_rotations() -> Vec<Matrix> {
+    let permutations = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+    let mut rotations = Vec::with_capacity(24);
+    for p in permutations {
+        for signs in 0..8 {
+            let mut m = [[0.0; 3]; 3];
+            for row in 0..3 {
+                m[row][p[row]] = if signs & (1 << row) != 0 { -1.0 } else { 1.0 };
+            }
+            let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+            if det > 0.0 {
+                rotations.push(m);
+            }
+        }
+    }
+    rotations
+}
+
+/// How a world-fixed vector seen in the body frame moves when the vehicle pitches nose-down
+const NOSE_DOWN: Matrix = [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]];
+/// Specific force of a vehicle at rest, in the body frame, when level and nose-down
+const UP_LEVEL: Vector = [0.0, 0.0, -1.0];
+const UP_NOSE_DOWN: Vector = [-1.0, 0.0, 0.0];
+
+/// Which reference a sensor's orientation is inferred from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reference {
+    /// Accelerometer: gravity
+    Gravity,
+    /// Magnetometer: the Earth field, given its inclination (degrees, positive down)
+    MagneticField { inclination_deg: f64 },
+}
+
+/// Infer a mounting rotation from averaged readings taken level and nose-down
+/// Returns the rotation and how far the measurements are from it (degrees)
+pub fn infer(
+    level: Vector,
+    nose_down: Vector,
+    reference: Reference,
+) -> Result<(SensorOrientation, f32), String> {
+    let (level, nose_down) = match (normalize(level), normalize(nose_down)) {
+        (Some(l), Some(n)) => (l, n),
+        _ => return Err("no signal".to_string()),
+    };
+    if angle_deg(&level, &nose_down) < MIN_POSE_CHANGE_DEG {
+        return Err("readings barely changed between the poses, was the vehicle tilted?".into());
+    }
+
+    let error = |r: &Matrix| -> f64 {
+        let level_body = mul(r, &level);
+        let nose_body = mul(r, &nose_down);
+        match reference {
+            Reference::Gravity => {
+                angle_deg(&level_body, &UP_LEVEL).max(angle_deg(&nose_body, &UP_NOSE_DOWN))
+            }
+            Reference::MagneticField { inclination_deg } => {
+                // The field is fixed in the world, so it must move with the vehicle, and
+                // while level its vertical component is set by the inclination
+                let dip =
+                    (level_body[2].clamp(-1.0, 1.0).asin().to_degrees() - inclination_deg).abs();
+                angle_deg(&nose_body, &mul(&NOSE_DOWN, &level_body)).max(dip)
+            }
+        }
+    };
+
+    let (best, best_error) = axis_rotations()
+        .into_iter()
+        .map(|r| {
+            let e = error(&r);
+            (r, e)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("24 candidate rotations");
+    if best_error > MAX_ERROR_DEG {
+        return Err(format!(
+            "no mounting rotation fits the readings (best is {:.0}° off)",
+            best_error
+        ));
+    }
+    Ok((SensorOrientation::from_matrix(&best), best_error as f32))
+}
+
+/// Sensor type being captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VectorKind {
+    Accel,
+    Mag,
+}
+
+#[derive(Default)]
+struct Capture {
+    /// Sensors to capture (all if empty)
+    sensor_ids: Vec<String>,
+    sums: HashMap<(String, VectorKind), (Vector, u32)>,
+}
+
+/// Mounting rotation of one sensor, precomputed as a matrix
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation(Matrix);
+
+impl Rotation {
+    pub fn rotate(&self, v: Vector) -> Vector {
+        mul(&self.0, &v)
+    }
+
+    pub fn rotate_f32(&self, v: [f32; 3]) -> [f32; 3] {
+        self.rotate(v.map(f64::from)).map(|x| x as f32)
+    }
+}
+
+/// Live mounting rotations plus the averaging used by orientation detection
+#[derive(Default)]
+pub struct Orientations {
+    rotations: RwLock<HashMap<String, Rotation>>,
+    /// Whether `capture` is running, checked before taking its lock
+    capturing: AtomicBool,
+    capture: Mutex<Option<Capture>>,
+    /// Level-pose averages waiting for the nose-down step
+    level: Mutex<HashMap<(String, VectorKind), Vector>>,
+}
+
+impl Orientations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, sensor_id: &str, orientation: SensorOrientation) {
+        let mut rotations = self.rotations.write().unwrap();
+        if orientation.is_identity() {
+            rotations.remove(sensor_id);
+        } else {
+            rotations.insert(sensor_id.to_string(), Rotation(orientation.matrix()));
+        }
+    }
+
+    /// Rotation currently applied to a sensor's vectors, if any
+    pub fn get(&self, sensor_id: &str) -> Option<SensorOrientation> {
+        self.rotations
+            .read()
+            .unwrap()
+            .get(sensor_id)
+            .map(|r| SensorOrientation::from_matrix(&r.0))
+    }
+
+    /// Rotation taking a sensor's vectors into the body frame (None if mounted level)
+    pub fn rotation(&self, sensor_id: &str) -> Option<Rotation> {
+        self.rotations.read().unwrap().get(sensor_id).copied()
+    }
+
+    /// Feed a raw sensor-frame vector to a running capture
+    pub fn observe(&self, sensor_id: &str, kind: VectorKind, v: [f32; 3]) {
+        if !self.capturing.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(capture) = self.capture.lock().unwrap().as_mut() {
+            if capture.sensor_ids.is_empty() || capture.sensor_ids.iter().any(|s| s == sensor_id) {
+                let (sum, count) = capture
+                    .sums
+                    .entry((sensor_id.to_string(), kind))
+                    .or_default();
+                for i in 0..3 {
+                    sum[i] += v[i] as f64;
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    /// Start averaging raw readings of `sensor_ids` (all IMUs and magnetometers if empty)
+    pub fn start_capture(&self, sensor_ids: Vec<String>) {
+        *self.capture.lock().unwrap() = Some(Capture {
+            sensor_ids,
+            sums: HashMap::new(),
+        });
+        self.capturing.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop averaging and return the mean reading of each captured sensor
+    pub fn finish_capture(&self) -> HashMap<(String, VectorKind), Vector> {
+        self.capturing.store(false, Ordering::Relaxed);
+        let capture = self.capture.lock().unwrap().take().unwrap_or_default();
+        capture
+            .sums
+            .into_iter()
+            .map(|(key, (sum, count))| (key, sum.map(|x| x / count as f64)))
+            .collect()
+    }
+
+    pub fn has_level(&self) -> bool {
+        !self.level.lock().unwrap().is_empty()
+    }
+
+    pub fn store_level(&self, means: HashMap<(String, VectorKind), Vector>) {
+        *self.level.lock().unwrap() = means;
+    }
+
+    pub fn take_level(&self) -> HashMap<(String, VectorKind), Vector> {
+        std::mem::take(&mut *self.level.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_upside_down_rotated_imu() {
+        // Board mounted upside down (roll 180) and turned 90° right
+        let mounting = SensorOrientation {
+            roll_deg: 180.0,
+            pitch_deg: 0.0,
+            yaw_deg: 90.0,
+        };
+        let to_sensor = |body: Vector| {
+            let r = mounting.matrix();
+            // Inverse of a rotation is its transpose
+            [0, 1, 2].map(|i| r[0][i] * body[0] + r[1][i] * body[1] + r[2][i] * body[2])
+        };
+        let g = 9.81;
+        let level = to_sensor([0.1, -0.05, -g]);
+        let nose_down = to_sensor([-g, 0.05, 0.2]);
+        let (detected, error) = infer(level, nose_down, Reference::Gravity).unwrap();
+        assert_eq!(detected, mounting);
+        assert!(error < 2.0);
+
+        // Field 60° below the horizon, heading north-east
+        let field_level = [0.35, 0.35, 0.866];
+        let mag = infer(
+            to_sensor(field_level),
+            to_sensor(mul(&NOSE_DOWN, &field_level)),
+            Reference::MagneticField {
+                inclination_deg: 60.0,
+            },
+        );
+        assert_eq!(mag.unwrap().0, mounting);
+
+        assert!(infer(level, level, Reference::Gravity).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::sensor_config::SensorConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Runtime changes kept across restarts, stored as TOML next to the config
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Overrides {
+    /// Log filter last set through SetLogLevel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_filter: Option<String>,
+    /// Per-sensor settings merged over `[[sensor]]` entries, matching `[sensor.<id>]`
+    #[serde(default, rename = "sensor", skip_serializing_if = "BTreeMap::is_empty")]
+    pub sensors: BTreeMap<String, SensorOverride>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+impl Overrides {
+    /// Apply per-sensor frequency and enable overrides to the loaded sensor list
+    pub fn apply_to_sensors(&self, config: &mut SensorConfig) {
+        config.sensors.retain(|s| {
+            let enabled = self
+                .sensors
+                .get(&s.id)
+                .and_then(|o| o.enabled)
+                .unwrap_or(true);
+            if !enabled {
+                info!("[overrides] Sensor {} disabled", s.id);
+            }
+            enabled
+        });
+        for sensor in config.sensors.iter_mut() {
+            if let Some(frequency) = self.sensors.get(&sensor.id).and_then(|o| o.frequency) {
+                info!("[overrides] Sensor {} frequency {}Hz", sensor.id, frequency);
+                sensor.frequency = Some(frequency);
+            }
+        }
+    }
+
+    /// Drop entries for the given sensors (all entries if empty); returns how many were removed
+    pub fn clear(&mut self, sensor_ids: &[String]) -> usize {
+        if sensor_ids.is_empty() {
+            let removed = self.log_filter.is_some() as usize + self.sensors.len();
+            *self = Overrides::default();
+            return removed;
+        }
+        sensor_ids
+            .iter()
+            .map(|id| self.sensors.remove(id).is_some() as usize)
+            .sum()
+    }
+}
+
+/// Overrides file shared by the control RPCs (inactive until `open` is called)
+#[derive(Default)]
+pub struct OverrideStore {
+    inner: Mutex<StoreState>,
+}
+
+#[derive(Default)]
+struct StoreState {
+    path: Option<PathBuf>,
+    overrides: Overrides,
+}
+
+impl OverrideStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start persisting to `path`, returning what it already holds
+    pub fn open(&self, path: PathBuf) -> Overrides {
+        let overrides = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("[overrides] Ignoring unreadable {}: {}", path.display(), e);
+                Overrides::default()
+            }),
+            Err(_) => Overrides::default(),
+        };
+        info!(
+            "[overrides] Persisting runtime changes to {}",
+            path.display()
+        );
+
+        let mut state = self.inner.lock().unwrap();
+        state.path = Some(path);
+        state.overrides = overrides.clone();
+        overrides
+    }
+
+    /// Change the stored overrides and write them out (no-op while persistence is off)
+    pub fn update<R>(&self, change: impl FnOnce(&mut Overrides) -> R) -> Option<R> {
+        let mut state = self.inner.lock().unwrap();
+        let path = state.path.clone()?;
+        let result = change(&mut state.overrides);
+        if let Err(e) = write_overrides(&path, &state.overrides) {
+            warn!("[overrides] Failed to write {}: {}", path.display(), e);
+        }
+        Some(result)
+    }
+}
+
+/// Write via a temporary file so a power cut never leaves a truncated file behind
+fn write_overrides(path: &Path, overrides: &Overrides) -> std::io::Result<()> {
+    if *overrides == Overrides::default() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let content = toml::to_string(overrides).map_err(std::io::Error::other)?;
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_round_trip_and_clear() {
+        let mut overrides: Overrides = toml::from_str(
+            r#"
+            log_filter = "debug"
+            [sensor.imu0]
+            frequency = 200
+            [sensor.baro0]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(overrides.sensors["imu0"].frequency, Some(200));
+
+        let encoded = toml::to_string(&overrides).unwrap();
+        assert_eq!(toml::from_str::<Overrides>(&encoded).unwrap(), overrides);
+
+        assert_eq!(overrides.clear(&["baro0".to_string()]), 1);
+        assert_eq!(overrides.clear(&[]), 2);
+        assert_eq!(overrides, Overrides::default());
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config::sensor_config::PpsConfig;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::interval;
+use tracing::{info, warn};
+
+const NS_PER_S: u64 = 1_000_000_000;
+
+/// Pulses the clock model is fitted over
+const WINDOW: usize = 16;
+/// Pulses needed before the model is trusted
+const MIN_PULSES: usize = 4;
+/// A pulse this far off the model means the system clock was stepped, the model restarts
+const STEP_NS: f64 = 1_000_000.0;
+/// Without a pulse for this long the lock is lost (a missed pulse or two is tolerated)
+const MAX_PULSE_AGE_NS: u64 = 2_500_000_000;
+/// Residual jitter above which the pulses are not trusted to correct timestamps
+const MAX_LOCKED_SIGMA_NS: f64 = 50_000.0;
+/// How often the kernel's latest assert time is read
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `sigma_t_ns` while neither a PPS lock nor the NTP daemon bounds the error of published
+/// timestamps
+pub const SIGMA_UNKNOWN_NS: u32 = u32::MAX;
+
+/// Clock quality published in every Header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockQuality {
+    pub pps_locked: bool,
+    /// Drift of the system clock against the pulses (ppb), 0 when not locked
+    pub clock_err_ppb: i32,
+    /// One standard deviation of a corrected timestamp's error (ns)
+    pub sigma_t_ns: u32,
+}
+
+/// Least-squares line through the recent pulse offsets
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    /// Time of the newest pulse, the origin of the line (system clock, ns)
+    t_ref_ns: u64,
+    /// System clock minus true time at `t_ref_ns`
+    offset_ns: f64,
+    /// Growth of the offset (ns per s, i.e. ppb)
+    drift_ppb: f64,
+    /// Residual standard deviation of the pulses around the line
+    sigma_ns: f64,
+}
+
+impl Fit {
+    fn offset_at(&self, t_ns: u64) -> f64 {
+        self.offset_ns + self.drift_ppb * (t_ns as f64 - self.t_ref_ns as f64) / NS_PER_S as f64
+    }
+}
+
+/// Model of the system clock against a pulse-per-second source
+///
+/// Each pulse marks a whole second of true time, so the system time the kernel stamped on
+/// its edge, less the nearest whole second, is the clock's offset. Only the sub-second part
+/// is observable: the system clock must already be within ±0.5 s (NTP, GPS time, RTC).
+#[derive(Debug)]
+pub struct Discipline {
+    /// System time of each recent pulse and the clock's offset at it
+    pulses: VecDeque<(u64, i64)>,
+    fit: Option<Fit>,
+}
+
+impl Default for Discipline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Discipline {
+    pub const fn new() -> Self {
+        Self {
+            pulses: VecDeque::new(),
+            fit: None,
+        }
+    }
+
+    /// A pulse the kernel stamped at `assert_ns` on the system clock
+    pub fn pulse(&mut self, assert_ns: u64) {
+        let second_ns = (assert_ns + NS_PER_S / 2) / NS_PER_S * NS_PER_S;
+        let offset_ns = assert_ns as i64 - second_ns as i64;
+        if let Some(fit) = self.fit {
+            let stepped = (offset_ns as f64 - fit.offset_at(assert_ns)).abs() > STEP_NS;
+            let stale = assert_ns.saturating_sub(fit.t_ref_ns) > WINDOW as u64 * NS_PER_S;
+            if stepped || stale {
+                self.pulses.clear();
+                self.fit = None;
+            }
+        }
+        if self.pulses.len() == WINDOW {
+            self.pulses.pop_front();
+        }
+        self.pulses.push_back((assert_ns, offset_ns));
+        self.refit();
+    }
+
+    fn refit(&mut self) {
+        let Some(&(t_ref_ns, _)) = self.pulses.back() else {
+            return;
+        };
+        let n = self.pulses.len() as f64;
+        // Seconds before the newest pulse, and offsets
+        let points: Vec<(f64, f64)> = self
+            .pulses
+            .iter()
+            .map(|&(t, offset)| {
+                (
+                    (t as f64 - t_ref_ns as f64) / NS_PER_S as f64,
+                    offset as f64,
+                )
+            })
+            .collect();
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        let drift_ppb = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+        let offset_ns = mean_y - drift_ppb * mean_x;
+        // Two points always fit a line exactly, the spread needs a third
+        let sigma_ns = if points.len() > 2 {
+            let residuals: f64 = points
+                .iter()
+                .map(|p| (p.1 - (offset_ns + drift_ppb * p.0)).powi(2))
+                .sum();
+            (residuals / (n - 2.0)).sqrt()
+        } else {
+            f64::INFINITY
+        };
+        self.fit = Some(Fit {
+            t_ref_ns,
+            offset_ns,
+            drift_ppb,
+            sigma_ns,
+        });
+    }
+
+    /// The fit, if recent and consistent enough to correct timestamps at `now_ns`
+    fn locked(&self, now_ns: u64) -> Option<&Fit> {
+        self.fit.as_ref().filter(|fit| {
+            self.pulses.len() >= MIN_PULSES
+                && now_ns.saturating_sub(fit.t_ref_ns) < MAX_PULSE_AGE_NS
+                && fit.sigma_ns < MAX_LOCKED_SIGMA_NS
+        })
+    }
+
+    pub fn quality(&self, now_ns: u64) -> ClockQuality {
+        match self.locked(now_ns) {
+            Some(fit) => ClockQuality {
+                pps_locked: true,
+                clock_err_ppb: fit.drift_ppb.round() as i32,
+                sigma_t_ns: (fit.sigma_ns.ceil() as u32).max(1),
+            },
+            None => ClockQuality {
+                pps_locked: false,
+                clock_err_ppb: 0,
+                sigma_t_ns: SIGMA_UNKNOWN_NS,
+            },
+        }
+    }
+
+    /// `t_ns` read from the system clock, moved onto the PPS time scale when locked
+    pub fn correct(&self, t_ns: u64, now_ns: u64) -> u64 {
+        match self.locked(now_ns) {
+            Some(fit) => (t_ns as i64 - fit.offset_at(t_ns).round() as i64).max(0) as u64,
+            None => t_ns,
+        }
+    }
+}
+
+/// The hub's clock model, fed by the reader task when `[pps]` names a device
+static DISCIPLINE: RwLock<Discipline> = RwLock::new(Discipline::new());
+
+fn system_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Quality of timestamps taken now: the PPS fit when locked, else the uncertainty the NTP
+/// daemon disciplining the system clock reports
+pub fn quality() -> ClockQuality {
+    let mut quality = DISCIPLINE.read().unwrap().quality(system_ns());
+    if !quality.pps_locked {
+        if let Some(sigma_t_ns) = crate::ntp::sigma_t_ns() {
+            quality.sigma_t_ns = sigma_t_ns;
+        }
+    }
+    quality
+}
+
+/// Correct a timestamp read from the system clock (e.g. a kernel-stamped edge)
+pub fn correct(t_ns: u64) -> u64 {
+    DISCIPLINE.read().unwrap().correct(t_ns, system_ns())
+}
+
+/// UTC now on the PPS time scale when locked, the system clock otherwise
+pub fn now_utc_ns() -> u64 {
+    correct(system_ns())
+}
+
+/// Parse a sysfs `assert` attribute, "sec.nsec#sequence"
+fn parse_assert(text: &str) -> Option<(u64, u32)> {
+    let (time, sequence) = text.trim().split_once('#')?;
+    let (sec, nsec) = time.split_once('.')?;
+    let t_ns = sec.parse::<u64>().ok()? * NS_PER_S + nsec.parse::<u64>().ok()?;
+    Some((t_ns, sequence.parse().ok()?))
+}
+
+/// The sysfs attribute of a /dev/ppsN device
+fn assert_path(device: &str) -> PathBuf {
+    let name = device.rsplit('/').next().unwrap_or(device);
+    PathBuf::from(format!("/sys/class/pps/{}/assert", name))
+}
+
+/// Follow the PPS device in `config`, if any, and discipline published timestamps with it
+pub fn spawn_discipline(config: &PpsConfig) {
+    let Some(device) = config.device.clone() else {
+        return;
+    };
+    let path = assert_path(&device);
+    tokio::spawn(async move {
+        info!("[pps] Following {} ({})", device, path.display());
+        let mut ticker = interval(POLL_INTERVAL);
+        let mut last_sequence = None;
+        let mut locked = false;
+        let mut readable = true;
+        loop {
+            ticker.tick().await;
+            match tokio::fs::read_to_string(&path).await {
+                Ok(text) => {
+                    readable = true;
+                    // Sequence 0 is the attribute before the first pulse
+                    if let Some((assert_ns, sequence)) =
+                        parse_assert(&text).filter(|&(_, sequence)| sequence != 0)
+                    {
+                        if last_sequence != Some(sequence) {
+                            last_sequence = Some(sequence);
+                            DISCIPLINE.write().unwrap().pulse(assert_ns);
+                        }
+                    }
+                }
+                Err(e) if readable => {
+                    warn!("[pps] Cannot read {}: {}", path.display(), e);
+                    readable = false;
+                }
+                Err(_) => {}
+            }
+            let quality = quality();
+            if quality.pps_locked && !locked {
+                info!(
+                    "[pps] Locked to {}: {} ppb, sigma {} ns",
+                    device, quality.clock_err_ppb, quality.sigma_t_ns
+                );
+            } else if !quality.pps_locked && locked {
+                warn!("[pps] Lost lock to {}", device);
+            }
+            locked = quality.pps_locked;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: u64 = 1_700_000_000 * NS_PER_S;
+
+    #[test]
+    fn test_parse_assert() {
+        assert_eq!(
+            parse_assert("1700000000.000012345#42\n"),
+            Some((T0 + 12_345, 42))
+        );
+        assert_eq!(parse_assert(""), None);
+        assert_eq!(
+            assert_path("/dev/pps1"),
+            PathBuf::from("/sys/class/pps/pps1/assert")
+        );
+    }
+
+    #[test]
+    fn test_discipline_lock_and_correct() {
+        let mut discipline = Discipline::new();
+        assert_eq!(discipline.quality(T0).sigma_t_ns, SIGMA_UNKNOWN_NS);
+
+        // System clock 200 µs ahead and gaining 10 ppm, with ±500 ns of edge jitter
+        let clock = |s: u64| T0 + s * NS_PER_S + 200_000 + s * 10_000;
+        for s in 0..8 {
+            let jitter = if s % 2 == 0 { 500 } else { 0 };
+            discipline.pulse(clock(s) + jitter);
+            let quality = discipline.quality(clock(s));
+            assert_eq!(quality.pps_locked, s + 1 >= MIN_PULSES as u64);
+        }
+        let now = clock(7) + NS_PER_S / 2;
+        let quality = discipline.quality(now);
+        assert!((quality.clock_err_ppb - 10_000).abs() < 100);
+        assert!(quality.sigma_t_ns < 1000);
+        // Half a second of the fast clock after the last pulse is 5 µs short of it in true time
+        let corrected = discipline.correct(now, now) as i64;
+        let truth = (T0 + 7 * NS_PER_S + NS_PER_S / 2 - 5_000) as i64;
+        assert!((corrected - truth).abs() < 1000);
+
+        // Pulses stopping loses the lock, timestamps pass through
+        let later = clock(7) + 3 * NS_PER_S;
+        assert!(!discipline.quality(later).pps_locked);
+        assert_eq!(discipline.correct(later, later), later);
+
+        // A stepped clock restarts the model
+        discipline.pulse(clock(8) + 5_000_000);
+        assert!(!discipline.quality(clock(8)).pps_locked);
+    }
+}
@@ -0,0 +1,666 @@
+use crate::config::sensor_config::{GroupMember, OverflowPolicy, QueueEntry, QueueTier};
+use crate::downlink::{ClientGuard, Decimator, DownlinkBudget};
+use crate::grpc_service::sensorhub::Watermark;
+use crate::kinds;
+use crate::metrics::{Metrics, CLIENT_DROPPED, CLIENT_SHED, STREAM_LAGGED};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use tracing::{debug, warn};
+
+/// Pending messages per sensor per client when no policy is configured
+pub const DEFAULT_QUEUE_DEPTH: usize = 1000;
+
+/// Pending bulk-tier messages per client, across sensors, before the oldest is shed
+pub const BULK_BACKLOG: usize = 500;
+
+/// Data kinds delivered ahead of everything else unless a `[[queue]]` entry says otherwise
+const CRITICAL_KINDS: [&str; 2] = [kinds::IMU, kinds::ATTITUDE];
+
+/// How a client's backlog for one sensor behaves when the client falls behind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueuePolicy {
+    /// Keep only the newest pending message (attitude, status-like data)
+    LatestWins,
+    /// Deliver every message while the backlog stays within `depth`, drop new ones beyond it
+    Lossless { depth: usize },
+}
+
+impl Default for QueuePolicy {
+    fn default() -> Self {
+        QueuePolicy::Lossless {
+            depth: DEFAULT_QUEUE_DEPTH,
+        }
+    }
+}
+
+impl QueuePolicy {
+    pub fn from_entry(entry: &QueueEntry) -> Self {
+        match entry.policy {
+            OverflowPolicy::LatestWins => QueuePolicy::LatestWins,
+            OverflowPolicy::Lossless => QueuePolicy::Lossless {
+                depth: entry.depth.unwrap_or(DEFAULT_QUEUE_DEPTH).max(1),
+            },
+        }
+    }
+}
+
+/// Which lane of a client's backlog a sensor's messages wait in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tier {
+    /// Always sent before any bulk message, never shed for them
+    Critical,
+    /// Sent when no critical message is pending; the oldest is shed beyond `BULK_BACKLOG`
+    Bulk,
+}
+
+impl Tier {
+    fn for_kind(kind: &str) -> Self {
+        if CRITICAL_KINDS.contains(&kind) {
+            Tier::Critical
+        } else {
+            Tier::Bulk
+        }
+    }
+}
+
+/// Queue settings of one sensor
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SensorQueue {
+    pub policy: QueuePolicy,
+    /// None = by data kind (IMU and attitude critical, the rest bulk)
+    pub tier: Option<Tier>,
+}
+
+impl SensorQueue {
+    pub fn from_entry(entry: &QueueEntry) -> Self {
+        Self {
+            policy: QueuePolicy::from_entry(entry),
+            tier: entry.tier.map(|tier| match tier {
+                QueueTier::Critical => Tier::Critical,
+                QueueTier::Bulk => Tier::Bulk,
+            }),
+        }
+    }
+}
+
+/// Source of subscription ids, unique for the life of the hub
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Id of a new client stream, as logged and reported in its watermarks and disconnect event
+pub fn next_subscription_id() -> u64 {
+    NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Per-sensor queue settings, shared by all client streams
+pub type QueuePolicies = Arc<RwLock<HashMap<String, SensorQueue>>>;
+
+/// Message that can be attributed to the sensor that produced it
+pub trait SensorTopic {
+    fn sensor_id(&self) -> &str;
+    /// Data kind, named as in the SensorData oneof (e.g. "imu", "gps")
+    fn kind(&self) -> &'static str;
+    /// Stamp the header with the provenance of this copy
+    fn set_watermark(&mut self, _watermark: Watermark) {}
+}
+
+/// What one client asked for in its SensorRequest
+#[derive(Debug, Clone, Default)]
+pub struct StreamFilter {
+    /// Only these sensors (empty = all)
+    pub sensor_ids: Vec<String>,
+    /// Cap on each sensor's delivery rate to this client
+    pub max_frequency_hz: Option<u32>,
+    /// Consumer group composition (everything if empty)
+    pub members: Vec<GroupMember>,
+    /// Data kinds the client's schema release knows (None = all)
+    pub kinds: Option<Vec<String>>,
+    /// Label to watermark every delivered header with (None = no watermark)
+    pub tag: Option<String>,
+}
+
+impl StreamFilter {
+    /// Whether `item` is selected, and if so the rate cap that applies to it
+    fn select<T: SensorTopic>(&self, item: &T) -> Option<Option<u32>> {
+        let sensor_id = item.sensor_id();
+        if let Some(kinds) = &self.kinds {
+            if !kinds.iter().any(|k| k == item.kind()) {
+                return None;
+            }
+        }
+        if !self.sensor_ids.is_empty() && !self.sensor_ids.iter().any(|id| id == sensor_id) {
+            return None;
+        }
+        if self.members.is_empty() {
+            return Some(self.max_frequency_hz);
+        }
+        self.members
+            .iter()
+            .find(|m| {
+                m.sensor.as_deref().is_none_or(|s| s == sensor_id)
+                    && m.kind.as_deref().is_none_or(|k| k == item.kind())
+            })
+            .map(|m| m.max_frequency_hz.or(self.max_frequency_hz))
+    }
+}
+
+/// Per-client rate cap: delivers at most one message per sensor per interval
+#[derive(Default)]
+struct RateLimiter {
+    next_due: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    fn admit(&mut self, sensor_id: &str, max_frequency_hz: Option<u32>, now: Instant) -> bool {
+        let Some(hz) = max_frequency_hz.filter(|&hz| hz > 0) else {
+            return true;
+        };
+        let interval = Duration::from_secs_f64(1.0 / hz as f64);
+        match self.next_due.get_mut(sensor_id) {
+            Some(due) if now < *due => false,
+            Some(due) => {
+                // Keep the cadence of earlier deliveries unless the source fell behind it
+                *due += interval;
+                if *due <= now {
+                    *due = now + interval;
+                }
+                true
+            }
+            None => {
+                self.next_due.insert(sensor_id.to_string(), now + interval);
+                true
+            }
+        }
+    }
+}
+
+/// One client's backlog: critical messages first, each tier in arrival order across sensors
+struct PolicyQueue<T> {
+    critical: VecDeque<(String, T)>,
+    bulk: VecDeque<(String, T)>,
+    pending: HashMap<String, usize>,
+}
+
+/// What happened to a message offered to a client's backlog
+#[derive(Debug, PartialEq)]
+enum Queued {
+    Yes,
+    /// Refused by the sensor's queue policy
+    Dropped,
+    /// Queued, but the oldest bulk message (from this sensor) was shed to make room
+    Shed(String),
+}
+
+impl<T> PolicyQueue<T> {
+    fn new() -> Self {
+        Self {
+            critical: VecDeque::new(),
+            bulk: VecDeque::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.critical.is_empty() && self.bulk.is_empty()
+    }
+
+    /// Queue a message under `policy` in `tier`
+    fn push(&mut self, sensor_id: &str, item: T, policy: QueuePolicy, tier: Tier) -> Queued {
+        let lane = match tier {
+            Tier::Critical => &mut self.critical,
+            Tier::Bulk => &mut self.bulk,
+        };
+        let pending = self.pending.entry(sensor_id.to_string()).or_default();
+        match policy {
+            QueuePolicy::LatestWins if *pending > 0 => {
+                // Replace the older message in place so ordering across sensors is kept
+                if let Some(slot) = lane.iter_mut().find(|(id, _)| id == sensor_id) {
+                    slot.1 = item;
+                }
+                return Queued::Yes;
+            }
+            QueuePolicy::Lossless { depth } if *pending >= depth => return Queued::Dropped,
+            _ => {}
+        }
+        *pending += 1;
+        lane.push_back((sensor_id.to_string(), item));
+        if tier == Tier::Bulk && self.bulk.len() > BULK_BACKLOG {
+            if let Some((shed, _)) = self.bulk.pop_front() {
+                if let Some(pending) = self.pending.get_mut(&shed) {
+                    *pending -= 1;
+                }
+                return Queued::Shed(shed);
+            }
+        }
+        Queued::Yes
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let (sensor_id, item) = self
+            .critical
+            .pop_front()
+            .or_else(|| self.bulk.pop_front())?;
+        if let Some(pending) = self.pending.get_mut(&sensor_id) {
+            *pending -= 1;
+        }
+        Some(item)
+    }
+}
+
+/// What one client stream received before it ended
+#[derive(Debug, Clone, Default)]
+pub struct StreamTotals {
+    pub delivered: u64,
+    /// Dropped by the queue policy because the client was too slow
+    pub dropped: u64,
+    /// Bulk-tier messages shed so critical ones kept flowing
+    pub shed: u64,
+    /// Lost because the forwarder fell behind the broadcast channel
+    pub lagged: u64,
+    pub duration: Duration,
+}
+
+impl StreamTotals {
+    /// Show the totals so far in the client's GetDownlinkBudget entry
+    fn report(&self, client: &ClientGuard) {
+        client.report(self.delivered, self.dropped, self.shed, self.lagged);
+    }
+}
+
+/// Called once a client stream's forwarder has released its subscription
+pub type DisconnectHook = Box<dyn FnOnce(StreamTotals) + Send>;
+
+/// Turn a broadcast subscription into a client stream that applies per-sensor queue policies
+/// and the downlink budget's decimation
+/// Only messages from `sensor_ids` are forwarded, or everything if it is empty
+pub fn policy_stream<T>(
+    mut rx: broadcast::Receiver<T>,
+    filter: StreamFilter,
+    policies: QueuePolicies,
+    budget: Arc<DownlinkBudget>,
+    metrics: Arc<Metrics>,
+    subscription_id: u64,
+    on_disconnect: DisconnectHook,
+) -> ReceiverStream<Result<T, Status>>
+where
+    T: SensorTopic + Clone + Send + 'static,
+{
+    let (tx, out) = mpsc::channel(1);
+    let mut rate_limiter = RateLimiter::default();
+
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let mut client = budget.client_connected(subscription_id);
+        let mut decimator = Decimator::default();
+        let mut queue = PolicyQueue::new();
+        let mut totals = StreamTotals::default();
+        let mut closed = false;
+        let mut dropped_counters = metrics.family(CLIENT_DROPPED);
+        let mut shed_counters = metrics.family(CLIENT_SHED);
+        let lagged_counter = metrics.counter(STREAM_LAGGED, "");
+
+        loop {
+            tokio::select! {
+                result = rx.recv(), if !closed => match result {
+                    Ok(mut item) => {
+                        let Some(max_frequency_hz) = filter.select(&item) else {
+                            continue;
+                        };
+                        if !rate_limiter.admit(item.sensor_id(), max_frequency_hz, Instant::now()) {
+                            continue;
+                        }
+                        let keep_ratio = client.keep_ratio(item.sensor_id());
+                        if !decimator.admit(item.sensor_id(), keep_ratio) {
+                            continue;
+                        }
+                        if let Some(tag) = &filter.tag {
+                            item.set_watermark(Watermark {
+                                tag: tag.clone(),
+                                subscription_id,
+                                max_frequency_hz: max_frequency_hz.unwrap_or(0),
+                                downlink_keep_ratio: keep_ratio,
+                            });
+                        }
+                        let settings = policies
+                            .read()
+                            .unwrap()
+                            .get(item.sensor_id())
+                            .copied()
+                            .unwrap_or_default();
+                        let tier = settings.tier.unwrap_or_else(|| Tier::for_kind(item.kind()));
+                        let sensor_id = item.sensor_id().to_string();
+                        match queue.push(&sensor_id, item, settings.policy, tier) {
+                            Queued::Yes => {}
+                            Queued::Dropped => {
+                                dropped_counters.add(&sensor_id, 1);
+                                totals.dropped += 1;
+                                totals.report(&client);
+                                if totals.dropped.is_power_of_two() {
+                                    warn!(
+                                        "[gRPC] Client #{} too slow, dropped {} message(s) (latest from {})",
+                                        subscription_id, totals.dropped, sensor_id
+                                    );
+                                }
+                            }
+                            Queued::Shed(shed) => {
+                                shed_counters.add(&shed, 1);
+                                totals.shed += 1;
+                                totals.report(&client);
+                                if totals.shed.is_power_of_two() {
+                                    warn!(
+                                        "[gRPC] Client #{} too slow, shed {} bulk message(s) (latest from {})",
+                                        subscription_id, totals.shed, shed
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        lagged_counter.fetch_add(n, Ordering::Relaxed);
+                        totals.lagged += n;
+                        totals.report(&client);
+                        warn!(
+                            "[gRPC] Stream forwarder of client #{} lagged, {} message(s) lost",
+                            subscription_id, n
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => closed = true,
+                },
+                permit = tx.reserve(), if !queue.is_empty() => match permit {
+                    Ok(permit) => {
+                        if let Some(item) = queue.pop() {
+                            permit.send(Ok(item));
+                            totals.delivered += 1;
+                            totals.report(&client);
+                        }
+                    }
+                    Err(_) => break, // Client disconnected
+                },
+                // Noticed even while the filter passes nothing, so idle clients do not linger
+                _ = tx.closed() => break,
+                else => break,
+            }
+        }
+        // Unsubscribe and leave the downlink budget before reporting
+        drop(rx);
+        drop(client);
+        debug!("[gRPC] Stream forwarder finished");
+        totals.duration = started.elapsed();
+        on_disconnect(totals);
+    });
+
+    ReceiverStream::new(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_queue_overflow() {
+        let mut queue = PolicyQueue::new();
+        let lossless = QueuePolicy::Lossless { depth: 2 };
+        let bulk = Tier::Bulk;
+
+        assert_eq!(queue.push("imu", 1, lossless, bulk), Queued::Yes);
+        assert_eq!(
+            queue.push("att", 10, QueuePolicy::LatestWins, bulk),
+            Queued::Yes
+        );
+        assert_eq!(queue.push("imu", 2, lossless, bulk), Queued::Yes);
+        assert_eq!(queue.push("imu", 3, lossless, bulk), Queued::Dropped);
+        assert_eq!(
+            queue.push("att", 11, QueuePolicy::LatestWins, bulk),
+            Queued::Yes
+        );
+
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(11));
+        assert_eq!(queue.push("imu", 4, lossless, bulk), Queued::Yes);
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(4));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_critical_tier_first_and_bulk_shed() {
+        let mut queue = PolicyQueue::new();
+        let lossless = QueuePolicy::default();
+
+        // A backlog of vibration spectra, then IMU samples arrive behind it
+        for i in 0..BULK_BACKLOG {
+            assert_eq!(queue.push("fft", i, lossless, Tier::Bulk), Queued::Yes);
+        }
+        assert_eq!(
+            queue.push("imu", 9000, lossless, Tier::Critical),
+            Queued::Yes
+        );
+        assert_eq!(
+            queue.push("fft", BULK_BACKLOG, lossless, Tier::Bulk),
+            Queued::Shed("fft".to_string())
+        );
+        assert_eq!(
+            queue.push("imu", 9001, lossless, Tier::Critical),
+            Queued::Yes
+        );
+
+        assert_eq!(queue.pop(), Some(9000));
+        assert_eq!(queue.pop(), Some(9001));
+        // The oldest spectrum went, the rest follow in order
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pending["fft"], BULK_BACKLOG - 1);
+        assert_eq!(Tier::for_kind("attitude"), Tier::Critical);
+        assert_eq!(Tier::for_kind("vibration"), Tier::Bulk);
+    }
+
+    #[derive(Debug, Clone)]
+    struct Sample(&'static str);
+
+    impl SensorTopic for Sample {
+        fn sensor_id(&self) -> &str {
+            self.0
+        }
+
+        fn kind(&self) -> &'static str {
+            "imu"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_released_on_disconnect() {
+        let (tx, rx) = broadcast::channel(16);
+        let budget = Arc::new(DownlinkBudget::new());
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        let filter = StreamFilter {
+            sensor_ids: vec!["mag".to_string()],
+            ..StreamFilter::default()
+        };
+        let out = policy_stream(
+            rx,
+            filter,
+            QueuePolicies::default(),
+            budget.clone(),
+            Arc::new(Metrics::new()),
+            next_subscription_id(),
+            Box::new(move |totals| {
+                let _ = done_tx.send(totals);
+            }),
+        );
+        tx.send(Sample("imu")).unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(budget.snapshot().1.len(), 1);
+
+        // Nothing passes the filter, so nothing is ever queued for the client
+        drop(out);
+        let totals = tokio::time::timeout(Duration::from_secs(1), done_rx)
+            .await
+            .expect("forwarder still running after the client left")
+            .unwrap();
+        assert_eq!(totals.delivered, 0);
+        assert_eq!(tx.receiver_count(), 0);
+        assert_eq!(budget.snapshot().1.len(), 0);
+    }
+
+    #[test]
+    fn test_rate_limiter_decimates_per_sensor() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        // 400 Hz for one second
+        let kept = (0..400)
+            .filter(|i| limiter.admit("imu", Some(10), start + Duration::from_micros(i * 2500)))
+            .count();
+        assert_eq!(kept, 10);
+        assert!(limiter.admit("baro", Some(10), start));
+        assert!(limiter.admit("imu", None, start));
+    }
+
+    /// A message from `sensor` of `kind`
+    struct Reading(&'static str, &'static str);
+
+    impl SensorTopic for Reading {
+        fn sensor_id(&self) -> &str {
+            self.0
+        }
+
+        fn kind(&self) -> &'static str {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_group_select_and_decimation() {
+        use crate::config::sensor_config::GroupEntry;
+
+        let group: GroupEntry = toml::from_str(
+            r#"
+            name = "nav"
+            max_frequency_hz = 10
+            [[member]]
+            sensor = "fc_imu0"
+            max_frequency_hz = 50
+            [[member]]
+            kind = "gps"
+            [[member]]
+            sensor = "baro0"
+            kind = "barometer"
+            "#,
+        )
+        .unwrap();
+        let filter = StreamFilter {
+            max_frequency_hz: group.max_frequency_hz,
+            members: group.members,
+            ..StreamFilter::default()
+        };
+        // First matching member wins, falling back to the group's own cap
+        assert_eq!(
+            filter.select(&Reading("fc_imu0", kinds::IMU)),
+            Some(Some(50))
+        );
+        assert_eq!(
+            filter.select(&Reading("fc_gps0", kinds::GPS)),
+            Some(Some(10))
+        );
+        assert_eq!(
+            filter.select(&Reading("fc_gps1", kinds::GPS)),
+            Some(Some(10))
+        );
+        assert_eq!(
+            filter.select(&Reading("baro0", kinds::BAROMETER)),
+            Some(Some(10))
+        );
+        assert_eq!(filter.select(&Reading("baro1", kinds::BAROMETER)), None);
+        assert_eq!(filter.select(&Reading("fc_imu1", kinds::IMU)), None);
+
+        // Each selected sensor is decimated to its own cap
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let mut kept = HashMap::new();
+        for i in 0..400u64 {
+            let now = start + Duration::from_micros(i * 2500);
+            for reading in [
+                Reading("fc_imu0", kinds::IMU),
+                Reading("fc_gps0", kinds::GPS),
+            ] {
+                let cap = filter.select(&reading).unwrap();
+                if limiter.admit(reading.sensor_id(), cap, now) {
+                    *kept.entry(reading.0).or_insert(0) += 1;
+                }
+            }
+        }
+        assert_eq!(kept["fc_imu0"], 50);
+        assert_eq!(kept["fc_gps0"], 10);
+
+        // A client on an older schema release never sees kinds it does not know
+        let filter = StreamFilter {
+            kinds: Some(vec![kinds::IMU.to_string()]),
+            ..filter
+        };
+        assert_eq!(filter.select(&Reading("fc_gps0", kinds::GPS)), None);
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Tagged(Option<Watermark>);
+
+    impl SensorTopic for Tagged {
+        fn sensor_id(&self) -> &str {
+            "imu"
+        }
+
+        fn kind(&self) -> &'static str {
+            "imu"
+        }
+
+        fn set_watermark(&mut self, watermark: Watermark) {
+            self.0 = Some(watermark);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watermark_per_subscription() {
+        use tokio_stream::StreamExt;
+
+        let (tx, _) = broadcast::channel(16);
+        let budget = Arc::new(DownlinkBudget::new());
+        let metrics = Arc::new(Metrics::new());
+        let open = |tag: Option<&str>| {
+            let filter = StreamFilter {
+                max_frequency_hz: Some(50),
+                tag: tag.map(str::to_string),
+                ..StreamFilter::default()
+            };
+            policy_stream(
+                tx.subscribe(),
+                filter,
+                QueuePolicies::default(),
+                budget.clone(),
+                metrics.clone(),
+                next_subscription_id(),
+                Box::new(|_| {}),
+            )
+        };
+        let mut logger = open(Some("logger-a"));
+        let mut mirror = open(Some("logger-a"));
+        let mut plain = open(None);
+        tx.send(Tagged::default()).unwrap();
+
+        async fn next(stream: &mut ReceiverStream<Result<Tagged, Status>>) -> Option<Watermark> {
+            let item = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+            item.unwrap().unwrap().unwrap().0
+        }
+        let first = next(&mut logger)
+            .await
+            .expect("tagged copy has no watermark");
+        let second = next(&mut mirror)
+            .await
+            .expect("tagged copy has no watermark");
+        assert_eq!(first.tag, "logger-a");
+        assert_eq!(first.max_frequency_hz, 50);
+        assert_eq!(first.downlink_keep_ratio, 1.0);
+        assert_ne!(first.subscription_id, second.subscription_id);
+        assert!(next(&mut plain).await.is_none());
+    }
+}
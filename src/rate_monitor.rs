@@ -0,0 +1,174 @@
+use crate::config::sensor_config::RateMonitorConfig;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+
+/// Rate windows ignored after a sensor starts publishing (startup bursts, discovery)
+const WARMUP_WINDOWS: u32 = 3;
+/// Weight of each healthy window in the learned rate
+const LEARN_RATE: f64 = 0.1;
+
+/// Change of a sensor's rate state, reported once per transition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateAnomaly {
+    pub expected_hz: f64,
+    pub measured_hz: f64,
+    /// True when the sensor became degraded, false when it recovered
+    pub degraded: bool,
+}
+
+#[derive(Debug, Default)]
+struct Tracker {
+    /// Configured or learned rate (0 until learned)
+    expected_hz: f64,
+    windows: u32,
+    /// Consecutive one-second windows below the trip threshold / above the clear threshold
+    low: u32,
+    ok: u32,
+    degraded: bool,
+}
+
+impl Tracker {
+    fn observe(
+        &mut self,
+        measured_hz: f64,
+        fixed_hz: Option<f64>,
+        config: &RateMonitorConfig,
+    ) -> Option<RateAnomaly> {
+        self.windows += 1;
+        if let Some(fixed) = fixed_hz {
+            self.expected_hz = fixed;
+        } else if self.windows <= WARMUP_WINDOWS {
+            return None;
+        } else if self.expected_hz == 0.0 {
+            self.expected_hz = measured_hz;
+            return None;
+        }
+        if self.expected_hz <= 0.0 {
+            return None;
+        }
+
+        let ratio = measured_hz / self.expected_hz;
+        if ratio < 1.0 - config.tolerance {
+            self.low += 1;
+            self.ok = 0;
+        } else if ratio >= 1.0 - config.tolerance / 2.0 {
+            self.ok += 1;
+            self.low = 0;
+        } else {
+            // Between the thresholds: hold the current state
+            self.low = 0;
+            self.ok = 0;
+        }
+
+        if !self.degraded {
+            if self.low >= config.trip_s {
+                self.degraded = true;
+                return Some(self.anomaly(measured_hz));
+            }
+            // Follow deliberate rate changes, but never learn from a low window
+            if fixed_hz.is_none() && self.low == 0 {
+                self.expected_hz += (measured_hz - self.expected_hz) * LEARN_RATE;
+            }
+        } else if self.ok >= config.clear_s {
+            self.degraded = false;
+            return Some(self.anomaly(measured_hz));
+        }
+        None
+    }
+
+    fn anomaly(&self, measured_hz: f64) -> RateAnomaly {
+        RateAnomaly {
+            expected_hz: self.expected_hz,
+            measured_hz,
+            degraded: self.degraded,
+        }
+    }
+}
+
+/// Compares each sensor's measured publish rate with its expected rate
+pub struct RateMonitor {
+    config: RwLock<RateMonitorConfig>,
+    trackers: Mutex<HashMap<String, Tracker>>,
+}
+
+impl RateMonitor {
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(RateMonitorConfig::default()),
+            trackers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn configure(&self, config: &RateMonitorConfig) {
+        *self.config.write().unwrap() = config.clone();
+    }
+
+    /// Feed one rate window (messages per second); returns a state change, if any
+    pub fn observe(&self, sensor_id: &str, measured_hz: f64) -> Option<RateAnomaly> {
+        let config = self.config.read().unwrap();
+        if !config.enabled {
+            return None;
+        }
+        let fixed_hz = config.expected.get(sensor_id).map(|&hz| hz as f64);
+        self.trackers
+            .lock()
+            .unwrap()
+            .entry(sensor_id.to_string())
+            .or_default()
+            .observe(measured_hz, fixed_hz, &config)
+    }
+
+    /// Learn a sensor's rate afresh after it was changed on purpose; a degraded sensor
+    /// still reports its recovery
+    pub fn relearn(&self, sensor_id: &str) {
+        if let Some(tracker) = self.trackers.lock().unwrap().get_mut(sensor_id) {
+            *tracker = Tracker {
+                degraded: tracker.degraded,
+                ..Tracker::default()
+            };
+        }
+    }
+}
+
+impl Default for RateMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halved_rate_trips_and_recovers() {
+        let config = RateMonitorConfig::default();
+        let mut tracker = Tracker::default();
+        // Warm-up, then a steady 50 Hz
+        for _ in 0..10 {
+            assert_eq!(tracker.observe(50.0, None, &config), None);
+        }
+        // The flight controller halves the stream: flagged after trip_s windows
+        for _ in 1..config.trip_s {
+            assert_eq!(tracker.observe(25.0, None, &config), None);
+        }
+        let anomaly = tracker.observe(25.0, None, &config).unwrap();
+        assert!(anomaly.degraded);
+        assert!((anomaly.expected_hz - 50.0).abs() < 1.0);
+        // Just under the clear threshold keeps it degraded
+        for _ in 0..10 {
+            assert_eq!(tracker.observe(42.0, None, &config), None);
+        }
+        for _ in 1..config.clear_s {
+            assert_eq!(tracker.observe(49.0, None, &config), None);
+        }
+        assert!(!tracker.observe(49.0, None, &config).unwrap().degraded);
+
+        // A configured rate applies from the first window
+        let mut tracker = Tracker::default();
+        for _ in 1..config.trip_s {
+            assert_eq!(tracker.observe(90.0, Some(200.0), &config), None);
+        }
+        assert!(tracker.observe(90.0, Some(200.0), &config).is_some());
+    }
+}
@@ -1,18 +1,41 @@
-use crate::bus::i2c::I2CBus;
-use crate::bus::mavlink::{DetectedSensor, MavlinkConnection};
-use crate::bus::serial::SerialBus;
+use crate::bus::i2c::{Address, BusRecovery, I2CBus, MAX_10BIT_ADDRESS};
+use crate::bus::mavlink::{DetectedSensor, LinkCounters, MavlinkConnection};
+use crate::bus::serial::{Rs485Config, SerialBus};
+use crate::bus::tcp::TcpEndpoint;
+use crate::bus::udp::UdpEndpoint;
 use crate::bus::BusType;
 use crate::config::load_bus_config;
 use crate::config::sensor_config::SensorConfig;
 use crate::errors::{ConfigError, RegistryError, RegistryResult, SensorError};
+use crate::grpc_service::sensorhub::SensorInfo;
 use crate::grpc_service::SensorHubService;
+use crate::mavlink_log::RawRecorder;
+use crate::metrics::{I2C_ERRORS, I2C_RECOVERIES, MAVLINK_PARSE_ERRORS, MAVLINK_RECONNECTS};
+use crate::replay::spawn_replay;
+use crate::safety::Interlock;
 use crate::sensors::create_sensor_driver;
+#[cfg(feature = "iio")]
+use crate::sensors::iio::IioSensor;
+use crate::sensors::uart::{UartPort, UartSensor};
 use crate::sensors::SensorDriver;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+/// How long a MAVLink sensor may go quiet before it is announced as removed
+const MAVLINK_SENSOR_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often MAVLink buses are re-scanned for new or lost sensors
+const MAVLINK_DISCOVERY_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest startup wait for a MAVLink bus's sensors
+const MAVLINK_DISCOVERY_WAIT: Duration = Duration::from_millis(500);
+/// Startup discovery ends once no new message type has appeared for this long
+const MAVLINK_DISCOVERY_SETTLE: Duration = Duration::from_millis(100);
+/// Role reported for auto-discovered flight controller sensors
+const MAVLINK_ROLE: &str = "flight_controller";
+
 /// Create a MAVLink sensor driver from detected sensor type with proper instance mapping
 async fn create_mavlink_sensor(
     sensor_type: DetectedSensor,
@@ -48,12 +71,37 @@ async fn create_mavlink_sensor(
             DetectedSensor::AttitudeQuaternion => {
                 ("fc_attitude".to_string(), MavlinkSensorType::Attitude)
             }
+            DetectedSensor::Attitude => (
+                "fc_attitude_euler".to_string(),
+                MavlinkSensorType::AttitudeEuler,
+            ),
+            DetectedSensor::GpsRawInt => ("fc_gps0".to_string(), MavlinkSensorType::Gps),
+            DetectedSensor::Gps2Raw => ("fc_gps1".to_string(), MavlinkSensorType::Gps2),
+            DetectedSensor::GlobalPositionInt => {
+                ("fc_position".to_string(), MavlinkSensorType::GlobalPosition)
+            }
+            DetectedSensor::LocalPositionNed => (
+                "fc_local_position".to_string(),
+                MavlinkSensorType::LocalPosition,
+            ),
+            DetectedSensor::VfrHud => ("fc_vfr_hud".to_string(), MavlinkSensorType::VfrHud),
+            DetectedSensor::Vibration => ("fc_vibration".to_string(), MavlinkSensorType::Vibration),
+            DetectedSensor::DistanceSensor { id } => (
+                format!("fc_range{}", id),
+                MavlinkSensorType::Rangefinder { id },
+            ),
+            DetectedSensor::RcChannels => ("fc_rc".to_string(), MavlinkSensorType::RcChannels),
+            DetectedSensor::EscStatus => ("fc_esc".to_string(), MavlinkSensorType::Esc),
+            DetectedSensor::GimbalDeviceAttitude { id } => {
+                (format!("fc_gimbal{}", id), MavlinkSensorType::Gimbal { id })
+            }
         };
 
         info!(
             "[registry] Auto-creating MAVLink sensor: {} (type: {:?})",
             id, mavlink_type
         );
+        let driver = mavlink_type.driver_name();
 
         // Create MavlinkSensor directly with the correct type (bypass factory)
         use crate::sensors::mavlink::MavlinkSensor;
@@ -87,6 +135,9 @@ async fn create_mavlink_sensor(
                 .map_err(RegistryError::RegistrationError)?;
         }
 
+        grpc_service
+            .register_sensor(&id, driver, bus_id, MAVLINK_ROLE)
+            .await;
         Ok(sensor)
     }
 
@@ -118,10 +169,12 @@ pub async fn init_all(
 
     let mut i2c_bus_map = HashMap::new();
     let mut mavlink_connections: HashMap<String, Arc<MavlinkConnection>> = HashMap::new();
+    let mut uart_buses: HashMap<String, UartPort> = HashMap::new();
+    let mut iio_buses: HashMap<String, String> = HashMap::new();
 
     // Initialize buses based on type
     for b in bus_cfg.buses.iter() {
-        let bus_type = BusType::from_str(&b.r#type).ok_or_else(|| {
+        let bus_type = BusType::parse(&b.r#type).ok_or_else(|| {
             RegistryError::BusInitError(ConfigError::ValidationError(format!(
                 "Unknown bus type: {}",
                 b.r#type
@@ -132,8 +185,21 @@ pub async fn init_all(
             BusType::I2C => {
                 info!("[registry] Initializing I2C bus: {} at {}", b.id, b.path);
                 match I2CBus::new(&b.path) {
-                    Ok(bus) => {
-                        i2c_bus_map.insert(b.id.clone(), Arc::new(Mutex::new(bus)));
+                    Ok(mut bus) => {
+                        bus.set_trace(&b.id, grpc_service.i2c_trace());
+                        bus.set_error_counter(grpc_service.metrics().counter(I2C_ERRORS, &b.id));
+                        let recovery = BusRecovery {
+                            error_threshold: b
+                                .recovery_errors
+                                .unwrap_or(BusRecovery::default().error_threshold),
+                        };
+                        bus.set_recovery(
+                            recovery,
+                            grpc_service.metrics().counter(I2C_RECOVERIES, &b.id),
+                        );
+                        let bus = Arc::new(Mutex::new(bus));
+                        grpc_service.register_i2c_bus(&b.id, bus.clone(), b.no_probe.clone());
+                        i2c_bus_map.insert(b.id.clone(), bus);
                         info!("[registry] I2C bus {} initialized successfully", b.id);
                     }
                     Err(e) => {
@@ -193,13 +259,111 @@ pub async fn init_all(
 
                 // Log which port was successfully opened (useful for multi-machine testing)
                 let port_path = serial.path().to_string();
-                let mavlink_conn = MavlinkConnection::new(serial, auto_detect);
+                let mavlink_conn = MavlinkConnection::new(
+                    serial,
+                    auto_detect,
+                    link_counters(&grpc_service, &b.id),
+                );
                 mavlink_connections.insert(b.id.clone(), Arc::new(mavlink_conn));
                 info!(
                     "[registry] Serial/MAVLink bus {} initialized successfully on {}",
                     b.id, port_path
                 );
             }
+            BusType::Tcp => {
+                let endpoint = TcpEndpoint::parse(&b.path).map_err(|e| {
+                    RegistryError::BusInitError(ConfigError::InvalidValue {
+                        field: format!("bus '{}' path", b.id),
+                        reason: e.to_string(),
+                    })
+                })?;
+                info!(
+                    "[registry] Initializing TCP/MAVLink bus: {} at {}",
+                    b.id, endpoint
+                );
+                let mavlink_conn =
+                    MavlinkConnection::new_tcp(endpoint, link_counters(&grpc_service, &b.id));
+                mavlink_connections.insert(b.id.clone(), Arc::new(mavlink_conn));
+            }
+            BusType::Udp => {
+                let endpoint = UdpEndpoint::parse(&b.path).map_err(|e| {
+                    RegistryError::BusInitError(ConfigError::InvalidValue {
+                        field: format!("bus '{}' path", b.id),
+                        reason: e.to_string(),
+                    })
+                })?;
+                info!(
+                    "[registry] Initializing UDP/MAVLink bus: {} at {}",
+                    b.id, endpoint
+                );
+                let mavlink_conn =
+                    MavlinkConnection::new_udp(endpoint, link_counters(&grpc_service, &b.id));
+                mavlink_connections.insert(b.id.clone(), Arc::new(mavlink_conn));
+            }
+            BusType::Uart => {
+                // UART buses are opened by the sensor attached to them
+                info!("[registry] Registered UART bus: {} at {}", b.id, b.path);
+                let rs485 = b.rs485.then(|| Rs485Config {
+                    de_gpio: b.rs485_de_gpio,
+                    de_chip: b.rs485_de_chip.clone(),
+                    delay_before: Duration::from_micros(b.rs485_delay_before_us.into()),
+                    delay_after: Duration::from_micros(b.rs485_delay_after_us.into()),
+                });
+                uart_buses.insert(
+                    b.id.clone(),
+                    UartPort {
+                        path: b.path.clone(),
+                        baud: b.baud,
+                        rs485,
+                    },
+                );
+            }
+            BusType::Iio => {
+                // IIO devices are opened by the sensor attached to them
+                info!("[registry] Registered IIO bus: {} at {}", b.id, b.path);
+                iio_buses.insert(b.id.clone(), b.path.clone());
+            }
+            BusType::Replay => {
+                let speed = b.speed.unwrap_or(1.0);
+                if !(speed.is_finite() && speed > 0.0) {
+                    return Err(RegistryError::BusInitError(ConfigError::InvalidValue {
+                        field: format!("bus '{}' speed", b.id),
+                        reason: "must be a positive number".to_string(),
+                    }));
+                }
+                // Replayed sensors register themselves as their messages appear
+                info!("[registry] Replaying bus {} from {}", b.id, b.path);
+                spawn_replay(b.id.clone(), b.path.clone(), speed, grpc_service.clone());
+            }
+        }
+    }
+
+    // Raw byte captures can be started at runtime, or for a while after startup
+    let captures = grpc_service.mavlink_captures();
+    captures.configure(&sensor_config.mavlink_log);
+    for (bus_id, mavlink_conn) in mavlink_connections.iter() {
+        captures.add_link(bus_id, mavlink_conn.capture_slot());
+    }
+    if sensor_config.mavlink_log.capture_s > 0 && !mavlink_connections.is_empty() {
+        let duration = Duration::from_secs(sensor_config.mavlink_log.capture_s);
+        if let Err(e) = captures.start("", duration) {
+            error!(
+                "[registry] Cannot capture MAVLink bytes to {}: {}",
+                sensor_config.mavlink_log.dir, e
+            );
+        }
+    }
+
+    // Record the raw MAVLink links if enabled
+    if sensor_config.mavlink_log.enabled {
+        for (bus_id, mavlink_conn) in mavlink_connections.iter() {
+            match RawRecorder::start(&sensor_config.mavlink_log, bus_id) {
+                Ok(recorder) => mavlink_conn.record_raw(recorder),
+                Err(e) => error!(
+                    "[registry] Cannot record MAVLink bus {} to {}: {}",
+                    bus_id, sensor_config.mavlink_log.dir, e
+                ),
+            }
         }
     }
 
@@ -217,63 +381,361 @@ pub async fn init_all(
         local_sensors.len()
     );
 
+    // UART and IIO sensors start their own read loops here; I2C sensors are collected per
+    // bus and initialized below, one task per bus
+    let mut i2c_pending: HashMap<String, Vec<PendingSensor>> = HashMap::new();
     for s in local_sensors.iter() {
         debug!(
-            "[registry] Creating sensor driver: id={} type={} bus={} addr=0x{:02X}",
-            s.id, s.driver, s.bus, s.address
+            "[registry] Creating sensor driver: id={} type={} bus={} addr={}",
+            s.id,
+            s.driver,
+            s.bus,
+            Address(s.address)
         );
+        let started = Instant::now();
         let mut sensor = create_sensor_driver(&s.driver, s.id.clone(), s.address, s.bus.clone())
             .map_err(|e| {
                 error!("[registry] Failed to create sensor {}: {:?}", s.id, e);
                 RegistryError::DriverCreationError(e)
             })?;
+        sensor.configure(&s.params).map_err(|e| {
+            error!("[registry] Invalid params for sensor {}: {}", s.id, e);
+            RegistryError::DriverCreationError(e)
+        })?;
+        if s.drdy_gpio.is_some() {
+            sensor.enable_data_ready().map_err(|e| {
+                error!("[registry] Invalid drdy_gpio for sensor {}: {}", s.id, e);
+                RegistryError::DriverCreationError(e)
+            })?;
+        }
+        let role = s.role.as_deref().unwrap_or("");
+
+        // UART sensors own their port and run their own read loop
+        if let Some(port) = uart_buses.get(&s.bus) {
+            let uart_sensor = sensor
+                .as_any_mut()
+                .downcast_mut::<UartSensor>()
+                .ok_or_else(|| {
+                    RegistryError::DriverCreationError(SensorError::ConfigError {
+                        sensor: s.id.clone(),
+                        reason: format!("driver '{}' cannot be used on a UART bus", s.driver),
+                    })
+                })?;
+            uart_sensor.set_grpc_service(grpc_service.clone());
+            uart_sensor
+                .start(port, s.frequency.unwrap_or(100))
+                .map_err(RegistryError::RegistrationError)?;
+
+            info!("[registry] UART sensor {} started on {}", s.id, port.path);
+            grpc_service
+                .register_sensor(&s.id, &s.driver, &s.bus, role)
+                .await;
+            grpc_service
+                .set_init_duration(&s.id, started.elapsed())
+                .await;
+            sensors.push(sensor);
+            continue;
+        }
+
+        // IIO sensors read through the kernel driver in their own read loop
+        if let Some(path) = iio_buses.get(&s.bus) {
+            #[cfg(feature = "iio")]
+            {
+                let iio_sensor =
+                    sensor
+                        .as_any_mut()
+                        .downcast_mut::<IioSensor>()
+                        .ok_or_else(|| {
+                            RegistryError::DriverCreationError(SensorError::ConfigError {
+                                sensor: s.id.clone(),
+                                reason: format!(
+                                    "driver '{}' cannot be used on an IIO bus",
+                                    s.driver
+                                ),
+                            })
+                        })?;
+                iio_sensor.set_grpc_service(grpc_service.clone());
+                iio_sensor
+                    .start(path, s.frequency.unwrap_or(100))
+                    .map_err(RegistryError::RegistrationError)?;
+
+                info!("[registry] IIO sensor {} started on {}", s.id, path);
+                grpc_service
+                    .register_sensor(&s.id, &s.driver, &s.bus, role)
+                    .await;
+                grpc_service
+                    .set_init_duration(&s.id, started.elapsed())
+                    .await;
+                sensors.push(sensor);
+                continue;
+            }
+            #[cfg(not(feature = "iio"))]
+            return Err(RegistryError::DriverCreationError(
+                SensorError::ConfigError {
+                    sensor: s.id.clone(),
+                    reason: format!("IIO bus {} requires the 'iio' feature", path),
+                },
+            ));
+        }
 
         // For I2C sensors, use the I2C bus
-        let bus_arc = i2c_bus_map.get(&s.bus).ok_or_else(|| {
-            RegistryError::DriverCreationError(SensorError::BusNotFound { bus: s.bus.clone() })
-        })?;
-        let mut bus = bus_arc.lock().await;
+        if s.address > MAX_10BIT_ADDRESS {
+            return Err(RegistryError::DriverCreationError(
+                SensorError::ConfigError {
+                    sensor: s.id.clone(),
+                    reason: format!("address {:#x} is beyond the 10-bit range", s.address),
+                },
+            ));
+        }
+        if !i2c_bus_map.contains_key(&s.bus) {
+            return Err(RegistryError::DriverCreationError(
+                SensorError::BusNotFound { bus: s.bus.clone() },
+            ));
+        }
+        i2c_pending
+            .entry(s.bus.clone())
+            .or_default()
+            .push(PendingSensor {
+                sensor,
+                driver: s.driver.clone(),
+                role: role.to_string(),
+            });
+    }
+
+    // I2C buses and MAVLink discovery proceed concurrently; sensors on one I2C bus are
+    // initialized in config order since they share it
+    let mut i2c_inits = JoinSet::new();
+    for (bus_id, pending) in i2c_pending {
+        i2c_inits.spawn(init_i2c_bus(
+            bus_id.clone(),
+            i2c_bus_map[&bus_id].clone(),
+            pending,
+            grpc_service.clone(),
+        ));
+    }
+    let mut discoveries = JoinSet::new();
+    for (bus_id, mavlink_conn) in mavlink_connections.iter() {
+        discoveries.spawn(discover_mavlink_sensors(
+            bus_id.clone(),
+            mavlink_conn.clone(),
+            grpc_service.clone(),
+            // MAVLink sensors don't use the I2C bus, any one satisfies their init()
+            i2c_bus_map.values().next().cloned(),
+        ));
+    }
+    while let Some(result) = i2c_inits.join_next().await {
+        match result {
+            Ok(initialized) => sensors.extend(initialized?),
+            // Surface a driver panic as if init had run inline
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    while let Some(result) = discoveries.join_next().await {
+        match result {
+            Ok(discovered) => sensors.extend(discovered),
+            Err(e) => error!("[registry] MAVLink discovery task failed: {}", e),
+        }
+    }
+
+    info!("[registry] Total sensors initialized: {}", sensors.len());
+    Ok((sensors, i2c_bus_map))
+}
+
+/// A configured I2C sensor waiting for its bus to be initialized
+struct PendingSensor {
+    sensor: Box<dyn SensorDriver>,
+    driver: String,
+    role: String,
+}
+
+/// Initialize the sensors of one I2C bus in order, recording how long each took
+async fn init_i2c_bus(
+    bus_id: String,
+    bus: Arc<Mutex<I2CBus>>,
+    pending: Vec<PendingSensor>,
+    grpc_service: Arc<SensorHubService>,
+) -> RegistryResult<Vec<Box<dyn SensorDriver>>> {
+    let mut bus = bus.lock().await;
+    let mut sensors = Vec::with_capacity(pending.len());
+    for PendingSensor {
+        mut sensor,
+        driver,
+        role,
+    } in pending
+    {
+        let started = Instant::now();
         sensor
             .init(&mut bus)
             .await
             .map_err(RegistryError::RegistrationError)?;
+        let elapsed = started.elapsed();
 
-        info!("[registry] Local sensor {} created successfully", s.id);
+        info!(
+            "[registry] Local sensor {} initialized in {} ms",
+            sensor.id(),
+            elapsed.as_millis()
+        );
+        grpc_service
+            .register_sensor(sensor.id(), &driver, &bus_id, &role)
+            .await;
+        grpc_service.set_init_duration(sensor.id(), elapsed).await;
         sensors.push(sensor);
     }
+    Ok(sensors)
+}
 
-    // Auto-discover MAVLink sensors from each serial bus
-    for (bus_id, mavlink_conn) in mavlink_connections.iter() {
-        info!(
-            "[registry] Waiting for MAVLink sensor auto-discovery on bus {}...",
-            bus_id
-        );
+/// Metrics counters of the MAVLink bus `bus_id`
+fn link_counters(grpc_service: &SensorHubService, bus_id: &str) -> LinkCounters {
+    LinkCounters {
+        parse_errors: grpc_service.metrics().counter(MAVLINK_PARSE_ERRORS, bus_id),
+        reconnects: grpc_service.metrics().counter(MAVLINK_RECONNECTS, bus_id),
+    }
+}
 
-        // Wait a short time for sensors to be detected (messages to arrive)
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Sensor types seen on a MAVLink bus once the set stops growing
+///
+/// Returns after `MAVLINK_DISCOVERY_SETTLE` without a new type, or after
+/// `MAVLINK_DISCOVERY_WAIT` at most; types that show up later are picked up by
+/// `watch_mavlink_sensors`.
+async fn wait_for_mavlink_sensors(mavlink_conn: &MavlinkConnection) -> Vec<DetectedSensor> {
+    let started = Instant::now();
+    let mut changed = started;
+    let mut detected = mavlink_conn.get_detected_sensors().await;
+    loop {
+        let settled = !detected.is_empty() && changed.elapsed() >= MAVLINK_DISCOVERY_SETTLE;
+        if settled || started.elapsed() >= MAVLINK_DISCOVERY_WAIT {
+            return detected;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let current = mavlink_conn.get_detected_sensors().await;
+        if current.len() != detected.len() {
+            changed = Instant::now();
+            detected = current;
+        }
+    }
+}
 
-        let detected = mavlink_conn.get_detected_sensors().await;
-        info!(
-            "[registry] Auto-detected {} MAVLink sensor type(s) on bus {}",
-            detected.len(),
-            bus_id
-        );
+/// Create the sensors already present on a MAVLink bus and start its background tasks
+async fn discover_mavlink_sensors(
+    bus_id: String,
+    mavlink_conn: Arc<MavlinkConnection>,
+    grpc_service: Arc<SensorHubService>,
+    dummy_bus: Option<Arc<Mutex<I2CBus>>>,
+) -> Vec<Box<dyn SensorDriver>> {
+    info!(
+        "[registry] Waiting for MAVLink sensor auto-discovery on bus {}...",
+        bus_id
+    );
+    let started = Instant::now();
+    let detected = wait_for_mavlink_sensors(&mavlink_conn).await;
+    info!(
+        "[registry] Auto-detected {} MAVLink sensor type(s) on bus {} in {} ms",
+        detected.len(),
+        bus_id,
+        started.elapsed().as_millis()
+    );
 
-        // Get a dummy I2C bus for initialization (MAVLink sensors don't actually use it)
-        let dummy_bus = i2c_bus_map.values().next();
+    let mut sensors = Vec::new();
+    let mut created = HashMap::new();
+    for sensor_type in detected {
+        match create_mavlink_sensor(
+            sensor_type,
+            &bus_id,
+            &mavlink_conn,
+            &grpc_service,
+            dummy_bus.as_ref(),
+        )
+        .await
+        {
+            Ok(sensor) => {
+                info!(
+                    "[registry] MAVLink sensor {} created successfully",
+                    sensor.id()
+                );
+                grpc_service
+                    .set_init_duration(sensor.id(), started.elapsed())
+                    .await;
+                created.insert(sensor_type, sensor.id().to_string());
+                sensors.push(sensor);
+            }
+            Err(e) => {
+                error!(
+                    "[registry] Failed to create MAVLink sensor {:?}: {:?}",
+                    sensor_type, e
+                );
+            }
+        }
+    }
+
+    // Forward injected RTCM corrections to the flight controller
+    tokio::spawn(forward_gps_corrections(
+        bus_id.clone(),
+        mavlink_conn.clone(),
+        grpc_service.subscribe_corrections(),
+    ));
 
-        for sensor_type in detected {
-            match create_mavlink_sensor(sensor_type, bus_id, mavlink_conn, &grpc_service, dummy_bus)
+    // Follow the vehicle arming state for the control RPC interlock
+    tokio::spawn(track_arming(mavlink_conn.clone(), grpc_service.interlock()));
+
+    // Keep watching for sensors that appear or go quiet after startup
+    tokio::spawn(watch_mavlink_sensors(
+        bus_id,
+        mavlink_conn,
+        grpc_service,
+        created,
+    ));
+    sensors
+}
+
+/// Re-scan a MAVLink bus for sensors that appear or go quiet after startup
+///
+/// New message types get a sensor created and announced; sensors that stop sending
+/// data are removed from ListSensors and announced again when their data resumes.
+async fn watch_mavlink_sensors(
+    bus_id: String,
+    mavlink_conn: Arc<MavlinkConnection>,
+    grpc_service: Arc<SensorHubService>,
+    mut online: HashMap<DetectedSensor, String>,
+) {
+    let mut offline: HashMap<DetectedSensor, SensorInfo> = HashMap::new();
+    // Types we could not create a sensor for - don't retry them every scan
+    let mut failed: HashSet<DetectedSensor> = HashSet::new();
+    // Sensors created here are kept alive for the lifetime of the bus
+    let mut created: Vec<Box<dyn SensorDriver>> = Vec::new();
+    let mut ticker = tokio::time::interval(MAVLINK_DISCOVERY_INTERVAL);
+
+    loop {
+        ticker.tick().await;
+        let active = mavlink_conn
+            .get_active_sensors(MAVLINK_SENSOR_TIMEOUT)
+            .await;
+
+        for sensor_type in active.iter().copied() {
+            if online.contains_key(&sensor_type) || failed.contains(&sensor_type) {
+                continue;
+            }
+            if let Some(info) = offline.remove(&sensor_type) {
+                // Known sensor came back
+                grpc_service
+                    .register_sensor(&info.sensor_id, &info.driver, &info.bus_id, &info.role)
+                    .await;
+                online.insert(sensor_type, info.sensor_id);
+                continue;
+            }
+            match create_mavlink_sensor(sensor_type, &bus_id, &mavlink_conn, &grpc_service, None)
                 .await
             {
                 Ok(sensor) => {
                     info!(
-                        "[registry] MAVLink sensor {} created successfully",
-                        sensor.id()
+                        "[registry] MAVLink sensor {} discovered at runtime on bus {}",
+                        sensor.id(),
+                        bus_id
                     );
-                    sensors.push(sensor);
+                    online.insert(sensor_type, sensor.id().to_string());
+                    created.push(sensor);
                 }
                 Err(e) => {
+                    failed.insert(sensor_type);
                     error!(
                         "[registry] Failed to create MAVLink sensor {:?}: {:?}",
                         sensor_type, e
@@ -281,8 +743,57 @@ pub async fn init_all(
                 }
             }
         }
+
+        let lost: Vec<DetectedSensor> = online
+            .keys()
+            .filter(|sensor_type| !active.contains(sensor_type))
+            .copied()
+            .collect();
+        for sensor_type in lost {
+            let sensor_id = online.remove(&sensor_type).unwrap_or_default();
+            let reason = format!("no data for {}s", MAVLINK_SENSOR_TIMEOUT.as_secs());
+            if let Some(info) = grpc_service.remove_sensor(&sensor_id, &reason).await {
+                offline.insert(sensor_type, info);
+            }
+        }
     }
+}
 
-    info!("[registry] Total sensors initialized: {}", sensors.len());
-    Ok((sensors, i2c_bus_map))
+/// Feed HEARTBEATs from a MAVLink bus to the arming interlock
+async fn track_arming(mavlink_conn: Arc<MavlinkConnection>, interlock: Arc<Interlock>) {
+    let mut rx = mavlink_conn.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(mavlink::common::MavMessage::HEARTBEAT(heartbeat)) => interlock.observe(&heartbeat),
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Send RTCM frames from InjectGpsCorrections to the flight controller as GPS_RTCM_DATA
+async fn forward_gps_corrections(
+    bus_id: String,
+    mavlink_conn: Arc<MavlinkConnection>,
+    mut corrections: broadcast::Receiver<Arc<Vec<u8>>>,
+) {
+    loop {
+        match corrections.recv().await {
+            Ok(frame) => {
+                if let Err(e) = mavlink_conn.inject_rtcm(&frame).await {
+                    warn!(
+                        "[registry] Failed to forward RTCM frame to bus {}: {}",
+                        bus_id, e
+                    );
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(
+                    "[registry] Bus {} fell behind, {} RTCM frame(s) skipped",
+                    bus_id, n
+                );
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
 }
@@ -0,0 +1,788 @@
+use crate::clock::Stamp;
+use crate::grpc_service::sensorhub::{self, sensor_data::Data};
+use crate::grpc_service::SensorHubService;
+use crate::kinds;
+use crate::messages::*;
+use prost::Message;
+use serde_json::{Map, Value};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Messages read ahead of the replay clock
+const READ_AHEAD: usize = 256;
+
+const MCAP_MAGIC: &[u8] = b"\x89MCAP0\r\n";
+const OP_FOOTER: u8 = 0x02;
+const OP_CHANNEL: u8 = 0x04;
+const OP_MESSAGE: u8 = 0x05;
+const OP_CHUNK: u8 = 0x06;
+const OP_SCHEMA: u8 = 0x03;
+
+/// Header fields, which CSV logs store as plain columns
+const HEADER_COLUMNS: [&str; 12] = [
+    "device_id",
+    "sensor_id",
+    "frame_id",
+    "seq",
+    "t_utc_ns",
+    "t_mono_ns",
+    "pps_locked",
+    "ptp_locked",
+    "clock_err_ppb",
+    "sigma_t_ns",
+    "schema_v",
+    "calibration_id",
+];
+
+/// Header fields that are text even when they look like numbers or are empty
+const TEXT_HEADER_COLUMNS: [&str; 4] = ["device_id", "sensor_id", "frame_id", "calibration_id"];
+
+/// SensorMessage variant of each data kind, as named in CSV file names
+const KINDS: [(&str, &str); 17] = [
+    (kinds::IMU, "Imu"),
+    (kinds::MAGNETOMETER, "Magnetometer"),
+    (kinds::BAROMETER, "Barometer"),
+    (kinds::GPS, "Gps"),
+    (kinds::RANGE, "Rangefinder"),
+    (kinds::GLOBAL_POSITION, "GlobalPosition"),
+    (kinds::LOCAL_POSITION, "LocalPosition"),
+    (kinds::ATTITUDE, "Attitude"),
+    (kinds::AIRSPEED, "Airspeed"),
+    (kinds::VFR_HUD, "VfrHud"),
+    (kinds::VIBRATION, "Vibration"),
+    (kinds::RC_CHANNELS, "RcChannels"),
+    (kinds::ESC, "EscTelemetry"),
+    (kinds::SYSTEM, "System"),
+    (kinds::ALTITUDE, "Altitude"),
+    (kinds::GIMBAL_ATTITUDE, "GimbalAttitude"),
+    (kinds::MEASUREMENTS, "Measurements"),
+];
+
+fn header_from_proto(h: Option<sensorhub::Header>) -> Header {
+    let h = h.unwrap_or_default();
+    Header {
+        device_id: h.device_id,
+        sensor_id: h.sensor_id,
+        frame_id: h.frame_id,
+        seq: h.seq,
+        t_utc_ns: h.t_utc_ns,
+        t_mono_ns: h.t_mono_ns,
+        pps_locked: h.pps_locked,
+        ptp_locked: h.ptp_locked,
+        clock_err_ppb: h.clock_err_ppb,
+        sigma_t_ns: h.sigma_t_ns,
+        schema_v: h.schema_v as u16,
+        calibration_id: h.calibration_id,
+    }
+}
+
+/// Rebuild the message a SensorData was published from
+fn from_proto(data: Data) -> SensorMessage {
+    match data {
+        Data::Imu(d) => SensorMessage::Imu(ImuMessage {
+            h: header_from_proto(d.header),
+            ax: d.ax,
+            ay: d.ay,
+            az: d.az,
+            gx: d.gx,
+            gy: d.gy,
+            gz: d.gz,
+            precise: d.precise.map(|p| [p.ax, p.ay, p.az, p.gx, p.gy, p.gz]),
+            temperature: d.temperature,
+        }),
+        Data::Magnetometer(d) => SensorMessage::Magnetometer(MagnetometerMessage {
+            h: header_from_proto(d.header),
+            mx: d.mx,
+            my: d.my,
+            mz: d.mz,
+        }),
+        Data::Barometer(d) => SensorMessage::Barometer(BarometerMessage {
+            h: header_from_proto(d.header),
+            pressure: d.pressure,
+            temperature: d.temperature,
+            altitude: d.altitude,
+        }),
+        Data::Gps(d) => SensorMessage::Gps(GpsMessage {
+            h: header_from_proto(d.header),
+            fix_type: d.fix_type,
+            satellites_visible: d.satellites_visible,
+            latitude: d.latitude,
+            longitude: d.longitude,
+            altitude: d.altitude,
+            hdop: d.hdop,
+            vdop: d.vdop,
+            ground_speed: d.ground_speed,
+            course: d.course,
+            yaw: d.yaw,
+            yaw_accuracy: d.yaw_accuracy,
+        }),
+        Data::Range(d) => SensorMessage::Rangefinder(RangefinderMessage {
+            h: header_from_proto(d.header),
+            distance: d.distance,
+            min_distance: d.min_distance,
+            max_distance: d.max_distance,
+            orientation: d.orientation,
+            covariance: d.covariance,
+        }),
+        Data::GlobalPosition(d) => SensorMessage::GlobalPosition(GlobalPositionMessage {
+            h: header_from_proto(d.header),
+            latitude: d.latitude,
+            longitude: d.longitude,
+            altitude: d.altitude,
+            relative_altitude: d.relative_altitude,
+            vn: d.vn,
+            ve: d.ve,
+            vd: d.vd,
+            heading: d.heading,
+        }),
+        Data::LocalPosition(d) => SensorMessage::LocalPosition(LocalPositionMessage {
+            h: header_from_proto(d.header),
+            x: d.x,
+            y: d.y,
+            z: d.z,
+            vx: d.vx,
+            vy: d.vy,
+            vz: d.vz,
+        }),
+        Data::Attitude(d) => SensorMessage::Attitude(AttitudeMessage {
+            h: header_from_proto(d.header),
+            qw: d.qw,
+            qx: d.qx,
+            qy: d.qy,
+            qz: d.qz,
+            roll: d.roll,
+            pitch: d.pitch,
+            yaw: d.yaw,
+            rollspeed: d.rollspeed,
+            pitchspeed: d.pitchspeed,
+            yawspeed: d.yawspeed,
+            gnss_yaw: d.gnss_yaw,
+            gnss_yaw_accuracy: d.gnss_yaw_accuracy,
+        }),
+        Data::Airspeed(d) => SensorMessage::Airspeed(AirspeedMessage {
+            h: header_from_proto(d.header),
+            differential_pressure: d.differential_pressure,
+            indicated_airspeed: d.indicated_airspeed,
+            temperature: d.temperature,
+        }),
+        Data::VfrHud(d) => SensorMessage::VfrHud(VfrHudMessage {
+            h: header_from_proto(d.header),
+            airspeed: d.airspeed,
+            groundspeed: d.groundspeed,
+            heading: d.heading,
+            climb_rate: d.climb_rate,
+            throttle: d.throttle,
+            altitude: d.altitude,
+        }),
+        Data::Vibration(d) => SensorMessage::Vibration(VibrationMessage {
+            h: header_from_proto(d.header),
+            vibration_x: d.vibration_x,
+            vibration_y: d.vibration_y,
+            vibration_z: d.vibration_z,
+            clipping: [d.clipping_0, d.clipping_1, d.clipping_2],
+        }),
+        Data::RcChannels(d) => SensorMessage::RcChannels(RcChannelsMessage {
+            h: header_from_proto(d.header),
+            channels: d.channels.iter().map(|&c| c as u16).collect(),
+            rssi: d.rssi.map(|r| r as u8),
+        }),
+        Data::Esc(d) => SensorMessage::EscTelemetry(EscTelemetryMessage {
+            h: header_from_proto(d.header),
+            escs: d
+                .escs
+                .into_iter()
+                .map(|e| EscReadingMessage {
+                    index: e.index as u8,
+                    rpm: e.rpm,
+                    voltage: e.voltage,
+                    current: e.current,
+                    temperature: e.temperature,
+                    error_count: e.error_count,
+                    failure_flags: e.failure_flags as u16,
+                })
+                .collect(),
+        }),
+        Data::System(d) => SensorMessage::System(SystemMessage {
+            h: header_from_proto(d.header),
+            cpu_load: d.cpu_load,
+            load_avg_1m: d.load_avg_1m,
+            mem_total_bytes: d.mem_total_bytes,
+            mem_available_bytes: d.mem_available_bytes,
+            disk_total_bytes: d.disk_total_bytes,
+            disk_available_bytes: d.disk_available_bytes,
+            history_bytes: d.history_bytes,
+            thermal_zones: d
+                .thermal_zones
+                .into_iter()
+                .map(|z| ThermalZoneMessage {
+                    name: z.name,
+                    temperature: z.temperature,
+                })
+                .collect(),
+        }),
+        Data::GimbalAttitude(d) => SensorMessage::GimbalAttitude(GimbalAttitudeMessage {
+            h: header_from_proto(d.header),
+            gimbal_device_id: d.gimbal_device_id as u8,
+            qw: d.qw,
+            qx: d.qx,
+            qy: d.qy,
+            qz: d.qz,
+            roll: d.roll,
+            pitch: d.pitch,
+            yaw: d.yaw,
+            angular_velocity_x: d.angular_velocity_x,
+            angular_velocity_y: d.angular_velocity_y,
+            angular_velocity_z: d.angular_velocity_z,
+            yaw_in_earth_frame: d.yaw_in_earth_frame,
+            delta_yaw: d.delta_yaw,
+            device_flags: d.device_flags as u16,
+            failure_flags: d.failure_flags,
+        }),
+        Data::Measurements(d) => SensorMessage::Measurements(MeasurementsMessage {
+            h: header_from_proto(d.header),
+            values: d
+                .values
+                .into_iter()
+                .map(|v| NamedValueMessage {
+                    name: v.name,
+                    value: v.value,
+                    unit: v.unit,
+                })
+                .collect(),
+        }),
+        Data::Altitude(d) => SensorMessage::Altitude(AltitudeMessage {
+            h: header_from_proto(d.header),
+            altitude: d.altitude,
+            climb_rate: d.climb_rate,
+            vertical_accel: d.vertical_accel,
+        }),
+    }
+}
+
+/// Decode a message body by its protobuf schema name (as written by the MCAP recorder)
+fn decode(schema: &str, body: &[u8]) -> Option<Data> {
+    use sensorhub::*;
+    Some(match schema {
+        "sensorhub.IMUData" => Data::Imu(ImuData::decode(body).ok()?),
+        "sensorhub.MagnetometerData" => Data::Magnetometer(MagnetometerData::decode(body).ok()?),
+        "sensorhub.BarometerData" => Data::Barometer(BarometerData::decode(body).ok()?),
+        "sensorhub.GPSData" => Data::Gps(GpsData::decode(body).ok()?),
+        "sensorhub.RangeData" => Data::Range(RangeData::decode(body).ok()?),
+        "sensorhub.GlobalPositionData" => {
+            Data::GlobalPosition(GlobalPositionData::decode(body).ok()?)
+        }
+        "sensorhub.LocalPositionData" => Data::LocalPosition(LocalPositionData::decode(body).ok()?),
+        "sensorhub.AttitudeData" => Data::Attitude(AttitudeData::decode(body).ok()?),
+        "sensorhub.AirspeedData" => Data::Airspeed(AirspeedData::decode(body).ok()?),
+        "sensorhub.VfrHudData" => Data::VfrHud(VfrHudData::decode(body).ok()?),
+        "sensorhub.VibrationData" => Data::Vibration(VibrationData::decode(body).ok()?),
+        "sensorhub.RcChannelsData" => Data::RcChannels(RcChannelsData::decode(body).ok()?),
+        "sensorhub.EscTelemetryData" => Data::Esc(EscTelemetryData::decode(body).ok()?),
+        "sensorhub.SystemData" => Data::System(SystemData::decode(body).ok()?),
+        "sensorhub.AltitudeData" => Data::Altitude(AltitudeData::decode(body).ok()?),
+        "sensorhub.GimbalAttitudeData" => {
+            Data::GimbalAttitude(GimbalAttitudeData::decode(body).ok()?)
+        }
+        "sensorhub.MeasurementsData" => Data::Measurements(MeasurementsData::decode(body).ok()?),
+        // Also accept channels carrying the unified message
+        "sensorhub.SensorData" => SensorData::decode(body).ok()?.data?,
+        _ => return None,
+    })
+}
+
+fn invalid(reason: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, reason.into())
+}
+
+/// Little-endian field reader over one record
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, n: usize) -> std::io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid("truncated MCAP record"));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+/// Sequential MCAP reader for sensorhub recordings (unchunked, or uncompressed chunks)
+struct McapReader<R: Read> {
+    input: R,
+    /// Schema name by schema id
+    schemas: HashMap<u16, String>,
+    /// Schema name by channel id
+    channels: HashMap<u16, String>,
+    /// Records of the chunk being read
+    pending: Vec<(u8, Vec<u8>)>,
+}
+
+impl<R: Read> McapReader<R> {
+    fn new(mut input: R) -> std::io::Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if magic != MCAP_MAGIC {
+            return Err(invalid("not an MCAP file"));
+        }
+        Ok(Self {
+            input,
+            schemas: HashMap::new(),
+            channels: HashMap::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn next_record(&mut self) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+        if !self.pending.is_empty() {
+            return Ok(Some(self.pending.remove(0)));
+        }
+        let mut head = [0u8; 9];
+        match self.input.read_exact(&mut head) {
+            Ok(()) => {}
+            // A recording cut short (power loss) ends without a footer
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let len = u64::from_le_bytes(head[1..].try_into().unwrap());
+        let mut content = Vec::new();
+        (&mut self.input).take(len).read_to_end(&mut content)?;
+        if (content.len() as u64) < len {
+            return Ok(None);
+        }
+        Ok(Some((head[0], content)))
+    }
+
+    /// Next message with its log time, or None at the end of the data
+    fn next_message(&mut self) -> std::io::Result<Option<(u64, SensorMessage)>> {
+        while let Some((opcode, content)) = self.next_record()? {
+            let mut fields = Fields(&content);
+            match opcode {
+                OP_SCHEMA => {
+                    let id = fields.u16()?;
+                    let name = fields.string()?;
+                    self.schemas.insert(id, name);
+                }
+                OP_CHANNEL => {
+                    let id = fields.u16()?;
+                    let schema_id = fields.u16()?;
+                    let schema = self.schemas.get(&schema_id).cloned().unwrap_or_default();
+                    self.channels.insert(id, schema);
+                }
+                OP_MESSAGE => {
+                    let channel_id = fields.u16()?;
+                    let _sequence = fields.u32()?;
+                    let log_time_ns = fields.u64()?;
+                    let _publish_time_ns = fields.u64()?;
+                    let schema = self.channels.get(&channel_id).map(String::as_str);
+                    if let Some(data) = schema.and_then(|s| decode(s, fields.0)) {
+                        return Ok(Some((log_time_ns, from_proto(data))));
+                    }
+                }
+                OP_CHUNK => {
+                    fields.take(8 + 8 + 8 + 4)?; // start/end time, size, CRC
+                    let compression = fields.string()?;
+                    if !compression.is_empty() {
+                        return Err(invalid(format!(
+                            "{}-compressed chunks are not supported, re-save with `mcap convert --compression none`",
+                            compression
+                        )));
+                    }
+                    let len = fields.u64()? as usize;
+                    let mut records = Fields(fields.take(len)?);
+                    while !records.0.is_empty() {
+                        let opcode = records.take(1)?[0];
+                        let len = records.u64()? as usize;
+                        self.pending.push((opcode, records.take(len)?.to_vec()));
+                    }
+                }
+                OP_FOOTER => return Ok(None),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Split one CSV line, honouring double-quoted cells
+fn split_csv(line: &str) -> Vec<String> {
+    let mut cells = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                cells.last_mut().unwrap().push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(c),
+        }
+    }
+    cells
+}
+
+fn csv_value(cell: &str) -> Value {
+    if cell.is_empty() {
+        Value::Null
+    } else if let Ok(n) = cell.parse::<u64>() {
+        n.into()
+    } else if let Ok(n) = cell.parse::<i64>() {
+        n.into()
+    } else if let Ok(n) = cell.parse::<f64>() {
+        n.into()
+    } else if let Ok(b) = cell.parse::<bool>() {
+        b.into()
+    } else {
+        cell.into()
+    }
+}
+
+/// Place `value` at a dotted column path, numeric segments being array indices
+fn insert(target: &mut Value, path: &[&str], value: Value) {
+    let Some((first, rest)) = path.split_first() else {
+        *target = value;
+        return;
+    };
+    let slot = match first.parse::<usize>() {
+        Ok(index) => {
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+            let items = target.as_array_mut().unwrap();
+            if items.len() <= index {
+                items.resize(index + 1, Value::Null);
+            }
+            &mut items[index]
+        }
+        Err(_) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            target
+                .as_object_mut()
+                .unwrap()
+                .entry(first.to_string())
+                .or_insert(Value::Null)
+        }
+    };
+    insert(slot, rest, value);
+}
+
+/// Rebuild a message from one row of a CSV log
+fn from_csv_row(variant: &str, columns: &[String], cells: &[String]) -> Option<SensorMessage> {
+    let mut body = Value::Object(Map::new());
+    for (column, cell) in columns.iter().zip(cells) {
+        let mut path: Vec<&str> = column.split('.').collect();
+        if HEADER_COLUMNS.contains(&column.as_str()) {
+            path.insert(0, "h");
+        }
+        let value = if TEXT_HEADER_COLUMNS.contains(&column.as_str()) {
+            Value::String(cell.clone())
+        } else {
+            csv_value(cell)
+        };
+        insert(&mut body, &path, value);
+    }
+    let mut message = Map::new();
+    message.insert(variant.to_string(), body);
+    serde_json::from_value(Value::Object(message)).ok()
+}
+
+/// Rows of one CSV log file, read as they are needed
+struct CsvRows {
+    path: PathBuf,
+    variant: &'static str,
+    columns: Vec<String>,
+    lines: std::io::Lines<BufReader<File>>,
+    skipped: usize,
+}
+
+impl CsvRows {
+    fn next_message(&mut self) -> std::io::Result<Option<SensorMessage>> {
+        for line in self.lines.by_ref() {
+            match from_csv_row(self.variant, &self.columns, &split_csv(&line?)) {
+                Some(message) => return Ok(Some(message)),
+                None => self.skipped += 1,
+            }
+        }
+        if self.skipped > 0 {
+            warn!(
+                "[replay] {} row(s) of {} could not be read",
+                self.skipped,
+                self.path.display()
+            );
+            self.skipped = 0;
+        }
+        Ok(None)
+    }
+}
+
+/// Rows of a CSV log directory (`<sensor_id>_<kind>.csv` files and their continuations,
+/// `<sensor_id>_<kind>.<part>.csv`), merged in time order
+///
+/// Each file is written in time order, so only its next row is held in memory.
+struct CsvDirReader {
+    files: Vec<CsvRows>,
+    /// Next row of each file
+    next: Vec<Option<SensorMessage>>,
+    /// Time of each file's next row, earliest on top
+    order: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl CsvDirReader {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        let mut reader = Self {
+            files: Vec::new(),
+            next: Vec::new(),
+            order: BinaryHeap::new(),
+        };
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(stem) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".csv"))
+            else {
+                continue;
+            };
+            let stem = match stem.rsplit_once('.') {
+                Some((stem, part))
+                    if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) =>
+                {
+                    stem
+                }
+                _ => stem,
+            };
+            let Some(variant) = KINDS.iter().find_map(|(kind, variant)| {
+                stem.strip_suffix(kind)
+                    .is_some_and(|id| id.ends_with('_') && id.len() > 1)
+                    .then_some(*variant)
+            }) else {
+                warn!("[replay] Skipping {}: unknown data kind", path.display());
+                continue;
+            };
+
+            let mut lines = BufReader::new(File::open(&path)?).lines();
+            let Some(header) = lines.next().transpose()? else {
+                continue;
+            };
+            let mut file = CsvRows {
+                columns: split_csv(&header),
+                path,
+                variant,
+                lines,
+                skipped: 0,
+            };
+            let next = file.next_message()?;
+            reader.push(file, next);
+        }
+        Ok(reader)
+    }
+
+    fn push(&mut self, file: CsvRows, next: Option<SensorMessage>) {
+        if let Some(message) = &next {
+            self.order
+                .push(Reverse((message.header().t_utc_ns, self.files.len())));
+        }
+        self.files.push(file);
+        self.next.push(next);
+    }
+
+    /// Earliest row not yet returned, with its timestamp
+    fn next_message(&mut self) -> std::io::Result<Option<(u64, SensorMessage)>> {
+        let Some(Reverse((t_ns, index))) = self.order.pop() else {
+            return Ok(None);
+        };
+        let following = self.files[index].next_message()?;
+        if let Some(message) = &following {
+            self.order.push(Reverse((message.header().t_utc_ns, index)));
+        }
+        let message = std::mem::replace(&mut self.next[index], following);
+        Ok(message.map(|message| (t_ns, message)))
+    }
+}
+
+/// Read the log on a blocking thread, handing messages over in time order
+fn read_log(path: PathBuf, tx: mpsc::Sender<(u64, SensorMessage)>) -> std::io::Result<()> {
+    if path.is_dir() {
+        let mut reader = CsvDirReader::open(&path)?;
+        while let Some(message) = reader.next_message()? {
+            if tx.blocking_send(message).is_err() {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let mut reader = McapReader::new(BufReader::new(File::open(&path)?))?;
+    while let Some(message) = reader.next_message()? {
+        if tx.blocking_send(message).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Replay an MCAP recording or CSV log directory as the sensors of bus `bus_id`
+///
+/// Messages are published with the original spacing divided by `speed` and restamped with
+/// the current time. They were recorded after the processing stages, so they are published
+/// without going through them again.
+pub fn spawn_replay(bus_id: String, path: String, speed: f64, grpc: Arc<SensorHubService>) {
+    let (tx, mut rx) = mpsc::channel(READ_AHEAD);
+    let reader_path = PathBuf::from(&path);
+    let reader_bus = bus_id.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = read_log(reader_path, tx) {
+            error!("[replay] Cannot read log of bus {}: {}", reader_bus, e);
+        }
+    });
+
+    tokio::spawn(async move {
+        info!("[replay] Bus {} replaying {} at {}x", bus_id, path, speed);
+        let mut registered = HashSet::new();
+        let mut start: Option<(u64, Instant)> = None;
+        let mut count = 0u64;
+        while let Some((t_ns, mut message)) = rx.recv().await {
+            let (t0_ns, wall_start) = *start.get_or_insert((t_ns, Instant::now()));
+            let offset = t_ns.saturating_sub(t0_ns) as f64 / speed;
+            tokio::time::sleep_until(wall_start + Duration::from_nanos(offset as u64)).await;
+
+            let sensor_id = message.header().sensor_id.clone();
+            if registered.insert(sensor_id.clone()) {
+                grpc.register_sensor(&sensor_id, "replay", &bus_id, "replay")
+                    .await;
+            }
+            let stamp = Stamp::now();
+            let h = header_mut(&mut message);
+            h.t_utc_ns = stamp.utc_ns;
+            h.t_mono_ns = stamp.mono_ns;
+            if let Err(e) = grpc.publish_processed(message).await {
+                error!("[replay] Failed to publish: {}", e);
+            }
+            count += 1;
+        }
+        info!(
+            "[replay] Bus {} finished: {} message(s) from {} sensor(s)",
+            bus_id,
+            count,
+            registered.len()
+        );
+    });
+}
+
+fn header_mut(message: &mut SensorMessage) -> &mut Header {
+    match message {
+        SensorMessage::Imu(m) => &mut m.h,
+        SensorMessage::Magnetometer(m) => &mut m.h,
+        SensorMessage::Barometer(m) => &mut m.h,
+        SensorMessage::Gps(m) => &mut m.h,
+        SensorMessage::Rangefinder(m) => &mut m.h,
+        SensorMessage::GlobalPosition(m) => &mut m.h,
+        SensorMessage::LocalPosition(m) => &mut m.h,
+        SensorMessage::Attitude(m) => &mut m.h,
+        SensorMessage::Airspeed(m) => &mut m.h,
+        SensorMessage::VfrHud(m) => &mut m.h,
+        SensorMessage::Vibration(m) => &mut m.h,
+        SensorMessage::RcChannels(m) => &mut m.h,
+        SensorMessage::EscTelemetry(m) => &mut m.h,
+        SensorMessage::System(m) => &mut m.h,
+        SensorMessage::Altitude(m) => &mut m.h,
+        SensorMessage::GimbalAttitude(m) => &mut m.h,
+        SensorMessage::Measurements(m) => &mut m.h,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_row_round_trip() {
+        let columns: Vec<String> = HEADER_COLUMNS
+            .into_iter()
+            .chain([
+                "ax",
+                "ay",
+                "az",
+                "gx",
+                "gy",
+                "gz",
+                "precise.0",
+                "precise.1",
+                "precise.2",
+                "precise.3",
+                "precise.4",
+                "precise.5",
+                "temperature",
+            ])
+            .map(String::from)
+            .collect();
+        let line = "\"hub,1\",imu0,imu_link,7,1700000000000000000,5,false,false,0,0,1,,0.1,0,-9.81,0,0,0.5,0.1,0,-9.81,0,0,0.5,";
+        let message = from_csv_row("Imu", &columns, &split_csv(line)).unwrap();
+        let SensorMessage::Imu(imu) = message else {
+            panic!("not an IMU message");
+        };
+        assert_eq!(imu.h.device_id, "hub,1");
+        assert_eq!((imu.h.seq, imu.h.t_utc_ns), (7, 1_700_000_000_000_000_000));
+        assert_eq!((imu.az, imu.gz), (-9.81, 0.5));
+        assert_eq!(imu.precise.unwrap()[2], -9.81);
+        assert_eq!(imu.temperature, None);
+    }
+
+    #[test]
+    fn test_csv_dir_merged_in_time_order() {
+        let dir = std::env::temp_dir().join(format!("sensorhub-replay-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let header = HEADER_COLUMNS.join(",") + ",pressure,temperature,altitude";
+        let row =
+            |id: &str, t: u64| format!("hub,{},,1,{},0,false,false,0,0,1,,101325,20,0", id, t);
+        let write = |name: &str, rows: &[String]| {
+            fs::write(dir.join(name), format!("{}\n{}\n", header, rows.join("\n"))).unwrap();
+        };
+        write("baro0_barometer.csv", &[row("baro0", 10), row("baro0", 30)]);
+        write("baro0_barometer.1.csv", &[row("baro0", 40)]);
+        write("baro1_barometer.csv", &[row("baro1", 20), row("baro1", 50)]);
+
+        let mut reader = CsvDirReader::open(&dir).unwrap();
+        let mut order = Vec::new();
+        while let Some((t_ns, message)) = reader.next_message().unwrap() {
+            order.push((t_ns, message.header().sensor_id.clone()));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        let expected = [
+            (10, "baro0"),
+            (20, "baro1"),
+            (30, "baro0"),
+            (40, "baro0"),
+            (50, "baro1"),
+        ];
+        assert_eq!(order, expected.map(|(t, id)| (t, id.to_string())));
+    }
+}
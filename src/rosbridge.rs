@@ -0,0 +1,345 @@
+use crate::config::sensor_config::RosbridgeConfig;
+use crate::grpc_service::sensorhub::FrameTransform;
+use crate::grpc_service::SensorHubService;
+use crate::messages::{Header, SensorMessage};
+use futures_util::{Sink, SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::time::Duration;
+use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// sensor_msgs covariance for "not known"; a leading -1 marks the field as absent
+const UNKNOWN_COVARIANCE: [f64; 9] = [0.0; 9];
+const NO_ORIENTATION: [f64; 9] = [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+
+/// NavSatStatus.service bit for GPS
+const SERVICE_GPS: u16 = 1;
+
+/// ROS body frame (FLU) that sensor frames hang off on /tf_static
+const BASE_FRAME_ID: &str = "base_link";
+
+fn stamp(h: &Header) -> Value {
+    json!({
+        "stamp": {
+            "sec": h.t_utc_ns / 1_000_000_000,
+            "nanosec": h.t_utc_ns % 1_000_000_000,
+        },
+        "frame_id": h.frame_id,
+    })
+}
+
+/// FRD body axes (MAVLink, our streams) to FLU (ROS REP 103)
+fn flu(x: f32, y: f32, z: f32) -> Value {
+    json!({ "x": x as f64, "y": -y as f64, "z": -z as f64 })
+}
+
+/// ROS 2 message type and body for a sensor message, if it has a sensor_msgs equivalent
+fn to_ros(message: &SensorMessage) -> Option<(&'static str, &'static str, Value)> {
+    match message {
+        SensorMessage::Imu(imu) => Some((
+            "imu",
+            "sensor_msgs/msg/Imu",
+            json!({
+                "header": stamp(&imu.h),
+                "orientation": { "x": 0.0, "y": 0.0, "z": 0.0, "w": 1.0 },
+                "orientation_covariance": NO_ORIENTATION,
+                "angular_velocity": flu(imu.gx, imu.gy, imu.gz),
+                "angular_velocity_covariance": UNKNOWN_COVARIANCE,
+                "linear_acceleration": flu(imu.ax, imu.ay, imu.az),
+                "linear_acceleration_covariance": UNKNOWN_COVARIANCE,
+            }),
+        )),
+        SensorMessage::Magnetometer(mag) => Some((
+            "mag",
+            "sensor_msgs/msg/MagneticField",
+            json!({
+                "header": stamp(&mag.h),
+                // μT to T
+                "magnetic_field": flu(mag.mx * 1e-6, mag.my * 1e-6, mag.mz * 1e-6),
+                "magnetic_field_covariance": UNKNOWN_COVARIANCE,
+            }),
+        )),
+        SensorMessage::Barometer(baro) => Some((
+            "pressure",
+            "sensor_msgs/msg/FluidPressure",
+            json!({
+                "header": stamp(&baro.h),
+                "fluid_pressure": baro.pressure as f64,
+                "variance": 0.0,
+            }),
+        )),
+        SensorMessage::Gps(gps) => {
+            // NavSatStatus: -1 no fix, 0 fix, 1 SBAS/DGPS, 2 GBAS/RTK
+            let status = match gps.fix_type {
+                0 | 1 => -1,
+                2 | 3 => 0,
+                4 => 1,
+                _ => 2,
+            };
+            // Receivers report no constellation mask, so a fix counts as GPS and no fix as none
+            let service = if status < 0 { 0 } else { SERVICE_GPS };
+            Some((
+                "fix",
+                "sensor_msgs/msg/NavSatFix",
+                json!({
+                    "header": stamp(&gps.h),
+                    "status": { "status": status, "service": service },
+                    "latitude": gps.latitude,
+                    "longitude": gps.longitude,
+                    "altitude": gps.altitude as f64,
+                    "position_covariance": UNKNOWN_COVARIANCE,
+                    "position_covariance_type": 0,
+                }),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// tf2_msgs/TFMessage placing each mounted sensor's frame on the vehicle
+///
+/// Published data is already rotated into body axes, so every transform is a translation.
+fn tf_static(transforms: &[FrameTransform], t_utc_ns: u64) -> Value {
+    let transforms: Vec<Value> = transforms
+        .iter()
+        .map(|t| {
+            let p = t.translation.unwrap_or_default();
+            json!({
+                "header": {
+                    "stamp": {
+                        "sec": t_utc_ns / 1_000_000_000,
+                        "nanosec": t_utc_ns % 1_000_000_000,
+                    },
+                    "frame_id": BASE_FRAME_ID,
+                },
+                "child_frame_id": t.child_frame_id,
+                "transform": {
+                    "translation": flu(p.x_m, p.y_m, p.z_m),
+                    "rotation": { "x": 0.0, "y": 0.0, "z": 0.0, "w": 1.0 },
+                },
+            })
+        })
+        .collect();
+    json!({ "transforms": transforms })
+}
+
+/// Topic of a sensor's messages, e.g. `/sensorhub/fc_imu0/imu`
+fn topic(prefix: &str, sensor_id: &str, suffix: &str) -> String {
+    format!("{}/{}/{}", prefix.trim_end_matches('/'), sensor_id, suffix)
+}
+
+/// Publish IMU, magnetometer, barometer and GPS data to a rosbridge_server, if enabled
+pub fn spawn_client(config: RosbridgeConfig, grpc_service: &SensorHubService) {
+    if !config.enabled {
+        return;
+    }
+    info!(
+        "[rosbridge] Publishing to rosbridge at {} under {}",
+        config.address, config.topic_prefix
+    );
+    // Mountings are fixed at startup, so the transforms are sent as they are now
+    let transforms = grpc_service.transforms();
+    let tf = (config.tf_static && !transforms.is_empty()).then(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Arc::new(tf_static(&transforms, now))
+    });
+    let rx = grpc_service.subscribe_messages();
+    tokio::spawn(run(Arc::new(config), tf, rx));
+}
+
+async fn run(
+    config: Arc<RosbridgeConfig>,
+    tf: Option<Arc<Value>>,
+    mut rx: broadcast::Receiver<SensorMessage>,
+) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match connect(&config.address).await {
+            Ok(stream) => {
+                info!("[rosbridge] Connected to {}", config.address);
+                backoff = Duration::from_secs(1);
+                rx = rx.resubscribe();
+                let e = forward(&config, tf.as_deref(), stream, &mut rx).await;
+                warn!("[rosbridge] Connection to {} lost: {}", config.address, e);
+            }
+            Err(e) => warn!(
+                "[rosbridge] Cannot connect to {} ({}), retrying in {}s",
+                config.address,
+                e,
+                backoff.as_secs()
+            ),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+type Connection = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Open the WebSocket to rosbridge_server
+async fn connect(address: &str) -> Result<Connection, Error> {
+    let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{}", address)).await?;
+    Ok(ws)
+}
+
+async fn send(
+    writer: &mut (impl Sink<Message, Error = Error> + Unpin),
+    op: &Value,
+) -> Result<(), Error> {
+    writer.send(Message::Text(op.to_string())).await
+}
+
+async fn forward(
+    config: &RosbridgeConfig,
+    tf: Option<&Value>,
+    ws: Connection,
+    rx: &mut broadcast::Receiver<SensorMessage>,
+) -> Error {
+    let (mut writer, mut reader) = ws.split();
+    if let Some(tf) = tf {
+        // Latched (transient local), so nodes started later still get the frames
+        let advertise = json!({
+            "op": "advertise",
+            "topic": "/tf_static",
+            "type": "tf2_msgs/msg/TFMessage",
+            "latch": true,
+        });
+        let publish = json!({ "op": "publish", "topic": "/tf_static", "msg": tf });
+        for op in [advertise, publish] {
+            if let Err(e) = send(&mut writer, &op).await {
+                return e;
+            }
+        }
+    }
+    // Topics advertised on this connection
+    let mut advertised = HashSet::new();
+
+    loop {
+        tokio::select! {
+            // Status messages from rosbridge are not used
+            frame = reader.next() => match frame {
+                Some(Ok(Message::Close(_))) | None => return Error::ConnectionClosed,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return e,
+            },
+            message = rx.recv() => match message {
+                Ok(message) => {
+                    let Some((suffix, msg_type, msg)) = to_ros(&message) else {
+                        continue;
+                    };
+                    let topic = topic(&config.topic_prefix, &message.header().sensor_id, suffix);
+                    if !advertised.contains(&topic) {
+                        let advertise = json!({ "op": "advertise", "topic": topic, "type": msg_type });
+                        if let Err(e) = send(&mut writer, &advertise).await {
+                            return e;
+                        }
+                        debug!("[rosbridge] Advertised {} ({})", topic, msg_type);
+                        advertised.insert(topic.clone());
+                    }
+                    let publish = json!({ "op": "publish", "topic": topic, "msg": msg });
+                    if let Err(e) = send(&mut writer, &publish).await {
+                        return e;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("[rosbridge] Fell behind, {} message(s) skipped", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    error!("[rosbridge] Sensor data channel closed");
+                    let _ = writer.send(Message::Close(None)).await;
+                    return Error::ConnectionClosed;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{GpsMessage, ImuMessage};
+
+    #[test]
+    fn test_imu_to_flu() {
+        let mut h = Header::new("hub".into(), "fc_imu0".into(), "base_link".into(), 1);
+        h.t_utc_ns = 1_700_000_000_250_000_000;
+        let imu = SensorMessage::Imu(ImuMessage {
+            h,
+            ax: 0.1,
+            ay: 0.2,
+            az: -9.81,
+            gx: 0.0,
+            gy: 0.5,
+            gz: 0.0,
+            precise: None,
+            temperature: None,
+        });
+        let (suffix, msg_type, msg) = to_ros(&imu).unwrap();
+        assert_eq!((suffix, msg_type), ("imu", "sensor_msgs/msg/Imu"));
+        assert_eq!(msg["header"]["stamp"]["sec"], 1_700_000_000u64);
+        assert_eq!(msg["header"]["stamp"]["nanosec"], 250_000_000u64);
+        assert_eq!(
+            msg["linear_acceleration"]["z"].as_f64().unwrap() as f32,
+            9.81
+        );
+        assert_eq!(msg["angular_velocity"]["y"].as_f64().unwrap() as f32, -0.5);
+        assert_eq!(msg["orientation_covariance"][0], -1.0);
+        assert_eq!(
+            topic("/sensorhub/", "fc_imu0", "imu"),
+            "/sensorhub/fc_imu0/imu"
+        );
+
+        let gps = |fix_type| {
+            SensorMessage::Gps(GpsMessage {
+                h: Header::new("hub".into(), "fc_gps0".into(), "fc_gps0".into(), 1),
+                fix_type,
+                satellites_visible: None,
+                latitude: 47.0,
+                longitude: 8.0,
+                altitude: 400.0,
+                hdop: None,
+                vdop: None,
+                ground_speed: None,
+                course: None,
+                yaw: None,
+                yaw_accuracy: None,
+            })
+        };
+        let (_, _, fix) = to_ros(&gps(6)).unwrap();
+        assert_eq!(fix["status"], json!({ "status": 2, "service": 1 }));
+        let (_, _, no_fix) = to_ros(&gps(1)).unwrap();
+        assert_eq!(no_fix["status"], json!({ "status": -1, "service": 0 }));
+
+        let tf = tf_static(
+            &[FrameTransform {
+                sensor_id: "fc_baro0".into(),
+                child_frame_id: "fc_baro0".into(),
+                translation: Some(crate::grpc_service::sensorhub::SensorMounting {
+                    x_m: 0.1,
+                    y_m: 0.03,
+                    z_m: -0.04,
+                }),
+                ..Default::default()
+            }],
+            1_700_000_000_250_000_000,
+        );
+        let transform = &tf["transforms"][0];
+        assert_eq!(transform["header"]["frame_id"], "base_link");
+        assert_eq!(transform["child_frame_id"], "fc_baro0");
+        assert_eq!(
+            transform["transform"]["translation"]["z"].as_f64().unwrap() as f32,
+            0.04
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use crate::config::sensor_config::GrpcConfig;
+use crate::grpc_service::sensorhub;
+use crate::metrics::{Metrics, RPC_ERRORS, RPC_LATENCY, RPC_REQUESTS};
+use crate::schema;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio_stream::{Stream, StreamExt};
+use tonic::body::BoxBody;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Margin by which our deadline precedes tonic's own `grpc-timeout` handling
+///
+/// tonic answers an expired deadline with CANCELLED; firing first lets the client see
+/// DEADLINE_EXCEEDED instead.
+const DEADLINE_MARGIN: Duration = Duration::from_millis(1);
+
+/// Parse a `grpc-timeout` header value (e.g. "250m", "5S")
+fn parse_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = amount.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(amount * 3600),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    })
+}
+
+/// Status of a response that ended the call before any message (trailers-only)
+fn response_code(response: &http::Response<BoxBody>) -> Code {
+    response
+        .headers()
+        .get("grpc-status")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i32>().ok())
+        .map_or(Code::Ok, Code::from_i32)
+}
+
+/// Call, latency and error counters of one RPC
+struct RpcCounters {
+    requests: Arc<AtomicU64>,
+    latency: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+}
+
+/// Tower layer enforcing client deadlines and counting calls, errors and latency per RPC
+#[derive(Clone)]
+pub struct RpcLayer {
+    metrics: Arc<Metrics>,
+    /// Counters of the hub's and the health service's RPCs, resolved up front
+    methods: Arc<HashMap<String, RpcCounters>>,
+}
+
+impl RpcLayer {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        let methods = [
+            sensorhub::FILE_DESCRIPTOR_SET,
+            tonic_health::pb::FILE_DESCRIPTOR_SET,
+        ]
+        .into_iter()
+        .flat_map(|set| schema::rpc_names(set).expect("build.rs emits valid descriptor sets"))
+        .map(|method| {
+            let counters = RpcCounters {
+                requests: metrics.counter(RPC_REQUESTS, &method),
+                latency: metrics.counter(RPC_LATENCY, &method),
+                errors: metrics.counter(RPC_ERRORS, &method),
+            };
+            (method, counters)
+        })
+        .collect();
+        Self {
+            metrics,
+            methods: Arc::new(methods),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcLayer {
+    type Service = RpcService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcService {
+            inner,
+            metrics: self.metrics.clone(),
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcService<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+    methods: Arc<HashMap<String, RpcCounters>>,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for RpcService<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let method = request.uri().path().trim_start_matches('/').to_string();
+        let deadline = request
+            .headers()
+            .get("grpc-timeout")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_timeout);
+        let metrics = self.metrics.clone();
+        let methods = self.methods.clone();
+        let call = self.inner.call(request);
+
+        Box::pin(async move {
+            let started = Instant::now();
+            // Streams return as soon as they are set up, so the deadline bounds unary calls
+            // and stream setup only
+            let result = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout(deadline.saturating_sub(DEADLINE_MARGIN), call).await
+                    {
+                        Ok(result) => result,
+                        Err(_) => Ok(Status::deadline_exceeded(format!(
+                            "{} did not complete within the client deadline of {} ms",
+                            method,
+                            deadline.as_millis()
+                        ))
+                        .into_http()),
+                    }
+                }
+                None => call.await,
+            };
+
+            let code = match &result {
+                Ok(response) => response_code(response),
+                Err(_) => Code::Internal,
+            };
+            let latency = started.elapsed().as_micros() as u64;
+            if let Some(counters) = methods.get(&method) {
+                counters.requests.fetch_add(1, Ordering::Relaxed);
+                counters.latency.fetch_add(latency, Ordering::Relaxed);
+                if code != Code::Ok {
+                    counters.errors.fetch_add(1, Ordering::Relaxed);
+                }
+            } else {
+                // Reflection calls, and probes for arbitrary paths, which are kept from
+                // growing the label set
+                let label = if code == Code::Unimplemented {
+                    "unknown"
+                } else {
+                    &method
+                };
+                metrics.add(RPC_REQUESTS, label, 1);
+                metrics.add(RPC_LATENCY, label, latency);
+                if code != Code::Ok {
+                    metrics.add(RPC_ERRORS, label, 1);
+                }
+            }
+            result
+        })
+    }
+}
+
+/// Cap on concurrent client streams, shared by all streaming RPCs
+pub struct StreamLimit {
+    open: Arc<AtomicUsize>,
+    max: AtomicUsize,
+    retry_after_ms: AtomicU64,
+}
+
+impl StreamLimit {
+    pub fn new() -> Self {
+        let defaults = GrpcConfig::default();
+        Self {
+            open: Arc::new(AtomicUsize::new(0)),
+            max: AtomicUsize::new(defaults.max_streams),
+            retry_after_ms: AtomicU64::new(defaults.retry_after_ms),
+        }
+    }
+
+    pub fn configure(&self, config: &GrpcConfig) {
+        self.max.store(config.max_streams, Ordering::Relaxed);
+        self.retry_after_ms
+            .store(config.retry_after_ms, Ordering::Relaxed);
+    }
+
+    /// Reserve a stream slot, or RESOURCE_EXHAUSTED with a retry pushback when all are taken
+    // tonic::Status is what the streaming RPCs return, so it is handed back as is
+    #[allow(clippy::result_large_err)]
+    pub fn admit(&self) -> Result<StreamPermit, Status> {
+        let max = self.max.load(Ordering::Relaxed);
+        let admitted = self
+            .open
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |open| {
+                (open < max).then_some(open + 1)
+            });
+        match admitted {
+            Ok(_) => Ok(StreamPermit {
+                open: self.open.clone(),
+            }),
+            Err(open) => {
+                let retry_after_ms = self.retry_after_ms.load(Ordering::Relaxed);
+                let mut status = Status::resource_exhausted(format!(
+                    "All {} client streams are in use, retry in {} ms",
+                    open, retry_after_ms
+                ));
+                // Standard gRPC retry pushback, honoured by clients with a retry policy
+                status.metadata_mut().insert(
+                    "grpc-retry-pushback-ms",
+                    MetadataValue::from(retry_after_ms),
+                );
+                Err(status)
+            }
+        }
+    }
+}
+
+impl Default for StreamLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One open client stream; the slot is released when the stream is dropped
+pub struct StreamPermit {
+    open: Arc<AtomicUsize>,
+}
+
+impl StreamPermit {
+    /// Tie the permit to the lifetime of `stream`
+    pub fn hold<S: Stream>(self, stream: S) -> impl Stream<Item = S::Item> {
+        stream.map(move |item| {
+            let _ = &self;
+            item
+        })
+    }
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_and_stream_limit() {
+        assert_eq!(parse_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_timeout("5S"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_timeout("10x"), None);
+        assert_eq!(parse_timeout("1234567890m"), None);
+
+        let limit = StreamLimit::new();
+        limit.configure(&GrpcConfig {
+            max_streams: 1,
+            retry_after_ms: 500,
+        });
+        let permit = limit.admit().unwrap();
+        let rejected = limit.admit().err().unwrap();
+        assert_eq!(rejected.code(), Code::ResourceExhausted);
+        assert_eq!(
+            rejected.metadata().get("grpc-retry-pushback-ms").unwrap(),
+            "500"
+        );
+        drop(permit);
+        assert!(limit.admit().is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+use crate::config::sensor_config::SafetyConfig;
+use mavlink::common::{MavAutopilot, MavModeFlag, HEARTBEAT_DATA};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Vehicle arming state as reported by the flight controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArmingState {
+    /// No recent HEARTBEAT from an autopilot
+    Unknown,
+    Disarmed,
+    Armed,
+}
+
+#[derive(Default)]
+struct State {
+    enabled: bool,
+    block_when_unknown: bool,
+    heartbeat_timeout: Duration,
+    /// Armed flag of the last autopilot HEARTBEAT and when it arrived
+    last: Option<(bool, Instant)>,
+}
+
+/// Refuses disruptive control RPCs while the vehicle is armed
+#[derive(Default)]
+pub struct Interlock {
+    state: Mutex<State>,
+}
+
+impl Interlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the `[safety]` settings
+    pub fn configure(&self, config: &SafetyConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.enabled = config.enabled;
+        state.block_when_unknown = config.block_when_unknown;
+        state.heartbeat_timeout = Duration::from_millis(config.heartbeat_timeout_ms);
+    }
+
+    /// Track the arming flag of a HEARTBEAT (GCS and companion heartbeats are ignored)
+    pub fn observe(&self, heartbeat: &HEARTBEAT_DATA) {
+        if heartbeat.autopilot == MavAutopilot::MAV_AUTOPILOT_INVALID {
+            return;
+        }
+        let armed = heartbeat
+            .base_mode
+            .contains(MavModeFlag::MAV_MODE_FLAG_SAFETY_ARMED);
+        self.update(armed, Instant::now());
+    }
+
+    fn update(&self, armed: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if state.last.map(|(was_armed, _)| was_armed) != Some(armed) {
+            info!(
+                "[safety] Vehicle {}",
+                if armed { "armed" } else { "disarmed" }
+            );
+        }
+        state.last = Some((armed, now));
+    }
+
+    fn state_at(&self, now: Instant) -> ArmingState {
+        let state = self.state.lock().unwrap();
+        match state.last {
+            Some((armed, at)) if now.duration_since(at) < state.heartbeat_timeout => {
+                if armed {
+                    ArmingState::Armed
+                } else {
+                    ArmingState::Disarmed
+                }
+            }
+            _ => ArmingState::Unknown,
+        }
+    }
+
+    pub fn state(&self) -> ArmingState {
+        self.state_at(Instant::now())
+    }
+
+    /// Ok if `action` may run now, else the reason it is refused
+    pub fn check(&self, action: &str) -> Result<(), String> {
+        let (enabled, block_when_unknown) = {
+            let state = self.state.lock().unwrap();
+            (state.enabled, state.block_when_unknown)
+        };
+        if !enabled {
+            return Ok(());
+        }
+        let refused = match self.state() {
+            ArmingState::Armed => "the vehicle is armed",
+            ArmingState::Unknown if block_when_unknown => {
+                "the arming state is unknown (no autopilot HEARTBEAT)"
+            }
+            _ => return Ok(()),
+        };
+        warn!("[safety] Refused {}: {}", action, refused);
+        Err(format!("{} refused: {}, disarm first", action, refused))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interlock_follows_heartbeats() {
+        let interlock = Interlock::new();
+        let mut config = SafetyConfig::default();
+        interlock.configure(&config);
+        assert_eq!(interlock.state(), ArmingState::Unknown);
+        assert!(interlock.check("ZeroAirspeed").is_ok());
+
+        let start = Instant::now();
+        interlock.update(true, start);
+        assert_eq!(interlock.state_at(start), ArmingState::Armed);
+        assert!(interlock
+            .check("ZeroAirspeed")
+            .unwrap_err()
+            .contains("armed"));
+
+        interlock.update(false, start);
+        assert!(interlock.check("ZeroAirspeed").is_ok());
+        // Heartbeats stopped
+        assert_eq!(
+            interlock.state_at(start + Duration::from_secs(10)),
+            ArmingState::Unknown
+        );
+
+        interlock.update(true, start);
+        config.enabled = false;
+        interlock.configure(&config);
+        assert!(interlock.check("ZeroAirspeed").is_ok());
+    }
+}
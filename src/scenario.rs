@@ -0,0 +1,221 @@
+use crate::config::sensor_config::{FaultEntry, FaultKind, ScenarioConfig};
+use crate::messages::{ImuMessage, SensorMessage};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+struct ScenarioState {
+    /// Scenario clock origin (when the scenario was loaded)
+    start: Instant,
+    faults: Vec<FaultEntry>,
+    /// Indices of faults currently in effect, for start/end logging
+    active: HashSet<usize>,
+    /// (fault index, field) pairs already reported as not applicable
+    unknown_fields: HashSet<(usize, String)>,
+}
+
+/// Injects scheduled degradations into published data (simulation and replay runs only)
+pub struct ScenarioEngine {
+    /// Whether a scenario is loaded, so `apply` skips the lock when none is
+    enabled: AtomicBool,
+    state: Mutex<Option<ScenarioState>>,
+}
+
+impl ScenarioEngine {
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Apply the `[scenario]` settings; the scenario clock starts now
+    pub fn configure(&self, config: &ScenarioConfig) {
+        let mut state = self.state.lock().unwrap();
+        if !config.enabled || config.faults.is_empty() {
+            *state = None;
+            self.enabled.store(false, Ordering::Relaxed);
+            return;
+        }
+        warn!(
+            "[scenario] Injecting {} simulated fault(s) into published data - not for flight",
+            config.faults.len()
+        );
+        for fault in config.faults.iter() {
+            info!(
+                "[scenario] {:?} on {} at t+{}s{}",
+                fault.kind,
+                fault.sensor,
+                fault.at_s,
+                fault
+                    .duration_s
+                    .map_or(String::new(), |d| format!(" for {}s", d))
+            );
+        }
+        *state = Some(ScenarioState {
+            start: Instant::now(),
+            faults: config.faults.clone(),
+            active: HashSet::new(),
+            unknown_fields: HashSet::new(),
+        });
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Pass a message through the faults active now; None means it is dropped
+    pub fn apply(&self, message: SensorMessage) -> Option<SensorMessage> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return Some(message);
+        }
+        let mut guard = self.state.lock().unwrap();
+        let Some(state) = guard.as_mut() else {
+            return Some(message);
+        };
+        let elapsed = state.start.elapsed();
+        apply_faults(state, elapsed, message)
+    }
+}
+
+impl Default for ScenarioEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn apply_faults(
+    state: &mut ScenarioState,
+    elapsed: Duration,
+    mut message: SensorMessage,
+) -> Option<SensorMessage> {
+    let t = elapsed.as_secs_f64();
+    for (index, fault) in state.faults.iter().enumerate() {
+        if fault.sensor != message.header().sensor_id {
+            continue;
+        }
+        let in_window = t >= fault.at_s && fault.duration_s.is_none_or(|d| t < fault.at_s + d);
+        if in_window != state.active.contains(&index) {
+            if in_window {
+                state.active.insert(index);
+                warn!("[scenario] {:?} on {} started", fault.kind, fault.sensor);
+            } else {
+                state.active.remove(&index);
+                info!("[scenario] {:?} on {} ended", fault.kind, fault.sensor);
+            }
+        }
+        if !in_window {
+            continue;
+        }
+
+        match fault.kind {
+            FaultKind::Dropout => return None,
+            FaultKind::Bias => {
+                for (field, offset) in fault.offsets.iter() {
+                    if !offset_field(&mut message, field, *offset)
+                        && state.unknown_fields.insert((index, field.clone()))
+                    {
+                        warn!(
+                            "[scenario] {} has no field '{}' to bias",
+                            fault.sensor, field
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Some(message)
+}
+
+/// Add `offset` to a named measurement field; false if the message has no such field
+fn offset_field(message: &mut SensorMessage, field: &str, offset: f64) -> bool {
+    // Geodetic fields are f64 and take degrees directly
+    let geodetic = match (&mut *message, field) {
+        (SensorMessage::Gps(m), "latitude") => Some(&mut m.latitude),
+        (SensorMessage::Gps(m), "longitude") => Some(&mut m.longitude),
+        (SensorMessage::GlobalPosition(m), "latitude") => Some(&mut m.latitude),
+        (SensorMessage::GlobalPosition(m), "longitude") => Some(&mut m.longitude),
+        _ => None,
+    };
+    if let Some(value) = geodetic {
+        *value += offset;
+        return true;
+    }
+    if let SensorMessage::Imu(ImuMessage {
+        precise: Some(precise),
+        ..
+    }) = message
+    {
+        if let Some(axis) = ["ax", "ay", "az", "gx", "gy", "gz"]
+            .iter()
+            .position(|&a| a == field)
+        {
+            precise[axis] += offset;
+        }
+    }
+
+    let Some(value) = message.field_mut(field) else {
+        return false;
+    };
+    *value += offset as f32;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Header;
+
+    fn imu(sensor_id: &str) -> SensorMessage {
+        SensorMessage::Imu(ImuMessage {
+            h: Header::new(
+                "hub".to_string(),
+                sensor_id.to_string(),
+                "imu".to_string(),
+                0,
+            ),
+            ax: 0.0,
+            ay: 0.0,
+            az: -9.81,
+            gx: 0.0,
+            gy: 0.0,
+            gz: 0.0,
+            precise: None,
+            temperature: None,
+        })
+    }
+
+    #[test]
+    fn test_dropout_window_and_bias_step() {
+        let config: ScenarioConfig = toml::from_str(
+            r#"
+            enabled = true
+            [[fault]]
+            sensor = "imu0"
+            kind = "dropout"
+            at_s = 120
+            duration_s = 30
+            [[fault]]
+            sensor = "imu0"
+            kind = "bias"
+            at_s = 60
+            offsets = { gz = 0.05 }
+            "#,
+        )
+        .unwrap();
+        let mut state = ScenarioState {
+            start: Instant::now(),
+            faults: config.faults,
+            active: HashSet::new(),
+            unknown_fields: HashSet::new(),
+        };
+        let at = |s: u64| Duration::from_secs(s);
+
+        let before = apply_faults(&mut state, at(10), imu("imu0")).unwrap();
+        assert!(matches!(before, SensorMessage::Imu(m) if m.gz == 0.0));
+        let biased = apply_faults(&mut state, at(60), imu("imu0")).unwrap();
+        assert!(matches!(biased, SensorMessage::Imu(m) if m.gz == 0.05));
+        assert!(apply_faults(&mut state, at(130), imu("imu0")).is_none());
+        assert!(apply_faults(&mut state, at(130), imu("imu1")).is_some());
+        assert!(apply_faults(&mut state, at(150), imu("imu0")).is_some());
+    }
+}
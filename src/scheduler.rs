@@ -1,145 +1,869 @@
+use crate::bus::gpio::{edge_age, DataReady};
 use crate::bus::i2c::I2CBus;
-use crate::config::sensor_config::SensorConfig;
+use crate::clock::Stamp;
+use crate::config::sensor_config::{RecoveryConfig, SensorConfig, SyncConfig};
+use crate::errors::{SensorError, SensorResult};
 use crate::grpc_service::SensorHubService;
-use crate::messages::{BarometerMessage, Header, ImuMessage, MagnetometerMessage, SensorMessage};
-use crate::sensors::SensorDriver;
+use crate::messages::Header;
+use crate::metrics::{Metrics, SENSOR_READ_ERRORS, SENSOR_REINITS};
+use crate::sensors::{frame_to_messages, RegisterWindow, SensorDataFrame, SensorDriver};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::time::{sleep, Duration};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
 use tracing::{error, info, warn};
 
+/// Largest SMBus block read
+const MAX_BLOCK_READ: usize = 32;
+
+/// Periods without a data-ready edge before a sensor is read on its timer anyway
+const DRDY_TIMEOUT_PERIODS: u32 = 4;
+
+/// Runtime change to one polled sensor's task
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskCommand {
+    /// Stop sampling; the driver is kept so the sensor can be started again
+    Stop,
+    /// Resume sampling after Stop
+    Start,
+    /// Re-run the driver's init() and resume sampling
+    Restart,
+    /// Sample at a new rate (Hz)
+    SetFrequency(u32),
+}
+
+/// A command on its way to the task of `sensor_id`
+struct Control {
+    sensor_id: String,
+    command: TaskCommand,
+    done: oneshot::Sender<SensorResult<()>>,
+}
+
+/// Handles of the polled sensor tasks, for controlling them at runtime and shutting down
+pub struct SensorTaskManager {
+    /// Control channel of each sensor's task (sensors with coalesced reads share one)
+    controls: std::sync::RwLock<HashMap<String, mpsc::Sender<Control>>>,
+    handles: std::sync::Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl SensorTaskManager {
+    fn new() -> Self {
+        Self {
+            controls: std::sync::RwLock::new(HashMap::new()),
+            handles: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Start a task serving `sensor_ids`, handing it the receiving end of their control channel
+    fn spawn<F>(&self, sensor_ids: Vec<String>, task: impl FnOnce(mpsc::Receiver<Control>) -> F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(4);
+        let mut controls = self.controls.write().unwrap();
+        for sensor_id in sensor_ids {
+            controls.insert(sensor_id, tx.clone());
+        }
+        self.handles.lock().unwrap().push(tokio::spawn(task(rx)));
+    }
+
+    /// Polled sensors with a task
+    pub fn sensor_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.controls.read().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn contains(&self, sensor_id: &str) -> bool {
+        self.controls.read().unwrap().contains_key(sensor_id)
+    }
+
+    /// Apply `command` to the task of `sensor_id` between two of its reads
+    pub async fn command(&self, sensor_id: &str, command: TaskCommand) -> SensorResult<()> {
+        let no_task = || SensorError::ConfigError {
+            sensor: sensor_id.to_string(),
+            reason: "no sampling task (push-based or shut down)".to_string(),
+        };
+        let control = self
+            .controls
+            .read()
+            .unwrap()
+            .get(sensor_id)
+            .cloned()
+            .ok_or_else(no_task)?;
+        let (done, result) = oneshot::channel();
+        control
+            .send(Control {
+                sensor_id: sensor_id.to_string(),
+                command,
+                done,
+            })
+            .await
+            .map_err(|_| no_task())?;
+        result.await.map_err(|_| no_task())?
+    }
+
+    /// End every sensor task after its current read and wait for them to finish
+    pub async fn shutdown(&self) {
+        // A task exits once its control channel is closed
+        self.controls.write().unwrap().clear();
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        for handle in handles {
+            if let Err(e) = handle.await {
+                error!("[scheduler] Sensor task failed: {}", e);
+            }
+        }
+        info!("[scheduler] All sensor tasks stopped");
+    }
+}
+
+/// A polled sensor and its publishing state
+struct Polled {
+    sensor: Box<dyn SensorDriver>,
+    sensor_id: String,
+    frequency: u32,
+    period: Duration,
+    /// Exact period for epoch-aligned ticks
+    period_ns: u64,
+    sequence_counter: u64,
+    recovery: Recovery,
+    read_errors: Arc<AtomicU64>,
+    reinits: Arc<AtomicU64>,
+    /// False while stopped through the task manager
+    running: bool,
+    /// Data-ready line the sensor is read on instead of its timer
+    drdy: Option<DataReady>,
+}
+
+impl Polled {
+    fn new(
+        sensor: Box<dyn SensorDriver>,
+        frequency: u32,
+        recovery: &RecoveryConfig,
+        metrics: &Metrics,
+    ) -> Self {
+        let mut polled = Self {
+            sensor_id: sensor.id().to_string(),
+            read_errors: metrics.counter(SENSOR_READ_ERRORS, sensor.id()),
+            reinits: metrics.counter(SENSOR_REINITS, sensor.id()),
+            sensor,
+            frequency,
+            period: Duration::ZERO,
+            period_ns: 0,
+            sequence_counter: 0,
+            recovery: Recovery::new(recovery),
+            running: true,
+            drdy: None,
+        };
+        polled.set_frequency(frequency);
+        polled
+    }
+
+    fn set_frequency(&mut self, frequency: u32) {
+        self.frequency = frequency;
+        self.period = Duration::from_millis((1000.0 / frequency as f32) as u64);
+        self.period_ns = 1_000_000_000 / u64::from(frequency.max(1));
+    }
+
+    /// Carry out a task manager command and report the outcome to its sender
+    async fn control(
+        &mut self,
+        control: Control,
+        bus: &Mutex<I2CBus>,
+        grpc_service: &SensorHubService,
+    ) {
+        let result = match control.command {
+            TaskCommand::Stop => {
+                info!("[{}] Sampling stopped", self.sensor_id);
+                self.running = false;
+                Ok(())
+            }
+            TaskCommand::Start => {
+                info!("[{}] Sampling resumed", self.sensor_id);
+                self.running = true;
+                Ok(())
+            }
+            TaskCommand::Restart => {
+                info!("[{}] Restarting", self.sensor_id);
+                let result = self.init(bus, grpc_service).await;
+                self.recovery.init_done(result.is_ok(), Instant::now());
+                self.running = true;
+                result
+            }
+            TaskCommand::SetFrequency(0) => Err(SensorError::ConfigError {
+                sensor: self.sensor_id.clone(),
+                reason: "frequency must be positive".to_string(),
+            }),
+            TaskCommand::SetFrequency(frequency) => {
+                info!(
+                    "[{}] Sampling at {}Hz (was {}Hz)",
+                    self.sensor_id, frequency, self.frequency
+                );
+                self.set_frequency(frequency);
+                grpc_service.sensor_rate_changed(&self.sensor_id);
+                Ok(())
+            }
+        };
+        let _ = control.done.send(result);
+    }
+
+    /// Publish a successful read, stamped with its sync tick if any, or count a failed one
+    async fn publish(
+        &mut self,
+        result: crate::errors::SensorResult<SensorDataFrame>,
+        tick_utc_ns: Option<u64>,
+        grpc_service: &SensorHubService,
+    ) {
+        match result {
+            Ok(frame) => {
+                self.recovery.read_ok();
+                self.sequence_counter += 1;
+
+                // Create header with timing metadata
+                let header = Header::at(
+                    "navigate_hub".to_string(),
+                    self.sensor_id.clone(),
+                    "sensor_frame".to_string(),
+                    self.sequence_counter,
+                    tick_utc_ns.map_or_else(Stamp::now, Stamp::at_utc),
+                );
+
+                // Convert SensorDataFrame to appropriate message types based on data present
+                let messages = frame_to_messages(frame, header, &self.sensor_id);
+
+                // Publish all messages to gRPC service
+                for msg in messages {
+                    if let Err(e) = grpc_service.publish(msg).await {
+                        error!("[{}] Failed to publish: {}", self.sensor_id, e);
+                    }
+                }
+            }
+            Err(e) => {
+                self.recovery.read_failed();
+                self.read_errors.fetch_add(1, Ordering::Relaxed);
+                warn!("[{}] Sensor read error: {}", self.sensor_id, e);
+            }
+        }
+    }
+
+    /// Re-run the driver's init() once its reads have kept failing, e.g. after a brown-out
+    /// reset the chip to its power-on configuration
+    async fn recover(&mut self, bus: &Mutex<I2CBus>, grpc_service: &SensorHubService) {
+        let now = Instant::now();
+        if !self.recovery.due(now) {
+            return;
+        }
+        warn!(
+            "[{}] {} consecutive read errors, re-initializing",
+            self.sensor_id, self.recovery.consecutive_errors
+        );
+        let result = self.init(bus, grpc_service).await;
+        if let Err(e) = &result {
+            warn!(
+                "[{}] Re-initialization failed: {}, retrying in {} ms",
+                self.sensor_id,
+                e,
+                self.recovery.backoff.as_millis()
+            );
+        }
+        self.recovery.init_done(result.is_ok(), Instant::now());
+    }
+
+    /// Run the driver's init() again, counted in the metrics and init_duration_ms
+    async fn init(
+        &mut self,
+        bus: &Mutex<I2CBus>,
+        grpc_service: &SensorHubService,
+    ) -> SensorResult<()> {
+        let started = Instant::now();
+        let result = {
+            let mut bus_lock = bus.lock().await;
+            self.sensor.init(&mut bus_lock).await
+        };
+        let elapsed = started.elapsed();
+        if result.is_ok() {
+            info!(
+                "[{}] Re-initialized in {} ms",
+                self.sensor_id,
+                elapsed.as_millis()
+            );
+            self.reinits.fetch_add(1, Ordering::Relaxed);
+            grpc_service
+                .set_init_duration(&self.sensor_id, elapsed)
+                .await;
+        }
+        result
+    }
+}
+
+/// Consecutive read failures of a polled sensor and the backoff between its re-inits
+struct Recovery {
+    config: RecoveryConfig,
+    consecutive_errors: u32,
+    backoff: Duration,
+    /// Earliest time of the next init() attempt
+    retry_at: Instant,
+}
+
+impl Recovery {
+    fn new(config: &RecoveryConfig) -> Self {
+        Self {
+            config: config.clone(),
+            consecutive_errors: 0,
+            backoff: Duration::from_millis(config.backoff_ms),
+            retry_at: Instant::now(),
+        }
+    }
+
+    fn read_ok(&mut self) {
+        self.consecutive_errors = 0;
+        self.backoff = Duration::from_millis(self.config.backoff_ms);
+    }
+
+    fn read_failed(&mut self) {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+    }
+
+    fn due(&self, now: Instant) -> bool {
+        self.config.enabled
+            && self.consecutive_errors >= self.config.error_threshold.max(1)
+            && now >= self.retry_at
+    }
+
+    /// After an init() attempt: start counting afresh, or wait out the backoff
+    fn init_done(&mut self, ok: bool, now: Instant) {
+        if ok {
+            self.read_ok();
+        } else {
+            self.retry_at = now + self.backoff;
+            self.backoff = (self.backoff * 2).min(Duration::from_millis(
+                self.config.max_backoff_ms.max(self.config.backoff_ms),
+            ));
+        }
+    }
+}
+
+/// Tick source for `[sync]` sampling: whole multiples of a sensor's period in TAI, so
+/// hubs whose clocks are disciplined by PTP or PPS sample at the same instants
+#[derive(Debug, Clone, Copy)]
+struct SyncClock {
+    tai_offset_ns: i64,
+    phase_ns: u64,
+}
+
+impl SyncClock {
+    fn new(config: &SyncConfig) -> Self {
+        Self {
+            tai_offset_ns: config.tai_offset_s * 1_000_000_000,
+            phase_ns: config.phase_us * 1000,
+        }
+    }
+
+    /// Ticks follow the PPS time scale when the hub is locked to one
+    fn now_tai_ns(&self) -> u64 {
+        let utc_ns = crate::pps::now_utc_ns() as i64;
+        (utc_ns + self.tai_offset_ns).max(0) as u64
+    }
+
+    fn utc_ns(&self, tai_ns: u64) -> u64 {
+        (tai_ns as i64 - self.tai_offset_ns).max(0) as u64
+    }
+
+    /// The next tick of `period_ns` after both `after_ns` and now; ticks missed while
+    /// the bus was busy are skipped rather than read late
+    fn next_tick(&self, after_ns: u64, period_ns: u64) -> u64 {
+        next_boundary_ns(after_ns.max(self.now_tai_ns()), period_ns, self.phase_ns)
+    }
+
+    /// How far past `tai_ns` the clock is now
+    fn lateness(&self, tai_ns: u64) -> Duration {
+        Duration::from_nanos(self.now_tai_ns().saturating_sub(tai_ns))
+    }
+
+    async fn sleep_until(&self, tai_ns: u64) {
+        let now = self.now_tai_ns();
+        if tai_ns > now {
+            sleep(Duration::from_nanos(tai_ns - now)).await;
+        }
+    }
+}
+
+/// First `phase_ns`-shifted multiple of `period_ns` strictly after `after_ns`
+fn next_boundary_ns(after_ns: u64, period_ns: u64, phase_ns: u64) -> u64 {
+    let period_ns = period_ns.max(1);
+    let phase_ns = phase_ns % period_ns;
+    if after_ns < phase_ns {
+        return phase_ns;
+    }
+    ((after_ns - phase_ns) / period_ns + 1) * period_ns + phase_ns
+}
+
+/// First register and length of one block read covering all `windows`, if it fits
+fn union_window(windows: &[RegisterWindow]) -> Option<(u8, usize)> {
+    let start = windows.iter().map(|w| w.start).min()?;
+    let end = windows
+        .iter()
+        .map(|w| w.start as usize + w.len as usize)
+        .max()?;
+    let len = end - start as usize;
+    (len <= MAX_BLOCK_READ).then_some((start, len))
+}
+
+/// Start a task for every polled sensor; the returned manager controls them at runtime
 pub async fn spawn_sensor_tasks(
     sensors: Vec<Box<dyn SensorDriver>>,
     buses: HashMap<String, Arc<Mutex<I2CBus>>>,
     grpc_service: Arc<SensorHubService>,
     sensor_config: &SensorConfig,
-) {
+) -> SensorTaskManager {
+    let manager = SensorTaskManager::new();
+    let clock = sensor_config.sync.enabled.then(|| {
+        info!(
+            "[scheduler] Sampling on TAI epoch ticks (TAI-UTC {} s, phase {} us)",
+            sensor_config.sync.tai_offset_s, sensor_config.sync.phase_us
+        );
+        SyncClock::new(&sensor_config.sync)
+    });
+
+    // Polled sensors on the same chip with register windows share one read per tick
+    let mut chips: HashMap<(String, u16), Vec<Polled>> = HashMap::new();
+
     for sensor in sensors.into_iter() {
         let sensor_id = sensor.id().to_string();
         let bus_id = sensor.bus().to_string();
 
-        // Check if this is a MAVLink sensor (push-based, doesn't need I2C bus)
-        let is_mavlink = bus_id.starts_with("serial");
-
-        // Get the bus for I2C sensors, use None for MAVLink (they don't access the bus)
-        let bus_opt = if !is_mavlink {
-            buses.get(&bus_id).cloned()
-        } else {
-            // MAVLink sensors don't need a bus - they read from their cached last_frame
-            None
-        };
+        // MAVLink sensors are push-based and don't need polling
+        // They publish data directly from their message loops
+        if sensor.is_push_based() {
+            continue;
+        }
 
         // For I2C sensors, bus is required
-        if !is_mavlink && bus_opt.is_none() {
+        if !buses.contains_key(&bus_id) {
             error!("[scheduler] No I2C bus available for sensor {}", sensor_id);
             continue;
         }
 
         // Find the sensor configuration to get frequency
-        let frequency = sensor_config
-            .sensors
+        let entry = sensor_config.sensors.iter().find(|s| s.id == sensor_id);
+        let frequency = entry.and_then(|s| s.frequency).unwrap_or(100); // Default to 100Hz if not specified
+        let mut polled = Polled::new(
+            sensor,
+            frequency,
+            &sensor_config.recovery,
+            &grpc_service.metrics(),
+        );
+        if let Some(line) = entry.and_then(|s| s.drdy_gpio) {
+            let chip = entry
+                .and_then(|s| s.drdy_chip.as_deref())
+                .unwrap_or("gpiochip0");
+            match DataReady::open(chip, line, &sensor_id) {
+                Ok(drdy) => polled.drdy = Some(drdy),
+                Err(e) => error!(
+                    "[scheduler] Failed to open data-ready GPIO {}:{} of {}: {}, sampling on a timer",
+                    chip, line, sensor_id, e
+                ),
+            }
+        }
+
+        // Sensors on a data-ready line are read on their own edges, never coalesced
+        let window = polled
+            .sensor
+            .register_window()
+            .filter(|_| polled.drdy.is_none());
+        match window {
+            Some(window) => chips
+                .entry((bus_id, window.address))
+                .or_default()
+                .push(polled),
+            None => {
+                let bus = buses[&bus_id].clone();
+                let grpc_service = grpc_service.clone();
+                manager.spawn(vec![polled.sensor_id.clone()], move |controls| {
+                    run_polled(polled, bus, clock, grpc_service, controls)
+                });
+            }
+        }
+    }
+
+    for ((bus_id, address), members) in chips {
+        let windows: Vec<RegisterWindow> = members
             .iter()
-            .find(|s| s.id == sensor_id)
-            .and_then(|s| s.frequency)
-            .unwrap_or(100); // Default to 100Hz if not specified
-        let sleep_duration = Duration::from_millis((1000.0 / frequency as f32) as u64);
-        let grpc_service_clone = grpc_service.clone();
-        let mut sequence_counter = 0u64;
-
-        tokio::spawn(async move {
-            info!("[{}] Starting sensor task at {}Hz", sensor_id, frequency);
-
-            if is_mavlink {
-                // MAVLink sensors are push-based and don't need polling
-                // They publish data directly from their message loops
-                return; // Exit this task
-            }
-
-            // Only I2C/SPI sensors reach here - they need polling
-            let bus = bus_opt.unwrap(); // Safe because we checked earlier
-
-            loop {
-                // Read sensor data from I2C bus
-                let mut bus_lock = bus.lock().await;
-                let result = sensor.read(&mut bus_lock).await;
-                drop(bus_lock); // Release lock early
-
-                match result {
-                    Ok(frame) => {
-                        sequence_counter += 1;
-
-                        // Create header with timing metadata
-                        let header = Header::new(
-                            "navigate_hub".to_string(),
-                            sensor_id.clone(),
-                            "sensor_frame".to_string(),
-                            sequence_counter,
-                        );
+            .filter_map(|m| m.sensor.register_window())
+            .collect();
+        let bus = buses[&bus_id].clone();
+        match union_window(&windows) {
+            Some((start, len)) if members.len() > 1 => {
+                let ids = members.iter().map(|m| m.sensor_id.clone()).collect();
+                let window = ChipWindow {
+                    address,
+                    start,
+                    len,
+                };
+                let grpc_service = grpc_service.clone();
+                manager.spawn(ids, move |controls| {
+                    run_coalesced(members, bus, window, clock, grpc_service, controls)
+                });
+            }
+            _ => {
+                for polled in members {
+                    let bus = bus.clone();
+                    let grpc_service = grpc_service.clone();
+                    manager.spawn(vec![polled.sensor_id.clone()], move |controls| {
+                        run_polled(polled, bus, clock, grpc_service, controls)
+                    });
+                }
+            }
+        }
+    }
+    manager
+}
 
-                        // Convert SensorDataFrame to appropriate message type based on data present
-                        let mut messages = Vec::new();
-
-                        // IMU data (accelerometer + gyroscope)
-                        if let (Some(accel), Some(gyro)) = (frame.accel, frame.gyro) {
-                            let imu_msg = ImuMessage {
-                                h: header.clone(),
-                                ax: accel[0],
-                                ay: accel[1],
-                                az: accel[2],
-                                gx: gyro[0],
-                                gy: gyro[1],
-                                gz: gyro[2],
-                            };
-                            messages.push(SensorMessage::Imu(imu_msg));
-                        }
-
-                        // Magnetometer data
-                        if let Some(mag) = frame.mag {
-                            let mag_msg = MagnetometerMessage {
-                                h: header.clone(),
-                                mx: mag[0],
-                                my: mag[1],
-                                mz: mag[2],
-                            };
-                            messages.push(SensorMessage::Magnetometer(mag_msg));
-                        }
-
-                        // Barometer data (use static pressure primarily)
-                        if let Some(pressure) = frame.pressure_static.or(frame.pressure_pitot) {
-                            let temperature = frame.temp.unwrap_or(20.0); // Default 20°C
-
-                            // Calculate altitude using standard atmosphere (ISA)
-                            // h = 44330 * (1 - (P/P0)^0.1903)
-                            let altitude = if pressure > 0.0 {
-                                44330.0 * (1.0 - (pressure / 101325.0).powf(0.1903))
-                            } else {
-                                0.0
-                            };
-
-                            let baro_msg = BarometerMessage {
-                                h: header.clone(),
-                                pressure,
-                                temperature,
-                                altitude,
-                            };
-                            messages.push(SensorMessage::Barometer(baro_msg));
-                        }
-
-                        // Publish all messages to gRPC service
-                        for msg in messages {
-                            if let Err(e) = grpc_service_clone.publish(msg).await {
-                                error!("[{}] Failed to publish: {}", sensor_id, e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        warn!("[{}] Sensor read error: {}", sensor_id, e);
+/// Poll one sensor at its own rate until the task manager shuts down
+async fn run_polled(
+    mut polled: Polled,
+    bus: Arc<Mutex<I2CBus>>,
+    clock: Option<SyncClock>,
+    grpc_service: Arc<SensorHubService>,
+    mut controls: mpsc::Receiver<Control>,
+) {
+    match &polled.drdy {
+        Some(drdy) => info!(
+            "[{}] Starting sensor task on data-ready GPIO {}, at most {}Hz",
+            polled.sensor_id, drdy.name, polled.frequency
+        ),
+        None => info!(
+            "[{}] Starting sensor task at {}Hz",
+            polled.sensor_id, polled.frequency
+        ),
+    }
+    let mut epoch = 0;
+    let mut next_read = Instant::now();
+    let mut drdy_quiet = false;
+    loop {
+        let clock = clock.filter(|_| polled.drdy.is_none());
+        let due = clock.map(|clock| clock.next_tick(epoch, polled.period_ns));
+        let wait = async {
+            match (&polled.drdy, clock, due) {
+                (Some(drdy), _, _) => {
+                    // Edges of a faster ODR are skipped down to the task's frequency
+                    let not_before = next_read - polled.period / 4;
+                    let deadline = next_read + polled.period * (DRDY_TIMEOUT_PERIODS - 1);
+                    return next_data_ready(drdy, &polled.sensor_id, not_before, deadline).await;
+                }
+                (None, Some(clock), Some(due)) => clock.sleep_until(due).await,
+                _ => sleep_until(next_read).await,
+            }
+            None
+        };
+        // Commands are handled between reads; a stopped sensor only waits for them
+        let edge = tokio::select! {
+            edge = wait, if polled.running => edge,
+            control = controls.recv() => match control {
+                Some(control) => {
+                    polled.control(control, &bus, &grpc_service).await;
+                    next_read = Instant::now();
+                    continue;
+                }
+                None => break,
+            },
+        };
+        if let Some(drdy) = &polled.drdy {
+            if edge.is_none() && !drdy_quiet {
+                warn!(
+                    "[{}] No data-ready edge on {} for {} periods, reading on the timer",
+                    polled.sensor_id, drdy.name, DRDY_TIMEOUT_PERIODS
+                );
+            } else if edge.is_some() && drdy_quiet {
+                info!("[{}] Data-ready edges resumed", polled.sensor_id);
+            }
+            drdy_quiet = edge.is_none();
+        }
+        if let Some(due) = due {
+            epoch = due;
+        }
+        // A data-ready read is stamped with the kernel's time of the edge
+        let tick_utc_ns = edge
+            .map(crate::pps::correct)
+            .or(clock.zip(due).map(|(clock, due)| clock.utc_ns(due)));
+        let jitter = match (edge, clock.zip(due)) {
+            (Some(edge), _) => edge_age(edge),
+            (None, Some((clock, due))) => clock.lateness(due),
+            (None, None) => next_read.elapsed(),
+        };
+
+        // Read sensor data from I2C bus
+        let started = Instant::now();
+        let mut bus_lock = bus.lock().await;
+        let result = polled.sensor.read(&mut bus_lock).await;
+        drop(bus_lock); // Release lock early
+        grpc_service
+            .timing()
+            .record(&polled.sensor_id, polled.period, jitter, started.elapsed());
+
+        polled.publish(result, tick_utc_ns, &grpc_service).await;
+        polled.recover(&bus, &grpc_service).await;
+        // Edges keep their own cadence, the time the read took does not delay the next
+        next_read = match polled.drdy {
+            Some(_) => started,
+            None => Instant::now(),
+        } + polled.period;
+    }
+    info!("[{}] Sensor task stopped", polled.sensor_id);
+}
+
+/// The first data-ready edge after `not_before`, or None once `deadline` passes without
+/// one so that a chip which lost its interrupt setup is still read (and recovered)
+async fn next_data_ready(
+    drdy: &DataReady,
+    sensor_id: &str,
+    not_before: Instant,
+    deadline: Instant,
+) -> Option<u64> {
+    loop {
+        match tokio::time::timeout_at(deadline, drdy.next_edge()).await {
+            Ok(Ok(edge)) if Instant::now() >= not_before => return Some(edge),
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                warn!("[{}] Data-ready GPIO {}: {}", sensor_id, drdy.name, e);
+                sleep_until(deadline).await;
+                return None;
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Registers of one chip read in a single transaction for all its sensors
+#[derive(Debug, Clone, Copy)]
+struct ChipWindow {
+    address: u16,
+    start: u8,
+    len: usize,
+}
+
+/// Sampling period of the fastest running sensor
+fn fastest_period(members: &[Polled]) -> Option<Duration> {
+    members.iter().filter(|m| m.running).map(|m| m.period).min()
+}
+
+/// Read the chip's combined window once per tick of its fastest running sensor, giving
+/// every sensor that is due its part of the bytes
+async fn run_coalesced(
+    mut members: Vec<Polled>,
+    bus: Arc<Mutex<I2CBus>>,
+    window: ChipWindow,
+    clock: Option<SyncClock>,
+    grpc_service: Arc<SensorHubService>,
+    mut controls: mpsc::Receiver<Control>,
+) {
+    let ChipWindow {
+        address,
+        start,
+        len,
+    } = window;
+    let mut tick = fastest_period(&members).unwrap_or_default();
+    let ids: Vec<&str> = members.iter().map(|m| m.sensor_id.as_str()).collect();
+    info!(
+        "[scheduler] Coalescing reads of {} at 0x{:02X} (registers 0x{:02X}..+{}) every {} ms",
+        ids.join(", "),
+        address,
+        start,
+        len,
+        tick.as_millis()
+    );
+
+    let mut buf = [0u8; MAX_BLOCK_READ];
+    let mut next_due = vec![Instant::now(); members.len()];
+    // With `[sync]`, each member's next epoch tick instead
+    let mut next_epoch: Vec<u64> = match clock {
+        Some(clock) => members
+            .iter()
+            .map(|m| clock.next_tick(0, m.period_ns))
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut next_read = Instant::now();
+    loop {
+        let due = clock.and_then(|_| {
+            members
+                .iter()
+                .zip(&next_epoch)
+                .filter(|(m, _)| m.running)
+                .map(|(_, &epoch)| epoch)
+                .min()
+        });
+        let wait = async {
+            match (clock, due) {
+                (Some(clock), Some(due)) => clock.sleep_until(due).await,
+                _ => sleep_until(next_read).await,
+            }
+        };
+        tokio::select! {
+            _ = wait, if members.iter().any(|m| m.running) => {}
+            control = controls.recv() => match control {
+                Some(control) => {
+                    let Some(i) = members.iter().position(|m| m.sensor_id == control.sensor_id)
+                    else {
+                        continue;
+                    };
+                    members[i].control(control, &bus, &grpc_service).await;
+                    // Restart the member's schedule from now, at its possibly new rate
+                    next_due[i] = Instant::now();
+                    if let Some(clock) = clock {
+                        next_epoch[i] = clock.next_tick(0, members[i].period_ns);
                     }
+                    tick = fastest_period(&members).unwrap_or_default();
+                    continue;
                 }
+                None => break,
+            },
+        }
+        let epoch = due;
+        let now = Instant::now();
+        let jitter = match clock.zip(epoch) {
+            Some((clock, epoch)) => clock.lateness(epoch),
+            None => now.saturating_duration_since(next_read),
+        };
+        let read = {
+            let mut bus_lock = bus.lock().await;
+            bus_lock.read_bytes(address, start, &mut buf[..len]).await
+        };
+        let latency = now.elapsed();
 
-                sleep(sleep_duration).await;
+        for (i, (polled, due)) in members.iter_mut().zip(next_due.iter_mut()).enumerate() {
+            if !polled.running {
+                continue;
             }
+            let tick_utc_ns = match (clock, epoch) {
+                (Some(clock), Some(epoch)) => {
+                    if next_epoch[i] > epoch {
+                        continue;
+                    }
+                    next_epoch[i] = clock.next_tick(epoch, polled.period_ns);
+                    Some(clock.utc_ns(epoch))
+                }
+                _ => {
+                    if now < *due {
+                        continue;
+                    }
+                    *due += polled.period;
+                    if *due < now {
+                        // Fell behind (slow bus), don't try to catch up
+                        *due = now + polled.period;
+                    }
+                    None
+                }
+            };
+            grpc_service
+                .timing()
+                .record(&polled.sensor_id, polled.period, jitter, latency);
+            let result = match (&read, polled.sensor.register_window()) {
+                (Ok(()), Some(window)) => {
+                    // The window was inside the block read when the group was formed
+                    let bytes = window
+                        .start
+                        .checked_sub(start)
+                        .map(|offset| offset as usize)
+                        .and_then(|offset| buf[..len].get(offset..offset + window.len as usize));
+                    let Some(bytes) = bytes else {
+                        warn!(
+                            "[{}] Register window moved outside the coalesced read, skipping tick",
+                            polled.sensor_id
+                        );
+                        continue;
+                    };
+                    polled.sensor.decode_window(bytes)
+                }
+                (Ok(()), None) => {
+                    warn!(
+                        "[{}] Coalesced sensor has no register window, skipping tick",
+                        polled.sensor_id
+                    );
+                    continue;
+                }
+                (Err(e), _) => Err(SensorError::ReadError {
+                    sensor: polled.sensor_id.clone(),
+                    reason: format!("Coalesced read failed: {}", e),
+                }),
+            };
+            polled.publish(result, tick_utc_ns, &grpc_service).await;
+            polled.recover(&bus, &grpc_service).await;
+        }
+        next_read = Instant::now() + tick;
+    }
+    info!(
+        "[scheduler] Coalesced task of {} stopped",
+        members
+            .iter()
+            .map(|m| m.sensor_id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union_window() {
+        let window = |start, len| RegisterWindow {
+            address: 0x68,
+            start,
+            len,
+        };
+        // Accel/gyro block and a magnetometer mirrored a few registers later
+        assert_eq!(
+            union_window(&[window(0x1D, 14), window(0x31, 6)]),
+            Some((0x1D, 26))
+        );
+        assert_eq!(
+            union_window(&[window(0x10, 6), window(0x12, 2)]),
+            Some((0x10, 6))
+        );
+        // Too far apart for one SMBus block read
+        assert_eq!(union_window(&[window(0x00, 6), window(0x40, 6)]), None);
+    }
+
+    #[test]
+    fn test_recovery_backoff() {
+        let mut recovery = Recovery::new(&RecoveryConfig {
+            enabled: true,
+            error_threshold: 3,
+            backoff_ms: 100,
+            max_backoff_ms: 300,
         });
+        let now = Instant::now();
+        recovery.read_failed();
+        recovery.read_failed();
+        assert!(!recovery.due(now));
+        recovery.read_failed();
+        assert!(recovery.due(now));
+
+        // Failed attempts back off 100, 200, then 300 ms at most
+        for wait_ms in [100, 200, 300, 300] {
+            recovery.init_done(false, now);
+            assert!(!recovery.due(now + Duration::from_millis(wait_ms - 1)));
+            assert!(recovery.due(now + Duration::from_millis(wait_ms)));
+        }
+
+        // A good read resets both the count and the backoff
+        recovery.read_ok();
+        assert!(!recovery.due(now + Duration::from_secs(1)));
+        assert_eq!(recovery.backoff, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_next_boundary() {
+        let ms = 1_000_000;
+        assert_eq!(next_boundary_ns(1_234 * ms + 5, 10 * ms, 0), 1_240 * ms);
+        // On a boundary the next one is due, not the same one again
+        assert_eq!(next_boundary_ns(1_240 * ms, 10 * ms, 0), 1_250 * ms);
+        assert_eq!(next_boundary_ns(1_234 * ms, 10 * ms, 2 * ms), 1_242 * ms);
+        assert_eq!(next_boundary_ns(1_243 * ms, 10 * ms, 2 * ms), 1_252 * ms);
     }
 }
@@ -0,0 +1,269 @@
+//! Compatibility of sensorhub.proto with its released versions: build.rs refuses breaking
+//! changes, and clients built against a release can ask for only the data they understand.
+//! Shared with build.rs, so it depends on prost alone.
+
+use prost::Message;
+use prost_types::{
+    field_descriptor_proto::Label, DescriptorProto, EnumDescriptorProto, FieldDescriptorProto,
+    FileDescriptorSet, MethodDescriptorProto,
+};
+use std::collections::HashMap;
+
+/// Descriptor sets of released schemas (proto/released), oldest first
+pub const RELEASES: &[(&str, &[u8])] = &[(
+    "0.1.0",
+    include_bytes!("../proto/released/sensorhub-0.1.0.binpb"),
+)];
+
+/// Request metadata naming the release a client was built against (e.g. "0.1.0")
+pub const SCHEMA_METADATA_KEY: &str = "x-sensorhub-schema";
+
+/// Fully qualified name of the unified data message
+const SENSOR_DATA: &str = ".sensorhub.SensorData";
+
+pub fn decode(bytes: &[u8]) -> Result<FileDescriptorSet, prost::DecodeError> {
+    FileDescriptorSet::decode(bytes)
+}
+
+/// Every message and enum in `set`, by fully qualified name (".package.Outer.Inner")
+#[derive(Default)]
+struct Types<'a> {
+    messages: HashMap<String, &'a DescriptorProto>,
+    enums: HashMap<String, &'a EnumDescriptorProto>,
+}
+
+impl<'a> Types<'a> {
+    fn of(set: &'a FileDescriptorSet) -> Self {
+        let mut types = Self::default();
+        for file in set.file.iter() {
+            let prefix = match file.package() {
+                "" => String::new(),
+                package => format!(".{}", package),
+            };
+            types.add(&prefix, &file.message_type, &file.enum_type);
+        }
+        types
+    }
+
+    fn add(
+        &mut self,
+        prefix: &str,
+        messages: &'a [DescriptorProto],
+        enums: &'a [EnumDescriptorProto],
+    ) {
+        for e in enums {
+            self.enums.insert(format!("{}.{}", prefix, e.name()), e);
+        }
+        for m in messages {
+            let name = format!("{}.{}", prefix, m.name());
+            self.add(&name, &m.nested_type, &m.enum_type);
+            self.messages.insert(name, m);
+        }
+    }
+}
+
+/// Oneof a field belongs to, ignoring the synthetic ones of proto3 `optional`
+fn real_oneof<'a>(message: &'a DescriptorProto, field: &FieldDescriptorProto) -> Option<&'a str> {
+    if field.proto3_optional() {
+        return None;
+    }
+    let index = usize::try_from(field.oneof_index?).ok()?;
+    message.oneof_decl.get(index).map(|o| o.name())
+}
+
+fn check_field(
+    name: &str,
+    old_message: &DescriptorProto,
+    old: &FieldDescriptorProto,
+    new_message: &DescriptorProto,
+    new: &FieldDescriptorProto,
+    changes: &mut Vec<String>,
+) {
+    let field = format!("{}.{} ({})", name, old.name(), old.number());
+    if old.name() != new.name() {
+        // Binary-compatible, but JSON clients (grpcurl, web) address fields by name
+        changes.push(format!("{} renamed to {}", field, new.name()));
+    }
+    if old.r#type() != new.r#type() || old.type_name() != new.type_name() {
+        changes.push(format!("{} changed type", field));
+    }
+    if (old.label() == Label::Repeated) != (new.label() == Label::Repeated) {
+        changes.push(format!("{} changed between singular and repeated", field));
+    }
+    if real_oneof(old_message, old) != real_oneof(new_message, new) {
+        changes.push(format!("{} moved into or out of a oneof", field));
+    }
+}
+
+fn check_message(
+    name: &str,
+    old: &DescriptorProto,
+    new: &DescriptorProto,
+    changes: &mut Vec<String>,
+) {
+    for old_field in old.field.iter() {
+        match new.field.iter().find(|f| f.number() == old_field.number()) {
+            Some(new_field) => check_field(name, old, old_field, new, new_field, changes),
+            None => {
+                let reserved = new
+                    .reserved_range
+                    .iter()
+                    .any(|r| (r.start()..r.end()).contains(&old_field.number()));
+                if !reserved {
+                    changes.push(format!(
+                        "{}.{} ({}) removed without reserving its number",
+                        name,
+                        old_field.name(),
+                        old_field.number()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_enum(
+    name: &str,
+    old: &EnumDescriptorProto,
+    new: &EnumDescriptorProto,
+    changes: &mut Vec<String>,
+) {
+    for value in old.value.iter() {
+        let kept = new.value.iter().any(|v| v.number() == value.number())
+            || new
+                .reserved_range
+                .iter()
+                .any(|r| (r.start()..=r.end()).contains(&value.number()));
+        if !kept {
+            changes.push(format!(
+                "{}.{} ({}) removed without reserving its number",
+                name,
+                value.name(),
+                value.number()
+            ));
+        }
+    }
+}
+
+/// Every RPC in `set`, by "package.Service/Method"
+fn methods(set: &FileDescriptorSet) -> HashMap<String, &MethodDescriptorProto> {
+    set.file
+        .iter()
+        .flat_map(|f| f.service.iter().map(move |s| (f.package(), s)))
+        .flat_map(|(package, s)| {
+            s.method
+                .iter()
+                .map(move |m| (format!("{}.{}/{}", package, s.name(), m.name()), m))
+        })
+        .collect()
+}
+
+/// Every RPC of an encoded descriptor set, by "package.Service/Method"
+pub fn rpc_names(bytes: &[u8]) -> Result<Vec<String>, prost::DecodeError> {
+    Ok(methods(&decode(bytes)?).into_keys().collect())
+}
+
+/// Changes from `old` to `new` that would break clients built against `old`: removed or
+/// retyped fields, enum values, messages and RPCs
+pub fn breaking_changes(old: &FileDescriptorSet, new: &FileDescriptorSet) -> Vec<String> {
+    let (old_types, new_types) = (Types::of(old), Types::of(new));
+    let mut changes = Vec::new();
+
+    let mut messages: Vec<_> = old_types.messages.iter().collect();
+    messages.sort_by_key(|(name, _)| *name);
+    for (name, old_message) in messages {
+        match new_types.messages.get(name) {
+            Some(new_message) => check_message(name, old_message, new_message, &mut changes),
+            None => changes.push(format!("message {} removed", name)),
+        }
+    }
+
+    let mut enums: Vec<_> = old_types.enums.iter().collect();
+    enums.sort_by_key(|(name, _)| *name);
+    for (name, old_enum) in enums {
+        match new_types.enums.get(name) {
+            Some(new_enum) => check_enum(name, old_enum, new_enum, &mut changes),
+            None => changes.push(format!("enum {} removed", name)),
+        }
+    }
+
+    let new_methods = methods(new);
+    let mut old_methods: Vec<_> = methods(old).into_iter().collect();
+    old_methods.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, old_method) in old_methods {
+        match new_methods.get(&name) {
+            Some(new_method) => {
+                if old_method.input_type() != new_method.input_type()
+                    || old_method.output_type() != new_method.output_type()
+                    || old_method.client_streaming() != new_method.client_streaming()
+                    || old_method.server_streaming() != new_method.server_streaming()
+                {
+                    changes.push(format!("rpc {} changed signature", name));
+                }
+            }
+            None => changes.push(format!("rpc {} removed", name)),
+        }
+    }
+    changes
+}
+
+/// SensorData kinds (oneof field names, e.g. "imu") known to a released schema, or None
+/// for a version this hub has no descriptor of
+pub fn released_kinds(version: &str) -> Option<Vec<String>> {
+    let (_, bytes) = RELEASES.iter().find(|(v, _)| *v == version)?;
+    let set = decode(bytes).ok()?;
+    let types = Types::of(&set);
+    let sensor_data = types.messages.get(SENSOR_DATA)?;
+    Some(
+        sensor_data
+            .field
+            .iter()
+            .filter(|f| real_oneof(sensor_data, f).is_some())
+            .map(|f| f.name().to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_service::sensorhub::FILE_DESCRIPTOR_SET;
+
+    #[test]
+    fn test_breaking_changes() {
+        let current = decode(FILE_DESCRIPTOR_SET).unwrap();
+        for (version, bytes) in RELEASES {
+            let released = decode(bytes).unwrap();
+            assert_eq!(
+                breaking_changes(&released, &current),
+                Vec::<String>::new(),
+                "{}",
+                version
+            );
+        }
+        assert_eq!(
+            released_kinds("0.1.0").unwrap(),
+            ["imu", "magnetometer", "barometer"]
+        );
+
+        // Retype IMUData.ax and drop BarometerData.altitude
+        let mut edited = decode(RELEASES[0].1).unwrap();
+        for message in edited.file[0].message_type.iter_mut() {
+            match message.name() {
+                "IMUData" => {
+                    message.field[1].set_type(prost_types::field_descriptor_proto::Type::Double)
+                }
+                "BarometerData" => message.field.retain(|f| f.name() != "altitude"),
+                _ => {}
+            }
+        }
+        let released = decode(RELEASES[0].1).unwrap();
+        assert_eq!(
+            breaking_changes(&released, &edited),
+            [
+                ".sensorhub.BarometerData.altitude (4) removed without reserving its number",
+                ".sensorhub.IMUData.ax (2) changed type",
+            ]
+        );
+    }
+}
@@ -1,23 +1,286 @@
+use crate::airspeed::indicated_airspeed;
 use crate::bus::i2c::I2CBus;
 use crate::errors::{SensorError, SensorResult};
+use crate::messages::{
+    AirspeedMessage, AttitudeMessage, BarometerMessage, EscReadingMessage, EscTelemetryMessage,
+    GimbalAttitudeMessage, GlobalPositionMessage, GpsMessage, Header, ImuMessage,
+    LocalPositionMessage, MagnetometerMessage, MeasurementsMessage, NamedValueMessage,
+    RangefinderMessage, RcChannelsMessage, SensorMessage, VfrHudMessage, VibrationMessage,
+};
 use async_trait::async_trait;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// Free-form driver settings from the `params` table of a `[[sensor]]` entry
+pub type DriverParams = BTreeMap<String, toml::Value>;
 
 #[derive(Debug, Default, Clone)]
 pub struct SensorDataFrame {
     pub accel: Option<[f32; 3]>,
     pub gyro: Option<[f32; 3]>,
+    /// Full-precision accel/gyro, for tactical-grade IMU drivers that compute in f64
+    pub accel_f64: Option<[f64; 3]>,
+    pub gyro_f64: Option<[f64; 3]>,
     pub mag: Option<[f32; 3]>,
     pub temp: Option<f32>,
     pub pressure_static: Option<f32>,
+    /// Differential (pitot) pressure before zero-offset correction (Pa)
     pub pressure_pitot: Option<f32>,
-    /// TODO: Attitude quaternion (w, x, y, z) from ATTITUDE_QUATERNION.
-    /// Currently populated from MAVLink but not published - need AttitudeMessage in messages.rs
-    #[allow(dead_code)]
+    /// Die temperature of the differential pressure sensor (°C), independent of `temp`
+    pub temp_pitot: Option<f32>,
+    /// Attitude quaternion (w, x, y, z) from ATTITUDE_QUATERNION or ATTITUDE
     pub quaternion: Option<[f32; 4]>,
-    /// TODO: Body angular velocity (roll, pitch, yaw rates in rad/s).
-    /// Currently populated from MAVLink but not published - need AttitudeMessage in messages.rs
-    #[allow(dead_code)]
+    /// Body angular velocity (roll, pitch, yaw rates in rad/s)
     pub angular_velocity_body: Option<[f32; 3]>,
+    /// Dual-antenna GNSS heading and its uncertainty (degrees), merged into the attitude
+    pub gnss_yaw: Option<f32>,
+    pub gnss_yaw_accuracy: Option<f32>,
+    /// GNSS fix from GPS_RAW_INT
+    pub gps: Option<GpsFix>,
+    /// Distance measurement from a rangefinder
+    pub range: Option<RangeReading>,
+    /// Fused global position from GLOBAL_POSITION_INT
+    pub global_position: Option<GlobalPosition>,
+    /// Local NED position and velocity from LOCAL_POSITION_NED
+    pub local_position: Option<LocalPosition>,
+    /// Flight instrument summary from VFR_HUD
+    pub vfr_hud: Option<VfrHud>,
+    /// Vibration levels and clipping counters from VIBRATION
+    pub vibration: Option<Vibration>,
+    /// RC receiver inputs from RC_CHANNELS
+    pub rc: Option<RcInput>,
+    /// Per-motor ESC telemetry from ESC_STATUS / ESC_INFO
+    pub esc: Option<Vec<EscReading>>,
+    /// Camera gimbal orientation from GIMBAL_DEVICE_ATTITUDE_STATUS
+    pub gimbal: Option<GimbalAttitude>,
+    /// Values named by the sensor's configuration (Modbus register maps)
+    pub measurements: Option<Vec<Measurement>>,
+}
+
+/// Raw GNSS receiver fix (not fused by the flight controller EKF)
+#[derive(Debug, Default, Clone)]
+pub struct GpsFix {
+    /// Fix type (0=no GPS, 1=no fix, 2=2D, 3=3D, 4=DGPS, 5=RTK float, 6=RTK fixed)
+    pub fix_type: u8,
+    /// Satellites used in the fix (None if unknown)
+    pub satellites_visible: Option<u8>,
+    /// Latitude (degrees, WGS84)
+    pub latitude: f64,
+    /// Longitude (degrees, WGS84)
+    pub longitude: f64,
+    /// Altitude above MSL (m)
+    pub altitude: f32,
+    /// Horizontal dilution of precision (None if unknown)
+    pub hdop: Option<f32>,
+    /// Vertical dilution of precision (None if unknown)
+    pub vdop: Option<f32>,
+    /// Ground speed (m/s, None if unknown)
+    pub ground_speed: Option<f32>,
+    /// Course over ground (degrees, 0..360, None if unknown)
+    pub course: Option<f32>,
+    /// Heading from a dual-antenna / moving-baseline receiver (degrees from true north)
+    pub yaw: Option<f32>,
+    /// Heading uncertainty (degrees)
+    pub yaw_accuracy: Option<f32>,
+}
+
+/// Fused global position estimate (flight controller EKF output, not raw GNSS)
+#[derive(Debug, Default, Clone)]
+pub struct GlobalPosition {
+    /// Latitude (degrees, WGS84)
+    pub latitude: f64,
+    /// Longitude (degrees, WGS84)
+    pub longitude: f64,
+    /// Altitude above MSL (m)
+    pub altitude: f32,
+    /// Altitude above home (m)
+    pub relative_altitude: f32,
+    /// Velocity in NED frame (m/s)
+    pub velocity: [f32; 3],
+    /// Heading (degrees, 0..360), None if unknown
+    pub heading: Option<f32>,
+}
+
+/// Local position estimate relative to the flight controller's EKF origin
+#[derive(Debug, Default, Clone)]
+pub struct LocalPosition {
+    /// Position north, east, down (m)
+    pub position: [f32; 3],
+    /// Velocity north, east, down (m/s)
+    pub velocity: [f32; 3],
+}
+
+/// Flight instrument summary as shown on a HUD (flight controller estimates)
+#[derive(Debug, Default, Clone)]
+pub struct VfrHud {
+    /// Indicated airspeed (m/s)
+    pub airspeed: f32,
+    /// Ground speed (m/s)
+    pub groundspeed: f32,
+    /// Heading (degrees, 0..360)
+    pub heading: f32,
+    /// Climb rate (m/s, positive up)
+    pub climb_rate: f32,
+    /// Throttle setting (percent, 0..100)
+    pub throttle: f32,
+    /// Altitude above MSL (m)
+    pub altitude: f32,
+}
+
+/// IMU vibration diagnostics (flight controller VIBRATION report)
+#[derive(Debug, Default, Clone)]
+pub struct Vibration {
+    /// Vibration levels on X, Y, Z (m/s²)
+    pub levels: [f32; 3],
+    /// Accelerometer clipping counts for the first three IMUs (monotonic)
+    pub clipping: [u32; 3],
+}
+
+/// RC receiver inputs as seen by the flight controller
+#[derive(Debug, Default, Clone)]
+pub struct RcInput {
+    /// Channel pulse widths (µs), only the channels the receiver reports
+    pub channels: Vec<u16>,
+    /// Receiver signal strength (0..254), None if unknown
+    pub rssi: Option<u8>,
+}
+
+/// Orientation reported by a camera gimbal
+#[derive(Debug, Default, Clone)]
+pub struct GimbalAttitude {
+    /// Gimbal device id (0 = the sending component itself)
+    pub device_id: u8,
+    /// Quaternion (w, x, y, z), relative to the vehicle or to north
+    pub quaternion: [f32; 4],
+    /// Angular velocity (rad/s), NaN when unknown
+    pub angular_velocity: [f32; 3],
+    pub yaw_in_earth_frame: bool,
+    /// Yaw relative to the vehicle (rad), if reported
+    pub delta_yaw: Option<f32>,
+    /// GIMBAL_DEVICE_FLAGS and GIMBAL_DEVICE_ERROR_FLAGS bitmasks
+    pub device_flags: u16,
+    pub failure_flags: u32,
+}
+
+/// A value of a config-driven sensor, already scaled to `unit`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+/// Telemetry from one ESC
+#[derive(Debug, Default, Clone)]
+pub struct EscReading {
+    /// ESC / motor index (0-based)
+    pub index: u8,
+    /// Motor speed (rpm)
+    pub rpm: i32,
+    /// Supply voltage (V)
+    pub voltage: f32,
+    /// Current draw (A)
+    pub current: f32,
+    /// Temperature (°C), None if not reported
+    pub temperature: Option<f32>,
+    /// Errors reported since boot, None if not reported
+    pub error_count: Option<u32>,
+    /// ESC_FAILURE_FLAGS bitmask (0 = no failure)
+    pub failure_flags: u16,
+}
+
+/// Single distance measurement from a rangefinder
+#[derive(Debug, Default, Clone)]
+pub struct RangeReading {
+    /// Measured distance (m)
+    pub distance: f32,
+    /// Minimum measurable distance (m)
+    pub min_distance: f32,
+    /// Maximum measurable distance (m)
+    pub max_distance: f32,
+    /// Mounting orientation as MAV_SENSOR_ORIENTATION (25 = pitch 270, i.e. downward)
+    pub orientation: u32,
+    /// Measurement variance (m²), None if unknown
+    pub covariance: Option<f32>,
+}
+
+/// Reject parameters a driver doesn't know, so a typo doesn't silently keep the default
+pub fn check_params(sensor: &str, params: &DriverParams, known: &[&str]) -> SensorResult<()> {
+    match params.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(SensorError::ConfigError {
+            sensor: sensor.to_string(),
+            reason: if known.is_empty() {
+                format!("driver takes no parameters, got '{}'", key)
+            } else {
+                format!(
+                    "unknown parameter '{}' (supported: {})",
+                    key,
+                    known.join(", ")
+                )
+            },
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Register setting for a numeric parameter such as `accel_range = "16g"` or `odr_hz = 400`
+///
+/// The value may be a number or a string with `unit` appended; it must be one of the
+/// values in `choices`, each paired with its setting. None if the parameter is not set.
+pub fn param_choice<T: Copy>(
+    sensor: &str,
+    params: &DriverParams,
+    key: &str,
+    unit: &str,
+    choices: &[(f64, T)],
+) -> SensorResult<Option<T>> {
+    let Some(value) = params.get(key) else {
+        return Ok(None);
+    };
+    let number = match value {
+        toml::Value::Integer(n) => Some(*n as f64),
+        toml::Value::Float(x) => Some(*x),
+        toml::Value::String(s) => {
+            let s = s.trim();
+            let s = match s.len().checked_sub(unit.len()) {
+                Some(at) if s.is_char_boundary(at) && s[at..].eq_ignore_ascii_case(unit) => {
+                    &s[..at]
+                }
+                _ => s,
+            };
+            s.trim().parse::<f64>().ok()
+        }
+        _ => None,
+    };
+    number
+        .and_then(|n| {
+            choices
+                .iter()
+                .find(|(choice, _)| (choice - n).abs() < 1e-6)
+                .map(|(_, setting)| *setting)
+        })
+        .map(Some)
+        .ok_or_else(|| {
+            let supported: Vec<String> = choices.iter().map(|(c, _)| c.to_string()).collect();
+            SensorError::ConfigError {
+                sensor: sensor.to_string(),
+                reason: format!(
+                    "{} = {} is not supported (one of {} {})",
+                    key,
+                    value,
+                    supported.join(", "),
+                    unit
+                ),
+            }
+        })
+}
+
+/// Contiguous registers of a chip, read in one I2C block transfer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegisterWindow {
+    pub address: u16,
+    pub start: u8,
+    pub len: u8,
 }
 
 #[async_trait]
@@ -27,25 +290,399 @@ pub trait SensorDriver: Send + Sync {
     fn id(&self) -> &str;
     fn bus(&self) -> &str;
 
+    /// Apply the `params` of the sensor's `[[sensor]]` entry, before `init`
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        check_params(self.id(), params, &[])
+    }
+
+    /// Make `init` route a pulse to the chip's interrupt pin on each new sample, for
+    /// `drdy_gpio` sampling; drivers that cannot refuse
+    fn enable_data_ready(&mut self) -> SensorResult<()> {
+        Err(SensorError::ConfigError {
+            sensor: self.id().to_string(),
+            reason: "driver cannot signal data-ready on an interrupt pin".to_string(),
+        })
+    }
+
+    /// Push-based sensors publish from their own task and are not polled by the scheduler
+    fn is_push_based(&self) -> bool {
+        false
+    }
+
+    /// Registers holding all of this sensor's data, if it can be decoded from them alone
+    ///
+    /// Sensors polled on the same chip (same bus and address) that declare a window are
+    /// read together: one block read of the union of their windows per tick, split
+    /// between them with `decode_window`.
+    fn register_window(&self) -> Option<RegisterWindow> {
+        None
+    }
+
+    /// Decode a frame from the bytes of `register_window`
+    fn decode_window(&self, _data: &[u8]) -> SensorResult<SensorDataFrame> {
+        Err(SensorError::DataError {
+            sensor: self.id().to_string(),
+            reason: "driver has no register window".to_string(),
+        })
+    }
+
     /// Downcast to any for dynamic type checking (needed for MAVLink sensor setup)
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
 
 #[cfg(feature = "bmp388")]
 pub mod bmp388;
+#[cfg(all(test, target_os = "linux"))]
+pub(crate) mod conformance;
 #[cfg(feature = "icm42688p")]
 pub mod icm42688p;
+#[cfg(feature = "icp10111")]
+pub mod icp10111;
+#[cfg(feature = "icp20100")]
+pub mod icp20100;
+#[cfg(feature = "iio")]
+pub mod iio;
+#[cfg(feature = "lightware")]
+pub mod lightware;
 #[cfg(feature = "lis3mdl")]
 pub mod lis3mdl;
 #[cfg(feature = "lsm6dsl")]
 pub mod lsm6dsl;
 #[cfg(feature = "mavlink_sensors")]
 pub mod mavlink;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+pub mod uart;
+
+/// Convert a SensorDataFrame into the typed messages published over gRPC
+pub fn frame_to_messages(
+    frame: SensorDataFrame,
+    header: Header,
+    sensor_id: &str,
+) -> Vec<SensorMessage> {
+    let mut messages = Vec::new();
+
+    // IMU data (accelerometer + gyroscope)
+    if let (Some(accel), Some(gyro)) = (frame.accel, frame.gyro) {
+        let imu_msg = ImuMessage {
+            h: header.clone(),
+            ax: accel[0],
+            ay: accel[1],
+            az: accel[2],
+            gx: gyro[0],
+            gy: gyro[1],
+            gz: gyro[2],
+            precise: match (frame.accel_f64, frame.gyro_f64) {
+                (Some(a), Some(g)) => Some([a[0], a[1], a[2], g[0], g[1], g[2]]),
+                _ => None,
+            },
+            temperature: frame.temp,
+        };
+        messages.push(SensorMessage::Imu(imu_msg));
+        debug!(
+            "[{}] Publishing IMU: accel={:?}, gyro={:?}",
+            sensor_id, accel, gyro
+        );
+    }
+
+    // Magnetometer data
+    if let Some(mag) = frame.mag {
+        let mag_msg = MagnetometerMessage {
+            h: header.clone(),
+            mx: mag[0],
+            my: mag[1],
+            mz: mag[2],
+        };
+        messages.push(SensorMessage::Magnetometer(mag_msg));
+        debug!("[{}] Publishing Mag: {:?}", sensor_id, mag);
+    }
+
+    // Barometer data
+    if let Some(pressure) = frame.pressure_static {
+        let temperature = frame.temp.unwrap_or(20.0);
+
+        // Calculate altitude using standard atmosphere (ISA): h = 44330 * (1 - (P/P0)^0.1903)
+        let altitude = if pressure > 0.0 {
+            44330.0 * (1.0 - (pressure / 101325.0).powf(0.1903))
+        } else {
+            0.0
+        };
+
+        let baro_msg = BarometerMessage {
+            h: header.clone(),
+            pressure,
+            temperature,
+            altitude,
+        };
+        messages.push(SensorMessage::Barometer(baro_msg));
+        debug!(
+            "[{}] Publishing Baro: press={:.1} Pa, temp={:.1}°C, alt={:.1}m",
+            sensor_id, pressure, temperature, altitude
+        );
+    }
+
+    // Pitot data (zero offset is applied by the gRPC service on publish)
+    if let Some(differential_pressure) = frame.pressure_pitot {
+        messages.push(SensorMessage::Airspeed(AirspeedMessage {
+            h: header.clone(),
+            differential_pressure,
+            indicated_airspeed: indicated_airspeed(differential_pressure),
+            temperature: frame.temp_pitot,
+        }));
+        debug!(
+            "[{}] Publishing raw pitot: diff={:.2} Pa",
+            sensor_id, differential_pressure
+        );
+    }
+
+    // GPS data
+    if let Some(gps) = frame.gps {
+        debug!(
+            "[{}] Publishing GPS: fix={}, sats={:?}, lat={:.7}, lon={:.7}, alt={:.1}m",
+            sensor_id,
+            gps.fix_type,
+            gps.satellites_visible,
+            gps.latitude,
+            gps.longitude,
+            gps.altitude
+        );
+        messages.push(SensorMessage::Gps(GpsMessage {
+            h: header.clone(),
+            fix_type: gps.fix_type as u32,
+            satellites_visible: gps.satellites_visible.map(u32::from),
+            latitude: gps.latitude,
+            longitude: gps.longitude,
+            altitude: gps.altitude,
+            hdop: gps.hdop,
+            vdop: gps.vdop,
+            ground_speed: gps.ground_speed,
+            course: gps.course,
+            yaw: gps.yaw,
+            yaw_accuracy: gps.yaw_accuracy,
+        }));
+    }
+
+    // Fused global position
+    if let Some(pos) = frame.global_position {
+        debug!(
+            "[{}] Publishing GlobalPosition: lat={:.7}, lon={:.7}, alt={:.1}m, rel_alt={:.1}m",
+            sensor_id, pos.latitude, pos.longitude, pos.altitude, pos.relative_altitude
+        );
+        messages.push(SensorMessage::GlobalPosition(GlobalPositionMessage {
+            h: header.clone(),
+            latitude: pos.latitude,
+            longitude: pos.longitude,
+            altitude: pos.altitude,
+            relative_altitude: pos.relative_altitude,
+            vn: pos.velocity[0],
+            ve: pos.velocity[1],
+            vd: pos.velocity[2],
+            heading: pos.heading,
+        }));
+    }
+
+    // Local NED position
+    if let Some(pos) = frame.local_position {
+        debug!(
+            "[{}] Publishing LocalPosition: ned=({:.2}, {:.2}, {:.2})m",
+            sensor_id, pos.position[0], pos.position[1], pos.position[2]
+        );
+        messages.push(SensorMessage::LocalPosition(LocalPositionMessage {
+            h: header.clone(),
+            x: pos.position[0],
+            y: pos.position[1],
+            z: pos.position[2],
+            vx: pos.velocity[0],
+            vy: pos.velocity[1],
+            vz: pos.velocity[2],
+        }));
+    }
+
+    // Rangefinder data
+    if let Some(range) = frame.range {
+        debug!(
+            "[{}] Publishing Range: dist={:.2}m [{:.2}..{:.2}]",
+            sensor_id, range.distance, range.min_distance, range.max_distance
+        );
+        messages.push(SensorMessage::Rangefinder(RangefinderMessage {
+            h: header.clone(),
+            distance: range.distance,
+            min_distance: range.min_distance,
+            max_distance: range.max_distance,
+            orientation: range.orientation,
+            covariance: range.covariance,
+        }));
+    }
+
+    // HUD summary
+    if let Some(hud) = frame.vfr_hud {
+        debug!(
+            "[{}] Publishing VfrHud: airspeed={:.1}m/s, groundspeed={:.1}m/s, hdg={:.0}, climb={:.1}m/s, thr={:.0}%",
+            sensor_id, hud.airspeed, hud.groundspeed, hud.heading, hud.climb_rate, hud.throttle
+        );
+        messages.push(SensorMessage::VfrHud(VfrHudMessage {
+            h: header.clone(),
+            airspeed: hud.airspeed,
+            groundspeed: hud.groundspeed,
+            heading: hud.heading,
+            climb_rate: hud.climb_rate,
+            throttle: hud.throttle,
+            altitude: hud.altitude,
+        }));
+    }
+
+    // Vibration diagnostics
+    if let Some(vib) = frame.vibration {
+        debug!(
+            "[{}] Publishing Vibration: levels={:?}, clipping={:?}",
+            sensor_id, vib.levels, vib.clipping
+        );
+        messages.push(SensorMessage::Vibration(VibrationMessage {
+            h: header.clone(),
+            vibration_x: vib.levels[0],
+            vibration_y: vib.levels[1],
+            vibration_z: vib.levels[2],
+            clipping: vib.clipping,
+        }));
+    }
+
+    // RC inputs
+    if let Some(rc) = frame.rc {
+        debug!(
+            "[{}] Publishing RC: channels={:?}, rssi={:?}",
+            sensor_id, rc.channels, rc.rssi
+        );
+        messages.push(SensorMessage::RcChannels(RcChannelsMessage {
+            h: header.clone(),
+            channels: rc.channels,
+            rssi: rc.rssi,
+        }));
+    }
+
+    // ESC telemetry
+    if let Some(escs) = frame.esc {
+        debug!(
+            "[{}] Publishing ESC telemetry: rpm={:?}",
+            sensor_id,
+            escs.iter().map(|e| e.rpm).collect::<Vec<_>>()
+        );
+        messages.push(SensorMessage::EscTelemetry(EscTelemetryMessage {
+            h: header.clone(),
+            escs: escs
+                .into_iter()
+                .map(|e| EscReadingMessage {
+                    index: e.index,
+                    rpm: e.rpm,
+                    voltage: e.voltage,
+                    current: e.current,
+                    temperature: e.temperature,
+                    error_count: e.error_count,
+                    failure_flags: e.failure_flags,
+                })
+                .collect(),
+        }));
+    }
+
+    if let Some(measurements) = frame.measurements {
+        debug!(
+            "[{}] Publishing {} measurements",
+            sensor_id,
+            measurements.len()
+        );
+        messages.push(SensorMessage::Measurements(MeasurementsMessage {
+            h: header.clone(),
+            values: measurements
+                .into_iter()
+                .map(|m| NamedValueMessage {
+                    name: m.name,
+                    value: m.value,
+                    unit: m.unit,
+                })
+                .collect(),
+        }));
+    }
+
+    if let Some(gimbal) = frame.gimbal {
+        let q = gimbal.quaternion;
+        let [roll, pitch, yaw] = euler_from_quaternion(q);
+        debug!(
+            "[{}] Publishing gimbal attitude: rpy=({:.3},{:.3},{:.3}) rad",
+            sensor_id, roll, pitch, yaw
+        );
+        messages.push(SensorMessage::GimbalAttitude(GimbalAttitudeMessage {
+            h: header.clone(),
+            gimbal_device_id: gimbal.device_id,
+            qw: q[0],
+            qx: q[1],
+            qy: q[2],
+            qz: q[3],
+            roll,
+            pitch,
+            yaw,
+            angular_velocity_x: gimbal.angular_velocity[0],
+            angular_velocity_y: gimbal.angular_velocity[1],
+            angular_velocity_z: gimbal.angular_velocity[2],
+            yaw_in_earth_frame: gimbal.yaw_in_earth_frame,
+            delta_yaw: gimbal.delta_yaw,
+            device_flags: gimbal.device_flags,
+            failure_flags: gimbal.failure_flags,
+        }));
+    }
+
+    // Attitude (quaternion is the canonical form, Euler angles derived from it)
+    if let Some(q) = frame.quaternion {
+        let [roll, pitch, yaw] = euler_from_quaternion(q);
+        let rates = frame.angular_velocity_body.unwrap_or_default();
+        debug!(
+            "[{}] Publishing Attitude: rpy=({:.3},{:.3},{:.3}) rad",
+            sensor_id, roll, pitch, yaw
+        );
+        messages.push(SensorMessage::Attitude(AttitudeMessage {
+            h: header.clone(),
+            qw: q[0],
+            qx: q[1],
+            qy: q[2],
+            qz: q[3],
+            roll,
+            pitch,
+            yaw,
+            rollspeed: rates[0],
+            pitchspeed: rates[1],
+            yawspeed: rates[2],
+            gnss_yaw: frame.gnss_yaw,
+            gnss_yaw_accuracy: frame.gnss_yaw_accuracy,
+        }));
+    }
+
+    messages
+}
+
+/// Quaternion (w, x, y, z) from ZYX Euler angles (roll, pitch, yaw in rad)
+pub fn quaternion_from_euler(roll: f32, pitch: f32, yaw: f32) -> [f32; 4] {
+    let (sr, cr) = (roll * 0.5).sin_cos();
+    let (sp, cp) = (pitch * 0.5).sin_cos();
+    let (sy, cy) = (yaw * 0.5).sin_cos();
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+/// ZYX Euler angles (roll, pitch, yaw in rad) from a quaternion (w, x, y, z)
+pub fn euler_from_quaternion(q: [f32; 4]) -> [f32; 3] {
+    let [w, x, y, z] = q;
+    let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+    let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+    let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+    [roll, pitch, yaw]
+}
 
 pub fn create_sensor_driver(
     driver: &str,
     id: String,
-    address: u8,
+    address: u16,
     bus_id: String,
 ) -> SensorResult<Box<dyn SensorDriver + Send>> {
     match driver {
@@ -57,6 +694,10 @@ pub fn create_sensor_driver(
         "bmp388" => Ok(Box::new(bmp388::Bmp388::new(id, address, bus_id))),
         #[cfg(feature = "icm42688p")]
         "icm42688p" => Ok(Box::new(icm42688p::Icm42688p::new(id, address, bus_id))),
+        #[cfg(feature = "icp10111")]
+        "icp10111" => Ok(Box::new(icp10111::Icp10111::new(id, address, bus_id))),
+        #[cfg(feature = "icp20100")]
+        "icp20100" => Ok(Box::new(icp20100::Icp20100::new(id, address, bus_id))),
         #[cfg(feature = "mavlink_sensors")]
         "mavlink_imu" => Ok(Box::new(mavlink::MavlinkSensor::new(
             id,
@@ -82,8 +723,162 @@ pub fn create_sensor_driver(
             bus_id,
             mavlink::MavlinkSensorType::Attitude,
         ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_attitude_euler" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::AttitudeEuler,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_gps" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::Gps,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_gps2" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::Gps2,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_global_position" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::GlobalPosition,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_local_position" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::LocalPosition,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_vfr_hud" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::VfrHud,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_vibration" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::Vibration,
+        ))),
+        // `address` is the DISTANCE_SENSOR id the autopilot gives the rangefinder
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_range" => match u8::try_from(address) {
+            Ok(range_id) => Ok(Box::new(mavlink::MavlinkSensor::new(
+                id,
+                bus_id,
+                mavlink::MavlinkSensorType::Rangefinder { id: range_id },
+            ))),
+            Err(_) => Err(SensorError::ConfigError {
+                sensor: id,
+                reason: format!("DISTANCE_SENSOR id {} is out of range (0-255)", address),
+            }),
+        },
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_rc" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::RcChannels,
+        ))),
+        #[cfg(feature = "mavlink_sensors")]
+        "mavlink_esc" => Ok(Box::new(mavlink::MavlinkSensor::new(
+            id,
+            bus_id,
+            mavlink::MavlinkSensorType::Esc,
+        ))),
+        #[cfg(feature = "lightware")]
+        "lw20" | "sf11" | "lw20_binary" => Ok(Box::new(uart::UartSensor::new(
+            id,
+            bus_id,
+            lightware::Lightware::for_driver(driver),
+        ))),
+        #[cfg(feature = "modbus")]
+        "modbus" => Ok(Box::new(uart::UartSensor::new(
+            id.clone(),
+            bus_id,
+            Box::new(modbus::ModbusSensor::new(&id, address)?),
+        ))),
+        #[cfg(feature = "iio")]
+        "iio" => Ok(Box::new(iio::IioSensor::new(id, bus_id))),
         _ => Err(SensorError::UnsupportedDriver {
             driver: driver.to_string(),
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_euler_quaternion_round_trip() {
+        let (roll, pitch, yaw) = (0.3f32, -0.2f32, 2.5f32);
+        let q = quaternion_from_euler(roll, pitch, yaw);
+        let norm = q.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+
+        let [r, p, y] = euler_from_quaternion(q);
+        assert!((r - roll).abs() < 1e-5);
+        assert!((p - pitch).abs() < 1e-5);
+        assert!((y - yaw).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_param_choice() {
+        let params: DriverParams =
+            toml::from_str("accel_range = \"16g\"\nodr_hz = 400\ngyro_range = 62.5\niir = \"x\"")
+                .unwrap();
+        let ranges = [(2.0, 0b11), (4.0, 0b10), (16.0, 0b00)];
+        assert_eq!(
+            param_choice("imu0", &params, "accel_range", "g", &ranges).unwrap(),
+            Some(0b00)
+        );
+        assert_eq!(
+            param_choice("imu0", &params, "odr_hz", "Hz", &[(200.0, 7), (400.0, 6)]).unwrap(),
+            Some(6)
+        );
+        assert_eq!(
+            param_choice("imu0", &params, "gyro_range", "dps", &[(62.5, 5)]).unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            param_choice("imu0", &params, "temp_range", "C", &ranges).unwrap(),
+            None
+        );
+        assert!(param_choice("imu0", &params, "odr_hz", "Hz", &[(200.0, 7)]).is_err());
+        assert!(param_choice("imu0", &params, "iir", "", &[(1.0, 1)]).is_err());
+        assert!(check_params("imu0", &params, &["accel_range", "odr_hz"]).is_err());
+    }
+
+    #[test]
+    fn test_pitot_temperature_separate() {
+        let frame = SensorDataFrame {
+            pressure_static: Some(101_325.0),
+            temp: Some(31.5),
+            pressure_pitot: Some(120.0),
+            temp_pitot: Some(12.0),
+            ..Default::default()
+        };
+        let header = Header::new("hub".into(), "fc_baro".into(), "fc_baro".into(), 0);
+        let messages = frame_to_messages(frame, header, "fc_baro");
+        let [SensorMessage::Barometer(baro), SensorMessage::Airspeed(pitot)] = &messages[..] else {
+            panic!("expected barometer and airspeed, got {:?}", messages);
+        };
+        assert_eq!(baro.temperature, 31.5);
+        assert_eq!(pitot.temperature, Some(12.0));
+    }
+
+    #[cfg(feature = "mavlink_sensors")]
+    #[test]
+    fn test_mavlink_range_id_from_address() {
+        let create =
+            |address| create_sensor_driver("mavlink_range", "range1".into(), address, "fc".into());
+        assert!(create(1).is_ok());
+        assert!(create(255).is_ok());
+        assert!(create(256).is_err());
+    }
+}
@@ -1,8 +1,44 @@
 use crate::bus::i2c::I2CBus;
 use crate::errors::{SensorError, SensorResult};
-use crate::sensors::{SensorDataFrame, SensorDriver};
+use crate::sensors::{check_params, param_choice, DriverParams, SensorDataFrame, SensorDriver};
 use async_trait::async_trait;
-use tracing::debug;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// STATUS register: cmd_rdy (bit 4), drdy_press (bit 5), drdy_temp (bit 6)
+const STATUS: u8 = 0x03;
+const STATUS_CMD_RDY: u8 = 0x10;
+const STATUS_DRDY: u8 = 0x60;
+/// Longest wait for the device to accept commands after a soft reset
+const READY_TIMEOUT: Duration = Duration::from_millis(10);
+/// Longest wait for the first pressure/temperature sample
+const FIRST_SAMPLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Pressure oversampling and its osr_p code (OSR bits 2-0)
+const PRESSURE_OSRS: [(f64, u8); 6] =
+    [(1.0, 0), (2.0, 1), (4.0, 2), (8.0, 3), (16.0, 4), (32.0, 5)];
+/// Output data rates and their ODR register values
+const ODRS: [(f64, u8); 8] = [
+    (200.0, 0),
+    (100.0, 1),
+    (50.0, 2),
+    (25.0, 3),
+    (12.5, 4),
+    (6.25, 5),
+    (3.125, 6),
+    (1.5625, 7),
+];
+/// IIR filter coefficients and their iir_filter code (CONFIG bits 3-1)
+const IIR_COEFFICIENTS: [(f64, u8); 8] = [
+    (0.0, 0),
+    (1.0, 1),
+    (3.0, 2),
+    (7.0, 3),
+    (15.0, 4),
+    (31.0, 5),
+    (63.0, 6),
+    (127.0, 7),
+];
 
 enum PressureKind {
     Static,
@@ -33,14 +69,18 @@ struct Bmp388Calibration {
 
 pub struct Bmp388 {
     id: String,
-    address: u8,
+    address: u16,
     bus_id: String,
     kind: PressureKind,
     calibration: Option<Bmp388Calibration>,
+    /// Register codes, x4 pressure oversampling at 50 Hz without IIR filter unless configured
+    pressure_osr: u8,
+    odr: u8,
+    iir: u8,
 }
 
 impl Bmp388 {
-    pub fn new(id: String, address: u8, bus_id: String) -> Self {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
         let kind = if id.to_lowercase().starts_with("pitot") {
             PressureKind::Pitot
         } else {
@@ -52,12 +92,29 @@ impl Bmp388 {
             bus_id,
             kind,
             calibration: None,
+            pressure_osr: 2,
+            odr: 2,
+            iir: 0,
         }
     }
 }
 
 #[async_trait]
 impl SensorDriver for Bmp388 {
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        check_params(&self.id, params, &["pressure_osr", "odr_hz", "iir"])?;
+        if let Some(osr) = param_choice(&self.id, params, "pressure_osr", "x", &PRESSURE_OSRS)? {
+            self.pressure_osr = osr;
+        }
+        if let Some(odr) = param_choice(&self.id, params, "odr_hz", "Hz", &ODRS)? {
+            self.odr = odr;
+        }
+        if let Some(iir) = param_choice(&self.id, params, "iir", "", &IIR_COEFFICIENTS)? {
+            self.iir = iir;
+        }
+        Ok(())
+    }
+
     async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
         // Check chip ID (should be 0x50)
         let mut buf = [0u8; 1];
@@ -78,7 +135,12 @@ impl SensorDriver for Bmp388 {
                 sensor: self.id.clone(),
                 reason: format!("Failed to reset sensor: {}", e),
             })?;
-        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        if !bus
+            .wait_for_bits(self.address, STATUS, STATUS_CMD_RDY, READY_TIMEOUT)
+            .await
+        {
+            warn!("[{}] BMP388 not command-ready after reset", self.id);
+        }
 
         // Read calibration coefficients (0x31 to 0x45)
         let mut cal_buf = [0u8; 21];
@@ -126,17 +188,17 @@ impl SensorDriver for Bmp388 {
         });
 
         // Set oversampling configuration
-        // 0x1C = OSR: [5:3]=temp_os x1 (000), [2:0]=press_os x4 (010) = 0x02
-        bus.write_byte(self.address, 0x1C, 0x02)
+        // 0x1C = OSR: [5:3]=temp_os x1 (000), [2:0]=press_os (x4 = 010 by default)
+        bus.write_byte(self.address, 0x1C, self.pressure_osr)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
                 reason: format!("Failed to set oversampling: {}", e),
             })?;
 
-        // Set output data rate to 50Hz
-        // 0x1D = ODR: 50Hz = 0x02
-        bus.write_byte(self.address, 0x1D, 0x02)
+        // Set output data rate
+        // 0x1D = ODR: 200 Hz >> code (50Hz = 0x02 by default)
+        bus.write_byte(self.address, 0x1D, self.odr)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
@@ -144,8 +206,8 @@ impl SensorDriver for Bmp388 {
             })?;
 
         // Set IIR filter
-        // 0x1F = CONFIG: filter_coeff=1 (001) = 0x00
-        bus.write_byte(self.address, 0x1F, 0x00)
+        // 0x1F = CONFIG: [3:1]=iir_filter (bypassed = 000 by default)
+        bus.write_byte(self.address, 0x1F, self.iir << 1)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
@@ -161,8 +223,14 @@ impl SensorDriver for Bmp388 {
                 reason: format!("Failed to enable sensors: {}", e),
             })?;
 
-        // Wait for first measurement to complete
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        // Wait for the first measurement to complete
+        if bus
+            .wait_for_bits(self.address, STATUS, STATUS_DRDY, FIRST_SAMPLE_TIMEOUT)
+            .await
+        {
+            debug!("[{}] BMP388 calibration loaded", self.id);
+            return Ok(());
+        }
 
         // Force a measurement in case normal mode isn't working
         // 0x1B = PWR_CTRL: forced mode with both sensors = 0x13
@@ -172,7 +240,8 @@ impl SensorDriver for Bmp388 {
                 sensor: self.id.clone(),
                 reason: format!("Failed to force measurement: {}", e),
             })?;
-        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        bus.wait_for_bits(self.address, STATUS, STATUS_DRDY, FIRST_SAMPLE_TIMEOUT / 2)
+            .await;
 
         // Back to normal mode
         bus.write_byte(self.address, 0x1B, 0x33)
@@ -250,24 +319,14 @@ impl SensorDriver for Bmp388 {
 
         let frame = match self.kind {
             PressureKind::Static => SensorDataFrame {
-                accel: None,
-                gyro: None,
-                mag: None,
                 temp: Some(temperature as f32),
                 pressure_static: Some(pressure as f32),
-                pressure_pitot: None,
-                quaternion: None,
-                angular_velocity_body: None,
+                ..Default::default()
             },
             PressureKind::Pitot => SensorDataFrame {
-                accel: None,
-                gyro: None,
-                mag: None,
-                temp: Some(temperature as f32),
-                pressure_static: None,
+                temp_pitot: Some(temperature as f32),
                 pressure_pitot: Some(pressure as f32),
-                quaternion: None,
-                angular_velocity_body: None,
+                ..Default::default()
             },
         };
 
@@ -286,3 +345,42 @@ impl SensorDriver for Bmp388 {
         self
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Bmp388::new("baro0".into(), 0x76, "i2c1".into())),
+            address: 0x76,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x76, 0x00, &[0x50]);
+                mock.set_registers(0x76, STATUS, &[STATUS_CMD_RDY | STATUS_DRDY]);
+                // Trimming coefficients T1..T3, P1..P11
+                mock.set_registers(
+                    0x76,
+                    0x31,
+                    &[
+                        0x78, 0x69, 0x38, 0x4A, 0xF9, 0x30, 0x75, 0x30, 0xF8, 0x23, 0x00, 0x40,
+                        0x1F, 0x30, 0x75, 0xFB, 0xF6, 0x80, 0x3E, 0x14, 0xC4,
+                    ],
+                );
+                // About 101.4 kPa and 25 °C with those coefficients
+                mock.set_registers(0x76, 0x04, &[0x00, 0x00, 0x20, 0xC0, 0x06, 0x7F]);
+            },
+            init_writes: vec![
+                Write::Register(0x7E, 0xB6),
+                Write::Register(0x1C, 0x02),
+                Write::Register(0x1D, 0x02),
+                Write::Register(0x1F, 0x00),
+                Write::Register(0x1B, 0x33),
+            ],
+        }
+        .check()
+        .await;
+    }
+}
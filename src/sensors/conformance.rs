@@ -0,0 +1,222 @@
+//! Checks every I2C driver must pass against a simulated chip: its init sequence, the
+//! units and ranges of what it reads, and how it fails. Each driver runs them from its
+//! own tests with a `Case` describing the chip.
+
+use super::{SensorDataFrame, SensorDriver};
+use crate::bus::i2c::{I2CBus, I2cTrace};
+use crate::bus::i2c_mock::MockI2c;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+const STANDARD_GRAVITY: f32 = 9.80665;
+/// Plausible readings of a chip at rest, level, indoors
+const ACCEL_AT_REST: RangeInclusive<f32> = 0.9 * STANDARD_GRAVITY..=1.1 * STANDARD_GRAVITY;
+/// rad/s (about 6 °/s), so a driver reporting °/s fails on the chip's small rotation
+const GYRO_AT_REST: f32 = 0.1;
+/// μT, the Earth's field anywhere
+const MAG_EARTH: RangeInclusive<f32> = 20.0..=70.0;
+/// °C
+const TEMPERATURE_ROOM: RangeInclusive<f32> = 0.0..=50.0;
+/// Pa, sea level to a few thousand metres
+const PRESSURE_AMBIENT: RangeInclusive<f32> = 60_000.0..=110_000.0;
+
+/// A write init() must make
+#[derive(Debug, Clone, PartialEq)]
+pub enum Write {
+    /// Register and value
+    Register(u8, u8),
+    /// Plain write, for command-based chips
+    Command(Vec<u8>),
+}
+
+/// A driver and a simulated chip for it
+pub struct Case {
+    pub make: fn() -> Box<dyn SensorDriver>,
+    pub address: u16,
+    /// Put the chip on `mock`: identity, calibration constants and a sample at rest,
+    /// level and at room conditions, with a slight rotation (1-2 °/s) on any gyro
+    pub chip: fn(&MockI2c),
+    /// Writes init() must make, in this order, among any others
+    pub init_writes: Vec<Write>,
+}
+
+impl Case {
+    /// Run every check, panicking on the first failure
+    pub async fn check(&self) {
+        self.check_init_and_read().await;
+        self.check_bus_errors().await;
+        self.check_absent_chip().await;
+        self.check_foreign_chip().await;
+    }
+
+    /// Initialize the simulated chip; returns the driver, its bus and the writes init made
+    async fn init(&self, mock: &MockI2c) -> (Box<dyn SensorDriver>, I2CBus, Vec<Write>) {
+        (self.chip)(mock);
+        let mut bus = I2CBus::mock(mock.clone());
+        let trace = Arc::new(I2cTrace::new(4096));
+        trace.set_enabled(true);
+        bus.set_trace("mock", trace.clone());
+        let mut driver = (self.make)();
+        if let Err(e) = driver.init(&mut bus).await {
+            panic!("[{}] init failed: {}", driver.id(), e);
+        }
+        let writes = trace
+            .snapshot()
+            .into_iter()
+            .filter(|t| t.write && t.address == self.address && t.error.is_none())
+            .map(|t| match t.reg {
+                Some(reg) => Write::Register(reg, t.data[0]),
+                None => Write::Command(t.data),
+            })
+            .collect();
+        (driver, bus, writes)
+    }
+
+    async fn check_init_and_read(&self) {
+        let mock = MockI2c::new();
+        let (mut driver, mut bus, writes) = self.init(&mock).await;
+        let id = driver.id().to_string();
+
+        let mut made = writes.iter();
+        for expected in self.init_writes.iter() {
+            assert!(
+                made.any(|w| w == expected),
+                "[{}] init did not write {:?} (in order) - wrote {:?}",
+                id,
+                expected,
+                writes
+            );
+        }
+
+        let frame = match driver.read(&mut bus).await {
+            Ok(frame) => frame,
+            Err(e) => panic!("[{}] read failed: {}", id, e),
+        };
+        check_frame(&id, &frame);
+
+        // A coalesced read must decode to the same frame
+        if let Some(window) = driver.register_window() {
+            assert_eq!(window.address, self.address, "[{}] window address", id);
+            let mut buf = vec![0u8; window.len as usize];
+            bus.read_bytes(window.address, window.start, &mut buf)
+                .await
+                .unwrap();
+            let decoded = driver.decode_window(&buf).unwrap();
+            assert_eq!(
+                format!("{:?}", decoded),
+                format!("{:?}", frame),
+                "[{}] decode_window differs from read",
+                id
+            );
+        }
+
+        // The scheduler re-runs init() on a live driver after persistent read errors
+        if let Err(e) = driver.init(&mut bus).await {
+            panic!("[{}] second init failed: {}", id, e);
+        }
+        match driver.read(&mut bus).await {
+            Ok(frame) => check_frame(&id, &frame),
+            Err(e) => panic!("[{}] read after re-init failed: {}", id, e),
+        }
+    }
+
+    /// Failed transfers are errors, never stale or zeroed data, and reads resume with the bus
+    async fn check_bus_errors(&self) {
+        let mock = MockI2c::new();
+        let (driver, mut bus, _) = self.init(&mock).await;
+        mock.set_failing(true);
+        assert!(
+            driver.read(&mut bus).await.is_err(),
+            "[{}] read succeeded on a failing bus",
+            driver.id()
+        );
+        mock.set_failing(false);
+        if let Err(e) = driver.read(&mut bus).await {
+            panic!(
+                "[{}] read failed after the bus recovered: {}",
+                driver.id(),
+                e
+            );
+        }
+    }
+
+    async fn check_absent_chip(&self) {
+        let mut bus = I2CBus::mock(MockI2c::new());
+        let mut driver = (self.make)();
+        assert!(
+            driver.init(&mut bus).await.is_err(),
+            "[{}] init succeeded without a chip",
+            driver.id()
+        );
+    }
+
+    /// A chip that acknowledges but is not the expected one (all registers 0xFF, no replies)
+    async fn check_foreign_chip(&self) {
+        let mock = MockI2c::new();
+        mock.set_registers(self.address, 0, &[0xFF; 256]);
+        let mut bus = I2CBus::mock(mock);
+        let mut driver = (self.make)();
+        assert!(
+            driver.init(&mut bus).await.is_err(),
+            "[{}] init accepted a foreign chip",
+            driver.id()
+        );
+    }
+}
+
+fn magnitude(v: [f32; 3]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Units and ranges of a frame read from a chip at rest
+fn check_frame(id: &str, frame: &SensorDataFrame) {
+    let mut quantities = 0;
+    if let Some(accel) = frame.accel {
+        quantities += 1;
+        assert!(
+            ACCEL_AT_REST.contains(&magnitude(accel)),
+            "[{}] accel {:?} is not 1 g in m/s²",
+            id,
+            accel
+        );
+    }
+    if let Some(gyro) = frame.gyro {
+        quantities += 1;
+        assert!(
+            magnitude(gyro) < GYRO_AT_REST,
+            "[{}] gyro {:?} is not a slow rotation in rad/s",
+            id,
+            gyro
+        );
+    }
+    if let Some(mag) = frame.mag {
+        quantities += 1;
+        assert!(
+            MAG_EARTH.contains(&magnitude(mag)),
+            "[{}] mag {:?} is not the Earth's field in μT",
+            id,
+            mag
+        );
+    }
+    for temp in [frame.temp, frame.temp_pitot].into_iter().flatten() {
+        assert!(
+            TEMPERATURE_ROOM.contains(&temp),
+            "[{}] temperature {} is not room temperature in °C",
+            id,
+            temp
+        );
+    }
+    for pressure in [frame.pressure_static, frame.pressure_pitot]
+        .into_iter()
+        .flatten()
+    {
+        quantities += 1;
+        assert!(
+            PRESSURE_AMBIENT.contains(&pressure),
+            "[{}] pressure {} is not ambient pressure in Pa",
+            id,
+            pressure
+        );
+    }
+    assert!(quantities > 0, "[{}] read returned no data", id);
+}
@@ -1,7 +1,10 @@
-use super::{SensorDataFrame, SensorDriver};
+use super::{
+    check_params, param_choice, DriverParams, RegisterWindow, SensorDataFrame, SensorDriver,
+};
 use crate::bus::i2c::I2CBus;
 use crate::errors::{SensorError, SensorResult};
 use async_trait::async_trait;
+use tracing::warn;
 
 // Register addresses for the ICM42688P
 const WHO_AM_I: u8 = 0x75;
@@ -12,36 +15,94 @@ const ACCEL_CONFIG0: u8 = 0x50;
 const TEMP_DATA1: u8 = 0x1D;
 const ACCEL_DATA_X1: u8 = 0x1F;
 const GYRO_DATA_X1: u8 = 0x25;
+/// TEMP_DATA1 through GYRO_DATA_Z0
+const DATA_LEN: u8 = 14;
 const REG_BANK_SEL: u8 = 0x76;
+const INT_STATUS: u8 = 0x2D;
+/// RESET_DONE_INT in INT_STATUS
+const RESET_DONE: u8 = 0x10;
 
 // Expected WHO_AM_I values
 const WHOAMI_ICM42688P: u8 = 0x47;
 const WHOAMI_ICM42688: u8 = 0x44;
 
-// Sensitivity values
-const ACCEL_SENSITIVITY_2G: f32 = 16384.0; // LSB/g
-const GYRO_SENSITIVITY_250DPS: f32 = 131.0; // LSB/dps
+// Full-scale ranges and their FS_SEL codes (bits 7-5 of GYRO_CONFIG0/ACCEL_CONFIG0)
+const ACCEL_RANGES: [(f64, u8); 4] = [(16.0, 0b000), (8.0, 0b001), (4.0, 0b010), (2.0, 0b011)];
+const GYRO_RANGES: [(f64, u8); 8] = [
+    (2000.0, 0b000),
+    (1000.0, 0b001),
+    (500.0, 0b010),
+    (250.0, 0b011),
+    (125.0, 0b100),
+    (62.5, 0b101),
+    (31.25, 0b110),
+    (15.625, 0b111),
+];
+// Output data rates in low noise mode and their ODR codes (bits 3-0)
+const ODRS: [(f64, u8); 10] = [
+    (8000.0, 0b0011),
+    (4000.0, 0b0100),
+    (2000.0, 0b0101),
+    (1000.0, 0b0110),
+    (500.0, 0b1111),
+    (200.0, 0b0111),
+    (100.0, 0b1000),
+    (50.0, 0b1001),
+    (25.0, 0b1010),
+    (12.5, 0b1011),
+];
+
 const TEMP_SENSITIVITY: f32 = 132.48; // LSB/°C
 const TEMP_OFFSET: f32 = 25.0; // °C
 
 pub struct Icm42688p {
     id: String,
-    address: u8,
+    address: u16,
     bus_id: String,
+    /// Full scale (g, dps) and ODR code, ±2 g / ±250 dps at 100 Hz unless configured
+    accel_range: f64,
+    gyro_range: f64,
+    odr: u8,
 }
 
 impl Icm42688p {
-    pub fn new(id: String, address: u8, bus_id: String) -> Self {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
         Self {
             id,
             address,
             bus_id,
+            accel_range: 2.0,
+            gyro_range: 250.0,
+            odr: 0b1000,
         }
     }
+
+    fn fs_sel(ranges: &[(f64, u8)], range: f64) -> u8 {
+        ranges
+            .iter()
+            .find(|(r, _)| *r == range)
+            .map_or(0, |(_, code)| *code)
+    }
 }
 
 #[async_trait]
 impl SensorDriver for Icm42688p {
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        check_params(&self.id, params, &["accel_range", "gyro_range", "odr_hz"])?;
+        let accel = ACCEL_RANGES.map(|(range, _)| (range, range));
+        let gyro = GYRO_RANGES.map(|(range, _)| (range, range));
+        if let Some(range) = param_choice(&self.id, params, "accel_range", "g", &accel)? {
+            self.accel_range = range;
+        }
+        if let Some(range) = param_choice(&self.id, params, "gyro_range", "dps", &gyro)? {
+            self.gyro_range = range;
+        }
+        if let Some(odr) = param_choice(&self.id, params, "odr_hz", "Hz", &ODRS)? {
+            self.odr = odr;
+        }
+        Ok(())
+    }
+
     async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
         // Select Bank 0
         bus.write_byte(self.address, REG_BANK_SEL, 0x00)
@@ -72,8 +133,18 @@ impl SensorDriver for Icm42688p {
                 reason: format!("Failed to reset device: {}", e),
             })?;
 
-        // Wait for reset to complete (15ms per datasheet)
-        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        // Wait for reset to complete (up to 15ms per datasheet)
+        if !bus
+            .wait_for_bits(
+                self.address,
+                INT_STATUS,
+                RESET_DONE,
+                tokio::time::Duration::from_millis(20),
+            )
+            .await
+        {
+            warn!("[{}] ICM42688P reset-done flag not seen", self.id);
+        }
 
         // Configure power management - enable gyro and accel in low noise mode
         // Bits 3-2: Gyro mode = 11 (Low Noise)
@@ -85,20 +156,22 @@ impl SensorDriver for Icm42688p {
                 reason: format!("Failed to configure power management: {}", e),
             })?;
 
-        // Configure gyroscope: ±250 dps, 100 Hz ODR
-        // Bits 7-5: FS_SEL = 011 (±250 dps)
-        // Bits 3-0: ODR = 1000 (100 Hz)
-        bus.write_byte(self.address, GYRO_CONFIG0, 0x68)
+        // Configure gyroscope range and ODR
+        // Bits 7-5: FS_SEL (011 = ±250 dps)
+        // Bits 3-0: ODR (1000 = 100 Hz)
+        let gyro_config = Self::fs_sel(&GYRO_RANGES, self.gyro_range) << 5 | self.odr;
+        bus.write_byte(self.address, GYRO_CONFIG0, gyro_config)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
                 reason: format!("Failed to configure gyroscope: {}", e),
             })?;
 
-        // Configure accelerometer: ±2g, 100 Hz ODR
-        // Bits 7-5: FS_SEL = 011 (±2g)
-        // Bits 3-0: ODR = 1000 (100 Hz)
-        bus.write_byte(self.address, ACCEL_CONFIG0, 0x68)
+        // Configure accelerometer range and ODR
+        // Bits 7-5: FS_SEL (011 = ±2g)
+        // Bits 3-0: ODR (1000 = 100 Hz)
+        let accel_config = Self::fs_sel(&ACCEL_RANGES, self.accel_range) << 5 | self.odr;
+        bus.write_byte(self.address, ACCEL_CONFIG0, accel_config)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
@@ -112,68 +185,47 @@ impl SensorDriver for Icm42688p {
     }
 
     async fn read(&self, bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
-        let mut frame = SensorDataFrame::default();
-
-        // Read accelerometer data (6 bytes from ACCEL_DATA_X1)
-        let mut accel_buf = [0u8; 6];
-        bus.read_bytes(self.address, ACCEL_DATA_X1, &mut accel_buf)
+        // Temperature, accelerometer and gyroscope in one burst
+        let mut buf = [0u8; DATA_LEN as usize];
+        bus.read_bytes(self.address, TEMP_DATA1, &mut buf)
             .await
             .map_err(|e| SensorError::ReadError {
                 sensor: self.id.clone(),
-                reason: format!("Failed to read accelerometer: {}", e),
+                reason: format!("Failed to read sensor data: {}", e),
             })?;
+        self.decode_window(&buf)
+    }
+
+    fn register_window(&self) -> Option<RegisterWindow> {
+        Some(RegisterWindow {
+            address: self.address,
+            start: TEMP_DATA1,
+            len: DATA_LEN,
+        })
+    }
 
+    fn decode_window(&self, data: &[u8]) -> SensorResult<SensorDataFrame> {
+        let mut frame = SensorDataFrame::default();
         // Data is big-endian in ICM42688P
-        let accel_raw = [
-            i16::from_be_bytes([accel_buf[0], accel_buf[1]]),
-            i16::from_be_bytes([accel_buf[2], accel_buf[3]]),
-            i16::from_be_bytes([accel_buf[4], accel_buf[5]]),
-        ];
+        let word = |reg: u8, axis: usize| {
+            let i = (reg - TEMP_DATA1) as usize + axis * 2;
+            i16::from_be_bytes([data[i], data[i + 1]])
+        };
 
-        // Convert to m/s^2
-        frame.accel = Some([
-            (accel_raw[0] as f32 / ACCEL_SENSITIVITY_2G) * 9.81,
-            (accel_raw[1] as f32 / ACCEL_SENSITIVITY_2G) * 9.81,
-            (accel_raw[2] as f32 / ACCEL_SENSITIVITY_2G) * 9.81,
-        ]);
-
-        // Read gyroscope data (6 bytes from GYRO_DATA_X1)
-        let mut gyro_buf = [0u8; 6];
-        bus.read_bytes(self.address, GYRO_DATA_X1, &mut gyro_buf)
-            .await
-            .map_err(|e| SensorError::ReadError {
-                sensor: self.id.clone(),
-                reason: format!("Failed to read gyroscope: {}", e),
-            })?;
+        // Full scale maps to ±32768 LSB
+        let accel_scale = (self.accel_range / 32768.0) as f32;
+        let gyro_scale = (self.gyro_range / 32768.0) as f32;
 
-        // Data is big-endian in ICM42688P
-        let gyro_raw = [
-            i16::from_be_bytes([gyro_buf[0], gyro_buf[1]]),
-            i16::from_be_bytes([gyro_buf[2], gyro_buf[3]]),
-            i16::from_be_bytes([gyro_buf[4], gyro_buf[5]]),
-        ];
-
-        // Convert to degrees per second
-        frame.gyro = Some([
-            gyro_raw[0] as f32 / GYRO_SENSITIVITY_250DPS,
-            gyro_raw[1] as f32 / GYRO_SENSITIVITY_250DPS,
-            gyro_raw[2] as f32 / GYRO_SENSITIVITY_250DPS,
-        ]);
-
-        // Read temperature data (2 bytes)
-        let mut temp_buf = [0u8; 2];
-        bus.read_bytes(self.address, TEMP_DATA1, &mut temp_buf)
-            .await
-            .map_err(|e| SensorError::ReadError {
-                sensor: self.id.clone(),
-                reason: format!("Failed to read temperature: {}", e),
-            })?;
+        // Convert to m/s^2
+        frame.accel =
+            Some([0, 1, 2].map(|axis| word(ACCEL_DATA_X1, axis) as f32 * accel_scale * 9.81));
 
-        // Temperature data is big-endian
-        let temp_raw = i16::from_be_bytes([temp_buf[0], temp_buf[1]]);
+        // Convert to rad/s
+        frame.gyro =
+            Some([0, 1, 2].map(|axis| (word(GYRO_DATA_X1, axis) as f32 * gyro_scale).to_radians()));
 
         // Convert to Celsius
-        frame.temp = Some((temp_raw as f32 / TEMP_SENSITIVITY) + TEMP_OFFSET);
+        frame.temp = Some((word(TEMP_DATA1, 0) as f32 / TEMP_SENSITIVITY) + TEMP_OFFSET);
 
         Ok(frame)
     }
@@ -190,3 +242,37 @@ impl SensorDriver for Icm42688p {
         self
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Icm42688p::new("imu0".into(), 0x68, "i2c1".into())),
+            address: 0x68,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x68, WHO_AM_I, &[WHOAMI_ICM42688P]);
+                mock.set_registers(0x68, INT_STATUS, &[RESET_DONE]);
+                // 27 °C; accel 1 g on z; gyro 1.5 °/s about x
+                mock.set_registers(
+                    0x68,
+                    TEMP_DATA1,
+                    &[0x01, 0x09, 0, 0, 0, 0, 0x40, 0x00, 0x00, 0xC5, 0, 0, 0, 0],
+                );
+            },
+            init_writes: vec![
+                Write::Register(REG_BANK_SEL, 0x00),
+                Write::Register(DEVICE_CONFIG, 0x01),
+                Write::Register(PWR_MGMT0, 0x0F),
+                Write::Register(GYRO_CONFIG0, 0x68),
+                Write::Register(ACCEL_CONFIG0, 0x68),
+            ],
+        }
+        .check()
+        .await;
+    }
+}
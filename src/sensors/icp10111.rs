@@ -0,0 +1,243 @@
+use super::{SensorDataFrame, SensorDriver};
+use crate::bus::i2c::I2CBus;
+use crate::errors::{SensorError, SensorResult};
+use async_trait::async_trait;
+use tracing::debug;
+
+// ICP-10111 is command based: every transaction starts with a 16-bit command
+const CMD_SOFT_RESET: [u8; 2] = [0x80, 0x5D];
+const CMD_READ_ID: [u8; 2] = [0xEF, 0xC8];
+/// Set up OTP read pointer (command + address 0x0066 + CRC)
+const CMD_OTP_SETUP: [u8; 5] = [0xC5, 0x95, 0x00, 0x66, 0x9C];
+const CMD_OTP_READ: [u8; 2] = [0xC7, 0xF7];
+/// Low-noise measurement, temperature transmitted first (23.8 ms conversion)
+const CMD_MEASURE_LN: [u8; 2] = [0x70, 0xDF];
+
+const PRODUCT_ID: u8 = 0x08;
+const PRODUCT_ID_MASK: u16 = 0x3F;
+
+// Conversion constants from the TDK reference implementation
+const P_PA_CALIB: [f64; 3] = [45000.0, 80000.0, 105000.0];
+const LUT_LOWER: f64 = 3.5 * 1048576.0;
+const LUT_UPPER: f64 = 11.5 * 1048576.0;
+const QUADR_FACTOR: f64 = 1.0 / 16777216.0;
+const OFFST_FACTOR: f64 = 2048.0;
+
+/// TDK ICP-10111 high-resolution barometer
+///
+/// Measurements are pipelined: each read collects the conversion started by the
+/// previous read (or by init) and immediately triggers the next one, so the bus is
+/// never held for the 24 ms conversion time. Poll at 40 Hz or slower.
+pub struct Icp10111 {
+    id: String,
+    address: u16,
+    bus_id: String,
+    /// OTP sensor constants c1..c4
+    otp: Option<[f64; 4]>,
+}
+
+impl Icp10111 {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
+        Self {
+            id,
+            address,
+            bus_id,
+            otp: None,
+        }
+    }
+
+    /// Read 2-byte words, each followed by a CRC byte
+    async fn read_words<const N: usize>(&self, bus: &mut I2CBus) -> SensorResult<[u16; N]> {
+        let mut buf = vec![0u8; N * 3];
+        bus.read(self.address, &mut buf).await?;
+
+        let mut words = [0u16; N];
+        for (i, chunk) in buf.chunks_exact(3).enumerate() {
+            if crc8(&chunk[..2]) != chunk[2] {
+                return Err(SensorError::DataError {
+                    sensor: self.id.clone(),
+                    reason: format!("CRC mismatch in word {}", i),
+                });
+            }
+            words[i] = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+        Ok(words)
+    }
+}
+
+#[async_trait]
+impl SensorDriver for Icp10111 {
+    async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
+        bus.write(self.address, &CMD_SOFT_RESET)
+            .await
+            .map_err(|e| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to reset sensor: {}", e),
+            })?;
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+
+        // Verify device identity
+        bus.write(self.address, &CMD_READ_ID).await?;
+        let [id_word] = self.read_words::<1>(bus).await?;
+        let product_id = (id_word & PRODUCT_ID_MASK) as u8;
+        if product_id != PRODUCT_ID {
+            return Err(SensorError::WrongChipId {
+                sensor: self.id.clone(),
+                expected: PRODUCT_ID,
+                actual: product_id,
+            });
+        }
+
+        // Read the four OTP calibration constants
+        bus.write(self.address, &CMD_OTP_SETUP).await.map_err(|e| {
+            SensorError::CalibrationError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to set up OTP read: {}", e),
+            }
+        })?;
+        let mut otp = [0f64; 4];
+        for value in otp.iter_mut() {
+            bus.write(self.address, &CMD_OTP_READ).await?;
+            let [word] =
+                self.read_words::<1>(bus)
+                    .await
+                    .map_err(|e| SensorError::CalibrationError {
+                        sensor: self.id.clone(),
+                        reason: format!("Failed to read OTP: {}", e),
+                    })?;
+            *value = word as i16 as f64;
+        }
+        self.otp = Some(otp);
+
+        // Start the first conversion so the first read has data
+        bus.write(self.address, &CMD_MEASURE_LN)
+            .await
+            .map_err(|e| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to start measurement: {}", e),
+            })?;
+
+        debug!("[{}] ICP-10111 OTP constants loaded: {:?}", self.id, otp);
+        Ok(())
+    }
+
+    async fn read(&self, bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
+        let otp = self.otp.as_ref().ok_or_else(|| SensorError::DataError {
+            sensor: self.id.clone(),
+            reason: "Calibration not loaded".to_string(),
+        })?;
+
+        // T first: [T_MSB T_LSB][P_MMSB P_MLSB][P_LSB unused]
+        let words = self
+            .read_words::<3>(bus)
+            .await
+            .map_err(|e| SensorError::ReadError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to read measurement: {}", e),
+            })?;
+
+        // Kick off the next conversion before doing the math
+        bus.write(self.address, &CMD_MEASURE_LN)
+            .await
+            .map_err(|e| SensorError::ReadError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to start measurement: {}", e),
+            })?;
+
+        let t_raw = words[0] as f64;
+        let p_raw = (((words[1] as u32) << 8) | (words[2] as u32 >> 8)) as f64;
+        let (pressure, temperature) = compensate(otp, p_raw, t_raw);
+
+        Ok(SensorDataFrame {
+            temp: Some(temperature as f32),
+            pressure_static: Some(pressure as f32),
+            ..Default::default()
+        })
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn bus(&self) -> &str {
+        &self.bus_id
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// Convert raw readings to (pressure Pa, temperature °C) using the OTP constants
+fn compensate(otp: &[f64; 4], p_raw: f64, t_raw: f64) -> (f64, f64) {
+    let t = t_raw - 32768.0;
+    let s1 = LUT_LOWER + otp[0] * t * t * QUADR_FACTOR;
+    let s2 = OFFST_FACTOR * otp[3] + otp[1] * t * t * QUADR_FACTOR;
+    let s3 = LUT_UPPER + otp[2] * t * t * QUADR_FACTOR;
+    let lut = [s1, s2, s3];
+    let p = P_PA_CALIB;
+
+    let c = (lut[0] * lut[1] * (p[0] - p[1])
+        + lut[1] * lut[2] * (p[1] - p[2])
+        + lut[2] * lut[0] * (p[2] - p[0]))
+        / (lut[2] * (p[0] - p[1]) + lut[0] * (p[1] - p[2]) + lut[1] * (p[2] - p[0]));
+    let a = (p[0] * lut[0] - p[1] * lut[1] - (p[1] - p[0]) * c) / (lut[0] - lut[1]);
+    let b = (p[0] - a) * (lut[0] + c);
+
+    let pressure = a + b / (c + p_raw);
+    let temperature = -45.0 + 175.0 / 65536.0 * t_raw;
+    (pressure, temperature)
+}
+
+/// CRC-8 (poly 0x31, init 0xFF) protecting every 16-bit word
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xFF;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    /// A word as sent by the chip, with its CRC
+    fn word(value: u16) -> Vec<u8> {
+        let [msb, lsb] = value.to_be_bytes();
+        vec![msb, lsb, crc8(&[msb, lsb])]
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Icp10111::new("baro0".into(), 0x63, "i2c1".into())),
+            address: 0x63,
+            chip: |mock: &MockI2c| {
+                mock.set_replies(0x63, &CMD_READ_ID, &[&word(PRODUCT_ID as u16)]);
+                let (c, c4) = (word(4000), word(3000));
+                mock.set_replies(0x63, &CMD_OTP_READ, &[&c, &c, &c, &c4]);
+                // 25 °C, then about 101.1 kPa with those constants
+                let measurement = [word(0x6666), word(0xA000), word(0x0000)].concat();
+                mock.set_replies(0x63, &CMD_MEASURE_LN, &[&measurement]);
+            },
+            init_writes: vec![
+                Write::Command(CMD_SOFT_RESET.to_vec()),
+                Write::Command(CMD_READ_ID.to_vec()),
+                Write::Command(CMD_OTP_SETUP.to_vec()),
+                Write::Command(CMD_MEASURE_LN.to_vec()),
+            ],
+        }
+        .check()
+        .await;
+    }
+}
@@ -0,0 +1,139 @@
+use super::{SensorDataFrame, SensorDriver};
+use crate::bus::i2c::I2CBus;
+use crate::errors::{SensorError, SensorResult};
+use async_trait::async_trait;
+use tracing::debug;
+
+// ICP-20100 register map
+const REG_DEVICE_ID: u8 = 0x0C;
+const REG_MODE_SELECT: u8 = 0xC0;
+const REG_VERSION: u8 = 0xD3;
+/// PRESS_DATA_0..2 followed by TEMP_DATA_0..2
+const REG_PRESS_DATA_0: u8 = 0xFA;
+
+const DEVICE_ID: u8 = 0x63;
+/// Version B parts are trimmed in the factory and need no OTP boot sequence
+const VERSION_B: u8 = 0xB2;
+/// Mode 0 (14 Hz, lowest noise), continuous measurement, power mode normal
+const MODE_CONTINUOUS_MODE0: u8 = 0x08;
+
+/// TDK ICP-20100 high-resolution barometer
+pub struct Icp20100 {
+    id: String,
+    address: u16,
+    bus_id: String,
+}
+
+impl Icp20100 {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
+        Self {
+            id,
+            address,
+            bus_id,
+        }
+    }
+}
+
+#[async_trait]
+impl SensorDriver for Icp20100 {
+    async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
+        let mut id_buf = [0u8; 1];
+        bus.read_bytes(self.address, REG_DEVICE_ID, &mut id_buf)
+            .await?;
+        if id_buf[0] != DEVICE_ID {
+            return Err(SensorError::WrongChipId {
+                sensor: self.id.clone(),
+                expected: DEVICE_ID,
+                actual: id_buf[0],
+            });
+        }
+
+        let mut version = [0u8; 1];
+        bus.read_bytes(self.address, REG_VERSION, &mut version)
+            .await?;
+        if version[0] != VERSION_B {
+            return Err(SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: format!(
+                    "Version {:#04x} requires the OTP boot sequence, only version B (0xb2) is supported",
+                    version[0]
+                ),
+            });
+        }
+
+        bus.write_byte(self.address, REG_MODE_SELECT, MODE_CONTINUOUS_MODE0)
+            .await
+            .map_err(|e| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to set measurement mode: {}", e),
+            })?;
+
+        debug!("[{}] ICP-20100 initialized in continuous mode", self.id);
+        Ok(())
+    }
+
+    async fn read(&self, bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
+        let mut data = [0u8; 6];
+        bus.read_bytes(self.address, REG_PRESS_DATA_0, &mut data)
+            .await
+            .map_err(|e| SensorError::ReadError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to read data: {}", e),
+            })?;
+
+        let p_raw = sign_extend_20(data[0], data[1], data[2]) as f64;
+        let t_raw = sign_extend_20(data[3], data[4], data[5]) as f64;
+
+        // Datasheet transfer functions: P in kPa, T in °C
+        let pressure_kpa = p_raw / 131072.0 * 40.0 + 70.0;
+        let temperature = t_raw / 262144.0 * 65.0 + 25.0;
+
+        Ok(SensorDataFrame {
+            temp: Some(temperature as f32),
+            pressure_static: Some((pressure_kpa * 1000.0) as f32),
+            ..Default::default()
+        })
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn bus(&self) -> &str {
+        &self.bus_id
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// Assemble a 20-bit two's-complement value sent LSB first
+fn sign_extend_20(b0: u8, b1: u8, b2: u8) -> i32 {
+    let raw = (b0 as u32) | ((b1 as u32) << 8) | (((b2 & 0x0F) as u32) << 16);
+    ((raw << 12) as i32) >> 12
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Icp20100::new("baro0".into(), 0x63, "i2c1".into())),
+            address: 0x63,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x63, REG_DEVICE_ID, &[DEVICE_ID]);
+                mock.set_registers(0x63, REG_VERSION, &[VERSION_B]);
+                // 101.3 kPa, 25 °C
+                mock.set_registers(0x63, REG_PRESS_DATA_0, &[0xA3, 0x90, 0x01, 0, 0, 0]);
+            },
+            init_writes: vec![Write::Register(REG_MODE_SELECT, MODE_CONTINUOUS_MODE0)],
+        }
+        .check()
+        .await;
+    }
+}
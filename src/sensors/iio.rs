@@ -0,0 +1,705 @@
+use super::{frame_to_messages, SensorDataFrame, SensorDriver};
+use crate::bus::i2c::I2CBus;
+use crate::errors::{SensorError, SensorResult};
+use crate::grpc_service::SensorHubService;
+use crate::messages::Header;
+use async_trait::async_trait;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::time::{Duration, MissedTickBehavior};
+use tracing::{error, info, trace, warn};
+
+const IIO_DEVICES: &str = "/sys/bus/iio/devices";
+/// Samples the kernel buffers between reads in buffered mode
+const BUFFER_LENGTH: u32 = 64;
+
+/// Measurement a channel contributes to, with the factor from IIO units to hub units
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantity {
+    /// m/s² in both
+    Accel,
+    /// rad/s in both
+    Gyro,
+    /// Gauss to μT
+    Mag,
+    /// kPa to Pa
+    Pressure,
+    /// Milli-degrees to °C
+    Temp,
+}
+
+impl Quantity {
+    fn unit_factor(self) -> f64 {
+        match self {
+            Quantity::Accel | Quantity::Gyro => 1.0,
+            Quantity::Mag => 100.0,
+            Quantity::Pressure => 1000.0,
+            Quantity::Temp => 0.001,
+        }
+    }
+}
+
+/// Map a channel name such as `in_accel_x` to its quantity and axis
+fn classify(channel: &str) -> Option<(Quantity, usize)> {
+    let rest = channel.strip_prefix("in_")?;
+    let (kind, axis) = match rest.rsplit_once('_') {
+        Some((kind, "x")) => (kind, 0),
+        Some((kind, "y")) => (kind, 1),
+        Some((kind, "z")) => (kind, 2),
+        _ => (rest, 0),
+    };
+    let quantity = match kind {
+        "accel" => Quantity::Accel,
+        "anglvel" => Quantity::Gyro,
+        "magn" => Quantity::Mag,
+        "pressure" => Quantity::Pressure,
+        "temp" => Quantity::Temp,
+        _ => return None,
+    };
+    Some((quantity, axis))
+}
+
+/// Sample layout of a buffered channel, parsed from `scan_elements/<channel>_type`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ScanType {
+    big_endian: bool,
+    signed: bool,
+    bits: u32,
+    storage_bytes: usize,
+    shift: u32,
+}
+
+impl ScanType {
+    /// Parse e.g. "le:s16/16>>0" or "be:u24/32>>8"
+    fn parse(s: &str) -> Option<Self> {
+        let (endian, rest) = s.trim().split_once(':')?;
+        let signed = match rest.as_bytes().first()? {
+            b's' => true,
+            b'u' => false,
+            _ => return None,
+        };
+        let (bits, rest) = rest[1..].split_once('/')?;
+        let (storage, shift) = rest.split_once(">>")?;
+        // Repeated channels ("16X2") are not used by any quantity we publish
+        let storage_bits: u32 = storage.split('X').next()?.parse().ok()?;
+        let scan_type = ScanType {
+            big_endian: endian == "be",
+            signed,
+            bits: bits.parse().ok()?,
+            storage_bytes: storage_bits as usize / 8,
+            shift: shift.parse().ok()?,
+        };
+        let valid = matches!(scan_type.storage_bytes, 1 | 2 | 4 | 8)
+            && scan_type.bits >= 1
+            && scan_type.bits + scan_type.shift <= storage_bits;
+        valid.then_some(scan_type)
+    }
+
+    /// Raw value of this channel in `bytes` (exactly `storage_bytes` long)
+    fn decode(&self, bytes: &[u8]) -> i64 {
+        let mut word = [0u8; 8];
+        let value = if self.big_endian {
+            word[8 - bytes.len()..].copy_from_slice(bytes);
+            u64::from_be_bytes(word)
+        } else {
+            word[..bytes.len()].copy_from_slice(bytes);
+            u64::from_le_bytes(word)
+        };
+        let value = value >> self.shift;
+        let unused = 64 - self.bits;
+        if self.signed {
+            ((value << unused) as i64) >> unused
+        } else {
+            ((value << unused) >> unused) as i64
+        }
+    }
+}
+
+/// One channel we publish, with its conversion to hub units
+#[derive(Debug, Clone)]
+struct Channel {
+    name: String,
+    quantity: Quantity,
+    axis: usize,
+    scale: f64,
+    offset: f64,
+    /// Sysfs attribute read in direct mode (`_raw`, or `_input` if already processed)
+    value_file: PathBuf,
+    /// Byte offset and format within a buffered sample
+    scan: Option<(usize, ScanType)>,
+}
+
+impl Channel {
+    fn convert(&self, raw: f64) -> f64 {
+        (raw + self.offset) * self.scale * self.quantity.unit_factor()
+    }
+}
+
+/// An IIO device opened for reading
+struct IioDevice {
+    name: String,
+    channels: Vec<Channel>,
+    /// Character device and bytes per sample when reading through the kernel buffer
+    buffer: Option<(PathBuf, usize)>,
+}
+
+/// Resolve the bus path to a sysfs directory and, for `/dev/iio:deviceN`, the buffer device
+///
+/// Accepted forms are the device node, a sysfs directory, or the device `name` attribute
+/// (e.g. "mpu6050").
+fn resolve(path: &str) -> io::Result<(PathBuf, Option<PathBuf>)> {
+    if let Some(device) = path.strip_prefix("/dev/") {
+        return Ok((
+            Path::new(IIO_DEVICES).join(device),
+            Some(PathBuf::from(path)),
+        ));
+    }
+    if path.starts_with('/') {
+        return Ok((PathBuf::from(path), None));
+    }
+    for entry in fs::read_dir(IIO_DEVICES)? {
+        let dir = entry?.path();
+        if read_attr(&dir.join("name")).is_some_and(|name| name == path) {
+            return Ok((dir, None));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no IIO device named '{}'", path),
+    ))
+}
+
+fn read_attr(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn write_attr(path: &Path, value: &str) -> io::Result<()> {
+    fs::write(path, value)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Per-channel attribute, falling back to the one shared by all channels of the type
+/// (`in_accel_x_scale`, then `in_accel_scale`)
+fn channel_attr(dir: &Path, channel: &str, attr: &str) -> Option<f64> {
+    let shared = channel
+        .rsplit_once('_')
+        .filter(|(_, axis)| matches!(*axis, "x" | "y" | "z"))
+        .map_or(channel, |(kind, _)| kind);
+    [channel, shared]
+        .iter()
+        .find_map(|name| read_attr(&dir.join(format!("{}_{}", name, attr))))
+        .and_then(|value| value.parse().ok())
+}
+
+impl IioDevice {
+    fn open(path: &str) -> io::Result<Self> {
+        let (dir, chardev) = resolve(path)?;
+        let name = read_attr(&dir.join("name")).unwrap_or_else(|| dir.display().to_string());
+
+        let mut channels = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let file = entry?.file_name().to_string_lossy().into_owned();
+            let (channel, processed) =
+                match (file.strip_suffix("_raw"), file.strip_suffix("_input")) {
+                    (Some(channel), _) => (channel.to_string(), false),
+                    (_, Some(channel)) => (channel.to_string(), true),
+                    _ => continue,
+                };
+            let Some((quantity, axis)) = classify(&channel) else {
+                continue;
+            };
+            // A channel with both attributes is read raw so buffered and direct data agree
+            if processed && dir.join(format!("{}_raw", channel)).exists() {
+                continue;
+            }
+            let (scale, offset) = if processed {
+                (1.0, 0.0)
+            } else {
+                (
+                    channel_attr(&dir, &channel, "scale").unwrap_or(1.0),
+                    channel_attr(&dir, &channel, "offset").unwrap_or(0.0),
+                )
+            };
+            channels.push(Channel {
+                value_file: dir.join(&file),
+                name: channel,
+                quantity,
+                axis,
+                scale,
+                offset,
+                scan: None,
+            });
+        }
+        if channels.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} has no accel, anglvel, magn, pressure or temp channels",
+                    name
+                ),
+            ));
+        }
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let buffer = match chardev {
+            Some(chardev) => {
+                let sample_bytes = enable_buffer(&dir, &mut channels)?;
+                Some((chardev, sample_bytes))
+            }
+            None => None,
+        };
+        Ok(Self {
+            name,
+            channels,
+            buffer,
+        })
+    }
+
+    /// Read every channel from sysfs
+    fn read_direct(&self) -> io::Result<SensorDataFrame> {
+        let mut values = Vec::with_capacity(self.channels.len());
+        for channel in self.channels.iter() {
+            let text = fs::read_to_string(&channel.value_file)?;
+            let raw: f64 = text.trim().parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: unexpected value '{}'", channel.name, text.trim()),
+                )
+            })?;
+            values.push(channel.convert(raw));
+        }
+        Ok(self.frame(&values))
+    }
+
+    /// Decode one sample read from the buffer device
+    fn decode_sample(&self, sample: &[u8]) -> SensorDataFrame {
+        let values: Vec<f64> = self
+            .channels
+            .iter()
+            .map(|channel| match channel.scan {
+                Some((offset, scan)) => channel
+                    .convert(scan.decode(&sample[offset..offset + scan.storage_bytes]) as f64),
+                None => 0.0,
+            })
+            .collect();
+        self.frame(&values)
+    }
+
+    /// Readings in hub units; accel and gyro are also kept at the f64 precision they were
+    /// scaled in, for sensors with `precision = "f64"`
+    fn frame(&self, values: &[f64]) -> SensorDataFrame {
+        let mut frame = SensorDataFrame::default();
+        for (channel, precise) in self.channels.iter().zip(values) {
+            let value = *precise as f32;
+            let vector = match channel.quantity {
+                Quantity::Accel => {
+                    frame.accel_f64.get_or_insert([0.0; 3])[channel.axis] = *precise;
+                    &mut frame.accel
+                }
+                Quantity::Gyro => {
+                    frame.gyro_f64.get_or_insert([0.0; 3])[channel.axis] = *precise;
+                    &mut frame.gyro
+                }
+                Quantity::Mag => &mut frame.mag,
+                Quantity::Pressure => {
+                    frame.pressure_static = Some(value);
+                    continue;
+                }
+                Quantity::Temp => {
+                    frame.temp = Some(value);
+                    continue;
+                }
+            };
+            vector.get_or_insert([0.0; 3])[channel.axis] = value;
+        }
+        frame
+    }
+}
+
+/// Enable our channels in the kernel buffer and return the size of one sample
+///
+/// The trigger (`trigger/current_trigger`) is left as configured on the system, since
+/// which trigger is right depends on the device.
+fn enable_buffer(dir: &Path, channels: &mut [Channel]) -> io::Result<usize> {
+    let scan_dir = dir.join("scan_elements");
+    // The scan elements cannot change while the buffer is running
+    write_attr(&dir.join("buffer/enable"), "0")?;
+
+    for entry in fs::read_dir(&scan_dir)? {
+        let file = entry?.file_name().to_string_lossy().into_owned();
+        if let Some(channel) = file.strip_suffix("_en") {
+            let wanted = channels.iter().any(|c| c.name == channel);
+            write_attr(&scan_dir.join(&file), if wanted { "1" } else { "0" })?;
+        }
+    }
+
+    let mut scans = Vec::with_capacity(channels.len());
+    for channel in channels.iter() {
+        let attr = |suffix: &str| read_attr(&scan_dir.join(format!("{}_{}", channel.name, suffix)));
+        let index: u32 = attr("index").and_then(|v| v.parse().ok()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} cannot be buffered", channel.name),
+            )
+        })?;
+        let scan = attr("type")
+            .and_then(|t| ScanType::parse(&t))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: unsupported scan type", channel.name),
+                )
+            })?;
+        scans.push((index, scan));
+    }
+    let (offsets, sample_bytes) = layout(&scans);
+    for ((channel, offset), (_, scan)) in channels.iter_mut().zip(offsets).zip(scans) {
+        channel.scan = Some((offset, scan));
+    }
+
+    write_attr(&dir.join("buffer/length"), &BUFFER_LENGTH.to_string())?;
+    write_attr(&dir.join("buffer/enable"), "1")?;
+    Ok(sample_bytes)
+}
+
+/// Byte offset of each enabled channel and the total sample size
+///
+/// The kernel packs channels in scan index order, each aligned to its own storage
+/// size, and pads the sample to a multiple of the largest storage size.
+fn layout(scans: &[(u32, ScanType)]) -> (Vec<usize>, usize) {
+    let mut order: Vec<usize> = (0..scans.len()).collect();
+    order.sort_by_key(|&i| scans[i].0);
+
+    let mut offsets = vec![0; scans.len()];
+    let mut size = 0usize;
+    let mut largest = 1;
+    for i in order {
+        let storage = scans[i].1.storage_bytes;
+        size = size.next_multiple_of(storage);
+        offsets[i] = size;
+        size += storage;
+        largest = largest.max(storage);
+    }
+    (offsets, size.next_multiple_of(largest))
+}
+
+/// Push-based sensor publishing an IIO device handled by a kernel driver
+pub struct IioSensor {
+    id: String,
+    bus_id: String,
+    grpc_service: Option<Arc<SensorHubService>>,
+    started: bool,
+}
+
+impl IioSensor {
+    pub fn new(id: String, bus_id: String) -> Self {
+        Self {
+            id,
+            bus_id,
+            grpc_service: None,
+            started: false,
+        }
+    }
+
+    /// Set the gRPC service for publishing sensor data
+    pub fn set_grpc_service(&mut self, service: Arc<SensorHubService>) {
+        self.grpc_service = Some(service);
+    }
+
+    /// Open the device and start the read loop
+    /// `frequency` is the sysfs poll rate; buffered devices deliver at their trigger rate
+    pub fn start(&mut self, path: &str, frequency: u32) -> SensorResult<()> {
+        let grpc = self
+            .grpc_service
+            .clone()
+            .ok_or_else(|| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: "gRPC service not set".to_string(),
+            })?;
+        if self.started {
+            return Err(SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: "IIO read loop already started".to_string(),
+            });
+        }
+
+        // Open once up front so a wrong path fails at startup rather than in the background
+        let device = IioDevice::open(path).map_err(|e| SensorError::InitError {
+            sensor: self.id.clone(),
+            reason: format!("IIO device {}: {}", path, e),
+        })?;
+        let channels: Vec<&str> = device.channels.iter().map(|c| c.name.as_str()).collect();
+        info!(
+            "[{}] Opened IIO device {} ({}, {}): {}",
+            self.id,
+            device.name,
+            path,
+            if device.buffer.is_some() {
+                "buffered"
+            } else {
+                "polled"
+            },
+            channels.join(", ")
+        );
+
+        let poll_period = Duration::from_millis((1000.0 / frequency.max(1) as f32) as u64);
+        tokio::spawn(read_loop(
+            self.id.clone(),
+            path.to_string(),
+            device,
+            poll_period,
+            grpc,
+        ));
+        self.started = true;
+        Ok(())
+    }
+}
+
+/// Read samples and publish them; re-open the device if it goes away
+async fn read_loop(
+    sensor_id: String,
+    path: String,
+    device: IioDevice,
+    poll_period: Duration,
+    grpc: Arc<SensorHubService>,
+) {
+    let mut sequence_counter = 0u64;
+    let mut device = Arc::new(device);
+
+    loop {
+        let error = match device.buffer.clone() {
+            Some((chardev, sample_bytes)) => {
+                read_buffered(
+                    &sensor_id,
+                    &device,
+                    chardev,
+                    sample_bytes,
+                    &grpc,
+                    &mut sequence_counter,
+                )
+                .await
+            }
+            None => {
+                read_polled(
+                    &sensor_id,
+                    &device,
+                    poll_period,
+                    &grpc,
+                    &mut sequence_counter,
+                )
+                .await
+            }
+        };
+        warn!("[{}] IIO read failed: {}", sensor_id, error);
+
+        // Device lost - announce the sensor as gone and keep trying to re-open it
+        let info = grpc.remove_sensor(&sensor_id, "device lost").await;
+        device = loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let open_path = path.clone();
+            match tokio::task::spawn_blocking(move || IioDevice::open(&open_path)).await {
+                Ok(Ok(device)) => {
+                    info!("[{}] Re-opened IIO device {}", sensor_id, device.name);
+                    if let Some(info) = &info {
+                        grpc.register_sensor(
+                            &info.sensor_id,
+                            &info.driver,
+                            &info.bus_id,
+                            &info.role,
+                        )
+                        .await;
+                    }
+                    break Arc::new(device);
+                }
+                Ok(Err(e)) => trace!("[{}] IIO device {} not available: {}", sensor_id, path, e),
+                Err(e) => error!("[{}] IIO open task failed: {}", sensor_id, e),
+            }
+        };
+    }
+}
+
+/// Poll sysfs attributes at a fixed rate until a read fails
+async fn read_polled(
+    sensor_id: &str,
+    device: &Arc<IioDevice>,
+    poll_period: Duration,
+    grpc: &SensorHubService,
+    sequence_counter: &mut u64,
+) -> io::Error {
+    let mut ticker = tokio::time::interval(poll_period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let reader = device.clone();
+        let frame = match tokio::task::spawn_blocking(move || reader.read_direct()).await {
+            Ok(Ok(frame)) => frame,
+            Ok(Err(e)) => return e,
+            Err(e) => return io::Error::other(e),
+        };
+        publish(sensor_id, frame, grpc, sequence_counter).await;
+    }
+}
+
+/// Read samples from the buffer character device until it fails
+async fn read_buffered(
+    sensor_id: &str,
+    device: &Arc<IioDevice>,
+    chardev: PathBuf,
+    sample_bytes: usize,
+    grpc: &SensorHubService,
+    sequence_counter: &mut u64,
+) -> io::Error {
+    // Reads block until the trigger fires, so they run on a dedicated thread
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(BUFFER_LENGTH as usize);
+    let reader = tokio::task::spawn_blocking(move || -> io::Error {
+        let mut file = match fs::File::open(&chardev) {
+            Ok(file) => file,
+            Err(e) => return e,
+        };
+        loop {
+            let mut sample = vec![0u8; sample_bytes];
+            if let Err(e) = file.read_exact(&mut sample) {
+                return e;
+            }
+            if tx.blocking_send(sample).is_err() {
+                return io::Error::other("reader stopped");
+            }
+        }
+    });
+
+    while let Some(sample) = rx.recv().await {
+        let frame = device.decode_sample(&sample);
+        publish(sensor_id, frame, grpc, sequence_counter).await;
+    }
+    match reader.await {
+        Ok(e) => e,
+        Err(e) => io::Error::other(e),
+    }
+}
+
+async fn publish(
+    sensor_id: &str,
+    frame: SensorDataFrame,
+    grpc: &SensorHubService,
+    sequence_counter: &mut u64,
+) {
+    *sequence_counter += 1;
+    let header = Header::new(
+        "navigate_hub".to_string(),
+        sensor_id.to_string(),
+        "sensor_frame".to_string(),
+        *sequence_counter,
+    );
+    for msg in frame_to_messages(frame, header, sensor_id) {
+        if let Err(e) = grpc.publish(msg).await {
+            error!("[{}] Failed to publish: {}", sensor_id, e);
+        }
+    }
+}
+
+/// Implement SensorDriver trait for compatibility
+/// Note: IIO sensors are push-based, like UART sensors
+#[async_trait]
+impl SensorDriver for IioSensor {
+    async fn init(&mut self, _bus: &mut I2CBus) -> SensorResult<()> {
+        // IIO sensors initialize via start()
+        if self.started {
+            Ok(())
+        } else {
+            Err(SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: "IIO read loop not started".to_string(),
+            })
+        }
+    }
+
+    async fn read(&self, _bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
+        Err(SensorError::ReadError {
+            sensor: self.id.clone(),
+            reason: "IIO sensors are push-based, data published via gRPC stream".to_string(),
+        })
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn bus(&self) -> &str {
+        &self.bus_id
+    }
+
+    fn is_push_based(&self) -> bool {
+        true
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::SensorMessage;
+
+    #[test]
+    fn test_scan_layout_and_decode() {
+        assert_eq!(classify("in_anglvel_z"), Some((Quantity::Gyro, 2)));
+        assert_eq!(classify("in_temp"), Some((Quantity::Temp, 0)));
+        assert_eq!(classify("in_voltage0"), None);
+
+        let accel = ScanType::parse("le:s16/16>>0").unwrap();
+        let pressure = ScanType::parse("be:u20/32>>4").unwrap();
+        assert!(ScanType::parse("le:s16/12>>0").is_none());
+
+        // Index order accel_x(0), accel_y(1), pressure(2): the u32 is aligned to 4 bytes
+        let (offsets, size) = layout(&[(2, pressure), (0, accel), (1, accel)]);
+        assert_eq!(offsets, [4, 0, 2]);
+        assert_eq!(size, 8);
+
+        assert_eq!(accel.decode(&[0x18, 0xFC]), -1000);
+        assert_eq!(pressure.decode(&[0x00, 0x18, 0x6A, 0x00]), 0x186A0);
+    }
+
+    #[test]
+    fn test_imu_frame_keeps_f64_readings() {
+        let channel = |name: &str| {
+            let (quantity, axis) = classify(name).unwrap();
+            Channel {
+                name: name.to_string(),
+                quantity,
+                axis,
+                scale: 0.000598550,
+                offset: 0.0,
+                value_file: PathBuf::new(),
+                scan: None,
+            }
+        };
+        let device = IioDevice {
+            name: "icm42688".to_string(),
+            channels: ["in_accel_x", "in_accel_y", "in_accel_z", "in_anglvel_x"]
+                .map(channel)
+                .to_vec(),
+            buffer: None,
+        };
+        let values: Vec<f64> = [1.0, -2.0, 16383.0, 3.0]
+            .iter()
+            .zip(&device.channels)
+            .map(|(raw, channel)| channel.convert(*raw))
+            .collect();
+        let frame = device.frame(&values);
+        assert_eq!(frame.accel_f64, Some([values[0], values[1], values[2]]));
+        assert_eq!(frame.gyro_f64, Some([values[3], 0.0, 0.0]));
+        assert_eq!(frame.accel.unwrap()[2], values[2] as f32);
+
+        let messages = frame_to_messages(frame, Header::default(), "imu0");
+        let [SensorMessage::Imu(imu)] = messages.as_slice() else {
+            panic!("expected one IMU message");
+        };
+        let precise = imu.precise.unwrap();
+        assert_eq!(precise[2], values[2]);
+        assert_ne!(precise[2], f64::from(imu.az));
+    }
+}
@@ -0,0 +1,214 @@
+use super::uart::UartProtocol;
+use super::{RangeReading, SensorDataFrame};
+use tracing::trace;
+
+/// MAV_SENSOR_ROTATION_PITCH_270 - facing down, the usual altimeter mounting
+const ORIENTATION_DOWNWARD: u32 = 25;
+
+// LightWare binary (LWNX) framing
+const LWNX_START: u8 = 0xAA;
+/// Command 44: distance data, first return in cm (i16 little-endian)
+const LWNX_CMD_DISTANCE: u8 = 44;
+/// Read request for the distance command: start, flags (payload length 1, read), id, CRC
+const LWNX_READ_DISTANCE: [u8; 6] = [0xAA, 0x40, 0x00, LWNX_CMD_DISTANCE, 0x9E, 0x7A];
+
+/// Longest ASCII line we buffer before giving up on a missing newline
+const MAX_LINE_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Protocol {
+    /// SF11: continuous ASCII output, one "12.34 m" line per sample
+    AsciiStream,
+    /// LW20: ASCII request/response, "?ld" answered by "ld:12.34"
+    AsciiPolled,
+    /// LW20/C and newer units in binary mode (LWNX packets with CRC)
+    Binary,
+}
+
+/// LightWare LW20 / SF11 laser altimeter
+pub struct Lightware {
+    protocol: Protocol,
+    min_distance: f32,
+    max_distance: f32,
+    buf: Vec<u8>,
+}
+
+impl Lightware {
+    /// Build the protocol handler for a driver name from sensors.toml
+    pub fn for_driver(driver: &str) -> Box<dyn UartProtocol> {
+        let (protocol, min_distance, max_distance) = match driver {
+            "sf11" => (Protocol::AsciiStream, 0.2, 120.0),
+            "lw20_binary" => (Protocol::Binary, 0.2, 100.0),
+            _ => (Protocol::AsciiPolled, 0.2, 100.0),
+        };
+        Box::new(Self {
+            protocol,
+            min_distance,
+            max_distance,
+            buf: Vec::new(),
+        })
+    }
+
+    fn frame(&self, distance: f32) -> Option<SensorDataFrame> {
+        if !distance.is_finite() || distance < 0.0 {
+            return None;
+        }
+        Some(SensorDataFrame {
+            range: Some(RangeReading {
+                distance,
+                min_distance: self.min_distance,
+                max_distance: self.max_distance,
+                orientation: ORIENTATION_DOWNWARD,
+                covariance: None,
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn parse_ascii(&mut self) -> Vec<SensorDataFrame> {
+        let mut frames = Vec::new();
+        while let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            match parse_ascii_distance(&line) {
+                Some(distance) => frames.extend(self.frame(distance)),
+                None => trace!("[lightware] Ignoring line: {:?}", line.trim()),
+            }
+        }
+        if self.buf.len() > MAX_LINE_LEN {
+            self.buf.clear();
+        }
+        frames
+    }
+
+    fn parse_binary(&mut self) -> Vec<SensorDataFrame> {
+        let mut frames = Vec::new();
+        loop {
+            // Resynchronize on the start byte
+            match self.buf.iter().position(|&b| b == LWNX_START) {
+                Some(start) => {
+                    self.buf.drain(..start);
+                }
+                None => {
+                    self.buf.clear();
+                    break;
+                }
+            }
+            if self.buf.len() < 3 {
+                break;
+            }
+            let flags = u16::from_le_bytes([self.buf[1], self.buf[2]]);
+            let payload_len = (flags >> 6) as usize;
+            let packet_len = 3 + payload_len + 2;
+            if payload_len == 0 || payload_len > 1023 {
+                self.buf.remove(0);
+                continue;
+            }
+            if self.buf.len() < packet_len {
+                break;
+            }
+
+            let packet: Vec<u8> = self.buf.drain(..packet_len).collect();
+            let crc = u16::from_le_bytes([packet[packet_len - 2], packet[packet_len - 1]]);
+            if crc16_ccitt(&packet[..packet_len - 2]) != crc {
+                trace!("[lightware] Dropping packet with bad CRC");
+                continue;
+            }
+
+            let payload = &packet[3..3 + payload_len];
+            if payload[0] == LWNX_CMD_DISTANCE && payload.len() >= 3 {
+                let cm = i16::from_le_bytes([payload[1], payload[2]]);
+                frames.extend(self.frame(cm as f32 / 100.0));
+            }
+        }
+        frames
+    }
+}
+
+impl UartProtocol for Lightware {
+    fn poll_command(&self) -> Option<&[u8]> {
+        match self.protocol {
+            Protocol::AsciiStream => None,
+            Protocol::AsciiPolled => Some(b"?ld\r\n"),
+            Protocol::Binary => Some(&LWNX_READ_DISTANCE),
+        }
+    }
+
+    fn parse(&mut self, bytes: &[u8]) -> Vec<SensorDataFrame> {
+        self.buf.extend_from_slice(bytes);
+        match self.protocol {
+            Protocol::AsciiStream | Protocol::AsciiPolled => self.parse_ascii(),
+            Protocol::Binary => self.parse_binary(),
+        }
+    }
+}
+
+/// Parse "12.34 m" (SF11 stream) or "ld:12.34" (LW20 response) into meters
+fn parse_ascii_distance(line: &str) -> Option<f32> {
+    let value = line.rsplit(':').next()?.trim();
+    let value = value.strip_suffix('m').unwrap_or(value).trim();
+    value.parse().ok()
+}
+
+/// CRC-16-CCITT (XModem: poly 0x1021, init 0) used by LWNX packets
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        let mut code = crc >> 8;
+        code ^= byte as u16;
+        code ^= code >> 4;
+        crc <<= 8;
+        crc ^= code;
+        code <<= 5;
+        crc ^= code;
+        code <<= 7;
+        crc ^= code;
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(frame: &SensorDataFrame) -> f32 {
+        frame.range.as_ref().unwrap().distance
+    }
+
+    #[test]
+    fn test_ascii_lines_split_across_reads() {
+        let mut sf11 = Lightware::for_driver("sf11");
+        assert!(sf11.parse(b" 12.3").is_empty());
+        let frames = sf11.parse(b"4 m\r\n5.00 m\r\n--.-- m\r\n");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(distance(&frames[0]), 12.34);
+        assert_eq!(distance(&frames[1]), 5.0);
+
+        let mut lw20 = Lightware::for_driver("lw20");
+        let frames = lw20.parse(b"ld:7.25\r\n");
+        assert_eq!(distance(&frames[0]), 7.25);
+    }
+
+    #[test]
+    fn test_binary_packet() {
+        // Read request constant must carry a valid CRC
+        let crc = crc16_ccitt(&LWNX_READ_DISTANCE[..4]);
+        assert_eq!(
+            crc.to_le_bytes(),
+            [LWNX_READ_DISTANCE[4], LWNX_READ_DISTANCE[5]]
+        );
+
+        // Response: 3-byte payload (id + 1234 cm), preceded by line noise
+        let mut packet = vec![LWNX_START, 3 << 6, 0x00, LWNX_CMD_DISTANCE];
+        packet.extend_from_slice(&1234i16.to_le_bytes());
+        let crc = crc16_ccitt(&packet);
+        packet.extend_from_slice(&crc.to_le_bytes());
+
+        let mut lw20 = Lightware::for_driver("lw20_binary");
+        let mut bytes = vec![0x00, 0x13];
+        bytes.extend_from_slice(&packet);
+        let frames = lw20.parse(&bytes);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(distance(&frames[0]), 12.34);
+    }
+}
@@ -1,7 +1,9 @@
-use super::{SensorDataFrame, SensorDriver};
+use super::{check_params, param_choice, DriverParams, SensorDataFrame, SensorDriver};
 use crate::bus::i2c::I2CBus;
 use crate::errors::{SensorError, SensorResult};
 use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tracing::warn;
 
 // Register addresses for the LIS3MDL
 const WHO_AM_I: u8 = 0x0F;
@@ -11,27 +13,89 @@ const CTRL_REG3: u8 = 0x22;
 const CTRL_REG4: u8 = 0x23;
 const OUT_X_L: u8 = 0x28;
 
-// Sensitivity for +/- 4 gauss full scale
-const SENSITIVITY_4GAUSS: f32 = 0.00014; // Tesla per LSB
+// Sensitivity for +/- 4 gauss full scale (6842 LSB/gauss)
+const SENSITIVITY_4GAUSS: f32 = 100.0 / 6842.0; // μT per LSB
+
+/// Counts at which an axis is taken to be clipping (about 99.5 % of full scale)
+const SATURATED: u16 = 32600;
+
+// Full-scale ranges (gauss) and their FS codes (CTRL_REG2 bits 6-5), in increasing order
+const RANGES: [(f64, u8); 4] = [(4.0, 0b00), (8.0, 0b01), (12.0, 0b10), (16.0, 0b11)];
+// Output data rates and their DO codes (CTRL_REG1 bits 4-2)
+const ODRS: [(f64, u8); 8] = [
+    (0.625, 0b000),
+    (1.25, 0b001),
+    (2.5, 0b010),
+    (5.0, 0b011),
+    (10.0, 0b100),
+    (20.0, 0b101),
+    (40.0, 0b110),
+    (80.0, 0b111),
+];
 
 pub struct Lis3mdl {
     id: String,
-    address: u8,
+    address: u16,
     bus_id: String,
+    /// Full scale (gauss) and DO code, ±4 gauss at 80 Hz unless configured
+    range: f64,
+    odr: u8,
+    /// Step up the full scale when an axis clips (default on)
+    auto_range: bool,
+    /// Index in RANGES of the full scale in use, raised from `range` by auto-ranging
+    active_range: AtomicUsize,
+    /// An axis clips at the widest range, warned once per episode
+    clipping: AtomicBool,
 }
 
 impl Lis3mdl {
-    pub fn new(id: String, address: u8, bus_id: String) -> Self {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
         Self {
             id,
             address,
             bus_id,
+            range: 4.0,
+            odr: 0b111,
+            auto_range: true,
+            active_range: AtomicUsize::new(0),
+            clipping: AtomicBool::new(false),
         }
     }
 }
 
+/// Axes of a sample at full scale, e.g. "x" or "x, z"
+fn saturated_axes(raw: [i16; 3]) -> Option<String> {
+    let axes: Vec<&str> = ["x", "y", "z"]
+        .into_iter()
+        .zip(raw)
+        .filter(|(_, v)| v.unsigned_abs() >= SATURATED)
+        .map(|(axis, _)| axis)
+        .collect();
+    (!axes.is_empty()).then(|| axes.join(", "))
+}
+
 #[async_trait]
 impl SensorDriver for Lis3mdl {
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        check_params(&self.id, params, &["range", "odr_hz", "auto_range"])?;
+        let ranges = RANGES.map(|(range, _)| (range, range));
+        if let Some(range) = param_choice(&self.id, params, "range", "gauss", &ranges)? {
+            self.range = range;
+        }
+        if let Some(odr) = param_choice(&self.id, params, "odr_hz", "Hz", &ODRS)? {
+            self.odr = odr;
+        }
+        if let Some(auto_range) = params.get("auto_range") {
+            self.auto_range = auto_range
+                .as_bool()
+                .ok_or_else(|| SensorError::ConfigError {
+                    sensor: self.id.clone(),
+                    reason: format!("auto_range = {} must be true or false", auto_range),
+                })?;
+        }
+        Ok(())
+    }
+
     async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
         // Verify device identity
         let mut who_am_i_buf = [0u8; 1];
@@ -47,15 +111,21 @@ impl SensorDriver for Lis3mdl {
         }
 
         // Configure magnetometer:
-        // CTRL_REG1: Temp sensor disabled, medium-performance mode, 80 Hz ODR
-        bus.write_byte(self.address, CTRL_REG1, 0b01011100)
+        // CTRL_REG1: Temp sensor disabled, medium-performance mode, ODR (80 Hz by default)
+        bus.write_byte(self.address, CTRL_REG1, 0b01000000 | self.odr << 2)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
                 reason: format!("Failed to configure CTRL_REG1: {}", e),
             })?;
-        // CTRL_REG2: +/- 4 gauss full scale
-        bus.write_byte(self.address, CTRL_REG2, 0b00000000)
+        // CTRL_REG2: full scale (+/- 4 gauss by default), back to the configured one
+        let index = RANGES
+            .iter()
+            .position(|(range, _)| *range == self.range)
+            .unwrap_or(0);
+        self.active_range.store(index, Ordering::Relaxed);
+        self.clipping.store(false, Ordering::Relaxed);
+        bus.write_byte(self.address, CTRL_REG2, RANGES[index].1 << 5)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
@@ -97,11 +167,43 @@ impl SensorDriver for Lis3mdl {
             i16::from_le_bytes([mag_buf[4], mag_buf[5]]),
         ];
 
-        frame.mag = Some([
-            mag_raw[0] as f32 * SENSITIVITY_4GAUSS,
-            mag_raw[1] as f32 * SENSITIVITY_4GAUSS,
-            mag_raw[2] as f32 * SENSITIVITY_4GAUSS,
-        ]);
+        let index = self.active_range.load(Ordering::Relaxed);
+        let range = RANGES[index].0;
+        match saturated_axes(mag_raw) {
+            // Discard the clipped sample; the next conversion uses the wider range
+            Some(axes) if self.auto_range && index + 1 < RANGES.len() => {
+                bus.write_byte(self.address, CTRL_REG2, RANGES[index + 1].1 << 5)
+                    .await
+                    .map_err(|e| SensorError::ReadError {
+                        sensor: self.id.clone(),
+                        reason: format!("Failed to raise the full scale: {}", e),
+                    })?;
+                self.active_range.store(index + 1, Ordering::Relaxed);
+                warn!(
+                    "[{}] {} at full scale, range raised to ±{} gauss",
+                    self.id,
+                    axes,
+                    RANGES[index + 1].0
+                );
+                return Err(SensorError::DataError {
+                    sensor: self.id.clone(),
+                    reason: format!("{} clipped at ±{} gauss", axes, range),
+                });
+            }
+            Some(axes) => {
+                if !self.clipping.swap(true, Ordering::Relaxed) {
+                    warn!(
+                        "[{}] {} clipping at ±{} gauss, readings are limited",
+                        self.id, axes, range
+                    );
+                }
+            }
+            None => self.clipping.store(false, Ordering::Relaxed),
+        }
+
+        // Sensitivity scales linearly with the full-scale range
+        let scale = SENSITIVITY_4GAUSS * (range / 4.0) as f32;
+        frame.mag = Some(mag_raw.map(|v| v as f32 * scale));
 
         Ok(frame)
     }
@@ -118,3 +220,65 @@ impl SensorDriver for Lis3mdl {
         self
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Lis3mdl::new("mag0".into(), 0x1C, "i2c1".into())),
+            address: 0x1C,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x1C, WHO_AM_I, &[0x3D]);
+                // About [20, -5, 40] μT
+                mock.set_registers(0x1C, OUT_X_L, &[0x58, 0x05, 0xAA, 0xFE, 0xB1, 0x0A]);
+            },
+            init_writes: vec![
+                Write::Register(CTRL_REG1, 0x5C),
+                Write::Register(CTRL_REG2, 0x00),
+                Write::Register(CTRL_REG3, 0x00),
+                Write::Register(CTRL_REG4, 0x04),
+            ],
+        }
+        .check()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_auto_range_on_saturation() {
+        let mock = MockI2c::new();
+        mock.set_registers(0x1C, WHO_AM_I, &[0x3D]);
+        let mut bus = I2CBus::mock(mock.clone());
+        let mut mag = Lis3mdl::new("mag0".into(), 0x1C, "i2c1".into());
+        mag.init(&mut bus).await.unwrap();
+
+        // y pinned at the negative end of ±4 gauss near a motor
+        mock.set_registers(0x1C, OUT_X_L, &[0x00, 0x10, 0x00, 0x80, 0x00, 0x00]);
+        assert!(matches!(
+            mag.read(&mut bus).await,
+            Err(SensorError::DataError { .. })
+        ));
+        let mut ctrl2 = [0u8; 1];
+        bus.read_bytes(0x1C, CTRL_REG2, &mut ctrl2).await.unwrap();
+        assert_eq!(ctrl2[0], 0b01 << 5);
+
+        // 4096 counts now read at the ±8 gauss sensitivity
+        mock.set_registers(0x1C, OUT_X_L, &[0x00, 0x10, 0x00, 0x00, 0x00, 0x00]);
+        let frame = mag.read(&mut bus).await.unwrap();
+        assert!((frame.mag.unwrap()[0] - 4096.0 * 200.0 / 6842.0).abs() < 0.01);
+
+        // At ±16 gauss the clipped sample is published as it is
+        mag.active_range.store(RANGES.len() - 1, Ordering::Relaxed);
+        mock.set_registers(0x1C, OUT_X_L, &[0xFF, 0x7F, 0x00, 0x00, 0x00, 0x00]);
+        assert!(mag.read(&mut bus).await.is_ok());
+
+        // Re-initializing returns to the configured range
+        mag.init(&mut bus).await.unwrap();
+        bus.read_bytes(0x1C, CTRL_REG2, &mut ctrl2).await.unwrap();
+        assert_eq!(ctrl2[0], 0);
+    }
+}
@@ -1,37 +1,205 @@
-use super::{SensorDataFrame, SensorDriver};
-use crate::bus::i2c::I2CBus;
+use super::{check_params, param_choice, DriverParams, SensorDataFrame, SensorDriver};
+use crate::bus::i2c::{I2CBus, I2CError};
 use crate::errors::{SensorError, SensorResult};
 use async_trait::async_trait;
+use tracing::debug;
 
 // Register addresses for the LSM6DSL
+const FIFO_CTRL3: u8 = 0x08;
+const FIFO_CTRL5: u8 = 0x0A;
+const DRDY_PULSE_CFG_G: u8 = 0x0B;
+const INT1_CTRL: u8 = 0x0D;
 const WHO_AM_I: u8 = 0x0F;
 const CTRL1_XL: u8 = 0x10;
 const CTRL2_G: u8 = 0x11;
+const CTRL3_C: u8 = 0x12;
 const OUT_TEMP_L: u8 = 0x20;
-const OUTX_L_G: u8 = 0x22;
-const OUTX_L_XL: u8 = 0x28;
+const FIFO_STATUS1: u8 = 0x3A;
+const FIFO_DATA_OUT_L: u8 = 0x3E;
+
+/// CTRL3_C: block data update (an output register pair is not overwritten until both
+/// bytes are read) with register auto-increment
+const CTRL3_BDU_IF_INC: u8 = 0x44;
+/// DRDY_PULSE_CFG_G: data-ready as 75 µs pulses rather than latched until read
+const DRDY_PULSED: u8 = 0x80;
+/// INT1_CTRL: accelerometer and gyroscope data-ready on INT1
+const INT1_DRDY_XL_G: u8 = 0x03;
+/// FIFO_CTRL3: gyroscope and accelerometer both stored in the FIFO, not decimated
+const FIFO_NO_DECIMATION: u8 = 0b001_001;
+/// FIFO_CTRL5 mode bits: continuous, the oldest samples overwritten when full
+const FIFO_MODE_CONTINUOUS: u8 = 0b110;
+/// FIFO_STATUS2: samples were overwritten before being read
+const FIFO_OVER_RUN: u8 = 0x40;
+/// Words of one FIFO data set: gyroscope x, y, z, then accelerometer x, y, z
+const FIFO_SET_WORDS: usize = 6;
+const FIFO_SET_BYTES: usize = FIFO_SET_WORDS * 2;
+/// Data sets per FIFO burst (the address rolls over from FIFO_DATA_OUT_H to _L), within
+/// one SMBus block read
+const FIFO_SETS_PER_READ: usize = 2;
 
 const ACCEL_SENSITIVITY_2G: f32 = 0.061 * 9.81 / 1000.0; // m/s^2 per LSB
 const GYRO_SENSITIVITY_250DPS: f32 = 8.75 / 1000.0; // dps per LSB
 
+// Full-scale ranges with their FS bits in CTRL1_XL / CTRL2_G (FS_125 included for the gyro)
+const ACCEL_RANGES: [(f64, u8); 4] = [(2.0, 0b0000), (4.0, 0b1000), (8.0, 0b1100), (16.0, 0b0100)];
+const GYRO_RANGES: [(f64, u8); 5] = [
+    (125.0, 0b0010),
+    (250.0, 0b0000),
+    (500.0, 0b0100),
+    (1000.0, 0b1000),
+    (2000.0, 0b1100),
+];
+// Output data rates and their ODR codes (bits 7-4), shared by accelerometer and gyroscope
+const ODRS: [(f64, u8); 10] = [
+    (12.5, 0b0001),
+    (26.0, 0b0010),
+    (52.0, 0b0011),
+    (104.0, 0b0100),
+    (208.0, 0b0101),
+    (416.0, 0b0110),
+    (833.0, 0b0111),
+    (1660.0, 0b1000),
+    (3330.0, 0b1001),
+    (6660.0, 0b1010),
+];
+
 pub struct Lsm6dsl {
     id: String,
-    address: u8,
+    address: u16,
     bus_id: String,
+    /// Full scale (g, dps) and ODR code, ±2 g / ±250 dps at 104 Hz unless configured
+    accel_range: f64,
+    gyro_range: f64,
+    odr: u8,
+    /// Average every sample queued in the FIFO since the last read instead of reading
+    /// only the latest one
+    fifo: bool,
+    /// Signal new samples on INT1 for data-ready sampling
+    drdy: bool,
 }
 
 impl Lsm6dsl {
-    pub fn new(id: String, address: u8, bus_id: String) -> Self {
+    pub fn new(id: String, address: u16, bus_id: String) -> Self {
         Self {
             id,
             address,
             bus_id,
+            accel_range: 2.0,
+            gyro_range: 250.0,
+            odr: 0b0100,
+            fifo: false,
+            drdy: false,
+        }
+    }
+
+    fn fs_bits(ranges: &[(f64, u8)], range: f64) -> u8 {
+        ranges
+            .iter()
+            .find(|(r, _)| *r == range)
+            .map_or(0, |(_, bits)| *bits)
+    }
+
+    fn read_error(&self, what: &str) -> impl Fn(I2CError) -> SensorError + '_ {
+        let what = what.to_string();
+        move |e| SensorError::ReadError {
+            sensor: self.id.clone(),
+            reason: format!("Failed to read {}: {}", what, e),
+        }
+    }
+
+    /// Mean gyroscope and accelerometer counts of the complete data sets queued in the FIFO,
+    /// None when none has completed since the last read
+    async fn read_fifo(&self, bus: &mut I2CBus) -> SensorResult<Option<[[f32; 3]; 2]>> {
+        let mut status = [0u8; 4];
+        bus.read_bytes(self.address, FIFO_STATUS1, &mut status)
+            .await
+            .map_err(self.read_error("FIFO status"))?;
+        let unread = status[0] as usize | ((status[1] as usize & 0x07) << 8);
+        let pattern = status[2] as usize | ((status[3] as usize & 0x03) << 8);
+        if status[1] & FIFO_OVER_RUN != 0 {
+            debug!("[{}] FIFO overrun, polled slower than it fills", self.id);
+        }
+
+        // Finish a data set left half-read, so that the next word is a gyroscope x
+        let skip = ((FIFO_SET_WORDS - pattern % FIFO_SET_WORDS) % FIFO_SET_WORDS).min(unread);
+        if skip > 0 {
+            let mut discard = [0u8; FIFO_SET_BYTES];
+            bus.read_bytes(self.address, FIFO_DATA_OUT_L, &mut discard[..skip * 2])
+                .await
+                .map_err(self.read_error("FIFO"))?;
+        }
+
+        let mut remaining = (unread - skip) / FIFO_SET_WORDS;
+        let mut data = Vec::with_capacity(remaining * FIFO_SET_BYTES);
+        while remaining > 0 {
+            let sets = remaining.min(FIFO_SETS_PER_READ);
+            let mut buf = [0u8; FIFO_SETS_PER_READ * FIFO_SET_BYTES];
+            let buf = &mut buf[..sets * FIFO_SET_BYTES];
+            bus.read_bytes(self.address, FIFO_DATA_OUT_L, buf)
+                .await
+                .map_err(self.read_error("FIFO"))?;
+            data.extend_from_slice(buf);
+            remaining -= sets;
+        }
+        Ok(average_sets(&data))
+    }
+}
+
+/// Little-endian 16-bit counts of three axes
+fn axes(bytes: &[u8]) -> [f32; 3] {
+    [0, 2, 4].map(|i| i16::from_le_bytes([bytes[i], bytes[i + 1]]) as f32)
+}
+
+/// Mean gyroscope and accelerometer counts of whole FIFO data sets
+fn average_sets(data: &[u8]) -> Option<[[f32; 3]; 2]> {
+    let sets = data.len() / FIFO_SET_BYTES;
+    if sets == 0 {
+        return None;
+    }
+    let mut sum = [[0f32; 3]; 2];
+    for set in data.chunks_exact(FIFO_SET_BYTES) {
+        for (sensor, values) in sum.iter_mut().zip(set.chunks_exact(6)) {
+            for (total, value) in sensor.iter_mut().zip(axes(values)) {
+                *total += value;
+            }
         }
     }
+    Some(sum.map(|sensor| sensor.map(|total| total / sets as f32)))
 }
 
 #[async_trait]
 impl SensorDriver for Lsm6dsl {
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        check_params(
+            &self.id,
+            params,
+            &["accel_range", "gyro_range", "odr_hz", "fifo"],
+        )?;
+        let accel = ACCEL_RANGES.map(|(range, _)| (range, range));
+        let gyro = GYRO_RANGES.map(|(range, _)| (range, range));
+        if let Some(range) = param_choice(&self.id, params, "accel_range", "g", &accel)? {
+            self.accel_range = range;
+        }
+        if let Some(range) = param_choice(&self.id, params, "gyro_range", "dps", &gyro)? {
+            self.gyro_range = range;
+        }
+        if let Some(odr) = param_choice(&self.id, params, "odr_hz", "Hz", &ODRS)? {
+            self.odr = odr;
+        }
+        if let Some(fifo) = params.get("fifo") {
+            self.fifo = fifo.as_bool().ok_or_else(|| SensorError::ConfigError {
+                sensor: self.id.clone(),
+                reason: format!("fifo = {} must be true or false", fifo),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn enable_data_ready(&mut self) -> SensorResult<()> {
+        self.drdy = true;
+        Ok(())
+    }
+
     async fn init(&mut self, bus: &mut I2CBus) -> SensorResult<()> {
         // Verify device identity
         let mut who_am_i_buf = [0u8; 1];
@@ -46,78 +214,92 @@ impl SensorDriver for Lsm6dsl {
             });
         }
 
-        // Configure accelerometer: 104 Hz, 2g
-        bus.write_byte(self.address, CTRL1_XL, 0b01000000)
+        // Keep each output register pair from one conversion, so a read never mixes samples
+        bus.write_byte(self.address, CTRL3_C, CTRL3_BDU_IF_INC)
+            .await
+            .map_err(|e| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: format!("Failed to enable block data update: {}", e),
+            })?;
+
+        // Configure accelerometer: ODR in bits 7-4, full scale in bits 3-2 (104 Hz, 2g by default)
+        let ctrl1 = self.odr << 4 | Self::fs_bits(&ACCEL_RANGES, self.accel_range);
+        bus.write_byte(self.address, CTRL1_XL, ctrl1)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
                 reason: format!("Failed to configure accelerometer: {}", e),
             })?;
 
-        // Configure gyroscope: 104 Hz, 250 dps
-        bus.write_byte(self.address, CTRL2_G, 0b01000000)
+        // Configure gyroscope: ODR in bits 7-4, full scale in bits 3-1 (104 Hz, 250 dps by default)
+        let ctrl2 = self.odr << 4 | Self::fs_bits(&GYRO_RANGES, self.gyro_range);
+        bus.write_byte(self.address, CTRL2_G, ctrl2)
             .await
             .map_err(|e| SensorError::InitError {
                 sensor: self.id.clone(),
                 reason: format!("Failed to configure gyroscope: {}", e),
             })?;
 
+        if self.drdy {
+            // Pulsed, so a sample the scheduler skips does not hold the line high
+            for (reg, value) in [(DRDY_PULSE_CFG_G, DRDY_PULSED), (INT1_CTRL, INT1_DRDY_XL_G)] {
+                bus.write_byte(self.address, reg, value)
+                    .await
+                    .map_err(|e| SensorError::InitError {
+                        sensor: self.id.clone(),
+                        reason: format!("Failed to enable data-ready interrupt: {}", e),
+                    })?;
+            }
+        }
+
+        if self.fifo {
+            // Bypass mode empties the FIFO, then store both sensors at the ODR, continuously
+            let fifo_writes = [
+                (FIFO_CTRL5, 0),
+                (FIFO_CTRL3, FIFO_NO_DECIMATION),
+                (FIFO_CTRL5, self.odr << 3 | FIFO_MODE_CONTINUOUS),
+            ];
+            for (reg, value) in fifo_writes {
+                bus.write_byte(self.address, reg, value)
+                    .await
+                    .map_err(|e| SensorError::InitError {
+                        sensor: self.id.clone(),
+                        reason: format!("Failed to configure FIFO: {}", e),
+                    })?;
+            }
+        }
+
         Ok(())
     }
 
     async fn read(&self, bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
-        let mut frame = SensorDataFrame::default();
-
-        // Read accelerometer data
-        let mut accel_buf = [0u8; 6];
-        bus.read_bytes(self.address, OUTX_L_XL, &mut accel_buf)
-            .await
-            .map_err(|e| SensorError::ReadError {
-                sensor: self.id.clone(),
-                reason: format!("Failed to read accelerometer: {}", e),
-            })?;
-        let accel_raw = [
-            i16::from_le_bytes([accel_buf[0], accel_buf[1]]),
-            i16::from_le_bytes([accel_buf[2], accel_buf[3]]),
-            i16::from_le_bytes([accel_buf[4], accel_buf[5]]),
-        ];
-        frame.accel = Some([
-            accel_raw[0] as f32 * ACCEL_SENSITIVITY_2G,
-            accel_raw[1] as f32 * ACCEL_SENSITIVITY_2G,
-            accel_raw[2] as f32 * ACCEL_SENSITIVITY_2G,
-        ]);
-
-        // Read gyroscope data
-        let mut gyro_buf = [0u8; 6];
-        bus.read_bytes(self.address, OUTX_L_G, &mut gyro_buf)
-            .await
-            .map_err(|e| SensorError::ReadError {
-                sensor: self.id.clone(),
-                reason: format!("Failed to read gyroscope: {}", e),
-            })?;
-        let gyro_raw = [
-            i16::from_le_bytes([gyro_buf[0], gyro_buf[1]]),
-            i16::from_le_bytes([gyro_buf[2], gyro_buf[3]]),
-            i16::from_le_bytes([gyro_buf[4], gyro_buf[5]]),
-        ];
-        frame.gyro = Some([
-            gyro_raw[0] as f32 * GYRO_SENSITIVITY_250DPS,
-            gyro_raw[1] as f32 * GYRO_SENSITIVITY_250DPS,
-            gyro_raw[2] as f32 * GYRO_SENSITIVITY_250DPS,
-        ]);
-
-        // Read temperature data
-        let mut temp_buf = [0u8; 2];
-        bus.read_bytes(self.address, OUT_TEMP_L, &mut temp_buf)
+        // Temperature, gyroscope and accelerometer in one burst, all from the same conversion
+        let mut out = [0u8; 14];
+        bus.read_bytes(self.address, OUT_TEMP_L, &mut out)
             .await
-            .map_err(|e| SensorError::ReadError {
-                sensor: self.id.clone(),
-                reason: format!("Failed to read temperature: {}", e),
-            })?;
-        let temp_raw = i16::from_le_bytes([temp_buf[0], temp_buf[1]]);
-        frame.temp = Some((temp_raw as f32 / 256.0) + 25.0);
+            .map_err(self.read_error("output registers"))?;
+        let temp_raw = i16::from_le_bytes([out[0], out[1]]);
+        let mut gyro_raw = axes(&out[2..8]);
+        let mut accel_raw = axes(&out[8..14]);
 
-        Ok(frame)
+        // Without a complete set in the FIFO (polled faster than the ODR) the latest sample
+        // from the output registers stands
+        if self.fifo {
+            if let Some([gyro, accel]) = self.read_fifo(bus).await? {
+                gyro_raw = gyro;
+                accel_raw = accel;
+            }
+        }
+
+        // Sensitivity scales linearly with the full-scale range
+        let accel_scale = ACCEL_SENSITIVITY_2G * (self.accel_range / 2.0) as f32;
+        let gyro_scale = GYRO_SENSITIVITY_250DPS * (self.gyro_range / 250.0) as f32;
+        Ok(SensorDataFrame {
+            accel: Some(accel_raw.map(|v| v * accel_scale)),
+            gyro: Some(gyro_raw.map(|v| (v * gyro_scale).to_radians())),
+            temp: Some((temp_raw as f32 / 256.0) + 25.0),
+            ..Default::default()
+        })
     }
 
     fn id(&self) -> &str {
@@ -132,3 +314,80 @@ impl SensorDriver for Lsm6dsl {
         self
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::bus::i2c_mock::MockI2c;
+    use crate::sensors::conformance::{Case, Write};
+
+    #[tokio::test]
+    async fn test_conformance() {
+        Case {
+            make: || Box::new(Lsm6dsl::new("imu0".into(), 0x6A, "i2c1".into())),
+            address: 0x6A,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x6A, WHO_AM_I, &[0x6A]);
+                // 26 °C; gyro 1.5 °/s about x; accel 1 g on z
+                mock.set_registers(
+                    0x6A,
+                    OUT_TEMP_L,
+                    &[0x00, 0x01, 0xAB, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x09, 0x40],
+                );
+            },
+            init_writes: vec![
+                Write::Register(CTRL3_C, CTRL3_BDU_IF_INC),
+                Write::Register(CTRL1_XL, 0x40),
+                Write::Register(CTRL2_G, 0x40),
+            ],
+        }
+        .check()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_conformance_fifo() {
+        // An empty FIFO falls back to the output registers
+        Case {
+            make: || {
+                let mut imu = Lsm6dsl::new("imu0".into(), 0x6A, "i2c1".into());
+                imu.fifo = true;
+                Box::new(imu)
+            },
+            address: 0x6A,
+            chip: |mock: &MockI2c| {
+                mock.set_registers(0x6A, WHO_AM_I, &[0x6A]);
+                mock.set_registers(
+                    0x6A,
+                    OUT_TEMP_L,
+                    &[0x00, 0x01, 0xAB, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0x09, 0x40],
+                );
+            },
+            init_writes: vec![
+                Write::Register(CTRL3_C, CTRL3_BDU_IF_INC),
+                Write::Register(FIFO_CTRL3, FIFO_NO_DECIMATION),
+                Write::Register(FIFO_CTRL5, 0x26),
+            ],
+        }
+        .check()
+        .await;
+    }
+
+    #[test]
+    fn test_fifo_average() {
+        let set = |g: i16, a: i16| -> Vec<u8> {
+            [g, -g, 0, a, 0, 16393]
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect()
+        };
+        let mut data = set(100, 10);
+        data.extend(set(300, 30));
+        // A trailing partial set is ignored
+        data.extend([1, 2, 3]);
+        let [gyro, accel] = average_sets(&data).unwrap();
+        assert_eq!(gyro, [200.0, -200.0, 0.0]);
+        assert_eq!(accel, [20.0, 0.0, 16393.0]);
+        assert_eq!(average_sets(&data[..11]), None);
+    }
+}
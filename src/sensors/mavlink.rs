@@ -1,15 +1,20 @@
-use super::{SensorDataFrame, SensorDriver};
+use super::{
+    frame_to_messages, quaternion_from_euler, EscReading, GimbalAttitude, GlobalPosition, GpsFix,
+    LocalPosition, RangeReading, RcInput, SensorDataFrame, SensorDriver, VfrHud, Vibration,
+};
 use crate::bus::i2c::I2CBus;
 use crate::bus::mavlink::MavlinkConnection;
 use crate::errors::{SensorError, SensorResult};
 use crate::grpc_service::SensorHubService;
-use crate::messages::{BarometerMessage, Header, ImuMessage, SensorMessage};
+use crate::messages::Header;
 use async_trait::async_trait;
 use mavlink::common::MavMessage;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 use tokio::sync::Mutex;
-use tracing::{debug, error, info, trace};
+use tracing::{error, info, trace};
 
 /// MAVLink sensor type enum - defines which message type this sensor processes
 ///
@@ -24,6 +29,51 @@ pub enum MavlinkSensorType {
     Barometer,
     /// Attitude quaternion (ATTITUDE_QUATERNION message)
     Attitude,
+    /// Attitude Euler angles (ATTITUDE message)
+    AttitudeEuler,
+    /// GPS receiver (GPS_RAW_INT message)
+    Gps,
+    /// Second GPS receiver (GPS2_RAW message)
+    Gps2,
+    /// Fused global position (GLOBAL_POSITION_INT message)
+    GlobalPosition,
+    /// Local position and velocity in the NED frame (LOCAL_POSITION_NED message)
+    LocalPosition,
+    /// Flight instrument summary (VFR_HUD message)
+    VfrHud,
+    /// IMU vibration levels and accelerometer clipping (VIBRATION message)
+    Vibration,
+    /// Rangefinder with MAVLink sensor id (DISTANCE_SENSOR message)
+    Rangefinder { id: u8 },
+    /// RC receiver inputs (RC_CHANNELS message)
+    RcChannels,
+    /// ESC telemetry (ESC_STATUS, with temperatures and faults from ESC_INFO)
+    Esc,
+    /// Camera gimbal with its gimbal device id (GIMBAL_DEVICE_ATTITUDE_STATUS message)
+    Gimbal { id: u8 },
+}
+
+impl MavlinkSensorType {
+    /// Driver name reported in ListSensors (matches the sensors.toml driver where one exists)
+    pub fn driver_name(&self) -> &'static str {
+        match self {
+            MavlinkSensorType::Imu { .. } => "mavlink_imu",
+            MavlinkSensorType::HighresImu => "mavlink_imu_highres",
+            MavlinkSensorType::Barometer => "mavlink_baro",
+            MavlinkSensorType::Attitude => "mavlink_attitude",
+            MavlinkSensorType::AttitudeEuler => "mavlink_attitude_euler",
+            MavlinkSensorType::Gps => "mavlink_gps",
+            MavlinkSensorType::Gps2 => "mavlink_gps2",
+            MavlinkSensorType::GlobalPosition => "mavlink_global_position",
+            MavlinkSensorType::LocalPosition => "mavlink_local_position",
+            MavlinkSensorType::VfrHud => "mavlink_vfr_hud",
+            MavlinkSensorType::Vibration => "mavlink_vibration",
+            MavlinkSensorType::Rangefinder { .. } => "mavlink_range",
+            MavlinkSensorType::RcChannels => "mavlink_rc",
+            MavlinkSensorType::Esc => "mavlink_esc",
+            MavlinkSensorType::Gimbal { .. } => "mavlink_gimbal",
+        }
+    }
 }
 
 /// Unified MAVLink sensor - handles all MAVLink message types
@@ -77,6 +127,11 @@ impl MavlinkSensor {
                 sensor_id, sensor_type
             );
 
+            // Latest ESC_INFO per ESC, merged into each ESC_STATUS
+            let mut esc_health: HashMap<u8, EscHealth> = HashMap::new();
+            // Latest dual-antenna GNSS heading, merged into each attitude
+            let mut gnss_heading = GnssHeading::default();
+
             while let Ok(msg) = rx.recv().await {
                 // Match on BOTH sensor type AND message type - only process matching pairs
                 let frame_opt = match (&sensor_type, &msg) {
@@ -105,10 +160,87 @@ impl MavlinkSensor {
                         trace!("[{}] Received SCALED_PRESSURE", sensor_id);
                         Some(convert_pressure_to_frame(p))
                     }
-                    // Attitude
+                    // Attitude - GPS_RAW_INT / GPS2_RAW only update the cached GNSS heading
+                    (
+                        MavlinkSensorType::Attitude | MavlinkSensorType::AttitudeEuler,
+                        MavMessage::GPS_RAW_INT(gps),
+                    ) => {
+                        gnss_heading.update(&convert_gps_raw_int_to_frame(gps));
+                        None
+                    }
+                    (
+                        MavlinkSensorType::Attitude | MavlinkSensorType::AttitudeEuler,
+                        MavMessage::GPS2_RAW(gps),
+                    ) => {
+                        gnss_heading.update(&convert_gps2_raw_to_frame(gps));
+                        None
+                    }
                     (MavlinkSensorType::Attitude, MavMessage::ATTITUDE_QUATERNION(att)) => {
                         trace!("[{}] Received ATTITUDE_QUATERNION", sensor_id);
-                        Some(convert_attitude_to_frame(att))
+                        Some(gnss_heading.attach(convert_attitude_to_frame(att)))
+                    }
+                    (MavlinkSensorType::AttitudeEuler, MavMessage::ATTITUDE(att)) => {
+                        trace!("[{}] Received ATTITUDE", sensor_id);
+                        Some(gnss_heading.attach(convert_attitude_euler_to_frame(att)))
+                    }
+                    // GPS
+                    (MavlinkSensorType::Gps, MavMessage::GPS_RAW_INT(gps)) => {
+                        trace!("[{}] Received GPS_RAW_INT", sensor_id);
+                        Some(convert_gps_raw_int_to_frame(gps))
+                    }
+                    (MavlinkSensorType::Gps2, MavMessage::GPS2_RAW(gps)) => {
+                        trace!("[{}] Received GPS2_RAW", sensor_id);
+                        Some(convert_gps2_raw_to_frame(gps))
+                    }
+                    // Fused global position
+                    (MavlinkSensorType::GlobalPosition, MavMessage::GLOBAL_POSITION_INT(pos)) => {
+                        trace!("[{}] Received GLOBAL_POSITION_INT", sensor_id);
+                        Some(convert_global_position_to_frame(pos))
+                    }
+                    // Local NED position
+                    (MavlinkSensorType::LocalPosition, MavMessage::LOCAL_POSITION_NED(pos)) => {
+                        trace!("[{}] Received LOCAL_POSITION_NED", sensor_id);
+                        Some(convert_local_position_to_frame(pos))
+                    }
+                    // HUD airspeed / groundspeed / climb
+                    (MavlinkSensorType::VfrHud, MavMessage::VFR_HUD(hud)) => {
+                        trace!("[{}] Received VFR_HUD", sensor_id);
+                        Some(convert_vfr_hud_to_frame(hud))
+                    }
+                    // Vibration diagnostics
+                    (MavlinkSensorType::Vibration, MavMessage::VIBRATION(vib)) => {
+                        trace!("[{}] Received VIBRATION", sensor_id);
+                        Some(convert_vibration_to_frame(vib))
+                    }
+                    // Rangefinder - match on the MAVLink sensor id
+                    (MavlinkSensorType::Rangefinder { id }, MavMessage::DISTANCE_SENSOR(dist))
+                        if dist.id == *id =>
+                    {
+                        trace!("[{}] Received DISTANCE_SENSOR", sensor_id);
+                        Some(convert_distance_sensor_to_frame(dist))
+                    }
+                    // RC inputs
+                    (MavlinkSensorType::RcChannels, MavMessage::RC_CHANNELS(rc)) => {
+                        trace!("[{}] Received RC_CHANNELS", sensor_id);
+                        Some(convert_rc_channels_to_frame(rc))
+                    }
+                    // ESC telemetry - ESC_INFO (~1 Hz) only updates the cached health
+                    (MavlinkSensorType::Esc, MavMessage::ESC_INFO(esc)) => {
+                        trace!("[{}] Received ESC_INFO", sensor_id);
+                        update_esc_health(&mut esc_health, esc);
+                        None
+                    }
+                    (MavlinkSensorType::Esc, MavMessage::ESC_STATUS(esc)) => {
+                        trace!("[{}] Received ESC_STATUS", sensor_id);
+                        Some(convert_esc_status_to_frame(esc, &esc_health))
+                    }
+                    // Gimbal - match on the gimbal device id
+                    (
+                        MavlinkSensorType::Gimbal { id },
+                        MavMessage::GIMBAL_DEVICE_ATTITUDE_STATUS(gimbal),
+                    ) if gimbal.gimbal_device_id == *id => {
+                        trace!("[{}] Received GIMBAL_DEVICE_ATTITUDE_STATUS", sensor_id);
+                        Some(convert_gimbal_attitude_to_frame(gimbal))
                     }
                     _ => None, // Not for this sensor instance
                 };
@@ -129,7 +261,7 @@ impl MavlinkSensor {
                     );
 
                     // Convert frame to gRPC messages and publish
-                    let messages = frame_to_grpc_messages(frame, header, &sensor_id);
+                    let messages = frame_to_messages(frame, header, &sensor_id);
                     for msg in messages {
                         if let Err(e) = grpc.publish(msg).await {
                             error!("[{}] Failed to publish: {}", sensor_id, e);
@@ -215,6 +347,12 @@ fn convert_pressure_to_frame(p: &mavlink::common::SCALED_PRESSURE_DATA) -> Senso
             None
         },
         temp: Some(p.temperature as f32 / 100.0), // centi-degrees to °C
+        // The pitot's own sensor, 0 if the autopilot does not report it
+        temp_pitot: if p.temperature_press_diff != 0 {
+            Some(p.temperature_press_diff as f32 / 100.0)
+        } else {
+            None
+        },
         ..Default::default()
     }
 }
@@ -228,60 +366,316 @@ fn convert_attitude_to_frame(att: &mavlink::common::ATTITUDE_QUATERNION_DATA) ->
     }
 }
 
-/// Convert SensorDataFrame to gRPC messages
-fn frame_to_grpc_messages(
-    frame: SensorDataFrame,
-    header: Header,
-    sensor_id: &str,
-) -> Vec<SensorMessage> {
-    let mut messages = Vec::new();
-
-    // IMU data (accelerometer + gyroscope)
-    if let (Some(accel), Some(gyro)) = (frame.accel, frame.gyro) {
-        let imu_msg = ImuMessage {
-            h: header.clone(),
-            ax: accel[0],
-            ay: accel[1],
-            az: accel[2],
-            gx: gyro[0],
-            gy: gyro[1],
-            gz: gyro[2],
-        };
-        messages.push(SensorMessage::Imu(imu_msg));
-        debug!(
-            "[{}] Publishing IMU: accel={:?}, gyro={:?}",
-            sensor_id, accel, gyro
-        );
+/// Convert ATTITUDE (Euler angles) data to SensorDataFrame
+fn convert_attitude_euler_to_frame(att: &mavlink::common::ATTITUDE_DATA) -> SensorDataFrame {
+    SensorDataFrame {
+        quaternion: Some(quaternion_from_euler(att.roll, att.pitch, att.yaw)), // w, x, y, z
+        angular_velocity_body: Some([att.rollspeed, att.pitchspeed, att.yawspeed]), // rad/s
+        ..Default::default()
     }
+}
 
-    // Barometer data
-    if let Some(pressure) = frame.pressure_static.or(frame.pressure_pitot) {
-        let temperature = frame.temp.unwrap_or(20.0);
+/// Convert GPS_RAW_INT data to SensorDataFrame
+fn convert_gps_raw_int_to_frame(gps: &mavlink::common::GPS_RAW_INT_DATA) -> SensorDataFrame {
+    gps_frame(
+        gps.fix_type as u8,
+        gps.satellites_visible,
+        [gps.lat, gps.lon, gps.alt],
+        [gps.eph, gps.epv, gps.vel, gps.cog],
+        gps.yaw,
+        gps.hdg_acc,
+    )
+}
 
-        // Calculate altitude using standard atmosphere (ISA): h = 44330 * (1 - (P/P0)^0.1903)
-        let altitude = if pressure > 0.0 {
-            44330.0 * (1.0 - (pressure / 101325.0).powf(0.1903))
-        } else {
-            0.0
-        };
+/// Convert GPS2_RAW data to SensorDataFrame
+fn convert_gps2_raw_to_frame(gps: &mavlink::common::GPS2_RAW_DATA) -> SensorDataFrame {
+    gps_frame(
+        gps.fix_type as u8,
+        gps.satellites_visible,
+        [gps.lat, gps.lon, gps.alt],
+        [gps.eph, gps.epv, gps.vel, gps.cog],
+        gps.yaw,
+        gps.hdg_acc,
+    )
+}
 
-        let baro_msg = BarometerMessage {
-            h: header.clone(),
-            pressure,
-            temperature,
-            altitude,
-        };
-        messages.push(SensorMessage::Barometer(baro_msg));
-        debug!(
-            "[{}] Publishing Baro: press={:.1} Pa, temp={:.1}°C, alt={:.1}m",
-            sensor_id, pressure, temperature, altitude
+/// Shared GPS_RAW_INT / GPS2_RAW conversion
+/// `position` is lat, lon (degE7), alt (mm); `quality` is eph, epv, vel (cm/s), cog (cdeg)
+fn gps_frame(
+    fix_type: u8,
+    satellites_visible: u8,
+    position: [i32; 3],
+    quality: [u16; 4],
+    yaw: u16,
+    hdg_acc: u32,
+) -> SensorDataFrame {
+    // UINT16_MAX marks an unknown value for eph/epv/vel/cog, UINT8_MAX for satellites_visible
+    let known = |v: u16| (v != u16::MAX).then_some(v as f32);
+    let [eph, epv, vel, cog] = quality;
+    let yaw = gnss_yaw(yaw);
+    SensorDataFrame {
+        gps: Some(GpsFix {
+            fix_type,
+            satellites_visible: (satellites_visible != u8::MAX).then_some(satellites_visible),
+            latitude: position[0] as f64 / 1e7, // degE7 to degrees
+            longitude: position[1] as f64 / 1e7, // degE7 to degrees
+            altitude: position[2] as f32 / 1000.0, // mm to m
+            hdop: known(eph).map(|v| v / 100.0),
+            vdop: known(epv).map(|v| v / 100.0),
+            ground_speed: known(vel).map(|v| v / 100.0), // cm/s to m/s
+            course: known(cog).map(|v| v / 100.0),       // cdeg to degrees
+            yaw,
+            // hdg_acc describes the course when there is no yaw, so only pair it with one
+            yaw_accuracy: yaw.and((hdg_acc > 0).then_some(hdg_acc as f32 / 1e5)), // degE5 to degrees
+        }),
+        ..Default::default()
+    }
+}
+
+/// GNSS yaw in degrees from the `yaw` extension field (cdeg)
+/// 0 means the receiver has no yaw, UINT16_MAX that it has none right now, 36000 is north.
+fn gnss_yaw(yaw: u16) -> Option<f32> {
+    match yaw {
+        0 | u16::MAX => None,
+        36000 => Some(0.0),
+        cdeg => Some(cdeg as f32 / 100.0),
+    }
+}
+
+/// Latest dual-antenna GNSS heading seen on GPS_RAW_INT or GPS2_RAW
+#[derive(Default)]
+struct GnssHeading {
+    /// Heading, its uncertainty (degrees) and when it arrived
+    latest: Option<(f32, Option<f32>, Instant)>,
+}
+
+impl GnssHeading {
+    /// GPS reports at 5-10 Hz, so an older heading means the receiver stopped providing one
+    const MAX_AGE: Duration = Duration::from_secs(1);
+
+    fn update(&mut self, frame: &SensorDataFrame) {
+        if let Some(gps) = &frame.gps {
+            if let Some(yaw) = gps.yaw {
+                self.latest = Some((yaw, gps.yaw_accuracy, Instant::now()));
+            }
+        }
+    }
+
+    fn attach(&self, mut frame: SensorDataFrame) -> SensorDataFrame {
+        if let Some((yaw, accuracy, at)) = self.latest {
+            if at.elapsed() <= Self::MAX_AGE {
+                frame.gnss_yaw = Some(yaw);
+                frame.gnss_yaw_accuracy = accuracy;
+            }
+        }
+        frame
+    }
+}
+
+/// Convert GLOBAL_POSITION_INT data to SensorDataFrame
+fn convert_global_position_to_frame(
+    pos: &mavlink::common::GLOBAL_POSITION_INT_DATA,
+) -> SensorDataFrame {
+    SensorDataFrame {
+        global_position: Some(GlobalPosition {
+            latitude: pos.lat as f64 / 1e7,    // degE7 to degrees
+            longitude: pos.lon as f64 / 1e7,   // degE7 to degrees
+            altitude: pos.alt as f32 / 1000.0, // mm to m
+            relative_altitude: pos.relative_alt as f32 / 1000.0, // mm to m
+            velocity: [
+                pos.vx as f32 / 100.0, // cm/s to m/s
+                pos.vy as f32 / 100.0,
+                pos.vz as f32 / 100.0,
+            ],
+            // UINT16_MAX marks an unknown heading
+            heading: (pos.hdg != u16::MAX).then_some(pos.hdg as f32 / 100.0), // cdeg to degrees
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert LOCAL_POSITION_NED data to SensorDataFrame
+fn convert_local_position_to_frame(
+    pos: &mavlink::common::LOCAL_POSITION_NED_DATA,
+) -> SensorDataFrame {
+    SensorDataFrame {
+        local_position: Some(LocalPosition {
+            position: [pos.x, pos.y, pos.z],    // Already in m
+            velocity: [pos.vx, pos.vy, pos.vz], // Already in m/s
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert VFR_HUD data to SensorDataFrame
+fn convert_vfr_hud_to_frame(hud: &mavlink::common::VFR_HUD_DATA) -> SensorDataFrame {
+    SensorDataFrame {
+        vfr_hud: Some(VfrHud {
+            airspeed: hud.airspeed,        // Already in m/s
+            groundspeed: hud.groundspeed,  // Already in m/s
+            heading: hud.heading as f32,   // Already in degrees
+            climb_rate: hud.climb,         // Already in m/s
+            throttle: hud.throttle as f32, // Already in percent
+            altitude: hud.alt,             // Already in m (MSL)
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert VIBRATION data to SensorDataFrame
+fn convert_vibration_to_frame(vib: &mavlink::common::VIBRATION_DATA) -> SensorDataFrame {
+    SensorDataFrame {
+        vibration: Some(Vibration {
+            levels: [vib.vibration_x, vib.vibration_y, vib.vibration_z], // Already in m/s/s
+            clipping: [vib.clipping_0, vib.clipping_1, vib.clipping_2],
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert DISTANCE_SENSOR data to SensorDataFrame
+fn convert_distance_sensor_to_frame(
+    dist: &mavlink::common::DISTANCE_SENSOR_DATA,
+) -> SensorDataFrame {
+    SensorDataFrame {
+        range: Some(RangeReading {
+            distance: dist.current_distance as f32 / 100.0, // cm to m
+            min_distance: dist.min_distance as f32 / 100.0, // cm to m
+            max_distance: dist.max_distance as f32 / 100.0, // cm to m
+            orientation: dist.orientation as u32,
+            // UINT8_MAX marks an unknown variance
+            covariance: (dist.covariance != u8::MAX).then_some(dist.covariance as f32 / 10000.0), // cm² to m²
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert RC_CHANNELS data to SensorDataFrame
+fn convert_rc_channels_to_frame(rc: &mavlink::common::RC_CHANNELS_DATA) -> SensorDataFrame {
+    let raw = [
+        rc.chan1_raw,
+        rc.chan2_raw,
+        rc.chan3_raw,
+        rc.chan4_raw,
+        rc.chan5_raw,
+        rc.chan6_raw,
+        rc.chan7_raw,
+        rc.chan8_raw,
+        rc.chan9_raw,
+        rc.chan10_raw,
+        rc.chan11_raw,
+        rc.chan12_raw,
+        rc.chan13_raw,
+        rc.chan14_raw,
+        rc.chan15_raw,
+        rc.chan16_raw,
+        rc.chan17_raw,
+        rc.chan18_raw,
+    ];
+    let count = (rc.chancount as usize).min(raw.len());
+    SensorDataFrame {
+        rc: Some(RcInput {
+            channels: raw[..count].to_vec(), // Already in µs
+            // UINT8_MAX marks an unknown RSSI
+            rssi: (rc.rssi != u8::MAX).then_some(rc.rssi),
+        }),
+        ..Default::default()
+    }
+}
+
+/// Per-ESC state reported by ESC_INFO
+#[derive(Debug, Clone, Copy)]
+struct EscHealth {
+    /// Temperature (°C), None if the ESC does not report it
+    temperature: Option<f32>,
+    error_count: u32,
+    failure_flags: u16,
+    /// Number of ESCs on the vehicle
+    count: u8,
+}
+
+/// Cache ESC_INFO data for the ESCs it covers
+fn update_esc_health(health: &mut HashMap<u8, EscHealth>, info: &mavlink::common::ESC_INFO_DATA) {
+    for slot in 0..4u8 {
+        let index = info.index.saturating_add(slot);
+        if index >= info.count {
+            break;
+        }
+        let temp = info.temperature[slot as usize];
+        health.insert(
+            index,
+            EscHealth {
+                // INT16_MAX marks a missing temperature
+                temperature: (temp != i16::MAX).then_some(temp as f32 / 100.0), // cdegC to °C
+                error_count: info.error_count[slot as usize],
+                failure_flags: info.failure_flags[slot as usize],
+                count: info.count,
+            },
         );
     }
+}
 
-    // Note: Attitude quaternion data is currently dropped - add Attitude message type
-    // to messages.rs if needed (see bus/mavlink.rs TODO for adding new message types)
+/// Convert GIMBAL_DEVICE_ATTITUDE_STATUS data to SensorDataFrame
+fn convert_gimbal_attitude_to_frame(
+    gimbal: &mavlink::common::GIMBAL_DEVICE_ATTITUDE_STATUS_DATA,
+) -> SensorDataFrame {
+    use mavlink::common::GimbalDeviceFlags;
+    // Before the explicit frame flags, a locked yaw meant "relative to north"
+    let yaw_in_earth_frame = gimbal
+        .flags
+        .contains(GimbalDeviceFlags::GIMBAL_DEVICE_FLAGS_YAW_IN_EARTH_FRAME)
+        || (gimbal
+            .flags
+            .contains(GimbalDeviceFlags::GIMBAL_DEVICE_FLAGS_YAW_LOCK)
+            && !gimbal
+                .flags
+                .contains(GimbalDeviceFlags::GIMBAL_DEVICE_FLAGS_YAW_IN_VEHICLE_FRAME));
+    SensorDataFrame {
+        gimbal: Some(GimbalAttitude {
+            device_id: gimbal.gimbal_device_id,
+            quaternion: gimbal.q, // w, x, y, z
+            angular_velocity: [
+                gimbal.angular_velocity_x,
+                gimbal.angular_velocity_y,
+                gimbal.angular_velocity_z,
+            ],
+            yaw_in_earth_frame,
+            delta_yaw: (!gimbal.delta_yaw.is_nan()).then_some(gimbal.delta_yaw),
+            device_flags: gimbal.flags.bits(),
+            failure_flags: gimbal.failure_flags.bits(),
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert ESC_STATUS data (up to four ESCs starting at `index`) to SensorDataFrame
+fn convert_esc_status_to_frame(
+    esc: &mavlink::common::ESC_STATUS_DATA,
+    health: &HashMap<u8, EscHealth>,
+) -> SensorDataFrame {
+    // ESC_INFO tells how many ESCs exist; without it all four slots are reported
+    let count = health.values().next().map_or(u8::MAX, |h| h.count);
+    let readings = (0..4u8)
+        .map(|slot| (slot, esc.index.saturating_add(slot)))
+        .take_while(|&(_, index)| index < count)
+        .map(|(slot, index)| {
+            let info = health.get(&index);
+            EscReading {
+                index,
+                rpm: esc.rpm[slot as usize],
+                voltage: esc.voltage[slot as usize], // Already in V
+                current: esc.current[slot as usize], // Already in A
+                temperature: info.and_then(|h| h.temperature),
+                error_count: info.map(|h| h.error_count),
+                failure_flags: info.map_or(0, |h| h.failure_flags),
+            }
+        })
+        .collect();
 
-    messages
+    SensorDataFrame {
+        esc: Some(readings),
+        ..Default::default()
+    }
 }
 
 /// Implement SensorDriver trait for compatibility
@@ -318,7 +712,112 @@ impl SensorDriver for MavlinkSensor {
         &self.bus_id
     }
 
+    fn is_push_based(&self) -> bool {
+        true
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mavlink::common::{GpsFixType, GPS_RAW_INT_DATA};
+
+    fn gps_raw_int(eph: u16, epv: u16, vel: u16, cog: u16, sats: u8) -> GPS_RAW_INT_DATA {
+        GPS_RAW_INT_DATA {
+            lat: 473_977_420,
+            lon: -85_455_940,
+            alt: 488_250,
+            eph,
+            epv,
+            vel,
+            cog,
+            fix_type: GpsFixType::GPS_FIX_TYPE_RTK_FIXED,
+            satellites_visible: sats,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gps_raw_int_units() {
+        let frame = convert_gps_raw_int_to_frame(&gps_raw_int(120, 250, 1550, 27050, 17));
+        let gps = frame.gps.unwrap();
+        assert_eq!(gps.fix_type, 6);
+        assert_eq!(gps.satellites_visible, Some(17));
+        assert!((gps.latitude - 47.397742).abs() < 1e-9); // degE7
+        assert!((gps.longitude + 8.545594).abs() < 1e-9);
+        assert_eq!(gps.altitude, 488.25); // mm
+        assert_eq!(gps.hdop, Some(1.2));
+        assert_eq!(gps.vdop, Some(2.5));
+        assert_eq!(gps.ground_speed, Some(15.5)); // cm/s
+        assert_eq!(gps.course, Some(270.5)); // cdeg
+    }
+
+    #[test]
+    fn test_gps_raw_int_unknown_values() {
+        let max = u16::MAX;
+        let frame = convert_gps_raw_int_to_frame(&gps_raw_int(max, max, max, max, u8::MAX));
+        let gps = frame.gps.unwrap();
+        assert_eq!(gps.satellites_visible, None);
+        assert_eq!(gps.hdop, None);
+        assert_eq!(gps.vdop, None);
+        assert_eq!(gps.ground_speed, None);
+        assert_eq!(gps.course, None);
+        assert_eq!(gps.yaw, None);
+        assert_eq!(gps.yaw_accuracy, None);
+
+        // Zero is a real reading, not a sentinel
+        let gps = convert_gps_raw_int_to_frame(&gps_raw_int(0, 0, 0, 0, 0))
+            .gps
+            .unwrap();
+        assert_eq!(gps.satellites_visible, Some(0));
+        assert_eq!(gps.ground_speed, Some(0.0));
+        assert_eq!(gps.course, Some(0.0));
+    }
+
+    #[test]
+    fn test_gnss_yaw_encoding() {
+        assert_eq!(gnss_yaw(0), None); // receiver has no yaw
+        assert_eq!(gnss_yaw(u16::MAX), None); // none right now
+        assert_eq!(gnss_yaw(36000), Some(0.0)); // north
+        assert_eq!(gnss_yaw(9000), Some(90.0));
+        assert_eq!(gnss_yaw(35999), Some(359.99));
+    }
+
+    #[test]
+    fn test_gnss_heading_on_attitude() {
+        let attitude = mavlink::common::ATTITUDE_DATA::default();
+        let mut heading = GnssHeading::default();
+        let frame = heading.attach(convert_attitude_euler_to_frame(&attitude));
+        assert_eq!(frame.gnss_yaw, None);
+
+        let gps = |yaw, hdg_acc| GPS_RAW_INT_DATA {
+            yaw,
+            hdg_acc,
+            ..gps_raw_int(120, 250, 1550, 27050, 17)
+        };
+        heading.update(&convert_gps_raw_int_to_frame(&gps(36000, 50_000)));
+        let frame = heading.attach(convert_attitude_euler_to_frame(&attitude));
+        assert_eq!(frame.gnss_yaw, Some(0.0));
+        assert_eq!(frame.gnss_yaw_accuracy, Some(0.5)); // degE5
+
+        // A fix without yaw keeps the last heading until it ages out
+        heading.update(&convert_gps_raw_int_to_frame(&gps(0, 0)));
+        let messages = frame_to_messages(
+            heading.attach(convert_attitude_euler_to_frame(&attitude)),
+            Header::new("hub".into(), "fc_att".into(), "fc_att".into(), 1),
+            "fc_att",
+        );
+        let Some(crate::messages::SensorMessage::Attitude(att)) = messages.first() else {
+            panic!("expected an attitude message");
+        };
+        assert_eq!(att.gnss_yaw, Some(0.0));
+
+        heading.latest.as_mut().unwrap().2 -= GnssHeading::MAX_AGE * 2;
+        let frame = heading.attach(convert_attitude_euler_to_frame(&attitude));
+        assert_eq!(frame.gnss_yaw, None);
+    }
+}
@@ -0,0 +1,520 @@
+use super::uart::UartProtocol;
+use super::{check_params, DriverParams, Measurement, SensorDataFrame};
+use crate::errors::{SensorError, SensorResult};
+use std::ops::Range;
+use toml::Value;
+use tracing::{trace, warn};
+
+/// Function codes of the two register reads
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+/// Set in the function code of an exception response
+const EXCEPTION_FLAG: u8 = 0x80;
+/// Most registers one read may return (250 data bytes)
+const MAX_READ_REGISTERS: u16 = 125;
+/// Keys of one `registers` entry
+const REGISTER_KEYS: [&str; 8] = [
+    "name",
+    "register",
+    "function",
+    "type",
+    "swap_words",
+    "scale",
+    "offset",
+    "unit",
+];
+
+/// CRC-16/MODBUS of an RTU frame (sent low byte first)
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// RTU request reading `count` registers from `start`
+fn read_request(slave: u8, function: u8, start: u16, count: u16) -> Vec<u8> {
+    let mut frame = vec![slave, function];
+    frame.extend_from_slice(&start.to_be_bytes());
+    frame.extend_from_slice(&count.to_be_bytes());
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+/// Registers read for `count` registers of `function` from `slave`, or the exception code
+/// it answered with; None until a complete frame is buffered
+///
+/// RTU frames have no start marker: bytes that cannot begin the expected response (line
+/// noise, the tail of an earlier answer, our own request echoed) are dropped one at a time
+/// until a frame with a valid CRC lines up.
+fn take_response(
+    buf: &mut Vec<u8>,
+    slave: u8,
+    function: u8,
+    count: u16,
+) -> Option<Result<Vec<u16>, u8>> {
+    let data_len = 2 * count as usize;
+    loop {
+        match buf.iter().position(|&b| b == slave) {
+            Some(start) => {
+                buf.drain(..start);
+            }
+            None => {
+                buf.clear();
+                return None;
+            }
+        }
+        if buf.len() < 3 {
+            return None;
+        }
+        let len = if buf[1] == function && buf[2] as usize == data_len {
+            3 + data_len + 2
+        } else if buf[1] == function | EXCEPTION_FLAG {
+            5
+        } else {
+            buf.remove(0);
+            continue;
+        };
+        if buf.len() < len {
+            return None;
+        }
+        let crc = u16::from_le_bytes([buf[len - 2], buf[len - 1]]);
+        if crc16(&buf[..len - 2]) != crc {
+            trace!("[modbus] Dropping byte before a frame with a bad CRC");
+            buf.remove(0);
+            continue;
+        }
+        let frame: Vec<u8> = buf.drain(..len).collect();
+        if frame[1] & EXCEPTION_FLAG != 0 {
+            return Some(Err(frame[2]));
+        }
+        let words = frame[3..3 + data_len]
+            .chunks_exact(2)
+            .map(|w| u16::from_be_bytes([w[0], w[1]]))
+            .collect();
+        return Some(Ok(words));
+    }
+}
+
+/// Encoding of a value in one or two registers
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl ValueType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "u16" => ValueType::U16,
+            "i16" => ValueType::I16,
+            "u32" => ValueType::U32,
+            "i32" => ValueType::I32,
+            "f32" => ValueType::F32,
+            _ => return None,
+        })
+    }
+
+    fn registers(self) -> u16 {
+        match self {
+            ValueType::U16 | ValueType::I16 => 1,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => 2,
+        }
+    }
+}
+
+/// One entry of the register map and how to turn it into a measurement
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    function: u8,
+    register: u16,
+    value_type: ValueType,
+    /// 32-bit values with the low word first (the Modbus default is high word first)
+    swap_words: bool,
+    scale: f64,
+    offset: f64,
+    unit: Option<String>,
+}
+
+impl Field {
+    fn end(&self) -> u32 {
+        self.register as u32 + self.value_type.registers() as u32
+    }
+
+    /// Scaled value from the field's registers
+    fn decode(&self, words: &[u16]) -> f64 {
+        let raw = match self.value_type {
+            ValueType::U16 => words[0] as f64,
+            ValueType::I16 => words[0] as i16 as f64,
+            ValueType::U32 | ValueType::I32 | ValueType::F32 => {
+                let (high, low) = if self.swap_words {
+                    (words[1], words[0])
+                } else {
+                    (words[0], words[1])
+                };
+                let bits = (high as u32) << 16 | low as u32;
+                match self.value_type {
+                    ValueType::U32 => bits as f64,
+                    ValueType::I32 => bits as i32 as f64,
+                    _ => f32::from_bits(bits) as f64,
+                }
+            }
+        };
+        raw * self.scale + self.offset
+    }
+}
+
+/// Registers fetched by one request, covering a run of adjacent fields
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    function: u8,
+    start: u16,
+    count: u16,
+    request: Vec<u8>,
+    /// Indices of the fields it covers
+    fields: Range<usize>,
+    /// An exception was already logged for it (further ones are traced)
+    warned: bool,
+}
+
+/// Group `fields` (sorted by function and register) into as few requests as possible,
+/// without reading registers outside the map: some devices reject gaps
+fn blocks(slave: u8, fields: &[Field]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        if let Some(block) = blocks.last_mut() {
+            let end = block.start as u32 + block.count as u32;
+            let new_end = end.max(field.end());
+            if block.function == field.function
+                && field.register as u32 <= end
+                && new_end - block.start as u32 <= MAX_READ_REGISTERS as u32
+            {
+                block.count = (new_end - block.start as u32) as u16;
+                block.fields.end = i + 1;
+                continue;
+            }
+        }
+        blocks.push(Block {
+            function: field.function,
+            start: field.register,
+            count: field.value_type.registers(),
+            request: Vec::new(),
+            fields: i..i + 1,
+            warned: false,
+        });
+    }
+    for block in blocks.iter_mut() {
+        block.request = read_request(slave, block.function, block.start, block.count);
+    }
+    blocks
+}
+
+/// Generic Modbus RTU slave, read through a register map from its `params`
+///
+/// Each poll sends the request of the next block of registers; once every block has been
+/// answered, the fields read are published as one `MeasurementsData` message.
+pub struct ModbusSensor {
+    sensor_id: String,
+    slave: u8,
+    fields: Vec<Field>,
+    blocks: Vec<Block>,
+    /// Block whose request is sent on the next poll
+    current: usize,
+    /// Values read so far in this round
+    values: Vec<Option<f64>>,
+    buf: Vec<u8>,
+}
+
+impl ModbusSensor {
+    /// `address` is the slave id (1-247) from the sensor's `address`
+    pub fn new(sensor_id: &str, address: u16) -> SensorResult<Self> {
+        if !(1..=247).contains(&address) {
+            return Err(SensorError::ConfigError {
+                sensor: sensor_id.to_string(),
+                reason: format!("Modbus slave address {} is not in 1-247", address),
+            });
+        }
+        Ok(Self {
+            sensor_id: sensor_id.to_string(),
+            slave: address as u8,
+            fields: Vec::new(),
+            blocks: Vec::new(),
+            current: 0,
+            values: Vec::new(),
+            buf: Vec::new(),
+        })
+    }
+
+    fn frame(&mut self) -> Option<SensorDataFrame> {
+        let measurements: Vec<Measurement> = self
+            .fields
+            .iter()
+            .zip(self.values.iter_mut())
+            .filter_map(|(field, value)| {
+                Some(Measurement {
+                    name: field.name.clone(),
+                    value: value.take()?,
+                    unit: field.unit.clone(),
+                })
+            })
+            .collect();
+        (!measurements.is_empty()).then(|| SensorDataFrame {
+            measurements: Some(measurements),
+            ..Default::default()
+        })
+    }
+}
+
+/// Parse one `registers` entry
+fn parse_field(sensor: &str, entry: &Value) -> SensorResult<Field> {
+    let error = |reason: String| SensorError::ConfigError {
+        sensor: sensor.to_string(),
+        reason,
+    };
+    let table = entry
+        .as_table()
+        .ok_or_else(|| error("each entry of 'registers' must be a table".to_string()))?;
+    if let Some(key) = table.keys().find(|k| !REGISTER_KEYS.contains(&k.as_str())) {
+        return Err(error(format!(
+            "unknown register key '{}' (supported: {})",
+            key,
+            REGISTER_KEYS.join(", ")
+        )));
+    }
+    let name = table
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| error("register entry without a 'name'".to_string()))?
+        .to_string();
+    let register = table
+        .get("register")
+        .and_then(Value::as_integer)
+        .and_then(|r| u16::try_from(r).ok())
+        .ok_or_else(|| error(format!("'{}': 'register' must be 0-65535", name)))?;
+    let function = match table.get("function").map(|f| f.as_str()) {
+        None | Some(Some("holding")) => READ_HOLDING_REGISTERS,
+        Some(Some("input")) => READ_INPUT_REGISTERS,
+        _ => {
+            return Err(error(format!(
+                "'{}': 'function' must be \"holding\" or \"input\"",
+                name
+            )))
+        }
+    };
+    let value_type = match table.get("type") {
+        None => ValueType::U16,
+        Some(t) => t.as_str().and_then(ValueType::parse).ok_or_else(|| {
+            error(format!(
+                "'{}': 'type' must be one of u16, i16, u32, i32, f32",
+                name
+            ))
+        })?,
+    };
+    let number = |key: &str, default: f64| -> SensorResult<f64> {
+        match table.get(key) {
+            None => Ok(default),
+            Some(v) => v
+                .as_float()
+                .or_else(|| v.as_integer().map(|i| i as f64))
+                .ok_or_else(|| error(format!("'{}': '{}' must be a number", name, key))),
+        }
+    };
+    let scale = number("scale", 1.0)?;
+    let offset = number("offset", 0.0)?;
+    let swap_words = match table.get("swap_words") {
+        None => false,
+        Some(v) => v
+            .as_bool()
+            .ok_or_else(|| error(format!("'{}': 'swap_words' must be true or false", name)))?,
+    };
+    let unit = match table.get("unit") {
+        None => None,
+        Some(v) => Some(
+            v.as_str()
+                .ok_or_else(|| error(format!("'{}': 'unit' must be a string", name)))?
+                .to_string(),
+        ),
+    };
+    if register as u32 + value_type.registers() as u32 > 0x10000 {
+        return Err(error(format!("'{}': runs past register 65535", name)));
+    }
+    Ok(Field {
+        name,
+        function,
+        register,
+        value_type,
+        swap_words,
+        scale,
+        offset,
+        unit,
+    })
+}
+
+impl UartProtocol for ModbusSensor {
+    fn default_baud(&self) -> u32 {
+        9600
+    }
+
+    fn configure(&mut self, sensor: &str, params: &DriverParams) -> SensorResult<()> {
+        check_params(sensor, params, &["registers"])?;
+        let entries = params
+            .get("registers")
+            .and_then(Value::as_array)
+            .filter(|entries| !entries.is_empty())
+            .ok_or_else(|| SensorError::ConfigError {
+                sensor: sensor.to_string(),
+                reason: "Modbus sensors need a non-empty 'registers' list".to_string(),
+            })?;
+        let mut fields = entries
+            .iter()
+            .map(|entry| parse_field(sensor, entry))
+            .collect::<SensorResult<Vec<_>>>()?;
+        fields.sort_by_key(|f| (f.function, f.register));
+        self.blocks = blocks(self.slave, &fields);
+        self.values = vec![None; fields.len()];
+        self.fields = fields;
+        self.current = 0;
+        Ok(())
+    }
+
+    fn poll_command(&self) -> Option<&[u8]> {
+        self.blocks
+            .get(self.current)
+            .map(|block| block.request.as_slice())
+    }
+
+    fn parse(&mut self, bytes: &[u8]) -> Vec<SensorDataFrame> {
+        self.buf.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+        while let Some(block) = self.blocks.get_mut(self.current) {
+            let Some(response) =
+                take_response(&mut self.buf, self.slave, block.function, block.count)
+            else {
+                break;
+            };
+            match response {
+                Ok(words) => {
+                    for i in block.fields.clone() {
+                        let field = &self.fields[i];
+                        let offset = (field.register - block.start) as usize;
+                        self.values[i] = Some(field.decode(&words[offset..]));
+                    }
+                }
+                Err(code) if !block.warned => {
+                    warn!(
+                        "[{}] Slave {} answered a read of registers {}..+{} with exception {}",
+                        self.sensor_id, self.slave, block.start, block.count, code
+                    );
+                    block.warned = true;
+                }
+                Err(code) => trace!("[{}] Modbus exception {}", self.sensor_id, code),
+            }
+            self.current += 1;
+            if self.current == self.blocks.len() {
+                self.current = 0;
+                frames.extend(self.frame());
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(slave: u8, function: u8, words: &[u16]) -> Vec<u8> {
+        let mut frame = vec![slave, function, (words.len() * 2) as u8];
+        for word in words {
+            frame.extend_from_slice(&word.to_be_bytes());
+        }
+        let crc = crc16(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    fn configured(registers: &str) -> ModbusSensor {
+        let params: DriverParams = toml::from_str(registers).unwrap();
+        let mut sensor = ModbusSensor::new("wind0", 1).unwrap();
+        sensor.configure("wind0", &params).unwrap();
+        sensor
+    }
+
+    #[test]
+    fn test_register_map_round() {
+        let mut sensor = configured(
+            r#"registers = [
+                { name = "temperature", register = 16, type = "i16", scale = 0.1, unit = "degC", function = "input" },
+                { name = "wind_speed", register = 0, scale = 0.01, unit = "m/s" },
+                { name = "wind_direction", register = 1, unit = "deg" },
+                { name = "pressure", register = 2, type = "f32", swap_words = true },
+            ]"#,
+        );
+        // Holding registers 0-3 in one request, the input register in another
+        assert_eq!(
+            sensor.poll_command().unwrap(),
+            [0x01, 0x03, 0x00, 0x00, 0x00, 0x04, 0x44, 0x09]
+        );
+
+        let pressure = 101325.5f32.to_bits();
+        let mut bytes = vec![0x00, 0x7F]; // line noise
+        bytes.extend(response(
+            1,
+            READ_HOLDING_REGISTERS,
+            &[1234, 270, pressure as u16, (pressure >> 16) as u16],
+        ));
+        let split = bytes.len() - 3;
+        assert!(sensor.parse(&bytes[..split]).is_empty());
+        assert!(sensor.parse(&bytes[split..]).is_empty());
+        assert_eq!(sensor.poll_command().unwrap()[..2], [0x01, 0x04]);
+
+        let frames = sensor.parse(&response(1, READ_INPUT_REGISTERS, &[-52i16 as u16]));
+        let values = frames[0].measurements.as_ref().unwrap();
+        let value = |name: &str| values.iter().find(|m| m.name == name).unwrap().value;
+        assert!((value("wind_speed") - 12.34).abs() < 1e-9);
+        assert_eq!(value("wind_direction"), 270.0);
+        assert_eq!(value("pressure"), 101325.5);
+        assert!((value("temperature") + 5.2).abs() < 1e-9);
+        assert_eq!(values[0].unit.as_deref(), Some("m/s"));
+        assert_eq!(sensor.poll_command().unwrap()[..2], [0x01, 0x03]);
+
+        // An exception skips the block; the rest is still published
+        let mut exception = vec![0x01, READ_HOLDING_REGISTERS | EXCEPTION_FLAG, 0x02];
+        exception.extend_from_slice(&crc16(&exception).to_le_bytes());
+        assert!(sensor.parse(&exception).is_empty());
+        let frames = sensor.parse(&response(1, READ_INPUT_REGISTERS, &[250]));
+        let values = frames[0].measurements.as_ref().unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].name, "temperature");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(ModbusSensor::new("wind0", 248).is_err());
+        let mut sensor = ModbusSensor::new("wind0", 1).unwrap();
+        for params in [
+            "",
+            "registers = []",
+            r#"registers = [{ name = "a", register = 70000 }]"#,
+            r#"registers = [{ name = "a", register = 0, type = "u64" }]"#,
+            r#"registers = [{ name = "a", register = 0, scael = 2 }]"#,
+            r#"registers = [{ name = "a", register = 65535, type = "u32" }]"#,
+        ] {
+            let params: DriverParams = toml::from_str(params).unwrap();
+            assert!(sensor.configure("wind0", &params).is_err(), "{:?}", params);
+        }
+    }
+}
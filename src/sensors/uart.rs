@@ -0,0 +1,255 @@
+use super::{check_params, frame_to_messages, DriverParams, SensorDataFrame, SensorDriver};
+use crate::bus::i2c::I2CBus;
+use crate::bus::serial::{Rs485Config, SerialBus};
+use crate::errors::{SensorError, SensorResult};
+use crate::grpc_service::SensorHubService;
+use crate::messages::Header;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::time::{Duration, MissedTickBehavior};
+use tracing::{error, info, trace, warn};
+
+/// Protocol handler for a sensor attached to a dedicated UART
+///
+/// The generic `UartSensor` owns the port and the read loop; a protocol only turns
+/// received bytes into frames and optionally supplies a command to poll the device.
+pub trait UartProtocol: Send + Sync {
+    /// Default baud rate when the bus config does not specify one
+    fn default_baud(&self) -> u32 {
+        115200
+    }
+
+    /// Apply the sensor's `params` (the protocol takes none unless it overrides this)
+    fn configure(&mut self, sensor: &str, params: &DriverParams) -> SensorResult<()> {
+        check_params(sensor, params, &[])
+    }
+
+    /// Bytes to write every poll period (None for devices that stream on their own)
+    fn poll_command(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Feed received bytes, returning any frames completed by them
+    fn parse(&mut self, bytes: &[u8]) -> Vec<SensorDataFrame>;
+}
+
+/// A UART bus from `buses.toml`
+#[derive(Debug, Clone)]
+pub struct UartPort {
+    pub path: String,
+    /// Baud rate (defaults to the protocol's)
+    pub baud: Option<u32>,
+    /// Half-duplex RS-485 transceiver settings
+    pub rs485: Option<Rs485Config>,
+}
+
+impl UartPort {
+    fn open(&self, baud: u32) -> std::io::Result<SerialBus> {
+        match &self.rs485 {
+            Some(rs485) => SerialBus::new_rs485(&self.path, baud, rs485.clone()),
+            None => SerialBus::new_with_baud(&self.path, baud),
+        }
+    }
+}
+
+/// Push-based sensor reading a serial device through a `UartProtocol`
+pub struct UartSensor {
+    id: String,
+    bus_id: String,
+    protocol: Option<Box<dyn UartProtocol>>,
+    grpc_service: Option<Arc<SensorHubService>>,
+    started: bool,
+}
+
+impl UartSensor {
+    pub fn new(id: String, bus_id: String, protocol: Box<dyn UartProtocol>) -> Self {
+        Self {
+            id,
+            bus_id,
+            protocol: Some(protocol),
+            grpc_service: None,
+            started: false,
+        }
+    }
+
+    /// Set the gRPC service for publishing sensor data
+    pub fn set_grpc_service(&mut self, service: Arc<SensorHubService>) {
+        self.grpc_service = Some(service);
+    }
+
+    /// Open the port and start the read loop
+    /// `frequency` is the poll rate for devices that need to be asked for each sample
+    pub fn start(&mut self, port: &UartPort, frequency: u32) -> SensorResult<()> {
+        let grpc = self
+            .grpc_service
+            .clone()
+            .ok_or_else(|| SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: "gRPC service not set".to_string(),
+            })?;
+        let protocol = self.protocol.take().ok_or_else(|| SensorError::InitError {
+            sensor: self.id.clone(),
+            reason: "UART read loop already started".to_string(),
+        })?;
+        let baud = port.baud.unwrap_or_else(|| protocol.default_baud());
+
+        // Open once up front so a wrong path fails at startup rather than in the background
+        let serial = port
+            .open(baud)
+            .map_err(|e| SensorError::SerialError(e.into()))?;
+        info!(
+            "[{}] Opened UART {} at {} baud{}",
+            self.id,
+            port.path,
+            baud,
+            if port.rs485.is_some() {
+                " (RS-485)"
+            } else {
+                ""
+            }
+        );
+
+        let poll_period = Duration::from_millis((1000.0 / frequency.max(1) as f32) as u64);
+        tokio::spawn(read_loop(
+            self.id.clone(),
+            port.clone(),
+            baud,
+            serial,
+            protocol,
+            poll_period,
+            grpc,
+        ));
+        self.started = true;
+        Ok(())
+    }
+}
+
+/// Read bytes, parse frames and publish them; re-open the port if it goes away
+async fn read_loop(
+    sensor_id: String,
+    uart: UartPort,
+    baud: u32,
+    mut port: SerialBus,
+    mut protocol: Box<dyn UartProtocol>,
+    poll_period: Duration,
+    grpc: Arc<SensorHubService>,
+) {
+    let mut sequence_counter = 0u64;
+    let mut buf = [0u8; 256];
+
+    loop {
+        let mut ticker = tokio::time::interval(poll_period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick(), if protocol.poll_command().is_some() => {
+                    if let Some(cmd) = protocol.poll_command() {
+                        if let Err(e) = port.write(cmd).await {
+                            warn!("[{}] UART write failed: {}", sensor_id, e);
+                            break;
+                        }
+                    }
+                }
+                result = port.read(&mut buf) => {
+                    let n = match result {
+                        Ok(0) => {
+                            warn!("[{}] UART {} closed", sensor_id, uart.path);
+                            break;
+                        }
+                        Ok(n) => n,
+                        Err(e) => {
+                            warn!("[{}] UART read failed: {}", sensor_id, e);
+                            break;
+                        }
+                    };
+                    trace!("[{}] Received {} bytes", sensor_id, n);
+
+                    for frame in protocol.parse(&buf[..n]) {
+                        sequence_counter += 1;
+                        let header = Header::new(
+                            "navigate_hub".to_string(),
+                            sensor_id.clone(),
+                            "sensor_frame".to_string(),
+                            sequence_counter,
+                        );
+                        for msg in frame_to_messages(frame, header, &sensor_id) {
+                            if let Err(e) = grpc.publish(msg).await {
+                                error!("[{}] Failed to publish: {}", sensor_id, e);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Port lost - announce the sensor as gone and keep trying to re-open it
+        let info = grpc.remove_sensor(&sensor_id, "port closed").await;
+        port = loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            match uart.open(baud) {
+                Ok(serial) => {
+                    info!("[{}] Re-opened UART {}", sensor_id, uart.path);
+                    if let Some(info) = &info {
+                        grpc.register_sensor(
+                            &info.sensor_id,
+                            &info.driver,
+                            &info.bus_id,
+                            &info.role,
+                        )
+                        .await;
+                    }
+                    break serial;
+                }
+                Err(e) => trace!("[{}] UART {} not available: {}", sensor_id, uart.path, e),
+            }
+        };
+    }
+}
+
+/// Implement SensorDriver trait for compatibility
+/// Note: UART sensors are push-based, like MAVLink sensors
+#[async_trait]
+impl SensorDriver for UartSensor {
+    async fn init(&mut self, _bus: &mut I2CBus) -> SensorResult<()> {
+        // UART sensors initialize via start()
+        if self.started {
+            Ok(())
+        } else {
+            Err(SensorError::InitError {
+                sensor: self.id.clone(),
+                reason: "UART read loop not started".to_string(),
+            })
+        }
+    }
+
+    async fn read(&self, _bus: &mut I2CBus) -> SensorResult<SensorDataFrame> {
+        Err(SensorError::ReadError {
+            sensor: self.id.clone(),
+            reason: "UART sensors are push-based, data published via gRPC stream".to_string(),
+        })
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn configure(&mut self, params: &DriverParams) -> SensorResult<()> {
+        match self.protocol.as_mut() {
+            Some(protocol) => protocol.configure(&self.id, params),
+            None => check_params(&self.id, params, &[]),
+        }
+    }
+
+    fn bus(&self) -> &str {
+        &self.bus_id
+    }
+
+    fn is_push_based(&self) -> bool {
+        true
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
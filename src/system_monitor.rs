@@ -0,0 +1,227 @@
+use crate::config::sensor_config::SystemConfig;
+use crate::grpc_service::SensorHubService;
+use crate::messages::{Header, SensorMessage, SystemMessage, ThermalZoneMessage};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, error, info};
+
+/// Sensor id of the host pseudo-sensor
+pub const SYSTEM_SENSOR_ID: &str = "system";
+
+/// Cumulative CPU time from the aggregate `cpu` line of /proc/stat (clock ticks)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+fn parse_cpu_times(stat: &str) -> Option<CpuTimes> {
+    let line = stat.lines().find(|l| l.starts_with("cpu "))?;
+    // user nice system idle iowait irq softirq steal (guest time is already in user)
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .filter_map(|f| f.parse().ok())
+        .collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    let total: u64 = fields.iter().sum();
+    let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+    Some(CpuTimes {
+        busy: total - idle,
+        total,
+    })
+}
+
+/// Busy fraction of all cores between two samples
+fn cpu_load(previous: CpuTimes, current: CpuTimes) -> f32 {
+    let total = current.total.saturating_sub(previous.total);
+    if total == 0 {
+        return 0.0;
+    }
+    current.busy.saturating_sub(previous.busy) as f32 / total as f32
+}
+
+/// MemTotal and MemAvailable from /proc/meminfo, in bytes
+fn parse_meminfo(meminfo: &str) -> (u64, u64) {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|rest| {
+                rest.trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+            .map_or(0, |kb| kb * 1024)
+    };
+    (field("MemTotal:"), field("MemAvailable:"))
+}
+
+/// Temperatures of /sys/class/thermal zones, named by their type
+fn thermal_zones(root: &Path) -> Vec<ThermalZoneMessage> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut zones: Vec<(u32, String, f32)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let index = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("thermal_zone")?
+                .parse()
+                .ok()?;
+            let kind = fs::read_to_string(path.join("type")).ok()?;
+            // Millidegrees Celsius
+            let milli: i64 = fs::read_to_string(path.join("temp"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some((index, kind.trim().to_string(), milli as f32 / 1000.0))
+        })
+        .collect();
+    zones.sort_by_key(|(index, _, _)| *index);
+
+    let mut named: Vec<ThermalZoneMessage> = Vec::with_capacity(zones.len());
+    for (index, kind, temperature) in zones {
+        // Several zones can share a type (e.g. "acpitz"), keep their names distinct
+        let name = if named.iter().any(|z| z.name == kind) {
+            format!("{}_{}", kind, index)
+        } else {
+            kind
+        };
+        named.push(ThermalZoneMessage { name, temperature });
+    }
+    named
+}
+
+/// Total and available bytes of the filesystem holding `path`
+// The statvfs counts are only 32-bit on some targets (e.g. 32-bit ARM)
+#[allow(clippy::unnecessary_cast)]
+fn disk_usage(path: &str) -> (u64, u64) {
+    match nix::sys::statvfs::statvfs(path) {
+        Ok(stat) => {
+            let block = stat.fragment_size() as u64;
+            (
+                stat.blocks() as u64 * block,
+                stat.blocks_available() as u64 * block,
+            )
+        }
+        Err(e) => {
+            debug!("[system] Cannot stat {}: {}", path, e);
+            (0, 0)
+        }
+    }
+}
+
+/// One reading of the host; `previous` holds the CPU times of the last reading
+fn sample(disk_path: &str, previous: &mut Option<CpuTimes>) -> SystemMessage {
+    let cpu = fs::read_to_string("/proc/stat")
+        .ok()
+        .and_then(|stat| parse_cpu_times(&stat));
+    let cpu_load = match (*previous, cpu) {
+        (Some(previous), Some(current)) => cpu_load(previous, current),
+        _ => 0.0,
+    };
+    *previous = cpu;
+    let load_avg_1m = fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|l| l.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0.0);
+    let (mem_total_bytes, mem_available_bytes) = fs::read_to_string("/proc/meminfo")
+        .map(|m| parse_meminfo(&m))
+        .unwrap_or_default();
+    let (disk_total_bytes, disk_available_bytes) = disk_usage(disk_path);
+
+    SystemMessage {
+        h: Header::new(
+            "navigate_hub".to_string(),
+            SYSTEM_SENSOR_ID.to_string(),
+            "host".to_string(),
+            0,
+        ),
+        cpu_load,
+        load_avg_1m,
+        mem_total_bytes,
+        mem_available_bytes,
+        disk_total_bytes,
+        disk_available_bytes,
+        thermal_zones: thermal_zones(Path::new("/sys/class/thermal")),
+        history_bytes: 0,
+    }
+}
+
+/// Publish the companion computer's load, memory, disk and thermal zones as sensor "system"
+pub fn spawn_monitor(config: &SystemConfig, grpc_service: Arc<SensorHubService>) {
+    if !config.enabled {
+        return;
+    }
+    let interval = Duration::from_millis(config.interval_ms.max(100));
+    let disk_path = config.disk_path.clone();
+    info!(
+        "[system] Publishing host health every {} ms",
+        interval.as_millis()
+    );
+
+    tokio::spawn(async move {
+        grpc_service
+            .register_sensor(SYSTEM_SENSOR_ID, "system", "host", "system")
+            .await;
+        let mut ticker = tokio::time::interval(interval);
+        let mut previous = None;
+        let mut seq = 0u64;
+        loop {
+            ticker.tick().await;
+            let path = disk_path.clone();
+            let reading = tokio::task::spawn_blocking(move || {
+                let mut cpu = previous;
+                let message = sample(&path, &mut cpu);
+                (message, cpu)
+            })
+            .await;
+            let Ok((mut message, cpu)) = reading else {
+                error!("[system] Sampling task failed");
+                continue;
+            };
+            previous = cpu;
+            seq += 1;
+            message.h.seq = seq;
+            message.history_bytes = grpc_service.history_bytes();
+            if let Err(e) = grpc_service.publish(SensorMessage::System(message)).await {
+                error!("[system] Failed to publish: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proc_parsing() {
+        let t0 = parse_cpu_times("cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 1 2 3 4\n").unwrap();
+        let t1 = parse_cpu_times("cpu  200 0 100 850 50 0 0 0 0 0\n").unwrap();
+        assert_eq!(
+            t0,
+            CpuTimes {
+                busy: 150,
+                total: 1000
+            }
+        );
+        assert_eq!(cpu_load(t0, t1), 0.75);
+        assert_eq!(cpu_load(t1, t1), 0.0);
+
+        let meminfo =
+            "MemTotal:        8000000 kB\nMemFree:  100 kB\nMemAvailable:    2000000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), (8_192_000_000, 2_048_000_000));
+    }
+}
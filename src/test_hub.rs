@@ -0,0 +1,183 @@
+//! In-process hub for testing gRPC clients without sockets or hardware (`test-util` feature)
+//!
+//! ```ignore
+//! let hub = TestHub::start().await;
+//! hub.add_sensor("imu0", "icm42688p", "primary_imu").await;
+//! let mut stream = hub.client().stream_imu(SensorRequest::default()).await?.into_inner();
+//! hub.publish(imu_message).await;
+//! ```
+
+use crate::grpc_service::sensorhub::sensor_hub_client::SensorHubClient;
+use crate::grpc_service::{create_grpc_server, SensorHubService};
+use crate::messages::SensorMessage;
+use crate::rpc_guard::RpcLayer;
+use hyper_util::rt::TokioIo;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::transport::{Channel, Endpoint, Server, Uri};
+
+/// Bus id reported for sensors added through [`TestHub::add_sensor`]
+pub const TEST_BUS_ID: &str = "test";
+/// Buffer of each in-memory connection
+const CONNECTION_BUFFER: usize = 64 * 1024;
+
+/// Opens in-memory connections, handing the server end to the test hub
+#[derive(Clone)]
+struct Connector(mpsc::UnboundedSender<DuplexStream>);
+
+impl tower_service::Service<Uri> for Connector {
+    type Response = TokioIo<DuplexStream>;
+    type Error = std::io::Error;
+    type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let (client, server) = tokio::io::duplex(CONNECTION_BUFFER);
+        std::future::ready(
+            self.0
+                .send(server)
+                .map(|_| TokioIo::new(client))
+                .map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "test hub stopped")
+                }),
+        )
+    }
+}
+
+/// The hub's gRPC service, fed by scripted messages instead of sensors
+pub struct TestHub {
+    service: Arc<SensorHubService>,
+    channel: Channel,
+    server: JoinHandle<()>,
+}
+
+impl TestHub {
+    /// Start a hub with no sensors; stopped when dropped
+    pub async fn start() -> Self {
+        let service = Arc::new(SensorHubService::new());
+        let (tx, rx) = mpsc::unbounded_channel();
+        let incoming = UnboundedReceiverStream::new(rx).map(Ok::<_, std::io::Error>);
+        let router = Server::builder()
+            .layer(RpcLayer::new(service.metrics()))
+            .add_service(create_grpc_server(service.as_ref().clone()));
+        let server = tokio::spawn(async move {
+            let _ = router.serve_with_incoming(incoming).await;
+        });
+        let channel =
+            Endpoint::from_static("http://test-hub").connect_with_connector_lazy(Connector(tx));
+        Self {
+            service,
+            channel,
+            server,
+        }
+    }
+
+    /// A client connected to this hub
+    pub fn client(&self) -> SensorHubClient<Channel> {
+        SensorHubClient::new(self.channel.clone())
+    }
+
+    /// The service behind the hub, for configuration not covered by the helpers
+    pub fn service(&self) -> &Arc<SensorHubService> {
+        &self.service
+    }
+
+    /// Announce a sensor on ListSensors and StreamEvents
+    pub async fn add_sensor(&self, sensor_id: &str, driver: &str, role: &str) {
+        self.service
+            .register_sensor(sensor_id, driver, TEST_BUS_ID, role)
+            .await;
+    }
+
+    /// Remove a sensor, as when its device goes away
+    pub async fn remove_sensor(&self, sensor_id: &str, reason: &str) {
+        self.service.remove_sensor(sensor_id, reason).await;
+    }
+
+    /// Publish one message through the hub's normal path (streams, latest sample, stats)
+    pub async fn publish(&self, message: SensorMessage) {
+        let _ = self.service.publish(message).await;
+    }
+
+    /// Publish scripted messages in order, waiting each one's delay before sending it
+    pub async fn play(&self, script: impl IntoIterator<Item = (Duration, SensorMessage)>) {
+        for (delay, message) in script {
+            tokio::time::sleep(delay).await;
+            self.publish(message).await;
+        }
+    }
+}
+
+impl Drop for TestHub {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_service::sensorhub::{sensor_event::Event, SensorRequest};
+    use crate::messages::{BarometerMessage, Header};
+
+    #[tokio::test]
+    async fn test_client_receives_scripted_data() {
+        let hub = TestHub::start().await;
+        let mut client = hub.client();
+        let mut events = client
+            .stream_events(SensorRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        let mut data = client
+            .stream_barometer(SensorRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+
+        hub.add_sensor("baro0", "bmp388", "primary_baro").await;
+        let Some(Event::SensorAdded(info)) = events.message().await.unwrap().unwrap().event else {
+            panic!("expected sensor_added");
+        };
+        assert_eq!(
+            (info.sensor_id.as_str(), info.bus_id.as_str()),
+            ("baro0", TEST_BUS_ID)
+        );
+
+        let baro = |seq| {
+            SensorMessage::Barometer(BarometerMessage {
+                h: Header::new("hub".into(), "baro0".into(), "baro0".into(), seq),
+                pressure: 101325.0,
+                temperature: 21.0,
+                altitude: 0.0,
+            })
+        };
+        hub.play([
+            (Duration::ZERO, baro(1)),
+            (Duration::from_millis(10), baro(2)),
+        ])
+        .await;
+        for seq in [1, 2] {
+            let received = data.message().await.unwrap().unwrap();
+            assert_eq!(received.header.unwrap().seq, seq);
+            assert_eq!(received.pressure, 101325.0);
+        }
+
+        let sensors = client
+            .list_sensors(SensorRequest::default())
+            .await
+            .unwrap()
+            .into_inner()
+            .sensors;
+        assert_eq!(sensors.len(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Reads the percentiles are computed over, per sensor
+const WINDOW: usize = 1024;
+
+/// Sampling timing of one polled sensor
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingSummary {
+    /// Delay of a read's start past its scheduled time (µs): p50 and p95 over the last
+    /// reads, max since startup
+    pub jitter_p50_us: u32,
+    pub jitter_p95_us: u32,
+    pub jitter_max_us: u32,
+    /// Time to take the bus and read the sensor (µs), same windows
+    pub latency_p50_us: u32,
+    pub latency_p95_us: u32,
+    pub latency_max_us: u32,
+    /// Reads measured since startup
+    pub reads: u64,
+    /// Reads that finished after the next one was due, since startup
+    pub misses: u64,
+    /// How far those reads ran past the deadline (µs): p50 and p95 over the last misses,
+    /// max since startup
+    pub overrun_p50_us: u32,
+    pub overrun_p95_us: u32,
+    pub overrun_max_us: u32,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    jitter_us: VecDeque<u32>,
+    latency_us: VecDeque<u32>,
+    overrun_us: VecDeque<u32>,
+    jitter_max_us: u32,
+    latency_max_us: u32,
+    overrun_max_us: u32,
+    reads: u64,
+    misses: u64,
+}
+
+fn micros(d: Duration) -> u32 {
+    d.as_micros().min(u32::MAX as u128) as u32
+}
+
+fn push(window: &mut VecDeque<u32>, value: u32) {
+    if window.len() == WINDOW {
+        window.pop_front();
+    }
+    window.push_back(value);
+}
+
+/// Nearest-rank percentile `p` (0..=1) of `values`
+fn percentile(values: &VecDeque<u32>, p: f64) -> u32 {
+    let mut sorted: Vec<u32> = values.iter().copied().collect();
+    sorted.sort_unstable();
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Jitter and latency of the scheduler's reads, for GetSensorStatus and /metrics
+#[derive(Default)]
+pub struct TimingStats {
+    sensors: Mutex<HashMap<String, Samples>>,
+}
+
+impl TimingStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One read of `sensor_id`, due every `period`, that started `jitter` after it was due
+    /// and took `latency`; it missed its deadline if it ran into the next period
+    pub fn record(&self, sensor_id: &str, period: Duration, jitter: Duration, latency: Duration) {
+        let overrun = (jitter + latency).checked_sub(period).map(micros);
+        let (jitter, latency) = (micros(jitter), micros(latency));
+        let mut sensors = self.sensors.lock().unwrap();
+        let samples = sensors.entry(sensor_id.to_string()).or_default();
+        push(&mut samples.jitter_us, jitter);
+        push(&mut samples.latency_us, latency);
+        samples.jitter_max_us = samples.jitter_max_us.max(jitter);
+        samples.latency_max_us = samples.latency_max_us.max(latency);
+        samples.reads += 1;
+        if let Some(overrun) = overrun.filter(|&overrun| overrun > 0) {
+            push(&mut samples.overrun_us, overrun);
+            samples.overrun_max_us = samples.overrun_max_us.max(overrun);
+            samples.misses += 1;
+        }
+    }
+
+    /// None for sensors the scheduler does not read (push-based ones)
+    pub fn summary(&self, sensor_id: &str) -> Option<TimingSummary> {
+        let sensors = self.sensors.lock().unwrap();
+        let samples = sensors.get(sensor_id)?;
+        Some(TimingSummary {
+            jitter_p50_us: percentile(&samples.jitter_us, 0.5),
+            jitter_p95_us: percentile(&samples.jitter_us, 0.95),
+            jitter_max_us: samples.jitter_max_us,
+            latency_p50_us: percentile(&samples.latency_us, 0.5),
+            latency_p95_us: percentile(&samples.latency_us, 0.95),
+            latency_max_us: samples.latency_max_us,
+            reads: samples.reads,
+            misses: samples.misses,
+            overrun_p50_us: percentile(&samples.overrun_us, 0.5),
+            overrun_p95_us: percentile(&samples.overrun_us, 0.95),
+            overrun_max_us: samples.overrun_max_us,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_percentiles_and_max() {
+        let stats = TimingStats::new();
+        assert_eq!(stats.summary("imu0"), None);
+        // One late wake-up among 100 reads, then enough fast ones to push it out of the window
+        for i in 1..=100u64 {
+            let jitter = if i == 50 { 5000 } else { i };
+            stats.record(
+                "imu0",
+                PERIOD,
+                Duration::from_micros(jitter),
+                Duration::from_micros(200),
+            );
+        }
+        let summary = stats.summary("imu0").unwrap();
+        assert_eq!(summary.jitter_p50_us, 51);
+        assert_eq!(summary.jitter_p95_us, 96);
+        assert_eq!(summary.jitter_max_us, 5000);
+        assert_eq!(summary.latency_p95_us, 200);
+        assert_eq!(summary.reads, 100);
+
+        for _ in 0..WINDOW {
+            stats.record("imu0", PERIOD, Duration::from_micros(10), Duration::ZERO);
+        }
+        let summary = stats.summary("imu0").unwrap();
+        assert_eq!(summary.jitter_p95_us, 10);
+        // The worst case seen is kept
+        assert_eq!(summary.jitter_max_us, 5000);
+        assert_eq!(summary.latency_max_us, 200);
+        // The late wake-up ran 5 ms into the 10 ms period, no deadline was missed
+        assert_eq!(summary.misses, 0);
+    }
+
+    #[test]
+    fn test_deadline_misses() {
+        let stats = TimingStats::new();
+        // A read finishing exactly at the next tick is on time
+        stats.record(
+            "baro0",
+            PERIOD,
+            Duration::from_millis(4),
+            Duration::from_millis(6),
+        );
+        for overrun_ms in [1, 2, 3, 40] {
+            stats.record(
+                "baro0",
+                PERIOD,
+                Duration::from_millis(2),
+                Duration::from_millis(8 + overrun_ms),
+            );
+        }
+        let summary = stats.summary("baro0").unwrap();
+        assert_eq!(summary.reads, 5);
+        assert_eq!(summary.misses, 4);
+        assert_eq!(summary.overrun_p50_us, 2000);
+        assert_eq!(summary.overrun_p95_us, 40_000);
+        assert_eq!(summary.overrun_max_us, 40_000);
+    }
+}
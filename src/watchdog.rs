@@ -0,0 +1,52 @@
+use crate::config::sensor_config::WatchdogConfig;
+use crate::grpc_service::SensorHubService;
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::info;
+
+/// Silence after which a sensor publishing at `frequency_hz` is stale, or None while its
+/// rate is not known yet
+pub fn timeout_ns(frequency_hz: u32, config: &WatchdogConfig) -> Option<u64> {
+    if frequency_hz == 0 {
+        return None;
+    }
+    let periods_ns =
+        u64::from(config.stale_periods.max(1)) * 1_000_000_000 / u64::from(frequency_hz);
+    Some(periods_ns.max(config.min_timeout_ms * 1_000_000))
+}
+
+/// Mark sensors that stop publishing unhealthy instead of letting them silently vanish
+/// from the streams
+pub fn spawn_watchdog(config: &WatchdogConfig, grpc_service: Arc<SensorHubService>) {
+    if !config.enabled {
+        return;
+    }
+    info!(
+        "[watchdog] Flagging sensors silent for {} periods (at least {} ms)",
+        config.stale_periods, config.min_timeout_ms
+    );
+    let config = config.clone();
+
+    tokio::spawn(async move {
+        let mut ticker =
+            tokio::time::interval(Duration::from_millis(config.check_interval_ms.max(10)));
+        loop {
+            ticker.tick().await;
+            grpc_service.check_stale(&config).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout() {
+        let config = WatchdogConfig::default();
+        // 5 periods, but never under 250 ms
+        assert_eq!(timeout_ns(10, &config), Some(500_000_000));
+        assert_eq!(timeout_ns(400, &config), Some(250_000_000));
+        assert_eq!(timeout_ns(0, &config), None);
+    }
+}
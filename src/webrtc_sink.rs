@@ -0,0 +1,319 @@
+use crate::config::sensor_config::WebRtcConfig;
+use crate::grpc_service::SensorHubService;
+use crate::messages::SensorMessage;
+use crate::websocket::requested_sensors;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{
+    ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    CONTENT_TYPE,
+};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use webrtc::api::{APIBuilder, API};
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+/// Largest SDP offer accepted
+const MAX_OFFER: usize = 64 * 1024;
+/// Bytes queued on a data channel beyond which messages are skipped, so a slow link sees
+/// fresh data instead of a growing backlog
+const MAX_BUFFERED: usize = 256 * 1024;
+
+/// Viewer slot, released when its peer connection ends
+struct PeerSlot(Arc<AtomicUsize>);
+
+impl Drop for PeerSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Sensor data over WebRTC data channels, with SDP offer/answer over HTTP
+struct Sink {
+    config: WebRtcConfig,
+    api: API,
+    service: Arc<SensorHubService>,
+    peers: Arc<AtomicUsize>,
+}
+
+/// Serve `POST /offer` on `[webrtc] signaling_addr` until the listener fails
+///
+/// A browser creates a data channel, POSTs its offer (non-trickle ICE) and gets the answer
+/// back; the hub then sends JSON messages on the channel, each sensor at most
+/// `max_rate_hz`. `?sensor_ids=a,b` on the offer URL limits the feed.
+pub async fn serve(config: WebRtcConfig, service: Arc<SensorHubService>) -> Result<(), String> {
+    let addr: SocketAddr = config
+        .signaling_addr
+        .parse()
+        .map_err(|e| format!("Invalid signaling_addr '{}': {}", config.signaling_addr, e))?;
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Cannot bind {}: {}", addr, e))?;
+    info!(
+        "[webrtc] Accepting SDP offers on http://{}/offer (ICE servers: {})",
+        addr,
+        if config.ice_servers.is_empty() {
+            "none".to_string()
+        } else {
+            config.ice_servers.join(", ")
+        }
+    );
+    let sink = Arc::new(Sink {
+        config,
+        api: APIBuilder::new().build(),
+        service,
+        peers: Arc::new(AtomicUsize::new(0)),
+    });
+    loop {
+        let (stream, peer) = listener.accept().await.map_err(|e| e.to_string())?;
+        let sink = sink.clone();
+        tokio::spawn(async move {
+            let handler = service_fn(move |request| sink.clone().handle(request));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), handler)
+                .await
+            {
+                debug!("[webrtc] Signaling request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Response with the CORS headers a page served from elsewhere needs to POST its offer
+fn reply(status: StatusCode, content_type: &str, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(ACCESS_CONTROL_ALLOW_METHODS, "POST, OPTIONS")
+        .header(ACCESS_CONTROL_ALLOW_HEADERS, "Content-Type")
+        .body(Full::new(Bytes::from(body)))
+        .expect("static headers are valid")
+}
+
+impl Sink {
+    async fn handle(
+        self: Arc<Self>,
+        request: Request<Incoming>,
+    ) -> Result<Response<Full<Bytes>>, Infallible> {
+        let text = "text/plain";
+        let response = match (request.method(), request.uri().path()) {
+            (&Method::POST, "/offer") => self.offer(request).await,
+            (&Method::OPTIONS, "/offer") => reply(StatusCode::NO_CONTENT, text, String::new()),
+            (_, "/offer") => reply(
+                StatusCode::METHOD_NOT_ALLOWED,
+                text,
+                "Method not allowed\n".to_string(),
+            ),
+            _ => reply(StatusCode::NOT_FOUND, text, "Not found\n".to_string()),
+        };
+        Ok(response)
+    }
+
+    /// Answer an SDP offer, or explain why not
+    async fn offer(&self, request: Request<Incoming>) -> Response<Full<Bytes>> {
+        let text = "text/plain";
+        if self.peers.fetch_add(1, Ordering::Relaxed) >= self.config.max_peers {
+            self.peers.fetch_sub(1, Ordering::Relaxed);
+            return reply(
+                StatusCode::SERVICE_UNAVAILABLE,
+                text,
+                format!("At most {} viewer(s)\n", self.config.max_peers),
+            );
+        }
+        let slot = PeerSlot(self.peers.clone());
+
+        let sensor_ids = requested_sensors(&request.uri().to_string());
+        let body = match Limited::new(request.into_body(), MAX_OFFER).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => return reply(StatusCode::BAD_REQUEST, text, format!("{}\n", e)),
+        };
+        let Ok(sdp) = String::from_utf8(body.to_vec()) else {
+            return reply(
+                StatusCode::BAD_REQUEST,
+                text,
+                "Offer is not UTF-8\n".to_string(),
+            );
+        };
+        match self.connect(sdp, sensor_ids, slot).await {
+            Ok(answer) => reply(StatusCode::CREATED, "application/sdp", answer),
+            Err(e) => {
+                warn!("[webrtc] Cannot answer offer: {}", e);
+                reply(StatusCode::BAD_REQUEST, text, format!("{}\n", e))
+            }
+        }
+    }
+
+    /// Set up a peer connection for `offer` and return the answer with all ICE candidates
+    async fn connect(
+        &self,
+        offer: String,
+        sensor_ids: Vec<String>,
+        slot: PeerSlot,
+    ) -> Result<String, webrtc::Error> {
+        let configuration = RTCConfiguration {
+            ice_servers: vec![RTCIceServer {
+                urls: self.config.ice_servers.clone(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let pc = Arc::new(self.api.new_peer_connection(configuration).await?);
+
+        let (ended_tx, mut ended_rx) = mpsc::channel(1);
+        pc.on_peer_connection_state_change(Box::new(move |state| {
+            debug!("[webrtc] Peer connection {}", state);
+            if matches!(
+                state,
+                RTCPeerConnectionState::Disconnected
+                    | RTCPeerConnectionState::Failed
+                    | RTCPeerConnectionState::Closed
+            ) {
+                let _ = ended_tx.try_send(());
+            }
+            Box::pin(async {})
+        }));
+
+        let service = self.service.clone();
+        let min_interval = Duration::from_secs_f64(1.0 / self.config.max_rate_hz.max(1) as f64);
+        pc.on_data_channel(Box::new(move |channel: Arc<RTCDataChannel>| {
+            let rx = service.subscribe_messages();
+            let sensor_ids = sensor_ids.clone();
+            Box::pin(async move {
+                let opened = channel.clone();
+                channel.on_open(Box::new(move || {
+                    info!(
+                        "[webrtc] Viewer opened channel '{}' for {}",
+                        opened.label(),
+                        if sensor_ids.is_empty() {
+                            "all sensors".to_string()
+                        } else {
+                            sensor_ids.join(", ")
+                        }
+                    );
+                    tokio::spawn(forward(opened, rx, sensor_ids, min_interval));
+                    Box::pin(async {})
+                }));
+            })
+        }));
+
+        pc.set_remote_description(RTCSessionDescription::offer(offer)?)
+            .await?;
+        let answer = pc.create_answer(None).await?;
+        let mut gathered = pc.gathering_complete_promise().await;
+        pc.set_local_description(answer).await?;
+        let _ = gathered.recv().await;
+        let answer = pc
+            .local_description()
+            .await
+            .ok_or_else(|| webrtc::Error::new("no local description".to_string()))?;
+
+        // Hold the connection and its viewer slot until it ends
+        tokio::spawn(async move {
+            ended_rx.recv().await;
+            if let Err(e) = pc.close().await {
+                debug!("[webrtc] Closing peer connection failed: {}", e);
+            }
+            drop(slot);
+            info!("[webrtc] Viewer disconnected");
+        });
+        Ok(answer.sdp)
+    }
+}
+
+/// Whether `sensor_id` is due again, at most once per `min_interval`
+fn due(
+    last_sent: &mut HashMap<String, Instant>,
+    sensor_id: &str,
+    now: Instant,
+    min_interval: Duration,
+) -> bool {
+    match last_sent.get(sensor_id) {
+        Some(&sent) if now.duration_since(sent) < min_interval => false,
+        _ => {
+            last_sent.insert(sensor_id.to_string(), now);
+            true
+        }
+    }
+}
+
+/// Send messages as JSON text until the channel closes
+async fn forward(
+    channel: Arc<RTCDataChannel>,
+    mut rx: broadcast::Receiver<SensorMessage>,
+    sensor_ids: Vec<String>,
+    min_interval: Duration,
+) {
+    let mut last_sent = HashMap::new();
+    loop {
+        match rx.recv().await {
+            Ok(message) => {
+                let sensor_id = &message.header().sensor_id;
+                if !sensor_ids.is_empty() && !sensor_ids.contains(sensor_id) {
+                    continue;
+                }
+                if !due(&mut last_sent, sensor_id, Instant::now(), min_interval)
+                    || channel.buffered_amount().await > MAX_BUFFERED
+                {
+                    continue;
+                }
+                let json = match serde_json::to_string(&message) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        debug!("[webrtc] Cannot encode message: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = channel.send_text(json).await {
+                    debug!("[webrtc] Channel '{}' closed: {}", channel.label(), e);
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                debug!("[webrtc] Viewer fell behind, {} message(s) skipped", n);
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_sensor_paced() {
+        let mut last_sent = HashMap::new();
+        let start = Instant::now();
+        let interval = Duration::from_millis(100);
+        assert!(due(&mut last_sent, "fc_imu0", start, interval));
+        assert!(due(&mut last_sent, "fc_gps0", start, interval));
+        assert!(!due(
+            &mut last_sent,
+            "fc_imu0",
+            start + Duration::from_millis(50),
+            interval
+        ));
+        assert!(due(
+            &mut last_sent,
+            "fc_imu0",
+            start + Duration::from_millis(100),
+            interval
+        ));
+    }
+}
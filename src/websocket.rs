@@ -0,0 +1,127 @@
+use crate::grpc_service::SensorHubService;
+use crate::messages::SensorMessage;
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, info};
+
+/// Sensors requested in the query string, e.g. `/stream?sensor_ids=fc_imu0,baro0` (empty = all)
+pub(crate) fn requested_sensors(target: &str) -> Vec<String> {
+    target
+        .split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("sensor_ids="))
+        .flat_map(|ids| ids.split(','))
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Stream sensor data as JSON text frames at ws://<addr>/stream until the listener fails
+pub async fn serve(addr: SocketAddr, service: Arc<SensorHubService>) -> Result<(), String> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Cannot bind {}: {}", addr, e))?;
+    info!("[websocket] Streaming sensor data on ws://{}/stream", addr);
+    loop {
+        let (stream, peer) = listener.accept().await.map_err(|e| e.to_string())?;
+        // Subscribe before the handshake so the client sees data from the moment it connected
+        let rx = service.subscribe_messages();
+        tokio::spawn(async move {
+            match handle(stream, rx).await {
+                Ok(()) => debug!("[websocket] Client {} disconnected", peer),
+                Err(e) => debug!("[websocket] Client {} dropped: {}", peer, e),
+            }
+        });
+    }
+}
+
+async fn handle(stream: TcpStream, rx: broadcast::Receiver<SensorMessage>) -> Result<(), Error> {
+    let peer = stream.peer_addr()?;
+    let mut target = String::new();
+    let ws =
+        tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+            if request.uri().path() != "/stream" {
+                let mut not_found = ErrorResponse::new(Some("Not found\n".to_string()));
+                *not_found.status_mut() = StatusCode::NOT_FOUND;
+                return Err(not_found);
+            }
+            target = request.uri().to_string();
+            Ok(response)
+        })
+        .await?;
+
+    let sensor_ids = requested_sensors(&target);
+    info!(
+        "[websocket] Client {} streaming {}",
+        peer,
+        if sensor_ids.is_empty() {
+            "all sensors".to_string()
+        } else {
+            sensor_ids.join(", ")
+        }
+    );
+    forward(ws, rx, &sensor_ids).await
+}
+
+/// Send messages as text frames; tungstenite answers the client's pings and close
+async fn forward(
+    ws: WebSocketStream<TcpStream>,
+    mut rx: broadcast::Receiver<SensorMessage>,
+    sensor_ids: &[String],
+) -> Result<(), Error> {
+    let (mut writer, mut reader) = ws.split();
+
+    loop {
+        tokio::select! {
+            // Data frames from the client carry nothing we use
+            frame = reader.next() => match frame {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+            },
+            message = rx.recv() => match message {
+                Ok(message) => {
+                    if !sensor_ids.is_empty()
+                        && !sensor_ids.contains(&message.header().sensor_id)
+                    {
+                        continue;
+                    }
+                    match serde_json::to_string(&message) {
+                        Ok(json) => writer.send(Message::Text(json)).await?,
+                        Err(e) => debug!("[websocket] Cannot encode message: {}", e),
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("[websocket] Client fell behind, {} message(s) skipped", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = writer.send(Message::Close(None)).await;
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_sensors() {
+        assert_eq!(
+            requested_sensors("/stream?sensor_ids=fc_imu0,baro0&x=1"),
+            ["fc_imu0", "baro0"]
+        );
+        assert!(requested_sensors("/stream").is_empty());
+    }
+}